@@ -11,11 +11,12 @@ use amethyst_core::{
     SystemBundle, SystemDesc,
 };
 use amethyst_error::Error;
+use amethyst_rendy::skinning::SocketSystem;
 use std::{hash::Hash, marker};
 
 /// Bundle for vertex skinning
 ///
-/// This registers `VertexSkinningSystem`.
+/// This registers `VertexSkinningSystem`, and `SocketSystem` running after it.
 /// Note that the user must make sure this system runs after `TransformSystem`
 #[derive(Default, Debug)]
 pub struct VertexSkinningBundle<'a> {
@@ -46,6 +47,7 @@ impl<'a, 'b, 'c> SystemBundle<'a, 'b> for VertexSkinningBundle<'c> {
             "vertex_skinning_system",
             self.dep,
         );
+        builder.add(SocketSystem, "socket_system", &["vertex_skinning_system"]);
         Ok(())
     }
 }
@@ -341,6 +341,8 @@ where
     pub channel: T::Channel,
     /// Blend weight
     pub blend_weight: f32,
+    /// Weight of the animation this sampler belongs to, multiplied with the blend weight
+    pub animation_weight: f32,
     /// Sampler
     pub sampler: Handle<Sampler<T::Primitive>>,
     /// State of sampling
@@ -459,6 +461,17 @@ where
             .for_each(|sampler| sampler.rate_multiplier = rate_multiplier);
     }
 
+    /// Update animation weight
+    pub fn set_animation_weight(&mut self, control_id: u64, weight: f32)
+    where
+        T: AnimationSampling,
+    {
+        self.samplers
+            .iter_mut()
+            .filter(|t| t.control_id == control_id)
+            .for_each(|sampler| sampler.animation_weight = weight);
+    }
+
     /// Forcibly set the input value (point of interpolation)
     pub fn set_input(&mut self, control_id: u64, input: f32)
     where
@@ -619,6 +632,9 @@ where
     pub command: AnimationCommand<T>,
     /// Control the rate of animation, default is 1.0
     pub rate_multiplier: f32,
+    /// Weight of the animation when blended with other animations of the same channels,
+    /// default is 1.0
+    pub weight: f32,
     m: marker::PhantomData<T>,
 }
 
@@ -641,6 +657,7 @@ where
             state,
             command,
             rate_multiplier,
+            weight: 1.0,
             m: marker::PhantomData,
         }
     }
@@ -779,6 +796,25 @@ where
         self
     }
 
+    /// Set animation weight
+    ///
+    /// Running animations of the same channels are blended with their weights as ratios, so
+    /// cross-fading between two animations is done by moving the weights between 0.0 and 1.0.
+    pub fn set_weight(&mut self, id: I, weight: f32) -> &mut Self {
+        if let Some(&mut (_, ref mut control)) = self.animations.iter_mut().find(|a| a.0 == id) {
+            control.weight = weight;
+        }
+        if let Some(ref mut control) = self
+            .deferred_animations
+            .iter_mut()
+            .find(|a| a.animation_id == id)
+        {
+            control.control.weight = weight;
+        }
+
+        self
+    }
+
     /// Step animation
     pub fn step(&mut self, id: I, direction: StepDirection) -> &mut Self {
         self.set_command(id, AnimationCommand::Step(direction))
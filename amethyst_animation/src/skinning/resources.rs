@@ -116,6 +116,13 @@ impl<'a> PrefabData<'a> for SkinPrefab {
         entities: &[Entity],
         _: &[Entity],
     ) -> Result<(), Error> {
+        if self.joints.len() != self.inverse_bind_matrices.len() {
+            return Err(Error::from_string(format!(
+                "Skin has {} joints but {} inverse bind matrices",
+                self.joints.len(),
+                self.inverse_bind_matrices.len()
+            )));
+        }
         storage
             .insert(
                 entity,
@@ -75,7 +75,9 @@ impl<'a> System<'a> for VertexSkinningSystem {
         }
 
         for (_id, skin) in (&self.updated_skins, &mut skins).join() {
-            // Compute the joint global_transforms
+            // Compute the joint global_transforms, reusing the scratch area of the skin. A joint
+            // without a transform keeps its bind pose, so the palette indices still match the
+            // joint indices of the mesh.
             skin.joint_matrices.clear();
             let bind_shape = skin.bind_shape_matrix;
             skin.joint_matrices.extend(
@@ -84,18 +86,14 @@ impl<'a> System<'a> for VertexSkinningSystem {
                     .zip(skin.inverse_bind_matrices.iter())
                     .map(|(joint_entity, inverse_bind_matrix)| {
                         if let Some(transform) = global_transforms.get(*joint_entity) {
-                            Some((transform, inverse_bind_matrix))
+                            transform.global_matrix() * inverse_bind_matrix * bind_shape
                         } else {
                             error!(
                                 "Missing `Transform` Component for join entity {:?}",
                                 joint_entity
                             );
-                            None
+                            bind_shape
                         }
-                    })
-                    .flatten()
-                    .map(|(global, inverse_bind_matrix)| {
-                        global.global_matrix() * inverse_bind_matrix * bind_shape
                     }),
             );
 
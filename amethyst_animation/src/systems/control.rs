@@ -340,6 +340,24 @@ where
             }
         }
     };
+    // Check that a newly requested animation targets the given hierarchy, e.g. that a skeletal
+    // clip isn't played on a skeleton with fewer joints. Unlike samplers that are still
+    // loading, this won't resolve itself later.
+    if let ControlState::Requested | ControlState::Deferred(..) = control.state {
+        if let Some(&(node_index, _, _)) = animation
+            .nodes
+            .iter()
+            .find(|node| !hierarchy.nodes.contains_key(&node.0))
+        {
+            error!(
+                "Animation targets node {} but the hierarchy only has {} nodes, dropping",
+                node_index,
+                hierarchy.nodes.len()
+            );
+            *remove = true;
+            return None;
+        }
+    }
     match (&control.state, &control.command) {
         // Check for aborted or done animation
         (_, &AnimationCommand::Abort) | (&ControlState::Abort, _) | (&ControlState::Done, _) => {
@@ -439,6 +457,7 @@ where
                 *remove = true;
             } else {
                 update_animation_rate(control.id, hierarchy, samplers, control.rate_multiplier);
+                update_animation_weight(control.id, hierarchy, samplers, control.weight);
             }
             None
         }
@@ -516,6 +535,7 @@ where
                 after: component.current_sample(channel, apply_data),
                 rate_multiplier: control.rate_multiplier,
                 blend_weight: 1.0,
+                animation_weight: control.weight,
             };
             if let Some(ref mut set) = samplers.get_mut(*node_entity) {
                 set.add_control(sampler_control);
@@ -628,6 +648,21 @@ fn update_animation_rate<T>(
     }
 }
 
+fn update_animation_weight<T>(
+    control_id: u64,
+    hierarchy: &AnimationHierarchy<T>,
+    samplers: &mut WriteStorage<'_, SamplerControlSet<T>>,
+    weight: f32,
+) where
+    T: AnimationSampling,
+{
+    for node_entity in hierarchy.nodes.values() {
+        if let Some(ref mut s) = samplers.get_mut(*node_entity) {
+            s.set_animation_weight(control_id, weight);
+        }
+    }
+}
+
 /// Check if all nodes in an `AnimationHierarchy` are ready for termination, if so remove all
 /// `SamplerControlSet`s for the hierarchy, if not request termination on all sampler controls
 fn check_and_terminate_animation<T>(
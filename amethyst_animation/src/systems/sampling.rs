@@ -1,6 +1,5 @@
 use std::{marker, time::Duration};
 
-use itertools::Itertools;
 use minterpolate::InterpolationPrimitive;
 
 use amethyst_assets::AssetStorage;
@@ -78,8 +77,12 @@ where
             }
             if !self.inner.is_empty() {
                 self.channels.clear();
-                self.channels
-                    .extend(self.inner.iter().map(|o| &o.1).unique().cloned());
+                // Deduplicated in the reused buffer, components only have a few channels
+                for (_, channel, _) in &self.inner {
+                    if !self.channels.contains(channel) {
+                        self.channels.push(channel.clone());
+                    }
+                }
                 for channel in &self.channels {
                     match comp.blend_method(channel) {
                         None => {
@@ -135,7 +138,7 @@ fn process_sampler<T>(
     match new_state {
         Running(duration) | Paused(duration) => {
             output.push((
-                control.blend_weight,
+                control.blend_weight * control.animation_weight,
                 control.channel.clone(),
                 sampler.function.interpolate(
                     duration_to_secs(duration),
@@ -148,7 +151,7 @@ fn process_sampler<T>(
         Done => {
             if let EndControl::Normal = control.end {
                 output.push((
-                    control.blend_weight,
+                    control.blend_weight * control.animation_weight,
                     control.channel.clone(),
                     control.after.clone(),
                 ));
@@ -157,7 +160,7 @@ fn process_sampler<T>(
                 let last_frame = sampler.input.last().cloned().unwrap_or(0.);
 
                 output.push((
-                    control.blend_weight,
+                    control.blend_weight * control.animation_weight,
                     control.channel.clone(),
                     sampler.function.interpolate(
                         last_frame,
@@ -49,10 +49,13 @@ impl Allocator {
 pub struct AssetStorage<A: Asset> {
     assets: VecStorage<(A, u32)>,
     bitset: BitSet,
+    failed: BitSet,
     handles: Vec<Handle<A>>,
     handle_alloc: Allocator,
     pub(crate) processed: Arc<SegQueue<Processed<A>>>,
     reloads: Vec<(WeakHandle<A>, Box<dyn Reload<A::Data>>)>,
+    /// Ids of unloaded or failed assets, freed once all of their handles are dropped.
+    released: Vec<(u32, WeakHandle<A>)>,
     unused_handles: SegQueue<Handle<A>>,
 }
 
@@ -95,6 +98,40 @@ impl<A: Asset> AssetStorage<A> {
         self.bitset.clear();
     }
 
+    /// Remove a single asset from the storage right away, even if there are
+    /// still handles pointing to it. Those handles become invalid and will
+    /// return `None` on access. Returns the removed asset, if it was loaded.
+    ///
+    /// The id of the unloaded asset is not reused while old handles are alive,
+    /// so they can never alias a newly inserted asset. Until then the handle
+    /// is reported by `is_failed`, and is never hot-reloaded. Once the last
+    /// handle is dropped, `process` frees the id for new assets.
+    pub fn unload(&mut self, handle: &Handle<A>) -> Option<A> {
+        let id = handle.id();
+        if !self.bitset.remove(id) {
+            return None;
+        }
+        self.failed.add(id);
+        self.released.push((id, handle.downgrade()));
+        if let Some(i) = self.handles.iter().position(|h| h.id() == id) {
+            self.handles.swap_remove(i);
+        }
+        self.reloads
+            .retain(|(handle, _)| handle.upgrade().is_some_and(|h| h.id() != id));
+        let (asset, _) = unsafe { self.assets.remove(id) };
+        Some(asset)
+    }
+
+    /// Iterate over all loaded assets together with their handles.
+    pub fn iter(&self) -> impl Iterator<Item = (&Handle<A>, &A)> {
+        let bitset = &self.bitset;
+        let assets = &self.assets;
+        self.handles
+            .iter()
+            .filter(move |h| bitset.contains(h.id()))
+            .map(move |h| (h, unsafe { &assets.get(h.id()).0 }))
+    }
+
     /// When cloning an asset handle, you'll get another handle,
     /// but pointing to the same asset. If you instead want to
     /// indeed create a new asset, you can use this method.
@@ -196,6 +233,12 @@ impl<A: Asset> AssetStorage<A> {
         self.bitset.contains(id)
     }
 
+    /// Check if the asset of given handle failed to load or was unloaded, as
+    /// opposed to still loading. Such handles never get an asset again.
+    pub fn is_failed(&self, handle: &Handle<A>) -> bool {
+        self.failed.contains(handle.id())
+    }
+
     /// Get an asset by its handle id without checking the internal bitset.
     /// Use `contains_id` to manually check its status before access.
     ///
@@ -247,8 +290,10 @@ impl<A: Asset> AssetStorage<A> {
             while let Ok(processed) = self.processed.pop() {
                 let assets = &mut self.assets;
                 let bitset = &mut self.bitset;
+                let failed = &mut self.failed;
                 let handles = &mut self.handles;
                 let reloads = &mut self.reloads;
+                let released = &mut self.released;
 
                 let f = &mut f;
                 let (reload_obj, handle) = match processed {
@@ -313,6 +358,8 @@ impl<A: Asset> AssetStorage<A> {
                                     handle,
                                     e,
                                 );
+                                failed.add(handle.id());
+                                released.push((handle.id(), handle.downgrade()));
                                 tracker.fail(handle.id(), A::NAME, name, e);
 
                                 continue;
@@ -337,6 +384,16 @@ impl<A: Asset> AssetStorage<A> {
                         name,
                         old_reload,
                     } => {
+                        // The reload may have been spawned before the asset was unloaded.
+                        if failed.contains(handle.id()) {
+                            debug!(
+                                "{:?}: Dropping hot-reload of unloaded asset {:?} (handle id: {:?})",
+                                A::NAME,
+                                name,
+                                handle,
+                            );
+                            continue;
+                        }
                         let (asset, reload_obj) = match data
                             .map(|FormatValue { data, reload }| (data, reload))
                             .and_then(|(d, rel)| f(d).map(|a| (a, rel)))
@@ -421,6 +478,20 @@ impl<A: Asset> AssetStorage<A> {
                 marker: PhantomData,
             });
         }
+        let failed = &mut self.failed;
+        let unused_handles = &self.unused_handles;
+        self.released.retain(|&(id, ref handle)| {
+            if !handle.is_dead() {
+                return true;
+            }
+            count += 1;
+            failed.remove(id);
+            unused_handles.push(Handle {
+                id: Arc::new(id),
+                marker: PhantomData,
+            });
+            false
+        });
         if count != 0 {
             debug!("{:?}: Freed {} handle ids", A::NAME, count,);
         }
@@ -479,10 +550,12 @@ impl<A: Asset> Default for AssetStorage<A> {
         AssetStorage {
             assets: Default::default(),
             bitset: Default::default(),
+            failed: Default::default(),
             handles: Default::default(),
             handle_alloc: Default::default(),
             processed: Arc::new(SegQueue::new()),
             reloads: Default::default(),
+            released: Default::default(),
             unused_handles: SegQueue::new(),
         }
     }
@@ -583,6 +656,12 @@ impl<A> Handle<A> {
         }
     }
 
+    /// Returns the number of strong handles pointing at the same asset,
+    /// including the one held by the `AssetStorage` itself.
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.id)
+    }
+
     /// Returns `true` if this is the only handle to the asset its pointing at.
     fn is_unique(&self) -> bool {
         Arc::strong_count(&self.id) == 1
@@ -637,3 +716,143 @@ impl<A> WeakHandle<A> {
         self.id.upgrade().is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::storage::DenseVecStorage;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Value(u32);
+
+    impl Asset for Value {
+        const NAME: &'static str = "Value";
+        type Data = Value;
+        type HandleStorage = DenseVecStorage<Handle<Value>>;
+    }
+
+    /// Reload that always reports a change, reloading given value.
+    #[derive(Clone)]
+    struct Changed(u32);
+
+    impl Reload<Value> for Changed {
+        fn needs_reload(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> String {
+            "changed".to_string()
+        }
+
+        fn format(&self) -> &'static str {
+            "test"
+        }
+
+        fn reload(self: Box<Self>) -> Result<FormatValue<Value>, Error> {
+            Ok(FormatValue::data(Value(self.0)))
+        }
+    }
+
+    fn pool() -> ThreadPool {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+    }
+
+    fn process(storage: &mut AssetStorage<Value>, pool: &ThreadPool) {
+        storage.process(ProcessableAsset::process, 0, pool, None);
+    }
+
+    #[test]
+    fn unloaded_assets_are_gone_while_their_handles_live() {
+        let mut storage = AssetStorage::<Value>::new();
+        let handle = storage.insert(Value(1));
+        let other = storage.insert(Value(2));
+
+        assert_eq!(storage.unload(&handle), Some(Value(1)));
+        assert_eq!(storage.get(&handle), None);
+        assert!(storage.is_failed(&handle));
+        assert!(!storage.is_failed(&other));
+        assert_eq!(storage.unload(&handle), None);
+
+        // `iter` skips the unloaded asset.
+        let values = storage.iter().map(|(_, v)| v.clone()).collect::<Vec<_>>();
+        assert_eq!(values, vec![Value(2)]);
+
+        // The id stays failed and isn't handed out again, even once the other asset is freed.
+        let pool = pool();
+        drop(other);
+        process(&mut storage, &pool);
+        assert!(storage.is_failed(&handle));
+        let kept = (0..4)
+            .map(|i| storage.insert(Value(10 + i)))
+            .collect::<Vec<_>>();
+        assert!(kept.iter().all(|new| new.id() != handle.id()));
+        assert_eq!(storage.get(&handle), None);
+        assert_eq!(storage.iter().count(), 4);
+
+        // Once its last handle is dropped, the id is freed and reused.
+        let id = handle.id();
+        drop(handle);
+        process(&mut storage, &pool);
+        let new = storage.insert(Value(20));
+        assert_eq!(new.id(), id);
+        assert!(!storage.is_failed(&new));
+        assert_eq!(storage.get(&new), Some(&Value(20)));
+    }
+
+    #[test]
+    fn failed_asset_ids_are_reused_once_their_handles_drop() {
+        let mut storage = AssetStorage::<Value>::new();
+        let handle = storage.allocate();
+        storage.processed.push(Processed::NewAsset {
+            data: Err(Error::from_string("broken")),
+            handle: handle.clone(),
+            name: "value".to_string(),
+            tracker: Box::new(()),
+        });
+        let pool = pool();
+        process(&mut storage, &pool);
+        assert!(storage.is_failed(&handle));
+
+        let id = handle.id();
+        drop(handle);
+        process(&mut storage, &pool);
+        let new = storage.insert(Value(1));
+        assert_eq!(new.id(), id);
+        assert!(!storage.is_failed(&new));
+    }
+
+    #[test]
+    fn unloaded_assets_are_not_hot_reloaded() {
+        let mut storage = AssetStorage::<Value>::new();
+        let handle = storage.allocate();
+        storage.processed.push(Processed::NewAsset {
+            data: Ok(FormatValue {
+                data: Value(1),
+                reload: Some(Box::new(Changed(2))),
+            }),
+            handle: handle.clone(),
+            name: "value".to_string(),
+            tracker: Box::new(()),
+        });
+        let pool = pool();
+        process(&mut storage, &pool);
+        assert_eq!(storage.get(&handle), Some(&Value(1)));
+        assert_eq!(storage.reloads.len(), 1);
+
+        // A reload spawned before the unload finishes after it.
+        storage.processed.push(Processed::HotReload {
+            data: Ok(FormatValue::data(Value(3))),
+            handle: handle.clone(),
+            name: "value".to_string(),
+            old_reload: Box::new(Changed(2)),
+        });
+        storage.unload(&handle);
+        assert!(storage.reloads.is_empty());
+        process(&mut storage, &pool);
+        assert_eq!(storage.get(&handle), None);
+        assert!(storage.is_failed(&handle));
+    }
+}
@@ -7,9 +7,10 @@ use amethyst_animation::{
     SamplerPrimitive, TransformChannel,
 };
 use amethyst_core::{
-    math::{convert, Vector3, Vector4},
+    math::{convert, Quaternion, UnitQuaternion, Vector3, Vector4},
     Transform,
 };
+use amethyst_rendy::formats::mesh::ImportTransform;
 
 use super::Buffers;
 use crate::error;
@@ -18,10 +19,11 @@ pub fn load_animations(
     gltf: &gltf::Gltf,
     buffers: &Buffers,
     node_map: &HashMap<usize, usize>,
+    transform: &ImportTransform,
 ) -> Result<AnimationSetPrefab<usize, Transform>, Error> {
     let mut prefab = AnimationSetPrefab::default();
     for animation in gltf.animations() {
-        let anim = load_animation(&animation, buffers)?;
+        let anim = load_animation(&animation, buffers, transform)?;
         if anim
             .samplers
             .iter()
@@ -36,11 +38,12 @@ pub fn load_animations(
 fn load_animation(
     animation: &gltf::Animation<'_>,
     buffers: &Buffers,
+    transform: &ImportTransform,
 ) -> Result<AnimationPrefab<Transform>, Error> {
     let mut a = AnimationPrefab::default();
     a.samplers = animation
         .channels()
-        .map(|ref channel| load_channel(channel, buffers))
+        .map(|ref channel| load_channel(channel, buffers, transform))
         .collect::<Result<Vec<_>, Error>>()?;
     Ok(a)
 }
@@ -48,6 +51,7 @@ fn load_animation(
 fn load_channel(
     channel: &gltf::animation::Channel<'_>,
     buffers: &Buffers,
+    transform: &ImportTransform,
 ) -> Result<(usize, TransformChannel, Sampler<SamplerPrimitive<f32>>), Error> {
     use gltf::animation::util::ReadOutputs::*;
     let sampler = channel.sampler();
@@ -68,7 +72,7 @@ fn load_channel(
                 input,
                 function: map_interpolation_type(sampler.interpolation()),
                 output: translations
-                    .map(Vector3::from)
+                    .map(|t| Vector3::from(transform.position(t)))
                     .map(|t| convert::<_, Vector3<f32>>(t).into())
                     .collect(),
             },
@@ -89,7 +93,10 @@ fn load_channel(
                     output: rotations
                         .into_f32()
                         .map(Vector4::from)
-                        .map(|q| convert::<_, Vector4<f32>>(q).into())
+                        .map(|q| {
+                            let q = UnitQuaternion::new_normalize(Quaternion::from(q));
+                            transform.rotation(q).into_inner().coords.into()
+                        })
                         .collect(),
                 },
             ))
@@ -101,7 +108,7 @@ fn load_channel(
                 input,
                 function: map_interpolation_type(sampler.interpolation()),
                 output: scales
-                    .map(Vector3::from)
+                    .map(|s| Vector3::from(transform.scaling(s)))
                     .map(|s| convert::<_, Vector3<f32>>(s).into())
                     .collect(),
             },
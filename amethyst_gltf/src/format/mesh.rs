@@ -33,6 +33,15 @@ enum Indices {
 }
 
 impl Indices {
+    /// Indices as `u32`, offset by `base`, listing all vertices when there are none.
+    fn offset(&self, base: u32, vertices: usize) -> Vec<u32> {
+        match self {
+            Indices::None => (base..base + vertices as u32).collect(),
+            Indices::U16(vec) => vec.iter().map(|&i| base + u32::from(i)).collect(),
+            Indices::U32(vec) => vec.iter().map(|&i| base + i).collect(),
+        }
+    }
+
     fn len(&self) -> Option<usize> {
         match self {
             Indices::None => None,
@@ -50,18 +59,110 @@ impl Indices {
     }
 }
 
+/// Vertex data and material of a mesh primitive.
+pub struct Primitive {
+    indices: Indices,
+    positions: Vec<Position>,
+    normals: Option<Vec<Normal>>,
+    tangents: Option<Vec<Tangent>>,
+    tex_coords: Option<Vec<TexCoord>>,
+    colors: Option<Vec<Color>>,
+    joints: Option<Vec<JointCombined>>,
+    pub material: Option<usize>,
+    pub bounds: Range<[f32; 3]>,
+}
+
+impl Primitive {
+    fn attributes(&self) -> [bool; 5] {
+        [
+            self.normals.is_some(),
+            self.tangents.is_some(),
+            self.tex_coords.is_some(),
+            self.colors.is_some(),
+            self.joints.is_some(),
+        ]
+    }
+
+    pub fn into_builder(self) -> MeshBuilder<'static> {
+        let mut builder = MeshBuilder::new();
+        match self.indices {
+            Indices::U16(vec) => {
+                builder.set_indices(vec);
+            }
+            Indices::U32(vec) => {
+                builder.set_indices(vec);
+            }
+            Indices::None => {}
+        };
+
+        builder.add_vertices(self.positions);
+        self.normals.map(|v| builder.add_vertices(v));
+        self.tangents.map(|v| builder.add_vertices(v));
+        self.tex_coords.map(|v| builder.add_vertices(v));
+        self.colors.map(|v| builder.add_vertices(v));
+        self.joints.map(|v| builder.add_vertices(v));
+        builder
+    }
+}
+
+/// Whether the primitives have the same vertex attributes, so they can share a vertex buffer.
+pub fn can_merge(primitives: &[Primitive]) -> bool {
+    primitives
+        .windows(2)
+        .all(|pair| pair[0].attributes() == pair[1].attributes())
+}
+
+/// Merge primitives with the same vertex attributes into one indexed mesh, returning the index
+/// range of every primitive.
+pub fn merge_primitives(primitives: Vec<Primitive>) -> (MeshBuilder<'static>, Vec<Range<u32>>) {
+    let mut merged = Primitive {
+        indices: Indices::U32(Vec::new()),
+        positions: Vec::new(),
+        normals: None,
+        tangents: None,
+        tex_coords: None,
+        colors: None,
+        joints: None,
+        material: None,
+        bounds: [0.0; 3]..[0.0; 3],
+    };
+    fn append<T>(merged: &mut Option<Vec<T>>, data: Option<Vec<T>>) {
+        if let Some(data) = data {
+            merged.get_or_insert_with(Vec::new).extend(data);
+        }
+    }
+
+    let mut indices = Vec::new();
+    let mut ranges = Vec::new();
+    for primitive in primitives {
+        let start = indices.len() as u32;
+        let base = merged.positions.len() as u32;
+        indices.extend(primitive.indices.offset(base, primitive.positions.len()));
+        ranges.push(start..indices.len() as u32);
+
+        merged.positions.extend(primitive.positions);
+        append(&mut merged.normals, primitive.normals);
+        append(&mut merged.tangents, primitive.tangents);
+        append(&mut merged.tex_coords, primitive.tex_coords);
+        append(&mut merged.colors, primitive.colors);
+        append(&mut merged.joints, primitive.joints);
+    }
+    merged.indices = Indices::U32(indices);
+    (merged.into_builder(), ranges)
+}
+
 pub fn load_mesh(
     mesh: &gltf::Mesh<'_>,
     buffers: &Buffers,
     options: &GltfSceneOptions,
-) -> Result<Vec<(MeshBuilder<'static>, Option<usize>, Range<[f32; 3]>)>, Error> {
+) -> Result<Vec<Primitive>, Error> {
     trace!("Loading mesh");
+    let convention = &options.import_transform;
     let mut primitives = vec![];
 
     for primitive in mesh.primitives() {
         trace!("Loading mesh primitive");
         let reader = primitive.reader(|buffer| buffers.buffer(&buffer));
-        let mut builder = MeshBuilder::new();
 
         trace!("Loading indices");
         use gltf::mesh::util::ReadIndices;
@@ -76,13 +177,35 @@ pub fn load_mesh(
         let positions = reader
             .read_positions()
             .ok_or(error::Error::MissingPositions)?
-            .map(Position)
+            .map(|p| Position(convention.position(p)))
             .collect::<Vec<_>>();
 
+        let indices = if convention.flip_winding {
+            match indices {
+                Indices::U16(mut vec) => {
+                    convention.flip_triangles(&mut vec);
+                    Indices::U16(vec)
+                }
+                Indices::U32(mut vec) => {
+                    convention.flip_triangles(&mut vec);
+                    Indices::U32(vec)
+                }
+                Indices::None => {
+                    let mut vec = (0..positions.len() as u32).collect::<Vec<_>>();
+                    convention.flip_triangles(&mut vec);
+                    Indices::U32(vec)
+                }
+            }
+        } else {
+            indices
+        };
+
         let normals = compute_if(options.load_normals || options.load_tangents, || {
             trace!("Loading normals");
             if let Some(normals) = reader.read_normals() {
-                normals.map(Normal).collect::<Vec<_>>()
+                normals
+                    .map(|n| Normal(convention.direction(n)))
+                    .collect::<Vec<_>>()
             } else {
                 trace!("Calculating normals");
                 calculate_normals(&positions, &indices)
@@ -112,7 +235,9 @@ pub fn load_mesh(
             trace!("Loading tangents");
             let tangents = reader.read_tangents();
             match tangents {
-                Some(tangents) => tangents.map(Tangent).collect::<Vec<_>>(),
+                Some(tangents) => tangents
+                    .map(|t| Tangent(convention.tangent(t)))
+                    .collect::<Vec<_>>(),
                 None => {
                     trace!("Calculating tangents");
                     calculate_tangents(
@@ -148,29 +273,27 @@ pub fn load_mesh(
             }
         });
 
-        match indices {
-            Indices::U16(vec) => {
-                builder.set_indices(vec);
-            }
-            Indices::U32(vec) => {
-                builder.set_indices(vec);
-            }
-            Indices::None => {}
-        };
-
-        builder.add_vertices(positions);
-        normals.map(|v| builder.add_vertices(v));
-        tangents.map(|v| builder.add_vertices(v));
-        tex_coords.map(|v| builder.add_vertices(v));
-        colors.map(|v| builder.add_vertices(v));
-        joints.map(|v| builder.add_vertices(v));
-
         trace!("Loading bounding box");
         let bounds = primitive.bounding_box();
-        let bounds = bounds.min..bounds.max;
+        let (a, b) = (
+            convention.position(bounds.min),
+            convention.position(bounds.max),
+        );
+        let bounds = [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])]
+            ..[a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])];
         let material = primitive.material().index();
 
-        primitives.push((builder, material, bounds));
+        primitives.push(Primitive {
+            indices,
+            positions,
+            normals,
+            tangents,
+            tex_coords,
+            colors,
+            joints,
+            material,
+            bounds,
+        });
     }
     trace!("Loaded mesh");
     Ok(primitives)
@@ -253,7 +376,7 @@ fn calculate_tangents(
 
 #[cfg(test)]
 mod tests {
-    use super::{calculate_tangents, Indices};
+    use super::{calculate_tangents, can_merge, merge_primitives, Indices, Primitive};
     use amethyst_rendy::rendy::mesh::{Normal, Position, Tangent, TexCoord};
 
     const POSITIONS: &[Position] = &[
@@ -317,4 +440,34 @@ mod tests {
             ]
         );
     }
+
+    fn primitive(indices: Indices, vertices: usize) -> Primitive {
+        Primitive {
+            indices,
+            positions: POSITIONS[..vertices].to_vec(),
+            normals: Some(NORMALS[..vertices].to_vec()),
+            tangents: None,
+            tex_coords: None,
+            colors: None,
+            joints: None,
+            material: None,
+            bounds: [0.0; 3]..[1.0; 3],
+        }
+    }
+
+    #[test]
+    fn merged_primitives_keep_their_index_ranges() {
+        let primitives = vec![
+            primitive(Indices::None, 3),
+            primitive(Indices::U16(vec![0, 1, 2, 2, 1, 3]), 4),
+        ];
+        assert!(can_merge(&primitives));
+        assert_eq!(primitives[1].indices.offset(3, 4), vec![3, 4, 5, 5, 4, 6]);
+        let (_, ranges) = merge_primitives(primitives);
+        assert_eq!(ranges, vec![0..3, 3..9]);
+
+        let mut textured = primitive(Indices::None, 3);
+        textured.tex_coords = Some(TEX_COORDS[..3].to_vec());
+        assert!(!can_merge(&[primitive(Indices::None, 3), textured]));
+    }
 }
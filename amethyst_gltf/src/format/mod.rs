@@ -21,7 +21,7 @@ use self::{
     animation::load_animations,
     importer::{get_image_data, import, Buffers, ImageFormat},
     material::load_material,
-    mesh::load_mesh,
+    mesh::{can_merge, load_mesh, merge_primitives},
     skin::load_skin,
 };
 
@@ -66,6 +66,7 @@ fn load_gltf(
     options: &GltfSceneOptions,
 ) -> Result<Prefab<GltfPrefab>, Error> {
     debug!("Loading GLTF scene '{}'", name);
+    options.import_transform.check()?;
     import(source.clone(), name)
         .with_context(|_| error::Error::GltfImporterError)
         .and_then(|(gltf, buffers)| {
@@ -158,6 +159,7 @@ fn load_scene(
                 .expect("Unreachable: `node_map` should contain all nodes present in `skin_map`"),
             &node_map,
             skin_info.mesh_indices,
+            &options.import_transform,
             prefab,
         )?;
     }
@@ -179,7 +181,12 @@ fn load_scene(
             .data_or_default(0)
             .animatable
             .get_or_insert_with(Default::default)
-            .animation_set = Some(load_animations(gltf, buffers, &node_map)?);
+            .animation_set = Some(load_animations(
+            gltf,
+            buffers,
+            &node_map,
+            &options.import_transform,
+        )?);
     }
 
     Ok(())
@@ -214,12 +221,16 @@ fn load_node(
 
     // Load transformation data, default will be identity
     let (translation, rotation, scale) = node.transform().decomposed();
+    let convention = &options.import_transform;
     let mut local_transform = Transform::default();
-    *local_transform.translation_mut() = convert::<_, Vector3<f32>>(Vector3::from(translation));
-    *local_transform.rotation_mut() = Unit::new_normalize(convert::<_, Quaternion<f32>>(
-        Quaternion::from(Vector4::from(rotation)),
-    ));
-    *local_transform.scale_mut() = convert::<_, Vector3<f32>>(Vector3::from(scale));
+    *local_transform.translation_mut() =
+        convert::<_, Vector3<f32>>(Vector3::from(convention.position(translation)));
+    *local_transform.rotation_mut() =
+        convention.rotation(Unit::new_normalize(convert::<_, Quaternion<f32>>(
+            Quaternion::from(Vector4::from(rotation)),
+        )));
+    *local_transform.scale_mut() =
+        convert::<_, Vector3<f32>>(Vector3::from(convention.scaling(scale)));
     prefab.data_or_default(entity_index).transform = Some(local_transform);
 
     // Load camera
@@ -265,10 +276,11 @@ fn load_node(
         match graphics.len().cmp(&1) {
             Ordering::Equal => {
                 // single primitive can be loaded directly onto the node
-                let (mesh, material_index, bounds) = graphics.remove(0);
+                let primitive = graphics.remove(0);
+                let (material_index, bounds) = (primitive.material, primitive.bounds.clone());
                 bounding_box.extend_range(&bounds);
                 let prefab_data = prefab.data_or_default(entity_index);
-                prefab_data.mesh = Some(mesh);
+                prefab_data.mesh = Some(primitive.into_builder());
                 if let Some((material_id, material)) =
                     material_index.and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
                 {
@@ -284,31 +296,64 @@ fn load_node(
                 }
             }
             Ordering::Greater => {
-                // if we have multiple primitives,
-                // we need to add each primitive as a child entity to the node
-                for (mesh, material_index, bounds) in graphics {
-                    let mesh_entity = prefab.add(Some(entity_index), None);
-                    let prefab_data = prefab.data_or_default(mesh_entity);
-                    prefab_data.transform = Some(Transform::default());
-                    prefab_data.mesh = Some(mesh);
-                    if let Some((material_id, material)) = material_index
-                        .and_then(|index| gltf.materials().nth(index).map(|m| (index, m)))
-                    {
-                        material_set
-                            .materials
-                            .entry(material_id)
-                            .or_insert(load_material(&material, buffers, source.clone(), name)?);
-                        prefab_data.material_id = Some(material_id);
+                // load the materials of all primitives first
+                for material_id in graphics.iter().filter_map(|p| p.material) {
+                    if let Some(material) = gltf.materials().nth(material_id) {
+                        if !material_set.materials.contains_key(&material_id) {
+                            let material = load_material(&material, buffers, source.clone(), name)?;
+                            material_set.materials.insert(material_id, material);
+                        }
                     }
-
-                    // if we have a skin we need to track the mesh entities
+                }
+                // primitives sharing vertex attributes and transparency are merged into one mesh,
+                // drawn with a sub-mesh per primitive
+                let materials = graphics
+                    .iter()
+                    .map(|p| {
+                        p.material
+                            .filter(|id| material_set.materials.contains_key(id))
+                    })
+                    .collect::<Option<Vec<_>>>();
+                let merge = materials.filter(|materials| {
+                    let transparent = |id: &usize| material_set.materials[id].transparent;
+                    can_merge(&graphics)
+                        && materials
+                            .iter()
+                            .all(|id| transparent(id) == transparent(&materials[0]))
+                });
+                if let Some(materials) = merge {
+                    for primitive in &graphics {
+                        bounding_box.extend_range(&primitive.bounds);
+                    }
+                    let (mesh, ranges) = merge_primitives(graphics);
+                    let prefab_data = prefab.data_or_default(entity_index);
+                    prefab_data.mesh = Some(mesh);
+                    prefab_data.material_id = Some(materials[0]);
+                    prefab_data.sub_mesh_materials = ranges.into_iter().zip(materials).collect();
                     if let Some(ref mut skin) = skin {
-                        skin.mesh_indices.push(mesh_entity);
+                        skin.mesh_indices.push(entity_index);
                     }
+                } else {
+                    // otherwise we need to add each primitive as a child entity to the node
+                    for primitive in graphics {
+                        let (material_index, bounds) =
+                            (primitive.material, primitive.bounds.clone());
+                        let mesh_entity = prefab.add(Some(entity_index), None);
+                        let prefab_data = prefab.data_or_default(mesh_entity);
+                        prefab_data.transform = Some(Transform::default());
+                        prefab_data.mesh = Some(primitive.into_builder());
+                        prefab_data.material_id =
+                            material_index.filter(|id| material_set.materials.contains_key(id));
 
-                    // extent
-                    bounding_box.extend_range(&bounds);
-                    prefab_data.extent = Some(bounds.into());
+                        // if we have a skin we need to track the mesh entities
+                        if let Some(ref mut skin) = skin {
+                            skin.mesh_indices.push(mesh_entity);
+                        }
+
+                        // extent
+                        bounding_box.extend_range(&bounds);
+                        prefab_data.extent = Some(bounds.into());
+                    }
                 }
             }
             Ordering::Less => {}
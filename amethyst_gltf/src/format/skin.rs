@@ -4,7 +4,10 @@ use amethyst_animation::{JointPrefab, SkinPrefab, SkinnablePrefab};
 use amethyst_assets::Prefab;
 use amethyst_core::math::{convert, Matrix4};
 use amethyst_error::Error;
-use amethyst_rendy::skinning::JointTransformsPrefab;
+use amethyst_rendy::{
+    formats::mesh::ImportTransform,
+    skinning::{JointTransformsPrefab, Socket, Sockets},
+};
 
 use super::Buffers;
 use crate::GltfPrefab;
@@ -15,6 +18,7 @@ pub fn load_skin(
     skin_entity: usize,
     node_map: &HashMap<usize, usize>,
     meshes: Vec<usize>,
+    transform: &ImportTransform,
     prefab: &mut Prefab<GltfPrefab>,
 ) -> Result<(), Error> {
     let joints = skin
@@ -34,6 +38,7 @@ pub fn load_skin(
             matrices
                 .map(Matrix4::from)
                 .map(convert::<_, Matrix4<f32>>)
+                .map(|m| transform.matrix(&m))
                 .collect()
         })
         .unwrap_or_else(|| vec![Matrix4::identity(); joints.len()]);
@@ -48,13 +53,17 @@ pub fn load_skin(
             .skins
             .push(skin_entity);
     }
+    let sockets = load_sockets(skin, &inverse_bind_matrices, transform);
     let joint_transforms = JointTransformsPrefab::new(skin_entity, joints.len());
     for mesh_index in &meshes {
-        prefab
-            .data_or_default(*mesh_index)
+        let prefab_data = prefab.data_or_default(*mesh_index);
+        prefab_data
             .skinnable
             .get_or_insert_with(SkinnablePrefab::default)
             .joint_transforms = Some(joint_transforms.clone());
+        if !sockets.is_empty() {
+            prefab_data.sockets = Some(Sockets::new(sockets.clone()));
+        }
     }
 
     let skin_prefab = SkinPrefab {
@@ -71,3 +80,36 @@ pub fn load_skin(
 
     Ok(())
 }
+
+/// Named child nodes of joints which aren't joints themselves and have no mesh become sockets.
+fn load_sockets(
+    skin: &gltf::Skin<'_>,
+    inverse_bind_matrices: &[Matrix4<f32>],
+    transform: &ImportTransform,
+) -> Vec<Socket> {
+    let joint_nodes = skin.joints().map(|j| j.index()).collect::<Vec<_>>();
+    let mut sockets = Vec::new();
+    for (joint, node) in skin.joints().enumerate() {
+        let inverse_bind = match inverse_bind_matrices
+            .get(joint)
+            .and_then(|m| m.try_inverse())
+        {
+            Some(m) => m,
+            None => continue,
+        };
+        for child in node.children() {
+            if child.mesh().is_some() || joint_nodes.contains(&child.index()) {
+                continue;
+            }
+            if let Some(name) = child.name() {
+                let local = transform.matrix(&Matrix4::from(child.transform().matrix()));
+                sockets.push(Socket {
+                    name: name.to_string(),
+                    joint,
+                    bind_transform: inverse_bind * local,
+                });
+            }
+        }
+    }
+    sockets
+}
@@ -22,8 +22,14 @@ use amethyst_core::{
 };
 use amethyst_error::Error;
 use amethyst_rendy::{
-    camera::CameraPrefab, formats::mtl::MaterialPrefab, light::LightPrefab,
-    rendy::mesh::MeshBuilder, types::Mesh, visibility::BoundingSphere,
+    camera::CameraPrefab,
+    formats::{mesh::ImportTransform, mtl::MaterialPrefab},
+    light::LightPrefab,
+    rendy::mesh::MeshBuilder,
+    skinning::Sockets,
+    submesh::SubMeshes,
+    types::Mesh,
+    visibility::BoundingSphere,
 };
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
@@ -68,8 +74,14 @@ pub struct GltfPrefab {
     pub extent: Option<GltfNodeExtent>,
     /// Node name
     pub name: Option<Named>,
+    /// Materials of the primitives of a mesh merged into one vertex buffer, after sub asset
+    /// loading is done
+    pub sub_meshes: Option<SubMeshes>,
+    /// Sockets of skinned meshes, named after the child nodes of their joints
+    pub sockets: Option<Sockets>,
     pub(crate) materials: Option<GltfMaterialSet>,
     pub(crate) material_id: Option<usize>,
+    pub(crate) sub_mesh_materials: Vec<(Range<u32>, usize)>,
 }
 
 impl GltfPrefab {
@@ -213,6 +225,10 @@ pub struct GltfSceneOptions {
     /// Load the given scene index, if not supplied will either load the default scene (if set),
     /// or the first scene (only if there is only one scene, otherwise an `Error` will be returned).
     pub scene_index: Option<usize>,
+    /// Conversion from the conventions of the file, baked into meshes, node transforms, skins and
+    /// animations. Cameras and lights are rotated with their nodes, so their view direction is
+    /// only preserved by transforms without axis remap.
+    pub import_transform: ImportTransform,
 }
 
 impl<'a> PrefabData<'a> for GltfPrefab {
@@ -229,6 +245,8 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         Read<'a, AssetStorage<Mesh>>,
         ReadExpect<'a, Loader>,
         Write<'a, GltfMaterialSet>,
+        WriteStorage<'a, SubMeshes>,
+        WriteStorage<'a, Sockets>,
     );
     type Result = ();
 
@@ -252,6 +270,8 @@ impl<'a> PrefabData<'a> for GltfPrefab {
             _,
             _,
             _,
+            sub_meshes,
+            sockets,
         ) = system_data;
         if let Some(transform) = &self.transform {
             transform.add_to_entity(entity, transforms, entities, children)?;
@@ -259,6 +279,12 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         if let Some(mesh) = &self.mesh_handle {
             meshes.insert(entity, mesh.clone())?;
         }
+        if let Some(parts) = &self.sub_meshes {
+            sub_meshes.insert(entity, parts.clone())?;
+        }
+        if let Some(points) = &self.sockets {
+            sockets.insert(entity, points.clone())?;
+        }
         if let Some(camera) = &self.camera {
             camera.add_to_entity(entity, cameras, entities, children)?;
         }
@@ -288,7 +314,7 @@ impl<'a> PrefabData<'a> for GltfPrefab {
         progress: &mut ProgressCounter,
         system_data: &mut Self::SystemData,
     ) -> Result<bool, Error> {
-        let (_, _, _, _, materials, animatables, _, _, _, meshes_storage, loader, mat_set) =
+        let (_, _, _, _, materials, animatables, _, _, _, meshes_storage, loader, mat_set, _, _) =
             system_data;
 
         let mut ret = false;
@@ -304,6 +330,13 @@ impl<'a> PrefabData<'a> for GltfPrefab {
                 self.material.replace(mat.clone_loaded());
             }
         }
+        if !self.sub_mesh_materials.is_empty() {
+            let parts = self.sub_mesh_materials.drain(..).filter_map(|(range, id)| {
+                let handle = mat_set.materials.get(&id)?.handle()?;
+                Some((range, handle.clone()))
+            });
+            self.sub_meshes = Some(SubMeshes::new(parts));
+        }
         if let Some(mesh) = self.mesh.take() {
             self.mesh_handle =
                 Some(loader.load_from_data(mesh.clone().into(), &mut *progress, meshes_storage));
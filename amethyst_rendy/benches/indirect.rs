@@ -0,0 +1,70 @@
+use amethyst_rendy::{
+    rendy::command::DrawIndexedCommand,
+    submodules::{DrawRun, DrawRuns},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// 10k batches of 64 materials, each mesh split in 4 submeshes drawn one after another, as the
+// 3D passes order them.
+const BATCHES: u32 = 10_000;
+
+fn key(batch: u32) -> (u32, u32) {
+    (batch / 4 % 64, batch / 4)
+}
+
+// The per-batch recording work of direct draws: one call with the index and instance ranges
+// of each batch.
+pub fn record_10k_direct(b: &mut Criterion) {
+    let mut runs = DrawRuns::<(u32, u32)>::default();
+    b.bench_function("record_10k_direct", move |b| {
+        b.iter(|| {
+            runs.clear();
+            for _ in 0..BATCHES {
+                runs.push(None);
+            }
+            let mut calls = 0;
+            for run in runs.runs() {
+                if let DrawRun::Direct(batch) = run {
+                    black_box(*batch * 6..*batch * 6 + 6);
+                    calls += 1;
+                }
+            }
+            black_box(calls)
+        });
+    });
+}
+
+// The per-batch recording work of indirect draws: one command per batch, and one call per run
+// of batches sharing a material and mesh.
+pub fn record_10k_indirect(b: &mut Criterion) {
+    let mut runs = DrawRuns::default();
+    let mut commands = Vec::with_capacity(BATCHES as usize);
+    b.bench_function("record_10k_indirect", move |b| {
+        b.iter(|| {
+            runs.clear();
+            commands.clear();
+            for batch in 0..BATCHES {
+                commands.push(DrawIndexedCommand {
+                    index_count: 6,
+                    instance_count: 1,
+                    first_index: batch % 4 * 6,
+                    vertex_offset: 0,
+                    first_instance: batch,
+                });
+                runs.push(Some(key(batch)));
+            }
+            let mut calls = 0;
+            for run in runs.runs() {
+                if let DrawRun::Indirect(range) = run {
+                    black_box(range.clone());
+                    calls += 1;
+                }
+            }
+            black_box((commands.len(), calls))
+        });
+    });
+}
+
+criterion_group!(indirect, record_10k_direct, record_10k_indirect);
+criterion_main!(indirect);
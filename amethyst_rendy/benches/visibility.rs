@@ -0,0 +1,70 @@
+use amethyst_core::{
+    ecs::{Builder, DispatcherBuilder, RunNow, System, World, WorldExt},
+    SystemBundle, Transform, TransformBundle,
+};
+use amethyst_rendy::{
+    camera::Camera,
+    transparent::Transparent,
+    visibility::{DrawDistance, VisibilitySortingSystem},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rayon::ThreadPoolBuilder;
+
+// A 50k entities scene, a quarter of them transparent, spread around a camera at the origin so
+// that about half of them are frustum or distance culled.
+fn setup(parallel_threshold: usize) -> (World, VisibilitySortingSystem) {
+    let mut world = World::new();
+    let mut builder = DispatcherBuilder::new();
+    TransformBundle::new()
+        .build(&mut world, &mut builder)
+        .unwrap();
+    let mut dispatcher = builder.build();
+    dispatcher.setup(&mut world);
+    let mut system = VisibilitySortingSystem::new().with_parallel_threshold(parallel_threshold);
+    System::setup(&mut system, &mut world);
+
+    world
+        .create_entity()
+        .with(Camera::standard_3d(16.0, 9.0))
+        .with(Transform::default())
+        .build();
+    for i in 0..50_000 {
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(
+            (i % 100) as f32 * 2.0 - 100.0,
+            (i / 100 % 10) as f32 * 2.0 - 10.0,
+            (i / 1000) as f32 * 4.0 - 100.0,
+        );
+        let mut builder = world
+            .create_entity()
+            .with(transform)
+            .with(DrawDistance::new(80.0));
+        if i % 4 == 0 {
+            builder = builder.with(Transparent);
+        }
+        builder.build();
+    }
+    dispatcher.dispatch(&world);
+    (world, system)
+}
+
+fn cull_50k(b: &mut Criterion, name: &str, parallel_threshold: usize) {
+    let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    let (world, mut system) = setup(parallel_threshold);
+
+    b.bench_function(name, move |b| {
+        b.iter(|| pool.install(|| system.run_now(&world)));
+    });
+}
+
+pub fn serial_cull_50k(b: &mut Criterion) {
+    cull_50k(b, "serial_cull_50k", usize::MAX);
+}
+
+pub fn parallel_cull_50k(b: &mut Criterion) {
+    cull_50k(b, "parallel_cull_50k", 0);
+}
+
+criterion_group!(visibility, serial_cull_50k, parallel_cull_50k);
+criterion_main!(visibility);
@@ -0,0 +1,4 @@
+#version 450
+
+#define USE_NORMALS
+#include "header/bilateral_upsample.frag"
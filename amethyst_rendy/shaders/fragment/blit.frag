@@ -0,0 +1,13 @@
+#version 450
+
+// Copy of an image of another target onto the viewport of the group, see
+// amethyst_rendy/src/pass/blit.rs.
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    out_color = texture(source, tex_uv);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in VertexData {
+    vec2 uv;
+    float opacity;
+} vertex;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Multiplied over the target, white leaves it untouched.
+    float falloff = 1.0 - smoothstep(0.0, 1.0, length(vertex.uv));
+    out_color = vec4(vec3(1.0 - vertex.opacity * falloff), 1.0);
+}
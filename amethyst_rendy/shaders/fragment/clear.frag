@@ -0,0 +1,14 @@
+#version 450
+
+// Keep in sync with amethyst_rendy/src/pass/clear.rs
+layout(std140, set = 0, binding = 0) uniform ClearArgs {
+    vec4 color;
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+// The fullscreen triangle lies on the far plane, so the depth is cleared along with the color.
+void main() {
+    out_color = color;
+}
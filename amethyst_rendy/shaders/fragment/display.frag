@@ -0,0 +1,3 @@
+#version 450
+
+#include "header/display.frag"
@@ -0,0 +1,4 @@
+#version 450
+
+#define ENCODE_SRGB
+#include "header/display.frag"
@@ -0,0 +1,28 @@
+#version 450
+
+#include "header/fog.frag"
+
+layout(set = 0, binding = 0) uniform sampler2D scene_depth;
+
+// Keep in sync with amethyst_rendy/src/pass/fog.rs
+layout(std140, set = 1, binding = 0) uniform FogArgs {
+    mat4 inverse_proj_view;
+    vec3 camera_position;
+    Fog fog;
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(scene_depth, min(pixel, textureSize(scene_depth, 0) - 1), 0).r;
+
+    // Reversed Z with infinite far plane, empty pixels have zero depth.
+    vec4 end = inverse_proj_view * vec4(tex_uv * 2.0 - 1.0, max(depth, 1.0e-7), 1.0);
+    vec3 ray = end.xyz / end.w - camera_position;
+    vec3 dir = normalize(ray);
+    float dist = depth > 0.0 ? length(ray) : FOG_INFINITY;
+
+    out_color = vec4(fog_color(fog, dir), fog_opacity(fog, camera_position, dir, dist));
+}
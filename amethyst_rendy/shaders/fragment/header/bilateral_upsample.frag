@@ -0,0 +1,67 @@
+#ifndef BILATERAL_UPSAMPLE_FRAG
+#define BILATERAL_UPSAMPLE_FRAG
+
+// Depth-aware upsample of a half resolution effect.
+// Define USE_NORMALS before including to also weight taps by normal similarity.
+
+layout(set = 0, binding = 0) uniform sampler2D effect;
+layout(set = 0, binding = 1) uniform sampler2D full_depth;
+layout(set = 0, binding = 2) uniform sampler2D half_depth;
+#ifdef USE_NORMALS
+layout(set = 0, binding = 3) uniform sampler2D full_normals;
+layout(set = 0, binding = 4) uniform sampler2D half_normals;
+#endif
+
+layout(std140, set = 1, binding = 0) uniform UpsampleArgs {
+    float depth_sigma;
+    float normal_power;
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    ivec2 half_size = textureSize(half_depth, 0);
+    vec2 half_coord = tex_uv * vec2(half_size) - 0.5;
+    ivec2 base = ivec2(floor(half_coord));
+    vec2 f = half_coord - vec2(base);
+
+    float depth = texelFetch(full_depth, ivec2(gl_FragCoord.xy), 0).r;
+#ifdef USE_NORMALS
+    vec3 normal = texelFetch(full_normals, ivec2(gl_FragCoord.xy), 0).xyz * 2.0 - 1.0;
+#endif
+
+    vec4 sum = vec4(0.0);
+    float total = 0.0;
+    vec4 nearest_color = vec4(0.0);
+    float nearest_diff = 1.0e30;
+
+    for (int i = 0; i < 4; i++) {
+        ivec2 offset = ivec2(i & 1, i >> 1);
+        ivec2 tap = clamp(base + offset, ivec2(0), half_size - 1);
+        vec2 bilinear = mix(1.0 - f, f, vec2(offset));
+
+        // Reversed Z depth is proportional to the inverse view distance, so the relative
+        // difference approximates the relative difference of view distances.
+        float tap_depth = texelFetch(half_depth, tap, 0).r;
+        float diff = abs(tap_depth - depth) / max(depth, 1.0e-6);
+        float weight = bilinear.x * bilinear.y * exp(-(diff * diff) / (2.0 * depth_sigma * depth_sigma));
+#ifdef USE_NORMALS
+        vec3 tap_normal = texelFetch(half_normals, tap, 0).xyz * 2.0 - 1.0;
+        weight *= pow(max(dot(normal, tap_normal), 0.0), normal_power);
+#endif
+
+        vec4 color = texelFetch(effect, tap, 0);
+        sum += color * weight;
+        total += weight;
+        if (diff < nearest_diff) {
+            nearest_diff = diff;
+            nearest_color = color;
+        }
+    }
+
+    // Every tap is across a discontinuity, take the one closest in depth.
+    out_color = total > 1.0e-4 ? sum / total : nearest_color;
+}
+
+#endif
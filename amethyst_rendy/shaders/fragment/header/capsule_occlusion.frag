@@ -0,0 +1,23 @@
+// Ambient occlusion of the capsule occluders of the environment, see
+// amethyst_rendy/src/capsule.rs. Must be included after "header/environment.frag".
+
+// Occlusion of a sphere of radius `r` on the point of the capsule axis `a`-`b` closest to
+// `position`, in closed form.
+float capsule_occlusion(vec3 position, vec3 normal, vec3 a, vec3 b, float r) {
+    vec3 ba = b - a;
+    vec3 pa = position - a;
+    float h = clamp(dot(pa, ba) / max(dot(ba, ba), 1e-8), 0.0, 1.0);
+    vec3 d = pa - h * ba;
+    float l = max(length(d), r);
+    return 1.0 - max(dot(-d, normal), 0.0) * r * r / (l * l * l);
+}
+
+// Ambient visibility of the fragment between all the capsule occluders.
+float capsule_occlusion(vec3 position, vec3 normal) {
+    float visibility = 1.0;
+    for (int i = 0; i < capsule_count; i++) {
+        float o = clamp(capsule_occlusion(position, normal, capsule[i].start, capsule[i].end, capsule[i].radius), 0.0, 1.0);
+        visibility *= sqrt(o * o * o);
+    }
+    return visibility;
+}
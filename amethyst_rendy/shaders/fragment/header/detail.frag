@@ -0,0 +1,44 @@
+// Detail maps of the materials of the 3D fragment shaders.
+//
+// The detail map is tiled `detail_scale` times over the other maps and blended over the albedo
+// up close, fading out from half of the fade distance to the fade distance so it doesn't
+// shimmer far away. Middle gray leaves the albedo unchanged with either blend. Materials with
+// a fade distance of 0 don't sample it. Must be included after "header/triplanar.frag".
+
+const uint DETAIL_OVERLAY = 0;
+const uint DETAIL_LINEAR = 1;
+
+// Weight of the detail map at given view distance.
+float detail_weight(float fade_distance, float view_distance) {
+    return 1.0 - smoothstep(0.5 * fade_distance, fade_distance, view_distance);
+}
+
+// Albedo with the detail map blended over it.
+vec3 apply_detail(vec3 albedo, vec3 detail, uint blend, float weight) {
+    vec3 detailed;
+    if (blend == DETAIL_OVERLAY) {
+        float d = dot(detail, vec3(0.2126, 0.7152, 0.0722));
+        vec3 low = 2.0 * albedo * d;
+        vec3 high = 1.0 - 2.0 * (1.0 - albedo) * (1.0 - d);
+        detailed = mix(low, high, step(0.5, albedo));
+    } else {
+        detailed = albedo * detail * 2.0;
+    }
+    return mix(albedo, detailed, weight);
+}
+
+// Sampling of the detail map, along the axes at the detail scale when triplanar mapped.
+#ifdef TRIPLANAR
+#define detail_texture(tex, coord) (triplanar_mapped ? \
+    triplanar_texture(tex, triplanar_projection(vertex.position, normalize(vertex.normal), triplanar_scale * detail_scale, triplanar_sharpness)) : \
+    texture(tex, (coord) * detail_scale))
+#else
+#define detail_texture(tex, coord) texture(tex, (coord) * detail_scale)
+#endif
+
+// Applies the detail map of the material to the `albedo` local.
+#define APPLY_DETAIL(coord) \
+    if (detail_fade_distance > 0.0) { \
+        vec3 detail_color = detail_texture(detail, coord).rgb; \
+        albedo = apply_detail(albedo, detail_color, detail_blend, detail_weight(detail_fade_distance, distance(camera_position, vertex.position))); \
+    }
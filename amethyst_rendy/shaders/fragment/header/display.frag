@@ -0,0 +1,50 @@
+#ifndef DISPLAY_FRAG
+#define DISPLAY_FRAG
+
+// Conversion of the linear scene to the display target.
+// Define ENCODE_SRGB before including when the display target doesn't encode sRGB on write.
+
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+// Keep in sync with amethyst_rendy/src/pass/display.rs
+layout(std140, set = 1, binding = 0) uniform DisplayArgs {
+    float exposure;
+    // Zero for the sRGB transfer function.
+    float gamma;
+    // 0 clamps, 1 applies Reinhard.
+    int tone_map;
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+vec3 srgb_encode(vec3 color) {
+    return mix(color * 12.92, 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, color));
+}
+
+vec3 srgb_decode(vec3 color) {
+    return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+}
+
+void main() {
+    ivec2 pixel = min(ivec2(gl_FragCoord.xy), textureSize(scene, 0) - 1);
+    vec3 color = max(texelFetch(scene, pixel, 0).rgb * exposure, 0.0);
+    if (tone_map == 1) {
+        color = color / (1.0 + color);
+    }
+    color = min(color, 1.0);
+    if (gamma > 0.0) {
+        color = pow(color, vec3(1.0 / gamma));
+#ifndef ENCODE_SRGB
+        // The target encodes sRGB on write, decode it first to display the power curve.
+        color = srgb_decode(color);
+#endif
+    } else {
+#ifdef ENCODE_SRGB
+        color = srgb_encode(color);
+#endif
+    }
+    out_color = vec4(color, 1.0);
+}
+
+#endif
@@ -0,0 +1,15 @@
+// Dissolve of the materials of the 3D fragment shaders.
+//
+// Fragments whose noise is at or below the dissolve amount are discarded, and fragments within
+// the edge width above it glow with the edge color of the material. Every shader drawing the
+// materials, including the depth of their shadows, must discard the same fragments, so the
+// dissolve stays in this header. Nothing is dissolved with an amount of 0.
+
+// Discards dissolved fragments. Returns 1 on the glowing edge, 0 elsewhere.
+float dissolve(float noise, float amount, float edge_width) {
+    if (amount <= 0.0) {
+        return 0.0;
+    }
+    if (noise <= amount) discard;
+    return noise < amount + edge_width ? 1.0 : 0.0;
+}
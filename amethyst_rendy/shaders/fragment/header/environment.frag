@@ -2,8 +2,11 @@
 // Set 0.
 // Keep in sync with amethyst_rendy/src/submodules/environment.rs
 
+#include "fog.frag"
+
 struct PointLight {
     vec3 position;
+    uint channels;
     vec3 color;
     float intensity;
 };
@@ -12,10 +15,12 @@ struct DirectionalLight {
     vec3 color;
     float intensity;
     vec3 direction;
+    uint channels;
 };
 
 struct SpotLight {
     vec3 position;
+    uint channels;
     vec3 color;
     vec3 direction;
     float angle;
@@ -30,6 +35,14 @@ layout(std140, set = 0, binding = 1) uniform Environment {
     int point_light_count;
     int directional_light_count;
     int spot_light_count;
+    float surface_layer;
+    int capsule_count;
+};
+
+struct CapsuleOccluder {
+    vec3 start;
+    float radius;
+    vec3 end;
 };
 
 layout(std140, set = 0, binding = 2) uniform PointLights {
@@ -42,4 +55,12 @@ layout(std140, set = 0, binding = 3) uniform DirectionalLights {
 
 layout(std140, set = 0, binding = 4) uniform SpotLights {
     SpotLight slight[128];
-};
\ No newline at end of file
+};
+
+layout(std140, set = 0, binding = 5) uniform FogArgs {
+    Fog fog;
+};
+
+layout(std140, set = 0, binding = 6) uniform CapsuleOccluders {
+    CapsuleOccluder capsule[32];
+};
@@ -0,0 +1,45 @@
+// Analytic exponential height fog.
+// Keep in sync with amethyst_rendy/src/pod.rs
+
+#define FOG_DISABLED 0
+#define FOG_FORWARD 1
+#define FOG_POST_PASS 2
+
+// Distance of pixels without geometry, such as the skybox.
+#define FOG_INFINITY 1.0e6
+
+struct Fog {
+    vec3 color;
+    float density;
+    vec3 inscattering_color;
+    float inscattering_exponent;
+    vec3 inscattering_direction;
+    float base_height;
+    float height_falloff;
+    float max_opacity;
+    int mode;
+};
+
+// Opacity of the fog between `origin` and the point `dist` away along the normalized `dir`.
+float fog_opacity(Fog fog, vec3 origin, vec3 dir, float dist) {
+    // Density at the origin, constant below the base height.
+    float height = max(origin.y - fog.base_height, 0.0);
+    float start = fog.density * exp(-fog.height_falloff * height);
+
+    // Integral of exp(-falloff * dir.y * t) over [0, dist], divided by dist.
+    float rate = fog.height_falloff * dir.y * dist;
+    float integral = abs(rate) > 1.0e-4 ? (1.0 - exp(min(-rate, 80.0))) / rate : 1.0;
+
+    float optical_depth = start * dist * integral;
+    return min(1.0 - exp(-optical_depth), fog.max_opacity);
+}
+
+// Fog color seen along the normalized `dir`, tinted by the sun.
+vec3 fog_color(Fog fog, vec3 dir) {
+    float sun = max(dot(dir, -fog.inscattering_direction), 0.0);
+    return mix(fog.color, fog.inscattering_color, pow(sun, fog.inscattering_exponent));
+}
+
+vec3 apply_fog(Fog fog, vec3 color, vec3 origin, vec3 dir, float dist) {
+    return mix(color, fog_color(fog, dir), fog_opacity(fog, origin, dir, dist));
+}
@@ -0,0 +1,42 @@
+// Shadow map of the directional light casting shadows, see amethyst_rendy/src/shadow.rs.
+// Set 0, must be included after "header/environment.frag".
+// Keep in sync with amethyst_rendy/src/submodules/environment.rs
+
+layout(std140, set = 0, binding = 7) uniform ShadowArgs {
+    mat4 proj_view;
+    float depth_bias;
+    float slope_bias;
+    // Index of the directional light casting shadows, -1 without shadow map.
+    int light;
+    int filter_radius;
+} shadow;
+
+layout(set = 0, binding = 8) uniform sampler2D shadow_map;
+
+// Fraction of the light shining along `light_dir` reaching `position`, 1.0 out of the map.
+// Depth goes from 1 towards the light to 0, so the fragment is lit when it isn't deeper than
+// the nearest caster.
+float shadow_factor(vec3 position, vec3 normal, vec3 light_dir) {
+    if (shadow.light < 0) return 1.0;
+    vec4 clip = shadow.proj_view * vec4(position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    vec2 uv = ndc.xy * 0.5 + 0.5;
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))) || ndc.z < 0.0 || ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    float cos_angle = clamp(dot(normal, -light_dir), 0.05, 1.0);
+    float tan_angle = sqrt(1.0 - cos_angle * cos_angle) / cos_angle;
+    float depth = ndc.z + shadow.depth_bias + shadow.slope_bias * min(tan_angle, 10.0);
+
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float lit = 0.0;
+    for (int x = -shadow.filter_radius; x <= shadow.filter_radius; x++) {
+        for (int y = -shadow.filter_radius; y <= shadow.filter_radius; y++) {
+            float caster = texture(shadow_map, uv + vec2(x, y) * texel).r;
+            lit += depth >= caster ? 1.0 : 0.0;
+        }
+    }
+    float side = float(2 * shadow.filter_radius + 1);
+    return lit / (side * side);
+}
@@ -0,0 +1,13 @@
+// Surface layer of the materials of the 3D fragment shaders, e.g. wetness or scorch marks.
+//
+// The shaded and PBR fragment shaders are also compiled with SURFACE_LAYER defined. The layer
+// darkens the albedo by the layer color and, in the PBR shader, replaces the roughness, weighted
+// by the red channel of the layer mask times the amount of the material plus its share of the
+// scene amount. Must be included after "header/triplanar.frag" and "header/environment.frag".
+
+#ifdef SURFACE_LAYER
+// Weight of the surface layer of the material. Materials without any amount skip the mask.
+#define SURFACE_LAYER_WEIGHT(coord) ((layer_amount > 0.0 || layer_scene_factor > 0.0) ? \
+    clamp(material_texture(layer_mask, coord).r * (layer_amount + layer_scene_factor * surface_layer), 0.0, 1.0) : \
+    0.0)
+#endif
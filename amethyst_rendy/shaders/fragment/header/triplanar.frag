@@ -0,0 +1,61 @@
+// Triplanar mapping of the 3D fragment shaders.
+//
+// The shaded and PBR fragment shaders are also compiled with TRIPLANAR defined. Materials with
+// a triplanar scale above 0 are then sampled along the three world axes instead of their
+// texture coordinates, blended by the world normal raised to the triplanar sharpness. The
+// projections are mirrored on the negative side of every axis, so textures aren't drawn
+// backwards there.
+
+#ifdef TRIPLANAR
+struct Triplanar {
+    vec2 uv_x;
+    vec2 uv_y;
+    vec2 uv_z;
+    vec3 signs;
+    vec3 weights;
+};
+
+Triplanar triplanar_projection(vec3 position, vec3 normal, float scale, float sharpness) {
+    Triplanar t;
+    vec3 p = position * scale;
+    t.signs = vec3(normal.x < 0.0 ? -1.0 : 1.0, normal.y < 0.0 ? -1.0 : 1.0, normal.z < 0.0 ? -1.0 : 1.0);
+    t.uv_x = vec2(p.z * t.signs.x, p.y);
+    t.uv_y = vec2(p.x * t.signs.y, p.z);
+    t.uv_z = vec2(-p.x * t.signs.z, p.y);
+    vec3 w = pow(abs(normal), vec3(sharpness));
+    t.weights = w / max(w.x + w.y + w.z, 1.0e-5);
+    return t;
+}
+
+vec4 triplanar_texture(sampler2D tex, Triplanar t) {
+    return texture(tex, t.uv_x) * t.weights.x +
+        texture(tex, t.uv_y) * t.weights.y +
+        texture(tex, t.uv_z) * t.weights.z;
+}
+
+// World space normal from a tangent space normal map. Every projection reorients the normal
+// map to its own axis with a whiteout blend against the surface normal before blending them,
+// as a single tangent basis doesn't exist for the three projections.
+vec3 triplanar_normal(sampler2D tex, Triplanar t, vec3 normal) {
+    vec3 nx = texture(tex, t.uv_x).xyz * 2.0 - 1.0;
+    vec3 ny = texture(tex, t.uv_y).xyz * 2.0 - 1.0;
+    vec3 nz = texture(tex, t.uv_z).xyz * 2.0 - 1.0;
+    nx.x *= t.signs.x;
+    ny.x *= t.signs.y;
+    nz.x *= -t.signs.z;
+    nx = vec3(nx.xy + normal.zy, abs(nx.z) * normal.x);
+    ny = vec3(ny.xy + normal.xz, abs(ny.z) * normal.y);
+    nz = vec3(nz.xy + normal.xy, abs(nz.z) * normal.z);
+    return normalize(nx.zyx * t.weights.x + ny.xzy * t.weights.y + nz.xyz * t.weights.z);
+}
+
+// Sampling of the maps of a material, along the axes when triplanar mapped. Needs the
+// `triplanar_mapped` and `triplanar` locals of `TRIPLANAR_LOCALS` in scope.
+#define TRIPLANAR_LOCALS \
+    bool triplanar_mapped = triplanar_scale > 0.0; \
+    Triplanar triplanar = triplanar_projection(vertex.position, normalize(vertex.normal), triplanar_scale, triplanar_sharpness);
+#define material_texture(tex, coord) (triplanar_mapped ? triplanar_texture(tex, triplanar) : texture(tex, coord))
+#else
+#define TRIPLANAR_LOCALS
+#define material_texture(tex, coord) texture(tex, coord)
+#endif
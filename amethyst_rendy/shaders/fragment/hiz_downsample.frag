@@ -0,0 +1,28 @@
+#version 450
+
+// Previous level of the Hi-Z chain.
+layout(set = 0, binding = 0) uniform sampler2D previous;
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out float out_depth;
+
+void main() {
+    ivec2 size = textureSize(previous, 0);
+    ivec2 out_size = max(size / 2, ivec2(1));
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    ivec2 base = texel * 2;
+
+    // Texels in the last column or row of an odd sized level also cover the extra one.
+    int columns = (size.x % 2 == 1 && texel.x == out_size.x - 1) ? 3 : 2;
+    int rows = (size.y % 2 == 1 && texel.y == out_size.y - 1) ? 3 : 2;
+
+    // Reversed Z, farthest depth is the smallest one.
+    float farthest = 1.0;
+    for (int y = 0; y < rows; y++) {
+        for (int x = 0; x < columns; x++) {
+            ivec2 source = min(base + ivec2(x, y), size - 1);
+            farthest = min(farthest, texelFetch(previous, source, 0).r);
+        }
+    }
+    out_depth = farthest;
+}
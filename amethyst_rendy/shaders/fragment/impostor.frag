@@ -0,0 +1,24 @@
+#version 450
+
+layout(set = 1, binding = 0) uniform sampler2D albedo;
+
+layout(location = 0) in VertexData {
+    vec2 tex_uv;
+    vec2 tex_uv_next;
+    float blend;
+    vec4 color;
+    float alpha_cutoff;
+} vertex;
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec4 color = texture(albedo, vertex.tex_uv);
+    if (vertex.blend > 0.0) {
+        color = mix(color, texture(albedo, vertex.tex_uv_next), vertex.blend);
+    }
+    color *= vertex.color;
+    if (color.a < vertex.alpha_cutoff) {
+        discard;
+    }
+    out_color = vec4(color.rgb, 1.0);
+}
@@ -0,0 +1,29 @@
+#version 450
+
+#include "header/math.frag"
+
+layout(std140, set = 0, binding = 0) uniform Material {
+    UvOffset uv_offset;
+    float alpha_cutoff;
+};
+
+layout(set = 0, binding = 1) uniform sampler2D albedo;
+
+layout(location = 0) in VertexData {
+    vec3 normal;
+    vec3 to_camera;
+    vec2 tex_coord;
+    vec4 light;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec4 albedo = texture(albedo, tex_coords(vertex.tex_coord, uv_offset));
+    if (albedo.a < alpha_cutoff) discard;
+    // Both sides of thin surfaces are lit, as seen from the camera.
+    vec3 normal = normalize(vertex.normal);
+    normal = dot(normal, vertex.to_camera) < 0.0 ? -normal : normal;
+    float lit = max(dot(normal, vertex.light.xyz), 0.0);
+    out_color = vec4(albedo.rgb * mix(vertex.light.w, 1.0, lit), 1.0);
+}
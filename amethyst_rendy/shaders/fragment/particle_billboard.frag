@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in VertexData {
+    vec2 uv;
+    vec4 color;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Soft round particle.
+    float falloff = 1.0 - smoothstep(0.5, 1.0, length(vertex.uv));
+    if (falloff <= 0.0) {
+        discard;
+    }
+    out_color = vec4(vertex.color.rgb, vertex.color.a * falloff);
+}
@@ -0,0 +1,16 @@
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2D state_position;
+layout(set = 0, binding = 1) uniform sampler2D state_velocity;
+layout(set = 0, binding = 2) uniform usampler2D state_appearance;
+
+layout(location = 0) out vec4 out_position;
+layout(location = 1) out vec4 out_velocity;
+layout(location = 2) out uvec4 out_appearance;
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    out_position = texelFetch(state_position, texel, 0);
+    out_velocity = texelFetch(state_velocity, texel, 0);
+    out_appearance = texelFetch(state_appearance, texel, 0);
+}
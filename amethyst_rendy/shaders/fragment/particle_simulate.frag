@@ -0,0 +1,55 @@
+#version 450
+
+// Keep in sync with amethyst_rendy/src/pass/gpu_particles.rs
+struct Attractor {
+    vec3 position;
+    float strength;
+};
+
+// Position and age, velocity and lifetime, packed colors and sizes.
+layout(set = 0, binding = 0) uniform sampler2D state_position;
+layout(set = 0, binding = 1) uniform sampler2D state_velocity;
+layout(set = 0, binding = 2) uniform usampler2D state_appearance;
+
+layout(std140, set = 1, binding = 0) uniform SimulationArgs {
+    uvec2 state_size;
+    vec3 gravity;
+    float drag;
+    float delta;
+    uint reset;
+    int attractor_count;
+    Attractor attractors[8];
+};
+
+layout(location = 0) out vec4 out_position;
+layout(location = 1) out vec4 out_velocity;
+layout(location = 2) out uvec4 out_appearance;
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    vec4 position = texelFetch(state_position, texel, 0);
+    vec4 velocity = texelFetch(state_velocity, texel, 0);
+
+    position.w += delta;
+    // Dead particles are older than their lifetime.
+    if (reset != 0u || position.w >= velocity.w) {
+        out_position = vec4(0.0, 0.0, 0.0, 1.0);
+        out_velocity = vec4(0.0);
+        out_appearance = uvec4(0u);
+        return;
+    }
+
+    vec3 acceleration = gravity;
+    for (int i = 0; i < attractor_count; i++) {
+        vec3 offset = attractors[i].position - position.xyz;
+        // Softened to keep particles passing through the attractor from shooting away.
+        float dist2 = dot(offset, offset) + 0.01;
+        acceleration += attractors[i].strength * offset * inversesqrt(dist2) / dist2;
+    }
+    velocity.xyz = (velocity.xyz + acceleration * delta) * max(1.0 - drag * delta, 0.0);
+    position.xyz += velocity.xyz * delta;
+
+    out_position = position;
+    out_velocity = velocity;
+    out_appearance = texelFetch(state_appearance, texel, 0);
+}
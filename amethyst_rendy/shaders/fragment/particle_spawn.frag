@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) flat in vec4 state_position;
+layout(location = 1) flat in vec4 state_velocity;
+layout(location = 2) flat in uvec4 state_appearance;
+
+layout(location = 0) out vec4 out_position;
+layout(location = 1) out vec4 out_velocity;
+layout(location = 2) out uvec4 out_appearance;
+
+void main() {
+    out_position = state_position;
+    out_velocity = state_velocity;
+    out_appearance = state_appearance;
+}
@@ -2,11 +2,53 @@
 
 #include "header/math.frag"
 
+#include "header/dissolve.frag"
+
+#include "header/triplanar.frag"
+
+#include "header/detail.frag"
+
 #include "header/environment.frag"
 
+#include "header/capsule_occlusion.frag"
+
+#include "header/shadow.frag"
+
+#include "header/surface_layer.frag"
+
+#ifdef EARLY_DEPTH
+// Without discard, depth can be tested before shading even if the driver doesn't detect it.
+layout(early_fragment_tests) in;
+#endif
+
 layout(std140, set = 1, binding = 0) uniform Material {
     UvOffset uv_offset;
     float alpha_cutoff;
+    vec3 emission_factor;
+    vec3 uv_transform_u;
+    vec3 uv_transform_v;
+    vec3 normal_uv_transform_u;
+    vec3 normal_uv_transform_v;
+    float dissolve_amount;
+    float dissolve_edge_width;
+    vec3 dissolve_edge_color;
+    float triplanar_scale;
+    float triplanar_sharpness;
+    float detail_scale;
+    float detail_fade_distance;
+    uint detail_blend;
+    float layer_amount;
+    float layer_scene_factor;
+    float layer_roughness;
+    vec3 layer_color;
+    uint light_channels;
+    float subsurface;
+    vec3 specular;
+    float shininess;
+#ifdef MATERIAL_SHADER
+    // Parameters of the material shader, see `MAX_MATERIAL_SHADER_PARAMS`.
+    vec4 shader_params[4];
+#endif
 };
 
 layout(set = 1, binding = 1) uniform sampler2D albedo;
@@ -15,13 +57,16 @@ layout(set = 1, binding = 3) uniform sampler2D normal;
 layout(set = 1, binding = 4) uniform sampler2D metallic_roughness;
 layout(set = 1, binding = 5) uniform sampler2D ambient_occlusion;
 layout(set = 1, binding = 6) uniform sampler2D cavity;
+layout(set = 1, binding = 7) uniform sampler2D dissolve_noise;
+layout(set = 1, binding = 8) uniform sampler2D detail;
+layout(set = 1, binding = 9) uniform sampler2D layer_mask;
 
 layout(location = 0) in VertexData {
     vec3 position;
     vec3 normal;
     vec3 tangent;
     float tang_handedness;
-    vec2 tex_coord;
+    vec4 tex_coord; // xy: every map but the normal map, zw: normal map
     vec4 color;
 } vertex;
 
@@ -58,26 +103,48 @@ vec3 compute_light(vec3 attenuation,
     vec3 nominator = normal_distribution * geometry * fresnel;
     float denominator = 4 * NdotV * NdotL + 0.0001;
     vec3 specular = nominator / denominator;
+#ifdef SUBSURFACE
+    specular = vec3(0.0);
+#endif
 
     vec3 resulting_light = (diffuse * albedo / PI + specular) * light_color * attenuation * NdotL;
     return resulting_light;
 }
 
 void main() {
-    vec2 final_tex_coords   = tex_coords(vertex.tex_coord, uv_offset);
-    vec4 albedo_alpha       = texture(albedo, final_tex_coords);
+    TRIPLANAR_LOCALS
+    vec2 final_tex_coords   = tex_coords(vertex.tex_coord.xy, uv_offset);
+    vec4 albedo_alpha       = material_texture(albedo, final_tex_coords);
     float alpha             = albedo_alpha.a;
+#ifdef EARLY_DEPTH
+    // Variant drawing the materials which discard no fragments, see `Material::discards`.
+    float dissolve_edge = 0.0;
+#else
     if(alpha < alpha_cutoff) discard;
+    float dissolve_edge = dissolve(material_texture(dissolve_noise, final_tex_coords).r, dissolve_amount, dissolve_edge_width);
+#endif
 
     vec3 albedo             = albedo_alpha.rgb;
-    vec3 emission           = texture(emission, final_tex_coords).rgb;
-    vec3 normal             = texture(normal, final_tex_coords).rgb;
-    vec2 metallic_roughness = texture(metallic_roughness, final_tex_coords).bg;
-    float ambient_occlusion = texture(ambient_occlusion, final_tex_coords).r;
+    APPLY_DETAIL(final_tex_coords)
+#ifdef SURFACE_LAYER
+    float layer_weight = SURFACE_LAYER_WEIGHT(final_tex_coords);
+    albedo *= mix(vec3(1.0), layer_color, layer_weight);
+#endif
+    vec3 emission           = material_texture(emission, final_tex_coords).rgb * emission_factor;
+#ifdef TRIPLANAR
+    // Sampled before the normal map is shadowed.
+    vec3 triplanar_world_normal = triplanar_mapped ? triplanar_normal(normal, triplanar, normalize(vertex.normal)) : vec3(0.0);
+#endif
+    vec3 normal             = texture(normal, tex_coords(vertex.tex_coord.zw, uv_offset)).rgb;
+    vec2 metallic_roughness = material_texture(metallic_roughness, final_tex_coords).bg;
+    float ambient_occlusion = material_texture(ambient_occlusion, final_tex_coords).r;
     // TODO: Use cavity
     // float cavity            = texture(cavity, tex_coords(vertex.tex_coord, final_tex_coords).r;
     float metallic          = metallic_roughness.r;
     float roughness         = metallic_roughness.g;
+#ifdef SURFACE_LAYER
+    roughness = mix(roughness, layer_roughness, layer_weight);
+#endif
 
     // normal conversion
     normal = normal * 2 - 1;
@@ -90,10 +157,22 @@ void main() {
     vec3 vertex_bitangent = normalize(cross(vertex_normal, vertex_tangent) * vertex.tang_handedness);
     mat3 vertex_basis = mat3(vertex_tangent, vertex_bitangent, vertex_normal);
     normal = normalize(vertex_basis * normal);
+#ifdef TRIPLANAR
+    if (triplanar_mapped) {
+        normal = triplanar_world_normal;
+    }
+#endif
+    // MATERIAL_SHADER
+#ifdef MATERIAL_SHADER
+    if (alpha < alpha_cutoff) discard;
+    normal = normalize(normal);
+    fresnel_base = mix(vec3(0.04), albedo, metallic);
+#endif
 
     vec3 view_direction = normalize(camera_position - vertex.position);
     vec3 lighted = vec3(0.0);
     for (int i = 0; i < point_light_count; i++) {
+        if ((plight[i].channels & light_channels) == 0u) continue;
         vec3 light_direction = normalize(plight[i].position - vertex.position);
         float attenuation = plight[i].intensity / dot(light_direction, light_direction);
 
@@ -111,8 +190,10 @@ void main() {
     }
 
     for (int i = 0; i < directional_light_count; i++) {
+        if ((dlight[i].channels & light_channels) == 0u) continue;
         vec3 light_direction = -normalize(dlight[i].direction);
         float attenuation = dlight[i].intensity;
+        if (i == shadow.light) attenuation *= shadow_factor(vertex.position, normal, -light_direction);
 
         vec3 light = compute_light(vec3(attenuation),
                                    dlight[i].color,
@@ -128,6 +209,7 @@ void main() {
     }
 
     for (int i = 0; i < spot_light_count; i++) {
+        if ((slight[i].channels & light_channels) == 0u) continue;
         vec3 light_vec = slight[i].position - vertex.position;
         vec3 normalized_light_vec = normalize(light_vec);
 
@@ -159,7 +241,7 @@ void main() {
 
         // How much is this outside of the ring? (let's call it "rim")
         // Also smooth this out.
-        float rim_attenuation = pow(max((1.0 - frag_angle) / (1.0 - spot_angle), 0.00001), smoothness);
+        float rim_attenuation = pow(max((1.0 - frag_angle) / max(1.0 - spot_angle, 0.00001), 0.00001), smoothness);
 
         // How much is this inside the "ring"?
         float ring_attenuation = 1.0 - rim_attenuation;
@@ -179,8 +261,21 @@ void main() {
         lighted += light;
     }
 
-    vec3 ambient = ambient_color * albedo * ambient_occlusion;
+    vec3 ambient = ambient_color * albedo * ambient_occlusion * capsule_occlusion(vertex.position, normal);
+#ifdef SUBSURFACE
+    // Diffuse lighting scattered by `DrawSubsurface`, swapped for the scattered light in the
+    // target by the composite.
+    out_color = vec4((ambient + lighted) * vertex.color.rgb * subsurface, subsurface);
+    return;
+#endif
     vec3 color = ambient + lighted + emission;
 
     out_color = vec4(color, alpha) * vertex.color;
+    out_color.rgb = mix(out_color.rgb, dissolve_edge_color, dissolve_edge);
+
+    if (fog.mode == FOG_FORWARD) {
+        vec3 view_ray = vertex.position - camera_position;
+        float view_distance = length(view_ray);
+        out_color.rgb = apply_fog(fog, out_color.rgb, camera_position, view_ray / max(view_distance, 1.0e-6), view_distance);
+    }
 }
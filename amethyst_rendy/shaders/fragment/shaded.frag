@@ -2,38 +2,131 @@
 
 #include "header/math.frag"
 
+#include "header/dissolve.frag"
+
+#include "header/triplanar.frag"
+
+#include "header/detail.frag"
+
 #include "header/environment.frag"
 
+#include "header/capsule_occlusion.frag"
+
+#include "header/shadow.frag"
+
+#include "header/surface_layer.frag"
+
+#ifdef EARLY_DEPTH
+// Without discard, depth can be tested before shading even if the driver doesn't detect it.
+layout(early_fragment_tests) in;
+#endif
+
 layout(set = 1, binding = 0) uniform Material {
     UvOffset uv_offset;
     float alpha_cutoff;
+    vec3 emission_factor;
+    vec3 uv_transform_u;
+    vec3 uv_transform_v;
+    vec3 normal_uv_transform_u;
+    vec3 normal_uv_transform_v;
+    float dissolve_amount;
+    float dissolve_edge_width;
+    vec3 dissolve_edge_color;
+    float triplanar_scale;
+    float triplanar_sharpness;
+    float detail_scale;
+    float detail_fade_distance;
+    uint detail_blend;
+    float layer_amount;
+    float layer_scene_factor;
+    float layer_roughness;
+    vec3 layer_color;
+    uint light_channels;
+    float subsurface;
+    vec3 specular;
+    float shininess;
+#ifdef MATERIAL_SHADER
+    // Parameters of the material shader, see `MAX_MATERIAL_SHADER_PARAMS`.
+    vec4 shader_params[4];
+#endif
 };
 
 layout(set = 1, binding = 1) uniform sampler2D albedo;
 layout(set = 1, binding = 2) uniform sampler2D emission;
+layout(set = 1, binding = 3) uniform sampler2D dissolve_noise;
+layout(set = 1, binding = 4) uniform sampler2D detail;
+layout(set = 1, binding = 5) uniform sampler2D layer_mask;
 
+#ifdef NORMAL_MAP
+// Variant perturbing the normal by the normal map of the material in the basis of the tangents
+// of the mesh, which is a flat normal map unless set.
+layout(set = 1, binding = 6) uniform sampler2D normal_map;
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec3 tangent;
+    float tang_handedness;
+    vec4 tex_coord; // xy: every map but the normal map, zw: normal map
+    vec4 color;
+} vertex;
+    #define BASE_TEX_COORD vertex.tex_coord.xy
+#else
 layout(location = 0) in VertexData {
     vec3 position;
     vec3 normal;
     vec2 tex_coord;
     vec4 color;
 } vertex;
+    #define BASE_TEX_COORD vertex.tex_coord
+#endif
 
 layout(location = 0) out vec4 out_color;
 
+// Blinn-Phong highlight of a light lighting the fragment with given diffuse factor.
+float highlight(vec3 light_dir, vec3 normal, vec3 view_dir, float diff) {
+    if (diff <= 0.0) return 0.0;
+    return pow(max(dot(normal, normalize(light_dir + view_dir)), 0.0), max(shininess, 1.0));
+}
 
 void main() {
-    vec2 final_tex_coords   = tex_coords(vertex.tex_coord, uv_offset);
-    vec4 albedo_alpha       = texture(albedo, final_tex_coords);
+    TRIPLANAR_LOCALS
+    vec2 final_tex_coords   = tex_coords(BASE_TEX_COORD, uv_offset);
+    vec4 albedo_alpha       = material_texture(albedo, final_tex_coords);
     float alpha             = albedo_alpha.a;
+#ifdef EARLY_DEPTH
+    // Variant drawing the materials which discard no fragments, see `Material::discards`.
+    float dissolve_edge = 0.0;
+#else
     if(alpha < alpha_cutoff) discard;
+    float dissolve_edge = dissolve(material_texture(dissolve_noise, final_tex_coords).r, dissolve_amount, dissolve_edge_width);
+#endif
 
     vec3 albedo = albedo_alpha.rgb;
-    vec3 emission = texture(emission, final_tex_coords).rgb;
+    APPLY_DETAIL(final_tex_coords)
+#ifdef SURFACE_LAYER
+    float layer_weight = SURFACE_LAYER_WEIGHT(final_tex_coords);
+    albedo *= mix(vec3(1.0), layer_color, layer_weight);
+#endif
+    vec3 emission = material_texture(emission, final_tex_coords).rgb * emission_factor;
 
     vec3 lighting = vec3(0.0);
+    vec3 specular_lighting = vec3(0.0);
     vec3 normal = normalize(vertex.normal);
+#ifdef NORMAL_MAP
+    vec3 tangent_normal = texture(normal_map, tex_coords(vertex.tex_coord.zw, uv_offset)).rgb * 2.0 - 1.0;
+    vec3 vertex_tangent = normalize(vertex.tangent - normal * dot(normal, vertex.tangent));
+    vec3 vertex_bitangent = normalize(cross(normal, vertex_tangent) * vertex.tang_handedness);
+    normal = normalize(mat3(vertex_tangent, vertex_bitangent, normal) * tangent_normal);
+#endif
+    vec3 view_dir = normalize(camera_position - vertex.position);
+    // MATERIAL_SHADER
+#ifdef MATERIAL_SHADER
+    if (alpha < alpha_cutoff) discard;
+    normal = normalize(normal);
+#endif
     for (uint i = 0u; i < point_light_count; i++) {
+        if ((plight[i].channels & light_channels) == 0u) continue;
         // Calculate diffuse light
         vec3 light_dir = normalize(plight[i].position - vertex.position);
         float diff = max(dot(light_dir, normal), 0.0);
@@ -43,13 +136,46 @@ void main() {
         float dist2 = dot(dist, dist);
         float attenuation = (plight[i].intensity / dist2);
         lighting += diffuse * attenuation;
+        specular_lighting += highlight(light_dir, normal, view_dir, diff) * normalize(plight[i].color) * attenuation;
     }
     for (uint i = 0u; i < directional_light_count; i++) {
+        if ((dlight[i].channels & light_channels) == 0u) continue;
         vec3 dir = dlight[i].direction;
         float diff = max(dot(-dir, normal), 0.0);
-        vec3 diffuse = diff * dlight[i].color;
+        float shadowed = int(i) == shadow.light ? shadow_factor(vertex.position, normal, normalize(dir)) : 1.0;
+        vec3 diffuse = diff * shadowed * dlight[i].color;
         lighting += diffuse * dlight[i].intensity;
+        specular_lighting += highlight(normalize(-dir), normal, view_dir, diff) * shadowed * dlight[i].color * dlight[i].intensity;
     }
-    lighting += ambient_color;
+    for (uint i = 0u; i < spot_light_count; i++) {
+        if ((slight[i].channels & light_channels) == 0u) continue;
+        vec3 dist = slight[i].position - vertex.position;
+        vec3 light_dir = normalize(dist);
+        float diff = max(dot(light_dir, normal), 0.0);
+        float range_attenuation = max(0.0, 1.0 - length(dist) / max(slight[i].range, 0.00001));
+        // The angle of spot lights is the cosine of their half aperture, 1.0 for a closed cone,
+        // which lights nothing.
+        float cos_angle = dot(normalize(slight[i].direction), -light_dir);
+        float cone = clamp((cos_angle - slight[i].angle) / max(1.0 - slight[i].angle, 0.00001), 0.0, 1.0);
+        float cone_attenuation = cone > 0.0 ? pow(cone, max(slight[i].smoothness, 0.0)) : 0.0;
+        vec3 attenuated = slight[i].color * range_attenuation * cone_attenuation * slight[i].intensity;
+        lighting += diff * attenuated;
+        specular_lighting += highlight(light_dir, normal, view_dir, diff) * attenuated;
+    }
+    lighting += ambient_color * capsule_occlusion(vertex.position, normal);
+#ifdef SUBSURFACE
+    // Diffuse lighting scattered by `DrawSubsurface`, swapped for the scattered light in the
+    // target by the composite.
+    out_color = vec4(lighting * albedo * vertex.color.rgb * subsurface, subsurface);
+    return;
+#endif
     out_color = vec4(lighting * albedo + emission, alpha) * vertex.color;
+    out_color.rgb += specular_lighting * specular;
+    out_color.rgb = mix(out_color.rgb, dissolve_edge_color, dissolve_edge);
+
+    if (fog.mode == FOG_FORWARD) {
+        vec3 view_ray = vertex.position - camera_position;
+        float view_distance = length(view_ray);
+        out_color.rgb = apply_fog(fog, out_color.rgb, camera_position, view_ray / max(view_distance, 1.0e-6), view_distance);
+    }
 }
@@ -0,0 +1,39 @@
+#version 450
+
+#include "header/environment.frag"
+
+layout(set = 1, binding = 0) uniform sampler2D density;
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+    float height;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    // Strands lower than the shell are cut out.
+    if (texture(density, vertex.tex_coord).r < vertex.height) discard;
+
+    vec3 normal = normalize(vertex.normal);
+    vec3 lighting = ambient_color;
+    for (int i = 0; i < point_light_count; i++) {
+        vec3 dist = plight[i].position - vertex.position;
+        float diff = max(dot(normalize(dist), normal), 0.0);
+        lighting += diff * plight[i].color * plight[i].intensity / dot(dist, dist);
+    }
+    for (int i = 0; i < directional_light_count; i++) {
+        float diff = max(dot(-dlight[i].direction, normal), 0.0);
+        lighting += diff * dlight[i].color * dlight[i].intensity;
+    }
+    out_color = vec4(lighting * vertex.color.rgb, 1.0);
+
+    if (fog.mode == FOG_FORWARD) {
+        vec3 view_ray = vertex.position - camera_position;
+        float view_distance = length(view_ray);
+        out_color.rgb = apply_fog(fog, out_color.rgb, camera_position, view_ray / max(view_distance, 1.0e-6), view_distance);
+    }
+}
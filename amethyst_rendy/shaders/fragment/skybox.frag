@@ -1,5 +1,7 @@
 #version 450
 
+#include "header/fog.frag"
+
 layout(early_fragment_tests) in;
 
 layout(location = 0) in VertexData {
@@ -14,8 +16,16 @@ layout(std140, set = 1, binding = 0) uniform _ {
     vec3 nadir_color;
 };
 
+layout(std140, set = 2, binding = 0) uniform FogArgs {
+    Fog fog;
+    vec3 camera_position;
+};
+
 void main() {
     vec3 normalized_position = normalize(vertex.position.xyz);
     vec3 horizon_color = mix(nadir_color, zenith_color, smoothstep(-1., 1., normalized_position.y));
+    if (fog.mode == FOG_FORWARD) {
+        horizon_color = apply_fog(fog, horizon_color, camera_position, normalized_position, FOG_INFINITY);
+    }
     out_color = vec4(horizon_color, 1.0f);
 }
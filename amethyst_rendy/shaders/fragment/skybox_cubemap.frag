@@ -0,0 +1,28 @@
+#version 450
+
+#include "header/fog.frag"
+
+layout(early_fragment_tests) in;
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec2 tex_coord;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = 1, binding = 0) uniform samplerCube cubemap;
+
+layout(std140, set = 2, binding = 0) uniform FogArgs {
+    Fog fog;
+    vec3 camera_position;
+};
+
+void main() {
+    vec3 direction = normalize(vertex.position.xyz);
+    vec3 sky_color = texture(cubemap, direction).rgb;
+    if (fog.mode == FOG_FORWARD) {
+        sky_color = apply_fog(fog, sky_color, camera_position, direction, FOG_INFINITY);
+    }
+    out_color = vec4(sky_color, 1.0f);
+}
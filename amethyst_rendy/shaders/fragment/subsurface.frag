@@ -0,0 +1,54 @@
+#version 450
+
+// Separable subsurface scattering blur, following the surface with the scene depth.
+// Keep in sync with amethyst_rendy/src/pass/subsurface.rs
+
+// Diffuse lighting of scattering materials in rgb, scattering strength in alpha.
+layout(set = 0, binding = 0) uniform sampler2D scattered;
+layout(set = 0, binding = 1) uniform sampler2D scene_depth;
+#ifdef SUBSURFACE_SOURCE
+// Unblurred input, removed from the target before the scattered light is added.
+layout(set = 0, binding = 2) uniform sampler2D source;
+#endif
+
+layout(std140, set = 1, binding = 0) uniform SubsurfaceArgs {
+    mat4 inverse_proj;
+    vec2 step_scale;
+    vec2 direction;
+    float width;
+    int taps;
+    vec4 kernel[25];
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+float view_depth(vec2 uv) {
+    // Reversed Z with infinite far plane, empty pixels have zero depth.
+    float depth = max(texture(scene_depth, uv).r, 1.0e-7);
+    vec4 position = inverse_proj * vec4(uv * 2.0 - 1.0, depth, 1.0);
+    return abs(position.z / position.w);
+}
+
+void main() {
+#ifdef SUBSURFACE_SOURCE
+    out_color = texture(source, tex_uv);
+    if (out_color.a == 0.0) discard;
+#else
+    vec4 center = texture(scattered, tex_uv);
+    if (center.a == 0.0) discard;
+    float center_depth = view_depth(tex_uv);
+
+    // The kernel spans the scattering width around the center, scaled by its strength.
+    vec2 tap_step = direction * step_scale * center.a / center_depth;
+    vec3 color = vec3(0.0);
+    for (int i = 0; i < taps; i++) {
+        vec2 uv = tex_uv + kernel[i].a * tap_step;
+        vec4 tap = texture(scattered, uv);
+        // Taps off the surface, away in depth or on other materials, take the center light.
+        float off_surface = tap.a == 0.0 ? 1.0 : clamp(abs(view_depth(uv) - center_depth) / width, 0.0, 1.0);
+        color += kernel[i].rgb * mix(tap.rgb, center.rgb, off_surface);
+    }
+    out_color = vec4(color, center.a);
+#endif
+}
@@ -0,0 +1,56 @@
+#version 450
+
+// Composite of the frame captured at the beginning of a transition over the live frame, see
+// amethyst_rendy/src/pass/transition.rs.
+
+#include "header/dissolve.frag"
+
+layout(set = 0, binding = 0) uniform sampler2D captured;
+
+// Keep in sync with amethyst_rendy/src/pass/transition.rs
+layout(std140, set = 1, binding = 0) uniform TransitionArgs {
+    // Unit direction of the wipe, in texture coordinates.
+    vec2 direction;
+    // From 0 showing the capture to 1 showing the live frame.
+    float progress;
+    // 0 fades, 1 wipes, 2 dissolves.
+    int pattern;
+    vec3 edge_color;
+    float edge_width;
+    float cell_size;
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+float hash(vec2 cell) {
+    return fract(sin(dot(cell, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+float value_noise(vec2 position) {
+    vec2 cell = floor(position);
+    vec2 f = fract(position);
+    vec2 t = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(hash(cell), hash(cell + vec2(1.0, 0.0)), t.x),
+        mix(hash(cell + vec2(0.0, 1.0)), hash(cell + vec2(1.0, 1.0)), t.x),
+        t.y
+    );
+}
+
+void main() {
+    ivec2 pixel = min(ivec2(gl_FragCoord.xy), textureSize(captured, 0) - 1);
+    vec3 color = texelFetch(captured, pixel, 0).rgb;
+    float alpha = 1.0;
+    if (pattern == 0) {
+        alpha = 1.0 - progress;
+    } else if (pattern == 1) {
+        // Position along the wipe, from 0 where it starts to 1 where it ends.
+        float along = dot(tex_uv - 0.5, direction) / (abs(direction.x) + abs(direction.y)) + 0.5;
+        if (along < progress) discard;
+    } else {
+        float edge = dissolve(value_noise(gl_FragCoord.xy / cell_size), progress, edge_width);
+        color = mix(color, edge_color, edge);
+    }
+    out_color = vec4(color, alpha);
+}
@@ -0,0 +1,71 @@
+#version 450
+
+// Debug views of the scene, see amethyst_rendy/src/view_mode.rs. Compiled once per view mode,
+// with one of NORMALS, DEPTH, LIGHT_COMPLEXITY, OVERDRAW, SHADOW_CASCADES or WIREFRAME defined.
+
+#include "header/environment.frag"
+
+#ifdef SHADOW_CASCADES
+#include "header/shadow.frag"
+#endif
+
+layout(location = 0) in VertexData {
+    vec3 position;
+    vec3 normal;
+    vec2 tex_coord;
+    vec4 color;
+} vertex;
+
+layout(location = 0) out vec4 out_color;
+
+// Lights dimmer than this on a surface don't count as reaching it.
+const float LIGHT_THRESHOLD = 0.01;
+// Number of lights shown red.
+const float MAX_LIGHTS = 8.0;
+// Distance at which the depth view is darkened by half.
+const float DEPTH_HALF_DISTANCE = 15.0;
+// Color added by every fragment to the overdraw view.
+const vec3 OVERDRAW_STEP = vec3(0.1, 0.05, 0.02);
+
+vec3 heat(float t) {
+    return t < 0.5
+        ? mix(vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 0.0), t * 2.0)
+        : mix(vec3(0.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0), t * 2.0 - 1.0);
+}
+
+void main() {
+#if defined(NORMALS)
+    out_color = vec4(normalize(vertex.normal) * 0.5 + 0.5, 1.0);
+#elif defined(DEPTH)
+    float dist = distance(camera_position, vertex.position);
+    out_color = vec4(vec3(exp2(-dist / DEPTH_HALF_DISTANCE)), 1.0);
+#elif defined(LIGHT_COMPLEXITY)
+    float count = float(directional_light_count);
+    for (int i = 0; i < point_light_count; i++) {
+        vec3 dist = plight[i].position - vertex.position;
+        if (plight[i].intensity / dot(dist, dist) > LIGHT_THRESHOLD) count += 1.0;
+    }
+    for (int i = 0; i < spot_light_count; i++) {
+        vec3 dist = slight[i].position - vertex.position;
+        // The angle of spot lights is the cosine of their half aperture.
+        float cos_angle = dot(normalize(slight[i].direction), normalize(-dist));
+        if (length(dist) < slight[i].range && cos_angle > slight[i].angle) count += 1.0;
+    }
+    out_color = vec4(heat(clamp(count / MAX_LIGHTS, 0.0, 1.0)), 1.0);
+#elif defined(SHADOW_CASCADES)
+    // Green in the shadow map, darker in the shadows, and red out of it.
+    vec4 clip = shadow.proj_view * vec4(vertex.position, 1.0);
+    vec3 ndc = clip.xyz / clip.w;
+    bool covered = shadow.light >= 0 && all(lessThanEqual(abs(ndc.xy), vec2(1.0))) && ndc.z >= 0.0 && ndc.z <= 1.0;
+    if (covered) {
+        float lit = shadow_factor(vertex.position, normalize(vertex.normal), normalize(dlight[shadow.light].direction));
+        out_color = vec4(vec3(0.1, 1.0, 0.2) * mix(0.3, 1.0, lit), 1.0);
+    } else {
+        out_color = vec4(1.0, 0.15, 0.1, 1.0);
+    }
+#elif defined(OVERDRAW)
+    out_color = vec4(OVERDRAW_STEP, 1.0);
+#else
+    out_color = vec4(0.8, 0.9, 1.0, 1.0);
+#endif
+}
@@ -0,0 +1,92 @@
+#version 450
+
+#include "header/math.frag"
+
+// Keep in sync with amethyst_rendy/src/pass/volumetric.rs
+struct VolumetricLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    // Cosine of the cone angle, -1 for point lights.
+    float cos_angle;
+    vec3 direction;
+    float smoothness;
+};
+
+layout(set = 0, binding = 0) uniform sampler2D scene_depth;
+
+layout(std140, set = 1, binding = 0) uniform VolumetricArgs {
+    mat4 inverse_proj_view;
+    vec3 camera_position;
+    int steps;
+    float density;
+    float anisotropy;
+    float height_falloff;
+    float base_height;
+    float max_distance;
+    uint frame;
+    int light_count;
+    VolumetricLight lights[8];
+};
+
+layout(location = 0) in vec2 tex_uv;
+layout(location = 0) out vec4 out_color;
+
+float medium_density(vec3 position) {
+    return density * exp(-height_falloff * max(position.y - base_height, 0.0));
+}
+
+float henyey_greenstein(float cos_theta, float g) {
+    float g2 = g * g;
+    return (1.0 - g2) / (4.0 * PI * pow(max(1.0 + g2 - 2.0 * g * cos_theta, 1.0e-4), 1.5));
+}
+
+// Jitters the first step per pixel and frame, trading banding for noise the upsample smooths.
+float interleaved_gradient_noise(vec2 pixel) {
+    pixel += 5.588238 * float(frame % 64u);
+    return fract(52.9829189 * fract(dot(pixel, vec2(0.06711056, 0.00583715))));
+}
+
+vec3 light_radiance(VolumetricLight light, vec3 position, vec3 ray_direction) {
+    vec3 to_light = light.position - position;
+    float dist = length(to_light);
+    vec3 l = to_light / max(dist, 1.0e-4);
+
+    float falloff = pow(clamp(1.0 - pow(dist / max(light.range, 1.0e-4), 4.0), 0.0, 1.0), 2.0);
+    float attenuation = falloff / (dist * dist + 1.0);
+    if (light.cos_angle > -1.0) {
+        float cone = (dot(normalize(light.direction), -l) - light.cos_angle)
+            / max(1.0 - light.cos_angle, 1.0e-4);
+        attenuation *= pow(clamp(cone, 0.0, 1.0), 1.0 / max(light.smoothness, 1.0e-4));
+    }
+    return light.color * attenuation * henyey_greenstein(dot(l, ray_direction), anisotropy);
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(scene_depth, min(pixel, textureSize(scene_depth, 0) - 1), 0).r;
+
+    // Reversed Z with infinite far plane, empty pixels have zero depth.
+    vec4 end = inverse_proj_view * vec4(tex_uv * 2.0 - 1.0, max(depth, 1.0e-7), 1.0);
+    vec3 ray = end.xyz / end.w - camera_position;
+    float ray_length = min(length(ray), max_distance);
+    vec3 ray_direction = normalize(ray);
+
+    float step_length = ray_length / float(max(steps, 1));
+    float offset = interleaved_gradient_noise(gl_FragCoord.xy);
+
+    vec3 scattered = vec3(0.0);
+    float transmittance = 1.0;
+    for (int i = 0; i < steps; i++) {
+        vec3 position = camera_position + ray_direction * (float(i) + offset) * step_length;
+        float sigma = medium_density(position);
+        vec3 radiance = vec3(0.0);
+        for (int j = 0; j < light_count; j++) {
+            radiance += light_radiance(lights[j], position, ray_direction);
+        }
+        scattered += transmittance * sigma * step_length * radiance;
+        transmittance *= exp(-sigma * step_length);
+    }
+
+    out_color = vec4(scattered, 1.0);
+}
@@ -0,0 +1,36 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+// Ellipse placement.
+layout(location = 0) in vec3 center;
+layout(location = 1) in vec3 axis_x;
+layout(location = 2) in vec3 axis_z;
+layout(location = 3) in float opacity;
+
+layout(location = 0) out VertexData {
+    vec2 uv;
+    float opacity;
+} vertex;
+
+const vec2 positions[4] = vec2[](
+    vec2(1.0, -1.0),
+    vec2(-1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+// Raise the quad above the ground to keep it in front of the ground surface in the depth test.
+const float LIFT = 0.01;
+
+void main() {
+    vec2 corner = positions[gl_VertexIndex];
+
+    vertex.uv = corner;
+    vertex.opacity = opacity;
+    gl_Position = proj_view * vec4(center + corner.x * axis_x + corner.y * axis_z + vec3(0.0, LIFT, 0.0), 1.0);
+}
@@ -0,0 +1,9 @@
+#version 450
+
+// Single triangle covering the whole framebuffer, drawn with 3 vertices and no vertex input.
+layout(location = 0) out vec2 tex_uv;
+
+void main() {
+    tex_uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(tex_uv * 2.0 - 1.0, 0.0, 1.0);
+}
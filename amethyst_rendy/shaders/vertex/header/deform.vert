@@ -0,0 +1,54 @@
+// Vertex deformations of the 3D vertex shaders.
+//
+// Every 3D vertex shader is compiled once per permutation, with SKINNING and/or MORPHING
+// defined for deformed meshes. Morph targets are applied first, in the bind pose of the
+// mesh, and the morphed vertex is then skinned.
+
+#if defined(SKINNING) || defined(MORPHING)
+    #define DEFORMED
+#endif
+
+#ifdef SKINNING
+layout(std430, set = 2, binding = 0) readonly buffer JointTransforms {
+    mat4 joints[];
+};
+
+mat4 joint_transform(uvec4 ids, vec4 weights, uint offset) {
+    return weights.x * joints[int(offset + ids.x)] +
+        weights.y * joints[int(offset + ids.y)] +
+        weights.z * joints[int(offset + ids.z)] +
+        weights.w * joints[int(offset + ids.w)];
+}
+#endif
+
+#ifdef MORPHING
+struct MorphDelta {
+    vec4 position;
+    vec4 normal;
+    vec4 tangent;
+};
+
+layout(std430, set = 3, binding = 0) readonly buffer MorphDeltas {
+    MorphDelta deltas[];
+};
+
+layout(std430, set = 3, binding = 1) readonly buffer MorphWeights {
+    float weights[];
+};
+
+// Weighted sum of the deltas of the current vertex. The offsets are the index of the first
+// weight, the index of the first delta and the number of targets. Deltas are stored per
+// vertex, with the deltas of all targets of a vertex next to each other.
+MorphDelta morph_delta(uvec3 offsets) {
+    MorphDelta sum = MorphDelta(vec4(0.0), vec4(0.0), vec4(0.0));
+    uint first = offsets.y + uint(gl_VertexIndex) * offsets.z;
+    for (uint i = 0; i < offsets.z; i++) {
+        float weight = weights[offsets.x + i];
+        MorphDelta delta = deltas[first + i];
+        sum.position += weight * delta.position;
+        sum.normal += weight * delta.normal;
+        sum.tangent += weight * delta.tangent;
+    }
+    return sum;
+}
+#endif
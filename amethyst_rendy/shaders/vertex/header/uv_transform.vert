@@ -0,0 +1,27 @@
+// UV transforms of the 3D vertex shaders.
+//
+// Every 3D vertex shader is also compiled with UV_TRANSFORM defined, transforming the texture
+// coordinates by the UV transforms of the material. Without it the coordinates are passed
+// through untouched, and the material isn't read.
+
+#ifdef UV_TRANSFORM
+struct UvOffset {
+    vec2 u_offset;
+    vec2 v_offset;
+};
+
+layout(std140, set = 1, binding = 0) uniform Material {
+    UvOffset uv_offset;
+    float alpha_cutoff;
+    vec3 emission_factor;
+    vec3 uv_transform_u;
+    vec3 uv_transform_v;
+    vec3 normal_uv_transform_u;
+    vec3 normal_uv_transform_v;
+};
+
+vec2 uv_transform(vec2 coord, vec3 row_u, vec3 row_v) {
+    vec3 affine = vec3(coord, 1.0);
+    return vec2(dot(row_u, affine), dot(row_v, affine));
+}
+#endif
@@ -0,0 +1,35 @@
+#version 450
+
+// Hi-Z level selected for this batch of instances.
+layout(set = 0, binding = 0) uniform sampler2D level;
+
+// Screen bounds of the object, in normalized device coordinates.
+layout(location = 0) in vec4 rect; // instance rate
+layout(location = 1) in float nearest; // instance rate
+
+layout(location = 0) out vec4 color;
+
+const vec2 positions[4] = vec2[](
+    vec2(1.0, 0.0), // Right top
+    vec2(0.0, 0.0), // Left top
+    vec2(1.0, 1.0), // Right bottom
+    vec2(0.0, 1.0) // Left bottom
+);
+
+void main() {
+    ivec2 size = textureSize(level, 0);
+    ivec2 lo = clamp(ivec2((rect.xy * 0.5 + 0.5) * vec2(size)), ivec2(0), size - 1);
+    ivec2 hi = clamp(ivec2((rect.zw * 0.5 + 0.5) * vec2(size)), ivec2(0), size - 1);
+
+    float farthest = min(
+        min(texelFetch(level, lo, 0).r, texelFetch(level, ivec2(hi.x, lo.y), 0).r),
+        min(texelFetch(level, ivec2(lo.x, hi.y), 0).r, texelFetch(level, hi, 0).r)
+    );
+
+    // Reversed Z, the object is hidden when its nearest point is farther than every occluder.
+    bool occluded = nearest < farthest;
+    color = occluded ? vec4(1.0, 0.0, 0.0, 0.35) : vec4(0.0, 1.0, 0.0, 0.2);
+
+    vec2 corner = positions[gl_VertexIndex];
+    gl_Position = vec4(mix(rect.xy, rect.zw, corner), 0.0, 1.0);
+}
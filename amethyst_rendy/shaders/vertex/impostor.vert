@@ -0,0 +1,45 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+// Quad placement.
+layout(location = 0) in vec3 center;
+layout(location = 1) in vec3 right;
+layout(location = 2) in vec3 up;
+layout(location = 3) in vec2 u_offset;
+layout(location = 4) in vec2 u_offset_next;
+layout(location = 5) in float blend;
+layout(location = 6) in float alpha_cutoff;
+layout(location = 7) in vec4 tint;
+
+layout(location = 0) out VertexData {
+    vec2 tex_uv;
+    vec2 tex_uv_next;
+    float blend;
+    vec4 color;
+    float alpha_cutoff;
+} vertex;
+
+const vec2 positions[4] = vec2[](
+    vec2(1.0, -1.0), // Right bottom
+    vec2(-1.0, -1.0), // Left bottom
+    vec2(1.0, 1.0), // Right top
+    vec2(-1.0, 1.0) // Left top
+);
+
+void main() {
+    vec2 corner = positions[gl_VertexIndex];
+
+    float u = corner.x * 0.5 + 0.5;
+    float v = 0.5 - corner.y * 0.5;
+    vertex.tex_uv = vec2(mix(u_offset.x, u_offset.y, u), v);
+    vertex.tex_uv_next = vec2(mix(u_offset_next.x, u_offset_next.y, u), v);
+    vertex.blend = blend;
+    vertex.color = tint;
+    vertex.alpha_cutoff = alpha_cutoff;
+    gl_Position = proj_view * vec4(center + corner.x * right + corner.y * up, 1.0);
+}
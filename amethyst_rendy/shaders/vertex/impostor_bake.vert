@@ -0,0 +1,25 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 tex_coord;
+// Object space to the clip space of the slot of a view in the atlas.
+layout(location = 3) in mat4 view_transform; // instance rate
+// xyz: direction towards the key light in object space, w: ambient fraction.
+layout(location = 7) in vec4 light; // instance rate
+
+layout(location = 0) out VertexData {
+    vec3 normal;
+    vec3 to_camera;
+    vec2 tex_coord;
+    vec4 light;
+} vertex;
+
+void main() {
+    vertex.normal = normal;
+    // The depth row of the transform points towards the camera.
+    vertex.to_camera = vec3(view_transform[0][2], view_transform[1][2], view_transform[2][2]);
+    vertex.tex_coord = tex_coord;
+    vertex.light = light;
+    gl_Position = view_transform * vec4(position, 1.0);
+}
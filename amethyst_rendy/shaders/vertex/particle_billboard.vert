@@ -0,0 +1,52 @@
+#version 450
+
+layout(std140, set = 0, binding = 0) uniform ViewArgs {
+    uniform mat4 proj;
+    uniform mat4 view;
+    uniform mat4 proj_view;
+};
+
+// Position and age, velocity and lifetime, packed colors and sizes.
+layout(set = 1, binding = 0) uniform sampler2D state_position;
+layout(set = 1, binding = 1) uniform sampler2D state_velocity;
+layout(set = 1, binding = 2) uniform usampler2D state_appearance;
+
+layout(location = 0) out VertexData {
+    vec2 uv;
+    vec4 color;
+} vertex;
+
+const vec2 positions[4] = vec2[](
+    vec2(1.0, -1.0),
+    vec2(-1.0, -1.0),
+    vec2(1.0, 1.0),
+    vec2(-1.0, 1.0)
+);
+
+void main() {
+    int width = textureSize(state_position, 0).x;
+    ivec2 texel = ivec2(gl_InstanceIndex % width, gl_InstanceIndex / width);
+    vec4 position = texelFetch(state_position, texel, 0);
+    vec4 velocity = texelFetch(state_velocity, texel, 0);
+
+    // Collapse dead particles outside of the clip volume.
+    if (position.w >= velocity.w) {
+        vertex.uv = vec2(0.0);
+        vertex.color = vec4(0.0);
+        gl_Position = vec4(0.0, 0.0, -2.0, 1.0);
+        return;
+    }
+
+    uvec4 appearance = texelFetch(state_appearance, texel, 0);
+    float t = position.w / velocity.w;
+    vec2 corner = positions[gl_VertexIndex];
+    float radius = 0.5 * mix(uintBitsToFloat(appearance.z), uintBitsToFloat(appearance.w), t);
+
+    // Face the camera with the right and up axes of the view.
+    vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+    vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+
+    vertex.uv = corner;
+    vertex.color = mix(unpackUnorm4x8(appearance.x), unpackUnorm4x8(appearance.y), t);
+    gl_Position = proj_view * vec4(position.xyz + (corner.x * right + corner.y * up) * radius, 1.0);
+}
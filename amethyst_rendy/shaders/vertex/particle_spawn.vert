@@ -0,0 +1,60 @@
+#version 450
+
+// Keep in sync with amethyst_rendy/src/pass/gpu_particles.rs
+layout(std140, set = 1, binding = 0) uniform SimulationArgs {
+    uvec2 state_size;
+};
+
+// Particle emitted this frame.
+layout(location = 0) in uint slot;
+layout(location = 1) in vec3 position;
+layout(location = 2) in vec3 velocity;
+layout(location = 3) in float spread;
+layout(location = 4) in float lifetime;
+layout(location = 5) in uint seed;
+layout(location = 6) in vec4 start_color;
+layout(location = 7) in vec4 end_color;
+layout(location = 8) in vec2 size;
+
+layout(location = 0) flat out vec4 state_position;
+layout(location = 1) flat out vec4 state_velocity;
+layout(location = 2) flat out uvec4 state_appearance;
+
+const float PI = 3.14159265359;
+
+uint hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352du;
+    x ^= x >> 15;
+    x *= 0x846ca68bu;
+    x ^= x >> 16;
+    return x;
+}
+
+float random(inout uint state) {
+    state = hash(state);
+    return float(state) / 4294967295.0;
+}
+
+void main() {
+    // Pick the direction uniformly in the cone of the spread around the velocity.
+    uint state = seed;
+    float cos_theta = mix(1.0, cos(spread), random(state));
+    float sin_theta = sqrt(max(1.0 - cos_theta * cos_theta, 0.0));
+    float phi = 2.0 * PI * random(state);
+
+    float speed = length(velocity);
+    vec3 direction = speed > 0.0 ? velocity / speed : vec3(0.0, 1.0, 0.0);
+    vec3 tangent = normalize(abs(direction.y) < 0.99 ? cross(direction, vec3(0.0, 1.0, 0.0)) : cross(direction, vec3(1.0, 0.0, 0.0)));
+    vec3 bitangent = cross(direction, tangent);
+    vec3 emitted = (direction * cos_theta + (tangent * cos(phi) + bitangent * sin(phi)) * sin_theta) * speed;
+
+    state_position = vec4(position, 0.0);
+    state_velocity = vec4(emitted, lifetime);
+    state_appearance = uvec4(packUnorm4x8(start_color), packUnorm4x8(end_color), floatBitsToUint(size.x), floatBitsToUint(size.y));
+
+    // Write the texel of the slot.
+    vec2 texel = vec2(slot % state_size.x, slot / state_size.x) + 0.5;
+    gl_Position = vec4(texel / vec2(state_size) * 2.0 - 1.0, 0.0, 1.0);
+    gl_PointSize = 1.0;
+}
@@ -1,5 +1,8 @@
 #version 450
 
+#include "header/deform.vert"
+#include "header/uv_transform.vert"
+
 layout(std140, set = 0, binding = 0) uniform Projview {
     mat4 proj;
     mat4 view;
@@ -10,25 +13,60 @@ layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
 layout(location = 2) in vec4 tangent;
 layout(location = 3) in vec2 tex_coord;
-layout(location = 4) in mat4 model; // instance rate
-layout(location = 8) in vec4 tint; // instance rate
+#ifdef SKINNING
+layout(location = 4) in uvec4 joint_ids;
+layout(location = 5) in vec4 joint_weights;
+    #define INSTANCE_LOC 6
+#else
+    #define INSTANCE_LOC 4
+#endif
+layout(location = INSTANCE_LOC) in mat4 model; // instance rate
+layout(location = INSTANCE_LOC + 4) in vec4 tint; // instance rate
+layout(location = INSTANCE_LOC + 5) in vec4 instance_data; // instance rate
+#ifdef DEFORMED
+layout(location = INSTANCE_LOC + 6) in uint joints_offset; // instance rate
+layout(location = INSTANCE_LOC + 7) in uvec3 morph_offsets; // instance rate
+#endif
 
 layout(location = 0) out VertexData {
     vec3 position;
     vec3 normal;
     vec3 tangent;
     float tang_handedness;
-    vec2 tex_coord;
+    vec4 tex_coord; // xy: every map but the normal map, zw: normal map
     vec4 color;
 } vertex;
 
 void main() {
-    vec4 vertex_position = model * vec4(position, 1.0);
+    vec3 local_position = position;
+    vec3 local_normal = normal;
+    vec3 local_tangent = tangent.xyz;
+#ifdef MORPHING
+    MorphDelta delta = morph_delta(morph_offsets);
+    local_position += delta.position.xyz;
+    local_normal += delta.normal.xyz;
+    local_tangent += delta.tangent.xyz;
+#endif
+#ifdef SKINNING
+    mat4 transform = model * joint_transform(joint_ids, joint_weights, joints_offset);
+#else
+    mat4 transform = model;
+#endif
+
+    vec4 vertex_position = transform * vec4(local_position, 1.0);
+    mat3 mat3_transform = mat3(transform);
     vertex.position = vertex_position.xyz;
-    vertex.normal = mat3(model) * normal;
-    vertex.tangent = mat3(model) * tangent.xyz;
+    vertex.normal = mat3_transform * local_normal;
+    vertex.tangent = mat3_transform * local_tangent;
     vertex.tang_handedness = tangent.w;
-    vertex.tex_coord = tex_coord;
+#ifdef UV_TRANSFORM
+    vertex.tex_coord = vec4(
+        uv_transform(tex_coord, uv_transform_u, uv_transform_v),
+        uv_transform(tex_coord, normal_uv_transform_u, normal_uv_transform_v)
+    );
+#else
+    vertex.tex_coord = tex_coord.xyxy;
+#endif
     vertex.color = tint;
     gl_Position = proj_view * vertex_position;
 }
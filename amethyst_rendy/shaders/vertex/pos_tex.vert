@@ -1,5 +1,8 @@
 #version 450
 
+#include "header/deform.vert"
+#include "header/uv_transform.vert"
+
 layout(std140, set = 0, binding = 0) uniform Projview {
     mat4 proj;
     mat4 view;
@@ -8,8 +11,20 @@ layout(std140, set = 0, binding = 0) uniform Projview {
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec2 tex_coord;
-layout(location = 2) in mat4 model; // instance rate
-layout(location = 6) in vec4 tint; // instance rate
+#ifdef SKINNING
+layout(location = 2) in uvec4 joint_ids;
+layout(location = 3) in vec4 joint_weights;
+    #define INSTANCE_LOC 4
+#else
+    #define INSTANCE_LOC 2
+#endif
+layout(location = INSTANCE_LOC) in mat4 model; // instance rate
+layout(location = INSTANCE_LOC + 4) in vec4 tint; // instance rate
+layout(location = INSTANCE_LOC + 5) in vec4 instance_data; // instance rate
+#ifdef DEFORMED
+layout(location = INSTANCE_LOC + 6) in uint joints_offset; // instance rate
+layout(location = INSTANCE_LOC + 7) in uvec3 morph_offsets; // instance rate
+#endif
 
 layout(location = 0) out VertexData {
     vec3 position;
@@ -18,9 +33,23 @@ layout(location = 0) out VertexData {
 } vertex;
 
 void main() {
-    vec4 vertex_position = model * vec4(position, 1.0);
+    vec3 local_position = position;
+#ifdef MORPHING
+    local_position += morph_delta(morph_offsets).position.xyz;
+#endif
+#ifdef SKINNING
+    mat4 transform = model * joint_transform(joint_ids, joint_weights, joints_offset);
+#else
+    mat4 transform = model;
+#endif
+
+    vec4 vertex_position = transform * vec4(local_position, 1.0);
     vertex.position = vertex_position.xyz;
+#ifdef UV_TRANSFORM
+    vertex.tex_coord = uv_transform(tex_coord, uv_transform_u, uv_transform_v);
+#else
     vertex.tex_coord = tex_coord;
+#endif
     vertex.color = tint;
     gl_Position = proj_view * vertex_position;
 }
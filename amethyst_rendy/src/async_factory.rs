@@ -0,0 +1,197 @@
+//! Queueing of mesh and texture creation requested from outside of the main thread.
+//!
+//! The rendy `Factory` lives in the `World` and can only be accessed by systems. `AsyncFactory`
+//! is a cheap, cloneable handle that can be moved to any thread, e.g. to a level streaming
+//! thread, and used to request GPU resources from there. It does not create anything itself:
+//! requests are only queued, and handed over to the regular asset processors by the
+//! `AsyncFactorySystem` at the start of the next frame. GPU resources are therefore still
+//! created on the main thread, on every backend.
+//!
+//! The mesh and texture formats are not tied to it. They already decode their data on the asset
+//! thread pool, and decoded `MeshData` or `TextureData` can be passed to either the `Loader`
+//! or an `AsyncFactory`.
+//!
+//! The mesh or texture is uploaded by the time its storage reports it as loaded, so it is safe
+//! to draw as soon as `Pending::is_loaded` returns `true`.
+
+use crate::types::{Mesh, MeshData, Texture, TextureData};
+use amethyst_assets::{Asset, AssetStorage, Handle, Loader};
+use amethyst_core::ecs::{Read, ReadExpect, System};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+#[derive(Debug)]
+enum Request {
+    Mesh(Box<MeshData>, Pending<Mesh>),
+    Texture(TextureData, Pending<Texture>),
+}
+
+/// A resource that is queued for creation by an `AsyncFactory`.
+///
+/// The asset handle becomes available once the `AsyncFactorySystem` picked up the request.
+#[derive(Debug)]
+pub struct Pending<A> {
+    slot: Arc<Mutex<Option<Handle<A>>>>,
+}
+
+impl<A> Clone for Pending<A> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<A: Asset> Pending<A> {
+    fn new() -> Self {
+        Self {
+            slot: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    fn resolve(&self, handle: Handle<A>) {
+        *self.slot.lock().unwrap() = Some(handle);
+    }
+
+    /// Get the asset handle, if the request was already handed over to the asset storage.
+    pub fn handle(&self) -> Option<Handle<A>> {
+        self.slot.lock().unwrap().clone()
+    }
+
+    /// Check if the resource is fully created and ready to be used for drawing.
+    pub fn is_loaded(&self, storage: &AssetStorage<A>) -> bool {
+        self.handle().is_some_and(|h| storage.contains(&h))
+    }
+}
+
+/// Thread-safe queue of mesh and texture creation requests, drained by the `AsyncFactorySystem`.
+///
+/// Fetch it from the `World` and clone it into any thread that needs to create resources.
+#[derive(Debug, Clone, Default)]
+pub struct AsyncFactory {
+    queue: Arc<Mutex<Vec<Request>>>,
+}
+
+impl AsyncFactory {
+    /// Queue creation of a mesh.
+    pub fn create_mesh(&self, data: impl Into<MeshData>) -> Pending<Mesh> {
+        let pending = Pending::new();
+        self.push(Request::Mesh(Box::new(data.into()), pending.clone()));
+        pending
+    }
+
+    /// Queue creation of a texture.
+    pub fn create_texture(&self, data: impl Into<TextureData>) -> Pending<Texture> {
+        let pending = Pending::new();
+        self.push(Request::Texture(data.into(), pending.clone()));
+        pending
+    }
+
+    /// Number of requests waiting to be picked up by the `AsyncFactorySystem`.
+    pub fn queued(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    fn push(&self, request: Request) {
+        self.queue.lock().unwrap().push(request);
+    }
+
+    fn drain(&self) -> Vec<Request> {
+        std::mem::take(&mut *self.queue.lock().unwrap())
+    }
+}
+
+/// Hands requests queued through `AsyncFactory` over to the mesh and texture processors.
+#[derive(Debug, Default)]
+pub struct AsyncFactorySystem;
+
+impl<'a> System<'a> for AsyncFactorySystem {
+    type SystemData = (
+        Read<'a, AsyncFactory>,
+        ReadExpect<'a, Loader>,
+        Read<'a, AssetStorage<Mesh>>,
+        Read<'a, AssetStorage<Texture>>,
+    );
+
+    fn run(&mut self, (factory, loader, meshes, textures): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("async_factory");
+
+        for request in factory.drain() {
+            match request {
+                Request::Mesh(data, pending) => {
+                    pending.resolve(loader.load_from_data(*data, (), &meshes));
+                }
+                Request::Texture(data, pending) => {
+                    pending.resolve(loader.load_from_data(data, (), &textures));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendy::{mesh::MeshBuilder, texture::palette::load_from_srgb};
+    use amethyst_core::ecs::{RunNow, World, WorldExt};
+    use palette::Srgb;
+    use rayon::ThreadPoolBuilder;
+
+    #[test]
+    fn requests_from_other_threads_are_resolved() {
+        let mut world = World::new();
+        world.insert(Loader::new(
+            ".",
+            Arc::new(ThreadPoolBuilder::new().build().unwrap()),
+        ));
+        world.insert(AssetStorage::<Mesh>::default());
+        world.insert(AssetStorage::<Texture>::default());
+        world.insert(AsyncFactory::default());
+
+        let factory = AsyncFactory::clone(&world.read_resource());
+        let pending = std::thread::spawn(move || {
+            factory.create_texture(load_from_srgb(Srgb::new(1.0, 0.0, 0.0)))
+        })
+        .join()
+        .unwrap();
+
+        assert!(pending.handle().is_none());
+        assert_eq!(world.read_resource::<AsyncFactory>().queued(), 1);
+
+        AsyncFactorySystem.run_now(&world);
+
+        assert!(pending.handle().is_some());
+        assert_eq!(world.read_resource::<AsyncFactory>().queued(), 0);
+        // Not processed by the `TextureProcessorSystem` yet.
+        let textures = world.read_resource::<AssetStorage<Texture>>();
+        assert!(!pending.is_loaded(&textures));
+    }
+
+    #[test]
+    fn pending_handles_wait_for_the_next_system_run() {
+        let mut world = World::new();
+        world.insert(Loader::new(
+            ".",
+            Arc::new(ThreadPoolBuilder::new().build().unwrap()),
+        ));
+        world.insert(AssetStorage::<Mesh>::default());
+        world.insert(AssetStorage::<Texture>::default());
+        world.insert(AsyncFactory::default());
+        let factory = AsyncFactory::clone(&world.read_resource());
+
+        let first = factory.create_mesh(MeshBuilder::new());
+        assert!(first.handle().is_none());
+
+        AsyncFactorySystem.run_now(&world);
+        let second = factory.create_mesh(MeshBuilder::new());
+        assert!(first.handle().is_some());
+        assert!(second.handle().is_none());
+
+        AsyncFactorySystem.run_now(&world);
+        assert!(second.handle().is_some());
+        assert_ne!(first.handle().unwrap().id(), second.handle().unwrap().id());
+    }
+}
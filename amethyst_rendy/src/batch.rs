@@ -4,7 +4,7 @@ use crate::util::TapCountIter;
 use derivative::Derivative;
 use smallvec::{smallvec, SmallVec};
 use std::{
-    collections::hash_map::Entry,
+    collections::{hash_map::Entry, BTreeMap},
     iter::{Extend, FromIterator},
     ops::Range,
 };
@@ -390,6 +390,167 @@ where
     }
 }
 
+/// A batching implementation retained between frames, with one level of indexing. Data type `D`
+/// of entries identified by an `u64` id, batched by primary key `PK`.
+///
+/// Every frame starts with `begin`, updates every entry still drawn, and ends with `finish`.
+/// Entries whose key and data didn't change aren't touched, entries whose key changed are moved
+/// to their new batch, and entries not updated during the frame are removed. Batches are kept
+/// sorted by key, items within a batch are in no particular order.
+///
+/// In debug builds, `finish` checks the batches against ones built from scratch.
+#[derive(Derivative, Debug)]
+#[derivative(Default(bound = ""))]
+pub struct RetainedBatch<PK, D>
+where
+    PK: Ord,
+{
+    batches: BTreeMap<PK, RetainedData<D>>,
+    entries: fnv::FnvHashMap<u64, RetainedEntry<PK>>,
+    frame: u64,
+    touched: usize,
+    data_count: usize,
+    scratch: Vec<(PK, u64, D)>,
+}
+
+#[derive(Derivative, Debug)]
+#[derivative(Default(bound = ""))]
+struct RetainedData<D> {
+    ids: Vec<u64>,
+    data: Vec<D>,
+}
+
+#[derive(Debug)]
+struct RetainedEntry<PK> {
+    key: PK,
+    index: usize,
+    frame: u64,
+}
+
+impl<PK, D> RetainedBatch<PK, D>
+where
+    PK: Ord + Clone + std::fmt::Debug,
+    D: PartialEq + Clone + std::fmt::Debug,
+{
+    /// Removes all entries, so that they are all inserted again by the next frame.
+    pub fn clear(&mut self) {
+        self.batches.clear();
+        self.entries.clear();
+        self.data_count = 0;
+    }
+
+    /// Starts a frame of updates.
+    pub fn begin(&mut self) {
+        self.frame += 1;
+        self.touched = 0;
+        self.scratch.clear();
+    }
+
+    /// Updates the key and data of an entry, inserting it if it's new. Every entry is updated at
+    /// most once per frame.
+    pub fn update(&mut self, id: u64, pk: PK, data: D) {
+        if cfg!(debug_assertions) {
+            self.scratch.push((pk.clone(), id, data.clone()));
+        }
+
+        let frame = self.frame;
+        if let Some(entry) = self.entries.get_mut(&id) {
+            entry.frame = frame;
+            if entry.key == pk {
+                let old = &mut self.batches.get_mut(&pk).unwrap().data[entry.index];
+                if *old != data {
+                    *old = data;
+                    self.touched += 1;
+                }
+                return;
+            }
+        }
+
+        self.touched += 1;
+        self.remove(id);
+        let batch = self.batches.entry(pk.clone()).or_default();
+        self.entries.insert(
+            id,
+            RetainedEntry {
+                key: pk,
+                index: batch.ids.len(),
+                frame,
+            },
+        );
+        batch.ids.push(id);
+        batch.data.push(data);
+        self.data_count += 1;
+    }
+
+    /// Ends a frame of updates, removing the entries that weren't updated.
+    pub fn finish(&mut self) {
+        let frame = self.frame;
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.frame != frame)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in stale {
+            self.touched += 1;
+            self.remove(id);
+        }
+        self.batches.retain(|_, b| !b.ids.is_empty());
+
+        if cfg!(debug_assertions) {
+            self.check();
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        if let Some(entry) = self.entries.remove(&id) {
+            let batch = self.batches.get_mut(&entry.key).unwrap();
+            batch.ids.swap_remove(entry.index);
+            batch.data.swap_remove(entry.index);
+            if let Some(&moved) = batch.ids.get(entry.index) {
+                self.entries.get_mut(&moved).unwrap().index = entry.index;
+            }
+            self.data_count -= 1;
+        }
+    }
+
+    /// Panics if the batches differ from the ones built by sorting the entries of this frame.
+    fn check(&self) {
+        let mut scratch = self.scratch.clone();
+        scratch.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+        let retained = self
+            .batches
+            .iter()
+            .flat_map(|(pk, b)| {
+                let mut items = b.ids.iter().zip(&b.data).collect::<Vec<_>>();
+                items.sort_by_key(|(id, _)| **id);
+                items
+                    .into_iter()
+                    .map(move |(&id, data)| (pk.clone(), id, data.clone()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            retained, scratch,
+            "Retained batches are inconsistent with the updated entries"
+        );
+    }
+
+    /// Returns an iterator over batched data lists, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&PK, &[D])> {
+        self.batches.iter().map(|(pk, b)| (pk, &b.data[..]))
+    }
+
+    /// Returns the number of entries added, removed, moved or updated during the last frame.
+    pub fn touched(&self) -> usize {
+        self.touched
+    }
+
+    /// Returns the number of items currently in this batch.
+    pub fn count(&self) -> usize {
+        self.data_count
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +583,44 @@ mod tests {
         assert_eq!(batch.count(), 0);
         assert_eq!(batch.iter().collect::<Vec<_>>(), vec![]);
     }
+
+    #[test]
+    fn retained_batch_touches_only_changed_entries() {
+        let mut batch = RetainedBatch::<u32, u32>::default();
+        batch.begin();
+        for id in 0..4 {
+            batch.update(id, (id % 2) as u32, 0);
+        }
+        batch.finish();
+        assert_eq!(batch.touched(), 4);
+        assert_eq!(batch.count(), 4);
+
+        // Entry 1 moves to batch 0, entry 2 changes, entry 3 is removed.
+        batch.begin();
+        batch.update(0, 0, 0);
+        batch.update(1, 0, 0);
+        batch.update(2, 0, 1);
+        batch.finish();
+        assert_eq!(batch.touched(), 3);
+        assert_eq!(batch.count(), 3);
+        let batches = batch.iter().collect::<Vec<_>>();
+        assert_eq!(batches.len(), 1);
+        let mut data = batches[0].1.to_vec();
+        data.sort();
+        assert_eq!((batches[0].0, data), (&0, vec![0, 0, 1]));
+
+        batch.begin();
+        batch.update(2, 0, 1);
+        batch.update(1, 0, 0);
+        batch.update(0, 0, 0);
+        batch.finish();
+        assert_eq!(batch.touched(), 0);
+
+        batch.clear();
+        batch.begin();
+        batch.update(0, 1, 0);
+        batch.finish();
+        assert_eq!(batch.touched(), 1);
+        assert_eq!(batch.iter().collect::<Vec<_>>(), vec![(&1, &[0][..])]);
+    }
 }
@@ -0,0 +1,86 @@
+//! Blob shadows, a cheap fallback for shadow maps.
+//!
+//! Entities with a [BlobShadow] and a `Transform` project a soft dark ellipse straight down
+//! onto the ground, a horizontal plane at the height of the [BlobShadowGround] resource. The
+//! ellipse widens and fades out as the entity rises above the ground, and disappears past the
+//! `fade_height` of the shadow. Use `RenderBlobShadows` plugin to draw them, multiplied over the
+//! opaque scene before the transparent objects.
+//!
+//! The ground is flat, so blob shadows don't follow slopes or steps. They aren't drawn while a
+//! directional light casts shadows into the shadow map of `RenderShadows`, see [crate::shadow].
+
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+use serde::{Deserialize, Serialize};
+
+/// Cast a blob shadow on the ground below the entity, see the [module documentation](self).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlobShadow {
+    /// Radii of the ellipse along the local X and Z axes of the entity, in world units, when
+    /// the entity touches the ground.
+    pub radius: [f32; 2],
+    /// Darkening of the center of the ellipse when the entity touches the ground, in [0, 1].
+    pub opacity: f32,
+    /// Height of the entity origin above the ground at which the shadow has faded out, in
+    /// world units. The ellipse is twice as large there.
+    pub fade_height: f32,
+}
+
+impl Default for BlobShadow {
+    fn default() -> Self {
+        Self {
+            radius: [0.5, 0.5],
+            opacity: 0.6,
+            fade_height: 2.0,
+        }
+    }
+}
+
+impl BlobShadow {
+    /// Round blob shadow of given radius.
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius: [radius, radius],
+            ..Default::default()
+        }
+    }
+
+    /// Radii and opacity of the shadow of an entity at given height above the ground, or
+    /// `None` once it has faded out or when the entity is below the ground.
+    pub fn at_height(&self, height: f32) -> Option<([f32; 2], f32)> {
+        if height < 0.0 || height >= self.fade_height {
+            return None;
+        }
+        let t = height / self.fade_height;
+        Some((
+            [self.radius[0] * (1.0 + t), self.radius[1] * (1.0 + t)],
+            self.opacity * (1.0 - t),
+        ))
+    }
+}
+
+impl Component for BlobShadow {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Height of the horizontal ground plane receiving the blob shadows, in world units.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlobShadowGround(pub f32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_shadow_grows_and_fades_with_height() {
+        let shadow = BlobShadow {
+            radius: [1.0, 0.5],
+            opacity: 0.8,
+            fade_height: 2.0,
+        };
+        assert_eq!(shadow.at_height(0.0), Some(([1.0, 0.5], 0.8)));
+        assert_eq!(shadow.at_height(1.0), Some(([1.5, 0.75], 0.4)));
+        assert_eq!(shadow.at_height(2.0), None);
+        assert_eq!(shadow.at_height(-0.1), None);
+    }
+}
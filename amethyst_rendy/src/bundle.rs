@@ -1,16 +1,23 @@
 //! A home of [RenderingBundle] with it's rendering plugins system and all types directly related to it.
 
 use crate::{
+    async_factory::AsyncFactorySystem,
+    frame_graph::{action_name, FrameGraph, FrameGraphRead, FrameGraphTarget},
+    material_animation::MaterialAnimationSystem,
+    memory::{image_bytes, GpuMemoryStatsSystem},
+    msaa::MultisampledPassNodeBuilder,
     mtl::Material,
     rendy::{
         factory::Factory,
         graph::{
             render::{RenderGroupBuilder, RenderPassNodeBuilder, SubpassBuilder},
-            GraphBuilder, ImageId, NodeId,
+            GraphBuilder, ImageId, NodeBuilder, NodeId,
         },
         hal,
         wsi::Surface,
     },
+    scene::SceneStatsSystem,
+    stats::RenderStats,
     system::{GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem},
     types::Backend,
     SpriteSheet,
@@ -35,6 +42,7 @@ use std::collections::HashMap;
 #[derive(Debug)]
 pub struct RenderingBundle<B: Backend> {
     plugins: Vec<Box<dyn RenderPlugin<B>>>,
+    working_space: WorkingSpace,
 }
 
 impl<B: Backend> RenderingBundle<B> {
@@ -43,9 +51,16 @@ impl<B: Backend> RenderingBundle<B> {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            working_space: Default::default(),
         }
     }
 
+    /// Select the color space scene passes render and blend in, see [WorkingSpace].
+    pub fn with_working_space(mut self, working_space: WorkingSpace) -> Self {
+        self.working_space = working_space;
+        self
+    }
+
     /// Register a [`RenderPlugin`].
     ///
     /// If you want the non-consuming version of this method, see [`add_plugin`].
@@ -62,6 +77,7 @@ impl<B: Backend> RenderingBundle<B> {
     fn into_graph_creator(self) -> PluggableRenderGraphCreator<B> {
         PluggableRenderGraphCreator {
             plugins: self.plugins,
+            working_space: self.working_space,
         }
     }
 }
@@ -72,11 +88,17 @@ impl<'a, 'b, B: Backend> SystemBundle<'a, 'b> for RenderingBundle<B> {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
-        builder.add(MeshProcessorSystem::<B>::default(), "mesh_processor", &[]);
+        world.insert(FrameGraph::default());
+        builder.add(AsyncFactorySystem, "async_factory", &[]);
+        builder.add(
+            MeshProcessorSystem::<B>::default(),
+            "mesh_processor",
+            &["async_factory"],
+        );
         builder.add(
             TextureProcessorSystem::<B>::default(),
             "texture_processor",
-            &[],
+            &["async_factory"],
         );
         builder.add(Processor::<Material>::new(), "material_processor", &[]);
         builder.add(
@@ -84,9 +106,16 @@ impl<'a, 'b, B: Backend> SystemBundle<'a, 'b> for RenderingBundle<B> {
             "sprite_sheet_processor",
             &[],
         );
+        builder.add(
+            GpuMemoryStatsSystem::<B>::default(),
+            "gpu_memory_stats",
+            &["mesh_processor", "texture_processor"],
+        );
 
         // make sure that all renderer-specific systems run after game code
         builder.add_barrier();
+        builder.add(MaterialAnimationSystem, "material_animation", &[]);
+        builder.add(SceneStatsSystem::<B>::default(), "scene_stats", &[]);
 
         for plugin in &mut self.plugins {
             plugin.on_build(world, builder)?;
@@ -99,6 +128,7 @@ impl<'a, 'b, B: Backend> SystemBundle<'a, 'b> for RenderingBundle<B> {
 
 struct PluggableRenderGraphCreator<B: Backend> {
     plugins: Vec<Box<dyn RenderPlugin<B>>>,
+    working_space: WorkingSpace,
 }
 
 impl<B: Backend> GraphCreator<B> for PluggableRenderGraphCreator<B> {
@@ -116,10 +146,18 @@ impl<B: Backend> GraphCreator<B> for PluggableRenderGraphCreator<B> {
         }
 
         let mut plan = RenderPlan::new();
+        plan.working_space = self.working_space;
         for plugin in self.plugins.iter_mut() {
             plugin.on_plan(&mut plan, factory, world).unwrap();
         }
-        plan.build(factory).unwrap()
+        let (builder, target_bytes, frame_graph) = plan.build_counted(factory).unwrap();
+        if let Some(mut stats) = world.try_fetch_mut::<RenderStats>() {
+            stats.gpu_memory.render_targets = target_bytes;
+        }
+        if let Some(mut graph) = world.try_fetch_mut::<FrameGraph>() {
+            *graph = frame_graph;
+        }
+        builder
     }
 }
 
@@ -153,17 +191,28 @@ pub trait RenderPlugin<B: Backend>: std::fmt::Debug {
 }
 
 /// Builder of a rendering plan for specified target.
-#[derive(Debug)]
+#[derive(derivative::Derivative)]
+#[derivative(Debug(bound = ""))]
 pub struct RenderPlan<B: Backend> {
     targets: HashMap<Target, TargetPlan<B>>,
     roots: Vec<Target>,
+    display_targets: HashMap<Target, Target>,
+    working_space: WorkingSpace,
+    #[derivative(Debug = "ignore")]
+    display_extensions: Vec<(Target, TargetExtension<B>)>,
 }
 
+type TargetExtension<B> =
+    Box<dyn FnOnce(&mut TargetPlanContext<'_, B>) -> Result<(), Error> + 'static>;
+
 impl<B: Backend> RenderPlan<B> {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             targets: Default::default(),
             roots: vec![],
+            display_targets: Default::default(),
+            working_space: Default::default(),
+            display_extensions: vec![],
         }
     }
 
@@ -206,7 +255,77 @@ impl<B: Backend> RenderPlan<B> {
         target_plan.add_extension(Box::new(closure));
     }
 
-    fn build(self, factory: &Factory<B>) -> Result<GraphBuilder<B, World>, Error> {
+    /// Color space scene passes render and blend in, selected with
+    /// `RenderingBundle::with_working_space`.
+    pub fn working_space(&self) -> WorkingSpace {
+        self.working_space
+    }
+
+    /// Extend the rendering plan of the target on which given target is displayed, see
+    /// `set_display_target`.
+    ///
+    /// Meant for passes drawing colors in display space, like UI and debug drawing, which must
+    /// be drawn after tonemapping so that exposure doesn't change their brightness. Their
+    /// colors are written as they are, the display target's format takes care of the sRGB
+    /// encoding. The target is resolved when the plan is built, so plugins registered later
+    /// can still redirect it. A warning is logged when it resolves to a floating point target.
+    pub fn extend_display_target(
+        &mut self,
+        target: Target,
+        closure: impl FnOnce(&mut TargetPlanContext<'_, B>) -> Result<(), Error> + 'static,
+    ) {
+        self.display_extensions.push((target, Box::new(closure)));
+    }
+
+    /// Display passes extended on `scene` with `extend_display_target` on `display` instead,
+    /// e.g. when `scene` is an HDR target tonemapped into `display`.
+    pub fn set_display_target(&mut self, scene: Target, display: Target) {
+        if scene == display {
+            self.display_targets.remove(&scene);
+        } else {
+            self.display_targets.insert(scene, display);
+        }
+    }
+
+    /// Target on which given target is displayed, following all `set_display_target` calls.
+    pub fn display_target(&self, target: Target) -> Target {
+        let mut display = target;
+        for _ in 0..self.display_targets.len() {
+            match self.display_targets.get(&display) {
+                Some(&next) => display = next,
+                None => break,
+            }
+        }
+        display
+    }
+
+    /// Retrieve metadata, e.g. size, of a render target already defined with `define_pass`.
+    pub fn target_metadata(&self, target: Target, factory: &Factory<B>) -> Option<TargetMetadata> {
+        self.targets
+            .get(&target)
+            .and_then(|t| unsafe { t.metadata(factory.physical()) })
+    }
+
+    pub(crate) fn build(self, factory: &Factory<B>) -> Result<GraphBuilder<B, World>, Error> {
+        self.build_counted(factory).map(|(builder, ..)| builder)
+    }
+
+    /// Build the graph, also returning the approximate size of all created target images and
+    /// the description of the graph.
+    fn build_counted(
+        mut self,
+        factory: &Factory<B>,
+    ) -> Result<(GraphBuilder<B, World>, u64, FrameGraph), Error> {
+        for (target, extension) in std::mem::take(&mut self.display_extensions) {
+            let target = self.display_target(target);
+            let target_plan = self
+                .targets
+                .entry(target)
+                .or_insert_with(|| TargetPlan::new(target));
+            target_plan.display_space = true;
+            target_plan.add_extension(extension);
+        }
+
         let mut ctx = PlanContext {
             target_metadata: self
                 .targets
@@ -217,13 +336,22 @@ impl<B: Backend> RenderPlan<B> {
             passes: Default::default(),
             outputs: Default::default(),
             graph_builder: GraphBuilder::new(),
+            image_bytes: 0,
+            frame_graph: Default::default(),
         };
 
         for target in self.roots {
             ctx.evaluate_target(target)?;
         }
 
-        Ok(ctx.graph_builder)
+        let skipped = ctx.targets.keys().map(|target| FrameGraphTarget {
+            name: format!("{:?}", target),
+            ..Default::default()
+        });
+        ctx.frame_graph.targets.extend(skipped);
+        ctx.frame_graph.sort();
+
+        Ok((ctx.graph_builder, ctx.image_bytes, ctx.frame_graph))
     }
 }
 
@@ -255,6 +383,23 @@ pub struct TargetMetadata {
     layers: u16,
 }
 
+impl TargetMetadata {
+    /// Width of the target's framebuffer.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the target's framebuffer.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of layers of the target's framebuffer.
+    pub fn layers(&self) -> u16 {
+        self.layers
+    }
+}
+
 #[derive(Debug)]
 struct PlanContext<B: Backend> {
     targets: HashMap<Target, TargetPlan<B>>,
@@ -262,6 +407,8 @@ struct PlanContext<B: Backend> {
     passes: HashMap<Target, EvaluationState>,
     outputs: HashMap<TargetImage, ImageId>,
     graph_builder: GraphBuilder<B, World>,
+    image_bytes: u64,
+    frame_graph: FrameGraph,
 }
 
 impl<B: Backend> PlanContext<B> {
@@ -287,7 +434,7 @@ impl<B: Backend> PlanContext<B> {
     fn submit_pass(
         &mut self,
         target: Target,
-        pass: RenderPassNodeBuilder<B, World>,
+        pass: impl NodeBuilder<B, World> + 'static,
     ) -> Result<(), Error> {
         match self.passes.get(&target) {
             None => {}
@@ -361,6 +508,7 @@ impl<B: Backend> PlanContext<B> {
     }
 
     pub fn create_image(&mut self, options: ImageOptions) -> ImageId {
+        self.image_bytes += image_bytes(options.kind, options.levels, options.format);
         self.graph_builder
             .create_image(options.kind, options.levels, options.format, options.clear)
     }
@@ -374,12 +522,14 @@ pub struct TargetPlanContext<'a, B: Backend> {
     colors: usize,
     depth: bool,
     actions: Vec<(i32, RenderableAction<B>)>,
+    action_names: Vec<(i32, String)>,
     deps: Vec<NodeId>,
+    reads: Vec<FrameGraphRead>,
 }
 
 impl<'a, B: Backend> TargetPlanContext<'a, B> {
     /// Add new action to render target in defined order.
-    pub fn add(&mut self, order: impl Into<i32>, action: impl IntoAction<B>) -> Result<(), Error> {
+    pub fn add<A: IntoAction<B>>(&mut self, order: impl Into<i32>, action: A) -> Result<(), Error> {
         let action = action.into();
 
         if self.colors != action.colors() {
@@ -399,7 +549,10 @@ impl<'a, B: Backend> TargetPlanContext<'a, B> {
             ));
         }
 
-        self.actions.push((order.into(), action));
+        let order = order.into();
+        self.actions.push((order, action));
+        self.action_names
+            .push((order, action_name(std::any::type_name::<A>())));
         Ok(())
     }
 
@@ -418,30 +571,38 @@ impl<'a, B: Backend> TargetPlanContext<'a, B> {
     /// Results in an error if such image doesn't exist or
     /// retreiving it would result in a dependency cycle.
     pub fn get_image(&mut self, image: TargetImage) -> Result<ImageId, Error> {
-        self.plan_context.get_image(image).map(|i| {
-            let node = self
-                .plan_context
-                .get_pass_node_raw(image.target())
-                .expect("Image without target node");
-            self.add_dep(node);
-            i
-        })
+        let i = self.plan_context.get_image(image)?;
+        self.add_image_dep(image);
+        self.add_read(image, true);
+        Ok(i)
     }
     /// Retrieve an image produced by other render target.
     /// Returns `None` when such image isn't registered.
     ///
     /// Results in an error if retreiving it would result in a dependency cycle.
     pub fn try_get_image(&mut self, image: TargetImage) -> Result<Option<ImageId>, Error> {
-        self.plan_context.try_get_image(image).map(|i| {
-            i.map(|i| {
-                let node = self
-                    .plan_context
-                    .get_pass_node_raw(image.target())
-                    .expect("Image without target node");
-                self.add_dep(node);
-                i
-            })
-        })
+        let i = self.plan_context.try_get_image(image)?;
+        if i.is_some() {
+            self.add_image_dep(image);
+        }
+        self.add_read(image, i.is_some());
+        Ok(i)
+    }
+
+    fn add_image_dep(&mut self, image: TargetImage) {
+        let node = self
+            .plan_context
+            .get_pass_node_raw(image.target())
+            .expect("Image without target node");
+        self.add_dep(node);
+    }
+
+    fn add_read(&mut self, image: TargetImage, written: bool) {
+        self.reads.push(FrameGraphRead {
+            target: format!("{:?}", image.target()),
+            image: format!("{:?}", image),
+            written,
+        });
     }
 
     /// Add explicit dependency on another node.
@@ -527,8 +688,9 @@ pub struct TargetPlanOutputs<B: Backend> {
 struct TargetPlan<B: Backend> {
     key: Target,
     #[derivative(Debug = "ignore")]
-    extensions: Vec<Box<dyn FnOnce(&mut TargetPlanContext<'_, B>) -> Result<(), Error> + 'static>>,
+    extensions: Vec<TargetExtension<B>>,
     outputs: Option<TargetPlanOutputs<B>>,
+    display_space: bool,
 }
 
 impl<B: Backend> TargetPlan<B> {
@@ -537,6 +699,7 @@ impl<B: Backend> TargetPlan<B> {
             key,
             extensions: vec![],
             outputs: None,
+            display_space: false,
         }
     }
 
@@ -597,10 +760,7 @@ impl<B: Backend> TargetPlan<B> {
         Ok(())
     }
 
-    fn add_extension(
-        &mut self,
-        extension: Box<dyn FnOnce(&mut TargetPlanContext<'_, B>) -> Result<(), Error> + 'static>,
-    ) {
+    fn add_extension(&mut self, extension: TargetExtension<B>) {
         self.extensions.push(extension);
     }
 
@@ -613,6 +773,15 @@ impl<B: Backend> TargetPlan<B> {
         }
         let mut outputs = self.outputs.unwrap();
 
+        if self.display_space && outputs.colors.iter().any(is_hdr_output) {
+            log::warn!(
+                "Display space passes like UI or debug drawing are scheduled into HDR target {:?}. \
+                Their brightness will be changed by tonemapping, use `RenderPlan::set_display_target` \
+                to draw them on the tonemapped target instead.",
+                self.key
+            );
+        }
+
         ctx.mark_evaluating(self.key)?;
 
         let mut target_ctx = TargetPlanContext {
@@ -621,7 +790,9 @@ impl<B: Backend> TargetPlan<B> {
             actions: vec![],
             colors: outputs.colors.len(),
             depth: outputs.depth.is_some(),
+            action_names: vec![],
             deps: vec![],
+            reads: vec![],
         };
 
         for extension in self.extensions {
@@ -629,13 +800,39 @@ impl<B: Backend> TargetPlan<B> {
         }
 
         let TargetPlanContext {
-            mut actions, deps, ..
+            mut actions,
+            mut action_names,
+            deps,
+            reads,
+            ..
         } = target_ctx;
 
+        let samples = target_samples(&outputs);
+        if samples > 1 {
+            return evaluate_multisampled(
+                ctx,
+                self.key,
+                outputs,
+                samples,
+                actions,
+                action_names,
+                deps,
+                reads,
+            );
+        }
+
         let mut subpass = SubpassBuilder::new();
         let mut pass = RenderPassNodeBuilder::new();
 
         actions.sort_by_key(|a| a.0);
+        action_names.sort_by_key(|a| a.0);
+        let mut graph_target = FrameGraphTarget {
+            name: format!("{:?}", self.key),
+            evaluated: true,
+            actions: action_names,
+            writes: vec![],
+            reads,
+        };
         for action in actions.drain(..).map(|a| a.1) {
             match action {
                 RenderableAction::RenderGroup(group) => {
@@ -654,6 +851,9 @@ impl<B: Backend> TargetPlan<B> {
                     let node = ctx.create_image(opts);
                     ctx.register_output(TargetImage::Color(self.key, i), node)?;
                     subpass.add_color(node);
+                    graph_target
+                        .writes
+                        .push(format!("{:?}", TargetImage::Color(self.key, i)));
                 }
             }
         }
@@ -662,6 +862,9 @@ impl<B: Backend> TargetPlan<B> {
             let node = ctx.create_image(opts);
             ctx.register_output(TargetImage::Depth(self.key), node)?;
             subpass.set_depth_stencil(node);
+            graph_target
+                .writes
+                .push(format!("{:?}", TargetImage::Depth(self.key)));
         }
 
         for node in deps {
@@ -670,10 +873,120 @@ impl<B: Backend> TargetPlan<B> {
 
         pass.add_subpass(subpass);
         ctx.submit_pass(self.key, pass)?;
+        ctx.frame_graph.targets.push(graph_target);
         Ok(())
     }
 }
 
+/// Number of samples of the image outputs of a target, 1 unless multisampled.
+fn target_samples<B: Backend>(outputs: &TargetPlanOutputs<B>) -> hal::image::NumSamples {
+    outputs
+        .colors
+        .iter()
+        .filter_map(|color| match color {
+            OutputColor::Image(options) => Some(options.kind.num_samples()),
+            OutputColor::Surface(..) => None,
+        })
+        .chain(
+            outputs
+                .depth
+                .iter()
+                .map(|options| options.kind.num_samples()),
+        )
+        .max()
+        .unwrap_or(1)
+}
+
+/// Evaluate a multisampled target, see [crate::msaa].
+#[allow(clippy::too_many_arguments)]
+fn evaluate_multisampled<B: Backend>(
+    ctx: &mut PlanContext<B>,
+    key: Target,
+    outputs: TargetPlanOutputs<B>,
+    samples: hal::image::NumSamples,
+    mut actions: Vec<(i32, RenderableAction<B>)>,
+    mut action_names: Vec<(i32, String)>,
+    deps: Vec<NodeId>,
+    reads: Vec<FrameGraphRead>,
+) -> Result<(), Error> {
+    let mut pass = MultisampledPassNodeBuilder::new(samples);
+
+    actions.sort_by_key(|a| a.0);
+    action_names.sort_by_key(|a| a.0);
+    let mut graph_target = FrameGraphTarget {
+        name: format!("{:?}", key),
+        evaluated: true,
+        actions: action_names,
+        writes: vec![],
+        reads,
+    };
+    for action in actions.drain(..).map(|a| a.1) {
+        match action {
+            RenderableAction::RenderGroup(group) => pass.add_group(group),
+        }
+    }
+
+    for (i, color) in outputs.colors.into_iter().enumerate() {
+        let mut options = match color {
+            OutputColor::Image(options) => options,
+            OutputColor::Surface(..) => {
+                return Err(format_err!(
+                    "Multisampled target {:?} can't render directly to a surface.",
+                    key
+                ))
+            }
+        };
+        options.kind = with_samples(options.kind, samples);
+        ctx.image_bytes += image_bytes(options.kind, options.levels, options.format);
+
+        let resolve = ctx.create_image(ImageOptions {
+            kind: with_samples(options.kind, 1),
+            levels: 1,
+            format: options.format,
+            clear: None,
+        });
+        ctx.register_output(TargetImage::Color(key, i), resolve)?;
+        pass.add_color(options, resolve);
+        graph_target
+            .writes
+            .push(format!("{:?}", TargetImage::Color(key, i)));
+    }
+
+    if let Some(mut options) = outputs.depth {
+        options.kind = with_samples(options.kind, samples);
+        ctx.image_bytes += image_bytes(options.kind, options.levels, options.format);
+        pass.set_depth(options);
+    }
+
+    for node in deps {
+        pass.add_dependency(node);
+    }
+
+    ctx.submit_pass(key, pass)?;
+    ctx.frame_graph.targets.push(graph_target);
+    Ok(())
+}
+
+fn with_samples(kind: hal::image::Kind, samples: hal::image::NumSamples) -> hal::image::Kind {
+    match kind {
+        hal::image::Kind::D2(width, height, layers, _) => {
+            hal::image::Kind::D2(width, height, layers, samples)
+        }
+        kind => kind,
+    }
+}
+
+fn is_hdr_output<B: Backend>(color: &OutputColor<B>) -> bool {
+    use hal::format::ChannelType;
+    match color {
+        OutputColor::Image(options) => matches!(
+            options.format.base_format().1,
+            ChannelType::Sfloat | ChannelType::Ufloat
+        ),
+        OutputColor::Surface(..) => false,
+    }
+}
+
 /// An action that represents a single transformation to the
 /// render graph, e.g. addition of single render group.
 ///
@@ -748,6 +1061,25 @@ impl Into<i32> for RenderOrder {
     }
 }
 
+/// Color space in which the scene is rendered and blended.
+///
+/// Shaders always output linear colors and sample textures loaded with sRGB formats as linear,
+/// so alpha blending is only correct when the render target stores linear values or converts
+/// them on write. Blending into a non-linear target produces dark fringes on particles and
+/// text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WorkingSpace {
+    /// Render the scene into a linear `Rgba16Sfloat` target and convert it to the window
+    /// surface in the tonemapping step. Display space passes, like UI, are drawn after it.
+    /// Preferred, as values above 1 are kept until tonemapping.
+    LinearHdr,
+    /// Render directly into an sRGB window surface, which converts the colors on write so that
+    /// blending happens in linear space. A warning is logged when the surface doesn't support
+    /// an sRGB format.
+    #[default]
+    SrgbFramebuffer,
+}
+
 /// An identifier for render target used in render plugins.
 /// Predefined targets are part of default rendering flow
 /// used by builtin amethyst render plugins, but the list
@@ -827,6 +1159,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn display_target_follows_redirects() {
+        let mut plan = RenderPlan::<DefaultBackend>::new();
+        assert_eq!(plan.display_target(Target::Main), Target::Main);
+
+        plan.set_display_target(Target::Custom("hdr"), Target::Custom("ldr"));
+        plan.set_display_target(Target::Custom("ldr"), Target::Main);
+        assert_eq!(plan.display_target(Target::Custom("hdr")), Target::Main);
+        assert_eq!(plan.display_target(Target::Custom("ldr")), Target::Main);
+
+        // Cycles don't hang.
+        plan.set_display_target(Target::Main, Target::Custom("hdr"));
+        plan.display_target(Target::Main);
+
+        plan.set_display_target(Target::Main, Target::Main);
+        assert_eq!(plan.display_target(Target::Main), Target::Main);
+    }
+
     #[test]
     #[ignore] // CI can't run tests requiring actual backend
     fn main_pass_color_image_plan() {
@@ -963,4 +1313,322 @@ mod tests {
             format!("{:?}", manual_graph)
         );
     }
+
+    /// Defines `Target::Main` as offscreen images, as a window would define it.
+    #[derive(Debug)]
+    struct HeadlessMain;
+
+    impl<B: Backend> RenderPlugin<B> for HeadlessMain {
+        fn on_plan(
+            &mut self,
+            plan: &mut RenderPlan<B>,
+            _factory: &mut Factory<B>,
+            _world: &World,
+        ) -> Result<(), Error> {
+            let kind = crate::Kind::D2(320, 240, 1, 1);
+            plan.add_root(Target::Main);
+            plan.define_pass(
+                Target::Main,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::Rgba8Srgb,
+                        clear: Some(ClearValue::Color([0.0; 4].into())),
+                    })],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    }),
+                },
+            )
+        }
+    }
+
+    #[test]
+    #[ignore] // CI can't run tests requiring actual backend
+    fn empty_scenes_render_headlessly() {
+        use crate::{
+            camera::Camera,
+            error::RenderGraphError,
+            light::{Light, PointLight},
+            plugins::{RenderFlat2D, RenderPbr3D, RenderShaded3D},
+        };
+        use amethyst_assets::Loader;
+        use amethyst_core::{
+            ecs::{Builder, WorldExt},
+            ArcThreadPool, Time, Transform,
+        };
+        use std::sync::Arc;
+
+        let mut world = World::new();
+        let pool: ArcThreadPool = Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap());
+        world.insert(pool.clone());
+        world.insert(Loader::new(std::env::temp_dir(), pool));
+        world.insert(Time::default());
+        world.insert(RenderStats::default());
+
+        let mut builder = DispatcherBuilder::new();
+        RenderingBundle::<DefaultBackend>::new()
+            .with_plugin(HeadlessMain)
+            .with_plugin(RenderShaded3D::default())
+            .with_plugin(RenderPbr3D::default())
+            .with_plugin(RenderFlat2D::default())
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder.build();
+        dispatcher.setup(&mut world);
+
+        let mut render = |world: &mut World| {
+            for _ in 0..3 {
+                dispatcher.dispatch(world);
+                world.maintain();
+                if let Some(error) = world.read_resource::<RenderGraphError>().error() {
+                    panic!("Failed to render: {}", error);
+                }
+                let stats = world.read_resource::<RenderStats>();
+                assert_eq!(stats.draw_list_touched, 0);
+                assert_eq!(stats.discard_draws, 0);
+            }
+        };
+
+        // No camera and nothing to draw.
+        render(&mut world);
+
+        // Only lights, seen from the default camera and then from a camera entity.
+        world
+            .create_entity()
+            .with(Light::Point(PointLight::default()))
+            .with(Transform::default())
+            .build();
+        render(&mut world);
+        world
+            .create_entity()
+            .with(Camera::standard_3d(320.0, 240.0))
+            .with(Transform::default())
+            .build();
+        render(&mut world);
+
+        // Back to an empty world.
+        world.delete_all();
+        render(&mut world);
+    }
+
+    /// Budget of the scene built by [heavy_scene] in `perf_budgets.toml`.
+    fn heavy_scene_budget() -> crate::stats::RenderBudget {
+        let mut budgets: HashMap<String, crate::stats::RenderBudget> =
+            toml::from_str(include_str!("../perf_budgets.toml")).unwrap();
+        budgets.remove("heavy_scene").unwrap()
+    }
+
+    /// Transforms of the 10k entities of the heavy scene, seen by a `standard_3d` camera at the
+    /// origin. Every fourth entity is behind the camera.
+    fn heavy_scene() -> impl Iterator<Item = amethyst_core::Transform> {
+        (0..10_000).map(|i| {
+            let mut transform = amethyst_core::Transform::default();
+            let depth = 5.0 + (i / 200) as f32 * 2.0;
+            transform.set_translation_xyz(
+                (i % 20) as f32 - 10.0,
+                (i / 20 % 10) as f32 - 5.0,
+                if i % 4 == 0 { depth } else { -depth },
+            );
+            transform
+        })
+    }
+
+    /// The 256 lights of the heavy scene: the directional light casting shadows, then point and
+    /// spot lights filling the light buffers, spread among the entities.
+    fn heavy_scene_lights() -> Vec<(crate::light::Light, amethyst_core::Transform)> {
+        use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+
+        std::iter::once(Light::from(DirectionalLight::default()))
+            .chain((0..127).map(|_| PointLight::default().into()))
+            .chain((0..128).map(|_| SpotLight::default().into()))
+            .enumerate()
+            .map(|(i, light)| {
+                let mut transform = amethyst_core::Transform::default();
+                transform.set_translation_xyz(
+                    (i % 16) as f32 * 2.0 - 16.0,
+                    6.0,
+                    -((i / 16) as f32) * 4.0,
+                );
+                (light, transform)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn heavy_scene_culls_within_budget() {
+        use crate::{camera::Camera, visibility::VisibilitySortingSystem};
+        use amethyst_core::{
+            ecs::{Builder, WorldExt},
+            Transform, TransformBundle,
+        };
+
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder
+            .with(
+                VisibilitySortingSystem::new(),
+                "visibility_system",
+                &["transform_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(16.0, 9.0))
+            .with(Transform::default())
+            .build();
+        for transform in heavy_scene() {
+            world.create_entity().with(transform).build();
+        }
+        world.register::<crate::light::Light>();
+        for (light, transform) in heavy_scene_lights() {
+            world.create_entity().with(light).with(transform).build();
+        }
+        dispatcher.dispatch(&world);
+
+        // Every light of the scene fits into the light buffers, none is dropped.
+        {
+            use crate::{
+                light::Light,
+                submodules::{MAX_POINT_LIGHTS, MAX_SPOT_LIGHTS},
+            };
+
+            let lights = heavy_scene_lights();
+            let count = |f: fn(&Light) -> bool| lights.iter().filter(|(l, _)| f(l)).count();
+            assert_eq!(lights.len(), 256);
+            assert_eq!(count(|l| matches!(l, Light::Directional(_))), 1);
+            assert!(count(|l| matches!(l, Light::Point(_))) <= MAX_POINT_LIGHTS);
+            assert!(count(|l| matches!(l, Light::Spot(_))) <= MAX_SPOT_LIGHTS);
+        }
+
+        // Only the culling limits can be checked without a backend, the other stats stay zero.
+        let stats = world.read_resource::<RenderStats>();
+        assert!(stats.frustum_culled >= 2500);
+        assert_eq!(
+            heavy_scene_budget().violations(&stats),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    #[ignore] // CI can't run tests requiring actual backend
+    fn heavy_scene_renders_within_budget() {
+        use crate::{
+            camera::Camera,
+            error::RenderGraphError,
+            mtl::{Material, MaterialDefaults},
+            plugins::{RenderShaded3D, RenderShadows},
+            rendy::mesh::{Normal, Position, TexCoord},
+            shape::Shape,
+            types::{Mesh, MeshData},
+        };
+        use amethyst_assets::{AssetStorage, Loader};
+        use amethyst_core::{
+            ecs::{Builder, WorldExt},
+            ArcThreadPool, Time, Transform, TransformBundle,
+        };
+        use std::{sync::Arc, time::Duration};
+
+        let mut world = World::new();
+        let pool: ArcThreadPool = Arc::new(rayon::ThreadPoolBuilder::new().build().unwrap());
+        world.insert(pool.clone());
+        world.insert(Loader::new(std::env::temp_dir(), pool));
+        world.insert(Time::default());
+        world.insert(RenderStats::default());
+
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        RenderingBundle::<DefaultBackend>::new()
+            .with_plugin(HeadlessMain)
+            .with_plugin(RenderShadows::default())
+            .with_plugin(RenderShaded3D::default().with_shadows())
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder.build();
+        dispatcher.setup(&mut world);
+
+        let (mesh, material) = {
+            let loader = world.read_resource::<Loader>();
+            let mesh = loader.load_from_data::<Mesh, _>(
+                MeshData(
+                    Shape::Cube.generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(Some((
+                        0.4, 0.4, 0.4,
+                    ))),
+                ),
+                (),
+                &world.read_resource::<AssetStorage<Mesh>>(),
+            );
+            let material = loader.load_from_data::<Material, _>(
+                world.read_resource::<MaterialDefaults>().0.clone(),
+                (),
+                &world.read_resource::<AssetStorage<Material>>(),
+            );
+            (mesh, material)
+        };
+        world
+            .create_entity()
+            .with(Camera::standard_3d(320.0, 240.0))
+            .with(Transform::default())
+            .build();
+        for transform in heavy_scene() {
+            world
+                .create_entity()
+                .with(transform)
+                .with(mesh.clone())
+                .with(material.clone())
+                .build();
+        }
+        for (light, transform) in heavy_scene_lights() {
+            world.create_entity().with(light).with(transform).build();
+        }
+
+        let mut frame = |world: &mut World| {
+            dispatcher.dispatch(world);
+            world.maintain();
+            if let Some(error) = world.read_resource::<RenderGraphError>().error() {
+                panic!("Failed to render: {}", error);
+            }
+            RenderStats::clone(&world.read_resource())
+        };
+
+        // Let the assets load and the draw lists fill before measuring the steady state.
+        for _ in 0..30 {
+            frame(&mut world);
+        }
+        const FRAMES: u32 = 300;
+        let mut stats = RenderStats::default();
+        let (mut graph_time, mut encode_time) = (Duration::default(), Duration::default());
+        let (mut draw_calls, mut buffer_allocations, mut light_uploads) = (0, 0, 0);
+        for _ in 0..FRAMES {
+            stats = frame(&mut world);
+            graph_time += stats.graph_time;
+            encode_time += stats.encode_time;
+            draw_calls = draw_calls.max(stats.draw_calls);
+            buffer_allocations += stats.buffer_allocations;
+            light_uploads += stats.light_uploads;
+        }
+        // Times averaged over the measured frames, the most draw calls of a frame and every
+        // allocation and light upload of the steady state, the other stats of the last frame.
+        assert!(draw_calls > 0);
+        stats.graph_time = graph_time / FRAMES;
+        stats.encode_time = encode_time / FRAMES;
+        stats.draw_calls = draw_calls;
+        stats.buffer_allocations = buffer_allocations;
+        stats.light_uploads = light_uploads;
+        assert_eq!(
+            heavy_scene_budget().violations(&stats),
+            Vec::<String>::new()
+        );
+    }
 }
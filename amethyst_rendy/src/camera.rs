@@ -1,8 +1,9 @@
 //! Camera type with support for perspective and orthographic projections.
 
+use crate::conventions::{mirror_z, Conventions, Handedness};
 use amethyst_assets::PrefabData;
 use amethyst_core::{
-    ecs::prelude::{Component, Entity, HashMapStorage, Write, WriteStorage},
+    ecs::prelude::{Component, Entity, HashMapStorage, Read, Write, WriteStorage},
     geometry::Ray,
     math::{Matrix4, Point2, Point3, Vector2},
     transform::components::Transform,
@@ -38,6 +39,9 @@ pub struct Camera {
     pub matrix: Matrix4<f32>,
     /// Its inverse
     pub inverse: Matrix4<f32>,
+    /// Handedness of the view space of the projection, see [crate::conventions].
+    #[serde(default)]
+    pub handedness: Handedness,
 }
 
 impl Camera {
@@ -161,10 +165,39 @@ impl Camera {
             matrix,
             inverse: matrix
                 .try_inverse()
-                .expect("Camera projection matrix is not invertible. This is normally due to having inverse values being superimposed (near=far, right=left)")
+                .expect("Camera projection matrix is not invertible. This is normally due to having inverse values being superimposed (near=far, right=left)"),
+            handedness: Handedness::Right,
         }
     }
 
+    /// Makes a camera from a projection matrix in given conventions, e.g. one built by the
+    /// math library of the project, converting its depth range to the one of the renderer.
+    ///
+    /// * panics if the matrix is not invertible
+    pub fn from_projection(matrix: Matrix4<f32>, conventions: &Conventions) -> Self {
+        Self {
+            handedness: conventions.handedness,
+            ..Self::from_matrix(conventions.projection(matrix))
+        }
+    }
+
+    /// Returns this camera looking along the Z axis of given handedness, mirroring the
+    /// projection when it changes. The constructors of this type build right handed cameras.
+    pub fn with_handedness(mut self, handedness: Handedness) -> Self {
+        if self.handedness != handedness {
+            let mirror = mirror_z();
+            self.matrix *= mirror;
+            self.inverse = mirror * self.inverse;
+            self.handedness = handedness;
+        }
+        self
+    }
+
+    /// Returns this camera converted to the handedness of given conventions.
+    pub fn with_conventions(self, conventions: &Conventions) -> Self {
+        self.with_handedness(conventions.handedness)
+    }
+
     /// Returns a `Ray` going out form the camera through provided screen position. The ray origin lies on camera near plane.
     ///
     /// The screen coordinate (0, 0) is the top-left corner of the top-left pixel.
@@ -243,6 +276,47 @@ pub struct ActiveCamera {
     pub entity: Option<Entity>,
 }
 
+/// Debugging freeze of the camera the scene is culled with.
+///
+/// While `freeze` is set, `VisibilitySortingSystem` keeps culling entities, switching them to
+/// impostors and picking the entities tested for occlusion with a snapshot of the active camera
+/// taken at the first frame the freeze was set, while the scene is still drawn from the live
+/// camera. Fly around to inspect what the frozen camera culled. `DrawDebugLinesDesc` draws the
+/// frustum of the snapshot.
+#[derive(Clone, Debug, Default)]
+pub struct FrozenCamera {
+    /// Cull with a snapshot of the active camera.
+    pub freeze: bool,
+    snapshot: Option<(Camera, Transform)>,
+}
+
+impl FrozenCamera {
+    /// Freeze the culling camera, or release it, when it's frozen.
+    pub fn toggle(&mut self) {
+        self.freeze = !self.freeze;
+    }
+
+    /// Camera and transform of the snapshot the scene is culled with, while frozen.
+    pub fn snapshot(&self) -> Option<(&Camera, &Transform)> {
+        self.snapshot.as_ref().map(|(c, t)| (c, t))
+    }
+
+    /// Take or drop the snapshot according to `freeze`, returning the camera to cull with.
+    pub(crate) fn update<'a>(
+        &'a mut self,
+        live: (&'a Camera, &'a Transform),
+    ) -> (&'a Camera, &'a Transform) {
+        if !self.freeze {
+            self.snapshot = None;
+            return live;
+        }
+        let (camera, transform) = self
+            .snapshot
+            .get_or_insert_with(|| (live.0.clone(), live.1.clone()));
+        (camera, transform)
+    }
+}
+
 /// Projection prefab
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 pub enum CameraPrefab {
@@ -272,35 +346,84 @@ pub enum CameraPrefab {
     },
 }
 
+impl CameraPrefab {
+    /// Prefab of a camera created with `Camera::perspective` or `Camera::orthographic`, in
+    /// either handedness, or `None` for other projection matrices.
+    pub fn from_camera(camera: &Camera) -> Option<Self> {
+        let m = &camera.clone().with_handedness(Handedness::Right).matrix;
+        let axis_aligned = [
+            (0, 1),
+            (0, 2),
+            (1, 0),
+            (1, 2),
+            (2, 0),
+            (2, 1),
+            (3, 0),
+            (3, 1),
+        ]
+        .iter()
+        .all(|&index| m[index] == 0.0);
+        if !axis_aligned {
+            None
+        } else if m[(3, 2)] == -1.0 && m[(3, 3)] == 0.0 {
+            let tan_half_fovy = -1.0 / m[(1, 1)];
+            Some(CameraPrefab::Perspective {
+                aspect: 1.0 / (m[(0, 0)] * tan_half_fovy),
+                fovy: 2.0 * tan_half_fovy.atan(),
+                znear: m[(2, 3)],
+            })
+        } else if m[(3, 2)] == 0.0 && m[(3, 3)] == 1.0 {
+            let (width, height) = (2.0 / m[(0, 0)], -2.0 / m[(1, 1)]);
+            let (center_x, center_y) = (-m[(0, 3)] * width / 2.0, -m[(1, 3)] * height / 2.0);
+            let zfar = m[(2, 3)] / m[(2, 2)];
+            Some(CameraPrefab::Orthographic {
+                left: center_x - width / 2.0,
+                right: center_x + width / 2.0,
+                bottom: center_y - height / 2.0,
+                top: center_y + height / 2.0,
+                znear: zfar - 1.0 / m[(2, 2)],
+                zfar,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Right handed camera with the projection of the prefab.
+    pub fn camera(&self) -> Camera {
+        match *self {
+            CameraPrefab::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                znear,
+                zfar,
+            } => Camera::orthographic(left, right, bottom, top, znear, zfar),
+            CameraPrefab::Perspective {
+                aspect,
+                fovy,
+                znear,
+            } => Camera::perspective(aspect, fovy, znear),
+        }
+    }
+}
+
 impl<'a> PrefabData<'a> for CameraPrefab {
-    type SystemData = WriteStorage<'a, Camera>;
+    type SystemData = (WriteStorage<'a, Camera>, Option<Read<'a, Conventions>>);
     type Result = ();
 
     fn add_to_entity(
         &self,
         entity: Entity,
-        storage: &mut Self::SystemData,
+        (storage, conventions): &mut Self::SystemData,
         _: &[Entity],
         _: &[Entity],
     ) -> Result<(), Error> {
-        storage.insert(
-            entity,
-            match *self {
-                CameraPrefab::Orthographic {
-                    left,
-                    right,
-                    bottom,
-                    top,
-                    znear,
-                    zfar,
-                } => Camera::orthographic(left, right, bottom, top, znear, zfar),
-                CameraPrefab::Perspective {
-                    aspect,
-                    fovy,
-                    znear,
-                } => Camera::perspective(aspect, fovy, znear),
-            },
-        )?;
+        let conventions = conventions
+            .as_ref()
+            .map_or_else(Conventions::default, |c| **c);
+        storage.insert(entity, self.camera().with_conventions(&conventions))?;
         Ok(())
     }
 }
@@ -474,6 +597,24 @@ mod tests {
         assert_eq!(test_persp, de);
     }
 
+    #[test]
+    fn prefab_from_camera_rebuilds_the_projection() {
+        let cameras = [
+            Camera::orthographic(0.0, 100.0, 10.0, 150.0, -5.0, 100.0),
+            Camera::perspective(1.7, std::f32::consts::FRAC_PI_3, 0.1),
+        ];
+        for camera in cameras.iter() {
+            let rebuilt = CameraPrefab::from_camera(camera).unwrap().camera();
+            assert_abs_diff_eq!(rebuilt.matrix, camera.matrix, epsilon = 1e-4);
+        }
+        assert!(
+            CameraPrefab::from_camera(&Camera::from_matrix(Matrix4::new_rotation(
+                Vector3::z() * 0.5
+            )))
+            .is_none()
+        );
+    }
+
     // Our world-space is +Y Up, +X Right and -Z Away
     // Current render target is +Y Down, +X Right and +Z Away
     fn setup() -> (Transform, [Point3<f32>; 3], [Point3<f32>; 3]) {
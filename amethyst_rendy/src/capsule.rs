@@ -0,0 +1,44 @@
+//! Analytic capsule occluders.
+//!
+//! Entities with a [CapsuleOccluder] and a `Transform` darken the ambient lighting of the
+//! surfaces around them in the shaded and PBR passes, grounding characters with soft contact
+//! shadows without a shadow casting light. A character is covered by a handful of capsules,
+//! e.g. for its torso and limbs, each placed on a joint with a `SocketAttachment` or a
+//! `Parent`.
+//!
+//! The occlusion of every capsule is evaluated in closed form, as the occlusion of a sphere at
+//! the point of its axis closest to the shaded point. Up to [MAX_CAPSULE_OCCLUDERS] capsules
+//! nearest to the camera occlude the scene, further ones are ignored.
+
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+
+/// Maximum number of capsules occluding the scene at once.
+pub const MAX_CAPSULE_OCCLUDERS: usize = 32;
+
+/// Occlude the ambient lighting around the entity with a capsule, see the
+/// [module documentation](self).
+///
+/// The capsule axis lies along the local Y axis of the entity `Transform`, centered on its
+/// origin. The radius isn't scaled by the transform.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CapsuleOccluder {
+    /// Radius of the capsule, in world units.
+    pub radius: f32,
+    /// Half of the axis length of the capsule, between the centers of its end spheres, in
+    /// object space.
+    pub half_length: f32,
+}
+
+impl CapsuleOccluder {
+    /// Capsule of given radius and half axis length.
+    pub fn new(radius: f32, half_length: f32) -> Self {
+        Self {
+            radius,
+            half_length,
+        }
+    }
+}
+
+impl Component for CapsuleOccluder {
+    type Storage = DenseVecStorage<Self>;
+}
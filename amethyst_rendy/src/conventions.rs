@@ -0,0 +1,315 @@
+//! Coordinate system conventions of the project using the renderer.
+//!
+//! The renderer works in a right handed world with counter-clockwise front faces, and its
+//! cameras map the near plane to depth 1 and infinity to depth 0. A project whose math library
+//! or assets use other conventions inserts a [Conventions] resource once, before the renderer
+//! is set up, instead of flipping signs at every call site:
+//!
+//! * cameras are converted with `Camera::with_conventions`, or built from the projection
+//!   matrices of the math library with `Camera::from_projection`. Camera prefabs are converted
+//!   when added to their entity,
+//! * the pipelines culling back faces cull the faces opposite to `front_face`,
+//! * the shadow map is drawn from the light with matrices of the same handedness as the
+//!   cameras,
+//! * [Conventions::import_transform] converts assets authored in the engine conventions, as
+//!   glTF and most OBJ files are, to the conventions of the project.
+//!
+//! Pipelines read the resource when the render graph is built, so changing it later only
+//! applies after a rebuild. Mixing conventions within a frame is unsupported: the
+//! `RenderingSystem` logs an error when the active camera was built with another handedness
+//! than the resource.
+
+use crate::formats::mesh::{Axis, ImportTransform};
+use amethyst_core::{
+    ecs::{Read, SystemData, World},
+    math::{Matrix4, Vector4},
+};
+use rendy::hal::pso::FrontFace;
+
+/// Handedness of the world coordinate system.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum Handedness {
+    /// Right handed, cameras looking along their -Z axis.
+    #[default]
+    Right,
+    /// Left handed, cameras looking along their +Z axis.
+    Left,
+}
+
+/// Corner order of the front faces of triangles, seen from their front.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum Winding {
+    /// Counter-clockwise front faces.
+    #[default]
+    CounterClockwise,
+    /// Clockwise front faces.
+    Clockwise,
+}
+
+/// Depth range of projection matrices.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum DepthRange {
+    /// Depth going from 1 at the near plane to 0 at the far plane, as used by the renderer.
+    #[default]
+    Reversed,
+    /// Depth going from 0 at the near plane to 1 at the far plane, as Vulkan and Direct3D
+    /// projections do.
+    ZeroToOne,
+    /// Depth going from -1 at the near plane to 1 at the far plane, as OpenGL projections do.
+    NegativeOneToOne,
+}
+
+/// Resource describing the coordinate system conventions of the project, see
+/// [crate::conventions].
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+#[serde(default)]
+pub struct Conventions {
+    /// Handedness of the world and of the cameras.
+    pub handedness: Handedness,
+    /// Winding of the front faces of meshes.
+    pub front_face: Winding,
+    /// Depth range of the projection matrices passed to `Camera::from_projection`.
+    pub depth_range: DepthRange,
+}
+
+impl Conventions {
+    /// Left handed world with clockwise front faces and 0 to 1 projections, as used by
+    /// Direct3D.
+    pub fn left_handed() -> Self {
+        Self {
+            handedness: Handedness::Left,
+            front_face: Winding::Clockwise,
+            depth_range: DepthRange::ZeroToOne,
+        }
+    }
+
+    /// Returns these conventions with given handedness.
+    pub fn with_handedness(mut self, handedness: Handedness) -> Self {
+        self.handedness = handedness;
+        self
+    }
+
+    /// Returns these conventions with given front face winding.
+    pub fn with_front_face(mut self, front_face: Winding) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    /// Returns these conventions with given depth range of projection matrices.
+    pub fn with_depth_range(mut self, depth_range: DepthRange) -> Self {
+        self.depth_range = depth_range;
+        self
+    }
+
+    /// Conventions of the world, the default ones when there is no `Conventions` resource.
+    pub fn of(world: &World) -> Self {
+        <Option<Read<'_, Conventions>>>::fetch(world).map_or_else(Self::default, |c| *c)
+    }
+
+    /// Whether the front faces appear with the opposite winding to the engine conventions once
+    /// projected, which is the case when exactly one of the handedness and winding differs.
+    fn flips_winding(&self) -> bool {
+        (self.handedness == Handedness::Left) != (self.front_face == Winding::Clockwise)
+    }
+
+    /// Front face of the rasterizer state of pipelines culling back faces.
+    pub fn rasterizer_front_face(&self) -> FrontFace {
+        if self.flips_winding() {
+            FrontFace::Clockwise
+        } else {
+            FrontFace::CounterClockwise
+        }
+    }
+
+    /// Import transform converting assets from the engine conventions, right handed with
+    /// counter-clockwise front faces, to these conventions.
+    pub fn import_transform(&self) -> ImportTransform {
+        let axes = match self.handedness {
+            Handedness::Right => [Axis::X, Axis::Y, Axis::Z],
+            Handedness::Left => [Axis::X, Axis::Y, Axis::NegZ],
+        };
+        ImportTransform {
+            axes,
+            scale: 1.0,
+            flip_winding: self.flips_winding(),
+        }
+    }
+
+    /// Convert a projection matrix with the depth range of these conventions to the reversed
+    /// depth of the renderer. Its clip space must have Y pointing down, as in Vulkan.
+    pub fn projection(&self, matrix: Matrix4<f32>) -> Matrix4<f32> {
+        let (z, w): (Vector4<f32>, Vector4<f32>) =
+            (matrix.row(2).transpose(), matrix.row(3).transpose());
+        let z = match self.depth_range {
+            DepthRange::Reversed => z,
+            DepthRange::ZeroToOne => w - z,
+            DepthRange::NegativeOneToOne => (w - z) * 0.5,
+        };
+        let mut matrix = matrix;
+        matrix.set_row(2, &z.transpose());
+        matrix
+    }
+}
+
+/// Mirror of the Z axis, converting between right and left handed view spaces.
+pub(crate) fn mirror_z() -> Matrix4<f32> {
+    Matrix4::new_nonuniform_scaling(&[1.0, 1.0, -1.0].into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::Camera, shadow::ShadowSettings};
+    use amethyst_core::{
+        math::{Point3, UnitQuaternion, Vector3},
+        Transform,
+    };
+
+    /// A triangle seen by a camera, in the world of given conventions.
+    struct Scene {
+        triangle: [Point3<f32>; 3],
+        camera: Camera,
+        transform: Transform,
+    }
+
+    /// The same triangle and camera in given conventions, from a right handed description with
+    /// a counter-clockwise triangle.
+    fn scene(conventions: &Conventions) -> Scene {
+        let mirror = |p: Point3<f32>| match conventions.handedness {
+            Handedness::Right => p,
+            Handedness::Left => Point3::new(p.x, p.y, -p.z),
+        };
+        let mut triangle = [
+            mirror(Point3::new(-1.0, 0.0, -4.0)),
+            mirror(Point3::new(1.0, 0.0, -5.0)),
+            mirror(Point3::new(0.0, 1.5, -4.5)),
+        ];
+        // Mirroring turns the triangle clockwise, reverse it where the front faces wind the
+        // other way.
+        if conventions.flips_winding() {
+            triangle.swap(1, 2);
+        }
+
+        // A camera behind the triangle turned to the left: the mirror of a rotation turns the
+        // other way around the Y axis.
+        let angle = match conventions.handedness {
+            Handedness::Right => 0.3,
+            Handedness::Left => -0.3,
+        };
+        let position = mirror(Point3::new(0.5, 1.0, 1.0));
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(position.x, position.y, position.z);
+        transform.set_rotation(UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angle));
+        transform.copy_local_to_global();
+
+        Scene {
+            triangle,
+            camera: Camera::standard_3d(16.0, 9.0).with_conventions(conventions),
+            transform,
+        }
+    }
+
+    /// Normalized device coordinates of the triangle, and whether the rasterizer sees its front.
+    fn rasterize(conventions: &Conventions, scene: &Scene) -> ([Point3<f32>; 3], bool) {
+        let matrix = scene.camera.matrix * scene.transform.global_view_matrix();
+        let ndc = [0, 1, 2].map(|i| matrix.transform_point(&scene.triangle[i]));
+        let (a, b) = (ndc[1] - ndc[0], ndc[2] - ndc[0]);
+        // Signed area in framebuffer coordinates, with Y pointing down.
+        let counter_clockwise = a.x * b.y - a.y * b.x < 0.0;
+        let front = match conventions.rasterizer_front_face() {
+            FrontFace::CounterClockwise => counter_clockwise,
+            FrontFace::Clockwise => !counter_clockwise,
+        };
+        (ndc, front)
+    }
+
+    fn assert_same_points(a: &[Point3<f32>], b: &[Point3<f32>]) {
+        for (a, b) in a.iter().zip(b) {
+            assert!((a - b).norm() < 1.0e-5, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn scenes_rasterize_the_same_in_every_convention() {
+        let engine = Conventions::default();
+        let (expected, front) = rasterize(&engine, &scene(&engine));
+        assert!(front);
+        assert!(expected.iter().all(|p| p.z > 0.0 && p.z < 1.0));
+
+        for &handedness in &[Handedness::Right, Handedness::Left] {
+            for &front_face in &[Winding::CounterClockwise, Winding::Clockwise] {
+                let conventions = Conventions::default()
+                    .with_handedness(handedness)
+                    .with_front_face(front_face);
+                let (ndc, front) = rasterize(&conventions, &scene(&conventions));
+                assert!(front, "{:?}", conventions);
+                let mut ndc = ndc;
+                if conventions.flips_winding() {
+                    ndc.swap(1, 2);
+                }
+                assert_same_points(&ndc, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn shadow_maps_match_in_both_handedness() {
+        let settings = ShadowSettings::default();
+        let project = |conventions: &Conventions| {
+            let scene = scene(conventions);
+            let direction = match conventions.handedness {
+                Handedness::Right => Vector3::new(-0.4, -1.0, -0.3),
+                Handedness::Left => Vector3::new(-0.4, -1.0, 0.3),
+            };
+            let (proj, view) = settings
+                .light_matrices_in(conventions, &direction, &scene.triangle[0], 1024)
+                .unwrap();
+            let ndc = scene.triangle.map(|p| (proj * view).transform_point(&p));
+            let (a, b) = (ndc[1] - ndc[0], ndc[2] - ndc[0]);
+            (ndc, a.x * b.y - a.y * b.x < 0.0)
+        };
+        let (expected, counter_clockwise) = project(&Conventions::default());
+        let (ndc, mirrored_counter_clockwise) = project(&Conventions::left_handed());
+        assert_same_points(&ndc, &expected);
+        // The left handed triangle is clockwise and culled with clockwise front faces.
+        assert_eq!(counter_clockwise, mirrored_counter_clockwise);
+        assert_eq!(
+            Conventions::left_handed().rasterizer_front_face(),
+            FrontFace::CounterClockwise
+        );
+    }
+
+    #[test]
+    fn foreign_projections_are_reversed() {
+        let (near, far) = (0.5, 100.0);
+        // Right handed 0 to 1 and -1 to 1 perspectives, with Y pointing down.
+        let mut zero_to_one = Matrix4::<f32>::zeros();
+        zero_to_one[(0, 0)] = 1.0;
+        zero_to_one[(1, 1)] = -1.0;
+        zero_to_one[(2, 2)] = far / (near - far);
+        zero_to_one[(2, 3)] = near * far / (near - far);
+        zero_to_one[(3, 2)] = -1.0;
+        let mut negative_one_to_one = zero_to_one;
+        negative_one_to_one[(2, 2)] = (far + near) / (near - far);
+        negative_one_to_one[(2, 3)] = 2.0 * far * near / (near - far);
+
+        for &(range, matrix) in &[
+            (DepthRange::ZeroToOne, zero_to_one),
+            (DepthRange::NegativeOneToOne, negative_one_to_one),
+        ] {
+            let conventions = Conventions::default().with_depth_range(range);
+            let camera = Camera::from_projection(matrix, &conventions);
+            let depth = |z: f32| camera.matrix.transform_point(&Point3::new(0.0, 0.0, z)).z;
+            assert!((depth(-near) - 1.0).abs() < 1.0e-5, "{:?}", range);
+            assert!(depth(-far).abs() < 1.0e-5, "{:?}", range);
+        }
+    }
+}
@@ -1,11 +1,22 @@
 //! Debug Drawing library
 use crate::pod::IntoPod;
 use amethyst_core::{
-    ecs::{Component, DenseVecStorage},
-    math::{Point2, Point3, UnitQuaternion, Vector2, Vector3},
+    ecs::{Component, DenseVecStorage, Entity, World},
+    math::{Matrix4, Point2, Point3, UnitQuaternion, Vector2, Vector3},
 };
+use derivative::Derivative;
+use fnv::{FnvHashMap, FnvHashSet};
 use palette::Srgba;
 use rendy::mesh::{AsVertex, Color, PosColor, VertexFormat};
+use std::{cmp::Ordering, sync::Arc};
+
+/// Maximum number of lines drawn for a single convex hull. Edges beyond are dropped with a
+/// warning, dense point clouds should be simplified before being visualized.
+pub const MAX_HULL_LINES: usize = 1024;
+
+/// Normalized depth of the far end of the frustums drawn by `add_frustum`. Perspective cameras
+/// have no far plane, their depth falls to 0 at an infinite distance.
+pub const FRUSTUM_FAR_DEPTH: f32 = 0.001;
 
 /// Debug lines are stored as a pair of position and color.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -25,6 +36,17 @@ impl DebugLine {
     fn new(start: PosColor, end: PosColor) -> Self {
         Self { start, end }
     }
+
+    fn transformed(&self, matrix: &Matrix4<f32>) -> Self {
+        let transform = |vertex: &PosColor| PosColor {
+            position: matrix
+                .transform_point(&Point3::from(vertex.position.0))
+                .coords
+                .into(),
+            color: vertex.color,
+        };
+        Self::new(transform(&self.start), transform(&self.end))
+    }
 }
 
 /// Parameters for renderer of debug lines. The params affect all lines.
@@ -364,6 +386,136 @@ impl DebugLinesComponent {
         }
     }
 
+    /// Adds multiple lines that form a capsule to be rendered by giving a center, a radius, the height of its cylindrical part and an amount of points.
+    ///
+    /// This capsule is aligned to the y axis.
+    pub fn add_capsule(
+        &mut self,
+        center: Point3<f32>,
+        radius: f32,
+        height: f32,
+        points: u32,
+        color: Srgba,
+    ) {
+        self.add_rotated_capsule(
+            center,
+            radius,
+            height,
+            points,
+            UnitQuaternion::identity(),
+            color,
+        );
+    }
+
+    /// Adds multiple lines that form a rotated capsule to be rendered by giving a center, a radius, the height of its cylindrical part, an amount of points and a rotation.
+    pub fn add_rotated_capsule(
+        &mut self,
+        center: Point3<f32>,
+        radius: f32,
+        height: f32,
+        points: u32,
+        rotation: UnitQuaternion<f32>,
+        color: Srgba,
+    ) {
+        let points = points.max(4);
+        let up = rotation * Vector3::y();
+        let top = center + up * (height / 2.0);
+        let bottom = center - up * (height / 2.0);
+        let ring = rotation
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::FRAC_PI_2);
+        self.add_rotated_circle(top, radius, points, ring, color);
+        self.add_rotated_circle(bottom, radius, points, ring, color);
+
+        // Side lines and hemisphere arcs in the XY and ZY planes.
+        for side in &[Vector3::x(), Vector3::z(), -Vector3::x(), -Vector3::z()] {
+            let side = rotation * side * radius;
+            self.add_line(top + side, bottom + side, color);
+        }
+        let arc_points = points / 2;
+        for plane in &[Vector3::x(), Vector3::z()] {
+            let horizontal = rotation * plane * radius;
+            let mut prev: Option<(Point3<f32>, Point3<f32>)> = None;
+            for i in 0..=arc_points {
+                let a = std::f32::consts::PI / (arc_points as f32) * (i as f32);
+                let offset = horizontal * a.cos();
+                let vertical = up * (radius * a.sin());
+                let upper = top + offset + vertical;
+                let lower = bottom + offset - vertical;
+                if let Some(prev) = prev {
+                    self.add_line(prev.0, upper, color);
+                    self.add_line(prev.1, lower, color);
+                }
+                prev = Some((upper, lower));
+            }
+        }
+    }
+
+    /// Adds the edges of the convex hull of a point cloud to be rendered.
+    ///
+    /// Edges between coplanar faces are omitted, and at most `MAX_HULL_LINES` lines are added.
+    pub fn add_convex_hull(&mut self, points: &[Point3<f32>], color: Srgba) {
+        let mut edges = convex_hull_edges(points);
+        if edges.len() > MAX_HULL_LINES {
+            log::warn!(
+                "Convex hull of {} points has {} edges, only the first {} are drawn.",
+                points.len(),
+                edges.len(),
+                MAX_HULL_LINES
+            );
+            edges.truncate(MAX_HULL_LINES);
+        }
+        for (a, b) in edges {
+            self.add_line(points[a], points[b], color);
+        }
+    }
+
+    /// Adds the edges of the frustum of a projection-view matrix to be rendered, from its near
+    /// plane to the depth of `FRUSTUM_FAR_DEPTH`. Nothing is added when the matrix isn't
+    /// invertible.
+    pub fn add_frustum(&mut self, proj_view: &Matrix4<f32>, color: Srgba) {
+        let inverse = match proj_view.try_inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        let corner = |x: f32, y: f32, z: f32| inverse.transform_point(&Point3::new(x, y, z));
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        for (i, &(x, y)) in corners.iter().enumerate() {
+            let (next_x, next_y) = corners[(i + 1) % corners.len()];
+            let near = corner(x, y, 1.0);
+            let far = corner(x, y, FRUSTUM_FAR_DEPTH);
+            self.add_line(near, corner(next_x, next_y, 1.0), color);
+            self.add_line(far, corner(next_x, next_y, FRUSTUM_FAR_DEPTH), color);
+            self.add_line(near, far, color);
+        }
+    }
+
+    /// Adds the lines of a grid of `cells` by `cells` square cells of given size to be rendered,
+    /// centered on `center`.
+    ///
+    /// This grid is aligned to the XZ plane, e.g. to visualize the ground.
+    pub fn add_grid(&mut self, center: Point3<f32>, cell_size: f32, cells: u32, color: Srgba) {
+        self.add_rotated_grid(center, cell_size, cells, UnitQuaternion::identity(), color);
+    }
+
+    /// Adds the lines of a rotated grid of `cells` by `cells` square cells of given size to be
+    /// rendered, centered on `center`.
+    pub fn add_rotated_grid(
+        &mut self,
+        center: Point3<f32>,
+        cell_size: f32,
+        cells: u32,
+        rotation: UnitQuaternion<f32>,
+        color: Srgba,
+    ) {
+        let half = cell_size * cells as f32 / 2.0;
+        let point = |x: f32, z: f32| center + rotation * Vector3::new(x, 0.0, z);
+        for i in 0..=cells {
+            let offset = i as f32 * cell_size - half;
+            self.add_line(point(offset, -half), point(offset, half), color);
+            self.add_line(point(-half, offset), point(half, offset), color);
+        }
+    }
+
     /// Clears lines buffer.
     ///
     /// As lines are persistent, it's necessary to use this function for updating or deleting lines.
@@ -516,7 +668,514 @@ impl DebugLines {
             .add_rotated_cylinder(center, radius, height, points, rotation, color);
     }
 
+    /// Submits multiple lines that form a capsule to be rendered by giving a center, a radius, the height of its cylindrical part and an amount of points.
+    ///
+    /// This capsule is aligned to the y axis.
+    pub fn draw_capsule(
+        &mut self,
+        center: Point3<f32>,
+        radius: f32,
+        height: f32,
+        points: u32,
+        color: Srgba,
+    ) {
+        self.inner
+            .add_capsule(center, radius, height, points, color);
+    }
+
+    /// Submits multiple lines that form a rotated capsule to be rendered by giving a center, a radius, the height of its cylindrical part, an amount of points and a rotation.
+    pub fn draw_rotated_capsule(
+        &mut self,
+        center: Point3<f32>,
+        radius: f32,
+        height: f32,
+        points: u32,
+        rotation: UnitQuaternion<f32>,
+        color: Srgba,
+    ) {
+        self.inner
+            .add_rotated_capsule(center, radius, height, points, rotation, color);
+    }
+
+    /// Submits the edges of the convex hull of a point cloud to be rendered.
+    pub fn draw_convex_hull(&mut self, points: &[Point3<f32>], color: Srgba) {
+        self.inner.add_convex_hull(points, color);
+    }
+
+    /// Submits the edges of the frustum of a projection-view matrix to be rendered.
+    pub fn draw_frustum(&mut self, proj_view: &Matrix4<f32>, color: Srgba) {
+        self.inner.add_frustum(proj_view, color);
+    }
+
+    /// Submits the lines of a grid of `cells` by `cells` square cells of given size to be
+    /// rendered, centered on `center`.
+    ///
+    /// This grid is aligned to the XZ plane, e.g. to visualize the ground.
+    pub fn draw_grid(&mut self, center: Point3<f32>, cell_size: f32, cells: u32, color: Srgba) {
+        self.inner.add_grid(center, cell_size, cells, color);
+    }
+
+    /// Submits the lines of a rotated grid of `cells` by `cells` square cells of given size to
+    /// be rendered, centered on `center`.
+    pub fn draw_rotated_grid(
+        &mut self,
+        center: Point3<f32>,
+        cell_size: f32,
+        cells: u32,
+        rotation: UnitQuaternion<f32>,
+        color: Srgba,
+    ) {
+        self.inner
+            .add_rotated_grid(center, cell_size, cells, rotation, color);
+    }
+
     pub(crate) fn drain<'a>(&'a mut self) -> impl Iterator<Item = DebugLine> + 'a {
         self.inner.lines.drain(..)
     }
 }
+
+/// Callback computing the world transform of a `DebugShape`.
+pub type DebugShapeProvider = Arc<dyn Fn(&World) -> Option<Matrix4<f32>> + Send + Sync>;
+
+/// Source of the world transform a `DebugShape` is drawn with.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub enum DebugShapeAnchor {
+    /// Global `Transform` of the entity the `DebugShapesComponent` is attached to.
+    Owner,
+    /// Global `Transform` of another entity, e.g. a joint of a skinned mesh.
+    Entity(Entity),
+    /// Transform computed by a callback every frame. The shape is not drawn while it returns
+    /// `None`. The callback must not write to `DebugLinesComponent` or `DebugLines`.
+    Provider(#[derivative(Debug = "ignore")] DebugShapeProvider),
+}
+
+/// Wireframe shape drawn by the debug lines pass at the transform of its anchor, e.g. to
+/// visualize physics colliders following animated bones.
+///
+/// The lines of the shape are generated once in local space. As the global transforms of
+/// joints are updated by the transform system, shapes may lag one frame behind the animation.
+#[derive(Clone, Debug)]
+pub struct DebugShape {
+    lines: Vec<DebugLine>,
+    local: Matrix4<f32>,
+    anchor: DebugShapeAnchor,
+    depth_test: bool,
+}
+
+impl DebugShape {
+    fn from_lines(lines: DebugLinesComponent) -> Self {
+        Self {
+            lines: lines.lines,
+            local: Matrix4::identity(),
+            anchor: DebugShapeAnchor::Owner,
+            depth_test: true,
+        }
+    }
+
+    /// Capsule around the y axis, with a cylindrical part of given height.
+    pub fn capsule(radius: f32, height: f32, points: u32, color: Srgba) -> Self {
+        let mut lines = DebugLinesComponent::new();
+        lines.add_capsule(Point3::origin(), radius, height, points, color);
+        Self::from_lines(lines)
+    }
+
+    /// Cylinder around the y axis.
+    pub fn cylinder(radius: f32, height: f32, points: u32, color: Srgba) -> Self {
+        let mut lines = DebugLinesComponent::new();
+        lines.add_cylinder(Point3::origin(), radius, height, points, color);
+        Self::from_lines(lines)
+    }
+
+    /// Convex hull of a point cloud given in local space.
+    pub fn convex_hull(points: &[Point3<f32>], color: Srgba) -> Self {
+        let mut lines = DebugLinesComponent::new();
+        lines.add_convex_hull(points, color);
+        Self::from_lines(lines)
+    }
+
+    /// Offset the shape from its anchor.
+    pub fn with_local_transform(mut self, local: Matrix4<f32>) -> Self {
+        self.local = local;
+        self
+    }
+
+    /// Set the source of the shape transform.
+    pub fn with_anchor(mut self, anchor: DebugShapeAnchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Set whether the shape is hidden behind geometry. Shapes without depth test are drawn
+    /// over the whole scene.
+    pub fn with_depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    /// Source of the shape transform.
+    pub fn anchor(&self) -> &DebugShapeAnchor {
+        &self.anchor
+    }
+
+    /// Whether the shape is hidden behind geometry.
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    /// Append the lines of the shape placed at given anchor transform.
+    pub(crate) fn write_lines(&self, anchor: &Matrix4<f32>, out: &mut Vec<DebugLine>) {
+        let matrix = anchor * self.local;
+        out.extend(self.lines.iter().map(|line| line.transformed(&matrix)));
+    }
+}
+
+/// Component storing persistent debug shapes drawn at the transform of their anchors.
+#[derive(Clone, Debug, Default)]
+pub struct DebugShapesComponent {
+    /// Shapes to be rendered
+    pub shapes: Vec<DebugShape>,
+}
+
+impl Component for DebugShapesComponent {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl DebugShapesComponent {
+    /// Creates a new component without shapes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder method to add a shape.
+    pub fn with_shape(mut self, shape: DebugShape) -> Self {
+        self.shapes.push(shape);
+        self
+    }
+}
+
+struct HullFace {
+    vertices: [usize; 3],
+    normal: Vector3<f32>,
+    offset: f32,
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl HullFace {
+    fn new(points: &[Point3<f32>], vertices: [usize; 3]) -> Self {
+        let [a, b, c] = vertices;
+        let normal = (points[b] - points[a])
+            .cross(&(points[c] - points[a]))
+            .normalize();
+        Self {
+            vertices,
+            normal,
+            offset: normal.dot(&points[a].coords),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, point: &Point3<f32>) -> f32 {
+        self.normal.dot(&point.coords) - self.offset
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.vertices;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// Indices of the points connected by the edges of their convex hull, computed with quickhull.
+/// Flat point clouds result in the edges of their 2D hull.
+fn convex_hull_edges(points: &[Point3<f32>]) -> Vec<(usize, usize)> {
+    if points.len() < 2 {
+        return Vec::new();
+    }
+    let distance_cmp = |a: f32, b: f32| a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    let farthest = |distance: &dyn Fn(&Point3<f32>) -> f32| {
+        (0..points.len())
+            .max_by(|&a, &b| distance_cmp(distance(&points[a]), distance(&points[b])))
+            .unwrap()
+    };
+
+    let (mut min, mut max) = (points[0].coords, points[0].coords);
+    for point in points {
+        for i in 0..3 {
+            min[i] = min[i].min(point[i]);
+            max[i] = max[i].max(point[i]);
+        }
+    }
+    let epsilon = (max - min).norm() * 1.0e-5;
+    let axis = (max - min).imax();
+
+    let a = farthest(&|p| -p[axis]);
+    let b = farthest(&|p| p[axis]);
+    if (points[b] - points[a]).norm() <= epsilon {
+        return Vec::new();
+    }
+    let direction = (points[b] - points[a]).normalize();
+    let line_distance = |p: &Point3<f32>| {
+        let offset = p - points[a];
+        (offset - direction * offset.dot(&direction)).norm()
+    };
+    let c = farthest(&line_distance);
+    if line_distance(&points[c]) <= epsilon {
+        return vec![(a, b)];
+    }
+    let normal = direction.cross(&(points[c] - points[a])).normalize();
+    let plane_distance = |p: &Point3<f32>| (p - points[a]).dot(&normal).abs();
+    let d = farthest(&plane_distance);
+    if plane_distance(&points[d]) <= epsilon {
+        return planar_hull_edges(points, normal);
+    }
+
+    let centroid = Point3::from(
+        (points[a].coords + points[b].coords + points[c].coords + points[d].coords) / 4.0,
+    );
+    let mut faces = Vec::new();
+    for &[i, j, k] in &[[a, b, c], [a, b, d], [a, c, d], [b, c, d]] {
+        let face = HullFace::new(points, [i, j, k]);
+        faces.push(if face.distance(&centroid) > 0.0 {
+            HullFace::new(points, [i, k, j])
+        } else {
+            face
+        });
+    }
+    let assign = |faces: &mut [HullFace], point: usize| {
+        if let Some(face) = faces
+            .iter_mut()
+            .find(|f| f.alive && f.distance(&points[point]) > epsilon)
+        {
+            face.outside.push(point);
+        }
+    };
+    for point in 0..points.len() {
+        if ![a, b, c, d].contains(&point) {
+            assign(&mut faces, point);
+        }
+    }
+
+    while let Some(face) = faces.iter().position(|f| f.alive && !f.outside.is_empty()) {
+        let eye = *faces[face]
+            .outside
+            .iter()
+            .max_by(|&&i, &&j| {
+                distance_cmp(
+                    faces[face].distance(&points[i]),
+                    faces[face].distance(&points[j]),
+                )
+            })
+            .unwrap();
+
+        let visible = (0..faces.len())
+            .filter(|&f| faces[f].alive && faces[f].distance(&points[eye]) > epsilon)
+            .collect::<Vec<_>>();
+        let visible_edges = visible
+            .iter()
+            .flat_map(|&f| faces[f].edges().to_vec())
+            .collect::<FnvHashSet<_>>();
+
+        let mut orphans = Vec::new();
+        let mut horizon = Vec::new();
+        for &f in &visible {
+            for &(i, j) in &faces[f].edges() {
+                if !visible_edges.contains(&(j, i)) {
+                    horizon.push((i, j));
+                }
+            }
+            faces[f].alive = false;
+            orphans.append(&mut faces[f].outside);
+        }
+
+        let first_new = faces.len();
+        faces.extend(
+            horizon
+                .into_iter()
+                .map(|(i, j)| HullFace::new(points, [i, j, eye])),
+        );
+        for point in orphans.into_iter().filter(|&p| p != eye) {
+            assign(&mut faces[first_new..], point);
+        }
+    }
+
+    // Keep only the edges between faces that are not coplanar, so that flat sides of the hull
+    // are not drawn triangulated.
+    let mut edge_normals = FnvHashMap::<(usize, usize), Vec<Vector3<f32>>>::default();
+    for face in faces.iter().filter(|f| f.alive) {
+        for &(i, j) in &face.edges() {
+            edge_normals
+                .entry((i.min(j), i.max(j)))
+                .or_default()
+                .push(face.normal);
+        }
+    }
+    let mut edges = edge_normals
+        .into_iter()
+        .filter(|(_, normals)| normals.len() != 2 || normals[0].dot(&normals[1]) < 1.0 - 1.0e-4)
+        .map(|(edge, _)| edge)
+        .collect::<Vec<_>>();
+    edges.sort();
+    edges
+}
+
+/// Edges of the 2D convex hull of points lying in a plane with given normal.
+fn planar_hull_edges(points: &[Point3<f32>], normal: Vector3<f32>) -> Vec<(usize, usize)> {
+    let u = normal.cross(&if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    });
+    let v = normal.cross(&u);
+    let projected = points
+        .iter()
+        .map(|p| (p.coords.dot(&u), p.coords.dot(&v)))
+        .collect::<Vec<_>>();
+
+    let mut order = (0..points.len()).collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (projected[a], projected[b]);
+        (a.0, a.1)
+            .partial_cmp(&(b.0, b.1))
+            .unwrap_or(Ordering::Equal)
+    });
+    let cross = |o: usize, a: usize, b: usize| {
+        let (o, a, b) = (projected[o], projected[a], projected[b]);
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+
+    // Andrew's monotone chain.
+    let mut hull: Vec<usize> = Vec::new();
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &usize>> = if pass == 0 {
+            Box::new(order.iter())
+        } else {
+            Box::new(order.iter().rev())
+        };
+        for &point in iter {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+
+    (0..hull.len())
+        .map(|i| (hull[i], hull[(i + 1) % hull.len()]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube() -> Vec<Point3<f32>> {
+        let mut points = Vec::new();
+        for &x in &[-1.0, 1.0] {
+            for &y in &[-1.0, 1.0] {
+                for &z in &[-1.0, 1.0] {
+                    points.push(Point3::new(x, y, z));
+                }
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn hull_of_cube_has_its_twelve_edges() {
+        let mut points = cube();
+        points.push(Point3::origin());
+        points.push(Point3::new(0.0, 1.0, 0.0));
+        points.push(Point3::new(0.5, -0.2, 0.9));
+
+        let edges = convex_hull_edges(&points);
+        assert_eq!(edges.len(), 12);
+        for (a, b) in edges {
+            // Every cube edge connects corners differing in exactly one coordinate.
+            let diff = points[a] - points[b];
+            assert!(a < 8 && b < 8);
+            assert_eq!(diff.iter().filter(|c| c.abs() > 1.0).count(), 1);
+        }
+    }
+
+    #[test]
+    fn hull_of_tetrahedron_and_flat_clouds() {
+        let tetrahedron = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Point3::new(0.1, 0.1, 0.1),
+        ];
+        assert_eq!(convex_hull_edges(&tetrahedron).len(), 6);
+
+        let square = [
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(1.0, 0.0, 2.0),
+            Point3::new(0.5, 0.5, 2.0),
+            Point3::new(1.0, 1.0, 2.0),
+            Point3::new(0.0, 1.0, 2.0),
+        ];
+        let edges = convex_hull_edges(&square);
+        assert_eq!(edges.len(), 4);
+        assert!(edges.iter().all(|&(a, b)| a != 2 && b != 2));
+
+        let line = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 1.0),
+            Point3::new(2.0, 2.0, 2.0),
+        ];
+        assert_eq!(convex_hull_edges(&line), vec![(0, 2)]);
+        assert!(convex_hull_edges(&[Point3::origin(); 3]).is_empty());
+    }
+
+    #[test]
+    fn dense_hulls_are_capped() {
+        let mut points = Vec::new();
+        for i in 0..64 {
+            for j in 0..32 {
+                let (lon, lat) = (i as f32 * 0.098, j as f32 * 0.098 + 0.05);
+                points.push(Point3::new(
+                    lat.sin() * lon.cos(),
+                    lat.cos(),
+                    lat.sin() * lon.sin(),
+                ));
+            }
+        }
+        let mut lines = DebugLinesComponent::new();
+        lines.add_convex_hull(&points, Srgba::new(1.0, 1.0, 1.0, 1.0));
+        assert_eq!(lines.lines().len(), MAX_HULL_LINES);
+    }
+
+    #[test]
+    fn grids_span_their_cells() {
+        let mut lines = DebugLinesComponent::new();
+        let white = Srgba::new(1.0, 1.0, 1.0, 1.0);
+        lines.add_grid(Point3::new(1.0, 2.0, 3.0), 0.5, 4, white);
+        // Five lines along each axis bound four cells.
+        assert_eq!(lines.lines().len(), 10);
+        for line in lines.lines() {
+            let (start, end): ([f32; 3], [f32; 3]) = (line.start.position.0, line.end.position.0);
+            assert_eq!((start[1], end[1]), (2.0, 2.0));
+            for &c in start.iter().chain(&end) {
+                assert!(c.abs() <= 4.0);
+            }
+            let length = Vector3::from(end) - Vector3::from(start);
+            assert!((length.norm() - 2.0).abs() < 1e-6);
+        }
+
+        let mut lines = DebugLinesComponent::new();
+        let rotation = UnitQuaternion::from_euler_angles(std::f32::consts::FRAC_PI_2, 0.0, 0.0);
+        lines.add_rotated_grid(Point3::origin(), 1.0, 2, rotation, white);
+        // Rotated about X, the grid lies in the XY plane.
+        for line in lines.lines() {
+            assert!(line.start.position.0[2].abs() < 1e-6);
+            assert!(line.end.position.0[2].abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Deterministic rendering, for replays and golden image tests.
+//!
+//! The renderer has no source of randomness other than hashes of explicit seeds, but some of
+//! its stochastic elements follow the frame time measured by the `Time` resource, and texture
+//! uploads can be deferred by a time budget. Inserting a seeded [RenderDeterminism] resource
+//! makes every one of them derive from the seed and the frame number instead:
+//!
+//! * the per pixel jitter of the volumetric lighting steps,
+//! * the random directions of the particles emitted by `GpuParticleEmitterSystem`, and the
+//!   time step of their emission and simulation,
+//! * the time the `MaterialAnimationSystem` samples the material animations at,
+//! * the time budget of `TextureUploadBudget`, which is ignored. The byte budget still applies.
+//!
+//! The frames are then the same from run to run as long as the world is, which includes
+//! waiting for the assets to be loaded: they are loaded and processed asynchronously, in an
+//! order depending on the thread pool.
+
+use amethyst_core::timing::Time;
+
+/// Resource making the stochastic elements of the renderer derive from a seed, see
+/// [crate::determinism].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderDeterminism {
+    /// Seed of every stochastic element, `None` to follow the `Time` resource.
+    pub seed: Option<u64>,
+    /// Time between frames while seeded, in seconds.
+    pub frame_delta: f32,
+}
+
+impl Default for RenderDeterminism {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            frame_delta: 1.0 / 60.0,
+        }
+    }
+}
+
+impl RenderDeterminism {
+    /// Derive the stochastic elements from given seed, with a frame every 60th of a second.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..Default::default()
+        }
+    }
+
+    /// Set the time between frames while seeded, in seconds.
+    pub fn with_frame_delta(mut self, frame_delta: f32) -> Self {
+        self.frame_delta = frame_delta;
+        self
+    }
+
+    /// Whether the elements are derived from a seed.
+    pub fn is_seeded(&self) -> bool {
+        self.seed.is_some()
+    }
+
+    /// Seed of given frame, `None` when not seeded.
+    pub fn frame_seed(&self, frame: u64) -> Option<u32> {
+        self.seed.map(|seed| hash(seed ^ hash(frame) as u64))
+    }
+
+    /// Seconds since the last frame, the fixed frame delta while seeded.
+    pub fn delta_seconds(&self, time: &Time) -> f32 {
+        if self.is_seeded() {
+            self.frame_delta
+        } else {
+            time.delta_seconds()
+        }
+    }
+
+    /// Seconds since the start of the application, counted in whole frames while seeded.
+    pub fn absolute_time_seconds(&self, time: &Time) -> f64 {
+        if self.is_seeded() {
+            time.frame_number() as f64 * f64::from(self.frame_delta)
+        } else {
+            time.absolute_time_seconds()
+        }
+    }
+}
+
+/// SplitMix64 finalizer, truncated to 32 bits.
+fn hash(x: u64) -> u32 {
+    let mut x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (x ^ (x >> 31)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_seeds_depend_on_the_seed_and_frame() {
+        let a = RenderDeterminism::seeded(7);
+        let seeds = |d: &RenderDeterminism| (0..10).map(|f| d.frame_seed(f)).collect::<Vec<_>>();
+        assert_eq!(seeds(&a), seeds(&RenderDeterminism::seeded(7)));
+        assert_ne!(seeds(&a), seeds(&RenderDeterminism::seeded(8)));
+        assert_ne!(a.frame_seed(0), a.frame_seed(1));
+        assert_eq!(RenderDeterminism::default().frame_seed(0), None);
+    }
+
+    #[test]
+    fn seeded_time_counts_frames() {
+        let mut time = Time::default();
+        time.set_delta_seconds(0.1234);
+        time.increment_frame_number();
+        time.increment_frame_number();
+
+        let seeded = RenderDeterminism::seeded(0).with_frame_delta(0.5);
+        assert_eq!(seeded.delta_seconds(&time), 0.5);
+        assert_eq!(seeded.absolute_time_seconds(&time), 1.0);
+        assert_eq!(RenderDeterminism::default().delta_seconds(&time), 0.1234);
+    }
+}
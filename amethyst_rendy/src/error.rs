@@ -1,22 +1,330 @@
 //! Renderer error types.
 
+use crate::{bundle::Target, view::ViewClear};
+use rendy::hal::pso::ShaderStageFlags;
 use std::{error, fmt};
 
-/// Common renderer error type.
+/// Error of the renderer, naming the pass, target or asset which failed.
+///
+/// Messages are meant to be shown as is, with the error of the underlying library, if any,
+/// available as its `source`. Converts with `?` into `amethyst_error::Error` and
+/// `failure::Error`, so it can be returned from plugins, formats and render groups alike.
 #[derive(Debug)]
-pub(crate) enum Error {
-    /// Failed to parse a Spritesheet from RON.
-    LoadSpritesheetError(ron::de::Error),
+pub enum RenderError {
+    /// A render pass failed to build.
+    Pass(PassError),
+    /// A shader failed to compile.
+    Shader(ShaderError),
+    /// A texture failed to load.
+    Texture(TextureError),
+    /// A sprite sheet failed to parse from RON.
+    SpriteSheet(ron::de::Error),
+    /// A plugin draws to or reads from a target no plugin registered before it defines.
+    MissingTarget {
+        /// The missing target.
+        target: Target,
+        /// Name of the plugin requiring the target.
+        plugin: &'static str,
+    },
+    /// Two views write overlapping regions of the same target with different clear settings,
+    /// so whichever clears last would erase part of the other.
+    OverlappingViews {
+        /// Index of the first view in `Views`.
+        first: usize,
+        /// Index of the second view in `Views`.
+        second: usize,
+        /// Target of both views.
+        target: Target,
+        /// Clear settings of the first and second view.
+        clears: (ViewClear, ViewClear),
+    },
 }
 
-impl error::Error for Error {}
+impl error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RenderError::Pass(e) => Some(e),
+            RenderError::Shader(e) => Some(e),
+            RenderError::Texture(e) => Some(e),
+            RenderError::SpriteSheet(e) => Some(e),
+            RenderError::MissingTarget { .. } | RenderError::OverlappingViews { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Pass(e) => write!(fmt, "{}", e),
+            RenderError::Shader(e) => write!(fmt, "{}", e),
+            RenderError::Texture(e) => write!(fmt, "{}", e),
+            RenderError::SpriteSheet(e) => write!(fmt, "Failed to parse SpriteSheet: {}", e),
+            RenderError::MissingTarget { target, plugin } => write!(
+                fmt,
+                "Target {:?} must be defined before adding {} plugin",
+                target, plugin
+            ),
+            RenderError::OverlappingViews {
+                first,
+                second,
+                target,
+                clears,
+            } => write!(
+                fmt,
+                "Views {} and {} write overlapping regions of target {:?} with different clear \
+                 settings {:?} and {:?}",
+                first, second, target, clears.0, clears.1
+            ),
+        }
+    }
+}
+
+impl From<PassError> for RenderError {
+    fn from(e: PassError) -> Self {
+        RenderError::Pass(e)
+    }
+}
+
+impl From<ShaderError> for RenderError {
+    fn from(e: ShaderError) -> Self {
+        RenderError::Shader(e)
+    }
+}
+
+impl From<TextureError> for RenderError {
+    fn from(e: TextureError) -> Self {
+        RenderError::Texture(e)
+    }
+}
+
+/// Error compiling a shader from source, with the log of the compiler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderError {
+    shader: String,
+    log: String,
+}
+
+impl ShaderError {
+    /// Error compiling the shader of given name, with the log of the compiler.
+    pub fn new(shader: impl Into<String>, log: impl Into<String>) -> Self {
+        Self {
+            shader: shader.into(),
+            log: log.into(),
+        }
+    }
+
+    /// Name of the shader which failed to compile.
+    pub fn shader(&self) -> &str {
+        &self.shader
+    }
+
+    /// Log of the compiler, listing the errors by line.
+    pub fn log(&self) -> &str {
+        &self.log
+    }
+}
+
+impl error::Error for ShaderError {}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "Shader {} failed to compile:\n{}",
+            self.shader, self.log
+        )
+    }
+}
+
+/// Error loading the texture of given name, e.g. the path of an image which failed to decode.
+#[derive(Debug)]
+pub struct TextureError {
+    name: String,
+    cause: Box<dyn error::Error + Send + Sync>,
+}
+
+impl TextureError {
+    /// Error loading the texture of given name.
+    pub fn new(
+        name: impl Into<String>,
+        cause: impl Into<Box<dyn error::Error + Send + Sync>>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            cause: cause.into(),
+        }
+    }
+
+    /// Name of the texture which failed to load.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.cause)
+    }
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "Texture {} failed to load: {}", self.name, self.cause)
+    }
+}
+
+/// Error building the pipelines of a render pass, e.g. when the driver rejects one of its
+/// shaders. Returned by the `build` of the render group descriptions of this crate, wrapped in
+/// a `failure::Error`, see [RenderError].
+#[derive(Debug)]
+pub struct PassError {
+    pass: &'static str,
+    stage: Option<ShaderStageFlags>,
+    cause: failure::Error,
+}
+
+impl PassError {
+    /// Error creating the shader module of given stage of a pass.
+    pub fn shader(pass: &'static str, stage: ShaderStageFlags, cause: failure::Error) -> Self {
+        Self {
+            pass,
+            stage: Some(stage),
+            cause,
+        }
+    }
+
+    /// Error building the pipelines of a pass from its shader modules.
+    pub fn pipelines(pass: &'static str, cause: failure::Error) -> Self {
+        Self {
+            pass,
+            stage: None,
+            cause,
+        }
+    }
+
+    /// Name of the pass which failed to build.
+    pub fn pass(&self) -> &'static str {
+        self.pass
+    }
+
+    /// Shader stage which failed to build, `None` when the shaders were created but their
+    /// pipelines failed to build.
+    pub fn stage(&self) -> Option<ShaderStageFlags> {
+        self.stage
+    }
+
+    /// Error returned by the device.
+    pub fn cause(&self) -> &failure::Error {
+        &self.cause
+    }
+}
+
+impl error::Error for PassError {}
 
-impl fmt::Display for Error {
+impl fmt::Display for PassError {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use self::Error::*;
+        match self.stage {
+            Some(stage) => write!(
+                fmt,
+                "Pass {} failed to create its {:?} shader module: {}",
+                self.pass, stage, self.cause
+            ),
+            None => write!(
+                fmt,
+                "Pass {} failed to build its pipelines: {}",
+                self.pass, self.cause
+            ),
+        }
+    }
+}
+
+/// Resource holding the error of the last build of the render graph, e.g. a [PassError].
+///
+/// Nothing is rendered while the graph fails to build. The `RenderingSystem` logs the error and
+/// tries again when its `GraphCreator` asks for a rebuild, after which the error is cleared.
+#[derive(Debug, Default)]
+pub struct RenderGraphError(Option<failure::Error>);
+
+impl RenderGraphError {
+    /// Error of the last build of the render graph, `None` when it succeeded.
+    pub fn error(&self) -> Option<&failure::Error> {
+        self.0.as_ref()
+    }
+
+    pub(crate) fn set(&mut self, error: Option<failure::Error>) {
+        self.0 = error;
+    }
+}
 
-        match *self {
-            LoadSpritesheetError(..) => write!(fmt, "Failed to parse SpriteSheet"),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use failure::format_err;
+
+    #[test]
+    fn pass_error_names_the_pass_and_stage() {
+        let error = PassError::shader(
+            "DrawShaded",
+            ShaderStageFlags::FRAGMENT,
+            format_err!("out of memory"),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Pass DrawShaded failed to create its FRAGMENT shader module: out of memory"
+        );
+        let error = PassError::pipelines("DrawSkybox", format_err!("out of memory"));
+        assert_eq!(error.stage(), None);
+        assert_eq!(
+            error.to_string(),
+            "Pass DrawSkybox failed to build its pipelines: out of memory"
+        );
+    }
+
+    #[test]
+    fn render_errors_name_what_failed() {
+        let error = RenderError::MissingTarget {
+            target: Target::Custom("scene"),
+            plugin: "RenderHiZ",
+        };
+        assert_eq!(
+            error.to_string(),
+            "Target Custom(\"scene\") must be defined before adding RenderHiZ plugin"
+        );
+
+        let log = "shader.frag:12: error: 'albedo' : undeclared identifier";
+        let error = RenderError::from(ShaderError::new("shaded.frag", log));
+        let message = error.to_string();
+        assert!(message.starts_with("Shader shaded.frag failed to compile"));
+        assert!(message.contains(log), "{}", message);
+
+        let error = RenderError::from(TextureError::new("logo.png", "unexpected end of file"));
+        assert_eq!(
+            error.to_string(),
+            "Texture logo.png failed to load: unexpected end of file"
+        );
+        let source = error::Error::source(&error).and_then(error::Error::source);
+        assert_eq!(source.unwrap().to_string(), "unexpected end of file");
+    }
+
+    #[test]
+    fn render_errors_convert_with_the_question_mark() {
+        fn plugin() -> Result<(), amethyst_error::Error> {
+            Err(RenderError::MissingTarget {
+                target: Target::Main,
+                plugin: "RenderTransitions",
+            })?;
+            Ok(())
         }
+
+        fn group() -> Result<(), failure::Error> {
+            Err(RenderError::from(ShaderError::new("blit.frag", "")))?;
+            Ok(())
+        }
+
+        assert!(plugin()
+            .unwrap_err()
+            .to_string()
+            .contains("before adding RenderTransitions"));
+        assert!(group().unwrap_err().to_string().contains("blit.frag"));
     }
 }
@@ -6,10 +6,15 @@ use crate::{
 use amethyst_assets::{
     AssetPrefab, AssetStorage, Format, Handle, Loader, PrefabData, ProgressCounter,
 };
-use amethyst_core::ecs::{Entity, Read, ReadExpect, WriteStorage};
-use amethyst_error::Error;
-use rendy::mesh::MeshBuilder;
+use amethyst_core::{
+    ecs::{Entity, Read, ReadExpect, WriteStorage},
+    math::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3},
+};
+use amethyst_error::{format_err, Error};
+use rendy::mesh::{MeshBuilder, Normal, Position, Tangent, TexCoord};
 use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use wavefront_obj::obj;
 
 /// 'Obj' mesh format `Format` implementation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
@@ -24,16 +29,381 @@ impl Format<MeshData> for ObjFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<MeshData, Error> {
-        rendy::mesh::obj::load_from_obj(&bytes)
-            .map(|mut builders| {
-                let mut iter = builders.drain(..);
-                let builder = iter.next().unwrap();
-                if iter.next().is_some() {
-                    log::warn!("OBJ file contains more than one object, only loading the first");
-                }
-                builder.0.into()
+        load_obj(&bytes, &ImportTransform::IDENTITY).map(ObjMesh::into_mesh_data)
+    }
+}
+
+/// 'Obj' mesh format `Format` implementation converting the mesh from the coordinate system and
+/// units of the source with an `ImportTransform`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformedObjFormat {
+    /// Conversion applied to the loaded mesh.
+    pub transform: ImportTransform,
+}
+
+amethyst_assets::register_format!("TransformedOBJ", TransformedObjFormat as MeshData);
+impl Format<MeshData> for TransformedObjFormat {
+    fn name(&self) -> &'static str {
+        "TransformedOBJ"
+    }
+
+    fn import_simple(&self, bytes: Vec<u8>) -> Result<MeshData, Error> {
+        load_obj(&bytes, &self.transform).map(ObjMesh::into_mesh_data)
+    }
+}
+
+/// Load the first object of an OBJ file as an indexed mesh, with the index range and material
+/// name of every geometry, which OBJ files start with `usemtl`.
+///
+/// Map the names to material handles to draw the mesh with `SubMeshes`.
+pub fn load_obj_sub_meshes(
+    bytes: &[u8],
+    transform: &ImportTransform,
+) -> Result<(MeshData, Vec<(Range<u32>, Option<String>)>), Error> {
+    let mesh = load_obj(bytes, transform)?;
+    let indices = (0..mesh.positions.len() as u32).collect::<Vec<_>>();
+    let groups = mesh.groups.clone();
+    Ok((mesh.builder().with_indices(indices).into(), groups))
+}
+
+/// Vertex data of the first object of an OBJ file, one vertex per triangle corner.
+#[derive(Debug)]
+struct ObjMesh {
+    positions: Vec<Position>,
+    normals: Vec<Normal>,
+    tex_coords: Vec<TexCoord>,
+    /// Vertices and material name of every geometry.
+    groups: Vec<(Range<u32>, Option<String>)>,
+}
+
+impl ObjMesh {
+    fn builder(self) -> MeshBuilder<'static> {
+        MeshBuilder::new()
+            .with_vertices(self.positions)
+            .with_vertices(self.normals)
+            .with_vertices(self.tex_coords)
+    }
+
+    fn into_mesh_data(self) -> MeshData {
+        self.builder().into()
+    }
+}
+
+fn load_obj(bytes: &[u8], transform: &ImportTransform) -> Result<ObjMesh, Error> {
+    transform.check()?;
+    let string = std::str::from_utf8(bytes).map_err(|e| Error::from_string(e.to_string()))?;
+    let set = obj::parse(string).map_err(|e| {
+        format_err!(
+            "Error during parsing obj-file at line '{}': {}",
+            e.line_number,
+            e.message
+        )
+    })?;
+
+    let object = set
+        .objects
+        .iter()
+        .find(|object| !object.geometry.is_empty())
+        .ok_or_else(|| format_err!("OBJ file contains no geometry"))?;
+    if set
+        .objects
+        .iter()
+        .filter(|o| !o.geometry.is_empty())
+        .count()
+        > 1
+    {
+        log::warn!("OBJ file contains more than one object, only loading the first");
+    }
+
+    let mut corners = Vec::new();
+    let mut groups = Vec::new();
+    for geometry in &object.geometry {
+        let start = corners.len() as u32;
+        for shape in &geometry.shapes {
+            if let obj::Primitive::Triangle(v1, v2, v3) = shape.primitive {
+                corners.extend_from_slice(&[v1, v2, v3]);
+            }
+        }
+        groups.push((start..corners.len() as u32, geometry.material_name.clone()));
+    }
+    transform.flip_triangles(&mut corners);
+
+    // Normals are per face in most OBJ files, so vertices are not shared between triangles.
+    Ok(ObjMesh {
+        positions: corners
+            .iter()
+            .map(|&(v, _, _)| {
+                let v = object.vertices[v];
+                Position(transform.position([v.x as f32, v.y as f32, v.z as f32]))
+            })
+            .collect(),
+        normals: corners
+            .iter()
+            .map(|&(_, _, n)| {
+                Normal(n.map_or([0.0; 3], |n| {
+                    let n = object.normals[n];
+                    transform.direction([n.x as f32, n.y as f32, n.z as f32])
+                }))
+            })
+            .collect(),
+        tex_coords: corners
+            .iter()
+            .map(|&(_, t, _)| {
+                TexCoord(t.map_or([0.0; 2], |t| {
+                    let t = object.tex_vertices[t];
+                    [t.u as f32, t.v as f32]
+                }))
             })
-            .map_err(|e| e.compat().into())
+            .collect(),
+        groups,
+    })
+}
+
+/// Signed axis of the coordinate system of imported data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Axis {
+    /// Positive X.
+    X,
+    /// Positive Y.
+    Y,
+    /// Positive Z.
+    Z,
+    /// Negative X.
+    NegX,
+    /// Negative Y.
+    NegY,
+    /// Negative Z.
+    NegZ,
+}
+
+impl Axis {
+    fn new(index: usize, sign: f32) -> Self {
+        match (index, sign < 0.0) {
+            (0, false) => Axis::X,
+            (1, false) => Axis::Y,
+            (2, false) => Axis::Z,
+            (0, true) => Axis::NegX,
+            (1, true) => Axis::NegY,
+            _ => Axis::NegZ,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Axis::X | Axis::NegX => 0,
+            Axis::Y | Axis::NegY => 1,
+            Axis::Z | Axis::NegZ => 2,
+        }
+    }
+
+    fn sign(self) -> f32 {
+        match self {
+            Axis::X | Axis::Y | Axis::Z => 1.0,
+            _ => -1.0,
+        }
+    }
+}
+
+/// Conversion of imported data from the coordinate system and units of its source to the ones
+/// of the engine: right handed, Y up and in meters.
+///
+/// Loaders taking an `ImportTransform` bake it into the vertex data, converting positions,
+/// normals and tangents and reordering triangles when `flip_winding` is set. Meshes built by hand
+/// can be converted the same way before handing their vertices to a `MeshBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportTransform {
+    /// Source axes becoming the X, Y and Z axes of the engine. Must use every axis once.
+    pub axes: [Axis; 3],
+    /// Uniform scale from the source unit to meters.
+    pub scale: f32,
+    /// Reverse the corner order of every triangle, for sources with clockwise front faces in the
+    /// same handedness as the engine.
+    pub flip_winding: bool,
+}
+
+impl Default for ImportTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl ImportTransform {
+    /// Source already in the conventions of the engine.
+    pub const IDENTITY: ImportTransform = ImportTransform {
+        axes: [Axis::X, Axis::Y, Axis::Z],
+        scale: 1.0,
+        flip_winding: false,
+    };
+
+    /// Blender's right handed Z up coordinate system, with -Y as forward.
+    pub const BLENDER_Z_UP: ImportTransform = ImportTransform {
+        axes: [Axis::X, Axis::Z, Axis::NegY],
+        scale: 1.0,
+        flip_winding: false,
+    };
+
+    /// 3ds Max's right handed Z up coordinate system, in the common centimeter unit setup.
+    pub const MAX_Z_UP_CM: ImportTransform = ImportTransform {
+        axes: [Axis::X, Axis::Z, Axis::NegY],
+        scale: 0.01,
+        flip_winding: false,
+    };
+
+    /// Y up source in centimeters.
+    pub const CENTIMETERS: ImportTransform = ImportTransform {
+        axes: [Axis::X, Axis::Y, Axis::Z],
+        scale: 0.01,
+        flip_winding: false,
+    };
+
+    /// Left handed Y up coordinate system with clockwise front faces, as used by Direct3D tools.
+    /// Mirroring the Z axis also turns the clockwise faces counter-clockwise.
+    pub const LEFT_HANDED_Y_UP: ImportTransform = ImportTransform {
+        axes: [Axis::X, Axis::Y, Axis::NegZ],
+        scale: 1.0,
+        flip_winding: false,
+    };
+
+    /// Returns this transform with given scale from the source unit to meters.
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Returns this transform with triangle winding flipped or not.
+    pub fn with_flipped_winding(mut self, flip_winding: bool) -> Self {
+        self.flip_winding = flip_winding;
+        self
+    }
+
+    /// Check that the axes are a permutation and the scale is positive.
+    pub fn check(&self) -> Result<(), Error> {
+        let mut used = [false; 3];
+        for axis in &self.axes {
+            if std::mem::replace(&mut used[axis.index()], true) {
+                return Err(format_err!(
+                    "Import transform axes {:?} use an axis more than once",
+                    self.axes
+                ));
+            }
+        }
+        if !(self.scale.is_finite() && self.scale > 0.0) {
+            return Err(format_err!(
+                "Import transform scale must be positive, not {}",
+                self.scale
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check if this transform doesn't change anything.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Check if the axis remap mirrors the source, changing its handedness.
+    pub fn is_mirroring(&self) -> bool {
+        self.axis_matrix().determinant() < 0.0
+    }
+
+    /// Rotation or reflection remapping the source axes, without scale.
+    pub fn axis_matrix(&self) -> Matrix3<f32> {
+        let mut matrix = Matrix3::zeros();
+        for (row, axis) in self.axes.iter().enumerate() {
+            matrix[(row, axis.index())] = axis.sign();
+        }
+        matrix
+    }
+
+    /// The transform converting back to the source conventions.
+    pub fn inverse(&self) -> ImportTransform {
+        let mut axes = self.axes;
+        for (row, axis) in self.axes.iter().enumerate() {
+            axes[axis.index()] = Axis::new(row, axis.sign());
+        }
+        ImportTransform {
+            axes,
+            scale: 1.0 / self.scale,
+            flip_winding: self.flip_winding,
+        }
+    }
+
+    /// Convert a position or translation.
+    pub fn position(&self, position: [f32; 3]) -> [f32; 3] {
+        (self.axis_matrix() * Vector3::from(position) * self.scale).into()
+    }
+
+    /// Convert a direction such as a normal. Uniform scale keeps directions unchanged.
+    pub fn direction(&self, direction: [f32; 3]) -> [f32; 3] {
+        (self.axis_matrix() * Vector3::from(direction)).into()
+    }
+
+    /// Convert a tangent, flipping its bitangent sign when the transform mirrors.
+    pub fn tangent(&self, tangent: [f32; 4]) -> [f32; 4] {
+        let [x, y, z] = self.direction([tangent[0], tangent[1], tangent[2]]);
+        let w = if self.is_mirroring() {
+            -tangent[3]
+        } else {
+            tangent[3]
+        };
+        [x, y, z, w]
+    }
+
+    /// Convert the rotation of a node transform.
+    pub fn rotation(&self, rotation: UnitQuaternion<f32>) -> UnitQuaternion<f32> {
+        let axes = self.axis_matrix();
+        let matrix = axes * rotation.to_rotation_matrix().into_inner() * axes.transpose();
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(matrix))
+    }
+
+    /// Convert the per axis scale of a node transform.
+    pub fn scaling(&self, scale: [f32; 3]) -> [f32; 3] {
+        let mut out = [0.0; 3];
+        for (out, axis) in out.iter_mut().zip(&self.axes) {
+            *out = scale[axis.index()];
+        }
+        out
+    }
+
+    /// Convert a matrix mapping between spaces in source conventions, e.g. an inverse bind
+    /// matrix, to the same mapping between the converted spaces.
+    pub fn matrix(&self, matrix: &Matrix4<f32>) -> Matrix4<f32> {
+        let axes = self.axis_matrix();
+        let forward = (axes * self.scale).to_homogeneous();
+        let backward = (axes.transpose() / self.scale).to_homogeneous();
+        forward * matrix * backward
+    }
+
+    /// Reverse the corner order of every triangle of a triangle list if `flip_winding` is set.
+    pub fn flip_triangles<I>(&self, indices: &mut [I]) {
+        if self.flip_winding {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+
+    /// Convert positions in place.
+    pub fn bake_positions(&self, positions: &mut [Position]) {
+        for p in positions {
+            p.0 = self.position(p.0);
+        }
+    }
+
+    /// Convert normals in place.
+    pub fn bake_normals(&self, normals: &mut [Normal]) {
+        for n in normals {
+            n.0 = self.direction(n.0);
+        }
+    }
+
+    /// Convert tangents in place.
+    pub fn bake_tangents(&self, tangents: &mut [Tangent]) {
+        for t in tangents {
+            t.0 = self.tangent(t.0);
+        }
     }
 }
 
@@ -94,3 +464,146 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::Point3;
+    use approx::{assert_relative_eq, relative_eq};
+
+    /// Quad in the XZ plane facing up, in engine conventions.
+    fn quad() -> Vec<[f32; 3]> {
+        vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 0.0, -1.0],
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, -1.0],
+            [0.0, 0.0, -1.0],
+        ]
+    }
+
+    fn face_normal(p: &[[f32; 3]]) -> Vector3<f32> {
+        let (a, b, c) = (
+            Vector3::from(p[0]),
+            Vector3::from(p[1]),
+            Vector3::from(p[2]),
+        );
+        (b - a).cross(&(c - a)).normalize()
+    }
+
+    /// Write positions in given conventions as OBJ with an up facing normal.
+    fn quad_obj(transform: &ImportTransform) -> String {
+        let source = transform.inverse();
+        let mut obj = String::new();
+        let mut corners = quad();
+        source.flip_triangles(&mut corners);
+        for p in &corners {
+            let [x, y, z] = source.position(*p);
+            obj.push_str(&format!("v {} {} {}\n", x, y, z));
+        }
+        let [x, y, z] = source.direction([0.0, 1.0, 0.0]);
+        obj.push_str(&format!("vn {} {} {}\n", x, y, z));
+        obj.push_str("f 1//1 2//1 3//1\nf 4//1 5//1 6//1\n");
+        obj
+    }
+
+    #[test]
+    fn presets_round_trip_obj() {
+        let presets = [
+            ImportTransform::IDENTITY,
+            ImportTransform::BLENDER_Z_UP,
+            ImportTransform::MAX_Z_UP_CM,
+            ImportTransform::CENTIMETERS,
+            ImportTransform::LEFT_HANDED_Y_UP,
+            ImportTransform::BLENDER_Z_UP.with_flipped_winding(true),
+        ];
+        for preset in &presets {
+            preset.check().unwrap();
+            let mesh = load_obj(quad_obj(preset).as_bytes(), preset).unwrap();
+            // The parser rotates the corners of a triangle, but keeps their winding.
+            let positions = mesh.positions.iter().map(|p| p.0).collect::<Vec<_>>();
+            for (loaded, expected) in positions.chunks(3).zip(quad().chunks(3)) {
+                assert!(
+                    (0..3).any(|r| (0..3).all(|i| {
+                        relative_eq!(
+                            Vector3::from(loaded[(i + r) % 3]),
+                            Vector3::from(expected[i]),
+                            epsilon = 1e-5
+                        )
+                    })),
+                    "{:?}: {:?} is not {:?}",
+                    preset,
+                    loaded,
+                    expected
+                );
+            }
+            for normal in &mesh.normals {
+                assert_relative_eq!(Vector3::from(normal.0), Vector3::y(), epsilon = 1e-5);
+            }
+            // Culling keeps working: the winding still agrees with the normal.
+            assert_relative_eq!(face_normal(&positions), Vector3::y(), epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn blender_z_up_to_y_up() {
+        let t = ImportTransform::MAX_Z_UP_CM;
+        assert_eq!(t.position([0.0, 0.0, 100.0]), [0.0, 1.0, 0.0]);
+        assert_eq!(t.position([0.0, -100.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert_eq!(t.scaling([1.0, 2.0, 3.0]), [1.0, 3.0, 2.0]);
+        assert!(!t.is_mirroring());
+        assert!(ImportTransform::LEFT_HANDED_Y_UP.is_mirroring());
+        assert_eq!(
+            ImportTransform::LEFT_HANDED_Y_UP.tangent([1.0, 0.0, 0.0, 1.0]),
+            [1.0, 0.0, 0.0, -1.0]
+        );
+        assert!(ImportTransform {
+            axes: [Axis::X, Axis::NegX, Axis::Z],
+            ..Default::default()
+        }
+        .check()
+        .is_err());
+    }
+
+    #[test]
+    fn node_transforms_follow_vertices() {
+        let t = ImportTransform::MAX_Z_UP_CM;
+        let rotation = UnitQuaternion::from_euler_angles(0.3, -1.2, 0.7);
+        let translation = [10.0, -20.0, 30.0];
+        let scale = [1.0, 2.0, 3.0];
+        let local = Matrix4::new_translation(&Vector3::from(translation))
+            * rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::from(scale));
+        let converted = Matrix4::new_translation(&Vector3::from(t.position(translation)))
+            * t.rotation(rotation).to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::from(t.scaling(scale)));
+
+        let vertex = [4.0, 5.0, -6.0];
+        let expected = t.position(local.transform_point(&Point3::from(vertex)).coords.into());
+        let actual = converted.transform_point(&Point3::from(t.position(vertex)));
+        assert_relative_eq!(actual.coords, Vector3::from(expected), epsilon = 1e-4);
+        assert_relative_eq!(t.matrix(&local), converted, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn obj_material_groups_are_sub_meshes() {
+        let mut obj = String::new();
+        for p in &quad() {
+            obj.push_str(&format!("v {} {} {}\n", p[0], p[1], p[2]));
+        }
+        obj.push_str("usemtl red\nf 1 2 3\nusemtl blue\nf 4 5 6\nf 1 2 3\n");
+
+        let mesh = load_obj(obj.as_bytes(), &ImportTransform::IDENTITY).unwrap();
+        assert_eq!(mesh.positions.len(), 9);
+        assert_eq!(
+            mesh.groups,
+            vec![
+                (0..3, Some("red".to_string())),
+                (3..9, Some("blue".to_string()))
+            ]
+        );
+        let (_, groups) = load_obj_sub_meshes(obj.as_bytes(), &ImportTransform::IDENTITY).unwrap();
+        assert_eq!(groups, mesh.groups);
+    }
+}
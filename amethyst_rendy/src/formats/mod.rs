@@ -1,6 +1,7 @@
 //! Pre-defined graphical formats and data provided by amethyst_rendy
 pub mod mesh;
 pub mod mtl;
+pub mod scene;
 pub mod texture;
 
 use self::{mesh::MeshPrefab, mtl::MaterialPrefab};
@@ -2,7 +2,12 @@
 
 use crate::{
     formats::texture::TexturePrefab,
-    mtl::{Material, MaterialDefaults, TextureOffset},
+    material_shader::MAX_MATERIAL_SHADER_PARAMS,
+    mtl::{
+        DetailBlend, Material, MaterialDefaults, TextureOffset, UvTransform, DETAIL_SCALE,
+        DISSOLVE_EDGE_COLOR, DISSOLVE_EDGE_WIDTH, LAYER_COLOR, LAYER_ROUGHNESS, SHININESS,
+        TRIPLANAR_SHARPNESS,
+    },
     transparent::Transparent,
     types::Texture,
 };
@@ -12,7 +17,7 @@ use amethyst_error::Error;
 use serde::{Deserialize, Serialize};
 
 /// `PrefabData` for loading `Material`s
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(default, bound = "")]
 pub struct MaterialPrefab {
     /// Diffuse map.
@@ -27,12 +32,58 @@ pub struct MaterialPrefab {
     pub ambient_occlusion: Option<TexturePrefab>,
     /// Cavity map.
     pub cavity: Option<TexturePrefab>,
+    /// Noise map of the dissolve.
+    pub dissolve_noise: Option<TexturePrefab>,
+    /// Detail map.
+    pub detail: Option<TexturePrefab>,
+    /// Mask of the surface layer.
+    pub layer_mask: Option<TexturePrefab>,
     /// Texture offset.
     pub uv_offset: TextureOffset,
     /// Set material as `Transparent`
     pub transparent: bool,
     /// Alpha cutoff: the value below which we do not draw the pixel
     pub alpha_cutoff: f32,
+    /// Linear RGB factor of the emission map.
+    pub emission_factor: [f32; 3],
+    /// Transform of the texture coordinates of every map but the normal map.
+    pub uv_transform: UvTransform,
+    /// Transform of the texture coordinates of the normal map.
+    pub normal_uv_transform: UvTransform,
+    /// Dissolve amount in [0, 1].
+    pub dissolve_amount: f32,
+    /// Width of the glowing edge of the dissolve.
+    pub dissolve_edge_width: f32,
+    /// Linear RGB color emitted by the edge of the dissolve.
+    pub dissolve_edge_color: [f32; 3],
+    /// Repeats of the maps per world unit when triplanar mapped, 0 to use texture coordinates.
+    pub triplanar_scale: f32,
+    /// Sharpness of the blend between the triplanar projections.
+    pub triplanar_sharpness: f32,
+    /// Repeats of the detail map per repeat of the other maps.
+    pub detail_scale: f32,
+    /// Blending of the detail map over the albedo.
+    pub detail_blend: DetailBlend,
+    /// View distance at which the detail map has faded out, 0 to not use it.
+    pub detail_fade_distance: f32,
+    /// Albedo factor under the surface layer.
+    pub layer_color: [f32; 3],
+    /// Roughness under the surface layer.
+    pub layer_roughness: f32,
+    /// Amount of the surface layer of the material alone.
+    pub layer_amount: f32,
+    /// Fraction of the scene surface layer amount added to `layer_amount`.
+    pub layer_scene_factor: f32,
+    /// Light channels of the material, every channel by default.
+    pub light_channels: u32,
+    /// Strength of the subsurface scattering of the material.
+    pub subsurface: f32,
+    /// Linear RGB color of the specular highlights of the material.
+    pub specular: [f32; 3],
+    /// Exponent of the specular highlights of the material.
+    pub shininess: f32,
+    /// Values of the parameters of the material shader of the passes drawing the material.
+    pub shader_params: [[f32; 4]; MAX_MATERIAL_SHADER_PARAMS],
     /// Clone handle only
     #[serde(skip)]
     handle: Option<Handle<Material>>,
@@ -49,6 +100,11 @@ impl MaterialPrefab {
             ..Self::default()
         }
     }
+
+    /// Handle of the material, once its sub assets are loaded.
+    pub fn handle(&self) -> Option<&Handle<Material>> {
+        self.handle.as_ref()
+    }
 }
 
 impl Default for MaterialPrefab {
@@ -60,9 +116,32 @@ impl Default for MaterialPrefab {
             metallic_roughness: None,
             ambient_occlusion: None,
             cavity: None,
+            dissolve_noise: None,
+            detail: None,
+            layer_mask: None,
             uv_offset: TextureOffset::default(),
             transparent: false,
             alpha_cutoff: std::f32::MIN_POSITIVE,
+            emission_factor: [1.0; 3],
+            uv_transform: UvTransform::default(),
+            normal_uv_transform: UvTransform::default(),
+            dissolve_amount: 0.0,
+            dissolve_edge_width: DISSOLVE_EDGE_WIDTH,
+            dissolve_edge_color: DISSOLVE_EDGE_COLOR,
+            triplanar_scale: 0.0,
+            triplanar_sharpness: TRIPLANAR_SHARPNESS,
+            detail_scale: DETAIL_SCALE,
+            detail_blend: DetailBlend::default(),
+            detail_fade_distance: 0.0,
+            layer_color: LAYER_COLOR,
+            layer_roughness: LAYER_ROUGHNESS,
+            layer_amount: 0.0,
+            layer_scene_factor: 0.0,
+            light_channels: !0,
+            subsurface: 0.0,
+            specular: [0.0; 3],
+            shininess: SHININESS,
+            shader_params: [[0.0; 4]; MAX_MATERIAL_SHADER_PARAMS],
             handle: None,
         }
     }
@@ -141,6 +220,21 @@ impl<'a> PrefabData<'a> for MaterialPrefab {
                 ret = true;
             }
         }
+        if let Some(ref mut texture) = self.dissolve_noise {
+            if texture.load_sub_assets(progress, tp_data)? {
+                ret = true;
+            }
+        }
+        if let Some(ref mut texture) = self.detail {
+            if texture.load_sub_assets(progress, tp_data)? {
+                ret = true;
+            }
+        }
+        if let Some(ref mut texture) = self.layer_mask {
+            if texture.load_sub_assets(progress, tp_data)? {
+                ret = true;
+            }
+        }
 
         if self.handle.is_none() {
             let mtl = Material {
@@ -158,6 +252,29 @@ impl<'a> PrefabData<'a> for MaterialPrefab {
                 cavity: load_handle(&self.cavity, &mat_default.0.cavity),
                 uv_offset: self.uv_offset.clone(),
                 alpha_cutoff: self.alpha_cutoff,
+                emission_factor: self.emission_factor,
+                uv_transform: self.uv_transform,
+                normal_uv_transform: self.normal_uv_transform,
+                dissolve_noise: load_handle(&self.dissolve_noise, &mat_default.0.dissolve_noise),
+                dissolve_amount: self.dissolve_amount,
+                dissolve_edge_width: self.dissolve_edge_width,
+                dissolve_edge_color: self.dissolve_edge_color,
+                triplanar_scale: self.triplanar_scale,
+                triplanar_sharpness: self.triplanar_sharpness,
+                detail: load_handle(&self.detail, &mat_default.0.detail),
+                detail_scale: self.detail_scale,
+                detail_blend: self.detail_blend,
+                detail_fade_distance: self.detail_fade_distance,
+                layer_mask: load_handle(&self.layer_mask, &mat_default.0.layer_mask),
+                layer_color: self.layer_color,
+                layer_roughness: self.layer_roughness,
+                layer_amount: self.layer_amount,
+                layer_scene_factor: self.layer_scene_factor,
+                light_channels: self.light_channels,
+                subsurface: self.subsurface,
+                specular: self.specular,
+                shininess: self.shininess,
+                shader_params: self.shader_params,
             };
 
             self.handle
@@ -0,0 +1,617 @@
+//! Scenes described in RON, e.g. for test fixtures and editors.
+//!
+//! A scene is a `Prefab<ScenePrefab>`, loaded with a `PrefabLoader` and `RonFormat` like any
+//! other prefab, and written back from the world with [save_scene]:
+//!
+//! ```ron
+//! #![enable(implicit_some)]
+//! Prefab(
+//!     entities: [
+//!         (data: (environment: (ambient_color: (Srgba(0.1, 0.1, 0.1, 1.0))))),
+//!         (
+//!             data: (
+//!                 name: (name: "floor"),
+//!                 transform: (translation: (0.0, -1.0, 0.0)),
+//!                 mesh: Shape(shape: Plane(None), scale: (10.0, 10.0, 1.0)),
+//!                 material: (albedo: Generate(Srgba(0.8, 0.8, 0.8, 1.0))),
+//!             ),
+//!         ),
+//!         (data: (mesh: File("mesh/teapot.obj", ("OBJ", ())))),
+//!         (data: (light: Point((intensity: 10.0)), transform: (translation: (0.0, 4.0, 0.0)))),
+//!     ],
+//! )
+//! ```
+//!
+//! GPU resources aren't serialized, scenes reference their assets instead: meshes by the path
+//! and format of their file in the asset directory or as a generated `Shape`, textures with a
+//! `TexturePrefab`. Loading resolves the references through the `Loader`. Every entity loaded
+//! from a scene keeps the references it was loaded from in a [SceneSource] component, which
+//! [save_scene] writes back. Meshes and materials created in code have no references, and are
+//! left out of saved scenes.
+//!
+//! Small groups of entities used many times, e.g. a streetlight with its mesh, light and blob
+//! shadow, are scenes of their own: spawn them with [instantiate_scene], or reference their file
+//! with the `scene` of an entity to nest them under it. Both load the scene into a new child
+//! entity, so its main entity is placed relative to the instance. Like in every prefab, the
+//! other entities of the scene belong to the instance when they have a parent in it, e.g.
+//! `parent: 0`. Register a `PrefabLoaderSystemDesc::<ScenePrefab>` to spawn scenes.
+
+use crate::{
+    blob_shadow::BlobShadow,
+    camera::{Camera, CameraPrefab},
+    formats::mtl::MaterialPrefab,
+    light::Light,
+    pass::SkyboxSettings,
+    resources::{AmbientColor, FogSettings},
+    shape::Shape,
+    types::{Mesh, MeshData},
+};
+use amethyst_assets::{
+    AssetStorage, Handle, Loader, Prefab, PrefabData, PrefabLoader, ProgressCounter, RonFormat,
+    SerializableFormat,
+};
+use amethyst_core::{
+    ecs::{
+        error::WrongGeneration, BitSet, Builder, Component, DenseVecStorage, Entities, Entity,
+        Join, LazyUpdate, Read, ReadExpect, ReadStorage, SystemData, World, WorldExt, WriteStorage,
+    },
+    Named, Parent, Transform,
+};
+use amethyst_error::Error;
+use fnv::FnvHashMap;
+use rendy::mesh::{Normal, Position, Tangent, TexCoord};
+use serde::{Deserialize, Serialize};
+
+/// Vertex format of the meshes generated from a `MeshReference::Shape`, with every attribute
+/// used by the 3D passes.
+pub type SceneShapeVertices = (Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>);
+
+/// Reference of a scene to a mesh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MeshReference {
+    /// Mesh file in the asset directory, with its format.
+    File(String, Box<dyn SerializableFormat<MeshData>>),
+    /// Generated shape, with the vertex format `SceneShapeVertices`.
+    Shape {
+        /// Generated shape.
+        shape: Shape,
+        /// Scale of the shape along every axis.
+        #[serde(default)]
+        scale: Option<(f32, f32, f32)>,
+    },
+}
+
+/// References an entity was loaded from by a `ScenePrefab`, saved back by `save_scene`.
+#[derive(Debug, Clone)]
+pub struct SceneSource {
+    /// Mesh of the entity.
+    pub mesh: Option<MeshReference>,
+    /// Material of the entity, before its textures were loaded.
+    pub material: Option<MaterialPrefab>,
+    /// Scene nested under the entity.
+    pub scene: Option<String>,
+}
+
+impl Component for SceneSource {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Scene wide settings, replacing the resources of the world when the scene is loaded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneEnvironment {
+    /// Ambient lighting of the scene.
+    pub ambient_color: Option<AmbientColor>,
+    /// Fog of the scene.
+    pub fog: Option<FogSettings>,
+    /// Background colors of the scene, drawn by the skybox.
+    pub sky: Option<SkyboxSettings>,
+}
+
+/// `PrefabData` of an entity of a scene, see the [module documentation](self).
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScenePrefab {
+    /// Name of the entity.
+    pub name: Option<Named>,
+    /// Transform of the entity, relative to its parent.
+    pub transform: Option<Transform>,
+    /// Mesh of the entity.
+    pub mesh: Option<MeshReference>,
+    /// Material of the entity.
+    pub material: Option<MaterialPrefab>,
+    /// Light of the entity.
+    pub light: Option<Light>,
+    /// Camera of the entity.
+    pub camera: Option<CameraPrefab>,
+    /// Blob shadow of the entity.
+    pub blob_shadow: Option<BlobShadow>,
+    /// Scene file in the asset directory nested under the entity. Nested scenes must not
+    /// reference the scenes they are nested in.
+    pub scene: Option<String>,
+    /// Settings of the scene, usually on the main entity of the prefab.
+    pub environment: Option<SceneEnvironment>,
+    #[serde(skip)]
+    mesh_handle: Option<Handle<Mesh>>,
+    #[serde(skip)]
+    source: Option<SceneSource>,
+}
+
+impl<'a> PrefabData<'a> for ScenePrefab {
+    type SystemData = (
+        (
+            ReadExpect<'a, Loader>,
+            Read<'a, AssetStorage<Mesh>>,
+            WriteStorage<'a, Handle<Mesh>>,
+        ),
+        <MaterialPrefab as PrefabData<'a>>::SystemData,
+        (
+            WriteStorage<'a, Named>,
+            WriteStorage<'a, Transform>,
+            WriteStorage<'a, Light>,
+            <CameraPrefab as PrefabData<'a>>::SystemData,
+            WriteStorage<'a, BlobShadow>,
+            WriteStorage<'a, SceneSource>,
+        ),
+        Read<'a, LazyUpdate>,
+    );
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        (mesh_data, material_data, storages, lazy): &mut Self::SystemData,
+        entities: &[Entity],
+        children: &[Entity],
+    ) -> Result<(), Error> {
+        let (names, transforms, lights, cameras, blob_shadows, sources) = storages;
+        if let Some(name) = &self.name {
+            names.insert(entity, name.clone())?;
+        }
+        if let Some(transform) = &self.transform {
+            transforms.insert(entity, transform.clone())?;
+        }
+        if let Some(mesh) = &self.mesh_handle {
+            mesh_data.2.insert(entity, mesh.clone())?;
+        }
+        if let Some(material) = &self.material {
+            material.add_to_entity(entity, material_data, entities, children)?;
+        }
+        if let Some(light) = &self.light {
+            lights.insert(entity, light.clone())?;
+        }
+        if let Some(camera) = &self.camera {
+            camera.add_to_entity(entity, cameras, entities, children)?;
+        }
+        if let Some(blob_shadow) = &self.blob_shadow {
+            blob_shadows.insert(entity, *blob_shadow)?;
+        }
+        if let Some(source) = &self.source {
+            sources.insert(entity, source.clone())?;
+        }
+        if let Some(path) = self.scene.clone() {
+            lazy.exec_mut(move |world| {
+                let scene = load_scene(world, &path);
+                spawn_instance(world, entity, scene);
+            });
+        }
+        if let Some(environment) = self.environment.clone() {
+            lazy.exec_mut(move |world| {
+                if let Some(ambient_color) = environment.ambient_color {
+                    world.insert(ambient_color);
+                }
+                if let Some(fog) = environment.fog {
+                    world.insert(fog);
+                }
+                if let Some(sky) = environment.sky {
+                    world.insert(sky);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn load_sub_assets(
+        &mut self,
+        progress: &mut ProgressCounter,
+        (mesh_data, material_data, _, _): &mut Self::SystemData,
+    ) -> Result<bool, Error> {
+        if self.mesh.is_some() || self.material.is_some() || self.scene.is_some() {
+            self.source = Some(SceneSource {
+                mesh: self.mesh.clone(),
+                material: self.material.clone(),
+                scene: self.scene.clone(),
+            });
+        }
+
+        let (loader, storage, _) = mesh_data;
+        let mut ret = false;
+        if let Some(mesh) = &self.mesh {
+            self.mesh_handle = Some(match mesh {
+                MeshReference::File(path, format) => {
+                    loader.load(path.as_str(), format.clone(), &mut *progress, &**storage)
+                }
+                MeshReference::Shape { shape, scale } => loader.load_from_data(
+                    shape.generate::<SceneShapeVertices>(*scale).into(),
+                    &mut *progress,
+                    &**storage,
+                ),
+            });
+            ret = true;
+        }
+        if let Some(material) = &mut self.material {
+            if material.load_sub_assets(progress, material_data)? {
+                ret = true;
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Handles of the scenes nested in other scenes, by path.
+#[derive(Default)]
+struct NestedScenes(FnvHashMap<String, Handle<Prefab<ScenePrefab>>>);
+
+fn load_scene(world: &mut World, path: &str) -> Handle<Prefab<ScenePrefab>> {
+    let loaded = world
+        .entry::<NestedScenes>()
+        .or_insert_with(NestedScenes::default)
+        .0
+        .get(path)
+        .cloned();
+    loaded.unwrap_or_else(|| {
+        let scene =
+            world.exec(|loader: PrefabLoader<'_, ScenePrefab>| loader.load(path, RonFormat, ()));
+        world
+            .write_resource::<NestedScenes>()
+            .0
+            .insert(path.to_owned(), scene.clone());
+        scene
+    })
+}
+
+fn spawn_instance(world: &mut World, parent: Entity, scene: Handle<Prefab<ScenePrefab>>) {
+    world
+        .create_entity()
+        .with(Parent { entity: parent })
+        .with(Transform::default())
+        .with(scene)
+        .build();
+}
+
+/// Instance of a scene spawned by [instantiate_scene], removed as a unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneInstance {
+    root: Entity,
+}
+
+impl SceneInstance {
+    /// Entity placing the instance, which the entities of the scene are children of.
+    pub fn root(&self) -> Entity {
+        self.root
+    }
+
+    /// Every entity of the instance, the root first. The entities of the scene are only there
+    /// once the `PrefabLoaderSystem` has spawned it.
+    pub fn parts(&self, world: &World) -> Vec<Entity> {
+        let (entities, parents) = <(Entities<'_>, ReadStorage<'_, Parent>)>::fetch(world);
+        let mut parts = vec![self.root];
+        let mut members = BitSet::new();
+        members.add(self.root.id());
+        loop {
+            let count = parts.len();
+            for (entity, parent) in (&entities, &parents).join() {
+                if members.contains(parent.entity.id()) && !members.add(entity.id()) {
+                    parts.push(entity);
+                }
+            }
+            if parts.len() == count {
+                return parts;
+            }
+        }
+    }
+
+    /// Delete every entity of the instance, including its lights and nested scenes.
+    pub fn remove(self, world: &mut World) -> Result<(), WrongGeneration> {
+        let parts = self.parts(world);
+        world.delete_entities(&parts)
+    }
+}
+
+/// Spawn a scene, e.g. a streetlight, placed with given transform.
+///
+/// The `PrefabLoaderSystem` spawns the entities of the scene into a child of the root of the
+/// instance, once the scene and its assets are loaded.
+pub fn instantiate_scene(
+    world: &mut World,
+    scene: &Handle<Prefab<ScenePrefab>>,
+    transform: Transform,
+) -> SceneInstance {
+    let root = world.create_entity().with(transform).build();
+    spawn_instance(world, root, scene.clone());
+    SceneInstance { root }
+}
+
+/// Describe the scene of the world, to write it with e.g. `ron::ser::to_string_pretty`.
+///
+/// Entities with a `SceneSource`, a `Light`, a `Camera` or a `BlobShadow` are saved with their
+/// name and transform, under their parent if it is saved too. Scenes nested with the `scene` of
+/// an entity are saved as a reference, scenes spawned by `instantiate_scene` are saved with
+/// their entities. The main entity of the prefab holds the ambient color, fog and skybox
+/// settings of the world.
+pub fn save_scene(world: &World) -> Prefab<ScenePrefab> {
+    let (entities, names, transforms, parents, sources, lights, cameras, blob_shadows, meshes) =
+        <(
+            Entities<'_>,
+            ReadStorage<'_, Named>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Parent>,
+            ReadStorage<'_, SceneSource>,
+            ReadStorage<'_, Light>,
+            ReadStorage<'_, Camera>,
+            ReadStorage<'_, BlobShadow>,
+            ReadStorage<'_, Handle<Mesh>>,
+        )>::fetch(world);
+    let nested = |mut entity: Entity| {
+        while let Some(parent) = parents.get(entity) {
+            entity = parent.entity;
+            if sources.get(entity).and_then(|s| s.scene.as_ref()).is_some() {
+                return true;
+            }
+        }
+        false
+    };
+
+    let mut prefab = Prefab::new_main(ScenePrefab {
+        environment: Some(SceneEnvironment {
+            ambient_color: world.try_fetch::<AmbientColor>().map(|a| (*a).clone()),
+            fog: world.try_fetch::<FogSettings>().map(|f| (*f).clone()),
+            sky: world.try_fetch::<SkyboxSettings>().map(|s| (*s).clone()),
+        }),
+        ..Default::default()
+    });
+
+    let mut indices = FnvHashMap::default();
+    let mut unreferenced = 0;
+    for (entity, source, light, camera, blob_shadow) in (
+        &entities,
+        sources.maybe(),
+        lights.maybe(),
+        cameras.maybe(),
+        blob_shadows.maybe(),
+    )
+        .join()
+    {
+        if source.is_none() && light.is_none() && camera.is_none() && blob_shadow.is_none()
+            || nested(entity)
+        {
+            continue;
+        }
+        if source.and_then(|s| s.mesh.as_ref()).is_none() && meshes.contains(entity) {
+            unreferenced += 1;
+        }
+        let index = prefab.add(
+            None,
+            Some(ScenePrefab {
+                name: names.get(entity).cloned(),
+                transform: transforms.get(entity).cloned(),
+                mesh: source.and_then(|s| s.mesh.clone()),
+                material: source.and_then(|s| s.material.clone()),
+                light: light.cloned(),
+                camera: camera.and_then(CameraPrefab::from_camera),
+                blob_shadow: blob_shadow.cloned(),
+                scene: source.and_then(|s| s.scene.clone()),
+                ..Default::default()
+            }),
+        );
+        indices.insert(entity, index);
+    }
+
+    for (&entity, &index) in &indices {
+        if let Some(&parent) = parents.get(entity).and_then(|p| indices.get(&p.entity)) {
+            prefab.entity(index).unwrap().set_parent(parent);
+        }
+    }
+
+    if unreferenced > 0 {
+        log::warn!(
+            "{} saved entities have a mesh created in code, which scenes can't reference.",
+            unreferenced
+        );
+    }
+
+    prefab
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        light::PointLight,
+        mtl::{Material, MaterialDefaults},
+        system::create_default_mat,
+        types::DefaultBackend,
+    };
+    use amethyst_assets::PrefabLoaderSystemDesc;
+    use amethyst_core::{
+        ecs::{Builder, RunNow, WorldExt},
+        ArcThreadPool, SystemDesc, Time,
+    };
+    use palette::Srgba;
+    use rayon::ThreadPoolBuilder;
+    use std::{fs, sync::Arc, thread, time::Duration};
+
+    const SCENE: &str = r#"#![enable(implicit_some)]
+Prefab(
+    entities: [
+        (data: (environment: (ambient_color: (Srgba(0.25, 0.25, 0.25, 1.0))))),
+        (
+            data: (
+                name: (name: "floor"),
+                transform: (translation: (0.0, -1.0, 0.0)),
+                mesh: Shape(shape: Cube, scale: (4.0, 0.1, 4.0)),
+                material: (albedo: Generate(Srgba(0.8, 0.8, 0.8, 1.0))),
+            ),
+        ),
+        (data: (light: Point((intensity: 10.0)), transform: (translation: (0.0, 4.0, 0.0)))),
+    ],
+)"#;
+
+    fn scene_world() -> World {
+        let mut world = World::new();
+        world.insert(Loader::new(
+            ".",
+            Arc::new(ThreadPoolBuilder::new().build().unwrap()),
+        ));
+        <ScenePrefab as PrefabData<'_>>::SystemData::setup(&mut world);
+        world.register::<Parent>();
+        let defaults = create_default_mat::<DefaultBackend>(&mut world);
+        world.insert(MaterialDefaults(defaults));
+        world
+    }
+
+    /// Spawn the entities of a scene like the prefab loader, without parenting them.
+    fn spawn(world: &mut World, mut prefab: Prefab<ScenePrefab>) {
+        let entities = (0..prefab.len())
+            .map(|_| world.create_entity().build())
+            .collect::<Vec<_>>();
+        {
+            let mut progress = ProgressCounter::new();
+            let mut system_data = <ScenePrefab as PrefabData<'_>>::SystemData::fetch(world);
+            for (index, &entity) in entities.iter().enumerate() {
+                let entity_data = prefab.entity(index).unwrap();
+                entity_data
+                    .load_sub_assets(&mut progress, &mut system_data)
+                    .unwrap();
+                if let Some(data) = entity_data.data() {
+                    data.add_to_entity(entity, &mut system_data, &entities, &[])
+                        .unwrap();
+                }
+            }
+        }
+        world.maintain();
+    }
+
+    fn to_ron(prefab: &Prefab<ScenePrefab>) -> String {
+        ron::ser::to_string_pretty(prefab, Default::default()).unwrap()
+    }
+
+    #[test]
+    fn saved_scene_loads_back_identically() {
+        let mut world = scene_world();
+        spawn(&mut world, ron::de::from_str(SCENE).unwrap());
+        let saved = to_ron(&save_scene(&world));
+
+        let mut reloaded = scene_world();
+        spawn(&mut reloaded, ron::de::from_str(&saved).unwrap());
+        assert_eq!(to_ron(&save_scene(&reloaded)), saved);
+
+        assert_eq!(
+            reloaded.read_resource::<AmbientColor>().0,
+            Srgba::new(0.25, 0.25, 0.25, 1.0)
+        );
+        assert_eq!(reloaded.read_storage::<Handle<Mesh>>().count(), 1);
+        assert_eq!(reloaded.read_storage::<Handle<Material>>().count(), 1);
+        let lights = reloaded.read_storage::<Light>();
+        match (&lights).join().next() {
+            Some(Light::Point(point)) => assert_eq!(point.intensity, 10.0),
+            light => panic!("Expected a point light, got {:?}", light),
+        }
+    }
+
+    #[test]
+    fn meshes_created_in_code_are_left_out() {
+        let mut world = scene_world();
+        let mesh = {
+            let loader = world.read_resource::<Loader>();
+            let storage = world.read_resource::<AssetStorage<Mesh>>();
+            loader.load_from_data(
+                Shape::Cube.generate::<SceneShapeVertices>(None).into(),
+                (),
+                &storage,
+            )
+        };
+        world.create_entity().with(mesh.clone()).build();
+        world
+            .create_entity()
+            .with(mesh)
+            .with(Light::Point(PointLight::default()))
+            .build();
+
+        let saved = save_scene(&world);
+        assert_eq!(saved.len(), 2);
+        let light = saved.entities().nth(1).unwrap().data().unwrap();
+        assert!(light.mesh.is_none() && light.light.is_some());
+    }
+
+    #[test]
+    fn scene_instances_are_removed_with_their_lights() {
+        let dir = std::env::temp_dir().join(format!("amethyst_scene_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("lamp.ron"),
+            r#"#![enable(implicit_some)]
+Prefab(
+    entities: [
+        (data: (transform: (scale: (2.0, 2.0, 2.0)), blob_shadow: (radius: (0.3, 0.3)))),
+        (parent: 0, data: (light: Point((intensity: 5.0)), transform: (translation: (0.0, 3.0, 0.0)))),
+    ],
+)"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("street.ron"),
+            r#"#![enable(implicit_some)]
+Prefab(
+    entities: [
+        (data: ()),
+        (parent: 0, data: (scene: "lamp.ron", transform: (translation: (-2.0, 0.0, 0.0)))),
+        (parent: 0, data: (scene: "lamp.ron", transform: (translation: (2.0, 0.0, 0.0)))),
+    ],
+)"#,
+        )
+        .unwrap();
+
+        let mut world = World::new();
+        let pool: ArcThreadPool = Arc::new(ThreadPoolBuilder::new().build().unwrap());
+        world.insert(pool.clone());
+        world.insert(Loader::new(&dir, pool));
+        world.insert(Time::default());
+        let mut system = PrefabLoaderSystemDesc::<ScenePrefab>::default().build(&mut world);
+        RunNow::setup(&mut system, &mut world);
+        let defaults = create_default_mat::<DefaultBackend>(&mut world);
+        world.insert(MaterialDefaults(defaults));
+        world.maintain();
+
+        let count = |world: &World| (&world.entities()).join().count();
+        let before = count(&world);
+        let street = world
+            .exec(|loader: PrefabLoader<'_, ScenePrefab>| loader.load("street.ron", RonFormat, ()));
+        let instance = instantiate_scene(&mut world, &street, Transform::default());
+        for _ in 0..500 {
+            system.run_now(&world);
+            world.maintain();
+            if world.read_storage::<Light>().count() == 2 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(world.read_storage::<Light>().count(), 2);
+        assert_eq!(world.read_storage::<BlobShadow>().count(), 2);
+        // Root, street, two lamps placed by the street, and a lamp and light in each.
+        assert_eq!(instance.parts(&world).len(), 8);
+        let transforms = world.read_storage::<Transform>();
+        let blob_shadows = world.read_storage::<BlobShadow>();
+        for (transform, _) in (&transforms, &blob_shadows).join() {
+            assert_eq!(transform.scale().x, 2.0);
+        }
+        drop((transforms, blob_shadows));
+
+        instance.remove(&mut world).unwrap();
+        world.maintain();
+        assert_eq!(count(&world), before);
+        assert_eq!(world.read_storage::<Light>().count(), 0);
+    }
+}
@@ -1,7 +1,11 @@
 //! Texture formats implementation.
-use crate::types::{Texture, TextureData};
+use crate::{
+    error::{RenderError, TextureError},
+    types::{Texture, TextureData},
+};
 use amethyst_assets::{
-    AssetStorage, Format, Handle, Loader, PrefabData, ProgressCounter, SerializableFormat,
+    AssetStorage, Format, FormatValue, Handle, Loader, PrefabData, ProgressCounter,
+    SerializableFormat, Source,
 };
 use amethyst_core::ecs::{Entity, Read, ReadExpect};
 use amethyst_error::Error;
@@ -17,6 +21,7 @@ use rendy::{
     },
 };
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Image format description newtype wrapper for `ImageTextureConfig` from rendy.
 ///
@@ -100,6 +105,105 @@ impl Format<TextureData> for ImageFormat {
     }
 }
 
+/// Names of the faces of a cubemap, in the order of the layers of the texture: the faces
+/// looking along +X, -X, +Y, -Y, +Z and -Z.
+pub const CUBEMAP_FACES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// Cubemap texture data, from the pixels of its six square faces of `size` x `size` in the order
+/// of [CUBEMAP_FACES]. Sampled with linear filtering and clamped to the edges of the faces.
+pub fn cubemap_data(size: u32, faces: [Vec<Rgba8Srgb>; 6]) -> Result<TextureData, RenderError> {
+    let face_len = (size * size) as usize;
+    if let Some(i) = faces.iter().position(|face| face.len() != face_len) {
+        let cause = format!(
+            "cubemap face {} has {} pixels, {}x{} expected",
+            CUBEMAP_FACES[i],
+            faces[i].len(),
+            size,
+            size
+        );
+        return Err(TextureError::new(CUBEMAP_FACES[i], cause).into());
+    }
+    let pixels = faces.concat();
+    Ok(TextureBuilder::new()
+        .with_kind(Kind::D2(size, size, 6, 1))
+        .with_view_kind(ViewKind::Cube)
+        .with_data_width(size)
+        .with_data_height(size)
+        .with_sampler_info(hal::image::SamplerInfo::new(
+            Filter::Linear,
+            hal::image::WrapMode::Clamp,
+        ))
+        .with_data(pixels)
+        .into())
+}
+
+/// Cubemap texture data, from the encoded images of its six faces in the order of
+/// [CUBEMAP_FACES], in any format supported by [ImageFormat]. The faces must be square images of
+/// the same size, and are read as sRGB.
+pub fn load_cubemap(faces: [&[u8]; 6]) -> Result<TextureData, RenderError> {
+    decode_cubemap(faces, CUBEMAP_FACES)
+}
+
+/// Decodes the faces of a cubemap, naming them in errors with given names.
+fn decode_cubemap(faces: [&[u8]; 6], names: [&str; 6]) -> Result<TextureData, RenderError> {
+    let mut size = None;
+    let mut pixels: [Vec<Rgba8Srgb>; 6] = Default::default();
+    let faces = faces
+        .iter()
+        .zip(&names)
+        .zip(&CUBEMAP_FACES)
+        .zip(&mut pixels);
+    for (((bytes, &name), face_name), face) in faces {
+        let image = image::load_from_memory(bytes)
+            .map_err(|e| TextureError::new(name, e))?
+            .to_rgba();
+        let (width, height) = image.dimensions();
+        if width != height || size.is_some_and(|size| size != width) {
+            let cause = format!(
+                "cubemap face {} is {}x{}, faces must be squares of the same size",
+                face_name, width, height
+            );
+            return Err(TextureError::new(name, cause).into());
+        }
+        size = Some(width);
+        *face = image.pixels().map(|p| Rgba8Srgb { repr: p.0 }).collect();
+    }
+    cubemap_data(size.unwrap_or(0), pixels)
+}
+
+/// Format loading a cubemap from the images of its six faces, see [load_cubemap].
+///
+/// The name of the asset is the path of the faces with `{}` in place of the names of
+/// [CUBEMAP_FACES], e.g. `"skybox/{}.png"` loads `skybox/px.png`, `skybox/nx.png` and so on.
+/// Cubemaps aren't hot reloaded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CubemapFormat;
+
+impl Format<TextureData> for CubemapFormat {
+    fn name(&self) -> &'static str {
+        "CUBEMAP"
+    }
+
+    fn import(
+        &self,
+        name: String,
+        source: Arc<dyn Source>,
+        _create_reload: Option<Box<dyn Format<TextureData>>>,
+    ) -> Result<FormatValue<TextureData>, Error> {
+        let paths = CUBEMAP_FACES.map(|face| name.replace("{}", face));
+        let faces = paths
+            .iter()
+            .map(|path| source.load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut bytes: [&[u8]; 6] = Default::default();
+        for (bytes, face) in bytes.iter_mut().zip(&faces) {
+            *bytes = face;
+        }
+        let names = [0, 1, 2, 3, 4, 5].map(|i| &paths[i][..]);
+        Ok(FormatValue::data(decode_cubemap(bytes, names)?))
+    }
+}
+
 /// `PrefabData` for loading `Texture`s.
 ///
 /// Will not add any `Component`s to the `Entity`, will only return a `Handle`
@@ -219,3 +323,74 @@ impl<'a> PrefabData<'a> for TexturePrefab {
         Ok(ret)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image::png::PNGEncoder::new(&mut bytes)
+            .encode(
+                &vec![128; (width * height * 4) as usize],
+                width,
+                height,
+                image::ColorType::RGBA(8),
+            )
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn cubemaps_need_six_square_faces_of_the_same_size() {
+        let face = png(4, 4);
+        assert!(load_cubemap([&face[..]; 6]).is_ok());
+
+        let small = png(2, 2);
+        let mut faces = [&face[..]; 6];
+        faces[3] = &small;
+        let error = load_cubemap(faces).unwrap_err().to_string();
+        assert!(error.contains("face ny is 2x2"), "{}", error);
+
+        let wide = png(4, 2);
+        assert!(load_cubemap([&wide[..]; 6]).is_err());
+        assert!(load_cubemap([&b"not an image"[..]; 6]).is_err());
+
+        let mut pixels: [Vec<Rgba8Srgb>; 6] = Default::default();
+        assert!(cubemap_data(0, pixels.clone()).is_ok());
+        pixels[0].push(Rgba8Srgb { repr: [0; 4] });
+        assert!(cubemap_data(0, pixels).is_err());
+    }
+
+    /// Source of faces which are all 4x4 images, except `ny.png` which isn't an image.
+    #[derive(Debug)]
+    struct Faces;
+
+    impl Source for Faces {
+        fn modified(&self, _path: &str) -> Result<u64, Error> {
+            Ok(0)
+        }
+
+        fn load(&self, path: &str) -> Result<Vec<u8>, Error> {
+            if path.ends_with("ny.png") {
+                Ok(b"not an image".to_vec())
+            } else {
+                Ok(png(4, 4))
+            }
+        }
+    }
+
+    #[test]
+    fn cubemap_errors_name_the_path_of_the_face() {
+        let error = CubemapFormat
+            .import("skybox/{}.png".into(), Arc::new(Faces), None)
+            .err()
+            .expect("Face ny isn't an image")
+            .to_string();
+        assert!(
+            error.starts_with("Texture skybox/ny.png failed to load: "),
+            "{}",
+            error
+        );
+    }
+}
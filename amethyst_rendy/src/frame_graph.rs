@@ -0,0 +1,259 @@
+//! Description of the render graph built from the `RenderPlan`, for debugging pass ordering.
+//!
+//! The `RenderingBundle` updates the [FrameGraph] resource every time it rebuilds the graph.
+//! Export it with [FrameGraph::to_dot] and render it with Graphviz, or serialize it with any
+//! serde format. Nodes are named after their targets and everything is sorted, so dumps of the
+//! same plan are identical across runs and can be diffed.
+
+use crate::{scene::SceneStats, stats::RenderStats};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Render targets of the last built render graph.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FrameGraph {
+    /// Targets of the plan, sorted by name.
+    pub targets: Vec<FrameGraphTarget>,
+}
+
+/// A render target of the [FrameGraph].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FrameGraphTarget {
+    /// Name of the target, e.g. `Main` or `Custom("bloom")`.
+    pub name: String,
+    /// False if the target was skipped, because it isn't a root and nothing reads it.
+    pub evaluated: bool,
+    /// Order and type name of every render group, in drawing order.
+    pub actions: Vec<(i32, String)>,
+    /// Images written by the target.
+    pub writes: Vec<String>,
+    /// Images of other targets read by the target, sorted by name.
+    pub reads: Vec<FrameGraphRead>,
+}
+
+/// An image read by a [FrameGraphTarget].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct FrameGraphRead {
+    /// Target writing the image.
+    pub target: String,
+    /// Name of the image, e.g. `Depth(ShadowMap)`.
+    pub image: String,
+    /// False if no target wrote the image.
+    pub written: bool,
+}
+
+impl FrameGraph {
+    /// Find a target by name.
+    pub fn target(&self, name: &str) -> Option<&FrameGraphTarget> {
+        self.targets.iter().find(|t| t.name == name)
+    }
+
+    /// Sort targets and reads by name.
+    pub(crate) fn sort(&mut self) {
+        self.targets.sort_by(|a, b| a.name.cmp(&b.name));
+        for target in &mut self.targets {
+            target.reads.sort();
+            target.reads.dedup();
+        }
+    }
+
+    /// Graphviz graph with targets as nodes and image reads as edges from the writing target.
+    ///
+    /// Skipped targets are dashed and images read but never written are red. The CPU time of
+    /// the last frame is added as the graph label when `stats` are given. Times of individual
+    /// passes aren't measured, as rendy doesn't expose GPU timestamp queries.
+    pub fn to_dot(&self, stats: Option<&RenderStats>) -> String {
+        self.to_dot_with_scene(stats, None)
+    }
+
+    /// Graphviz graph of [FrameGraph::to_dot], with the totals of the scene statistics added to
+    /// the graph label when `scene` is given.
+    pub fn to_dot_with_scene(
+        &self,
+        stats: Option<&RenderStats>,
+        scene: Option<&SceneStats>,
+    ) -> String {
+        let mut dot = String::from("digraph frame_graph {\n    node [shape=box];\n");
+        let mut labels = Vec::new();
+        if let Some(stats) = stats {
+            labels.push(format!(
+                "graph time {:.3}ms",
+                stats.graph_time.as_secs_f64() * 1000.0
+            ));
+        }
+        if let Some(scene) = scene {
+            labels.push(format!(
+                "{} fragments, {} triangles, {} lights",
+                scene.fragments,
+                scene.triangles,
+                scene.lights.values().sum::<usize>()
+            ));
+        }
+        if !labels.is_empty() {
+            writeln!(dot, "    label={};", quote(&labels.join("\n"))).unwrap();
+        }
+
+        for target in &self.targets {
+            let mut label = target.name.clone();
+            if !target.evaluated {
+                label.push_str("\n(skipped)");
+            }
+            for (order, action) in &target.actions {
+                write!(label, "\n{} {}", order, action).unwrap();
+            }
+            let style = if target.evaluated {
+                ""
+            } else {
+                ", style=dashed"
+            };
+            writeln!(
+                dot,
+                "    {} [label={}{}];",
+                quote(&target.name),
+                quote(&label),
+                style
+            )
+            .unwrap();
+        }
+
+        for target in &self.targets {
+            for read in &target.reads {
+                if read.written {
+                    writeln!(
+                        dot,
+                        "    {} -> {} [label={}];",
+                        quote(&read.target),
+                        quote(&target.name),
+                        quote(&read.image)
+                    )
+                    .unwrap();
+                } else {
+                    let missing = format!("missing {}", read.image);
+                    writeln!(
+                        dot,
+                        "    {} [label={}, shape=octagon, color=red];\n    {} -> {} [color=red];",
+                        quote(&missing),
+                        quote(&missing),
+                        quote(&missing),
+                        quote(&target.name)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn quote(s: &str) -> String {
+    format!(
+        "\"{}\"",
+        s.replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+/// Readable name of a render group builder type, e.g. `DrawPbrDesc` for its `DescBuilder`.
+pub(crate) fn action_name(type_name: &str) -> String {
+    let (base, args) = match type_name.find('<') {
+        Some(i) if type_name.ends_with('>') => {
+            (&type_name[..i], &type_name[i + 1..type_name.len() - 1])
+        }
+        _ => (type_name, ""),
+    };
+    let base = base.rsplit("::").next().unwrap_or(base);
+    if base == "DescBuilder" {
+        if let Some(desc) = split_args(args).get(2) {
+            return action_name(desc.trim());
+        }
+    }
+    base.to_string()
+}
+
+fn split_args(args: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut split = Vec::new();
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&args[start..]);
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_names_are_short() {
+        assert_eq!(
+            action_name(
+                "rendy_graph::node::render::group::DescBuilder<rendy::empty::Backend, \
+                 shred::world::World, amethyst_rendy::pass::pbr::DrawPbrDesc<rendy::empty::Backend, \
+                 amethyst_rendy::pass::base_3d::Pbr>>"
+            ),
+            "DrawPbrDesc"
+        );
+        assert_eq!(action_name("my_game::CustomGroup"), "CustomGroup");
+    }
+
+    #[test]
+    fn dot_marks_skipped_targets_and_missing_reads() {
+        let mut graph = FrameGraph {
+            targets: vec![
+                FrameGraphTarget {
+                    name: "Main".into(),
+                    evaluated: true,
+                    actions: vec![(100, "DrawPbrDesc".into())],
+                    writes: vec!["Color(Main, 0)".into()],
+                    reads: vec![
+                        FrameGraphRead {
+                            target: "Custom(\"bloom\")".into(),
+                            image: "Color(Custom(\"bloom\"), 0)".into(),
+                            written: false,
+                        },
+                        FrameGraphRead {
+                            target: "ShadowMap".into(),
+                            image: "Depth(ShadowMap)".into(),
+                            written: true,
+                        },
+                    ],
+                },
+                FrameGraphTarget {
+                    name: "Custom(\"minimap\")".into(),
+                    ..Default::default()
+                },
+            ],
+        };
+        graph.sort();
+        assert_eq!(graph.targets[0].name, "Custom(\"minimap\")");
+
+        let dot = graph.to_dot(None);
+        assert_eq!(dot, graph.clone().to_dot(None));
+        assert!(dot.contains("\"Custom(\\\"minimap\\\")\" [label=\"Custom(\\\"minimap\\\")\\n(skipped)\", style=dashed];"));
+        assert!(dot.contains("\"Main\" [label=\"Main\\n100 DrawPbrDesc\"];"));
+        assert!(dot.contains("\"ShadowMap\" -> \"Main\" [label=\"Depth(ShadowMap)\"];"));
+        assert!(
+            dot.contains("\"missing Color(Custom(\\\"bloom\\\"), 0)\" -> \"Main\" [color=red];")
+        );
+
+        let scene = SceneStats {
+            fragments: 3,
+            triangles: 12,
+            ..Default::default()
+        };
+        let dot = graph.to_dot_with_scene(None, Some(&scene));
+        assert!(dot.contains("label=\"3 fragments, 12 triangles, 0 lights\";"));
+    }
+}
@@ -0,0 +1,144 @@
+//! View frustum of a camera, for culling bounding spheres on the CPU.
+use crate::camera::Camera;
+use amethyst_core::{
+    math::{convert, Matrix4, Point3, Vector4},
+    Transform,
+};
+
+/// Simple view Frustum implementation
+///
+/// Points on a plane of the frustum are inside of it, so spheres touching the frustum are never
+/// culled. Spheres near its edges and corners can be kept while outside of it, as every plane
+/// is tested separately.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+    /// The planes of the frustum, pointing inwards: left, right, bottom, top, far and near.
+    /// The far plane of an infinite projection keeps every point.
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Create a new simple frustum from the provided matrix, projecting to the Vulkan clip space
+    /// with depth going from 1 at the near plane to 0 at the far plane.
+    pub fn new(matrix: Matrix4<f32>) -> Self {
+        let normalize = |plane: Vector4<f32>| {
+            let length = plane.xyz().magnitude();
+            if length > 0.0 {
+                plane / length
+            } else {
+                Vector4::new(0.0, 0.0, 0.0, 1.0)
+            }
+        };
+        Self {
+            planes: [
+                normalize((matrix.row(3) + matrix.row(0)).transpose()),
+                normalize((matrix.row(3) - matrix.row(0)).transpose()),
+                normalize((matrix.row(3) - matrix.row(1)).transpose()),
+                normalize((matrix.row(3) + matrix.row(1)).transpose()),
+                normalize(matrix.row(2).transpose()),
+                normalize((matrix.row(3) - matrix.row(2)).transpose()),
+            ],
+        }
+    }
+
+    /// Frustum of given camera at given transform, in world space.
+    pub fn from_camera(camera: &Camera, transform: &Transform) -> Self {
+        Self::new(
+            convert::<_, Matrix4<f32>>(camera.matrix)
+                * transform.global_matrix().try_inverse().unwrap(),
+        )
+    }
+
+    /// Check if the given sphere is within the Frustum
+    pub fn check_sphere(&self, center: &Point3<f32>, radius: f32) -> bool {
+        for plane in &self.planes {
+            if plane.xyz().dot(&center.coords) + plane.w < -radius {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn ortho() -> Frustum {
+        Frustum::new(Camera::orthographic(-1.0, 1.0, -1.0, 1.0, 1.0, 5.0).matrix)
+    }
+
+    #[test]
+    fn orthographic_planes_bound_the_box() {
+        let planes = ortho().planes;
+        assert_eq!(planes[0], Vector4::new(1.0, 0.0, 0.0, 1.0));
+        assert_eq!(planes[1], Vector4::new(-1.0, 0.0, 0.0, 1.0));
+        assert_eq!(planes[4], Vector4::new(0.0, 0.0, 1.0, 5.0));
+        assert_eq!(planes[5], Vector4::new(0.0, 0.0, -1.0, -1.0));
+        for plane in &planes[..4] {
+            assert_eq!(plane.xyz().magnitude(), 1.0);
+            assert_eq!(plane.z, 0.0);
+        }
+    }
+
+    #[test]
+    fn points_on_the_near_and_far_planes_are_inside() {
+        let frustum = ortho();
+        let point = |z| frustum.check_sphere(&Point3::new(0.0, 0.0, z), 0.0);
+        assert!(point(-1.0));
+        assert!(point(-5.0));
+        assert!(point(-3.0));
+        assert!(!point(-0.99));
+        assert!(!point(-5.01));
+        assert!(!point(1.0));
+    }
+
+    #[test]
+    fn points_on_an_edge_are_inside() {
+        let frustum = ortho();
+        let point = |x, y, z| frustum.check_sphere(&Point3::new(x, y, z), 0.0);
+        assert!(point(1.0, 1.0, -3.0));
+        assert!(point(-1.0, -1.0, -1.0));
+        assert!(point(1.0, -1.0, -5.0));
+        assert!(!point(1.01, 1.0, -3.0));
+        assert!(!point(1.0, 1.01, -3.0));
+    }
+
+    #[test]
+    fn spheres_touching_the_frustum_are_kept() {
+        let frustum = ortho();
+        assert!(frustum.check_sphere(&Point3::new(2.0, 0.0, -3.0), 1.0));
+        assert!(frustum.check_sphere(&Point3::new(0.0, 0.0, -6.0), 1.0));
+        assert!(!frustum.check_sphere(&Point3::new(2.5, 0.0, -3.0), 1.0));
+        assert!(!frustum.check_sphere(&Point3::new(0.0, 0.0, 1.0), 1.0));
+        // Outside of the corner, but within the radius of both planes.
+        assert!(frustum.check_sphere(&Point3::new(1.9, 1.9, -3.0), 1.0));
+    }
+
+    #[test]
+    fn perspective_far_plane_is_infinite() {
+        let frustum = Frustum::new(Camera::perspective(1.0, FRAC_PI_2, 0.5).matrix);
+        assert_eq!(frustum.planes[4], Vector4::new(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(frustum.planes[5], Vector4::new(0.0, 0.0, -1.0, -0.5));
+
+        let point = |x, y, z| frustum.check_sphere(&Point3::new(x, y, z), 0.0);
+        assert!(point(0.0, 0.0, -0.5));
+        assert!(!point(0.0, 0.0, -0.49));
+        assert!(point(0.0, 0.0, -1.0e6));
+        assert!(point(0.9, 0.9, -1.0));
+        assert!(!point(1.1, 0.0, -1.0));
+        assert!(!point(0.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn frustum_follows_the_camera() {
+        let camera = Camera::orthographic(-1.0, 1.0, -1.0, 1.0, 1.0, 5.0);
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(10.0, 0.0, 0.0);
+        transform.copy_local_to_global();
+        let frustum = Frustum::from_camera(&camera, &transform);
+        assert!(frustum.check_sphere(&Point3::new(10.0, 0.0, -3.0), 0.0));
+        assert!(!frustum.check_sphere(&Point3::new(0.0, 0.0, -3.0), 0.0));
+    }
+}
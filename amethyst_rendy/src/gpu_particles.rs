@@ -0,0 +1,365 @@
+//! Particles simulated on the GPU.
+//!
+//! Entities with a [GpuParticleEmitter] and a `Transform` emit particles from their position.
+//! The state of every particle lives in float textures on the GPU, one texel per particle,
+//! updated every frame by a fullscreen simulation pass applying the [GpuParticleForces]
+//! resource, and drawn as camera facing billboards fetching their state in the vertex shader.
+//! Use `RenderGpuParticles` plugin to simulate and draw them.
+//!
+//! The CPU never reads the state back. The [GpuParticles] resource tracks which slots of the
+//! state are free from the lifetime of the particles, and the [GpuParticleEmitterSystem]
+//! writes new particles into free slots. Emitters skip particles while every slot is used.
+//!
+//! Particles are not sorted, and are blended in the order of their slots.
+
+use crate::{
+    determinism::RenderDeterminism,
+    pod::{GpuParticleSpawnArgs, IntoPod},
+};
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Join, Read, ReadStorage, System, Write, WriteStorage},
+    math::{convert, Vector3},
+    timing::Time,
+    transform::Transform,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Maximum number of attractors of the [GpuParticleForces]. Further attractors are ignored.
+pub const MAX_PARTICLE_ATTRACTORS: usize = 8;
+
+/// Width of the textures holding the particle state, in texels. Their height is the number of
+/// rows needed for the capacity of the [GpuParticles].
+pub const GPU_PARTICLE_STATE_WIDTH: u32 = 1024;
+
+/// Width and height of the textures holding the state of `capacity` particles.
+pub fn gpu_particle_state_size(capacity: u32) -> (u32, u32) {
+    let capacity = capacity.max(1);
+    let width = capacity.min(GPU_PARTICLE_STATE_WIDTH);
+    (width, capacity.div_ceil(width))
+}
+
+/// Emit particles simulated on the GPU from the position of the entity, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuParticleEmitter {
+    /// Particles emitted per second.
+    pub rate: f32,
+    /// Seconds every particle lives. Particles with no lifetime are not emitted.
+    pub lifetime: f32,
+    /// Initial velocity of the particles in world space.
+    pub velocity: Vector3<f32>,
+    /// Angle in radians around `velocity` the initial direction of the particles is randomly
+    /// picked in.
+    pub spread: f32,
+    /// Color of the particles when emitted.
+    #[serde(with = "crate::serde_shim::srgba")]
+    pub start_color: palette::Srgba,
+    /// Color of the particles at the end of their lifetime.
+    #[serde(with = "crate::serde_shim::srgba")]
+    pub end_color: palette::Srgba,
+    /// Diameter of the particles when emitted, in world units.
+    pub start_size: f32,
+    /// Diameter of the particles at the end of their lifetime, in world units.
+    pub end_size: f32,
+    #[serde(skip)]
+    pending: f32,
+}
+
+impl Default for GpuParticleEmitter {
+    fn default() -> Self {
+        Self {
+            rate: 100.0,
+            lifetime: 2.0,
+            velocity: Vector3::new(0.0, 1.0, 0.0),
+            spread: 0.3,
+            start_color: palette::Srgba::new(1.0, 1.0, 1.0, 1.0),
+            end_color: palette::Srgba::new(1.0, 1.0, 1.0, 0.0),
+            start_size: 0.1,
+            end_size: 0.1,
+            pending: 0.0,
+        }
+    }
+}
+
+impl Component for GpuParticleEmitter {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Point attracting the GPU particles, or repelling them with a negative strength.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ParticleAttractor {
+    /// Position of the attractor in world space.
+    pub position: Vector3<f32>,
+    /// Acceleration of the particles one unit away from the attractor, falling off with the
+    /// square of the distance.
+    pub strength: f32,
+}
+
+/// Forces applied to every GPU particle, read by the simulation pass every frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuParticleForces {
+    /// Acceleration applied to every particle, in world units per second squared.
+    pub gravity: Vector3<f32>,
+    /// Fraction of their velocity the particles lose per second.
+    pub drag: f32,
+    /// Points attracting the particles, up to [MAX_PARTICLE_ATTRACTORS].
+    pub attractors: Vec<ParticleAttractor>,
+}
+
+impl Default for GpuParticleForces {
+    fn default() -> Self {
+        Self {
+            gravity: Vector3::new(0.0, -9.81, 0.0),
+            drag: 0.0,
+            attractors: Vec::new(),
+        }
+    }
+}
+
+/// Resource tracking the slots of the GPU particle state.
+///
+/// Slots are freed once their particle has outlived its lifetime, following the same frame
+/// time as the simulation pass. [GpuParticles::reset] kills every particle, e.g. on a camera
+/// cut or when loading a level. The particles are also reset when the render graph is rebuilt,
+/// as their state is lost with it.
+#[derive(Debug)]
+pub struct GpuParticles {
+    capacity: u32,
+    time: f64,
+    free: Vec<u32>,
+    dying: BinaryHeap<Reverse<(u64, u32)>>,
+    spawns: Vec<GpuParticleSpawnArgs>,
+    seed: u32,
+    reset: bool,
+}
+
+impl Default for GpuParticles {
+    fn default() -> Self {
+        Self::new(1 << 16)
+    }
+}
+
+impl GpuParticles {
+    /// Track the slots of a state holding up to `capacity` particles.
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            time: 0.0,
+            free: (0..capacity).rev().collect(),
+            dying: BinaryHeap::new(),
+            spawns: Vec::new(),
+            seed: 0,
+            reset: true,
+        }
+    }
+
+    /// Maximum number of particles alive at once.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Number of particles alive, including the ones emitted this frame.
+    pub fn alive(&self) -> usize {
+        self.dying.len()
+    }
+
+    /// Kill every particle, freeing all slots.
+    pub fn reset(&mut self) {
+        self.free = (0..self.capacity).rev().collect();
+        self.dying.clear();
+        self.spawns.clear();
+        self.reset = true;
+    }
+
+    /// Derive the random directions of the next particles from given seed.
+    pub fn reseed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
+    /// Advance the frame time by `delta` seconds, freeing the slots of the particles that
+    /// outlived their lifetime.
+    pub fn advance(&mut self, delta: f32) {
+        self.time += f64::from(delta);
+        let now = micros(self.time);
+        while let Some(&Reverse((death, slot))) = self.dying.peek() {
+            if death > now {
+                break;
+            }
+            self.dying.pop();
+            self.free.push(slot);
+        }
+    }
+
+    /// Emit a particle of `emitter` at given position in world space. Returns `false` when
+    /// every slot is used, or the emitter has no lifetime.
+    pub fn emit(&mut self, emitter: &GpuParticleEmitter, position: Vector3<f32>) -> bool {
+        if emitter.lifetime.is_nan() || emitter.lifetime <= 0.0 {
+            return false;
+        }
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => return false,
+        };
+        self.dying.push(Reverse((
+            micros(self.time + f64::from(emitter.lifetime)),
+            slot,
+        )));
+        self.seed = self.seed.wrapping_add(1);
+        self.spawns.push(GpuParticleSpawnArgs {
+            slot,
+            position: position.into_pod(),
+            velocity: emitter.velocity.into_pod(),
+            spread: emitter.spread,
+            lifetime: emitter.lifetime,
+            seed: self.seed,
+            start_color: linear(emitter.start_color),
+            end_color: linear(emitter.end_color),
+            size: [emitter.start_size, emitter.end_size].into(),
+        });
+        true
+    }
+
+    /// Take the particles emitted since the last call, and whether the state must be reset
+    /// before writing them.
+    pub(crate) fn take_spawns(&mut self, spawns: &mut Vec<GpuParticleSpawnArgs>) -> bool {
+        spawns.clear();
+        spawns.append(&mut self.spawns);
+        std::mem::replace(&mut self.reset, false)
+    }
+}
+
+fn micros(seconds: f64) -> u64 {
+    (seconds * 1.0e6) as u64
+}
+
+fn linear(color: palette::Srgba) -> glsl_layout::vec4 {
+    let (r, g, b, a) = color.into_linear().into_components();
+    [r, g, b, a].into()
+}
+
+/// Emits the particles of every [GpuParticleEmitter] into the [GpuParticles] every frame.
+#[derive(Debug, Default)]
+pub struct GpuParticleEmitterSystem;
+
+impl<'a> System<'a> for GpuParticleEmitterSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, RenderDeterminism>,
+        Write<'a, GpuParticles>,
+        WriteStorage<'a, GpuParticleEmitter>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (time, determinism, mut particles, mut emitters, transforms): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("gpu_particle_emitter_system");
+
+        let delta = determinism.delta_seconds(&time);
+        if let Some(seed) = determinism.frame_seed(time.frame_number()) {
+            particles.reseed(seed);
+        }
+        particles.advance(delta);
+        for (emitter, transform) in (&mut emitters, &transforms).join() {
+            emitter.pending += emitter.rate.max(0.0) * delta;
+            let position = convert(transform.global_matrix().column(3).xyz());
+            while emitter.pending >= 1.0 {
+                emitter.pending -= 1.0;
+                if !particles.emit(emitter, position) {
+                    emitter.pending = 0.0;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_holds_the_capacity() {
+        assert_eq!(gpu_particle_state_size(0), (1, 1));
+        assert_eq!(gpu_particle_state_size(100), (100, 1));
+        assert_eq!(gpu_particle_state_size(500_000), (1024, 489));
+    }
+
+    #[test]
+    fn slots_are_reused_after_the_particles_die() {
+        let emitter = GpuParticleEmitter {
+            lifetime: 1.0,
+            ..Default::default()
+        };
+        let mut particles = GpuParticles::new(2);
+        let mut spawns = Vec::new();
+        assert!(particles.take_spawns(&mut spawns));
+
+        assert!(particles.emit(&emitter, Vector3::zeros()));
+        assert!(particles.emit(&emitter, Vector3::zeros()));
+        assert!(!particles.emit(&emitter, Vector3::zeros()));
+        assert!(!particles.take_spawns(&mut spawns));
+        assert_eq!(
+            spawns.iter().map(|s| s.slot).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        particles.advance(0.5);
+        assert!(!particles.emit(&emitter, Vector3::zeros()));
+        particles.advance(0.5);
+        assert_eq!(particles.alive(), 0);
+        assert!(particles.emit(&emitter, Vector3::zeros()));
+
+        particles.reset();
+        assert_eq!(particles.alive(), 0);
+        assert!(particles.take_spawns(&mut spawns));
+        assert!(spawns.is_empty());
+    }
+
+    #[test]
+    fn seeded_emission_is_repeatable() {
+        use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+
+        let run = |seed| {
+            let mut world = World::new();
+            world.register::<GpuParticleEmitter>();
+            world.register::<Transform>();
+            world.insert(Time::default());
+            world.insert(RenderDeterminism::seeded(seed));
+            world.insert(GpuParticles::new(64));
+            world
+                .create_entity()
+                .with(GpuParticleEmitter {
+                    rate: 120.0,
+                    lifetime: 1.0,
+                    ..Default::default()
+                })
+                .with(Transform::default())
+                .build();
+
+            let mut spawns = Vec::new();
+            let mut emitted = Vec::new();
+            for _ in 0..10 {
+                GpuParticleEmitterSystem.run_now(&world);
+                world.write_resource::<Time>().increment_frame_number();
+                world
+                    .write_resource::<GpuParticles>()
+                    .take_spawns(&mut spawns);
+                emitted.extend(spawns.iter().map(|s| (s.slot, s.seed)));
+            }
+            emitted
+        };
+
+        let emitted = run(3);
+        assert_eq!(emitted.len(), 20);
+        assert_eq!(emitted, run(3));
+        assert_ne!(emitted, run(4));
+    }
+}
@@ -0,0 +1,216 @@
+//! Hierarchical depth buffer (Hi-Z) of the opaque scene.
+//!
+//! The `RenderHiZ` plugin renders the depth of opaque static meshes into [HIZ_DEPTH_TARGET] and
+//! reduces it into a chain of progressively smaller levels, each texel holding the **farthest**
+//! depth of the texels it covers in the previous level. Amethyst uses reversed Z, so the farthest
+//! depth is the *smallest* value. An object whose nearest depth is farther than the value stored
+//! in the level texels covering its screen rectangle is guaranteed to be hidden.
+//!
+//! Render targets only write their first mip level, so every level lives in its own target,
+//! retrieved with [hiz_image]. Level 0 is the depth output of [HIZ_DEPTH_TARGET], levels from 1
+//! are single channel `R32Sfloat` color outputs. Consumers look up levels in their target plan:
+//!
+//! ```ignore
+//! plan.extend_target(Target::Main, |ctx| {
+//!     let mut level = 0;
+//!     while let Some(image) = ctx.try_get_image(hiz_image(level))? {
+//!         // bind `image` to a render group
+//!         level += 1;
+//!     }
+//!     Ok(())
+//! });
+//! ```
+//!
+//! Levels are only built when a consumer requests them.
+//!
+//! Level sizes are halved and rounded down. When the previous level has an odd width or height,
+//! texels in the last column or row of the next level also cover the extra column or row, so no
+//! texel of the previous level is skipped. Skipping it would let an occlusion test see past a
+//! nearby occluder edge and cull a visible object.
+
+use crate::bundle::{Target, TargetImage};
+use amethyst_core::math::{Matrix4, Point3, Vector4};
+
+/// Maximum number of levels in the Hi-Z chain, including the full resolution depth.
+pub const HIZ_MAX_LEVELS: usize = 16;
+
+/// Target the opaque scene depth is rendered to. Its depth output is level 0 of the chain.
+pub const HIZ_DEPTH_TARGET: Target = Target::Custom("hiz_depth");
+
+const LEVEL_TARGETS: [&str; HIZ_MAX_LEVELS] = [
+    "hiz_depth",
+    "hiz_1",
+    "hiz_2",
+    "hiz_3",
+    "hiz_4",
+    "hiz_5",
+    "hiz_6",
+    "hiz_7",
+    "hiz_8",
+    "hiz_9",
+    "hiz_10",
+    "hiz_11",
+    "hiz_12",
+    "hiz_13",
+    "hiz_14",
+    "hiz_15",
+];
+
+/// Target producing given level of the Hi-Z chain.
+pub fn hiz_target(level: usize) -> Target {
+    Target::Custom(LEVEL_TARGETS[level])
+}
+
+/// Image holding given level of the Hi-Z chain.
+pub fn hiz_image(level: usize) -> TargetImage {
+    if level == 0 {
+        TargetImage::Depth(HIZ_DEPTH_TARGET)
+    } else {
+        TargetImage::Color(hiz_target(level), 0)
+    }
+}
+
+/// Sizes of all levels of a Hi-Z chain built from depth of given size, ending at 1x1.
+pub fn hiz_level_sizes(width: u32, height: u32) -> Vec<(u32, u32)> {
+    let mut sizes = vec![(width.max(1), height.max(1))];
+    while sizes.len() < HIZ_MAX_LEVELS {
+        let (w, h) = sizes[sizes.len() - 1];
+        if w == 1 && h == 1 {
+            break;
+        }
+        sizes.push(((w / 2).max(1), (h / 2).max(1)));
+    }
+    sizes
+}
+
+/// Reduce one level of the chain into the next one on the CPU.
+///
+/// Matches the downsample shader texel for texel, which makes it useful for validating
+/// read back levels.
+pub fn reduce_level(depth: &[f32], width: u32, height: u32) -> Vec<f32> {
+    let (w, h) = ((width / 2).max(1), (height / 2).max(1));
+    let mut out = Vec::with_capacity((w * h) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let extra_x = if width % 2 == 1 && x == w - 1 { 3 } else { 2 };
+            let extra_y = if height % 2 == 1 && y == h - 1 { 3 } else { 2 };
+            let mut farthest = f32::MAX;
+            for dy in 0..extra_y {
+                for dx in 0..extra_x {
+                    let sx = (x * 2 + dx).min(width - 1);
+                    let sy = (y * 2 + dy).min(height - 1);
+                    farthest = farthest.min(depth[(sy * width + sx) as usize]);
+                }
+            }
+            out.push(farthest);
+        }
+    }
+    out
+}
+
+/// Screen space bounds of a sphere, used to test it against the Hi-Z chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HiZBounds {
+    /// Normalized device coordinates of the top left corner.
+    pub min: [f32; 2],
+    /// Normalized device coordinates of the bottom right corner.
+    pub max: [f32; 2],
+    /// Depth of the point nearest to the camera.
+    pub nearest: f32,
+}
+
+impl HiZBounds {
+    /// Project the box enclosing a world space sphere.
+    ///
+    /// Returns `None` when the box crosses the camera plane, as such objects can't be culled.
+    pub fn project(proj_view: &Matrix4<f32>, center: &Point3<f32>, radius: f32) -> Option<Self> {
+        let mut bounds = Self {
+            min: [f32::MAX; 2],
+            max: [f32::MIN; 2],
+            nearest: f32::MIN,
+        };
+        for i in 0..8 {
+            let corner = Vector4::new(
+                center.x + if i & 1 == 0 { -radius } else { radius },
+                center.y + if i & 2 == 0 { -radius } else { radius },
+                center.z + if i & 4 == 0 { -radius } else { radius },
+                1.0,
+            );
+            let clip = proj_view * corner;
+            if clip.w <= f32::EPSILON {
+                return None;
+            }
+            let (x, y, z) = (clip.x / clip.w, clip.y / clip.w, clip.z / clip.w);
+            bounds.min = [bounds.min[0].min(x), bounds.min[1].min(y)];
+            bounds.max = [bounds.max[0].max(x), bounds.max[1].max(y)];
+            bounds.nearest = bounds.nearest.max(z);
+        }
+        bounds.min = [bounds.min[0].max(-1.0), bounds.min[1].max(-1.0)];
+        bounds.max = [bounds.max[0].min(1.0), bounds.max[1].min(1.0)];
+        Some(bounds)
+    }
+
+    /// Level of a chain with given level 0 size where the bounds span at most two texels in
+    /// each direction, so that sampling the four corners covers them entirely.
+    ///
+    /// Returns `None` when the chain doesn't have such a level.
+    pub fn level(&self, sizes: &[(u32, u32)]) -> Option<usize> {
+        let (width, height) = *sizes.first()?;
+        let extent = ((self.max[0] - self.min[0]) * 0.5 * width as f32)
+            .max((self.max[1] - self.min[1]) * 0.5 * height as f32)
+            .max(1.0);
+        let level = extent.log2().ceil() as usize;
+        if level < sizes.len() {
+            Some(level)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_sizes_end_at_one() {
+        assert_eq!(
+            hiz_level_sizes(13, 6),
+            vec![(13, 6), (6, 3), (3, 1), (1, 1)]
+        );
+        assert_eq!(hiz_level_sizes(1, 1), vec![(1, 1)]);
+        assert_eq!(hiz_level_sizes(1 << 20, 1).len(), HIZ_MAX_LEVELS);
+    }
+
+    #[test]
+    fn odd_sizes_keep_last_row_and_column() {
+        // 3x3 with the only far texel in the last row and column.
+        let mut depth = vec![1.0; 9];
+        depth[8] = 0.25;
+        assert_eq!(reduce_level(&depth, 3, 3), vec![0.25]);
+
+        // 5x1 reduces to 2x1, the last texel covering three source texels.
+        let depth = [0.9, 0.8, 0.7, 0.6, 0.1];
+        assert_eq!(reduce_level(&depth, 5, 1), vec![0.8, 0.1]);
+    }
+
+    #[test]
+    fn bounds_select_covering_level() {
+        let bounds = HiZBounds {
+            min: [-0.5, -0.1],
+            max: [0.5, 0.1],
+            nearest: 0.5,
+        };
+        let sizes = hiz_level_sizes(64, 64);
+        // 32 pixels wide at level 0, a single texel at level 5.
+        assert_eq!(bounds.level(&sizes), Some(5));
+        assert_eq!(bounds.level(&sizes[..4]), None);
+
+        let behind = HiZBounds::project(&Matrix4::identity(), &Point3::new(0.0, 0.0, 0.0), 1.0);
+        assert!(behind.is_some());
+        let mut proj = Matrix4::identity();
+        proj[(3, 3)] = 0.0;
+        proj[(3, 2)] = -1.0;
+        assert!(HiZBounds::project(&proj, &Point3::new(0.0, 0.0, 0.5), 1.0).is_none());
+    }
+}
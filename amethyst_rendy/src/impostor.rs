@@ -0,0 +1,284 @@
+//! Impostor level of detail for distant objects.
+//!
+//! An impostor is an atlas of pre-rendered views of an object, captured from evenly spaced angles
+//! around its vertical axis. Entities with an [Impostor] component further away from the camera
+//! than its `transition_distance` are drawn as a camera-facing quad showing the captured view
+//! closest to the direction of the camera, or a blend of the two closest views, instead of their
+//! mesh. Use `RenderImpostors` plugin to draw them.
+//!
+//! The atlas is either a texture, or baked on the GPU by `RenderImpostors::with_bake` into an
+//! offscreen target, drawing the mesh with its own material. All views are lit by a single key
+//! light fixed relative to the object, so the lighting of an impostor won't match the lighting of
+//! the mesh exactly. Keep the transition distance large enough for the difference to be hard to
+//! notice.
+
+use crate::{bundle::Target, types::Texture};
+use amethyst_assets::Handle;
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::{Matrix4, Point3, Vector3, Vector4},
+};
+use rendy::mesh::Position;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Settings for baking an impostor atlas on the GPU, see `RenderImpostors::with_bake`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImpostorBakeSettings {
+    /// Number of views captured around the vertical axis, usually between 8 and 16. Must match
+    /// the `angles` of the layout of the impostors using the atlas.
+    pub angles: u32,
+    /// Width and height of a single view in pixels.
+    pub resolution: u32,
+    /// Direction of the key light in object space.
+    pub light_direction: [f32; 3],
+    /// Fraction of the material color visible on surfaces facing away from the key light.
+    pub ambient: f32,
+}
+
+impl Default for ImpostorBakeSettings {
+    fn default() -> Self {
+        Self {
+            angles: 8,
+            resolution: 64,
+            light_direction: [-0.4, -1.0, -0.3],
+            ambient: 0.25,
+        }
+    }
+}
+
+impl ImpostorBakeSettings {
+    /// Width and height of the atlas in pixels.
+    pub fn atlas_size(&self) -> (u32, u32) {
+        let resolution = self.resolution.max(1);
+        (resolution * self.angles.max(1), resolution)
+    }
+}
+
+/// Placement of an object's views in an impostor atlas.
+///
+/// Views are stored left to right, view `i` showing the object seen from the angle
+/// `2π * i / angles` around its Y axis, starting at the positive Z axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImpostorLayout {
+    /// Number of views in the atlas.
+    pub angles: u32,
+    /// Center of the captured area in object space.
+    pub center: [f32; 3],
+    /// Half of the width and height of the captured area in object space.
+    pub radius: f32,
+}
+
+impl ImpostorLayout {
+    /// Layout capturing the bounding sphere of the vertices of a mesh.
+    pub fn from_positions(positions: &[Position], angles: u32) -> Self {
+        let (center, radius) = bounds(positions);
+        Self {
+            angles: angles.max(1),
+            center: center.coords.into(),
+            radius,
+        }
+    }
+
+    /// Index of the view closest to the given direction towards the camera, in object space.
+    pub fn nearest_view(&self, to_camera: Vector3<f32>) -> u32 {
+        let (view, next, weight) = self.nearest_views(to_camera);
+        if weight > 0.5 {
+            next
+        } else {
+            view
+        }
+    }
+
+    /// The two views around the given direction towards the camera, in object space, and the
+    /// weight of the second one, between 0 and 1.
+    pub fn nearest_views(&self, to_camera: Vector3<f32>) -> (u32, u32, f32) {
+        let step = 2.0 * PI / self.angles as f32;
+        let angle = to_camera.x.atan2(to_camera.z) / step;
+        let view = (angle.floor() as i32).rem_euclid(self.angles as i32) as u32;
+        (view, (view + 1) % self.angles, angle - angle.floor())
+    }
+
+    /// Horizontal texture coordinates of the left and right edge of a view.
+    pub fn view_u_range(&self, view: u32) -> [f32; 2] {
+        let width = 1.0 / self.angles as f32;
+        [view as f32 * width, (view + 1) as f32 * width]
+    }
+
+    /// Transform of object space to the clip space of an atlas target, drawing the captured area
+    /// seen from given view into its slot of the atlas. Depth is reversed, nearer is greater.
+    pub fn view_transform(&self, view: u32) -> Matrix4<f32> {
+        let angle = 2.0 * PI * view as f32 / self.angles as f32;
+        let forward = Vector3::new(angle.sin(), 0.0, angle.cos());
+        let right = Vector3::new(angle.cos(), 0.0, -angle.sin());
+        let up = Vector3::y();
+        let center = Vector3::from(self.center);
+        let (width, scale) = (1.0 / self.angles as f32, 1.0 / self.radius);
+        let slot = -1.0 + (2 * view + 1) as f32 * width;
+
+        let row = |axis: Vector3<f32>, scale: f32, offset: f32| {
+            let axis = axis * scale;
+            Vector4::new(axis.x, axis.y, axis.z, offset - axis.dot(&center)).transpose()
+        };
+        Matrix4::from_rows(&[
+            row(right, scale * width, slot),
+            // Clip space Y points down, the top of the view is up.
+            row(up, -scale, 0.0),
+            row(forward, scale * 0.5, 0.5),
+            Vector4::new(0.0, 0.0, 0.0, 1.0).transpose(),
+        ])
+    }
+}
+
+/// Atlas of the views of an impostor.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImpostorAtlas {
+    /// Atlas texture, with transparent background.
+    Texture(Handle<Texture>),
+    /// Color image of the offscreen target to which `RenderImpostors::with_bake` draws the mesh
+    /// and material of the entity.
+    Baked(Target),
+}
+
+fn bounds(positions: &[Position]) -> (Point3<f32>, f32) {
+    if positions.is_empty() {
+        return (Point3::origin(), 1.0);
+    }
+    let (min, max) = positions.iter().fold(
+        (Vector3::repeat(f32::MAX), Vector3::repeat(f32::MIN)),
+        |(min, max), p| {
+            let p = Vector3::from(p.0);
+            (min.zip_map(&p, f32::min), max.zip_map(&p, f32::max))
+        },
+    );
+    let center = Point3::from((min + max) * 0.5);
+    let radius = positions
+        .iter()
+        .map(|p| (Point3::from(p.0) - center).norm())
+        .fold(0.0, f32::max);
+    (center, radius.max(f32::EPSILON))
+}
+
+/// Draw an entity as an impostor when it's far away from the camera.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Impostor {
+    /// Atlas of the views of the entity.
+    pub atlas: ImpostorAtlas,
+    /// Placement of the views in the atlas.
+    pub layout: ImpostorLayout,
+    /// Distance from the camera at which the mesh is replaced by the impostor.
+    pub transition_distance: f32,
+    /// Pixels with lower alpha are discarded.
+    pub alpha_cutoff: f32,
+    /// Whether the two views closest to the camera are blended, instead of showing the closest.
+    pub blend_views: bool,
+}
+
+impl Impostor {
+    /// Create a new `Impostor` with an atlas texture, switching from the mesh at given distance.
+    pub fn new(texture: Handle<Texture>, layout: ImpostorLayout, transition_distance: f32) -> Self {
+        Self::with_atlas(ImpostorAtlas::Texture(texture), layout, transition_distance)
+    }
+
+    /// Create a new `Impostor` showing the atlas baked into `target`, switching from the mesh at
+    /// given distance. The entity needs a mesh and a material to be baked.
+    pub fn baked(target: Target, layout: ImpostorLayout, transition_distance: f32) -> Self {
+        Self::with_atlas(ImpostorAtlas::Baked(target), layout, transition_distance)
+    }
+
+    fn with_atlas(atlas: ImpostorAtlas, layout: ImpostorLayout, transition_distance: f32) -> Self {
+        Self {
+            atlas,
+            layout,
+            transition_distance,
+            alpha_cutoff: 0.5,
+            blend_views: false,
+        }
+    }
+
+    /// Set the alpha cutoff.
+    pub fn with_alpha_cutoff(mut self, alpha_cutoff: f32) -> Self {
+        self.alpha_cutoff = alpha_cutoff;
+        self
+    }
+
+    /// Blend the two views closest to the camera if true is passed, hiding the jump between
+    /// views as the camera turns around the entity at the cost of a second texture fetch.
+    pub fn with_view_blending(mut self, blend_views: bool) -> Self {
+        self.blend_views = blend_views;
+        self
+    }
+}
+
+impl Component for Impostor {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(angles: u32) -> ImpostorLayout {
+        ImpostorLayout {
+            angles,
+            center: [0.0; 3],
+            radius: 1.0,
+        }
+    }
+
+    #[test]
+    fn nearest_view_wraps_around() {
+        let layout = layout(8);
+        assert_eq!(layout.nearest_view(Vector3::new(0.0, 0.0, 1.0)), 0);
+        assert_eq!(layout.nearest_view(Vector3::new(1.0, 0.0, 0.0)), 2);
+        assert_eq!(layout.nearest_view(Vector3::new(0.0, 5.0, -1.0)), 4);
+        assert_eq!(layout.nearest_view(Vector3::new(-1.0, 0.0, 0.0)), 6);
+        assert_eq!(layout.nearest_view(Vector3::new(-0.1, 0.0, 1.0)), 0);
+        assert_eq!(layout.view_u_range(2), [0.25, 0.375]);
+    }
+
+    #[test]
+    fn nearest_views_blend_between_neighbours() {
+        let layout = layout(4);
+        let (view, next, weight) = layout.nearest_views(Vector3::new(1.0, 0.0, 1.0));
+        assert_eq!((view, next), (0, 1));
+        assert!((weight - 0.5).abs() < 1e-5);
+
+        // Just left of the positive Z axis, between the last view and the first.
+        let (view, next, weight) = layout.nearest_views(Vector3::new(-0.1, 0.0, 1.0));
+        assert_eq!((view, next), (3, 0));
+        assert!(weight > 0.9);
+    }
+
+    #[test]
+    fn views_are_drawn_into_their_slot() {
+        let positions = [
+            Position([-1.0, -1.0, 0.0]),
+            Position([1.0, -1.0, 0.0]),
+            Position([1.0, 1.0, 0.0]),
+            Position([-1.0, 1.0, 0.0]),
+        ];
+        let layout = ImpostorLayout::from_positions(&positions, 4);
+        assert_eq!(layout.center, [0.0; 3]);
+        assert!((layout.radius - 2.0f32.sqrt()).abs() < 1e-5);
+
+        for view in 0..4 {
+            let transform = layout.view_transform(view);
+            let [left, right] = layout.view_u_range(view);
+            for p in &positions {
+                let clip = transform * Point3::from(p.0).to_homogeneous();
+                let u = clip.x * 0.5 + 0.5;
+                assert!(u >= left - 1e-5 && u <= right + 1e-5, "{} {}", view, u);
+                assert!(clip.y.abs() <= 1.0 && clip.z >= 0.0 && clip.z <= 1.0);
+            }
+        }
+
+        // Seen from the front, the top right corner is on the right and up.
+        let clip = layout.view_transform(0) * Point3::new(1.0, 1.0, 0.0).to_homogeneous();
+        assert!(clip.x > -0.75 && clip.y < 0.0);
+        // Nearer points have a greater depth.
+        let near = layout.view_transform(0) * Point3::new(0.0, 0.0, 1.0).to_homogeneous();
+        assert!(near.z > 0.5);
+    }
+}
@@ -14,14 +14,42 @@
 //! * [`DrawPbrDesc`](crate::pass::pbr::DrawPbrDesc)
 //! * [`DrawFlatDesc`](crate::pass::flat::DrawFlatDesc)
 //! * [`DrawShadedDesc`](crate::pass::shaded::DrawShadedDesc)
+//! * [`DrawShadedNormalMappedDesc`](crate::pass::shaded::DrawShadedNormalMappedDesc)
 //! * [`DrawSkyboxDesc`](crate::pass::skybox::DrawSkyboxDesc)
 //! * [`DrawDebugLinesDesc`](crate::pass::debug_lines::DrawDebugLinesDesc)
+//! * [`DrawScreenSpritesDesc`](crate::pass::screen_sprite::DrawScreenSpritesDesc)
+//! * [`DrawImpostorsDesc`](crate::pass::impostor::DrawImpostorsDesc)
+//! * [`DrawImpostorBakeDesc`](crate::pass::impostor::DrawImpostorBakeDesc)
+//! * [`DrawShellsDesc`](crate::pass::shells::DrawShellsDesc)
+//! * [`DrawBlobShadowsDesc`](crate::pass::blob_shadow::DrawBlobShadowsDesc)
+//! * [`DrawGpuParticlesDesc`](crate::pass::gpu_particles::DrawGpuParticlesDesc)
+//! * [`DrawGpuParticleSimulationDesc`](crate::pass::gpu_particles::DrawGpuParticleSimulationDesc)
+//! * [`DrawGpuParticleCopyDesc`](crate::pass::gpu_particles::DrawGpuParticleCopyDesc)
+//! * [`DrawDepthDesc`](crate::pass::depth::DrawDepthDesc)
+//! * [`DrawHiZDownsampleDesc`](crate::pass::hiz::DrawHiZDownsampleDesc)
+//! * [`DrawHiZOcclusionDebugDesc`](crate::pass::hiz::DrawHiZOcclusionDebugDesc)
+//! * [`DrawBilateralUpsampleDesc`](crate::pass::upsample::DrawBilateralUpsampleDesc)
+//! * [`DrawVolumetricsDesc`](crate::pass::volumetric::DrawVolumetricsDesc)
+//! * [`DrawFogDesc`](crate::pass::fog::DrawFogDesc)
+//! * [`DrawTransitionDesc`](crate::pass::transition::DrawTransitionDesc)
+//! * [`DrawSubsurfaceDesc`](crate::pass::subsurface::DrawSubsurfaceDesc)
+//! * [`DrawDisplayDesc`](crate::pass::display::DrawDisplayDesc), tone mapping the linear scene
+//! * [`DrawBlitDesc`](crate::pass::blit::DrawBlitDesc), drawing the image of another target
+//! * [`DrawViewClearDesc`](crate::pass::clear::DrawViewClearDesc)
+//! * [`DrawExternalDesc`](crate::pass::external::DrawExternalDesc), drawing with raw commands
+//! * [`DrawViewNormalsDesc`](crate::pass::view_mode::DrawViewNormalsDesc) and the other debug
+//!   views of [`ViewModes`](view_mode::ViewModes)
 //!
 //! ## Systems
 //!
 //! * [`RenderingSystem`](crate::system::RenderingSystem)
+//! * [`AsyncFactorySystem`](crate::async_factory::AsyncFactorySystem)
 //! * [`VisibilitySortingSystem`](crate::visibility::VisibilitySortingSystem)
 //! * [`SpriteVisibilitySortingSystem`](crate::sprite_visibility::SpriteVisibilitySortingSystem)
+//! * [`GpuMemoryStatsSystem`](crate::memory::GpuMemoryStatsSystem)
+//! * [`SocketSystem`](crate::skinning::SocketSystem)
+//! * [`MaterialAnimationSystem`](crate::material_animation::MaterialAnimationSystem)
+//! * [`GpuParticleEmitterSystem`](crate::gpu_particles::GpuParticleEmitterSystem)
 //!
 //! ## Components
 //!
@@ -29,10 +57,26 @@
 //! * [`SpriteVisibility`](sprite_visibility::SpriteVisibility)
 //! * [`Visibility`](visibility::Visibility)
 //! * [`BoundingSphere`](visibility::BoundingSphere)
+//! * [`DrawDistance`](visibility::DrawDistance)
+//! * [`RenderLayers`](view::RenderLayers)
+//! * [`Impostor`](impostor::Impostor)
+//! * [`Shells`](shells::Shells)
 //! * [`DebugLinesComponent`](debug_drawing::DebugLinesComponent)
+//! * [`DebugShapesComponent`](debug_drawing::DebugShapesComponent)
 //! * [`Light`](light::Light)
+//! * [`CapsuleOccluder`](capsule::CapsuleOccluder)
+//! * [`BlobShadow`](blob_shadow::BlobShadow)
+//! * [`GpuParticleEmitter`](gpu_particles::GpuParticleEmitter)
+//! * [`SceneSource`](formats::scene::SceneSource)
 //! * [`Tint`](resources::Tint)
+//! * [`InstanceData`](resources::InstanceData)
+//! * [`DepthMode`](resources::DepthMode)
+//! * [`DepthBias`](resources::DepthBias)
 //! * [`JointTransforms`](skinning::JointTransforms)
+//! * [`Sockets`](skinning::Sockets)
+//! * [`SocketAttachment`](skinning::SocketAttachment)
+//! * [`SubMeshes`](submesh::SubMeshes)
+//! * [`BlendShapes`](morph::BlendShapes)
 //! * [`SpriteRender`](sprite::SpriteRender)
 
 #![warn(
@@ -42,6 +86,7 @@
     rust_2018_compatibility
 )]
 #![warn(clippy::all)]
+#![recursion_limit = "256"]
 #![allow(clippy::new_without_default)]
 #![allow(unused_variables, dead_code)]
 
@@ -58,26 +103,55 @@ pub use rendy;
 
 pub mod pass;
 
+pub mod async_factory;
 pub mod batch;
+pub mod blob_shadow;
 pub mod bundle;
 pub mod camera;
+pub mod capsule;
+pub mod conventions;
 pub mod debug_drawing;
+pub mod determinism;
 pub mod error;
 pub mod formats;
+pub mod frame_graph;
+pub mod frustum;
+pub mod gpu_particles;
+pub mod hiz;
+pub mod impostor;
 pub mod light;
+pub mod load_queue;
+pub mod material_animation;
+pub mod material_shader;
+pub mod memory;
+pub mod morph;
+pub mod msaa;
 pub mod mtl;
 pub mod pipeline;
 pub mod plugins;
+pub mod preset;
 pub mod resources;
+pub mod scene;
+pub mod screen_sprite;
 pub mod serde_shim;
+pub mod shadow;
 pub mod shape;
+pub mod shells;
 pub mod skinning;
 pub mod sprite;
 pub mod sprite_visibility;
+pub mod stats;
+pub mod streaming;
+pub mod submesh;
 pub mod submodules;
 pub mod system;
+pub mod tangent;
+pub mod texture;
+pub mod transition;
 pub mod transparent;
 pub mod types;
+pub mod view;
+pub mod view_mode;
 pub mod visibility;
 
 pub mod pod;
@@ -88,16 +162,20 @@ mod render_test_bundle;
 
 #[doc(inline)]
 pub use crate::{
-    bundle::{RenderPlugin, RenderingBundle},
+    async_factory::AsyncFactory,
+    bundle::{RenderPlugin, RenderingBundle, WorkingSpace},
     camera::{ActiveCamera, Camera},
     formats::{
         mesh::MeshPrefab,
         texture::{ImageFormat, TexturePrefab},
     },
-    mtl::{Material, MaterialDefaults},
+    mtl::{Material, MaterialDefaults, MaterialPlaceholders},
     plugins::*,
     sprite::{Sprite, SpriteRender, SpriteSheet, SpriteSheetFormat},
-    system::{GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem},
+    system::{
+        GraphCreator, MeshProcessorSystem, RenderingSystem, TextureProcessorSystem,
+        TextureUploadBudget,
+    },
     transparent::Transparent,
     types::{Backend, Mesh, Texture},
     util::{simple_shader_set, ChangeDetection},
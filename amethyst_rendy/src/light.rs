@@ -2,7 +2,10 @@
 //!
 //! TODO: Remove redundant padding once `#[repr(align(...))]` stabilizes.
 
-use crate::resources::AmbientColor;
+use crate::{
+    resources::AmbientColor,
+    submodules::{MAX_DIR_LIGHTS, MAX_POINT_LIGHTS, MAX_SPOT_LIGHTS},
+};
 use amethyst_assets::{PrefabData, ProgressCounter};
 use amethyst_core::{
     ecs::prelude::{Component, DenseVecStorage, Entity, WriteStorage},
@@ -39,6 +42,9 @@ pub struct DirectionalLight {
     pub intensity: f32,
     /// Direction that the light is pointing.
     pub direction: Vector3<f32>,
+    /// Light channels of the light, as a bitmask. It only lights materials whose
+    /// `light_channels` share a channel with it, every channel by default.
+    pub channels: u32,
 }
 
 impl Default for DirectionalLight {
@@ -47,6 +53,7 @@ impl Default for DirectionalLight {
             color: Default::default(),
             intensity: 1.0,
             direction: [-1.0, -1.0, -1.0].into(),
+            channels: !0,
         }
     }
 }
@@ -91,6 +98,11 @@ pub struct PointLight {
     /// Smoothness of the light-to-dark transition from the center to the
     /// radius.
     pub smoothness: f32,
+    /// Scatter the light in the participating medium of `RenderVolumetrics`.
+    pub volumetric: bool,
+    /// Light channels of the light, as a bitmask. It only lights materials whose
+    /// `light_channels` share a channel with it, every channel by default.
+    pub channels: u32,
 }
 
 impl Default for PointLight {
@@ -100,6 +112,8 @@ impl Default for PointLight {
             intensity: 10.0,
             radius: 10.0,
             smoothness: 4.0,
+            volumetric: false,
+            channels: !0,
         }
     }
 }
@@ -115,7 +129,8 @@ impl From<PointLight> for Light {
 #[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(default)]
 pub struct SpotLight {
-    /// Opening angle of the light cone in radians.
+    /// Opening angle of the light cone in radians, between its direction and its edge. A
+    /// zero angle closes the cone, and the light lights nothing.
     pub angle: f32,
     /// Color of the light in SRGB format.
     #[serde(with = "crate::serde_shim::srgb")]
@@ -129,6 +144,11 @@ pub struct SpotLight {
     /// Smoothness of the light-to-dark transition from the center to the
     /// radius.
     pub smoothness: f32,
+    /// Scatter the light in the participating medium of `RenderVolumetrics`.
+    pub volumetric: bool,
+    /// Light channels of the light, as a bitmask. It only lights materials whose
+    /// `light_channels` share a channel with it, every channel by default.
+    pub channels: u32,
 }
 
 impl Default for SpotLight {
@@ -140,6 +160,8 @@ impl Default for SpotLight {
             intensity: 10.0,
             range: 10.0,
             smoothness: 4.0,
+            volumetric: false,
+            channels: !0,
         }
     }
 }
@@ -187,6 +209,29 @@ impl Component for Light {
     type Storage = DenseVecStorage<Self>;
 }
 
+/// Resource lowering the number of lights drawn by the 3D passes, e.g. from a graphics quality
+/// setting. The lights nearest to the camera are drawn, up to the `MAX_*_LIGHTS` of the passes.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LightLimits {
+    /// Maximum number of point lights drawn.
+    pub point: usize,
+    /// Maximum number of directional lights drawn.
+    pub directional: usize,
+    /// Maximum number of spot lights drawn.
+    pub spot: usize,
+}
+
+impl Default for LightLimits {
+    fn default() -> Self {
+        Self {
+            point: MAX_POINT_LIGHTS,
+            directional: MAX_DIR_LIGHTS,
+            spot: MAX_SPOT_LIGHTS,
+        }
+    }
+}
+
 /// Prefab for lighting
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize, PrefabData)]
 #[serde(default)]
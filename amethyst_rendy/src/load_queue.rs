@@ -0,0 +1,305 @@
+//! Prioritized, cancellable background loading of meshes and textures.
+//!
+//! `LoadQueue` runs user provided loading jobs on the asset thread pool, highest priority first
+//! and with a limited number of jobs running at once. Finished data is submitted to an
+//! `AsyncFactory`, and the resulting `Pending` resource is delivered to the completion callback.
+//!
+//! Priorities can be changed and jobs can be cancelled through the `LoadToken` returned on
+//! submission. A job cancelled before it finished never reaches the GPU. Once the completion
+//! callback is called the resource belongs to the callback, and dropping its last handle
+//! releases it through the usual asset storage path, which defers the actual destruction
+//! until the GPU is done with it.
+
+use crate::{
+    async_factory::{AsyncFactory, Pending},
+    types::{Mesh, MeshData, Texture, TextureData},
+};
+use amethyst_assets::ThreadPool;
+use amethyst_error::Error;
+use std::{
+    any::Any,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Data produced by a loading job.
+#[derive(Debug)]
+pub enum LoadedData {
+    /// Mesh data to be uploaded.
+    Mesh(Box<MeshData>),
+    /// Texture data to be uploaded.
+    Texture(TextureData),
+}
+
+impl From<MeshData> for LoadedData {
+    fn from(data: MeshData) -> Self {
+        LoadedData::Mesh(Box::new(data))
+    }
+}
+
+impl From<TextureData> for LoadedData {
+    fn from(data: TextureData) -> Self {
+        LoadedData::Texture(data)
+    }
+}
+
+/// Outcome of a loading job, delivered to its completion callback.
+#[derive(Debug)]
+pub enum LoadResult {
+    /// Mesh queued for creation.
+    Mesh(Pending<Mesh>),
+    /// Texture queued for creation.
+    Texture(Pending<Texture>),
+    /// The job failed to produce its data, or panicked while producing it.
+    Failed(Error),
+}
+
+/// Handle to a submitted loading job, used to update its priority or cancel it.
+#[derive(Debug, Clone)]
+pub struct LoadToken {
+    state: Arc<JobState>,
+}
+
+#[derive(Debug)]
+struct JobState {
+    priority: AtomicI32,
+    cancelled: AtomicBool,
+}
+
+impl LoadToken {
+    /// Change the priority of the job. Jobs with higher priority are started first.
+    /// Has no effect if the job is already running.
+    pub fn set_priority(&self, priority: i32) {
+        self.state.priority.store(priority, Ordering::Relaxed);
+    }
+
+    /// Current priority of the job.
+    pub fn priority(&self) -> i32 {
+        self.state.priority.load(Ordering::Relaxed)
+    }
+
+    /// Cancel the job. If it is already running, its result is discarded
+    /// and the completion callback is never called.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check if the job was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+type LoadFn = Box<dyn FnOnce() -> Result<LoadedData, Error> + Send>;
+type CompleteFn = Box<dyn FnOnce(LoadResult) + Send>;
+
+struct Job {
+    state: Arc<JobState>,
+    load: LoadFn,
+    on_complete: CompleteFn,
+}
+
+struct Shared {
+    waiting: Vec<Job>,
+    running: usize,
+}
+
+/// A queue of loading jobs in front of an `AsyncFactory`. Cheap to clone, usable from any thread.
+#[derive(derivative::Derivative, Clone)]
+#[derivative(Debug)]
+pub struct LoadQueue {
+    #[derivative(Debug = "ignore")]
+    shared: Arc<Mutex<Shared>>,
+    #[derivative(Debug = "ignore")]
+    pool: Arc<ThreadPool>,
+    factory: AsyncFactory,
+    max_concurrent: usize,
+}
+
+impl LoadQueue {
+    /// Create a new queue running at most `max_concurrent` jobs at once on the given pool.
+    pub fn new(factory: AsyncFactory, pool: Arc<ThreadPool>, max_concurrent: usize) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(Shared {
+                waiting: Vec::new(),
+                running: 0,
+            })),
+            pool,
+            factory,
+            max_concurrent: max_concurrent.max(1),
+        }
+    }
+
+    /// Submit a new loading job with given priority.
+    ///
+    /// `load` is executed on the thread pool. Unless the job gets cancelled,
+    /// `on_complete` is called on the same thread with the created resource.
+    pub fn submit<L, C>(&self, priority: i32, load: L, on_complete: C) -> LoadToken
+    where
+        L: FnOnce() -> Result<LoadedData, Error> + Send + 'static,
+        C: FnOnce(LoadResult) + Send + 'static,
+    {
+        let state = Arc::new(JobState {
+            priority: AtomicI32::new(priority),
+            cancelled: AtomicBool::new(false),
+        });
+        self.shared.lock().unwrap().waiting.push(Job {
+            state: state.clone(),
+            load: Box::new(load),
+            on_complete: Box::new(on_complete),
+        });
+        self.dispatch();
+        LoadToken { state }
+    }
+
+    /// Number of jobs that were not started yet, including cancelled ones
+    /// that were not removed from the queue yet.
+    pub fn waiting(&self) -> usize {
+        self.shared.lock().unwrap().waiting.len()
+    }
+
+    /// Number of jobs currently running.
+    pub fn running(&self) -> usize {
+        self.shared.lock().unwrap().running
+    }
+
+    fn dispatch(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared
+            .waiting
+            .retain(|job| !job.state.cancelled.load(Ordering::Relaxed));
+        while shared.running < self.max_concurrent && !shared.waiting.is_empty() {
+            let next = shared
+                .waiting
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, job)| job.state.priority.load(Ordering::Relaxed))
+                .map(|(i, _)| i)
+                .expect("Queue is not empty");
+            let job = shared.waiting.swap_remove(next);
+            shared.running += 1;
+
+            let queue = self.clone();
+            self.pool.spawn(move || {
+                // A panicking completion callback must not leave its slot taken forever.
+                if catch_unwind(AssertUnwindSafe(|| queue.run(job))).is_err() {
+                    log::error!("Load queue completion callback panicked");
+                }
+                queue.shared.lock().unwrap().running -= 1;
+                queue.dispatch();
+            });
+        }
+    }
+
+    fn run(&self, job: Job) {
+        if job.state.cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let load = job.load;
+        let result = catch_unwind(AssertUnwindSafe(load))
+            .unwrap_or_else(|panic| Err(Error::from_string(panic_message(&*panic))));
+        if job.state.cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let result = match result {
+            Ok(LoadedData::Mesh(data)) => LoadResult::Mesh(self.factory.create_mesh(*data)),
+            Ok(LoadedData::Texture(data)) => LoadResult::Texture(self.factory.create_texture(data)),
+            Err(err) => LoadResult::Failed(err),
+        };
+        (job.on_complete)(result);
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    format!("Loading job panicked: {}", message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::ThreadPoolBuilder;
+    use rendy::mesh::MeshBuilder;
+    use std::{sync::mpsc, time::Duration};
+
+    fn mesh() -> Result<LoadedData, Error> {
+        Ok(MeshData::from(MeshBuilder::new()).into())
+    }
+
+    #[test]
+    fn runs_by_priority_and_skips_cancelled() {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let factory = AsyncFactory::default();
+        let queue = LoadQueue::new(factory.clone(), pool, 1);
+
+        let (block_tx, block_rx) = mpsc::channel::<()>();
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let submit = |name: &'static str, priority| {
+            let done_tx = done_tx.clone();
+            queue.submit(priority, mesh, move |_| done_tx.send(name).unwrap())
+        };
+
+        let first = {
+            let done_tx = done_tx.clone();
+            queue.submit(
+                0,
+                move || {
+                    block_rx.recv().unwrap();
+                    mesh()
+                },
+                move |_| done_tx.send("first").unwrap(),
+            )
+        };
+        let low = submit("low", 1);
+        let high = submit("high", 5);
+        let cancelled = submit("cancelled", 10);
+        let raised = submit("raised", 2);
+
+        cancelled.cancel();
+        raised.set_priority(7);
+        assert_eq!(first.priority(), 0);
+        assert!(!low.is_cancelled() && !high.is_cancelled());
+        block_tx.send(()).unwrap();
+
+        let order: Vec<_> = (0..4)
+            .map(|_| done_rx.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        assert_eq!(order, vec!["first", "raised", "high", "low"]);
+        assert!(done_rx.recv_timeout(Duration::from_millis(100)).is_err());
+        assert_eq!(factory.queued(), 4);
+    }
+
+    #[test]
+    fn panicking_job_fails_and_frees_its_slot() {
+        let pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+        let factory = AsyncFactory::default();
+        let queue = LoadQueue::new(factory.clone(), pool, 1);
+        let (done_tx, done_rx) = mpsc::channel();
+
+        let panicking_tx = done_tx.clone();
+        queue.submit(
+            1,
+            || panic!("corrupt file"),
+            move |result| panicking_tx.send(result).unwrap(),
+        );
+        queue.submit(0, mesh, move |result| done_tx.send(result).unwrap());
+
+        match done_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            LoadResult::Failed(err) => assert!(err.to_string().contains("corrupt file")),
+            other => panic!("Expected failure, got {:?}", other),
+        }
+        match done_rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            LoadResult::Mesh(_) => {}
+            other => panic!("Expected mesh, got {:?}", other),
+        }
+        assert_eq!(factory.queued(), 1);
+    }
+}
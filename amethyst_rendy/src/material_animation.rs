@@ -0,0 +1,405 @@
+//! Animation of material parameters over time.
+//!
+//! Materials are shared by every entity drawing them: animating a material animates all of
+//! them. Tracks are evaluated every frame by the `MaterialAnimationSystem`, written to the
+//! material asset, and the material is marked in `MaterialChanges`, so that only its region
+//! of the material constant buffer is written again.
+
+use crate::{
+    determinism::RenderDeterminism,
+    mtl::{Material, MaterialChanges, TextureOffset, UvTransform},
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Read, System, Write},
+    Time,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Parameter of a `Material` animated by a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MaterialParameter {
+    /// `Material::alpha_cutoff`, from the first component of the value.
+    AlphaCutoff,
+    /// `Material::emission_factor`, from the first three components of the value.
+    EmissionFactor,
+    /// `Material::dissolve_amount`, from the first component of the value.
+    DissolveAmount,
+    /// `Material::dissolve_edge_color`, from the first three components of the value.
+    DissolveEdgeColor,
+    /// Offset added to the `Material::uv_offset` the material had when first animated, from
+    /// the first two components of the value. The offset wraps around at 1, so it can scroll
+    /// forever on repeating textures.
+    UvOffset,
+    /// Offset of a `UvTransform` of the material, from the first two components of the value.
+    UvTransformOffset(UvTransformMaps),
+    /// Scale of a `UvTransform` of the material, from the first two components of the value.
+    UvTransformScale(UvTransformMaps),
+    /// Rotation of a `UvTransform` of the material in radians, from the first component of the
+    /// value.
+    UvTransformRotation(UvTransformMaps),
+}
+
+/// Maps of a material transformed by one of its `UvTransform`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UvTransformMaps {
+    /// `Material::uv_transform`, of every map but the normal map.
+    Base,
+    /// `Material::normal_uv_transform`, of the normal map.
+    Normal,
+}
+
+impl UvTransformMaps {
+    fn of(self, material: &mut Material) -> &mut UvTransform {
+        match self {
+            UvTransformMaps::Base => &mut material.uv_transform,
+            UvTransformMaps::Normal => &mut material.normal_uv_transform,
+        }
+    }
+}
+
+/// Value of a track over time, in seconds since the start of the animation.
+///
+/// Values have four components, parameters only use as many as they need.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MaterialCurve {
+    /// `base + amplitude * sin(2π * (frequency * t + phase))`.
+    Pulse {
+        /// Value around which the pulse oscillates.
+        base: [f32; 4],
+        /// Amplitude of the oscillation.
+        amplitude: [f32; 4],
+        /// Oscillations per second.
+        frequency: f32,
+        /// Phase of the oscillation, in oscillations.
+        phase: f32,
+    },
+    /// `start + velocity * t`.
+    Scroll {
+        /// Value at the start of the animation.
+        start: [f32; 4],
+        /// Change of the value per second.
+        velocity: [f32; 4],
+    },
+    /// Linear interpolation between keyframes of increasing time, holding the first and last
+    /// values outside of them. Loops over the time of the last keyframe if `looping`.
+    Keyframes {
+        /// Times and values of the keyframes.
+        keys: Vec<(f32, [f32; 4])>,
+        /// Whether the keyframes repeat after the last one.
+        looping: bool,
+    },
+}
+
+impl MaterialCurve {
+    /// Value of the curve at given time.
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        match self {
+            MaterialCurve::Pulse {
+                base,
+                amplitude,
+                frequency,
+                phase,
+            } => {
+                let s = (2.0 * PI * (frequency * t + phase)).sin();
+                lerp_each(base, amplitude, |b, a| b + a * s)
+            }
+            MaterialCurve::Scroll { start, velocity } => {
+                lerp_each(start, velocity, |s, v| s + v * t)
+            }
+            MaterialCurve::Keyframes { keys, looping } => {
+                let (first, last) = match (keys.first(), keys.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return [0.0; 4],
+                };
+                let t = if *looping && last.0 > 0.0 {
+                    t.rem_euclid(last.0)
+                } else {
+                    t
+                };
+                if t <= first.0 {
+                    return first.1;
+                }
+                match keys.windows(2).find(|w| t <= w[1].0) {
+                    Some(w) => {
+                        let span = w[1].0 - w[0].0;
+                        let f = if span > 0.0 { (t - w[0].0) / span } else { 1.0 };
+                        lerp_each(&w[0].1, &w[1].1, |a, b| a + (b - a) * f)
+                    }
+                    None => last.1,
+                }
+            }
+        }
+    }
+}
+
+fn lerp_each(a: &[f32; 4], b: &[f32; 4], f: impl Fn(f32, f32) -> f32) -> [f32; 4] {
+    [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])]
+}
+
+/// Curve animating a parameter of a material.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialTrack {
+    /// Animated parameter.
+    pub parameter: MaterialParameter,
+    /// Value of the parameter over time.
+    pub curve: MaterialCurve,
+}
+
+impl MaterialTrack {
+    /// Create a new track animating given parameter.
+    pub fn new(parameter: MaterialParameter, curve: MaterialCurve) -> Self {
+        Self { parameter, curve }
+    }
+
+    /// Write the value of the track at given time to a material.
+    fn apply(&self, t: f32, base: &TextureOffset, material: &mut Material) {
+        let value = self.curve.sample(t);
+        match self.parameter {
+            MaterialParameter::AlphaCutoff => material.alpha_cutoff = value[0],
+            MaterialParameter::EmissionFactor => {
+                material.emission_factor = [value[0], value[1], value[2]]
+            }
+            MaterialParameter::DissolveAmount => material.dissolve_amount = value[0],
+            MaterialParameter::DissolveEdgeColor => {
+                material.dissolve_edge_color = [value[0], value[1], value[2]]
+            }
+            MaterialParameter::UvOffset => {
+                let (du, dv) = (value[0].rem_euclid(1.0), value[1].rem_euclid(1.0));
+                material.uv_offset = TextureOffset {
+                    u: (base.u.0 + du, base.u.1 + du),
+                    v: (base.v.0 + dv, base.v.1 + dv),
+                };
+            }
+            MaterialParameter::UvTransformOffset(maps) => {
+                maps.of(material).offset = [value[0], value[1]]
+            }
+            MaterialParameter::UvTransformScale(maps) => {
+                maps.of(material).scale = [value[0], value[1]]
+            }
+            MaterialParameter::UvTransformRotation(maps) => maps.of(material).rotation = value[0],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AnimatedMaterial {
+    handle: Handle<Material>,
+    tracks: Vec<MaterialTrack>,
+    start: Option<f64>,
+    base_offset: Option<TextureOffset>,
+}
+
+/// Resource holding the tracks of every animated material, evaluated by the
+/// `MaterialAnimationSystem` against the absolute frame time.
+///
+/// Tracks of a material start together, the first frame it's loaded after being animated.
+/// Tracks animating the same parameter are applied in the order they were added, the last one
+/// wins.
+#[derive(Debug, Default)]
+pub struct MaterialAnimator {
+    materials: Vec<AnimatedMaterial>,
+}
+
+impl MaterialAnimator {
+    /// Add a track animating a material.
+    pub fn animate(&mut self, handle: &Handle<Material>, track: MaterialTrack) {
+        match self.materials.iter_mut().find(|m| &m.handle == handle) {
+            Some(animated) => animated.tracks.push(track),
+            None => self.materials.push(AnimatedMaterial {
+                handle: handle.clone(),
+                tracks: vec![track],
+                start: None,
+                base_offset: None,
+            }),
+        }
+    }
+
+    /// Stop animating a material, leaving its parameters at their current values.
+    pub fn stop(&mut self, handle: &Handle<Material>) {
+        self.materials.retain(|m| &m.handle != handle);
+    }
+
+    /// Returns `true` if the material has any track.
+    pub fn is_animated(&self, handle: &Handle<Material>) -> bool {
+        self.materials.iter().any(|m| &m.handle == handle)
+    }
+
+    /// Write the value of every track at given absolute time to its material, marking the
+    /// materials written in `changes`.
+    pub fn apply(
+        &mut self,
+        time: f64,
+        storage: &mut AssetStorage<Material>,
+        changes: &mut MaterialChanges,
+    ) {
+        for animated in &mut self.materials {
+            let material = match storage.get_mut(&animated.handle) {
+                Some(material) => material,
+                None => continue,
+            };
+            let start = *animated.start.get_or_insert(time);
+            let base = animated
+                .base_offset
+                .get_or_insert_with(|| material.uv_offset.clone());
+            let t = (time - start) as f32;
+            for track in &animated.tracks {
+                track.apply(t, base, material);
+            }
+            changes.mark(&animated.handle);
+        }
+    }
+}
+
+/// Evaluates the tracks of the `MaterialAnimator` every frame.
+#[derive(Debug, Default)]
+pub struct MaterialAnimationSystem;
+
+impl<'a> System<'a> for MaterialAnimationSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, RenderDeterminism>,
+        Write<'a, MaterialAnimator>,
+        Write<'a, AssetStorage<Material>>,
+        Write<'a, MaterialChanges>,
+    );
+
+    fn run(
+        &mut self,
+        (time, determinism, mut animator, mut storage, mut changes): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("material_animation_system");
+
+        animator.apply(
+            determinism.absolute_time_seconds(&time),
+            &mut storage,
+            &mut changes,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material_shader::MAX_MATERIAL_SHADER_PARAMS, texture::uv_gradient_data, types::Texture,
+    };
+    use amethyst_assets::Loader;
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn material(uv_offset: TextureOffset) -> Material {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let tex_storage = AssetStorage::<Texture>::new();
+        let tex = loader.load_from_data(uv_gradient_data(4), (), &tex_storage);
+        Material {
+            alpha_cutoff: 0.0,
+            albedo: tex.clone(),
+            emission: tex.clone(),
+            normal: tex.clone(),
+            metallic_roughness: tex.clone(),
+            ambient_occlusion: tex.clone(),
+            cavity: tex.clone(),
+            uv_offset,
+            emission_factor: [1.0; 3],
+            uv_transform: UvTransform::default(),
+            normal_uv_transform: UvTransform::default(),
+            dissolve_noise: tex.clone(),
+            dissolve_amount: 0.0,
+            dissolve_edge_width: 0.0,
+            dissolve_edge_color: [0.0; 3],
+            triplanar_scale: 0.0,
+            triplanar_sharpness: 1.0,
+            detail: tex.clone(),
+            detail_scale: 1.0,
+            detail_blend: Default::default(),
+            detail_fade_distance: 0.0,
+            layer_mask: tex,
+            layer_color: [1.0; 3],
+            layer_roughness: 0.0,
+            layer_amount: 0.0,
+            layer_scene_factor: 0.0,
+            light_channels: !0,
+            subsurface: 0.0,
+            specular: [0.0; 3],
+            shininess: 1.0,
+            shader_params: [[0.0; 4]; MAX_MATERIAL_SHADER_PARAMS],
+        }
+    }
+
+    #[test]
+    fn curves_sample_pulse_scroll_and_keyframes() {
+        let pulse = MaterialCurve::Pulse {
+            base: [1.0; 4],
+            amplitude: [0.5; 4],
+            frequency: 1.0,
+            phase: 0.0,
+        };
+        assert!((pulse.sample(0.25)[0] - 1.5).abs() < 1e-5);
+        assert!((pulse.sample(0.75)[1] - 0.5).abs() < 1e-5);
+
+        let scroll = MaterialCurve::Scroll {
+            start: [0.0; 4],
+            velocity: [0.5, -1.0, 0.0, 0.0],
+        };
+        assert_eq!(scroll.sample(2.0), [1.0, -2.0, 0.0, 0.0]);
+
+        let keys = MaterialCurve::Keyframes {
+            keys: vec![(0.0, [0.0; 4]), (1.0, [1.0; 4]), (2.0, [0.0; 4])],
+            looping: true,
+        };
+        assert_eq!(keys.sample(0.5), [0.5; 4]);
+        assert_eq!(keys.sample(1.5), [0.5; 4]);
+        assert_eq!(keys.sample(2.5), [0.5; 4]);
+        assert_eq!(keys.sample(-0.5), [0.5; 4]);
+    }
+
+    #[test]
+    fn uv_offset_scrolls_from_base_and_wraps() {
+        let track = MaterialTrack::new(
+            MaterialParameter::UvOffset,
+            MaterialCurve::Scroll {
+                start: [0.0; 4],
+                velocity: [0.25, 0.0, 0.0, 0.0],
+            },
+        );
+        let base = TextureOffset {
+            u: (0.0, 0.5),
+            v: (0.0, 1.0),
+        };
+        let mut material = material(base.clone());
+        track.apply(5.0, &base, &mut material);
+        assert_eq!(material.uv_offset.u, (0.25, 0.75));
+        assert_eq!(material.uv_offset.v, (0.0, 1.0));
+    }
+
+    #[test]
+    fn uv_transform_rotates_around_texture_center() {
+        let transform = UvTransform {
+            offset: [0.25, 0.0],
+            scale: [2.0, 1.0],
+            rotation: PI / 2.0,
+        };
+        let close = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).abs() + (a[1] - b[1]).abs() < 1e-5;
+        assert!(close(transform.apply([0.5, 0.5]), [0.75, 0.5]));
+        assert!(close(transform.apply([1.0, 0.5]), [0.75, 1.5]));
+        assert!(close(UvTransform::default().apply([0.3, 0.7]), [0.3, 0.7]));
+
+        let mut material = material(TextureOffset::default());
+        let track = MaterialTrack::new(
+            MaterialParameter::UvTransformRotation(UvTransformMaps::Normal),
+            MaterialCurve::Scroll {
+                start: [0.0; 4],
+                velocity: [1.0, 0.0, 0.0, 0.0],
+            },
+        );
+        track.apply(2.0, &TextureOffset::default(), &mut material);
+        assert_eq!(material.normal_uv_transform.rotation, 2.0);
+        assert_eq!(material.uv_transform, UvTransform::default());
+    }
+}
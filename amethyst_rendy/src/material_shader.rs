@@ -0,0 +1,382 @@
+//! GLSL snippets tweaking the materials of the 3D passes.
+//!
+//! A [MaterialShader] is injected into the fragment shader of `DrawShadedDesc` or
+//! `DrawPbrDesc`, after the maps of the material are sampled and before it is lit. The snippet
+//! is a block of statements modifying any of these variables:
+//!
+//! * `vec3 albedo`, the linear albedo of the material.
+//! * `vec3 normal`, the world space normal, normalized again after the snippet.
+//! * `vec3 emission`, the linear emission of the material.
+//! * `float alpha`, the opacity of the material, cut off again after the snippet.
+//!
+//! The snippet can read the inputs of the fragment shader, e.g. `vertex.position`,
+//! `camera_position` or `final_tex_coords`, and the parameters it declares, read every frame
+//! from `Material::shader_params`. E.g. a fresnel tint:
+//!
+//! ```
+//! # use amethyst_rendy::material_shader::MaterialShader;
+//! let fresnel = MaterialShader::new(
+//!     "float facing = abs(dot(normalize(camera_position - vertex.position), normal));
+//!      albedo = mix(tint.rgb, albedo, pow(facing, power));",
+//! )
+//! .with_vec4("tint")
+//! .with_float("power");
+//! ```
+//!
+//! Passes compile their material shader when they are built, which requires the
+//! `shader-compiler` feature. Errors in the snippet are reported with the line numbers of the
+//! snippet, in the file named `material_shader`.
+
+use crate::rendy::shader::SpirvShader;
+use failure::format_err;
+
+/// Maximum number of parameters a [MaterialShader] declares.
+pub const MAX_MATERIAL_SHADER_PARAMS: usize = 4;
+
+/// Line of the fragment shaders the snippet of a [MaterialShader] replaces.
+const HOOK: &str = "// MATERIAL_SHADER";
+
+/// Name of the snippet in the errors of the shader compiler.
+const SNIPPET_NAME: &str = "material_shader";
+
+/// Variables the snippet modifies, which parameters can't shadow.
+const VARIABLES: [&str; 4] = ["albedo", "normal", "emission", "alpha"];
+
+/// Headers included by the fragment shaders accepting a [MaterialShader].
+const HEADERS: [(&str, &str); 9] = [
+    (
+        "header/math.frag",
+        include_str!("../shaders/fragment/header/math.frag"),
+    ),
+    (
+        "header/dissolve.frag",
+        include_str!("../shaders/fragment/header/dissolve.frag"),
+    ),
+    (
+        "header/triplanar.frag",
+        include_str!("../shaders/fragment/header/triplanar.frag"),
+    ),
+    (
+        "header/detail.frag",
+        include_str!("../shaders/fragment/header/detail.frag"),
+    ),
+    (
+        "header/environment.frag",
+        include_str!("../shaders/fragment/header/environment.frag"),
+    ),
+    (
+        "header/fog.frag",
+        include_str!("../shaders/fragment/header/fog.frag"),
+    ),
+    (
+        "header/shadow.frag",
+        include_str!("../shaders/fragment/header/shadow.frag"),
+    ),
+    (
+        "header/capsule_occlusion.frag",
+        include_str!("../shaders/fragment/header/capsule_occlusion.frag"),
+    ),
+    (
+        "header/surface_layer.frag",
+        include_str!("../shaders/fragment/header/surface_layer.frag"),
+    ),
+];
+
+/// Type of a parameter of a [MaterialShader].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaterialShaderParam {
+    /// `float`, the first component of its `Material::shader_params`.
+    Float,
+    /// `vec4`, all of its `Material::shader_params`.
+    Vec4,
+}
+
+/// GLSL snippet modifying the materials of a 3D pass before they are lit, see the
+/// [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialShader {
+    code: String,
+    params: Vec<(String, MaterialShaderParam)>,
+}
+
+impl MaterialShader {
+    /// Snippet of GLSL statements modifying the material.
+    pub fn new(code: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Declare a `float` parameter read from the next `Material::shader_params`.
+    pub fn with_float(self, name: impl Into<String>) -> Self {
+        self.with_param(name, MaterialShaderParam::Float)
+    }
+
+    /// Declare a `vec4` parameter read from the next `Material::shader_params`.
+    pub fn with_vec4(self, name: impl Into<String>) -> Self {
+        self.with_param(name, MaterialShaderParam::Vec4)
+    }
+
+    /// Declare a parameter read from the next `Material::shader_params`, up to
+    /// [MAX_MATERIAL_SHADER_PARAMS].
+    pub fn with_param(mut self, name: impl Into<String>, param: MaterialShaderParam) -> Self {
+        self.params.push((name.into(), param));
+        self
+    }
+
+    /// GLSL statements of the snippet.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Index of the `Material::shader_params` holding the parameter of given name.
+    pub fn param_index(&self, name: &str) -> Option<usize> {
+        self.params.iter().position(|(param, _)| param == name)
+    }
+
+    fn validate(&self) -> Result<(), failure::Error> {
+        if self.params.len() > MAX_MATERIAL_SHADER_PARAMS {
+            return Err(format_err!(
+                "Material shader declares {} parameters, at most {} are supported",
+                self.params.len(),
+                MAX_MATERIAL_SHADER_PARAMS
+            ));
+        }
+        for (i, (name, _)) in self.params.iter().enumerate() {
+            let mut chars = name.chars();
+            let identifier = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !name.starts_with("gl_");
+            if !identifier {
+                return Err(format_err!(
+                    "Material shader parameter `{}` isn't a GLSL identifier",
+                    name
+                ));
+            }
+            if VARIABLES.contains(&name.as_str()) {
+                return Err(format_err!(
+                    "Material shader parameter `{}` shadows the variable it modifies",
+                    name
+                ));
+            }
+            if self.param_index(name) != Some(i) {
+                return Err(format_err!(
+                    "Material shader parameter `{}` is declared twice",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fragment shader `source` named `name` with the snippet injected and its includes
+    /// resolved, compiled with given defines.
+    pub(crate) fn inject(
+        &self,
+        name: &str,
+        source: &str,
+        defines: &[&str],
+    ) -> Result<String, failure::Error> {
+        self.validate()?;
+
+        let mut resolved = Vec::new();
+        resolve_includes("", source, &mut resolved, &mut Vec::new())?;
+        if !resolved.iter().any(|&(_, _, line)| line.trim() == HOOK) {
+            return Err(format_err!(
+                "Fragment shader {} doesn't accept a material shader",
+                name
+            ));
+        }
+
+        // Lines are numbered in the file they come from, the main file being named `name`.
+        // `next` is the position the compiler assigns to the next line without a `#line`.
+        let mut out = String::new();
+        let mut next = ("", 1);
+        for &(path, number, line) in &resolved {
+            if (path, number) != next {
+                let file = if path.is_empty() { name } else { path };
+                out.push_str(&format!("#line {} \"{}\"\n", number, file));
+            }
+            next = (path, number + 1);
+            if line.trim() == HOOK {
+                out.push_str("    {\n");
+                for (index, (param, kind)) in self.params.iter().enumerate() {
+                    out.push_str(&match kind {
+                        MaterialShaderParam::Float => {
+                            format!("    float {} = shader_params[{}].x;\n", param, index)
+                        }
+                        MaterialShaderParam::Vec4 => {
+                            format!("    vec4 {} = shader_params[{}];\n", param, index)
+                        }
+                    });
+                }
+                out.push_str(&format!("#line 1 \"{}\"\n", SNIPPET_NAME));
+                out.push_str(&self.code);
+                out.push_str("\n    }\n");
+                next = ("", 0);
+                continue;
+            }
+            out.push_str(line);
+            out.push('\n');
+            if line.starts_with("#version") {
+                out.push_str("#extension GL_GOOGLE_cpp_style_line_directive : require\n");
+                out.push_str("#define MATERIAL_SHADER\n");
+                for define in defines {
+                    out.push_str(&format!("#define {}\n", define));
+                }
+                next = ("", 0);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Compile the fragment shader `source` named `name` with the snippet injected.
+    #[cfg(feature = "shader-compiler")]
+    pub(crate) fn compile(
+        &self,
+        name: &'static str,
+        source: &str,
+        defines: &[&str],
+    ) -> Result<SpirvShader, failure::Error> {
+        use crate::{
+            error::{RenderError, ShaderError},
+            rendy::shader::{ShaderKind, SourceCodeShaderInfo, SourceLanguage},
+        };
+
+        let source = self.inject(name, source, defines)?;
+        SourceCodeShaderInfo::new(
+            source,
+            name,
+            ShaderKind::Fragment,
+            SourceLanguage::GLSL,
+            "main",
+        )
+        .precompile()
+        .map_err(|e| RenderError::from(ShaderError::new(name, e.to_string())).into())
+    }
+
+    /// Compile the fragment shader `source` named `name` with the snippet injected.
+    #[cfg(not(feature = "shader-compiler"))]
+    pub(crate) fn compile(
+        &self,
+        name: &'static str,
+        source: &str,
+        defines: &[&str],
+    ) -> Result<SpirvShader, failure::Error> {
+        self.inject(name, source, defines)?;
+        Err(format_err!(
+            "Material shader of {} can't be compiled without the `shader-compiler` feature",
+            name
+        ))
+    }
+}
+
+/// Append the lines of `source` located at `path` to `out` with their path and line number,
+/// replacing its includes by the headers, every header included once.
+fn resolve_includes<'a>(
+    path: &'a str,
+    source: &'a str,
+    out: &mut Vec<(&'a str, usize, &'a str)>,
+    included: &mut Vec<&'static str>,
+) -> Result<(), failure::Error> {
+    let dir = path.rfind('/').map_or("", |end| &path[..=end]);
+    for (i, line) in source.lines().enumerate() {
+        let include = line
+            .trim()
+            .strip_prefix("#include")
+            .map(|include| include.trim().trim_matches('"'));
+        match include {
+            Some(include) => {
+                let header_path = format!("{}{}", dir, include);
+                let &(header_path, header) = HEADERS
+                    .iter()
+                    .find(|(header, _)| *header == header_path)
+                    .ok_or_else(|| format_err!("Unknown shader header {}", header_path))?;
+                if !included.contains(&header_path) {
+                    included.push(header_path);
+                    resolve_includes(header_path, header, out, included)?;
+                }
+            }
+            None => out.push((path, i + 1, line)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADED: &str = include_str!("../shaders/fragment/shaded.frag");
+
+    #[test]
+    fn snippet_lines_are_numbered_from_one() {
+        let shader = MaterialShader::new("albedo *= tint.rgb;\nalpha *= fade;")
+            .with_vec4("tint")
+            .with_float("fade");
+        let source = shader
+            .inject("shaded.frag", SHADED, &["TRIPLANAR"])
+            .unwrap();
+        assert!(!source.contains("#include"));
+        assert!(source.contains("#define TRIPLANAR\n"));
+
+        let lines = source.lines().collect::<Vec<_>>();
+        let start = lines
+            .iter()
+            .position(|line| *line == "#line 1 \"material_shader\"")
+            .unwrap();
+        assert_eq!(lines[start - 2], "    vec4 tint = shader_params[0];");
+        assert_eq!(lines[start - 1], "    float fade = shader_params[1].x;");
+        assert_eq!(lines[start + 1], "albedo *= tint.rgb;");
+        assert_eq!(lines[start + 2], "alpha *= fade;");
+    }
+
+    #[test]
+    fn lines_after_includes_and_snippet_keep_their_numbers() {
+        let shader = MaterialShader::new("albedo.r = 1.0;");
+        let source = shader.inject("shaded.frag", SHADED, &[]).unwrap();
+        let lines = source.lines().collect::<Vec<_>>();
+
+        let header = lines
+            .iter()
+            .position(|line| *line == "#line 1 \"header/math.frag\"")
+            .unwrap();
+        assert_eq!(
+            lines[header + 1],
+            include_str!("../shaders/fragment/header/math.frag")
+                .lines()
+                .next()
+                .unwrap()
+        );
+
+        let hook = SHADED.lines().position(|line| line.trim() == HOOK).unwrap();
+        let snippet = lines
+            .iter()
+            .position(|line| *line == "#line 1 \"material_shader\"")
+            .unwrap();
+        assert_eq!(lines[snippet + 2], "    }");
+        assert_eq!(
+            lines[snippet + 3],
+            format!("#line {} \"shaded.frag\"", hook + 2)
+        );
+        assert_eq!(lines[snippet + 4], SHADED.lines().nth(hook + 1).unwrap());
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let inject = |shader: MaterialShader| shader.inject("shaded.frag", SHADED, &[]);
+        assert!(inject(MaterialShader::new("").with_float("albedo")).is_err());
+        assert!(inject(MaterialShader::new("").with_float("a").with_vec4("a")).is_err());
+        assert!(inject(MaterialShader::new("").with_float("2x")).is_err());
+        let many = (0..=MAX_MATERIAL_SHADER_PARAMS).fold(MaterialShader::new(""), |shader, i| {
+            shader.with_float(format!("p{}", i))
+        });
+        assert!(inject(many).is_err());
+        assert!(MaterialShader::new("")
+            .inject("flat.frag", "#version 450\nvoid main() {}\n", &[])
+            .is_err());
+    }
+}
@@ -0,0 +1,402 @@
+//! Introspection of the GPU resources kept alive by the renderer asset storages.
+//!
+//! Meshes and textures are destroyed once the last `Handle` pointing at them is dropped and
+//! their storage is processed. The underlying buffers and images are not freed immediately:
+//! `rendy` keeps them around until every frame that could still be reading them has completed.
+//! The functions in this module help finding out what is still pinned by some handle.
+//!
+//! The `GpuMemoryStatsSystem` summarizes the same information into `RenderStats::gpu_memory`,
+//! split by category and by named `ResourceGroups`. It only recomputes it on frames where the
+//! loaded meshes and textures, their handles or the groups changed.
+//!
+//! All sizes are approximations. They do not account for alignment, padding or any other
+//! driver-specific overhead, so they will never exactly match what the driver reports.
+//! Mesh sizes are rougher still, see [estimated_mesh_bytes]. They are meant for catching
+//! regressions, not for budgeting to the byte.
+
+use crate::{
+    rendy::hal::{format::Format, image::Kind},
+    stats::RenderStats,
+    types::{Backend, Mesh, Texture},
+};
+use amethyst_assets::{AssetStorage, Handle, WeakHandle};
+use amethyst_core::ecs::{Read, System, Write};
+use fnv::FnvHashMap;
+use std::marker::PhantomData;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of the biggest resources kept in `GpuMemory::top_consumers`.
+pub const TOP_CONSUMERS: usize = 10;
+
+/// Assumed size of a single mesh element in bytes, used by [estimated_mesh_bytes].
+///
+/// It's the stride of the standard 3D vertex layout (position, normal, tangent and texture
+/// coordinates).
+pub const ESTIMATED_MESH_ELEMENT_SIZE: u64 = 48;
+
+/// Kind of GPU resource reported by `live_resources`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ResourceKind {
+    /// A `Mesh` asset.
+    Mesh,
+    /// A `Texture` asset.
+    Texture,
+}
+
+/// A single resource currently loaded in one of the renderer asset storages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiveResource {
+    /// Kind of this resource.
+    pub kind: ResourceKind,
+    /// Id of the asset handle.
+    pub id: u32,
+    /// Approximate number of bytes used on the GPU, only a rough estimate for meshes.
+    pub bytes: u64,
+    /// Number of handles keeping this resource alive, not counting the storage itself.
+    pub handles: usize,
+}
+
+/// Approximate size in bytes of an image with the given kind, mip levels and format.
+pub fn image_bytes(kind: Kind, levels: u8, format: Format) -> u64 {
+    let desc = format.surface_desc();
+    let (block_w, block_h) = (u32::from(desc.dim.0), u32::from(desc.dim.1));
+    let layers = u64::from(kind.num_layers()) * u64::from(kind.num_samples());
+    (0..levels.max(1))
+        .map(|level| {
+            let extent = kind.level_extent(level);
+            let blocks_w = u64::from(extent.width.div_ceil(block_w));
+            let blocks_h = u64::from(extent.height.div_ceil(block_h));
+            blocks_w * blocks_h * u64::from(extent.depth) * u64::from(desc.bits) / 8
+        })
+        .sum::<u64>()
+        * layers
+}
+
+/// Approximate size in bytes of a texture, including its whole mip chain.
+pub fn texture_bytes<B: Backend>(texture: &Texture) -> u64 {
+    B::unwrap_texture(texture)
+        .map(|texture| {
+            // The image of the view, as streamed textures swap the view of a placeholder.
+            let image = texture.view().image();
+            image_bytes(image.kind(), image.levels(), image.format())
+        })
+        .unwrap_or(0)
+}
+
+/// Rough estimate of the size in bytes of a mesh, its number of elements times
+/// `ESTIMATED_MESH_ELEMENT_SIZE`.
+///
+/// `rendy` doesn't expose the buffers of a built mesh, only the number of elements it draws.
+/// That's its vertex count without indices, but its index count with them, so the estimate of
+/// indexed meshes sharing vertices between triangles overstates their vertex buffers while
+/// ignoring their index buffers. Meshes with other vertex layouts are off by the difference of
+/// stride. Only compare the estimates of meshes built alike.
+pub fn estimated_mesh_bytes<B: Backend>(mesh: &Mesh) -> u64 {
+    B::unwrap_mesh(mesh)
+        .map(|mesh| u64::from(mesh.len()) * ESTIMATED_MESH_ELEMENT_SIZE)
+        .unwrap_or(0)
+}
+
+/// List all meshes and textures that are currently loaded, largest first.
+pub fn live_resources<B: Backend>(
+    meshes: &AssetStorage<Mesh>,
+    textures: &AssetStorage<Texture>,
+) -> Vec<LiveResource> {
+    let mut resources = Vec::new();
+    collect_live_resources::<B>(meshes, textures, &mut resources);
+    resources
+}
+
+fn collect_live_resources<B: Backend>(
+    meshes: &AssetStorage<Mesh>,
+    textures: &AssetStorage<Texture>,
+    resources: &mut Vec<LiveResource>,
+) {
+    resources.clear();
+    resources.extend(
+        meshes
+            .iter()
+            .map(|(handle, mesh)| LiveResource {
+                kind: ResourceKind::Mesh,
+                id: handle.id(),
+                bytes: estimated_mesh_bytes::<B>(mesh),
+                handles: handle.ref_count() - 1,
+            })
+            .chain(textures.iter().map(|(handle, texture)| LiveResource {
+                kind: ResourceKind::Texture,
+                id: handle.id(),
+                bytes: texture_bytes::<B>(texture),
+                handles: handle.ref_count() - 1,
+            })),
+    );
+    sort_largest_first(resources);
+}
+
+/// Sort resources by decreasing size, then meshes before textures and by id.
+fn sort_largest_first(resources: &mut [LiveResource]) {
+    resources.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(a.kind.cmp(&b.kind))
+            .then(a.id.cmp(&b.id))
+    });
+}
+
+/// Cheap summary of the loaded assets and of the handles to them, changing with any of them.
+fn storage_fingerprint<A: amethyst_assets::Asset>(storage: &AssetStorage<A>) -> u64 {
+    storage
+        .iter()
+        .map(|(handle, _)| {
+            let version = storage.get_version(handle).unwrap_or(0);
+            mix(u64::from(handle.id())
+                ^ (u64::from(version) << 32)
+                ^ ((handle.ref_count() as u64) << 48))
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+/// Finalizer of splitmix64, spreading the bits of summed values.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Approximate GPU memory usage, broken down by category.
+#[derive(Debug, Clone, Default)]
+pub struct GpuMemory {
+    /// Rough estimate of the bytes used by mesh vertex and index buffers, see
+    /// [estimated_mesh_bytes].
+    pub meshes: u64,
+    /// Bytes used by textures, including their mip chains.
+    pub textures: u64,
+    /// Bytes used by images created for render targets of the `RenderingBundle`.
+    /// Updated every time the render graph is rebuilt.
+    pub render_targets: u64,
+    /// Bytes used by every named resource group, largest first.
+    pub groups: Vec<(String, u64)>,
+    /// The biggest live resources, largest first.
+    pub top_consumers: Vec<LiveResource>,
+}
+
+impl GpuMemory {
+    /// Total number of bytes over all categories.
+    pub fn total(&self) -> u64 {
+        self.meshes + self.textures + self.render_targets
+    }
+}
+
+#[derive(Debug, Clone)]
+enum GroupEntry {
+    Mesh(WeakHandle<Mesh>),
+    Texture(WeakHandle<Texture>),
+}
+
+impl GroupEntry {
+    fn key(&self) -> Option<(ResourceKind, u32)> {
+        match self {
+            GroupEntry::Mesh(weak) => weak.upgrade().map(|h| (ResourceKind::Mesh, h.id())),
+            GroupEntry::Texture(weak) => weak.upgrade().map(|h| (ResourceKind::Texture, h.id())),
+        }
+    }
+}
+
+/// Named groups of resources, e.g. everything loaded for a single level.
+///
+/// Groups only keep weak handles, so adding a resource to a group never keeps it alive.
+/// A resource may belong to multiple groups, in which case it is counted in each of them.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceGroups {
+    groups: FnvHashMap<String, Vec<GroupEntry>>,
+    changed: bool,
+}
+
+impl ResourceGroups {
+    /// Add a mesh to the named group.
+    pub fn add_mesh(&mut self, group: impl Into<String>, handle: &Handle<Mesh>) {
+        self.changed = true;
+        self.groups
+            .entry(group.into())
+            .or_default()
+            .push(GroupEntry::Mesh(handle.downgrade()));
+    }
+
+    /// Add a texture to the named group.
+    pub fn add_texture(&mut self, group: impl Into<String>, handle: &Handle<Texture>) {
+        self.changed = true;
+        self.groups
+            .entry(group.into())
+            .or_default()
+            .push(GroupEntry::Texture(handle.downgrade()));
+    }
+
+    /// Forget about the named group. Resources themselves are not affected.
+    pub fn remove_group(&mut self, group: &str) {
+        self.changed |= self.groups.remove(group).is_some();
+    }
+
+    fn prune(&mut self) {
+        for entries in self.groups.values_mut() {
+            entries.retain(|entry| match entry {
+                GroupEntry::Mesh(weak) => !weak.is_dead(),
+                GroupEntry::Texture(weak) => !weak.is_dead(),
+            });
+        }
+    }
+
+    /// Whether groups changed since the last call.
+    fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.changed, false)
+    }
+}
+
+/// Updates `RenderStats::gpu_memory` from the mesh and texture storages.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct GpuMemoryStatsSystem<B: Backend> {
+    fingerprint: Option<(u64, u64)>,
+    resources: Vec<LiveResource>,
+    sizes: FnvHashMap<(ResourceKind, u32), u64>,
+    marker: PhantomData<B>,
+}
+
+impl<'a, B: Backend> System<'a> for GpuMemoryStatsSystem<B> {
+    type SystemData = (
+        Read<'a, AssetStorage<Mesh>>,
+        Read<'a, AssetStorage<Texture>>,
+        Write<'a, ResourceGroups>,
+        Write<'a, RenderStats>,
+    );
+
+    fn run(&mut self, (meshes, textures, mut groups, mut stats): Self::SystemData) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("gpu_memory_stats");
+
+        // Dropped group members leave the storages, changing their fingerprint.
+        let fingerprint = (storage_fingerprint(&meshes), storage_fingerprint(&textures));
+        if !groups.take_changed() && self.fingerprint == Some(fingerprint) {
+            return;
+        }
+        self.fingerprint = Some(fingerprint);
+
+        collect_live_resources::<B>(&meshes, &textures, &mut self.resources);
+        self.sizes.clear();
+        self.sizes
+            .extend(self.resources.iter().map(|r| ((r.kind, r.id), r.bytes)));
+
+        groups.prune();
+        let sizes = &self.sizes;
+        let memory = &mut stats.gpu_memory;
+        memory.groups.clear();
+        memory
+            .groups
+            .extend(groups.groups.iter().map(|(name, entries)| {
+                let bytes: u64 = entries
+                    .iter()
+                    .filter_map(|e| e.key().and_then(|k| sizes.get(&k)))
+                    .sum();
+                (name.clone(), bytes)
+            }));
+        memory
+            .groups
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        memory.meshes = self
+            .resources
+            .iter()
+            .filter(|r| r.kind == ResourceKind::Mesh)
+            .map(|r| r.bytes)
+            .sum();
+        memory.textures = self
+            .resources
+            .iter()
+            .filter(|r| r.kind == ResourceKind::Texture)
+            .map(|r| r.bytes)
+            .sum();
+        memory.top_consumers.clear();
+        memory
+            .top_consumers
+            .extend(self.resources.iter().take(TOP_CONSUMERS).copied());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_bytes_single_level() {
+        let kind = Kind::D2(4, 4, 1, 1);
+        assert_eq!(image_bytes(kind, 1, Format::Rgba8Unorm), 64);
+        assert_eq!(image_bytes(kind, 1, Format::R32Sfloat), 64);
+        assert_eq!(image_bytes(kind, 1, Format::R8Unorm), 16);
+    }
+
+    #[test]
+    fn image_bytes_mip_chain() {
+        let kind = Kind::D2(4, 4, 1, 1);
+        // 4x4 + 2x2 + 1x1
+        assert_eq!(image_bytes(kind, 3, Format::Rgba8Unorm), (16 + 4 + 1) * 4);
+    }
+
+    #[test]
+    fn image_bytes_layers() {
+        let kind = Kind::D2(2, 2, 6, 1);
+        assert_eq!(image_bytes(kind, 1, Format::Rgba8Unorm), 6 * 16);
+    }
+
+    #[test]
+    fn image_bytes_compressed() {
+        // BC1 uses 64 bits per 4x4 block.
+        let kind = Kind::D2(8, 8, 1, 1);
+        assert_eq!(image_bytes(kind, 1, Format::Bc1RgbUnorm), 4 * 8);
+        // Partial blocks still occupy the whole block.
+        let kind = Kind::D2(2, 2, 1, 1);
+        assert_eq!(image_bytes(kind, 1, Format::Bc1RgbUnorm), 8);
+    }
+
+    #[test]
+    fn image_bytes_compressed_mip_chain() {
+        // 8x8 + 4x4 + 2x2 + 1x1 in 4x4 blocks of 128 bits, the two smallest levels taking a
+        // whole block each, over 6 layers.
+        let kind = Kind::D2(8, 8, 6, 1);
+        assert_eq!(
+            image_bytes(kind, 4, Format::Bc3Unorm),
+            (4 + 1 + 1 + 1) * 16 * 6
+        );
+        // Multisampled images store every sample.
+        let kind = Kind::D2(4, 4, 1, 4);
+        assert_eq!(image_bytes(kind, 1, Format::Rgba8Unorm), 4 * 64);
+    }
+
+    #[test]
+    fn top_consumers_are_sorted_largest_first() {
+        let resource = |kind, id, bytes| LiveResource {
+            kind,
+            id,
+            bytes,
+            handles: 1,
+        };
+        let mut resources = vec![
+            resource(ResourceKind::Texture, 3, 64),
+            resource(ResourceKind::Mesh, 1, 16),
+            resource(ResourceKind::Texture, 0, 1024),
+            resource(ResourceKind::Mesh, 7, 64),
+            resource(ResourceKind::Mesh, 2, 64),
+        ];
+        sort_largest_first(&mut resources);
+        let order = resources.iter().map(|r| (r.kind, r.id)).collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![
+                (ResourceKind::Texture, 0),
+                (ResourceKind::Mesh, 2),
+                (ResourceKind::Mesh, 7),
+                (ResourceKind::Texture, 3),
+                (ResourceKind::Mesh, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+//! Blend shapes, meshes deformed by a weighted sum of morph targets.
+//!
+//! [MorphTargets] hold the vertex deltas of every target of a mesh, and the [BlendShapes]
+//! component of a mesh entity sets the weight of each target. The 3D passes apply the targets
+//! in the vertex shader when morphing is enabled on their plugin. Meshes which are also skinned
+//! are morphed first, in their bind pose, and then skinned, so facial morphs follow the
+//! skeleton. [deform_vertex] computes the same deformation on the CPU.
+
+use crate::skinning::JointCombined;
+use amethyst_core::{
+    ecs::prelude::{Component, DenseVecStorage},
+    math::{Matrix4, Point3, Vector3},
+};
+use amethyst_error::Error;
+use std::sync::Arc;
+
+/// Displacement of a vertex by a morph target of weight 1.0, laid out as read by the shaders.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MorphDelta {
+    /// Position displacement, the last component is unused.
+    pub position: [f32; 4],
+    /// Normal displacement, the last component is unused.
+    pub normal: [f32; 4],
+    /// Tangent displacement, the last component is unused.
+    pub tangent: [f32; 4],
+}
+
+impl MorphDelta {
+    fn add_weighted(&mut self, other: &MorphDelta, weight: f32) {
+        for i in 0..4 {
+            self.position[i] += weight * other.position[i];
+            self.normal[i] += weight * other.normal[i];
+            self.tangent[i] += weight * other.tangent[i];
+        }
+    }
+}
+
+/// Deltas of a single morph target, one per vertex of the mesh.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+    /// Position deltas.
+    pub positions: Vec<[f32; 3]>,
+    /// Normal deltas, or empty if the target doesn't change normals.
+    pub normals: Vec<[f32; 3]>,
+    /// Tangent deltas, or empty if the target doesn't change tangents.
+    pub tangents: Vec<[f32; 3]>,
+}
+
+/// All morph targets of a mesh.
+///
+/// The deltas are stored per vertex, with the deltas of all targets of a vertex next to each
+/// other, so the vertex shader reads them without knowing the vertex count.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MorphTargets {
+    vertices: usize,
+    targets: usize,
+    deltas: Vec<MorphDelta>,
+}
+
+impl MorphTargets {
+    /// Morph targets of a mesh with `vertices` vertices.
+    ///
+    /// Fails if there is no target, or a target doesn't have a delta for every vertex.
+    pub fn new(
+        vertices: usize,
+        targets: impl IntoIterator<Item = MorphTarget>,
+    ) -> Result<Self, Error> {
+        let targets = targets.into_iter().collect::<Vec<_>>();
+        if targets.is_empty() || vertices == 0 {
+            return Err(Error::from_string(
+                "Morph targets need at least one target and one vertex",
+            ));
+        }
+        for (i, target) in targets.iter().enumerate() {
+            let valid = |len: usize, optional: bool| len == vertices || (optional && len == 0);
+            if !valid(target.positions.len(), false)
+                || !valid(target.normals.len(), true)
+                || !valid(target.tangents.len(), true)
+            {
+                return Err(Error::from_string(format!(
+                    "Morph target {} doesn't have a delta for each of the {} vertices",
+                    i, vertices
+                )));
+            }
+        }
+
+        let extend = |v: Option<&[f32; 3]>| v.map_or([0.0; 4], |v| [v[0], v[1], v[2], 0.0]);
+        let deltas = (0..vertices)
+            .flat_map(|vertex| {
+                targets.iter().map(move |target| MorphDelta {
+                    position: extend(target.positions.get(vertex)),
+                    normal: extend(target.normals.get(vertex)),
+                    tangent: extend(target.tangents.get(vertex)),
+                })
+            })
+            .collect();
+        Ok(MorphTargets {
+            vertices,
+            targets: targets.len(),
+            deltas,
+        })
+    }
+
+    /// Number of vertices of the mesh.
+    pub fn vertices(&self) -> usize {
+        self.vertices
+    }
+
+    /// Number of morph targets.
+    pub fn targets(&self) -> usize {
+        self.targets
+    }
+
+    /// Deltas of all targets, per vertex.
+    pub fn deltas(&self) -> &[MorphDelta] {
+        &self.deltas
+    }
+
+    /// Weighted sum of the deltas of a vertex. Missing weights count as zero, weights beyond
+    /// the number of targets are ignored.
+    pub fn delta(&self, vertex: usize, weights: &[f32]) -> MorphDelta {
+        let mut sum = MorphDelta::default();
+        let first = vertex * self.targets;
+        for (delta, weight) in self.deltas[first..first + self.targets].iter().zip(weights) {
+            sum.add_weighted(delta, *weight);
+        }
+        sum
+    }
+
+    /// Morph the positions and normals of the mesh on the CPU.
+    pub fn apply(&self, weights: &[f32], positions: &mut [[f32; 3]], normals: &mut [[f32; 3]]) {
+        for (vertex, position) in positions.iter_mut().enumerate().take(self.vertices) {
+            let delta = self.delta(vertex, weights);
+            for (p, d) in position.iter_mut().zip(&delta.position) {
+                *p += d;
+            }
+            if let Some(normal) = normals.get_mut(vertex) {
+                for (n, d) in normal.iter_mut().zip(&delta.normal) {
+                    *n += d;
+                }
+            }
+        }
+    }
+}
+
+/// Morph targets of the mesh of an entity and their current weights.
+///
+/// Entities sharing the same `Arc<MorphTargets>` share its deltas on the GPU, so only the
+/// weights are uploaded per entity every frame.
+#[derive(Clone, Debug)]
+pub struct BlendShapes {
+    /// Morph targets of the mesh.
+    pub targets: Arc<MorphTargets>,
+    /// Weight of every target, missing weights count as zero.
+    pub weights: Vec<f32>,
+}
+
+impl Component for BlendShapes {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl BlendShapes {
+    /// Blend shapes of given targets, all at weight zero.
+    pub fn new(targets: Arc<MorphTargets>) -> Self {
+        let weights = vec![0.0; targets.targets()];
+        BlendShapes { targets, weights }
+    }
+
+    /// Weights of all targets, padded with zeros or truncated to the number of targets.
+    pub fn target_weights(&self) -> impl Iterator<Item = f32> + '_ {
+        (0..self.targets.targets()).map(move |i| self.weights.get(i).copied().unwrap_or(0.0))
+    }
+}
+
+/// Deform a vertex in mesh space the same way the vertex shaders do: the morph targets are
+/// applied first, in the bind pose, and the morphed vertex is then skinned with the joint
+/// matrices of the mesh. Returns the position and the normal, which isn't normalized.
+pub fn deform_vertex(
+    vertex: usize,
+    position: [f32; 3],
+    normal: [f32; 3],
+    morph: Option<(&MorphTargets, &[f32])>,
+    skin: Option<(&[Matrix4<f32>], &JointCombined)>,
+) -> ([f32; 3], [f32; 3]) {
+    let mut position = Point3::from(position);
+    let mut normal = Vector3::from(normal);
+    if let Some((targets, weights)) = morph {
+        let delta = targets.delta(vertex, weights);
+        position += Vector3::new(delta.position[0], delta.position[1], delta.position[2]);
+        normal += Vector3::new(delta.normal[0], delta.normal[1], delta.normal[2]);
+    }
+    if let Some((palette, joints)) = skin {
+        let transform = joints.transform(palette);
+        position = transform.transform_point(&position);
+        normal = transform.transform_vector(&normal);
+    }
+    (position.coords.into(), normal.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::{UnitQuaternion, Vector3};
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        for i in 0..3 {
+            assert!((a[i] - b[i]).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn morphing_then_skinning_matches_sequential_cpu_deformation() {
+        let positions = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let normals = vec![[0.0, 0.0, 1.0]; 3];
+        let targets = MorphTargets::new(
+            3,
+            vec![
+                MorphTarget {
+                    positions: vec![[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [0.5, 0.0, 0.0]],
+                    normals: vec![[0.0, 0.5, 0.0]; 3],
+                    tangents: Vec::new(),
+                },
+                MorphTarget {
+                    positions: vec![[0.0, 2.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]],
+                    ..Default::default()
+                },
+            ],
+        )
+        .unwrap();
+        let weights = [0.5, 0.25];
+        let palette = [
+            Matrix4::new_translation(&Vector3::new(0.0, 0.0, -3.0)),
+            UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 1.0).to_homogeneous()
+                * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 1.0, 1.0)),
+        ];
+        let joints = [
+            JointCombined::new([0, 1, 0, 0], [1.0, 0.0, 0.0, 0.0]),
+            JointCombined::new([0, 1, 0, 0], [0.25, 0.75, 0.0, 0.0]),
+            JointCombined::new([1, 0, 0, 0], [1.0, 0.0, 0.0, 0.0]),
+        ];
+
+        let mut morphed = positions.clone();
+        let mut morphed_normals = normals.clone();
+        targets.apply(&weights, &mut morphed, &mut morphed_normals);
+        assert_close(morphed[0], [0.0, 0.5, 0.5]);
+
+        for vertex in 0..3 {
+            let skin = joints[vertex].transform(&palette);
+            let expected_position = skin
+                .transform_point(&Point3::from(morphed[vertex]))
+                .coords
+                .into();
+            let expected_normal = skin
+                .transform_vector(&Vector3::from(morphed_normals[vertex]))
+                .into();
+
+            let (position, normal) = deform_vertex(
+                vertex,
+                positions[vertex],
+                normals[vertex],
+                Some((&targets, &weights)),
+                Some((&palette, &joints[vertex])),
+            );
+            assert_close(position, expected_position);
+            assert_close(normal, expected_normal);
+
+            // The single deformations of the other permutations.
+            let morphed_only = deform_vertex(
+                vertex,
+                positions[vertex],
+                normals[vertex],
+                Some((&targets, &weights)),
+                None,
+            );
+            assert_close(morphed_only.0, morphed[vertex]);
+            let skinned_only = deform_vertex(
+                vertex,
+                positions[vertex],
+                normals[vertex],
+                None,
+                Some((&palette, &joints[vertex])),
+            );
+            let skinned: [f32; 3] = skin
+                .transform_point(&Point3::from(positions[vertex]))
+                .coords
+                .into();
+            assert_close(skinned_only.0, skinned);
+            assert_eq!(
+                deform_vertex(vertex, positions[vertex], normals[vertex], None, None),
+                (positions[vertex], normals[vertex])
+            );
+        }
+
+        // Skinning before morphing would move the deltas of rotated joints elsewhere.
+        let delta = targets.delta(2, &weights);
+        let skinned_then_morphed = deform_vertex(
+            2,
+            positions[2],
+            normals[2],
+            None,
+            Some((&palette, &joints[2])),
+        )
+        .0;
+        let wrong = [
+            skinned_then_morphed[0] + delta.position[0],
+            skinned_then_morphed[1] + delta.position[1],
+            skinned_then_morphed[2] + delta.position[2],
+        ];
+        let right = deform_vertex(
+            2,
+            positions[2],
+            normals[2],
+            Some((&targets, &weights)),
+            Some((&palette, &joints[2])),
+        )
+        .0;
+        assert!((wrong[0] - right[0]).abs() > 0.1 || (wrong[1] - right[1]).abs() > 0.1);
+    }
+
+    #[test]
+    fn targets_must_cover_all_vertices() {
+        let target = MorphTarget {
+            positions: vec![[0.0; 3]; 2],
+            normals: vec![[0.0; 3]; 1],
+            tangents: Vec::new(),
+        };
+        assert!(MorphTargets::new(2, vec![target]).is_err());
+        assert!(MorphTargets::new(2, Vec::new()).is_err());
+
+        let targets = Arc::new(
+            MorphTargets::new(
+                1,
+                vec![MorphTarget {
+                    positions: vec![[1.0, 0.0, 0.0]],
+                    ..Default::default()
+                }],
+            )
+            .unwrap(),
+        );
+        let mut shapes = BlendShapes::new(targets);
+        shapes.weights = vec![0.5, 1.0];
+        assert_eq!(shapes.target_weights().collect::<Vec<_>>(), vec![0.5]);
+        shapes.weights.clear();
+        assert_eq!(shapes.target_weights().collect::<Vec<_>>(), vec![0.0]);
+    }
+}
@@ -0,0 +1,674 @@
+//! Multisample anti-aliasing of render targets.
+//!
+//! A target is multisampled when the `kind` of its image outputs has more than one sample, e.g.
+//! the scene target of `RenderToWindow::with_msaa`. Its render groups then draw into
+//! multisampled color and depth attachments owned by its render pass, which resolves them into
+//! single sampled images at the end of the pass. Only the resolved colors are registered as
+//! `TargetImage::Color` outputs of the target, so the targets reading them, like the tone
+//! mapping of the display target, don't need to know about multisampling. The multisampled
+//! depth isn't resolved, so multisampled targets have no `TargetImage::Depth` output.
+//!
+//! The attachments are created along with the graph, so they follow the size of the target
+//! when the graph is rebuilt, e.g. when the window is resized. The pipelines built by render
+//! groups with `PipelineDescBuilder` rasterize as many samples as their target, see
+//! [target_samples].
+
+use crate::{bundle::ImageOptions, types::Backend};
+use amethyst_core::ecs::World;
+use rendy::{
+    command::{
+        CommandBuffer, CommandPool, ExecutableState, Family, FamilyId, Fence, Graphics,
+        IndividualReset, MultiShot, NoSimultaneousUse, PendingState, Queue, QueueId,
+        SecondaryLevel, SimultaneousUse, Submission, Submit, Supports,
+    },
+    factory::Factory,
+    frame::{
+        cirque::{CirqueRef, CommandCirque},
+        Frames,
+    },
+    graph::{
+        gfx_acquire_barriers, gfx_release_barriers, is_metal,
+        render::{PrepareResult, RenderGroup, RenderGroupBuilder},
+        BufferAccess, BufferId, DynNode, GraphContext, ImageAccess, ImageId, NodeBuffer,
+        NodeBuilder, NodeId, NodeImage,
+    },
+    hal::{
+        self,
+        adapter::PhysicalDevice,
+        command::{ClearColor, ClearValue, ClearValueRaw},
+        device::Device,
+        format::{Format, Swizzle},
+        image::{Layout, NumSamples, SubresourceRange, Tiling, Usage, ViewCapabilities, ViewKind},
+        pass::{Attachment, AttachmentLoadOp, AttachmentOps, AttachmentStoreOp, SubpassDesc},
+    },
+    memory::Data,
+    resource::{Escape, Image, ImageInfo},
+};
+use std::{cell::Cell, cmp::min, collections::HashMap};
+
+/// Sample count used by `RenderToWindow::with_msaa` when none is given.
+pub const DEFAULT_MSAA_SAMPLES: NumSamples = 4;
+
+thread_local! {
+    static TARGET_SAMPLES: Cell<NumSamples> = const { Cell::new(1) };
+}
+
+/// Number of samples of the target whose render groups are being built, 1 outside of the build
+/// of a multisampled target.
+///
+/// Used by `PipelineDescBuilder` for the pipelines built without explicit `Multisampling`.
+/// Render groups building their pipelines by other means must rasterize this many samples.
+pub fn target_samples() -> NumSamples {
+    TARGET_SAMPLES.with(Cell::get)
+}
+
+fn with_target_samples<R>(samples: NumSamples, f: impl FnOnce() -> R) -> R {
+    let previous = TARGET_SAMPLES.with(|s| s.replace(samples));
+    let result = f();
+    TARGET_SAMPLES.with(|s| s.set(previous));
+    result
+}
+
+/// Largest sample count up to `requested` in the mask of supported sample counts, where bit
+/// `n` stands for `2^n` samples. Falls back to 1 sample, which is always supported.
+pub fn supported_samples(requested: NumSamples, supported: NumSamples) -> NumSamples {
+    // 64 is the largest sample count devices can support.
+    let requested = requested.clamp(1, 64);
+    let mut samples = requested.next_power_of_two();
+    if samples > requested {
+        samples /= 2;
+    }
+    while samples > 1 && supported & samples == 0 {
+        samples /= 2;
+    }
+    samples
+}
+
+/// Sample count up to `requested` the device supports for color attachments of given format
+/// with depth attachments of given format, logging a warning when it's less than requested.
+pub fn device_samples<B: Backend>(
+    factory: &Factory<B>,
+    requested: NumSamples,
+    color: Format,
+    depth: Option<Format>,
+) -> NumSamples {
+    let format_samples = |format: Format, usage: Usage| {
+        factory
+            .image_format_properties(ImageInfo {
+                kind: hal::image::Kind::D2(1, 1, 1, 1),
+                levels: 1,
+                format,
+                tiling: Tiling::Optimal,
+                view_caps: ViewCapabilities::empty(),
+                usage,
+            })
+            .map_or(1, |properties| properties.sample_count_mask)
+    };
+    let limits = factory.physical().limits();
+    let mut supported = limits.framebuffer_color_sample_counts
+        & format_samples(color, Usage::COLOR_ATTACHMENT | Usage::SAMPLED);
+    if let Some(depth) = depth {
+        supported &= limits.framebuffer_depth_sample_counts
+            & format_samples(depth, Usage::DEPTH_STENCIL_ATTACHMENT);
+    }
+
+    let samples = supported_samples(requested, supported);
+    if samples < requested {
+        log::warn!(
+            "{}x MSAA isn't supported for {:?} targets, using {}x instead.",
+            requested,
+            color,
+            samples
+        );
+    }
+    samples
+}
+
+/// Builder of the render pass of a multisampled target, resolving its colors into graph images.
+#[derive(derivative::Derivative)]
+#[derivative(Debug(bound = ""))]
+pub(crate) struct MultisampledPassNodeBuilder<B: Backend> {
+    samples: NumSamples,
+    colors: Vec<(ImageOptions, ImageId)>,
+    depth: Option<ImageOptions>,
+    groups: Vec<Box<dyn RenderGroupBuilder<B, World>>>,
+    dependencies: Vec<NodeId>,
+}
+
+impl<B: Backend> MultisampledPassNodeBuilder<B> {
+    pub(crate) fn new(samples: NumSamples) -> Self {
+        Self {
+            samples,
+            colors: Vec::new(),
+            depth: None,
+            groups: Vec::new(),
+            dependencies: Vec::new(),
+        }
+    }
+
+    /// Add a multisampled color attachment, resolved into given graph image.
+    pub(crate) fn add_color(&mut self, options: ImageOptions, resolve: ImageId) {
+        self.colors.push((options, resolve));
+    }
+
+    pub(crate) fn set_depth(&mut self, options: ImageOptions) {
+        self.depth = Some(options);
+    }
+
+    pub(crate) fn add_group(&mut self, group: Box<dyn RenderGroupBuilder<B, World>>) {
+        self.groups.push(group);
+    }
+
+    pub(crate) fn add_dependency(&mut self, node: NodeId) {
+        self.dependencies.push(node);
+    }
+}
+
+fn attachment_info(options: &ImageOptions, usage: Usage) -> ImageInfo {
+    ImageInfo {
+        kind: options.kind,
+        levels: 1,
+        format: options.format,
+        tiling: Tiling::Optimal,
+        view_caps: ViewCapabilities::empty(),
+        usage,
+    }
+}
+
+fn common_layout(acc: Layout, layout: Layout) -> Layout {
+    match (acc, layout) {
+        (Layout::Undefined, layout) | (layout, Layout::Undefined) => layout,
+        (acc, layout) if acc == layout => acc,
+        _ => Layout::General,
+    }
+}
+
+impl<B: Backend> NodeBuilder<B, World> for MultisampledPassNodeBuilder<B> {
+    fn family(&self, _factory: &mut Factory<B>, families: &[Family<B>]) -> Option<FamilyId> {
+        families
+            .iter()
+            .find(|family| Supports::<Graphics>::supports(&family.capability()).is_some())
+            .map(|family| family.id())
+    }
+
+    fn buffers(&self) -> Vec<(BufferId, BufferAccess)> {
+        let mut buffers = HashMap::new();
+        for group in &self.groups {
+            for (id, access) in group.buffers() {
+                let entry = buffers.entry(id).or_insert(BufferAccess {
+                    access: hal::buffer::Access::empty(),
+                    usage: hal::buffer::Usage::empty(),
+                    stages: hal::pso::PipelineStage::empty(),
+                });
+                entry.access |= access.access;
+                entry.usage |= access.usage;
+                entry.stages |= access.stages;
+            }
+        }
+        buffers.into_iter().collect()
+    }
+
+    fn images(&self) -> Vec<(ImageId, ImageAccess)> {
+        let mut images = HashMap::new();
+        for group in &self.groups {
+            for (id, access) in group.images() {
+                assert!(
+                    self.colors.iter().all(|&(_, resolve)| resolve != id),
+                    "Attachment image can't be used otherwise in render pass"
+                );
+                let entry = images.entry(id).or_insert(ImageAccess {
+                    access: hal::image::Access::empty(),
+                    usage: Usage::empty(),
+                    stages: hal::pso::PipelineStage::empty(),
+                    layout: Layout::Undefined,
+                });
+                entry.access |= access.access;
+                entry.usage |= access.usage;
+                entry.stages |= access.stages;
+                entry.layout = common_layout(entry.layout, access.layout);
+            }
+        }
+
+        self.colors
+            .iter()
+            .map(|&(_, resolve)| {
+                (
+                    resolve,
+                    ImageAccess {
+                        access: hal::image::Access::COLOR_ATTACHMENT_WRITE,
+                        usage: Usage::COLOR_ATTACHMENT,
+                        stages: hal::pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT,
+                        layout: Layout::ColorAttachmentOptimal,
+                    },
+                )
+            })
+            .chain(images)
+            .collect()
+    }
+
+    fn dependencies(&self) -> Vec<NodeId> {
+        let mut dependencies = self
+            .dependencies
+            .iter()
+            .cloned()
+            .chain(self.groups.iter().flat_map(|group| group.dependencies()))
+            .collect::<Vec<_>>();
+        dependencies.sort();
+        dependencies.dedup();
+        dependencies
+    }
+
+    fn build<'a>(
+        self: Box<Self>,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        queue: usize,
+        aux: &World,
+        buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn DynNode<B, World>>, failure::Error> {
+        let MultisampledPassNodeBuilder {
+            samples,
+            colors,
+            depth,
+            groups,
+            ..
+        } = *self;
+
+        let node_image = |id: ImageId| -> &NodeImage {
+            images
+                .iter()
+                .find(|image| image.id == id)
+                .expect("Attachment image wasn't provided")
+        };
+
+        let mut framebuffer_width = u32::MAX;
+        let mut framebuffer_height = u32::MAX;
+        for options in colors.iter().map(|(options, _)| options).chain(&depth) {
+            let extent = options.kind.extent();
+            framebuffer_width = min(framebuffer_width, extent.width);
+            framebuffer_height = min(framebuffer_height, extent.height);
+        }
+
+        // Multisampled attachments, then resolved colors.
+        let mut attachments = Vec::new();
+        let mut views = Vec::new();
+        let mut clears = Vec::new();
+        let mut multisampled = Vec::new();
+        let attachment_ops = |clear: Option<ClearValue>| AttachmentOps {
+            // Without clear, the contents of the attachments are undefined when the pass begins.
+            load: if clear.is_some() {
+                AttachmentLoadOp::Clear
+            } else {
+                AttachmentLoadOp::DontCare
+            },
+            store: AttachmentStoreOp::DontCare,
+        };
+        let placeholder = ClearValue::Color(ClearColor::Sfloat([0.0; 4]));
+
+        let color_attachments = colors.iter().map(|(options, _)| {
+            (
+                options,
+                Usage::COLOR_ATTACHMENT,
+                Layout::ColorAttachmentOptimal,
+            )
+        });
+        let depth_attachment = depth.iter().map(|options| {
+            (
+                options,
+                Usage::DEPTH_STENCIL_ATTACHMENT,
+                Layout::DepthStencilAttachmentOptimal,
+            )
+        });
+        for (options, usage, layout) in color_attachments.chain(depth_attachment) {
+            let image = factory.create_image(attachment_info(options, usage), Data)?;
+            views.push(unsafe {
+                factory.device().create_image_view(
+                    image.raw(),
+                    ViewKind::D2,
+                    options.format,
+                    Swizzle::NO,
+                    SubresourceRange {
+                        aspects: options.format.surface_desc().aspects,
+                        levels: 0..1,
+                        layers: 0..1,
+                    },
+                )
+            }?);
+            attachments.push(Attachment {
+                format: Some(options.format),
+                samples,
+                ops: attachment_ops(options.clear),
+                stencil_ops: AttachmentOps::DONT_CARE,
+                layouts: Layout::Undefined..layout,
+            });
+            clears.push(ClearValueRaw::from(options.clear.unwrap_or(placeholder)));
+            multisampled.push(image);
+        }
+
+        for &(_, resolve) in &colors {
+            let node_image = node_image(resolve);
+            let image = ctx.get_image(resolve).expect("Image does not exist");
+            views.push(unsafe {
+                factory.device().create_image_view(
+                    image.raw(),
+                    ViewKind::D2,
+                    image.format(),
+                    Swizzle::NO,
+                    node_image.range.clone(),
+                )
+            }?);
+            attachments.push(Attachment {
+                format: Some(image.format()),
+                samples: 1,
+                ops: AttachmentOps::new(AttachmentLoadOp::DontCare, AttachmentStoreOp::Store),
+                stencil_ops: AttachmentOps::DONT_CARE,
+                layouts: Layout::Undefined..node_image.layout,
+            });
+            clears.push(ClearValueRaw::from(placeholder));
+        }
+
+        let color_refs = (0..colors.len())
+            .map(|i| (i, Layout::ColorAttachmentOptimal))
+            .collect::<Vec<_>>();
+        let depth_ref = depth
+            .as_ref()
+            .map(|_| (colors.len(), Layout::DepthStencilAttachmentOptimal));
+        let resolve_offset = colors.len() + depth_ref.iter().count();
+        let resolve_refs = (0..colors.len())
+            .map(|i| (resolve_offset + i, Layout::ColorAttachmentOptimal))
+            .collect::<Vec<_>>();
+
+        let render_pass = unsafe {
+            factory.device().create_render_pass(
+                attachments,
+                Some(SubpassDesc {
+                    colors: &color_refs,
+                    depth_stencil: depth_ref.as_ref(),
+                    inputs: &[],
+                    resolves: &resolve_refs,
+                    preserves: &[],
+                }),
+                std::iter::empty::<hal::pass::SubpassDependency>(),
+            )
+        }?;
+
+        let framebuffer = unsafe {
+            factory.device().create_framebuffer(
+                &render_pass,
+                &views,
+                hal::image::Extent {
+                    width: framebuffer_width,
+                    height: framebuffer_height,
+                    depth: 1,
+                },
+            )
+        }?;
+
+        let mut command_pool = factory
+            .create_command_pool(family)?
+            .with_capability()
+            .expect("Graph must specify family that supports `Graphics`");
+
+        let mut barriers = |(stages, barriers): (_, Vec<_>)| {
+            if barriers.is_empty() || is_metal::<B>() {
+                return None;
+            }
+            let initial = command_pool.allocate_buffers(1).pop().unwrap();
+            let mut recording = initial.begin(MultiShot(SimultaneousUse), ());
+            unsafe {
+                recording.encoder().pipeline_barrier(
+                    stages,
+                    hal::memory::Dependencies::empty(),
+                    barriers,
+                );
+            }
+            let (submit, buffer) = recording.finish().submit();
+            Some(BarriersCommands { submit, buffer })
+        };
+        let acquire = barriers(gfx_acquire_barriers(ctx, &buffers, &images));
+        let release = barriers(gfx_release_barriers(ctx, &buffers, &images));
+
+        let groups = with_target_samples(samples, || {
+            groups
+                .into_iter()
+                .map(|group| {
+                    let group_buffers = group
+                        .buffers()
+                        .into_iter()
+                        .map(|(id, _)| {
+                            buffers
+                                .iter()
+                                .find(|b| b.id == id)
+                                .expect("Transient buffer wasn't provided")
+                                .clone()
+                        })
+                        .collect();
+                    let group_images = group
+                        .images()
+                        .into_iter()
+                        .map(|(id, _)| {
+                            images
+                                .iter()
+                                .find(|i| i.id == id)
+                                .expect("Transient image wasn't provided")
+                                .clone()
+                        })
+                        .collect();
+                    group.build(
+                        ctx,
+                        factory,
+                        QueueId {
+                            family: family.id(),
+                            index: queue,
+                        },
+                        aux,
+                        framebuffer_width,
+                        framebuffer_height,
+                        hal::pass::Subpass {
+                            index: 0,
+                            main_pass: &render_pass,
+                        },
+                        group_buffers,
+                        group_images,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        Ok(Box::new(MultisampledPassNode {
+            groups,
+            framebuffer_width,
+            framebuffer_height,
+            render_pass,
+            framebuffer,
+            views,
+            clears,
+            multisampled,
+            command_pool,
+            command_cirque: CommandCirque::new(),
+            acquire,
+            release,
+        }))
+    }
+}
+
+#[derive(derivative::Derivative)]
+#[derivative(Debug(bound = ""))]
+struct BarriersCommands<B: Backend> {
+    submit: Submit<B, SimultaneousUse, SecondaryLevel>,
+    buffer: CommandBuffer<
+        B,
+        Graphics,
+        PendingState<ExecutableState<MultiShot<SimultaneousUse>>>,
+        SecondaryLevel,
+        IndividualReset,
+    >,
+}
+
+/// Render pass of a multisampled target.
+#[derive(derivative::Derivative)]
+#[derivative(Debug(bound = ""))]
+struct MultisampledPassNode<B: Backend> {
+    groups: Vec<Box<dyn RenderGroup<B, World>>>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    render_pass: B::RenderPass,
+    framebuffer: B::Framebuffer,
+    views: Vec<B::ImageView>,
+    clears: Vec<ClearValueRaw>,
+    multisampled: Vec<Escape<Image<B>>>,
+    command_pool: CommandPool<B, Graphics, IndividualReset>,
+    command_cirque: CommandCirque<B, Graphics>,
+    acquire: Option<BarriersCommands<B>>,
+    release: Option<BarriersCommands<B>>,
+}
+
+impl<B: Backend> DynNode<B, World> for MultisampledPassNode<B> {
+    unsafe fn run<'a>(
+        &mut self,
+        _ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        queue: &mut Queue<B>,
+        aux: &World,
+        frames: &Frames<B>,
+        waits: &[(&'a B::Semaphore, hal::pso::PipelineStage)],
+        signals: &[&'a B::Semaphore],
+        fence: Option<&mut Fence<B>>,
+    ) {
+        let MultisampledPassNode {
+            groups,
+            framebuffer_width,
+            framebuffer_height,
+            render_pass,
+            framebuffer,
+            clears,
+            command_pool,
+            command_cirque,
+            acquire,
+            release,
+            ..
+        } = self;
+
+        let submit = command_cirque.encode(frames, command_pool, |mut cbuf| {
+            let index = cbuf.index();
+            let subpass = || hal::pass::Subpass {
+                index: 0,
+                main_pass: &*render_pass,
+            };
+
+            let force_record = groups.iter_mut().fold(false, |force_record, group| {
+                let result = group.prepare(factory, queue.id(), index, subpass(), aux);
+                matches!(result, PrepareResult::DrawRecord) || force_record
+            });
+            if force_record {
+                cbuf = CirqueRef::Initial(cbuf.or_reset(|cbuf| cbuf.reset()));
+            }
+
+            cbuf.or_init(|cbuf| {
+                let mut cbuf = cbuf.begin(MultiShot(NoSimultaneousUse), ());
+                let mut encoder = cbuf.encoder();
+                if let Some(barriers) = &acquire {
+                    encoder.execute_commands(std::iter::once(&barriers.submit));
+                }
+
+                let area = hal::pso::Rect {
+                    x: 0,
+                    y: 0,
+                    w: *framebuffer_width as _,
+                    h: *framebuffer_height as _,
+                };
+                let mut pass_encoder =
+                    encoder.begin_render_pass_inline(render_pass, framebuffer, area, clears);
+                for group in groups.iter_mut() {
+                    group.draw_inline(pass_encoder.reborrow(), index, subpass(), aux);
+                }
+                drop(pass_encoder);
+
+                if let Some(barriers) = &release {
+                    encoder.execute_commands(std::iter::once(&barriers.submit));
+                }
+                cbuf.finish()
+            })
+        });
+
+        queue.submit(
+            Some(
+                Submission::new()
+                    .submits(Some(submit))
+                    .wait(waits.iter().cloned())
+                    .signal(signals.iter().cloned()),
+            ),
+            fence,
+        );
+    }
+
+    unsafe fn dispose(self: Box<Self>, factory: &mut Factory<B>, aux: &World) {
+        let MultisampledPassNode {
+            groups,
+            render_pass,
+            framebuffer,
+            views,
+            multisampled,
+            mut command_pool,
+            command_cirque,
+            acquire,
+            release,
+            ..
+        } = *self;
+
+        for group in groups {
+            group.dispose(factory, aux);
+        }
+        let pool = &mut command_pool;
+        command_cirque.dispose(|buffer| {
+            buffer.either_with(
+                &mut *pool,
+                |pool, executable| pool.free_buffers(Some(executable)),
+                |pool, pending| pool.free_buffers(Some(pending.mark_complete())),
+            );
+        });
+        for BarriersCommands { buffer, .. } in acquire.into_iter().chain(release) {
+            pool.free_buffers(Some(buffer.mark_complete()));
+        }
+        factory.destroy_command_pool(command_pool.with_queue_type());
+
+        factory.device().destroy_framebuffer(framebuffer);
+        for view in views {
+            factory.device().destroy_image_view(view);
+        }
+        drop(multisampled);
+        factory.device().destroy_render_pass(render_pass);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_sample_counts_fall_back_to_supported_ones() {
+        // 1, 2, 4 and 8 samples.
+        let supported = 0b1111;
+        assert_eq!(supported_samples(4, supported), 4);
+        assert_eq!(supported_samples(16, supported), 8);
+        assert_eq!(supported_samples(6, supported), 4);
+        assert_eq!(supported_samples(4, 0b0011), 2);
+        assert_eq!(supported_samples(4, 0), 1);
+        assert_eq!(supported_samples(0, supported), 1);
+        assert_eq!(supported_samples(200, supported), 8);
+        assert_eq!(supported_samples(NumSamples::MAX, 0b111_1111), 64);
+    }
+
+    #[test]
+    fn target_samples_are_scoped_to_the_build_of_the_target() {
+        assert_eq!(target_samples(), 1);
+        let samples = with_target_samples(4, || {
+            assert_eq!(with_target_samples(2, target_samples), 2);
+            target_samples()
+        });
+        assert_eq!(samples, 4);
+        assert_eq!(target_samples(), 1);
+    }
+}
@@ -1,8 +1,9 @@
 //! Physically-based material.
 
-use crate::types::Texture;
+use crate::{material_shader::MAX_MATERIAL_SHADER_PARAMS, types::Texture};
 use amethyst_assets::{Asset, Handle};
 use amethyst_core::ecs::prelude::DenseVecStorage;
+use fnv::FnvHashMap;
 
 /// Material reference this part of the texture
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -22,6 +23,75 @@ impl Default for TextureOffset {
     }
 }
 
+/// 2D transform of the texture coordinates of a material, applied before its `TextureOffset`.
+///
+/// Coordinates are scaled, then rotated counterclockwise by `rotation` radians around the
+/// center of the texture, then offset. Only drawn by passes built with a UV transform, see
+/// `DrawBase3DDesc::with_uv_transform`.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct UvTransform {
+    /// Offset of the coordinates.
+    pub offset: [f32; 2],
+    /// Scale of the coordinates.
+    pub scale: [f32; 2],
+    /// Rotation of the coordinates, in radians.
+    pub rotation: f32,
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        UvTransform {
+            offset: [0.0; 2],
+            scale: [1.0; 2],
+            rotation: 0.0,
+        }
+    }
+}
+
+impl UvTransform {
+    /// Rows of the affine matrix of the transform, multiplied with `(u, v, 1)`.
+    pub fn rows(&self) -> [[f32; 3]; 2] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (a, b) = (cos * self.scale[0], -sin * self.scale[1]);
+        let (c, d) = (sin * self.scale[0], cos * self.scale[1]);
+        [
+            [a, b, 0.5 - 0.5 * (a + b) + self.offset[0]],
+            [c, d, 0.5 - 0.5 * (c + d) + self.offset[1]],
+        ]
+    }
+
+    /// Transformed texture coordinates.
+    pub fn apply(&self, uv: [f32; 2]) -> [f32; 2] {
+        let [u, v] = self.rows();
+        [
+            u[0] * uv[0] + u[1] * uv[1] + u[2],
+            v[0] * uv[0] + v[1] * uv[1] + v[2],
+        ]
+    }
+}
+
+/// Default `Material::dissolve_edge_width`.
+pub const DISSOLVE_EDGE_WIDTH: f32 = 0.05;
+
+/// Default `Material::dissolve_edge_color`, a bright orange.
+pub const DISSOLVE_EDGE_COLOR: [f32; 3] = [4.0, 1.2, 0.2];
+
+/// Default `Material::triplanar_sharpness`.
+pub const TRIPLANAR_SHARPNESS: f32 = 4.0;
+
+/// Default `Material::detail_scale`.
+pub const DETAIL_SCALE: f32 = 8.0;
+
+/// Default `Material::layer_color`, darkening the albedo like water soaking into a surface.
+pub const LAYER_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+/// Default `Material::layer_roughness`, the roughness of a wet surface.
+pub const LAYER_ROUGHNESS: f32 = 0.1;
+
+/// Default `Material::shininess`.
+pub const SHININESS: f32 = 32.0;
+
 /// A physically based Material with metallic workflow, fully utilized in PBR render pass.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Material {
@@ -41,6 +111,89 @@ pub struct Material {
     pub cavity: Handle<Texture>,
     /// Texture offset
     pub uv_offset: TextureOffset,
+    /// Linear RGB factor of the emission map.
+    pub emission_factor: [f32; 3],
+    /// Transform of the texture coordinates of every map but the normal map.
+    pub uv_transform: UvTransform,
+    /// Transform of the texture coordinates of the normal map.
+    pub normal_uv_transform: UvTransform,
+    /// Noise map of the dissolve, red channel.
+    pub dissolve_noise: Handle<Texture>,
+    /// Dissolve amount in [0, 1]: fragments whose noise is below it are discarded. Nothing is
+    /// dissolved at 0.
+    pub dissolve_amount: f32,
+    /// Width of the glowing edge of the dissolve, in noise values above `dissolve_amount`.
+    pub dissolve_edge_width: f32,
+    /// Linear RGB color emitted by the edge of the dissolve.
+    pub dissolve_edge_color: [f32; 3],
+    /// Repeats of the maps per world unit when sampled along the world axes instead of the
+    /// texture coordinates, by passes built with triplanar mapping. Texture coordinates are
+    /// used at 0.
+    pub triplanar_scale: f32,
+    /// Sharpness of the blend between the triplanar projections, as the exponent of the world
+    /// normal weighting them.
+    pub triplanar_sharpness: f32,
+    /// Detail map, blended over the albedo up close.
+    pub detail: Handle<Texture>,
+    /// Repeats of the detail map per repeat of the other maps.
+    pub detail_scale: f32,
+    /// Blending of the detail map over the albedo.
+    pub detail_blend: DetailBlend,
+    /// View distance at which the detail map has faded out, starting at half of it. The detail
+    /// map isn't sampled at 0.
+    pub detail_fade_distance: f32,
+    /// Mask of the surface layer, red channel. Replace the texture to update the mask, e.g.
+    /// for scorch marks left by gameplay.
+    pub layer_mask: Handle<Texture>,
+    /// Linear RGB factor of the albedo under the surface layer.
+    pub layer_color: [f32; 3],
+    /// Roughness under the surface layer.
+    pub layer_roughness: f32,
+    /// Amount of the surface layer of the material alone, in [0, 1].
+    pub layer_amount: f32,
+    /// Fraction of the scene `SurfaceLayer` amount added to `layer_amount`. The surface layer
+    /// is only drawn by passes built with it, and materials with no amount and a factor of 0
+    /// don't sample the mask.
+    pub layer_scene_factor: f32,
+    /// Light channels of the material, as a bitmask. It is only lit by the lights sharing a
+    /// channel with it, see `channels` of `PointLight`, `DirectionalLight` and `SpotLight`.
+    pub light_channels: u32,
+    /// Strength of the scattering of the diffuse lighting under the surface, in [0, 1], e.g.
+    /// for skin. Only scattered by passes built with a subsurface profile, see
+    /// `RenderBase3D::with_subsurface`.
+    pub subsurface: f32,
+    /// Linear RGB color of the Blinn-Phong highlights of the shaded pass, attenuated like the
+    /// diffuse lighting. Black draws no highlights.
+    pub specular: [f32; 3],
+    /// Exponent of the Blinn-Phong highlights, higher for smaller and sharper highlights.
+    pub shininess: f32,
+    /// Values of the parameters of the `MaterialShader` of the passes drawing the material, in
+    /// the order they are declared. `float` parameters read the first component.
+    pub shader_params: [[f32; 4]; MAX_MATERIAL_SHADER_PARAMS],
+}
+
+/// Blending of the detail map of a `Material` over its albedo.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum DetailBlend {
+    /// Overlay of the luminance of the detail map: darker than middle gray darkens the
+    /// albedo, brighter lightens it.
+    #[default]
+    Overlay,
+    /// Albedo scaled by twice the detail map.
+    Linear,
+}
+
+impl Material {
+    /// Whether drawing this material discards fragments, below its `alpha_cutoff` or dissolved.
+    ///
+    /// The 3D passes draw the materials discarding nothing with a variant of their fragment
+    /// shader testing depth before shading, which is much cheaper when they are covered. Set
+    /// `alpha_cutoff` to 0 on opaque materials, as glTF does for its opaque materials.
+    pub fn discards(&self) -> bool {
+        self.alpha_cutoff > 0.0 || self.dissolve_amount > 0.0
+    }
 }
 
 impl Asset for Material {
@@ -49,6 +202,30 @@ impl Asset for Material {
     type HandleStorage = DenseVecStorage<Handle<Self>>;
 }
 
+/// Resource tracking the materials whose parameters changed after being loaded, so that only
+/// their region of the material constant buffer is written again.
+///
+/// Mark a material after editing its parameters through `AssetStorage::get_mut`. Texture
+/// changes aren't tracked, draw with a new material instead. The
+/// [MaterialAnimator](crate::material_animation::MaterialAnimator) marks every material it
+/// animates.
+#[derive(Debug, Default)]
+pub struct MaterialChanges {
+    versions: FnvHashMap<u32, u64>,
+}
+
+impl MaterialChanges {
+    /// Mark the parameters of a material as changed.
+    pub fn mark(&mut self, handle: &Handle<Material>) {
+        *self.versions.entry(handle.id()).or_insert(0) += 1;
+    }
+
+    /// Number of times the material was marked as changed.
+    pub fn version(&self, handle: &Handle<Material>) -> u64 {
+        self.versions.get(&handle.id()).copied().unwrap_or(0)
+    }
+}
+
 /// A resource providing default textures for `Material`.
 /// These will be be used by the renderer in case a texture
 /// handle points to a texture which is not loaded already.
@@ -57,6 +234,19 @@ impl Asset for Material {
 #[derive(Debug, Clone)]
 pub struct MaterialDefaults(pub Material);
 
+/// A resource providing the materials drawn in place of materials that can't be drawn.
+///
+/// Materials that failed to load or were unloaded, or use such a texture, are drawn with
+/// `missing`, a magenta and black checker. Materials still loading are drawn with `loading`,
+/// the plain gray `MaterialDefaults`.
+#[derive(Debug, Clone)]
+pub struct MaterialPlaceholders {
+    /// Material drawn in place of broken materials.
+    pub missing: Handle<Material>,
+    /// Material drawn in place of materials that are still loading.
+    pub loading: Handle<Material>,
+}
+
 /// Trait providing generic access to a collection of texture handles
 pub trait StaticTextureSet<'a>:
     Clone + Copy + std::fmt::Debug + PartialEq + Eq + std::hash::Hash + Send + Sync + 'static
@@ -81,6 +271,9 @@ pub type FullTextureSet = (
     TexMetallicRoughness,
     TexAmbientOcclusion,
     TexCavity,
+    TexDissolveNoise,
+    TexDetail,
+    TexLayerMask,
 );
 
 macro_rules! impl_texture {
@@ -104,6 +297,9 @@ impl_texture!(TexNormal, normal);
 impl_texture!(TexMetallicRoughness, metallic_roughness);
 impl_texture!(TexAmbientOcclusion, ambient_occlusion);
 impl_texture!(TexCavity, cavity);
+impl_texture!(TexDissolveNoise, dissolve_noise);
+impl_texture!(TexDetail, detail);
+impl_texture!(TexLayerMask, layer_mask);
 
 macro_rules! recursive_iter {
     (@value $first:expr, $($rest:expr),*) => { $first.chain(recursive_iter!(@value $($rest),*)) };
@@ -136,3 +332,6 @@ impl_texture_set_tuple!(A, B, C);
 impl_texture_set_tuple!(A, B, C, D);
 impl_texture_set_tuple!(A, B, C, D, E);
 impl_texture_set_tuple!(A, B, C, D, E, F);
+impl_texture_set_tuple!(A, B, C, D, E, F, G);
+impl_texture_set_tuple!(A, B, C, D, E, F, G, H);
+impl_texture_set_tuple!(A, B, C, D, E, F, G, H, I);
@@ -1,36 +1,54 @@
 use crate::{
-    batch::{GroupIterator, OrderedTwoLevelBatch, TwoLevelBatch},
-    mtl::{FullTextureSet, Material, StaticTextureSet},
+    batch::{GroupIterator, OrderedTwoLevelBatch, RetainedBatch, TwoLevelBatch},
+    conventions::Conventions,
+    error::PassError,
+    material_shader::MaterialShader,
+    morph::BlendShapes,
+    mtl::{Material, StaticTextureSet},
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::{SkinnedVertexArgs, VertexArgs},
-    resources::Tint,
+    resources::{DepthBias, DepthMode, InstanceData, Tint},
     skinning::JointTransforms,
-    submodules::{DynamicVertexBuffer, EnvironmentSub, MaterialId, MaterialSub, SkinningSub},
+    stats::RenderStats,
+    submesh::{mesh_parts, MeshPart, SubMeshes},
+    submodules::{
+        sampled_image_access, DrawRun, DrawRuns, DynamicVertexBuffer, EnvironmentSub,
+        IndirectDrawSub, MaterialId, MaterialSub, MorphSub, SkinningSub,
+    },
     transparent::Transparent,
     types::{Backend, Mesh},
     util,
+    view::{View, ViewVisibility, Viewport},
+    view_mode::{active_view_mode, ViewMode},
     visibility::Visibility,
 };
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
-    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    ecs::{
+        hibitset::{BitSet, BitSetLike},
+        Entities, Entity, Join, Read, ReadExpect, ReadStorage, SystemData, World,
+    },
     transform::Transform,
     Hidden, HiddenPropagate,
 };
 use derivative::Derivative;
+use fnv::{FnvHashMap, FnvHashSet};
 use rendy::{
     command::{QueueId, RenderPassEncoder},
     factory::Factory,
     graph::{
         render::{PrepareResult, RenderGroup, RenderGroupDesc},
-        GraphContext, NodeBuffer, NodeImage,
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
     },
     hal::{self, device::Device, pso},
     mesh::{AsVertex, VertexFormat},
-    shader::{Shader, SpirvShader},
+    shader::SpirvShader,
 };
 use smallvec::SmallVec;
-use std::marker::PhantomData;
+use std::{cmp::Ordering, collections::BTreeMap, marker::PhantomData, ops::Range, time::Instant};
+
+/// Number of distinct `DepthBias` values of a pass above which a warning is logged.
+const DEPTH_BIAS_WARN_COUNT: usize = 4;
 
 macro_rules! profile_scope_impl {
     ($string:expr) => {
@@ -58,9 +76,84 @@ pub trait Base3DPassDef: 'static + std::fmt::Debug + Send + Sync {
     /// Returns the vertex `SpirvShader` which will be used for this pass on skinned meshes
     fn vertex_skinned_shader() -> &'static SpirvShader;
 
+    /// Returns the vertex `SpirvShader` which will be used for this pass on meshes with
+    /// `BlendShapes`, or `None` if the pass doesn't support morphing
+    fn vertex_morphed_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the vertex `SpirvShader` which will be used for this pass on skinned meshes with
+    /// `BlendShapes`, which are morphed before being skinned
+    fn vertex_skinned_morphed_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the vertex `SpirvShader` which will be used for this pass on meshes with given
+    /// deformation when transforming their texture coordinates by the `UvTransform`s of their
+    /// material, or `None` if the pass doesn't support UV transforms
+    fn vertex_uv_transform_shader(_skinned: bool, _morphed: bool) -> Option<&'static SpirvShader> {
+        None
+    }
+
     /// Returns the fragment `SpirvShader` which will be used for this pass
     fn fragment_shader() -> &'static SpirvShader;
 
+    /// Returns the fragment `SpirvShader` which will be used for this pass when sampling the
+    /// maps of materials with a `triplanar_scale` along the world axes, or `None` if the pass
+    /// doesn't support triplanar mapping
+    fn fragment_triplanar_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the fragment `SpirvShader` which will be used for this pass when blending the
+    /// surface layer of materials over them, with or without triplanar mapping, or `None` if
+    /// the pass doesn't support surface layers
+    fn fragment_surface_layer_shader(_triplanar: bool) -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the fragment `SpirvShader` which will be used for this pass when drawing the
+    /// diffuse lighting scattered under the surface of materials, or `None` if the pass
+    /// doesn't support subsurface scattering
+    fn fragment_subsurface_shader() -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the fragment `SpirvShader` of given variant without the paths discarding
+    /// fragments, drawing the materials which `Material::discards` nothing with their depth
+    /// tested before shading, or `None` if the pass has no such variant
+    fn fragment_early_depth_shader(
+        _subsurface: bool,
+        _triplanar: bool,
+        _surface_layer: bool,
+    ) -> Option<&'static SpirvShader> {
+        None
+    }
+
+    /// Returns the file name and GLSL source of the fragment shaders of this pass, into which
+    /// a `MaterialShader` is injected, or `None` if the pass doesn't accept material shaders
+    fn fragment_source() -> Option<(&'static str, &'static str)> {
+        None
+    }
+
+    /// Returns the `PolygonMode` the triangles of this pass are rasterized with
+    fn polygon_mode() -> pso::PolygonMode {
+        pso::PolygonMode::Fill
+    }
+
+    /// Returns whether this pass adds its fragments to the target without testing or writing
+    /// depth, e.g. to count overdraw, instead of drawing them with the `DepthMode` of entities
+    fn additive() -> bool {
+        false
+    }
+
+    /// Returns whether this pass draws over the surfaces drawn by the other passes, e.g. their
+    /// edges, testing depth without writing it and pulled towards the camera by a small depth
+    /// bias so that its fragments don't fight with the surfaces
+    fn overlay() -> bool {
+        false
+    }
+
     /// Returns the `VertexFormat` of this pass
     fn base_format() -> Vec<VertexFormat>;
 
@@ -68,11 +161,285 @@ pub trait Base3DPassDef: 'static + std::fmt::Debug + Send + Sync {
     fn skinned_format() -> Vec<VertexFormat>;
 }
 
+/// Vertex deformation of a mesh, each drawn with its own pipelines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Deformation {
+    Skinned,
+    Morphed,
+    SkinnedMorphed,
+}
+
+impl Deformation {
+    /// Deformation of a mesh with or without joints and blend shapes, or `None` when the pass
+    /// draws it undeformed.
+    fn of(settings: &PipelineSettings, joints: bool, shapes: bool) -> Option<Self> {
+        match (joints && settings.skinning, shapes && settings.morphing) {
+            (true, true) => Some(Deformation::SkinnedMorphed),
+            (true, false) => Some(Deformation::Skinned),
+            (false, true) => Some(Deformation::Morphed),
+            (false, false) => None,
+        }
+    }
+}
+
+/// Batch of an undeformed mesh part in a retained draw list, sorted like the batches rebuilt
+/// every frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RetainedKey {
+    mode: DepthMode,
+    bias: DepthBias,
+    material: Handle<Material>,
+    part: MeshPart,
+}
+
+impl RetainedKey {
+    fn order(&self) -> (DepthMode, DepthBias, u32, u32, Option<(u32, u32)>) {
+        (
+            self.mode,
+            self.bias,
+            self.material.id(),
+            self.part.mesh,
+            self.part.indices.as_ref().map(|i| (i.start, i.end)),
+        )
+    }
+}
+
+impl PartialOrd for RetainedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RetainedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order().cmp(&other.order())
+    }
+}
+
+/// Instanced draw of an undeformed mesh part, in drawing order.
+#[derive(Debug)]
+struct StaticDraw {
+    mode: DepthMode,
+    bias: DepthBias,
+    material: MaterialId,
+    part: MeshPart,
+    instances: Range<u32>,
+}
+
+/// Pipelines of a 3D pass for one `DepthMode`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+struct DepthPipelines<B: Backend> {
+    basic: B::GraphicsPipeline,
+    skinned: Option<B::GraphicsPipeline>,
+    morphed: Option<B::GraphicsPipeline>,
+    skinned_morphed: Option<B::GraphicsPipeline>,
+}
+
+impl<B: Backend> DepthPipelines<B> {
+    fn deformed_mut(&mut self, deformation: Deformation) -> &mut Option<B::GraphicsPipeline> {
+        match deformation {
+            Deformation::Skinned => &mut self.skinned,
+            Deformation::Morphed => &mut self.morphed,
+            Deformation::SkinnedMorphed => &mut self.skinned_morphed,
+        }
+    }
+
+    /// Pipeline of a deformation, which is built whenever `Deformation::of` returns it.
+    fn deformed(&self, deformation: Deformation) -> &B::GraphicsPipeline {
+        match deformation {
+            Deformation::Skinned => &self.skinned,
+            Deformation::Morphed => &self.morphed,
+            Deformation::SkinnedMorphed => &self.skinned_morphed,
+        }
+        .as_ref()
+        .unwrap()
+    }
+}
+
+/// Settings shared by all pipelines of a 3D pass.
+#[derive(Clone, Copy, Debug)]
+struct PipelineSettings {
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    viewport: Viewport,
+    skinning: bool,
+    morphing: bool,
+    uv_transform: bool,
+    triplanar: bool,
+    surface_layer: bool,
+    subsurface: bool,
+    transparent: bool,
+    front_face: pso::FrontFace,
+    line_width: f32,
+}
+
+impl PipelineSettings {
+    /// Deformations drawn by the pass, in the order their pipelines are built.
+    fn deformations(&self) -> Vec<Deformation> {
+        let mut deformations = Vec::new();
+        if self.skinning {
+            deformations.push(Deformation::Skinned);
+        }
+        if self.morphing {
+            deformations.push(Deformation::Morphed);
+            if self.skinning {
+                deformations.push(Deformation::SkinnedMorphed);
+            }
+        }
+        deformations
+    }
+}
+
+/// Pipelines of a 3D pass with given depth bias, drawing materials which discard fragments or
+/// not, see `Material::discards`.
+type VariantKey = (DepthBias, bool);
+
+/// Pipelines of a 3D pass for every `DepthMode` and every `DepthBias` in use, with the paths
+/// discarding fragments or without them. Pipelines of a depth bias other than the default, or
+/// without discard, are built when they are first drawn with.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+struct PipelineCache<B: Backend> {
+    variants: FnvHashMap<VariantKey, Vec<DepthPipelines<B>>>,
+    failed: FnvHashSet<VariantKey>,
+    settings: PipelineSettings,
+    fragment: Option<SpirvShader>,
+}
+
+impl<B: Backend> PipelineCache<B> {
+    fn deforming(&self) -> bool {
+        self.settings.skinning || self.settings.morphing
+    }
+
+    fn deformation(&self, joints: bool, shapes: bool) -> Option<Deformation> {
+        Deformation::of(&self.settings, joints, shapes)
+    }
+
+    /// Pipelines of given depth mode and bias drawing a material which discards fragments or
+    /// not. Falls back to the pipelines with discard, then to the default bias, when these
+    /// failed to build.
+    fn get(&self, mode: DepthMode, bias: DepthBias, discards: bool) -> &DepthPipelines<B> {
+        let variant = self
+            .variants
+            .get(&(bias, discards))
+            .or_else(|| self.variants.get(&(bias, true)))
+            .unwrap_or_else(|| &self.variants[&(DepthBias::default(), true)]);
+        &variant[mode.index()]
+    }
+
+    /// Build the pipelines of all given depth biases and discards which are missing.
+    ///
+    /// Materials are always drawn with discard when the pass has a material shader, which
+    /// may lower their alpha below the cutoff.
+    fn prepare<T: Base3DPassDef>(
+        &mut self,
+        factory: &Factory<B>,
+        subpass: hal::pass::Subpass<'_, B>,
+        layout: &B::PipelineLayout,
+        keys: impl IntoIterator<Item = VariantKey>,
+    ) {
+        for (bias, discards) in keys {
+            let early_depth = match &self.fragment {
+                None if !discards && !self.failed.contains(&(bias, false)) => {
+                    early_depth_fragment_shader::<T>(&self.settings)
+                }
+                _ => None,
+            };
+            let key = (bias, early_depth.is_none());
+            if self.variants.contains_key(&key) || self.failed.contains(&key) {
+                continue;
+            }
+            let fragment = early_depth.or(self.fragment.as_ref());
+            let new_bias = !self.variants.keys().any(|&(b, _)| b == bias);
+            match build_variant::<B, T>(factory, subpass, layout, &self.settings, fragment, bias) {
+                Ok(variant) => {
+                    self.variants.insert(key, variant);
+                    let biases = self
+                        .variants
+                        .keys()
+                        .map(|&(b, _)| b)
+                        .collect::<FnvHashSet<_>>()
+                        .len();
+                    if new_bias && biases == DEPTH_BIAS_WARN_COUNT + 1 {
+                        log::warn!(
+                            "Pass {} draws with more than {} distinct `DepthBias` values, each of \
+                             them needs its own pipelines and splits the batches.",
+                            T::NAME,
+                            DEPTH_BIAS_WARN_COUNT
+                        );
+                    }
+                }
+                Err(e) => {
+                    if key.1 {
+                        log::error!(
+                            "Failed to build pipelines of pass {} for {:?}, drawing without \
+                             bias: {}",
+                            T::NAME,
+                            bias,
+                            e
+                        );
+                    } else {
+                        log::error!(
+                            "Failed to build pipelines of pass {} without discard for {:?}, \
+                             drawing with discard: {}",
+                            T::NAME,
+                            bias,
+                            e
+                        );
+                    }
+                    self.failed.insert(key);
+                }
+            }
+        }
+    }
+
+    /// Number of draws drawn with the paths discarding fragments, among draws of given
+    /// pipeline keys and counts.
+    fn discard_draws(&self, draws: impl IntoIterator<Item = (VariantKey, usize)>) -> usize {
+        draws
+            .into_iter()
+            .filter(|&((bias, discards), _)| {
+                discards || !self.variants.contains_key(&(bias, false))
+            })
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    unsafe fn dispose(self, factory: &Factory<B>) {
+        for pipelines in self.variants.into_values().flatten() {
+            factory.device().destroy_graphics_pipeline(pipelines.basic);
+            for pipeline in pipelines
+                .skinned
+                .into_iter()
+                .chain(pipelines.morphed)
+                .chain(pipelines.skinned_morphed)
+            {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+        }
+    }
+}
+
 /// Draw opaque 3d meshes with specified shaders and texture set
 #[derive(Clone, Derivative)]
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct DrawBase3DDesc<B: Backend, T: Base3DPassDef> {
     skinning: bool,
+    morphing: bool,
+    uv_transform: bool,
+    triplanar: bool,
+    surface_layer: bool,
+    subsurface: bool,
+    indirect_draws: bool,
+    retained_draw_list: bool,
+    draw_culled: bool,
+    shadows: bool,
+    view: ViewBinding,
+    view_mode: ViewMode,
+    #[derivative(Default(value = "1.0"))]
+    line_width: f32,
+    material_shader: Option<MaterialShader>,
     marker: PhantomData<(B, T)>,
 }
 
@@ -86,7 +453,7 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DDesc<B, T> {
     pub fn skinned() -> Self {
         Self {
             skinning: true,
-            marker: PhantomData,
+            ..Default::default()
         }
     }
 
@@ -95,20 +462,127 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DDesc<B, T> {
         self.skinning = skinned;
         self
     }
+
+    /// Create pass in with morphing of meshes with `BlendShapes` enabled if true is passed
+    pub fn with_morphing(mut self, morphing: bool) -> Self {
+        self.morphing = morphing;
+        self
+    }
+
+    /// Transform the texture coordinates of meshes by the `UvTransform`s of their material if
+    /// true is passed. Without it the transforms are ignored, and the vertex shaders don't
+    /// read the material.
+    pub fn with_uv_transform(mut self, uv_transform: bool) -> Self {
+        self.uv_transform = uv_transform;
+        self
+    }
+
+    /// Sample the maps of materials with a `triplanar_scale` along the world axes if true is
+    /// passed. Without it the triplanar settings of materials are ignored.
+    pub fn with_triplanar(mut self, triplanar: bool) -> Self {
+        self.triplanar = triplanar;
+        self
+    }
+
+    /// Blend the surface layer of materials over them if true is passed, see
+    /// `Material::layer_scene_factor`. Without it the surface layer settings of materials and
+    /// the scene `SurfaceLayer` are ignored, and the fragment shaders don't sample the masks.
+    pub fn with_surface_layer(mut self, surface_layer: bool) -> Self {
+        self.surface_layer = surface_layer;
+        self
+    }
+
+    /// Draw the diffuse lighting of materials scaled by their `subsurface` strength, with the
+    /// strength in alpha, instead of shading them if true is passed. Used for the input of
+    /// `DrawSubsurfaceDesc`, triplanar mapping and surface layers are ignored meanwhile.
+    pub fn with_subsurface(mut self, subsurface: bool) -> Self {
+        self.subsurface = subsurface;
+        self
+    }
+
+    /// Draw meshes which aren't deformed with indirect draws if true is passed, reading the
+    /// draw parameters from a buffer written every frame. Falls back to direct draws on
+    /// devices without indirect draws with a first instance.
+    ///
+    /// Consecutive draws of the same mesh and material share one indirect call. Meshes without
+    /// indices are still drawn directly.
+    pub fn with_indirect_draws(mut self, indirect_draws: bool) -> Self {
+        self.indirect_draws = indirect_draws;
+        self
+    }
+
+    /// Keep the batches of meshes which aren't deformed between frames if true is passed,
+    /// only moving the entities whose mesh, material or depth settings changed, and updating
+    /// the ones whose instance data changed. The number of entries touched is added to
+    /// `RenderStats::draw_list_touched`.
+    ///
+    /// Without it, the batches are rebuilt from scratch every frame.
+    pub fn with_retained_draw_list(mut self, retained_draw_list: bool) -> Self {
+        self.retained_draw_list = retained_draw_list;
+        self
+    }
+
+    /// Skip the entities culled by the `VisibilitySortingSystem` if true is passed, the
+    /// default. Without it every opaque entity which isn't hidden is drawn, whether it is in
+    /// the frustum of the camera or not, e.g. to debug culling.
+    pub fn with_frustum_culling(mut self, frustum_culling: bool) -> Self {
+        self.draw_culled = !frustum_culling;
+        self
+    }
+
+    /// Darken the light casting shadows with the shadow map if true is passed, which must
+    /// then be the only image of the pass, e.g. the depth of `SHADOW_MAP_TARGET`. Only the
+    /// shaded and PBR passes sample it, see [crate::shadow].
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Draw the entities visible to the view at given index of `Views`, using its camera
+    /// and viewport.
+    pub fn with_view(mut self, index: usize, view: &View) -> Self {
+        self.view = ViewBinding::new(index, view);
+        self
+    }
+
+    /// Only draw while the scene is shown in given `ViewMode`, `ViewMode::Lit` by default.
+    pub fn with_view_mode(mut self, view_mode: ViewMode) -> Self {
+        self.view_mode = view_mode;
+        self
+    }
+
+    /// Set the width in pixels of the lines of passes drawing edges, 1 by default. Other
+    /// widths require the `LINE_WIDTH` device feature.
+    pub fn with_line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Modify the materials with given GLSL snippet before lighting them, see
+    /// `MaterialShader`. Building the pass fails when the pass doesn't accept material
+    /// shaders, or the snippet doesn't compile.
+    pub fn with_material_shader(mut self, shader: MaterialShader) -> Self {
+        self.material_shader = Some(shader);
+        self
+    }
 }
 
 impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<B, T> {
+    fn images(&self) -> Vec<ImageAccess> {
+        shadow_map_access(self.shadows)
+    }
+
     fn build(
         self,
-        _ctx: &GraphContext<B>,
+        ctx: &GraphContext<B>,
         factory: &mut Factory<B>,
-        _queue: QueueId,
-        _aux: &World,
+        queue: QueueId,
+        aux: &World,
         framebuffer_width: u32,
         framebuffer_height: u32,
         subpass: hal::pass::Subpass<'_, B>,
         _buffers: Vec<NodeBuffer>,
-        _images: Vec<NodeImage>,
+        images: Vec<NodeImage>,
     ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
         profile_scope_impl!("build");
 
@@ -118,26 +592,57 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
                 hal::pso::ShaderStageFlags::VERTEX,
                 hal::pso::ShaderStageFlags::FRAGMENT,
             ],
-        )?;
+        )?
+        .with_camera(self.view.camera)
+        .with_shadow_map(ctx, factory, queue, images.first().filter(|_| self.shadows))?;
         let materials = MaterialSub::new(factory)?;
         let skinning = SkinningSub::new(factory)?;
+        let morph = MorphSub::new(factory)?;
+        let indirect = if self.indirect_draws {
+            let indirect = IndirectDrawSub::new(factory);
+            if indirect.is_none() {
+                log::info!(
+                    "Pass {} draws directly, the device doesn't support indirect draws with a \
+                     first instance.",
+                    T::NAME
+                );
+            }
+            indirect
+        } else {
+            None
+        };
 
         let mut vertex_format_base = T::base_format();
         let mut vertex_format_skinned = T::skinned_format();
 
-        let (mut pipelines, pipeline_layout) = build_pipelines::<B, T>(
-            factory,
-            subpass,
+        let subsurface = self.subsurface && supports_subsurface::<T>();
+        let triplanar = !subsurface && self.triplanar && supports_triplanar::<T>();
+        let settings = PipelineSettings {
             framebuffer_width,
             framebuffer_height,
-            &vertex_format_base,
-            &vertex_format_skinned,
-            self.skinning,
-            false,
+            viewport: self.view.viewport,
+            skinning: self.skinning,
+            morphing: self.morphing && supports_morphing::<T>(),
+            uv_transform: self.uv_transform && supports_uv_transform::<T>(),
+            triplanar,
+            surface_layer: !subsurface
+                && self.surface_layer
+                && supports_surface_layer::<T>(triplanar),
+            subsurface,
+            transparent: false,
+            front_face: Conventions::of(aux).rasterizer_front_face(),
+            line_width: self.line_width,
+        };
+        let (pipelines, pipeline_layout) = build_pipelines::<B, T>(
+            factory,
+            subpass,
+            settings,
+            self.material_shader.as_ref(),
             vec![
                 env.raw_layout(),
                 materials.raw_layout(),
                 skinning.raw_layout(),
+                morph.raw_layout(),
             ],
         )?;
 
@@ -145,18 +650,32 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
         vertex_format_skinned.sort();
 
         Ok(Box::new(DrawBase3D::<B, T> {
-            pipeline_basic: pipelines.remove(0),
-            pipeline_skinned: pipelines.pop(),
+            pipelines,
+            indirect,
             pipeline_layout,
             static_batches: Default::default(),
+            retained: if self.retained_draw_list {
+                Some(Default::default())
+            } else {
+                None
+            },
+            static_draws: Vec::new(),
+            static_runs: DrawRuns::default(),
             skinned_batches: Default::default(),
             vertex_format_base,
             vertex_format_skinned,
             env,
             materials,
             skinning,
-            models: DynamicVertexBuffer::new(),
-            skinned_models: DynamicVertexBuffer::new(),
+            morph,
+            models: DynamicVertexBuffer::new().with_partial_updates(),
+            skinned_models: DynamicVertexBuffer::new().with_partial_updates(),
+            view_index: self.view.index,
+            view_mode: self.view_mode,
+            draw_culled: self.draw_culled,
+            active: true,
+            empty: false,
+            warned_unsorted: false,
             marker: PhantomData,
         }))
     }
@@ -167,18 +686,34 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DDesc<
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
 pub struct DrawBase3D<B: Backend, T: Base3DPassDef> {
-    pipeline_basic: B::GraphicsPipeline,
-    pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipelines: PipelineCache<B>,
+    indirect: Option<IndirectDrawSub<B>>,
     pipeline_layout: B::PipelineLayout,
-    static_batches: TwoLevelBatch<MaterialId, u32, SmallVec<[VertexArgs; 4]>>,
-    skinned_batches: TwoLevelBatch<MaterialId, u32, SmallVec<[SkinnedVertexArgs; 4]>>,
+    static_batches: BTreeMap<
+        (DepthMode, DepthBias),
+        TwoLevelBatch<MaterialId, MeshPart, SmallVec<[VertexArgs; 4]>>,
+    >,
+    retained: Option<RetainedBatch<RetainedKey, VertexArgs>>,
+    static_draws: Vec<StaticDraw>,
+    static_runs: DrawRuns<(DepthMode, DepthBias, MaterialId, u32)>,
+    skinned_batches: BTreeMap<
+        (Deformation, DepthMode, DepthBias),
+        TwoLevelBatch<MaterialId, MeshPart, SmallVec<[SkinnedVertexArgs; 4]>>,
+    >,
     vertex_format_base: Vec<VertexFormat>,
     vertex_format_skinned: Vec<VertexFormat>,
     env: EnvironmentSub<B>,
     materials: MaterialSub<B, T::TextureSet>,
     skinning: SkinningSub<B>,
+    morph: MorphSub<B>,
     models: DynamicVertexBuffer<B, VertexArgs>,
     skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    view_index: Option<usize>,
+    view_mode: ViewMode,
+    draw_culled: bool,
+    active: bool,
+    empty: bool,
+    warned_unsorted: bool,
     marker: PhantomData<T>,
 }
 
@@ -188,14 +723,21 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
         factory: &Factory<B>,
         _queue: QueueId,
         index: usize,
-        _subpass: hal::pass::Subpass<'_, B>,
+        subpass: hal::pass::Subpass<'_, B>,
         resources: &World,
     ) -> PrepareResult {
         profile_scope_impl!("prepare opaque");
 
+        self.active = active_view_mode(resources).draws(self.view_mode);
+        if !self.active {
+            return PrepareResult::DrawRecord;
+        }
+
         let (
+            entities,
             mesh_storage,
             visibility,
+            view_visibility,
             transparent,
             hiddens,
             hiddens_prop,
@@ -203,10 +745,17 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             materials,
             transforms,
             joints,
+            blend_shapes,
             tints,
+            instance_data,
+            depth_modes,
+            depth_biases,
+            sub_meshes,
         ) = <(
+            Entities<'_>,
             Read<'_, AssetStorage<Mesh>>,
             ReadExpect<'_, Visibility>,
+            Option<Read<'_, ViewVisibility>>,
             ReadStorage<'_, Transparent>,
             ReadStorage<'_, Hidden>,
             ReadStorage<'_, HiddenPropagate>,
@@ -214,57 +763,149 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
             ReadStorage<'_, Handle<Material>>,
             ReadStorage<'_, Transform>,
             ReadStorage<'_, JointTransforms>,
+            ReadStorage<'_, BlendShapes>,
             ReadStorage<'_, Tint>,
+            ReadStorage<'_, InstanceData>,
+            ReadStorage<'_, DepthMode>,
+            ReadStorage<'_, DepthBias>,
+            ReadStorage<'_, SubMeshes>,
         )>::fetch(resources);
+        let visibility = view_visibility_or(&visibility, &view_visibility, self.view_index);
+        let unculled;
+        let visible = if self.draw_culled {
+            unculled =
+                (meshes.mask() & !transparent.mask() & !hiddens.mask() & !hiddens_prop.mask())
+                    .iter()
+                    .collect::<BitSet>();
+            &unculled
+        } else {
+            &visibility.visible_unordered
+        };
+
+        // Skip the uploads of lights and instances when there is nothing to draw, unless the
+        // retained draw list still has to drop the entries of the last frame.
+        self.empty = (meshes.mask() & visible).iter().next().is_none()
+            && self.retained.as_ref().is_none_or(|r| r.count() == 0);
+        if self.empty {
+            self.static_draws.clear();
+            self.static_runs.clear();
+            return PrepareResult::DrawRecord;
+        }
 
         // Prepare environment
         self.env.process(factory, index, resources);
         self.materials.maintain();
 
-        self.static_batches.clear_inner();
-        self.skinned_batches.clear_inner();
+        self.static_batches
+            .values_mut()
+            .for_each(|b| b.clear_inner());
+        self.skinned_batches
+            .values_mut()
+            .for_each(|b| b.clear_inner());
 
         let materials_ref = &mut self.materials;
         let skinning_ref = &mut self.skinning;
+        let morph_ref = &mut self.morph;
         let statics_ref = &mut self.static_batches;
+        let retained_ref = &mut self.retained;
         let skinned_ref = &mut self.skinned_batches;
 
-        let static_input = || ((&materials, &meshes, &transforms, tints.maybe()), !&joints);
-        let skinned_input = || (&materials, &meshes, &transforms, tints.maybe(), &joints);
-        {
+        let mode = |depth: Option<&DepthMode>| depth.copied().unwrap_or(DepthMode::TestWrite);
+        let bias = |bias: Option<&DepthBias>| bias.copied().unwrap_or_default();
+        let pipelines = &self.pipelines;
+        let undeformed =
+            |shapes: Option<&BlendShapes>| pipelines.deformation(false, shapes.is_some()).is_none();
+        let static_input = || {
+            (
+                (&meshes, &transforms, (tints.maybe(), instance_data.maybe())),
+                (materials.maybe(), sub_meshes.maybe()),
+                (depth_modes.maybe(), depth_biases.maybe()),
+                (!&joints, blend_shapes.maybe()),
+            )
+        };
+        let deformed_input = || {
+            (
+                (&meshes, &transforms, (tints.maybe(), instance_data.maybe())),
+                (materials.maybe(), sub_meshes.maybe()),
+                (depth_modes.maybe(), depth_biases.maybe()),
+                (joints.maybe(), blend_shapes.maybe()),
+            )
+        };
+        if let Some(retained) = retained_ref {
+            profile_scope_impl!("prepare_retained");
+
+            retained.begin();
+            for (entity, ((mesh, tform, tint_data), (mat, subs), (depth, bias_of), _), _) in
+                (&entities, static_input(), visible)
+                    .join()
+                    .filter(|(_, (_, _, _, (_, shapes)), _)| undeformed(*shapes))
+            {
+                let args = VertexArgs::from_object_data(tform, tint_data.0)
+                    .with_instance_data(tint_data.1);
+                for (slot, (mat, part)) in mesh_parts(mesh.id(), mat, subs).enumerate() {
+                    let key = RetainedKey {
+                        mode: mode(depth),
+                        bias: bias(bias_of),
+                        material: mat.clone(),
+                        part,
+                    };
+                    retained.update(u64::from(entity.id()) << 32 | slot as u64, key, args);
+                }
+            }
+            retained.finish();
+            if let Some(mut stats) = resources.try_fetch_mut::<RenderStats>() {
+                stats.draw_list_touched += retained.touched();
+            }
+        } else {
             profile_scope_impl!("prepare");
-            (static_input(), &visibility.visible_unordered)
+            (static_input(), visible)
                 .join()
-                .map(|(((mat, mesh, tform, tint), _), _)| {
-                    ((mat, mesh.id()), VertexArgs::from_object_data(tform, tint))
-                })
-                .for_each_group(|(mat, mesh_id), data| {
-                    if mesh_storage.contains_id(mesh_id) {
+                .filter(|((_, _, _, (_, shapes)), _)| undeformed(*shapes))
+                .flat_map(
+                    |(((mesh, tform, tint_data), (mat, subs), (depth, bias_of), _), _)| {
+                        let args = VertexArgs::from_object_data(tform, tint_data.0)
+                            .with_instance_data(tint_data.1);
+                        mesh_parts(mesh.id(), mat, subs)
+                            .map(move |(mat, part)| ((mode(depth), bias(bias_of), mat, part), args))
+                    },
+                )
+                .for_each_group(|(mode, bias, mat, part), data| {
+                    if mesh_storage.contains_id(part.mesh) {
                         if let Some((mat, _)) = materials_ref.insert(factory, resources, mat) {
-                            statics_ref.insert(mat, mesh_id, data.drain(..));
+                            statics_ref.entry((mode, bias)).or_default().insert(
+                                mat,
+                                part,
+                                data.drain(..),
+                            );
                         }
                     }
                 });
         }
-        if self.pipeline_skinned.is_some() {
-            profile_scope_impl!("prepare_skinning");
+        if self.pipelines.deforming() {
+            profile_scope_impl!("prepare_deformed");
 
-            (skinned_input(), &visibility.visible_unordered)
+            (deformed_input(), visible)
                 .join()
-                .map(|((mat, mesh, tform, tint, joints), _)| {
-                    (
-                        (mat, mesh.id()),
-                        SkinnedVertexArgs::from_object_data(
-                            tform,
-                            tint,
-                            skinning_ref.insert(joints),
-                        ),
-                    )
+                .filter_map(|((object, material, depth, (joints, shapes)), _)| {
+                    let deformation = pipelines.deformation(joints.is_some(), shapes.is_some())?;
+                    Some((object, material, depth, (deformation, joints, shapes)))
                 })
-                .for_each_group(|(mat, mesh_id), data| {
-                    if mesh_storage.contains_id(mesh_id) {
+                .flat_map(
+                    |((mesh, tform, tint_data), (mat, subs), (depth, bias_of), deform)| {
+                        let (deformation, args) =
+                            deformed_args(skinning_ref, morph_ref, tform, tint_data, deform);
+                        mesh_parts(mesh.id(), mat, subs).map(move |(mat, part)| {
+                            ((deformation, mode(depth), bias(bias_of), mat, part), args)
+                        })
+                    },
+                )
+                .for_each_group(|(deformation, mode, bias, mat, part), data| {
+                    if mesh_storage.contains_id(part.mesh) {
                         if let Some((mat, _)) = materials_ref.insert(factory, resources, mat) {
-                            skinned_ref.insert(mat, mesh_id, data.drain(..));
+                            skinned_ref
+                                .entry((deformation, mode, bias))
+                                .or_default()
+                                .insert(mat, part, data.drain(..));
                         }
                     }
                 });
@@ -273,23 +914,141 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
         {
             profile_scope_impl!("write");
 
-            self.static_batches.prune();
-            self.skinned_batches.prune();
+            self.static_batches.values_mut().for_each(|b| b.prune());
+            self.skinned_batches.values_mut().for_each(|b| b.prune());
+
+            self.static_draws.clear();
+            let mut instances = 0;
+            if let Some(retained) = &self.retained {
+                for (key, data) in retained.iter() {
+                    let count = data.len() as u32;
+                    if mesh_storage.contains_id(key.part.mesh) {
+                        if let Some((material, _)) =
+                            self.materials.insert(factory, resources, &key.material)
+                        {
+                            self.static_draws.push(StaticDraw {
+                                mode: key.mode,
+                                bias: key.bias,
+                                material,
+                                part: key.part.clone(),
+                                instances: instances..instances + count,
+                            });
+                        }
+                    }
+                    instances += count;
+                }
+                self.models.write(
+                    factory,
+                    index,
+                    retained.count() as u64,
+                    retained.iter().map(|(_, data)| data),
+                );
+            } else {
+                for (&(mode, bias), batches) in &self.static_batches {
+                    for (&material, parts) in batches.iter() {
+                        for (part, batch_data) in parts {
+                            let count = batch_data.len() as u32;
+                            self.static_draws.push(StaticDraw {
+                                mode,
+                                bias,
+                                material,
+                                part: part.clone(),
+                                instances: instances..instances + count,
+                            });
+                            instances += count;
+                        }
+                    }
+                }
+                self.models.write(
+                    factory,
+                    index,
+                    self.static_batches
+                        .values()
+                        .map(|b| b.count())
+                        .sum::<usize>() as u64,
+                    self.static_batches.values().flat_map(|b| b.data()),
+                );
+            }
 
-            self.models.write(
-                factory,
-                index,
-                self.static_batches.count() as u64,
-                self.static_batches.data(),
-            );
+            // Consecutive indexed draws of the same mesh and material are drawn with one
+            // indirect call. Draws of meshes without indices are always drawn directly.
+            self.static_runs.clear();
+            for draw in &self.static_draws {
+                let mesh = unsafe { mesh_storage.get_by_id_unchecked(draw.part.mesh) };
+                let key = if self.indirect.is_some() && mesh.is_indexed() {
+                    Some((draw.mode, draw.bias, draw.material, draw.part.mesh))
+                } else {
+                    None
+                };
+                self.static_runs.push(key);
+            }
+            if let Some(indirect) = self.indirect.as_mut() {
+                // One command per draw, in drawing order, so that runs index them.
+                for draw in &self.static_draws {
+                    let len =
+                        B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(draw.part.mesh) })
+                            .map_or(0, |mesh| mesh.len());
+                    indirect.push(draw.part.index_range(len), draw.instances.clone());
+                }
+                indirect.commit(factory, index);
+            }
 
             self.skinned_models.write(
                 factory,
                 index,
-                self.skinned_batches.count() as u64,
-                self.skinned_batches.data(),
+                self.skinned_batches
+                    .values()
+                    .map(|b| b.count())
+                    .sum::<usize>() as u64,
+                self.skinned_batches.values().flat_map(|b| b.data()),
             );
             self.skinning.commit(factory, index);
+            self.morph.commit(factory, index);
+        }
+
+        let materials = &self.materials;
+        let static_keys = self
+            .static_draws
+            .iter()
+            .map(|draw| ((draw.bias, materials.discards(draw.material)), 1));
+        let skinned_keys = self
+            .skinned_batches
+            .iter()
+            .flat_map(|(&(_, _, bias), batches)| {
+                batches
+                    .iter()
+                    .map(move |(&mat, parts)| ((bias, materials.discards(mat)), parts.count()))
+            });
+        let keys = static_keys.chain(skinned_keys).collect::<Vec<_>>();
+        self.pipelines.prepare::<T>(
+            factory,
+            subpass,
+            &self.pipeline_layout,
+            keys.iter().map(|&(key, _)| key),
+        );
+        if let Some(mut stats) = resources.try_fetch_mut::<RenderStats>() {
+            stats.discard_draws += self.pipelines.discard_draws(keys);
+        }
+
+        let off = |(mode, count): (DepthMode, usize)| mode == DepthMode::Off && count > 0;
+        if !self.warned_unsorted
+            && (self
+                .static_draws
+                .iter()
+                .map(|draw| (draw.mode, draw.instances.len()))
+                .any(off)
+                || self
+                    .skinned_batches
+                    .iter()
+                    .map(|(&(_, mode, _), b)| (mode, b.count()))
+                    .any(off))
+        {
+            self.warned_unsorted = true;
+            log::warn!(
+                "Opaque meshes with `DepthMode::Off` are drawn by pass {} in no particular order. \
+                 Make them `Transparent` to sort them, or use `DepthMode::AlwaysOnTop`.",
+                T::NAME
+            );
         }
         PrepareResult::DrawRecord
     }
@@ -303,65 +1062,133 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
     ) {
         profile_scope_impl!("draw opaque");
 
+        if !self.active || self.empty {
+            return;
+        }
+        let start = Instant::now();
+        let mut draws = 0;
+
         let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
         let models_loc = self.vertex_format_base.len() as u32;
-        let skin_models_loc = self.vertex_format_skinned.len() as u32;
 
-        encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        encoder.bind_graphics_pipeline(
+            &self
+                .pipelines
+                .get(DepthMode::TestWrite, DepthBias::default(), true)
+                .basic,
+        );
         self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
 
         if self.models.bind(index, models_loc, 0, &mut encoder) {
-            let mut instances_drawn = 0;
-            for (&mat_id, batches) in self.static_batches.iter() {
-                if self.materials.loaded(mat_id) {
+            let mut bound_pipeline = None;
+            let mut bound_material = None;
+            let mut bound_mesh = None;
+            for run in self.static_runs.runs() {
+                let first = match run {
+                    DrawRun::Direct(draw) => *draw,
+                    DrawRun::Indirect(commands) => commands.start,
+                };
+                let draw = &self.static_draws[first as usize];
+                let pipeline = (draw.mode, draw.bias, self.materials.discards(draw.material));
+                if bound_pipeline != Some(pipeline) {
+                    let (mode, bias, discards) = pipeline;
+                    encoder.bind_graphics_pipeline(&self.pipelines.get(mode, bias, discards).basic);
+                    bound_pipeline = Some(pipeline);
+                }
+                if !self.materials.loaded(draw.material) {
+                    continue;
+                }
+                if bound_material != Some(draw.material) {
                     self.materials
-                        .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
-                    for (mesh_id, batch_data) in batches {
-                        debug_assert!(mesh_storage.contains_id(*mesh_id));
-                        if let Some(mesh) =
-                            B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) })
-                        {
-                            mesh.bind_and_draw(
-                                0,
+                        .bind(&self.pipeline_layout, 1, draw.material, &mut encoder);
+                    bound_material = Some(draw.material);
+                }
+                debug_assert!(mesh_storage.contains_id(draw.part.mesh));
+                if let Some(mesh) =
+                    B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(draw.part.mesh) })
+                {
+                    match (run, &self.indirect) {
+                        (DrawRun::Indirect(commands), Some(indirect)) => {
+                            draw.part
+                                .bind(
+                                    mesh,
+                                    &mut bound_mesh,
+                                    &self.vertex_format_base,
+                                    &mut encoder,
+                                )
+                                .unwrap();
+                            indirect.draw(index, commands.clone(), &mut encoder);
+                        }
+                        _ => draw
+                            .part
+                            .draw(
+                                mesh,
+                                &mut bound_mesh,
                                 &self.vertex_format_base,
-                                instances_drawn..instances_drawn + batch_data.len() as u32,
+                                draw.instances.clone(),
                                 &mut encoder,
                             )
-                            .unwrap();
-                        }
-                        instances_drawn += batch_data.len() as u32;
+                            .unwrap(),
                     }
+                    draws += 1;
                 }
             }
         }
 
-        if let Some(pipeline_skinned) = self.pipeline_skinned.as_ref() {
-            encoder.bind_graphics_pipeline(pipeline_skinned);
-
-            if self
-                .skinned_models
-                .bind(index, skin_models_loc, 0, &mut encoder)
-            {
-                self.skinning
-                    .bind(index, &self.pipeline_layout, 2, &mut encoder);
+        if self.pipelines.deforming() {
+            self.skinning
+                .bind(index, &self.pipeline_layout, 2, &mut encoder);
+            self.morph
+                .bind(index, &self.pipeline_layout, 3, &mut encoder);
 
-                let mut instances_drawn = 0;
-                for (&mat_id, batches) in self.skinned_batches.iter() {
+            let mut instances_drawn = 0;
+            let mut bound_deformation = None;
+            let mut bound_mesh = None;
+            for (&(deformation, mode, bias), mode_batches) in &self.skinned_batches {
+                if mode_batches.count() == 0 {
+                    continue;
+                }
+                let format = deformed_format(
+                    deformation,
+                    &self.vertex_format_base,
+                    &self.vertex_format_skinned,
+                );
+                if bound_deformation != Some(deformation) {
+                    // Instance data follows the mesh attributes, whose count differs.
+                    if !self
+                        .skinned_models
+                        .bind(index, format.len() as u32, 0, &mut encoder)
+                    {
+                        break;
+                    }
+                    bound_deformation = Some(deformation);
+                    bound_mesh = None;
+                }
+                let mut bound_discards = None;
+                for (&mat_id, batches) in mode_batches.iter() {
                     if self.materials.loaded(mat_id) {
+                        let discards = self.materials.discards(mat_id);
+                        if bound_discards != Some(discards) {
+                            let pipelines = self.pipelines.get(mode, bias, discards);
+                            encoder.bind_graphics_pipeline(pipelines.deformed(deformation));
+                            bound_discards = Some(discards);
+                        }
                         self.materials
                             .bind(&self.pipeline_layout, 1, mat_id, &mut encoder);
-                        for (mesh_id, batch_data) in batches {
-                            debug_assert!(mesh_storage.contains_id(*mesh_id));
+                        for (part, batch_data) in batches {
+                            debug_assert!(mesh_storage.contains_id(part.mesh));
                             if let Some(mesh) = B::unwrap_mesh(unsafe {
-                                mesh_storage.get_by_id_unchecked(*mesh_id)
+                                mesh_storage.get_by_id_unchecked(part.mesh)
                             }) {
-                                mesh.bind_and_draw(
-                                    0,
-                                    &self.vertex_format_skinned,
+                                part.draw(
+                                    mesh,
+                                    &mut bound_mesh,
+                                    format,
                                     instances_drawn..instances_drawn + batch_data.len() as u32,
                                     &mut encoder,
                                 )
                                 .unwrap();
+                                draws += 1;
                             }
                             instances_drawn += batch_data.len() as u32;
                         }
@@ -369,17 +1196,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
                 }
             }
         }
+        RenderStats::add_draws(resources, draws, start.elapsed());
     }
 
-    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
         profile_scope_impl!("dispose");
         unsafe {
-            factory
-                .device()
-                .destroy_graphics_pipeline(self.pipeline_basic);
-            if let Some(pipeline) = self.pipeline_skinned.take() {
-                factory.device().destroy_graphics_pipeline(pipeline);
-            }
+            self.pipelines.dispose(factory);
             factory
                 .device()
                 .destroy_pipeline_layout(self.pipeline_layout);
@@ -392,23 +1215,30 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3D<B, T> {
 #[derivative(Debug(bound = ""), Default(bound = ""))]
 pub struct DrawBase3DTransparentDesc<B: Backend, T: Base3DPassDef> {
     skinning: bool,
+    morphing: bool,
+    uv_transform: bool,
+    triplanar: bool,
+    surface_layer: bool,
+    shadows: bool,
+    view: ViewBinding,
+    view_mode: ViewMode,
+    #[derivative(Default(value = "1.0"))]
+    line_width: f32,
+    material_shader: Option<MaterialShader>,
     marker: PhantomData<(B, T)>,
 }
 
 impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
     /// Create pass in default configuration
     pub fn new() -> Self {
-        Self {
-            skinning: false,
-            marker: PhantomData,
-        }
+        Default::default()
     }
 
     /// Create pass in with vertex skinning enabled
     pub fn skinned() -> Self {
         Self {
             skinning: true,
-            marker: PhantomData,
+            ..Default::default()
         }
     }
 
@@ -417,20 +1247,89 @@ impl<B: Backend, T: Base3DPassDef> DrawBase3DTransparentDesc<B, T> {
         self.skinning = skinned;
         self
     }
+
+    /// Create pass in with morphing of meshes with `BlendShapes` enabled if true is passed
+    pub fn with_morphing(mut self, morphing: bool) -> Self {
+        self.morphing = morphing;
+        self
+    }
+
+    /// Transform the texture coordinates of meshes by the `UvTransform`s of their material if
+    /// true is passed. Without it the transforms are ignored, and the vertex shaders don't
+    /// read the material.
+    pub fn with_uv_transform(mut self, uv_transform: bool) -> Self {
+        self.uv_transform = uv_transform;
+        self
+    }
+
+    /// Sample the maps of materials with a `triplanar_scale` along the world axes if true is
+    /// passed. Without it the triplanar settings of materials are ignored.
+    pub fn with_triplanar(mut self, triplanar: bool) -> Self {
+        self.triplanar = triplanar;
+        self
+    }
+
+    /// Blend the surface layer of materials over them if true is passed, see
+    /// `Material::layer_scene_factor`. Without it the surface layer settings of materials and
+    /// the scene `SurfaceLayer` are ignored, and the fragment shaders don't sample the masks.
+    pub fn with_surface_layer(mut self, surface_layer: bool) -> Self {
+        self.surface_layer = surface_layer;
+        self
+    }
+
+    /// Darken the light casting shadows with the shadow map if true is passed, which must
+    /// then be the only image of the pass, e.g. the depth of `SHADOW_MAP_TARGET`. Only the
+    /// shaded and PBR passes sample it, see [crate::shadow].
+    pub fn with_shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Draw the entities visible to the view at given index of `Views`, using its camera
+    /// and viewport.
+    pub fn with_view(mut self, index: usize, view: &View) -> Self {
+        self.view = ViewBinding::new(index, view);
+        self
+    }
+
+    /// Only draw while the scene is shown in given `ViewMode`, `ViewMode::Lit` by default.
+    pub fn with_view_mode(mut self, view_mode: ViewMode) -> Self {
+        self.view_mode = view_mode;
+        self
+    }
+
+    /// Set the width in pixels of the lines of passes drawing edges, 1 by default. Other
+    /// widths require the `LINE_WIDTH` device feature.
+    pub fn with_line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    /// Modify the materials with given GLSL snippet before lighting them, see
+    /// `MaterialShader`. Building the pass fails when the pass doesn't accept material
+    /// shaders, or the snippet doesn't compile.
+    pub fn with_material_shader(mut self, shader: MaterialShader) -> Self {
+        self.material_shader = Some(shader);
+        self
+    }
 }
 
 impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTransparentDesc<B, T> {
+    fn images(&self) -> Vec<ImageAccess> {
+        shadow_map_access(self.shadows)
+    }
+
     fn build(
         self,
-        _ctx: &GraphContext<B>,
+        ctx: &GraphContext<B>,
         factory: &mut Factory<B>,
-        _queue: QueueId,
-        _aux: &World,
+        queue: QueueId,
+        aux: &World,
         framebuffer_width: u32,
         framebuffer_height: u32,
         subpass: hal::pass::Subpass<'_, B>,
         _buffers: Vec<NodeBuffer>,
-        _images: Vec<NodeImage>,
+        images: Vec<NodeImage>,
     ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
         let env = EnvironmentSub::new(
             factory,
@@ -438,27 +1337,42 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
                 hal::pso::ShaderStageFlags::VERTEX,
                 hal::pso::ShaderStageFlags::FRAGMENT,
             ],
-        )?;
+        )?
+        .with_camera(self.view.camera)
+        .with_shadow_map(ctx, factory, queue, images.first().filter(|_| self.shadows))?;
 
         let materials = MaterialSub::new(factory)?;
         let skinning = SkinningSub::new(factory)?;
+        let morph = MorphSub::new(factory)?;
 
         let mut vertex_format_base = T::base_format();
         let mut vertex_format_skinned = T::skinned_format();
 
-        let (mut pipelines, pipeline_layout) = build_pipelines::<B, T>(
-            factory,
-            subpass,
+        let triplanar = self.triplanar && supports_triplanar::<T>();
+        let settings = PipelineSettings {
             framebuffer_width,
             framebuffer_height,
-            &vertex_format_base,
-            &vertex_format_skinned,
-            self.skinning,
-            true,
+            viewport: self.view.viewport,
+            skinning: self.skinning,
+            morphing: self.morphing && supports_morphing::<T>(),
+            uv_transform: self.uv_transform && supports_uv_transform::<T>(),
+            triplanar,
+            surface_layer: self.surface_layer && supports_surface_layer::<T>(triplanar),
+            subsurface: false,
+            transparent: true,
+            front_face: Conventions::of(aux).rasterizer_front_face(),
+            line_width: self.line_width,
+        };
+        let (pipelines, pipeline_layout) = build_pipelines::<B, T>(
+            factory,
+            subpass,
+            settings,
+            self.material_shader.as_ref(),
             vec![
                 env.raw_layout(),
                 materials.raw_layout(),
                 skinning.raw_layout(),
+                morph.raw_layout(),
             ],
         )?;
 
@@ -466,8 +1380,7 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
         vertex_format_skinned.sort();
 
         Ok(Box::new(DrawBase3DTransparent::<B, T> {
-            pipeline_basic: pipelines.remove(0),
-            pipeline_skinned: pipelines.pop(),
+            pipelines,
             pipeline_layout,
             static_batches: Default::default(),
             skinned_batches: Default::default(),
@@ -476,8 +1389,13 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
             env,
             materials,
             skinning,
-            models: DynamicVertexBuffer::new(),
-            skinned_models: DynamicVertexBuffer::new(),
+            morph,
+            models: DynamicVertexBuffer::new().with_partial_updates(),
+            skinned_models: DynamicVertexBuffer::new().with_partial_updates(),
+            view_index: self.view.index,
+            view_mode: self.view_mode,
+            active: true,
+            empty: false,
             change: Default::default(),
             marker: PhantomData,
         }))
@@ -488,18 +1406,26 @@ impl<B: Backend, T: Base3DPassDef> RenderGroupDesc<B, World> for DrawBase3DTrans
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""))]
 pub struct DrawBase3DTransparent<B: Backend, T: Base3DPassDef> {
-    pipeline_basic: B::GraphicsPipeline,
-    pipeline_skinned: Option<B::GraphicsPipeline>,
+    pipelines: PipelineCache<B>,
     pipeline_layout: B::PipelineLayout,
-    static_batches: OrderedTwoLevelBatch<MaterialId, u32, VertexArgs>,
-    skinned_batches: OrderedTwoLevelBatch<MaterialId, u32, SkinnedVertexArgs>,
+    static_batches: OrderedTwoLevelBatch<(DepthMode, DepthBias, MaterialId), MeshPart, VertexArgs>,
+    skinned_batches: OrderedTwoLevelBatch<
+        (Deformation, DepthMode, DepthBias, MaterialId),
+        MeshPart,
+        SkinnedVertexArgs,
+    >,
     vertex_format_base: Vec<VertexFormat>,
     vertex_format_skinned: Vec<VertexFormat>,
     env: EnvironmentSub<B>,
-    materials: MaterialSub<B, FullTextureSet>,
+    materials: MaterialSub<B, T::TextureSet>,
     skinning: SkinningSub<B>,
+    morph: MorphSub<B>,
     models: DynamicVertexBuffer<B, VertexArgs>,
     skinned_models: DynamicVertexBuffer<B, SkinnedVertexArgs>,
+    view_index: Option<usize>,
+    view_mode: ViewMode,
+    active: bool,
+    empty: bool,
     change: util::ChangeDetection,
     marker: PhantomData<T>,
 }
@@ -510,21 +1436,59 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
         factory: &Factory<B>,
         _queue: QueueId,
         index: usize,
-        _subpass: hal::pass::Subpass<'_, B>,
+        subpass: hal::pass::Subpass<'_, B>,
         resources: &World,
     ) -> PrepareResult {
         profile_scope_impl!("prepare transparent");
 
-        let (mesh_storage, visibility, meshes, materials, transforms, joints, tints) =
-            <(
-                Read<'_, AssetStorage<Mesh>>,
-                ReadExpect<'_, Visibility>,
-                ReadStorage<'_, Handle<Mesh>>,
-                ReadStorage<'_, Handle<Material>>,
-                ReadStorage<'_, Transform>,
-                ReadStorage<'_, JointTransforms>,
-                ReadStorage<'_, Tint>,
-            )>::fetch(resources);
+        let active = active_view_mode(resources).draws(self.view_mode);
+        let toggled = active != self.active;
+        self.active = active;
+        if !active {
+            return self.change.prepare_result(index, toggled);
+        }
+
+        let (
+            mesh_storage,
+            visibility,
+            view_visibility,
+            meshes,
+            materials,
+            transforms,
+            joints,
+            blend_shapes,
+            tints,
+            instance_data,
+            depth_modes,
+            depth_biases,
+            sub_meshes,
+        ) = <(
+            Read<'_, AssetStorage<Mesh>>,
+            ReadExpect<'_, Visibility>,
+            Option<Read<'_, ViewVisibility>>,
+            ReadStorage<'_, Handle<Mesh>>,
+            ReadStorage<'_, Handle<Material>>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, JointTransforms>,
+            ReadStorage<'_, BlendShapes>,
+            ReadStorage<'_, Tint>,
+            ReadStorage<'_, InstanceData>,
+            ReadStorage<'_, DepthMode>,
+            ReadStorage<'_, DepthBias>,
+            ReadStorage<'_, SubMeshes>,
+        )>::fetch(resources);
+        let visibility = view_visibility_or(&visibility, &view_visibility, self.view_index);
+
+        // Skip the uploads of lights and instances when there is nothing to draw.
+        let empty = !visibility
+            .visible_ordered
+            .iter()
+            .any(|entity| meshes.contains(*entity));
+        let emptied = empty != self.empty;
+        self.empty = empty;
+        if empty {
+            return self.change.prepare_result(index, emptied || toggled);
+        }
 
         // Prepare environment
         self.env.process(factory, index, resources);
@@ -535,55 +1499,108 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
 
         let materials_ref = &mut self.materials;
         let skinning_ref = &mut self.skinning;
+        let morph_ref = &mut self.morph;
         let statics_ref = &mut self.static_batches;
         let skinned_ref = &mut self.skinned_batches;
         let mut changed = false;
 
-        let mut joined = ((&materials, &meshes, &transforms, tints.maybe()), !&joints).join();
-        visibility
-            .visible_ordered
-            .iter()
-            .filter_map(|e| joined.get_unchecked(e.id()))
-            .map(|((mat, mesh, tform, tint), _)| {
-                ((mat, mesh.id()), VertexArgs::from_object_data(tform, tint))
-            })
-            .for_each_group(|(mat, mesh_id), data| {
-                if mesh_storage.contains_id(mesh_id) {
-                    if let Some((mat, this_changed)) = materials_ref.insert(factory, resources, mat)
-                    {
-                        changed = changed || this_changed;
-                        statics_ref.insert(mat, mesh_id, data.drain(..));
-                    }
-                }
-            });
-
-        if self.pipeline_skinned.is_some() {
-            let mut joined = (&materials, &meshes, &transforms, tints.maybe(), &joints).join();
-
+        let mode = |depth: Option<&DepthMode>| depth.copied().unwrap_or(DepthMode::TestOnly);
+        let bias = |bias: Option<&DepthBias>| bias.copied().unwrap_or_default();
+        let pipelines = &self.pipelines;
+        let deforming = pipelines.deforming();
+
+        // Meshes drawn always on top keep their order, but after all other meshes.
+        for &on_top in &[false, true] {
+            let draws =
+                |depth: Option<&DepthMode>| (mode(depth) == DepthMode::AlwaysOnTop) == on_top;
+
+            let mut joined = (
+                (&meshes, &transforms, (tints.maybe(), instance_data.maybe())),
+                (materials.maybe(), sub_meshes.maybe()),
+                (depth_modes.maybe(), depth_biases.maybe()),
+                (!&joints, blend_shapes.maybe()),
+            )
+                .join();
             visibility
                 .visible_ordered
                 .iter()
-                .filter_map(|e| joined.get_unchecked(e.id()))
-                .map(|(mat, mesh, tform, tint, joints)| {
-                    (
-                        (mat, mesh.id()),
-                        SkinnedVertexArgs::from_object_data(
-                            tform,
-                            tint,
-                            skinning_ref.insert(joints),
-                        ),
-                    )
+                .filter_map(|e| {
+                    joined
+                        .get_unchecked(e.id())
+                        .map(|d| (visibility.fade(*e), d))
                 })
-                .for_each_group(|(mat, mesh_id), data| {
-                    if mesh_storage.contains_id(mesh_id) {
+                .filter(|(_, (_, _, (depth, _), (_, shapes)))| {
+                    draws(*depth) && pipelines.deformation(false, shapes.is_some()).is_none()
+                })
+                .flat_map(
+                    |(fade, ((mesh, tform, tint_data), (mat, subs), (depth, bias_of), _))| {
+                        let mut args = VertexArgs::from_object_data(tform, tint_data.0)
+                            .with_instance_data(tint_data.1);
+                        args.tint = apply_fade(args.tint, fade);
+                        mesh_parts(mesh.id(), mat, subs)
+                            .map(move |(mat, part)| ((mode(depth), bias(bias_of), mat, part), args))
+                    },
+                )
+                .for_each_group(|(mode, bias, mat, part), data| {
+                    if mesh_storage.contains_id(part.mesh) {
                         if let Some((mat, this_changed)) =
                             materials_ref.insert(factory, resources, mat)
                         {
                             changed = changed || this_changed;
-                            skinned_ref.insert(mat, mesh_id, data.drain(..));
+                            statics_ref.insert((mode, bias, mat), part, data.drain(..));
                         }
                     }
                 });
+
+            if deforming {
+                let mut joined = (
+                    (&meshes, &transforms, (tints.maybe(), instance_data.maybe())),
+                    (materials.maybe(), sub_meshes.maybe()),
+                    (depth_modes.maybe(), depth_biases.maybe()),
+                    (joints.maybe(), blend_shapes.maybe()),
+                )
+                    .join();
+
+                visibility
+                    .visible_ordered
+                    .iter()
+                    .filter_map(|e| {
+                        let (object, material, depth, (joints, shapes)) =
+                            joined.get_unchecked(e.id())?;
+                        let deformation =
+                            pipelines.deformation(joints.is_some(), shapes.is_some())?;
+                        let deform = (deformation, joints, shapes);
+                        Some((visibility.fade(*e), (object, material, depth, deform)))
+                    })
+                    .filter(|(_, (_, _, (depth, _), _))| draws(*depth))
+                    .flat_map(
+                        |(
+                            fade,
+                            ((mesh, tform, tint_data), (mat, subs), (depth, bias_of), deform),
+                        )| {
+                            let (deformation, mut args) =
+                                deformed_args(skinning_ref, morph_ref, tform, tint_data, deform);
+                            args.tint = apply_fade(args.tint, fade);
+                            mesh_parts(mesh.id(), mat, subs).map(move |(mat, part)| {
+                                ((deformation, mode(depth), bias(bias_of), mat, part), args)
+                            })
+                        },
+                    )
+                    .for_each_group(|(deformation, mode, bias, mat, part), data| {
+                        if mesh_storage.contains_id(part.mesh) {
+                            if let Some((mat, this_changed)) =
+                                materials_ref.insert(factory, resources, mat)
+                            {
+                                changed = changed || this_changed;
+                                skinned_ref.insert(
+                                    (deformation, mode, bias, mat),
+                                    part,
+                                    data.drain(..),
+                                );
+                            }
+                        }
+                    });
+            }
         }
 
         self.models.write(
@@ -601,11 +1618,33 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
         );
 
         self.skinning.commit(factory, index);
+        self.morph.commit(factory, index);
 
         changed = changed || self.static_batches.changed();
         changed = changed || self.skinned_batches.changed();
 
-        self.change.prepare_result(index, changed)
+        let materials = &self.materials;
+        let static_keys = self
+            .static_batches
+            .iter()
+            .map(|(&(_, bias, mat), parts)| ((bias, materials.discards(mat)), parts.len()));
+        let skinned_keys = self
+            .skinned_batches
+            .iter()
+            .map(|(&(_, _, bias, mat), parts)| ((bias, materials.discards(mat)), parts.len()));
+        let keys = static_keys.chain(skinned_keys).collect::<Vec<_>>();
+        self.pipelines.prepare::<T>(
+            factory,
+            subpass,
+            &self.pipeline_layout,
+            keys.iter().map(|&(key, _)| key),
+        );
+        if let Some(mut stats) = resources.try_fetch_mut::<RenderStats>() {
+            stats.discard_draws += self.pipelines.discard_draws(keys);
+        }
+
+        self.change
+            .prepare_result(index, changed || toggled || emptied)
     }
 
     fn draw_inline(
@@ -617,27 +1656,43 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     ) {
         profile_scope_impl!("draw transparent");
 
+        if !self.active || self.empty {
+            return;
+        }
+        let start = Instant::now();
+        let mut draws = 0;
+
         let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(resources);
         let layout = &self.pipeline_layout;
         let encoder = &mut encoder;
 
         let models_loc = self.vertex_format_base.len() as u32;
-        let skin_models_loc = self.vertex_format_skinned.len() as u32;
 
-        encoder.bind_graphics_pipeline(&self.pipeline_basic);
+        let default = (DepthMode::TestOnly, DepthBias::default(), true);
+        encoder.bind_graphics_pipeline(&self.pipelines.get(default.0, default.1, default.2).basic);
         self.env.bind(index, layout, 0, encoder);
 
         if self.models.bind(index, models_loc, 0, encoder) {
-            for (&mat, batches) in self.static_batches.iter() {
+            let mut bound = default;
+            let mut bound_mesh = None;
+            for (&(mode, bias, mat), batches) in self.static_batches.iter() {
                 if self.materials.loaded(mat) {
+                    let pipeline = (mode, bias, self.materials.discards(mat));
+                    if pipeline != bound {
+                        encoder.bind_graphics_pipeline(
+                            &self.pipelines.get(mode, bias, pipeline.2).basic,
+                        );
+                        bound = pipeline;
+                    }
                     self.materials.bind(layout, 1, mat, encoder);
-                    for (mesh, range) in batches {
-                        debug_assert!(mesh_storage.contains_id(*mesh));
+                    for (part, range) in batches {
+                        debug_assert!(mesh_storage.contains_id(part.mesh));
                         if let Some(mesh) =
-                            B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh) })
+                            B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(part.mesh) })
                         {
-                            if let Err(error) = mesh.bind_and_draw(
-                                0,
+                            if let Err(error) = part.draw(
+                                mesh,
+                                &mut bound_mesh,
                                 &self.vertex_format_base,
                                 range.clone(),
                                 encoder,
@@ -648,6 +1703,8 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
                                     T::NAME,
                                     T::base_format(),
                                 );
+                            } else {
+                                draws += 1;
                             }
                         }
                     }
@@ -655,48 +1712,65 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
             }
         }
 
-        if let Some(pipeline_skinned) = self.pipeline_skinned.as_ref() {
-            encoder.bind_graphics_pipeline(pipeline_skinned);
-
-            if self.skinned_models.bind(index, skin_models_loc, 0, encoder) {
-                self.skinning.bind(index, layout, 2, encoder);
-                for (&mat, batches) in self.skinned_batches.iter() {
-                    if self.materials.loaded(mat) {
-                        self.materials.bind(layout, 1, mat, encoder);
-                        for (mesh, range) in batches {
-                            debug_assert!(mesh_storage.contains_id(*mesh));
-                            if let Some(mesh) =
-                                B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh) })
+        if self.pipelines.deforming() {
+            self.skinning.bind(index, layout, 2, encoder);
+            self.morph.bind(index, layout, 3, encoder);
+            let mut bound = None;
+            let mut bound_deformation = None;
+            let mut bound_mesh = None;
+            for (&(deformation, mode, bias, mat), batches) in self.skinned_batches.iter() {
+                let format = deformed_format(
+                    deformation,
+                    &self.vertex_format_base,
+                    &self.vertex_format_skinned,
+                );
+                if bound_deformation != Some(deformation) {
+                    // Instance data follows the mesh attributes, whose count differs.
+                    if !self
+                        .skinned_models
+                        .bind(index, format.len() as u32, 0, encoder)
+                    {
+                        break;
+                    }
+                    bound_deformation = Some(deformation);
+                    bound_mesh = None;
+                }
+                if self.materials.loaded(mat) {
+                    let discards = self.materials.discards(mat);
+                    if bound != Some((deformation, mode, bias, discards)) {
+                        let pipelines = self.pipelines.get(mode, bias, discards);
+                        encoder.bind_graphics_pipeline(pipelines.deformed(deformation));
+                        bound = Some((deformation, mode, bias, discards));
+                    }
+                    self.materials.bind(layout, 1, mat, encoder);
+                    for (part, range) in batches {
+                        debug_assert!(mesh_storage.contains_id(part.mesh));
+                        if let Some(mesh) =
+                            B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(part.mesh) })
+                        {
+                            if let Err(error) =
+                                part.draw(mesh, &mut bound_mesh, format, range.clone(), encoder)
                             {
-                                if let Err(error) = mesh.bind_and_draw(
-                                    0,
-                                    &self.vertex_format_skinned,
-                                    range.clone(),
-                                    encoder,
-                                ) {
-                                    log::warn!(
-                                        "Trying to draw a skinned mesh that lacks {:?} vertex attributes. Pass {} requires attributes {:?}.",
-                                        error.not_found.attributes,
-                                        T::NAME,
-                                        T::skinned_format(),
-                                    );
-                                }
+                                log::warn!(
+                                    "Trying to draw a deformed mesh that lacks {:?} vertex attributes. Pass {} requires attributes {:?}.",
+                                    error.not_found.attributes,
+                                    T::NAME,
+                                    format,
+                                );
+                            } else {
+                                draws += 1;
                             }
                         }
                     }
                 }
             }
         }
+        RenderStats::add_draws(resources, draws, start.elapsed());
     }
 
-    fn dispose(mut self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
         unsafe {
-            factory
-                .device()
-                .destroy_graphics_pipeline(self.pipeline_basic);
-            if let Some(pipeline) = self.pipeline_skinned.take() {
-                factory.device().destroy_graphics_pipeline(pipeline);
-            }
+            self.pipelines.dispose(factory);
             factory
                 .device()
                 .destroy_pipeline_layout(self.pipeline_layout);
@@ -704,105 +1778,434 @@ impl<B: Backend, T: Base3DPassDef> RenderGroup<B, World> for DrawBase3DTranspare
     }
 }
 
+/// Access of the passes to the shadow map, when drawn with shadows.
+fn shadow_map_access(shadows: bool) -> Vec<ImageAccess> {
+    if shadows {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    } else {
+        Vec::new()
+    }
+}
+
 fn build_pipelines<B: Backend, T: Base3DPassDef>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
-    framebuffer_width: u32,
-    framebuffer_height: u32,
-    vertex_format_base: &[VertexFormat],
-    vertex_format_skinned: &[VertexFormat],
-    skinning: bool,
-    transparent: bool,
+    settings: PipelineSettings,
+    material_shader: Option<&MaterialShader>,
     layouts: Vec<&B::DescriptorSetLayout>,
-) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+) -> Result<(PipelineCache<B>, B::PipelineLayout), failure::Error> {
+    let fragment = material_shader
+        .map(|shader| material_fragment_shader::<T>(shader, &settings))
+        .transpose()?;
     let pipeline_layout = unsafe {
         factory
             .device()
             .create_pipeline_layout(layouts, None as Option<(_, _)>)
     }?;
 
-    let vertex_desc = vertex_format_base
-        .iter()
-        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+    match build_variant::<B, T>(
+        factory,
+        subpass,
+        &pipeline_layout,
+        &settings,
+        fragment.as_ref(),
+        DepthBias::default(),
+    ) {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(e)
+        }
+        Ok(variant) => {
+            let mut variants = FnvHashMap::default();
+            variants.insert((DepthBias::default(), true), variant);
+            let cache = PipelineCache {
+                variants,
+                failed: FnvHashSet::default(),
+                settings,
+                fragment,
+            };
+            Ok((cache, pipeline_layout))
+        }
+    }
+}
+
+/// Depth bias added to the one of entities by overlay passes, see `Base3DPassDef::overlay`.
+const OVERLAY_DEPTH_BIAS: i32 = 2;
+
+/// Build the pipelines of every `DepthMode` with given depth bias, and given fragment shader
+/// instead of the one of the settings.
+fn build_variant<B: Backend, T: Base3DPassDef>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    pipeline_layout: &B::PipelineLayout,
+    settings: &PipelineSettings,
+    fragment: Option<&SpirvShader>,
+    bias: DepthBias,
+) -> Result<Vec<DepthPipelines<B>>, failure::Error> {
+    let vertex_desc = T::base_format()
+        .into_iter()
+        .map(|f| (f, pso::VertexInputRate::Vertex))
         .chain(Some((
             VertexArgs::vertex(),
             pso::VertexInputRate::Instance(1),
         )))
         .collect::<Vec<_>>();
 
-    let shader_vertex_basic = unsafe { T::vertex_shader().module(factory).unwrap() };
-    let shader_fragment = unsafe { T::fragment_shader().module(factory).unwrap() };
+    let (width, height) = (settings.framebuffer_width, settings.framebuffer_height);
+    let fragment = match (fragment, settings.triplanar, settings.surface_layer) {
+        (Some(fragment), _, _) => fragment,
+        _ if settings.subsurface => T::fragment_subsurface_shader().unwrap(),
+        (None, triplanar, true) => T::fragment_surface_layer_shader(triplanar).unwrap(),
+        (None, true, false) => T::fragment_triplanar_shader().unwrap(),
+        (None, false, false) => T::fragment_shader(),
+    };
+    let deformations = settings.deformations();
+    // The basic vertex shader and the fragment shader, followed by the vertex shader of every
+    // deformation.
+    let shaders = [vertex_shader::<T>(settings, None), fragment]
+        .iter()
+        .copied()
+        .chain(
+            deformations
+                .iter()
+                .map(|&deformation| vertex_shader::<T>(settings, Some(deformation))),
+        )
+        .collect::<Vec<_>>();
+    let mut shader_modules = unsafe { util::shader_module_vec(factory, T::NAME, &shaders)? };
+    let deformed_modules = shader_modules.split_off(2);
+    let shader_fragment = shader_modules.pop().unwrap();
+    let shader_vertex_basic = shader_modules.pop().unwrap();
     let pipe_desc = PipelineDescBuilder::new()
         .with_vertex_desc(&vertex_desc)
         .with_shaders(util::simple_shader_set(
             &shader_vertex_basic,
             Some(&shader_fragment),
         ))
-        .with_layout(&pipeline_layout)
+        .with_layout(pipeline_layout)
         .with_subpass(subpass)
-        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_framebuffer_size(width, height)
+        .with_viewport(settings.viewport.rect(width, height))
         .with_face_culling(pso::Face::BACK)
-        .with_depth_test(pso::DepthTest {
-            fun: pso::Comparison::Greater,
-            write: !transparent,
+        .with_front_face(settings.front_face)
+        .with_polygon_mode(match T::polygon_mode() {
+            pso::PolygonMode::Line(_) => {
+                pso::PolygonMode::Line(pso::State::Static(settings.line_width))
+            }
+            mode => mode,
+        })
+        .with_depth_bias(if T::overlay() {
+            DepthBias(bias.0 + OVERLAY_DEPTH_BIAS).state()
+        } else {
+            bias.state()
         })
         .with_blend_targets(vec![pso::ColorBlendDesc {
             mask: pso::ColorMask::ALL,
-            blend: if transparent {
+            blend: if T::additive() {
+                Some(pso::BlendState {
+                    color: pso::BlendOp::ADD,
+                    alpha: pso::BlendOp::Add {
+                        src: pso::Factor::Zero,
+                        dst: pso::Factor::One,
+                    },
+                })
+            } else if settings.transparent {
                 Some(pso::BlendState::PREMULTIPLIED_ALPHA)
             } else {
                 None
             },
         }]);
+    let depth_test = |mode: &DepthMode| {
+        if T::additive() {
+            pso::DepthTest {
+                fun: pso::Comparison::Always,
+                write: false,
+            }
+        } else if T::overlay() {
+            pso::DepthTest {
+                fun: match mode.depth_test().fun {
+                    pso::Comparison::Greater => pso::Comparison::GreaterEqual,
+                    fun => fun,
+                },
+                write: false,
+            }
+        } else {
+            mode.depth_test()
+        }
+    };
 
-    let pipelines = if skinning {
-        let shader_vertex_skinned = unsafe { T::vertex_skinned_shader().module(factory).unwrap() };
+    // One pipeline per depth mode, all derived from the first one. Pipelines of every
+    // deformation follow after the basic ones, in the order of `PipelineSettings::deformations`.
+    let mut builder = PipelinesBuilder::new();
+    for (i, mode) in DepthMode::ALL.iter().enumerate() {
+        let desc = pipe_desc.clone().with_depth_test(depth_test(mode));
+        builder = if i == 0 {
+            builder.with_pipeline(desc)
+        } else {
+            builder.with_child_pipeline(0, desc)
+        };
+    }
 
-        let vertex_desc = vertex_format_skinned
-            .iter()
-            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
-            .chain(Some((
-                SkinnedVertexArgs::vertex(),
-                pso::VertexInputRate::Instance(1),
-            )))
-            .collect::<Vec<_>>();
-
-        let pipe = PipelinesBuilder::new()
-            .with_pipeline(pipe_desc.clone())
-            .with_child_pipeline(
+    let deformed_shaders = deformed_modules
+        .into_iter()
+        .zip(&deformations)
+        .map(|(shader_vertex_deformed, &deformation)| {
+            let format = match deformation {
+                Deformation::Morphed => T::base_format(),
+                Deformation::Skinned | Deformation::SkinnedMorphed => T::skinned_format(),
+            };
+            let vertex_desc = format
+                .into_iter()
+                .map(|f| (f, pso::VertexInputRate::Vertex))
+                .chain(Some((
+                    SkinnedVertexArgs::vertex(),
+                    pso::VertexInputRate::Instance(1),
+                )))
+                .collect::<Vec<_>>();
+            (shader_vertex_deformed, vertex_desc)
+        })
+        .collect::<Vec<_>>();
+
+    for (shader_vertex_deformed, vertex_desc) in &deformed_shaders {
+        for mode in DepthMode::ALL.iter() {
+            builder = builder.with_child_pipeline(
                 0,
                 pipe_desc
-                    .with_vertex_desc(&vertex_desc)
+                    .clone()
+                    .with_depth_test(depth_test(mode))
+                    .with_vertex_desc(vertex_desc)
                     .with_shaders(util::simple_shader_set(
-                        &shader_vertex_skinned,
+                        shader_vertex_deformed,
                         Some(&shader_fragment),
                     )),
-            )
-            .build(factory, None);
-
-        unsafe {
-            factory.destroy_shader_module(shader_vertex_skinned);
+            );
         }
-
-        pipe
-    } else {
-        PipelinesBuilder::new()
-            .with_pipeline(pipe_desc)
-            .build(factory, None)
-    };
+    }
+    let pipelines = builder.build(factory, None);
 
     unsafe {
+        for (shader_vertex_deformed, _) in deformed_shaders {
+            factory.destroy_shader_module(shader_vertex_deformed);
+        }
         factory.destroy_shader_module(shader_vertex_basic);
         factory.destroy_shader_module(shader_fragment);
     }
 
-    match pipelines {
-        Err(e) => {
-            unsafe {
-                factory.device().destroy_pipeline_layout(pipeline_layout);
-            }
-            Err(e)
+    let mut pipelines = pipelines
+        .map_err(|e| PassError::pipelines(T::NAME, e))?
+        .into_iter();
+    let mut variant = pipelines
+        .by_ref()
+        .take(DepthMode::ALL.len())
+        .map(|basic| DepthPipelines {
+            basic,
+            skinned: None,
+            morphed: None,
+            skinned_morphed: None,
+        })
+        .collect::<Vec<_>>();
+    for deformation in deformations {
+        for (depth_pipelines, pipeline) in variant.iter_mut().zip(pipelines.by_ref()) {
+            *depth_pipelines.deformed_mut(deformation) = Some(pipeline);
+        }
+    }
+    Ok(variant)
+}
+
+/// Fragment shader of the pass with the material shader injected, for the variant drawn with
+/// given settings.
+fn material_fragment_shader<T: Base3DPassDef>(
+    shader: &MaterialShader,
+    settings: &PipelineSettings,
+) -> Result<SpirvShader, failure::Error> {
+    let (name, source) = T::fragment_source()
+        .ok_or_else(|| failure::format_err!("Pass {} doesn't accept material shaders", T::NAME))?;
+    let defines = [
+        (settings.subsurface, "SUBSURFACE"),
+        (settings.triplanar, "TRIPLANAR"),
+        (settings.surface_layer, "SURFACE_LAYER"),
+    ];
+    let defines = defines
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|&(_, define)| define)
+        .collect::<Vec<_>>();
+    shader
+        .compile(name, source, &defines)
+        .map_err(|e| failure::format_err!("Pass {}: {}", T::NAME, e))
+}
+
+/// Fragment shader of the pass without discard, for the variant drawn with given settings.
+fn early_depth_fragment_shader<T: Base3DPassDef>(
+    settings: &PipelineSettings,
+) -> Option<&'static SpirvShader> {
+    T::fragment_early_depth_shader(
+        settings.subsurface,
+        settings.triplanar,
+        settings.surface_layer,
+    )
+}
+
+/// Vertex shader of the meshes of a deformation, or of undeformed meshes.
+fn vertex_shader<T: Base3DPassDef>(
+    settings: &PipelineSettings,
+    deformation: Option<Deformation>,
+) -> &'static SpirvShader {
+    let (skinned, morphed) = match deformation {
+        None => (false, false),
+        Some(Deformation::Skinned) => (true, false),
+        Some(Deformation::Morphed) => (false, true),
+        Some(Deformation::SkinnedMorphed) => (true, true),
+    };
+    if settings.uv_transform {
+        return T::vertex_uv_transform_shader(skinned, morphed).unwrap();
+    }
+    match (skinned, morphed) {
+        (false, false) => T::vertex_shader(),
+        (true, false) => T::vertex_skinned_shader(),
+        (false, true) => T::vertex_morphed_shader().unwrap(),
+        (true, true) => T::vertex_skinned_morphed_shader().unwrap(),
+    }
+}
+
+/// Whether the pass has the vertex shaders of every deformation transforming texture
+/// coordinates, warning when it hasn't.
+fn supports_uv_transform<T: Base3DPassDef>() -> bool {
+    let supported = [(false, false), (true, false), (false, true), (true, true)]
+        .iter()
+        .all(|&(skinned, morphed)| T::vertex_uv_transform_shader(skinned, morphed).is_some());
+    if !supported {
+        log::warn!(
+            "Pass {} has no UV transform vertex shaders, `UvTransform`s are ignored.",
+            T::NAME
+        );
+    }
+    supported
+}
+
+/// Whether the pass has a triplanar fragment shader, warning when it hasn't.
+fn supports_triplanar<T: Base3DPassDef>() -> bool {
+    let supported = T::fragment_triplanar_shader().is_some();
+    if !supported {
+        log::warn!(
+            "Pass {} has no triplanar fragment shader, materials are sampled with their \
+             texture coordinates.",
+            T::NAME
+        );
+    }
+    supported
+}
+
+/// Whether the pass has a surface layer fragment shader with given triplanar mapping, warning
+/// when it hasn't.
+fn supports_surface_layer<T: Base3DPassDef>(triplanar: bool) -> bool {
+    let supported = T::fragment_surface_layer_shader(triplanar).is_some();
+    if !supported {
+        log::warn!(
+            "Pass {} has no surface layer fragment shader, surface layers aren't drawn.",
+            T::NAME
+        );
+    }
+    supported
+}
+
+/// Whether the pass has a subsurface fragment shader, warning when it hasn't.
+fn supports_subsurface<T: Base3DPassDef>() -> bool {
+    let supported = T::fragment_subsurface_shader().is_some();
+    if !supported {
+        log::warn!(
+            "Pass {} has no subsurface fragment shader, materials are shaded instead.",
+            T::NAME
+        );
+    }
+    supported
+}
+
+/// Whether the pass has the vertex shaders of morphed meshes, warning when it hasn't.
+fn supports_morphing<T: Base3DPassDef>() -> bool {
+    let supported =
+        T::vertex_morphed_shader().is_some() && T::vertex_skinned_morphed_shader().is_some();
+    if !supported {
+        log::warn!(
+            "Pass {} has no morphed vertex shaders, `BlendShapes` are drawn undeformed.",
+            T::NAME
+        );
+    }
+    supported
+}
+
+/// Vertex formats of the meshes of a deformation. Meshes morphed without a skin have no joints.
+fn deformed_format<'a>(
+    deformation: Deformation,
+    base: &'a [VertexFormat],
+    skinned: &'a [VertexFormat],
+) -> &'a [VertexFormat] {
+    match deformation {
+        Deformation::Morphed => base,
+        Deformation::Skinned | Deformation::SkinnedMorphed => skinned,
+    }
+}
+
+/// Instance arguments of a deformed mesh, inserting its joints and blend shapes for submission.
+fn deformed_args<B: Backend>(
+    skinning: &mut SkinningSub<B>,
+    morph: &mut MorphSub<B>,
+    transform: &Transform,
+    (tint, data): (Option<&Tint>, Option<&InstanceData>),
+    (deformation, joints, shapes): (Deformation, Option<&JointTransforms>, Option<&BlendShapes>),
+) -> (Deformation, SkinnedVertexArgs) {
+    let joints_offset = match (deformation, joints) {
+        (Deformation::Skinned, Some(joints)) | (Deformation::SkinnedMorphed, Some(joints)) => {
+            skinning.insert(joints)
+        }
+        _ => 0,
+    };
+    let morph_offsets = match (deformation, shapes) {
+        (Deformation::Morphed, Some(shapes)) | (Deformation::SkinnedMorphed, Some(shapes)) => {
+            morph.insert(shapes)
+        }
+        _ => [0; 3],
+    };
+    let args = SkinnedVertexArgs::from_object_data(transform, tint, joints_offset)
+        .with_instance_data(data)
+        .with_morph_offsets(morph_offsets);
+    (deformation, args)
+}
+
+/// Camera, viewport and visibility of the `View` a 3D pass draws, if any.
+#[derive(Clone, Debug, Default)]
+struct ViewBinding {
+    index: Option<usize>,
+    camera: Option<Entity>,
+    viewport: Viewport,
+}
+
+impl ViewBinding {
+    fn new(index: usize, view: &View) -> Self {
+        Self {
+            index: Some(index),
+            camera: Some(view.camera),
+            viewport: view.viewport,
         }
-        Ok(pipelines) => Ok((pipelines, pipeline_layout)),
     }
 }
+
+/// Visibility of the view at `index`, or the `ActiveCamera` one when not drawing a view or the
+/// view hasn't been culled yet.
+fn view_visibility_or<'a>(
+    visibility: &'a Visibility,
+    view_visibility: &'a Option<Read<'_, ViewVisibility>>,
+    index: Option<usize>,
+) -> &'a Visibility {
+    index
+        .and_then(|index| view_visibility.as_ref()?.0.get(&index))
+        .unwrap_or(visibility)
+}
+
+/// Scale the alpha of an instance tint by a `Visibility` fade factor.
+fn apply_fade(mut tint: glsl_layout::vec4, fade: f32) -> glsl_layout::vec4 {
+    let tint_ref: &mut [f32; 4] = tint.as_mut();
+    tint_ref[3] *= fade;
+    tint
+}
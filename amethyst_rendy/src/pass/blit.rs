@@ -0,0 +1,177 @@
+use super::FullscreenPipeline;
+use crate::{
+    submodules::{sampled_image_access, GraphImageSub},
+    types::Backend,
+    view::Viewport,
+};
+use amethyst_core::ecs::World;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the image bound as the only image of the group stretched over a viewport of the target,
+/// e.g. the color of an offscreen target drawn as a minimap or a security camera screen.
+///
+/// The image is sampled with linear filtering and replaces the colors of the viewport, unless
+/// the group is created `with_blending`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawBlitDesc {
+    viewport: Viewport,
+    filter: Filter,
+    blending: bool,
+    depth: bool,
+}
+
+impl Default for DrawBlitDesc {
+    fn default() -> Self {
+        Self {
+            viewport: Viewport::FULL,
+            filter: Filter::Linear,
+            blending: false,
+            depth: true,
+        }
+    }
+}
+
+impl DrawBlitDesc {
+    /// Create instance of `DrawBlit` render group covering the whole target.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Draw the image over given viewport of the target.
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Sample the image with given filter, `Filter::Linear` by default.
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Blend the image over the target with its alpha if true is passed.
+    pub fn with_blending(mut self, blending: bool) -> Self {
+        self.blending = blending;
+        self
+    }
+
+    /// Set whether the target this group is added to has a depth output.
+    pub fn with_target_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawBlitDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let source = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            self.filter,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let (pipeline, pipeline_layout) =
+            FullscreenPipeline::new("DrawBlit", &super::BLIT_FRAGMENT)
+                .with_viewport(self.viewport)
+                .with_blend(if self.blending {
+                    Some(pso::BlendState::ALPHA)
+                } else {
+                    None
+                })
+                .build(
+                    factory,
+                    subpass,
+                    framebuffer_width,
+                    framebuffer_height,
+                    vec![source.raw_layout()],
+                )?;
+
+        Ok(Box::new(DrawBlit::<B> {
+            pipeline,
+            pipeline_layout,
+            source,
+        }))
+    }
+}
+
+/// Draws an image of another target.
+#[derive(Debug)]
+pub struct DrawBlit<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    source: GraphImageSub<B>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawBlit<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) -> PrepareResult {
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.source.bind(&self.pipeline_layout, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    blob_shadow::{BlobShadow, BlobShadowGround},
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::BlobShadowArgs,
+    submodules::{gather::ShadowGatherer, DynamicVertexBuffer, FlatEnvironmentSub},
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::AsVertex,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the blob shadows of entities with a [BlobShadow] component on the ground.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawBlobShadowsDesc;
+
+impl DrawBlobShadowsDesc {
+    /// Create instance of `DrawBlobShadows` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawBlobShadowsDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let vertex = DynamicVertexBuffer::new();
+
+        let (pipeline, pipeline_layout) = build_blob_shadow_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![env.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawBlobShadows::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            vertex,
+            shadows: Vec::new(),
+        }))
+    }
+}
+
+/// Draws blob shadows, multiplied over the opaque scene.
+#[derive(Debug)]
+pub struct DrawBlobShadows<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: FlatEnvironmentSub<B>,
+    vertex: DynamicVertexBuffer<B, BlobShadowArgs>,
+    shadows: Vec<BlobShadowArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawBlobShadows<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (shadows, transforms, ground) = <(
+            ReadStorage<'_, BlobShadow>,
+            ReadStorage<'_, Transform>,
+            Option<Read<'_, BlobShadowGround>>,
+        )>::fetch(world);
+        let ground = ground.map_or(0.0, |g| g.0);
+
+        self.env.process(factory, index, world);
+
+        self.shadows.clear();
+        // Shadow maps replace blob shadows while a light casts shadows.
+        let shadow_map = ShadowGatherer::gather(world, 1).is_some();
+        self.shadows.extend(
+            (&shadows, &transforms)
+                .join()
+                .filter(|_| !shadow_map)
+                .filter_map(|(shadow, transform)| {
+                    BlobShadowArgs::from_object_data(shadow, transform, ground)
+                }),
+        );
+
+        self.vertex.write(
+            factory,
+            index,
+            self.shadows.len() as u64,
+            Some(self.shadows.as_slice()),
+        );
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if self.shadows.is_empty() {
+            return;
+        }
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..4, 0..self.shadows.len() as u32);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_blob_shadow_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawBlobShadows",
+            [&super::BLOB_SHADOW_VERTEX, &super::BLOB_SHADOW_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(BlobShadowArgs::vertex(), pso::VertexInputRate::Instance(1))])
+                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                // Multiply the target color, keeping its alpha.
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState {
+                        color: pso::BlendOp::Add {
+                            src: pso::Factor::DstColor,
+                            dst: pso::Factor::Zero,
+                        },
+                        alpha: pso::BlendOp::Add {
+                            src: pso::Factor::Zero,
+                            dst: pso::Factor::One,
+                        },
+                    }),
+                }])
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::GreaterEqual,
+                    write: false,
+                }),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawBlobShadows", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
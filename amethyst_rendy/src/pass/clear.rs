@@ -0,0 +1,207 @@
+use crate::{
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    submodules::DynamicUniform,
+    types::Backend,
+    util,
+    view::Viewport,
+};
+use amethyst_core::ecs::World;
+use glsl_layout::{vec4, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct ClearUniform {
+    color: vec4,
+}
+
+/// Clear a viewport of the target to a color and its depth to the far plane.
+///
+/// Attachments can only be cleared as a whole when the render pass begins, this group is used
+/// by views sharing a target to clear only their own region. The target must have a depth output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawViewClearDesc {
+    color: [f32; 4],
+    viewport: Viewport,
+}
+
+impl DrawViewClearDesc {
+    /// Create instance of `DrawViewClear` render group clearing given viewport to a linear
+    /// RGBA color.
+    pub fn new(color: [f32; 4], viewport: Viewport) -> Self {
+        Self { color, viewport }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawViewClearDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let (pipeline, pipeline_layout) = build_clear_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.viewport,
+            vec![args.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawViewClear::<B> {
+            pipeline,
+            pipeline_layout,
+            args,
+            color: self.color,
+        }))
+    }
+}
+
+/// Clears a viewport of the target.
+#[derive(Debug)]
+pub struct DrawViewClear<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    args: DynamicUniform<B, ClearUniform>,
+    color: [f32; 4],
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawViewClear<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) -> PrepareResult {
+        let uniform = ClearUniform {
+            color: self.color.into(),
+        }
+        .std140();
+
+        if self.args.write(factory, index, uniform) {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.args
+            .bind(index, &self.pipeline_layout, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_clear_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    viewport: Viewport,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawViewClear",
+            [&super::FULLSCREEN_VERTEX, &super::CLEAR_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_viewport(viewport.rect(framebuffer_width, framebuffer_height))
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Always,
+                    write: true,
+                })
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawViewClear", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
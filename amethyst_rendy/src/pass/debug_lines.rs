@@ -1,14 +1,24 @@
 use crate::{
-    debug_drawing::{DebugLine, DebugLines, DebugLinesComponent, DebugLinesParams},
+    camera::FrozenCamera,
+    debug_drawing::{
+        DebugLine, DebugLines, DebugLinesComponent, DebugLinesParams, DebugShapeAnchor,
+        DebugShapesComponent,
+    },
+    error::PassError,
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::ViewArgs,
     submodules::{gather::CameraGatherer, DynamicUniform, DynamicVertexBuffer},
     types::Backend,
     util,
 };
-use amethyst_core::ecs::{Join, Read, SystemData, World, Write, WriteStorage};
+use amethyst_core::{
+    ecs::{Entities, Join, Read, ReadStorage, SystemData, World, Write, WriteStorage},
+    math::{convert, Matrix4},
+    transform::Transform,
+};
 use derivative::Derivative;
 use glsl_layout::*;
+use palette::Srgba;
 use rendy::{
     command::{QueueId, RenderPassEncoder},
     factory::Factory,
@@ -18,7 +28,6 @@ use rendy::{
     },
     hal::{self, device::Device, pso},
     mesh::AsVertex,
-    shader::Shader,
 };
 
 #[cfg(feature = "profiler")]
@@ -61,7 +70,7 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
         let args = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
         let vertex = DynamicVertexBuffer::new();
 
-        let (pipeline, pipeline_layout) = build_lines_pipeline(
+        let (mut pipelines, pipeline_layout) = build_lines_pipelines(
             factory,
             subpass,
             framebuffer_width,
@@ -70,7 +79,8 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
         )?;
 
         Ok(Box::new(DrawDebugLines::<B> {
-            pipeline,
+            pipeline_overlay: pipelines.pop().unwrap(),
+            pipeline: pipelines.pop().unwrap(),
             pipeline_layout,
             env,
             args,
@@ -78,15 +88,20 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawDebugLinesDesc {
             framebuffer_width: framebuffer_width as f32,
             framebuffer_height: framebuffer_height as f32,
             lines: Vec::new(),
+            overlay_lines: Vec::new(),
+            depth_tested: 0,
             change: Default::default(),
         }))
     }
 }
 
 /// Draws debug lines
+///
+/// Lines of `DebugShapesComponent` shapes without depth test are drawn last, over the scene.
 #[derive(Debug)]
 pub struct DrawDebugLines<B: Backend> {
     pipeline: B::GraphicsPipeline,
+    pipeline_overlay: B::GraphicsPipeline,
     pipeline_layout: B::PipelineLayout,
     env: DynamicUniform<B, ViewArgs>,
     args: DynamicUniform<B, DebugLinesArgs>,
@@ -94,6 +109,8 @@ pub struct DrawDebugLines<B: Backend> {
     framebuffer_width: f32,
     framebuffer_height: f32,
     lines: Vec<DebugLine>,
+    overlay_lines: Vec<DebugLine>,
+    depth_tested: usize,
     change: util::ChangeDetection,
 }
 
@@ -125,6 +142,50 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
             self.lines.extend(lines_res.drain());
         };
 
+        if let Some((camera, transform)) = <Option<Read<'_, FrozenCamera>>>::fetch(resources)
+            .as_ref()
+            .and_then(|f| f.snapshot())
+        {
+            let mut frustum = DebugLinesComponent::with_capacity(12);
+            frustum.add_frustum(
+                &convert::<_, Matrix4<f32>>(camera.matrix * transform.global_view_matrix()),
+                Srgba::new(1.0, 0.5, 0.0, 1.0),
+            );
+            self.lines.extend_from_slice(frustum.lines());
+        }
+
+        let (entities, shapes, transforms) = <(
+            Entities<'_>,
+            ReadStorage<'_, DebugShapesComponent>,
+            ReadStorage<'_, Transform>,
+        )>::fetch(resources);
+        let global = |entity| {
+            transforms
+                .get(entity)
+                .map(|t| convert::<_, Matrix4<f32>>(*t.global_matrix()))
+        };
+        self.overlay_lines.clear();
+        for (owner, shapes) in (&entities, &shapes).join() {
+            for shape in &shapes.shapes {
+                let anchor = match shape.anchor() {
+                    DebugShapeAnchor::Owner => global(owner),
+                    DebugShapeAnchor::Entity(entity) => global(*entity),
+                    DebugShapeAnchor::Provider(provider) => provider(resources),
+                };
+                if let Some(anchor) = anchor {
+                    let out = if shape.depth_test() {
+                        &mut self.lines
+                    } else {
+                        &mut self.overlay_lines
+                    };
+                    shape.write_lines(&anchor, out);
+                }
+            }
+        }
+        let old_depth_tested = self.depth_tested;
+        self.depth_tested = self.lines.len();
+        self.lines.append(&mut self.overlay_lines);
+
         let cam = CameraGatherer::gather(resources);
         let line_width = line_params
             .map(|p| p.line_width)
@@ -151,7 +212,7 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
                 .write(factory, index, self.lines.len() as u64, Some(&self.lines));
         }
 
-        let changed = old_len != self.lines.len();
+        let changed = old_len != self.lines.len() || old_depth_tested != self.depth_tested;
         self.change.prepare_result(index, changed)
     }
 
@@ -175,13 +236,23 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
         self.args.bind(index, layout, 1, &mut encoder);
         self.vertex.bind(index, 0, 0, &mut encoder);
         unsafe {
-            encoder.draw(0..4, 0..self.lines.len() as u32);
+            encoder.draw(0..4, 0..self.depth_tested as u32);
+        }
+
+        if self.depth_tested < self.lines.len() {
+            encoder.bind_graphics_pipeline(&self.pipeline_overlay);
+            unsafe {
+                encoder.draw(0..4, self.depth_tested as u32..self.lines.len() as u32);
+            }
         }
     }
 
     fn dispose(self: Box<Self>, factory: &mut Factory<B>, _aux: &World) {
         unsafe {
             factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.pipeline_overlay);
             factory
                 .device()
                 .destroy_pipeline_layout(self.pipeline_layout);
@@ -189,42 +260,61 @@ impl<B: Backend> RenderGroup<B, World> for DrawDebugLines<B> {
     }
 }
 
-fn build_lines_pipeline<B: Backend>(
+fn build_lines_pipelines<B: Backend>(
     factory: &Factory<B>,
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
     layouts: Vec<&B::DescriptorSetLayout>,
-) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
         factory
             .device()
             .create_pipeline_layout(layouts, None as Option<(_, _)>)
     }?;
 
-    let shader_vertex = unsafe { super::DEBUG_LINES_VERTEX.module(factory).unwrap() };
-    let shader_fragment = unsafe { super::DEBUG_LINES_FRAGMENT.module(factory).unwrap() };
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawDebugLines",
+            [&super::DEBUG_LINES_VERTEX, &super::DEBUG_LINES_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipe_desc = PipelineDescBuilder::new()
+        .with_vertex_desc(&[(DebugLine::vertex(), pso::VertexInputRate::Instance(1))])
+        .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+        .with_shaders(util::simple_shader_set(
+            &shader_vertex,
+            Some(&shader_fragment),
+        ))
+        .with_layout(&pipeline_layout)
+        .with_subpass(subpass)
+        .with_framebuffer_size(framebuffer_width, framebuffer_height)
+        .with_blend_targets(vec![pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: Some(pso::BlendState::ALPHA),
+        }]);
 
     let pipes = PipelinesBuilder::new()
-        .with_pipeline(
-            PipelineDescBuilder::new()
-                .with_vertex_desc(&[(DebugLine::vertex(), pso::VertexInputRate::Instance(1))])
-                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
-                .with_shaders(util::simple_shader_set(
-                    &shader_vertex,
-                    Some(&shader_fragment),
-                ))
-                .with_layout(&pipeline_layout)
-                .with_subpass(subpass)
-                .with_framebuffer_size(framebuffer_width, framebuffer_height)
-                .with_blend_targets(vec![pso::ColorBlendDesc {
-                    mask: pso::ColorMask::ALL,
-                    blend: Some(pso::BlendState::ALPHA),
-                }])
-                .with_depth_test(pso::DepthTest {
-                    fun: pso::Comparison::GreaterEqual,
-                    write: true,
-                }),
+        .with_pipeline(pipe_desc.clone().with_depth_test(pso::DepthTest {
+            fun: pso::Comparison::GreaterEqual,
+            write: true,
+        }))
+        .with_child_pipeline(
+            0,
+            pipe_desc.with_depth_test(pso::DepthTest {
+                fun: pso::Comparison::Always,
+                write: false,
+            }),
         )
         .build(factory, None);
 
@@ -238,8 +328,8 @@ fn build_lines_pipeline<B: Backend>(
             unsafe {
                 factory.device().destroy_pipeline_layout(pipeline_layout);
             }
-            Err(e)
+            Err(PassError::pipelines("DrawDebugLines", e).into())
         }
-        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
     }
 }
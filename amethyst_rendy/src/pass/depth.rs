@@ -0,0 +1,308 @@
+use crate::{
+    batch::{GroupIterator, OneLevelBatch},
+    conventions::Conventions,
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::VertexArgs,
+    skinning::JointTransforms,
+    stats::RenderStats,
+    submodules::{gather::ShadowGatherer, DynamicVertexBuffer, FlatEnvironmentSub},
+    transparent::Transparent,
+    types::{Backend, Mesh},
+    util,
+    visibility::Visibility,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::{AsVertex, Position, VertexFormat},
+};
+use std::time::Instant;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw depth of visible opaque static meshes into a target without color outputs.
+///
+/// The depth is drawn from the camera the scene is culled with, see `FrozenCamera`.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawDepthDesc {
+    shadow_map: bool,
+}
+
+impl DrawDepthDesc {
+    /// Create instance of `DrawDepth` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Draw the shadow casters of `Visibility` from the light casting shadows instead, into a
+    /// square target, see [crate::shadow]. Nothing is drawn while no light casts shadows.
+    pub fn for_shadow_map() -> Self {
+        Self { shadow_map: true }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDepthDesc {
+    fn colors(&self) -> usize {
+        0
+    }
+
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = if self.shadow_map {
+            FlatEnvironmentSub::new(factory)?.for_shadow_map(framebuffer_width)
+        } else {
+            FlatEnvironmentSub::new(factory)?.for_culling()
+        };
+        let vertex_format = vec![Position::vertex()];
+
+        let (pipeline, pipeline_layout) = build_depth_pipeline(
+            factory,
+            subpass,
+            Conventions::of(aux).rasterizer_front_face(),
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format,
+            vec![env.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawDepth::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            vertex_format,
+            models: DynamicVertexBuffer::new(),
+            batches: Default::default(),
+            shadow_map: if self.shadow_map {
+                Some(framebuffer_width)
+            } else {
+                None
+            },
+        }))
+    }
+}
+
+/// Draws depth of opaque static meshes.
+#[derive(Debug)]
+pub struct DrawDepth<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: FlatEnvironmentSub<B>,
+    vertex_format: Vec<VertexFormat>,
+    models: DynamicVertexBuffer<B, VertexArgs>,
+    batches: OneLevelBatch<u32, VertexArgs>,
+    shadow_map: Option<u32>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDepth<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (mesh_storage, visibility, meshes, transforms, joints, transparent) =
+            <(
+                Read<'_, AssetStorage<Mesh>>,
+                ReadExpect<'_, Visibility>,
+                ReadStorage<'_, Handle<Mesh>>,
+                ReadStorage<'_, Transform>,
+                ReadStorage<'_, JointTransforms>,
+                ReadStorage<'_, Transparent>,
+            )>::fetch(world);
+
+        self.env.process(factory, index, world);
+        self.batches.clear_inner();
+
+        let batches_ref = &mut self.batches;
+        let mut insert = |mesh_id, data: &mut Vec<VertexArgs>| {
+            if mesh_storage.contains_id(mesh_id) {
+                batches_ref.insert(mesh_id, data.drain(..));
+            }
+        };
+        match self.shadow_map {
+            Some(resolution) => {
+                if ShadowGatherer::gather(world, resolution).is_some() {
+                    (
+                        &meshes,
+                        &transforms,
+                        !&joints,
+                        !&transparent,
+                        &visibility.shadow_casters,
+                    )
+                        .join()
+                        .map(|(mesh, tform, _, _, _)| {
+                            (mesh.id(), VertexArgs::from_object_data(tform, None))
+                        })
+                        .for_each_group(&mut insert);
+                }
+            }
+            None => {
+                (
+                    &meshes,
+                    &transforms,
+                    !&joints,
+                    &visibility.visible_unordered,
+                )
+                    .join()
+                    .map(|(mesh, tform, _, _)| {
+                        (mesh.id(), VertexArgs::from_object_data(tform, None))
+                    })
+                    .for_each_group(&mut insert);
+            }
+        }
+
+        self.batches.prune();
+        self.models.write(
+            factory,
+            index,
+            self.batches.count() as u64,
+            self.batches.data(),
+        );
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(world);
+        let models_loc = self.vertex_format.len() as u32;
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+
+        let start = Instant::now();
+        let mut draws = 0;
+        if self.models.bind(index, models_loc, 0, &mut encoder) {
+            for (mesh_id, range) in self.batches.iter() {
+                debug_assert!(mesh_storage.contains_id(*mesh_id));
+                if let Some(mesh) =
+                    B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) })
+                {
+                    match mesh.bind_and_draw(0, &self.vertex_format, range, &mut encoder) {
+                        Ok(_) => draws += 1,
+                        Err(error) => {
+                            log::warn!("Trying to draw a mesh without positions: {}", error)
+                        }
+                    }
+                }
+            }
+        }
+        RenderStats::add_draws(world, draws, start.elapsed());
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_depth_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    front_face: pso::FrontFace,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format: &[VertexFormat],
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let vertex_desc = vertex_format
+        .iter()
+        .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            VertexArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+
+    let [shader_vertex] =
+        match unsafe { util::shader_modules(factory, "DrawDepth", [&super::DEPTH_VERTEX]) } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&vertex_desc)
+                .with_shaders(util::simple_shader_set(&shader_vertex, None))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_face_culling(pso::Face::BACK)
+                .with_front_face(front_face)
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Greater,
+                    write: true,
+                }),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawDepth", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
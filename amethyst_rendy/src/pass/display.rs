@@ -0,0 +1,373 @@
+use super::FullscreenPipeline;
+use crate::{
+    submodules::{sampled_image_access, DynamicUniform, GraphImageSub},
+    types::Backend,
+};
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::{float, int, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{
+        self,
+        device::Device,
+        format::{ChannelType, Format},
+        image::Filter,
+        pso,
+    },
+    shader::SpirvShader,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Check if the hardware converts linear colors to sRGB when writing to given format.
+pub fn encodes_srgb(format: Format) -> bool {
+    format.base_format().1 == ChannelType::Srgb
+}
+
+/// Operator compressing the linear scene colors into the displayable range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ToneMapOperator {
+    /// Clamp every channel to 1, clipping bright colors to white.
+    Clamp,
+    /// Reinhard operator `c / (1 + c)` on every channel, keeping detail in bright areas at
+    /// the cost of contrast.
+    Reinhard,
+}
+
+/// Conversion of the linear scene to the display target done by the `DrawDisplay` group.
+///
+/// Add as a resource to override the settings the render group was created with, e.g. to
+/// animate the exposure every frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToneMapping {
+    /// Factor the scene colors are multiplied by before tone mapping.
+    pub exposure: f32,
+    /// Operator applied after the exposure.
+    pub operator: ToneMapOperator,
+    /// Exponent of the power curve the colors are encoded with for display, e.g. 2.2. `None`
+    /// encodes them with the sRGB transfer function, like sRGB targets do on write.
+    pub gamma: Option<f32>,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            operator: ToneMapOperator::Clamp,
+            gamma: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct DisplayUniform {
+    exposure: float,
+    gamma: float,
+    operator: int,
+}
+
+impl ToneMapping {
+    fn uniform(&self) -> DisplayUniform {
+        DisplayUniform {
+            exposure: self.exposure.max(0.0),
+            gamma: self.gamma.filter(|gamma| *gamma > 0.0).unwrap_or(0.0),
+            operator: match self.operator {
+                ToneMapOperator::Clamp => 0,
+                ToneMapOperator::Reinhard => 1,
+            },
+        }
+    }
+}
+
+/// Convert the linear scene, bound as the only image of the group, to the display target with
+/// the [ToneMapping] of the world.
+///
+/// Used as the tonemapping step of `WorkingSpace::LinearHdr`. When the display target doesn't
+/// encode sRGB on write, the group has to be created `with_srgb_encoding`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawDisplayDesc {
+    encode_srgb: bool,
+    depth: bool,
+    tone_mapping: ToneMapping,
+}
+
+impl DrawDisplayDesc {
+    /// Create instance of `DrawDisplay` render group.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Convert the colors to sRGB in the shader.
+    pub fn with_srgb_encoding(mut self, encode_srgb: bool) -> Self {
+        self.encode_srgb = encode_srgb;
+        self
+    }
+
+    /// Set whether the target this group is added to has a depth output.
+    pub fn with_target_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Tone map the scene with given settings when the world has no `ToneMapping` resource.
+    pub fn with_tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+        self.tone_mapping = tone_mapping;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawDisplayDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let scene = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let fragment: &SpirvShader = if self.encode_srgb {
+            &super::DISPLAY_SRGB_FRAGMENT
+        } else {
+            &super::DISPLAY_FRAGMENT
+        };
+        let (pipeline, pipeline_layout) = FullscreenPipeline::new("DrawDisplay", fragment).build(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![scene.raw_layout(), args.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawDisplay::<B> {
+            pipeline,
+            pipeline_layout,
+            scene,
+            args,
+            default_tone_mapping: self.tone_mapping,
+        }))
+    }
+}
+
+/// Draws the linear scene to the display target.
+#[derive(Debug)]
+pub struct DrawDisplay<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    scene: GraphImageSub<B>,
+    args: DynamicUniform<B, DisplayUniform>,
+    default_tone_mapping: ToneMapping,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawDisplay<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let tone_mapping = <Option<Read<'_, ToneMapping>>>::fetch(world);
+        let tone_mapping = tone_mapping
+            .as_deref()
+            .unwrap_or(&self.default_tone_mapping);
+        if self
+            .args
+            .write(factory, index, tone_mapping.uniform().std140())
+        {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.scene.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_formats_encode_on_write() {
+        assert!(encodes_srgb(Format::Bgra8Srgb));
+        assert!(encodes_srgb(Format::Rgba8Srgb));
+        assert!(!encodes_srgb(Format::Bgra8Unorm));
+        assert!(!encodes_srgb(Format::Rgba16Sfloat));
+    }
+
+    // CPU mirror of the display shader, keep in sync with
+    // amethyst_rendy/shaders/fragment/header/display.frag
+
+    fn srgb_encode(c: f32) -> f32 {
+        if c < 0.003_130_8 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    fn srgb_decode(c: f32) -> f32 {
+        if c < 0.040_45 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    fn display(scene: f32, uniform: &DisplayUniform, encode_srgb: bool) -> f32 {
+        let mut c = (scene * uniform.exposure).max(0.0);
+        if uniform.operator == 1 {
+            c /= 1.0 + c;
+        }
+        c = c.min(1.0);
+        if uniform.gamma > 0.0 {
+            c = c.powf(1.0 / uniform.gamma);
+            if !encode_srgb {
+                c = srgb_decode(c);
+            }
+        } else if encode_srgb {
+            c = srgb_encode(c);
+        }
+        c
+    }
+
+    /// Byte stored by a color attachment for a shader output, encoded on write by sRGB formats.
+    fn store(c: f32, srgb: bool) -> u8 {
+        let c = if srgb { srgb_encode(c) } else { c };
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    /// Byte stored by an 8 bit attachment after alpha blending `src` over the stored `dst`.
+    /// sRGB formats decode the attachment before blending.
+    fn blend(src: f32, alpha: f32, dst: u8, srgb: bool) -> u8 {
+        let dst = f32::from(dst) / 255.0;
+        let dst = if srgb { srgb_decode(dst) } else { dst };
+        store(src * alpha + dst * (1.0 - alpha), srgb)
+    }
+
+    #[test]
+    fn half_transparent_white_over_black_blends_linearly_in_both_working_spaces() {
+        // Displayed value of linear 0.5.
+        let expected = store(0.5, true);
+        assert_eq!(expected, 188);
+
+        // `WorkingSpace::SrgbFramebuffer`: the quad is blended in the sRGB surface.
+        let black = store(0.0, true);
+        assert_eq!(blend(1.0, 0.5, black, true), expected);
+
+        // `WorkingSpace::LinearHdr`: the quad is blended in the linear `Rgba16Sfloat` scene,
+        // then displayed on either kind of surface.
+        let scene = 1.0 * 0.5 + 0.0 * 0.5;
+        let uniform = ToneMapping::default().uniform();
+        assert_eq!(store(display(scene, &uniform, false), true), expected);
+        assert_eq!(store(display(scene, &uniform, true), false), expected);
+
+        // Blending in gamma space, what both modes avoid, darkens the result.
+        assert_eq!(blend(1.0, 0.5, store(0.0, false), false), 128);
+    }
+
+    #[test]
+    fn display_gamma_is_the_same_on_both_kinds_of_surfaces() {
+        let uniform = ToneMapping {
+            gamma: Some(2.2),
+            ..Default::default()
+        }
+        .uniform();
+        for &scene in &[0.0, 0.01, 0.2, 0.5, 0.9, 4.0] {
+            let expected = store(f32::min(scene, 1.0).powf(1.0 / 2.2), false);
+            let on_srgb = store(display(scene, &uniform, false), true);
+            let on_unorm = store(display(scene, &uniform, true), false);
+            assert!(on_srgb.max(expected) - on_srgb.min(expected) <= 1);
+            assert_eq!(on_unorm, expected);
+        }
+    }
+
+    #[test]
+    fn tone_mapping_uniform() {
+        let uniform = ToneMapping::default().uniform();
+        assert_eq!(
+            (uniform.exposure, uniform.gamma, uniform.operator),
+            (1.0, 0.0, 0)
+        );
+
+        let uniform = ToneMapping {
+            exposure: -1.0,
+            operator: ToneMapOperator::Reinhard,
+            gamma: Some(2.2),
+        }
+        .uniform();
+        assert_eq!(
+            (uniform.exposure, uniform.gamma, uniform.operator),
+            (0.0, 2.2, 1)
+        );
+
+        let uniform = ToneMapping {
+            gamma: Some(0.0),
+            ..Default::default()
+        }
+        .uniform();
+        assert_eq!(uniform.gamma, 0.0);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::types::Backend;
+use amethyst_core::ecs::World;
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal,
+    resource::{Handle, Image},
+};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Frame drawn by an [ExternalDraw].
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct ExternalFrame<'a, B: Backend> {
+    /// Index of the image in flight, for per-image resources.
+    pub index: usize,
+    /// Width of the target, in pixels.
+    pub framebuffer_width: u32,
+    /// Height of the target, in pixels.
+    pub framebuffer_height: u32,
+    /// Images of other targets read by the pass, in the order they were declared.
+    pub images: &'a [Handle<Image<B>>],
+    /// World the frame is drawn from.
+    #[derivative(Debug = "ignore")]
+    pub world: &'a World,
+}
+
+/// Raw drawing in a subpass of a target, see [DrawExternalDesc].
+///
+/// Any `FnMut(&ExternalFrame<'_, B>, &mut RenderPassEncoder<'_, B>)` closure draws without
+/// building anything. Implement the trait to create pipelines and other resources when the
+/// render graph is built.
+///
+/// The passes of this crate bind their pipeline, descriptor sets and vertex buffers before
+/// drawing, and pipelines set the depth, blend and rasterizer state, so the closure may leave
+/// them bound. It must not assume any of them is bound when called, and must reset the dynamic
+/// state it sets, such as the viewport, scissor or depth bias, as the passes built with static
+/// state don't set it again.
+pub trait ExternalDraw<B: Backend>: Send + 'static {
+    /// Create the resources of the pass, called every time the render graph is built.
+    fn build(
+        &mut self,
+        _factory: &mut Factory<B>,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _framebuffer_width: u32,
+        _framebuffer_height: u32,
+        _images: &[Handle<Image<B>>],
+    ) -> Result<(), failure::Error> {
+        Ok(())
+    }
+
+    /// Write the per-frame data of the pass, before the frame is recorded.
+    fn prepare(&mut self, _factory: &Factory<B>, _index: usize, _world: &World) {}
+
+    /// Record the draws of the pass into the subpass of the target.
+    fn draw(&mut self, frame: &ExternalFrame<'_, B>, encoder: &mut RenderPassEncoder<'_, B>);
+
+    /// Destroy the resources of the pass, called before the render graph is rebuilt or
+    /// destroyed.
+    fn dispose(&mut self, _factory: &mut Factory<B>) {}
+}
+
+impl<B, F> ExternalDraw<B> for F
+where
+    B: Backend,
+    F: FnMut(&ExternalFrame<'_, B>, &mut RenderPassEncoder<'_, B>) + Send + 'static,
+{
+    fn draw(&mut self, frame: &ExternalFrame<'_, B>, encoder: &mut RenderPassEncoder<'_, B>) {
+        self(frame, encoder)
+    }
+}
+
+/// Draw into a target with an [ExternalDraw], e.g. to let an external library draw between
+/// the passes of the crate.
+///
+/// The images of other targets it reads must be declared with `with_image`, along with
+/// passing them to the builder, so the render graph orders their writes before the pass and
+/// transitions them to the declared layout.
+#[derive(Clone)]
+pub struct DrawExternalDesc<B: Backend> {
+    draw: Arc<Mutex<dyn ExternalDraw<B>>>,
+    colors: usize,
+    depth: bool,
+    images: Vec<ImageAccess>,
+}
+
+impl<B: Backend> std::fmt::Debug for DrawExternalDesc<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DrawExternalDesc")
+            .field("colors", &self.colors)
+            .field("depth", &self.depth)
+            .field("images", &self.images)
+            .finish()
+    }
+}
+
+impl<B: Backend> DrawExternalDesc<B> {
+    /// Create instance of `DrawExternal` render group, for a target with one color output and
+    /// a depth output.
+    pub fn new(draw: impl ExternalDraw<B>) -> Self {
+        Self::shared(Arc::new(Mutex::new(draw)))
+    }
+
+    /// Create instance of `DrawExternal` render group drawing with an `ExternalDraw` shared
+    /// between the builds of the render graph.
+    pub fn shared(draw: Arc<Mutex<dyn ExternalDraw<B>>>) -> Self {
+        Self {
+            draw,
+            colors: 1,
+            depth: true,
+            images: Vec::new(),
+        }
+    }
+
+    /// Set the number of color outputs of the target.
+    pub fn with_colors(mut self, colors: usize) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Set whether the target has a depth output.
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Declare the access of the pass to the next image passed to the builder.
+    pub fn with_image(mut self, access: ImageAccess) -> Self {
+        self.images.push(access);
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawExternalDesc<B> {
+    fn colors(&self) -> usize {
+        self.colors
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn images(&self) -> Vec<ImageAccess> {
+        self.images.clone()
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let images = images
+            .iter()
+            .map(|node_image| {
+                ctx.get_image(node_image.id).cloned().ok_or_else(|| {
+                    failure::format_err!("Graph image {:?} not found", node_image.id)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.draw.lock().unwrap().build(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &images,
+        )?;
+
+        Ok(Box::new(DrawExternal {
+            draw: self.draw,
+            images,
+            framebuffer_width,
+            framebuffer_height,
+        }))
+    }
+}
+
+/// Draws with an [ExternalDraw].
+pub struct DrawExternal<B: Backend> {
+    draw: Arc<Mutex<dyn ExternalDraw<B>>>,
+    images: Vec<Handle<Image<B>>>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+}
+
+impl<B: Backend> std::fmt::Debug for DrawExternal<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DrawExternal")
+            .field("images", &self.images)
+            .field("framebuffer_width", &self.framebuffer_width)
+            .field("framebuffer_height", &self.framebuffer_height)
+            .finish()
+    }
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawExternal<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        self.draw.lock().unwrap().prepare(factory, index, world);
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let frame = ExternalFrame {
+            index,
+            framebuffer_width: self.framebuffer_width,
+            framebuffer_height: self.framebuffer_height,
+            images: &self.images,
+            world,
+        };
+        self.draw.lock().unwrap().draw(&frame, &mut encoder);
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        self.draw.lock().unwrap().dispose(factory);
+    }
+}
@@ -17,6 +17,20 @@ impl Base3DPassDef for FlatPassDef {
     fn vertex_skinned_shader() -> &'static SpirvShader {
         &super::POS_TEX_SKIN_VERTEX
     }
+    fn vertex_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_TEX_MORPH_VERTEX)
+    }
+    fn vertex_skinned_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_TEX_SKIN_MORPH_VERTEX)
+    }
+    fn vertex_uv_transform_shader(skinned: bool, morphed: bool) -> Option<&'static SpirvShader> {
+        Some(match (skinned, morphed) {
+            (false, false) => &super::POS_TEX_UV_VERTEX,
+            (true, false) => &super::POS_TEX_SKIN_UV_VERTEX,
+            (false, true) => &super::POS_TEX_MORPH_UV_VERTEX,
+            (true, true) => &super::POS_TEX_SKIN_MORPH_UV_VERTEX,
+        })
+    }
     fn fragment_shader() -> &'static SpirvShader {
         &super::FLAT_FRAGMENT
     }
@@ -1,5 +1,6 @@
 use crate::{
     batch::{GroupIterator, OneLevelBatch, OrderedOneLevelBatch},
+    error::PassError,
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
     pod::SpriteArgs,
     resources::Tint,
@@ -25,7 +26,6 @@ use rendy::{
     },
     hal::{self, device::Device, pso},
     mesh::AsVertex,
-    shader::Shader,
 };
 
 #[cfg(feature = "profiler")]
@@ -406,8 +406,21 @@ fn build_sprite_pipeline<B: Backend>(
             .create_pipeline_layout(layouts, None as Option<(_, _)>)
     }?;
 
-    let shader_vertex = unsafe { super::SPRITE_VERTEX.module(factory).unwrap() };
-    let shader_fragment = unsafe { super::SPRITE_FRAGMENT.module(factory).unwrap() };
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawFlat2D",
+            [&super::SPRITE_VERTEX, &super::SPRITE_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
 
     let pipes = PipelinesBuilder::new()
         .with_pipeline(
@@ -446,7 +459,7 @@ fn build_sprite_pipeline<B: Backend>(
             unsafe {
                 factory.device().destroy_pipeline_layout(pipeline_layout);
             }
-            Err(e)
+            Err(PassError::pipelines("DrawFlat2D", e).into())
         }
         Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
     }
@@ -0,0 +1,250 @@
+use crate::{
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod,
+    resources::{FogMode, FogSettings},
+    submodules::{
+        gather::{CameraGatherer, FogGatherer},
+        sampled_image_access, DynamicUniform, GraphImageSub,
+    },
+    types::Backend,
+    util,
+};
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::{mat4, vec3, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct FogPostUniform {
+    inverse_proj_view: mat4,
+    camera_position: vec3,
+    fog: pod::Fog,
+}
+
+/// Blend the scene `FogSettings` over the target, using the scene depth bound as the only
+/// image of the group. Only draws when the fog mode is `FogMode::PostPass`.
+///
+/// The depth must match the size of the target. Pixels with zero depth, such as the skybox,
+/// are fogged as infinitely distant, so the horizon takes the fog color.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawFogDesc {
+    depth: bool,
+}
+
+impl DrawFogDesc {
+    /// Create instance of `DrawFog` render group.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set whether the target this group is added to has a depth output.
+    pub fn with_target_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawFogDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let depth = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let (pipeline, pipeline_layout) = build_fog_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![depth.raw_layout(), args.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawFog::<B> {
+            pipeline,
+            pipeline_layout,
+            depth,
+            args,
+            enabled: false,
+        }))
+    }
+}
+
+/// Draws the scene fog from depth.
+#[derive(Debug)]
+pub struct DrawFog<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    depth: GraphImageSub<B>,
+    args: DynamicUniform<B, FogPostUniform>,
+    enabled: bool,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawFog<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let enabled = <Option<Read<'_, FogSettings>>>::fetch(world)
+            .is_some_and(|settings| settings.mode == FogMode::PostPass);
+        let toggled = enabled != self.enabled;
+        self.enabled = enabled;
+        if !enabled {
+            return if toggled {
+                PrepareResult::DrawRecord
+            } else {
+                PrepareResult::DrawReuse
+            };
+        }
+
+        let matrix: [[f32; 4]; 4] = CameraGatherer::gather_inverse_proj_view(world).into();
+        let uniform = FogPostUniform {
+            inverse_proj_view: matrix.into(),
+            camera_position: CameraGatherer::gather(world).camera_position,
+            fog: FogGatherer::gather(world),
+        }
+        .std140();
+
+        if self.args.write(factory, index, uniform) || toggled {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if !self.enabled {
+            return;
+        }
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.depth.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_fog_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawFog",
+            [&super::FULLSCREEN_VERTEX, &super::FOG_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawFog", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    types::Backend,
+    util,
+    view::Viewport,
+};
+use rendy::{
+    factory::Factory,
+    hal::{self, device::Device, pso},
+    shader::SpirvShader,
+};
+
+/// Pipeline of a post effect, drawing a fragment shader over a triangle covering the target.
+///
+/// The vertex shader has no inputs and passes the texture coordinates of the fragments to
+/// location 0 of the fragment shader, going from 0 at the top left corner to 1 at the bottom
+/// right corner. Draw the pipeline with `encoder.draw(0..3, 0..1)`.
+#[derive(Debug)]
+pub struct FullscreenPipeline<'a> {
+    pass: &'static str,
+    fragment: &'a SpirvShader,
+    blend: Option<pso::BlendState>,
+    viewport: Viewport,
+}
+
+impl<'a> FullscreenPipeline<'a> {
+    /// Draw given fragment shader for the pass named `pass`, replacing the colors of the
+    /// target.
+    pub fn new(pass: &'static str, fragment: &'a SpirvShader) -> Self {
+        Self {
+            pass,
+            fragment,
+            blend: None,
+            viewport: Viewport::FULL,
+        }
+    }
+
+    /// Blend the colors of the fragment shader with the target.
+    pub fn with_blend(mut self, blend: Option<pso::BlendState>) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Only cover given viewport of the target.
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Create the pipeline and its layout, with given descriptor set layouts.
+    pub fn build<B: Backend>(
+        self,
+        factory: &Factory<B>,
+        subpass: hal::pass::Subpass<'_, B>,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        layouts: Vec<&B::DescriptorSetLayout>,
+    ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+        let pipeline_layout = unsafe {
+            factory
+                .device()
+                .create_pipeline_layout(layouts, None as Option<(_, _)>)
+        }?;
+
+        let [shader_vertex, shader_fragment] = match unsafe {
+            util::shader_modules(
+                factory,
+                self.pass,
+                [&super::FULLSCREEN_VERTEX, self.fragment],
+            )
+        } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+
+        let pipes = PipelinesBuilder::new()
+            .with_pipeline(
+                PipelineDescBuilder::new()
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex,
+                        Some(&shader_fragment),
+                    ))
+                    .with_layout(&pipeline_layout)
+                    .with_subpass(subpass)
+                    .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                    .with_viewport(self.viewport.rect(framebuffer_width, framebuffer_height))
+                    .with_blend_targets(vec![pso::ColorBlendDesc {
+                        mask: pso::ColorMask::ALL,
+                        blend: self.blend,
+                    }]),
+            )
+            .build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_fragment);
+        }
+
+        match pipes {
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                Err(PassError::pipelines(self.pass, e).into())
+            }
+            Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+        }
+    }
+}
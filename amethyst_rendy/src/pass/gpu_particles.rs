@@ -0,0 +1,694 @@
+use crate::{
+    determinism::RenderDeterminism,
+    error::PassError,
+    gpu_particles::{GpuParticleForces, GpuParticles, MAX_PARTICLE_ATTRACTORS},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{GpuParticleSpawnArgs, IntoPod},
+    submodules::{
+        sampled_image_access, DynamicUniform, DynamicVertexBuffer, FlatEnvironmentSub,
+        GraphImageSub,
+    },
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{Read, SystemData, World, Write},
+    timing::Time,
+};
+use derivative::Derivative;
+use glsl_layout::{float, int, uint, uvec2, vec3, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+    mesh::AsVertex,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of images holding the particle state: position and age, velocity and lifetime,
+/// packed colors and sizes.
+pub const GPU_PARTICLE_STATE_IMAGES: usize = 3;
+
+#[derive(Clone, Copy, Debug, Default, AsStd140)]
+pub(crate) struct ParticleAttractorArgs {
+    position: vec3,
+    strength: float,
+}
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct ParticleSimulationUniform {
+    state_size: uvec2,
+    gravity: vec3,
+    drag: float,
+    delta: float,
+    reset: uint,
+    attractor_count: int,
+    attractors: [ParticleAttractorArgs; MAX_PARTICLE_ATTRACTORS],
+}
+
+/// Advance the GPU particle state by a frame, then write the particles emitted this frame, see
+/// [crate::gpu_particles].
+///
+/// Expects the current state bound as the images of the group, and the next state as the
+/// color outputs of its subpass, all of the same size.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawGpuParticleSimulationDesc;
+
+impl DrawGpuParticleSimulationDesc {
+    /// Create instance of `DrawGpuParticleSimulation` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawGpuParticleSimulationDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER); GPU_PARTICLE_STATE_IMAGES]
+    }
+
+    fn colors(&self) -> usize {
+        GPU_PARTICLE_STATE_IMAGES
+    }
+
+    fn depth(&self) -> bool {
+        false
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let state = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(
+            factory,
+            pso::ShaderStageFlags::VERTEX | pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let (pipelines, pipeline_layout) = build_simulation_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![state.raw_layout(), args.raw_layout()],
+        )?;
+
+        // The state is created with the graph, so previous particles are gone.
+        if let Some(mut particles) = <Option<Write<'_, GpuParticles>>>::fetch(aux) {
+            particles.reset();
+        }
+
+        Ok(Box::new(DrawGpuParticleSimulation::<B> {
+            pipelines,
+            pipeline_layout,
+            state,
+            args,
+            vertex: DynamicVertexBuffer::new(),
+            spawns: Vec::new(),
+            state_size: [framebuffer_width, framebuffer_height],
+        }))
+    }
+}
+
+/// Simulates GPU particles.
+#[derive(Debug)]
+pub struct DrawGpuParticleSimulation<B: Backend> {
+    pipelines: Vec<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    state: GraphImageSub<B>,
+    args: DynamicUniform<B, ParticleSimulationUniform>,
+    vertex: DynamicVertexBuffer<B, GpuParticleSpawnArgs>,
+    spawns: Vec<GpuParticleSpawnArgs>,
+    state_size: [u32; 2],
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawGpuParticleSimulation<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (time, determinism, forces, particles) = <(
+            Read<'_, Time>,
+            Option<Read<'_, RenderDeterminism>>,
+            Option<Read<'_, GpuParticleForces>>,
+            Option<Write<'_, GpuParticles>>,
+        )>::fetch(world);
+        let reset = match particles {
+            Some(mut particles) => particles.take_spawns(&mut self.spawns),
+            None => {
+                self.spawns.clear();
+                false
+            }
+        };
+        let default_forces;
+        let forces = match forces {
+            Some(ref forces) => &**forces,
+            None => {
+                default_forces = GpuParticleForces::default();
+                &default_forces
+            }
+        };
+
+        let mut attractors = [ParticleAttractorArgs::default(); MAX_PARTICLE_ATTRACTORS];
+        for (args, attractor) in attractors.iter_mut().zip(&forces.attractors) {
+            *args = ParticleAttractorArgs {
+                position: attractor.position.into_pod(),
+                strength: attractor.strength,
+            };
+        }
+        let uniform = ParticleSimulationUniform {
+            state_size: self.state_size.into(),
+            gravity: forces.gravity.into_pod(),
+            drag: forces.drag.max(0.0),
+            delta: determinism.map_or(time.delta_seconds(), |d| d.delta_seconds(&time)),
+            reset: reset as u32,
+            attractor_count: forces.attractors.len().min(MAX_PARTICLE_ATTRACTORS) as i32,
+            attractors,
+        }
+        .std140();
+
+        self.args.write(factory, index, uniform);
+        self.vertex.write(
+            factory,
+            index,
+            self.spawns.len() as u64,
+            Some(self.spawns.as_slice()),
+        );
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipelines[0]);
+        self.state.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+
+        if self.spawns.is_empty() {
+            return;
+        }
+        encoder.bind_graphics_pipeline(&self.pipelines[1]);
+        self.state.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..1, 0..self.spawns.len() as u32);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            for pipeline in self.pipelines {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Copy the GPU particle state simulated this frame back into the state read by the next
+/// frame and by `DrawGpuParticlesDesc`.
+///
+/// Expects the simulated state bound as the images of the group, and the state to write as
+/// the color outputs of its subpass.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawGpuParticleCopyDesc;
+
+impl DrawGpuParticleCopyDesc {
+    /// Create instance of `DrawGpuParticleCopy` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawGpuParticleCopyDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER); GPU_PARTICLE_STATE_IMAGES]
+    }
+
+    fn colors(&self) -> usize {
+        GPU_PARTICLE_STATE_IMAGES
+    }
+
+    fn depth(&self) -> bool {
+        false
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let state = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let pipeline_layout = unsafe {
+            factory
+                .device()
+                .create_pipeline_layout(vec![state.raw_layout()], None as Option<(_, _)>)
+        }?;
+
+        let [shader_vertex, shader_fragment] = match unsafe {
+            util::shader_modules(
+                factory,
+                "DrawGpuParticleCopy",
+                [&super::FULLSCREEN_VERTEX, &super::PARTICLE_COPY_FRAGMENT],
+            )
+        } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+
+        let pipes = PipelinesBuilder::new()
+            .with_pipeline(
+                PipelineDescBuilder::new()
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex,
+                        Some(&shader_fragment),
+                    ))
+                    .with_layout(&pipeline_layout)
+                    .with_subpass(subpass)
+                    .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                    .with_blend_targets(state_blend_targets()),
+            )
+            .build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_fragment);
+        }
+
+        let pipeline = match pipes {
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(PassError::pipelines("DrawGpuParticleCopy", e).into());
+            }
+            Ok(mut pipes) => pipes.remove(0),
+        };
+
+        Ok(Box::new(DrawGpuParticleCopy::<B> {
+            pipeline,
+            pipeline_layout,
+            state,
+        }))
+    }
+}
+
+/// Copies the GPU particle state.
+#[derive(Debug)]
+pub struct DrawGpuParticleCopy<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    state: GraphImageSub<B>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawGpuParticleCopy<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) -> PrepareResult {
+        PrepareResult::DrawReuse
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.state.bind(&self.pipeline_layout, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Draw the GPU particles as camera facing billboards, blended over the scene.
+///
+/// Expects the particle state bound as the images of the group. Every slot of the state is
+/// drawn, dead particles are collapsed by the vertex shader.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawGpuParticlesDesc {
+    additive: bool,
+}
+
+impl DrawGpuParticlesDesc {
+    /// Create instance of `DrawGpuParticles` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add the particles to the target instead of blending them with their alpha, e.g. for
+    /// sparks and fire.
+    pub fn with_additive(mut self, additive: bool) -> Self {
+        self.additive = additive;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawGpuParticlesDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::VERTEX_SHADER); GPU_PARTICLE_STATE_IMAGES]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let state = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::VERTEX,
+        )?;
+        let capacity = ctx.get_image(images[0].id).map_or(0, |image| {
+            image.kind().extent().width * image.kind().extent().height
+        });
+
+        let pipeline_layout = unsafe {
+            factory.device().create_pipeline_layout(
+                vec![env.raw_layout(), state.raw_layout()],
+                None as Option<(_, _)>,
+            )
+        }?;
+
+        let [shader_vertex, shader_fragment] = match unsafe {
+            util::shader_modules(
+                factory,
+                "DrawGpuParticles",
+                [
+                    &super::PARTICLE_BILLBOARD_VERTEX,
+                    &super::PARTICLE_BILLBOARD_FRAGMENT,
+                ],
+            )
+        } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+
+        let blend = if self.additive {
+            pso::BlendState::ADD
+        } else {
+            pso::BlendState::ALPHA
+        };
+        let pipes = PipelinesBuilder::new()
+            .with_pipeline(
+                PipelineDescBuilder::new()
+                    .with_input_assembler(pso::InputAssemblerDesc::new(
+                        hal::Primitive::TriangleStrip,
+                    ))
+                    .with_shaders(util::simple_shader_set(
+                        &shader_vertex,
+                        Some(&shader_fragment),
+                    ))
+                    .with_layout(&pipeline_layout)
+                    .with_subpass(subpass)
+                    .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                    .with_blend_targets(vec![pso::ColorBlendDesc {
+                        mask: pso::ColorMask::ALL,
+                        blend: Some(blend),
+                    }])
+                    .with_depth_test(pso::DepthTest {
+                        fun: pso::Comparison::GreaterEqual,
+                        write: false,
+                    }),
+            )
+            .build(factory, None);
+
+        unsafe {
+            factory.destroy_shader_module(shader_vertex);
+            factory.destroy_shader_module(shader_fragment);
+        }
+
+        let pipeline = match pipes {
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(PassError::pipelines("DrawGpuParticles", e).into());
+            }
+            Ok(mut pipes) => pipes.remove(0),
+        };
+
+        Ok(Box::new(DrawGpuParticles::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            state,
+            capacity,
+        }))
+    }
+}
+
+/// Draws GPU particles.
+#[derive(Debug)]
+pub struct DrawGpuParticles<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: FlatEnvironmentSub<B>,
+    state: GraphImageSub<B>,
+    capacity: u32,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawGpuParticles<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        self.env.process(factory, index, world);
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+        self.state.bind(&self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..4, 0..self.capacity);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// The state is written as is, the appearance image has an integer format which can't blend.
+fn state_blend_targets() -> Vec<pso::ColorBlendDesc> {
+    vec![
+        pso::ColorBlendDesc {
+            mask: pso::ColorMask::ALL,
+            blend: None,
+        };
+        GPU_PARTICLE_STATE_IMAGES
+    ]
+}
+
+fn build_simulation_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_fullscreen, shader_simulate, shader_spawn_vertex, shader_spawn_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawGpuParticleSimulation",
+            [
+                &super::FULLSCREEN_VERTEX,
+                &super::PARTICLE_SIMULATE_FRAGMENT,
+                &super::PARTICLE_SPAWN_VERTEX,
+                &super::PARTICLE_SPAWN_FRAGMENT,
+            ],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_fullscreen,
+                    Some(&shader_simulate),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(state_blend_targets()),
+        )
+        .with_pipeline(
+            // One point per emitted particle, on the texel of its slot.
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(
+                    GpuParticleSpawnArgs::vertex(),
+                    pso::VertexInputRate::Instance(1),
+                )])
+                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::PointList))
+                .with_shaders(util::simple_shader_set(
+                    &shader_spawn_vertex,
+                    Some(&shader_spawn_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(state_blend_targets()),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_fullscreen);
+        factory.destroy_shader_module(shader_simulate);
+        factory.destroy_shader_module(shader_spawn_vertex);
+        factory.destroy_shader_module(shader_spawn_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawGpuParticleSimulation", e).into())
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{
+    batch::{GroupIterator, OneLevelBatch},
+    camera::{Camera, FrozenCamera},
+    error::PassError,
+    hiz::HiZBounds,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::HiZOcclusionArgs,
+    submodules::{
+        gather::CameraGatherer, sampled_image_access, DynamicVertexBuffer, GraphImageSub,
+    },
+    types::Backend,
+    util,
+    visibility::{BoundingSphere, Visibility},
+};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+    mesh::AsVertex,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Reduce the previous level of the Hi-Z chain into the next one.
+///
+/// Expects the previous level bound as the only image of the group.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawHiZDownsampleDesc;
+
+impl DrawHiZDownsampleDesc {
+    /// Create instance of `DrawHiZDownsample` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawHiZDownsampleDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        false
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let previous = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let (pipeline, pipeline_layout) = build_hiz_pipeline(
+            factory,
+            "DrawHiZDownsample",
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![previous.raw_layout()],
+            &[],
+            hal::Primitive::TriangleList,
+            &super::FULLSCREEN_VERTEX,
+            &super::HIZ_DOWNSAMPLE_FRAGMENT,
+            None,
+        )?;
+
+        Ok(Box::new(DrawHiZDownsample::<B> {
+            pipeline,
+            pipeline_layout,
+            previous,
+        }))
+    }
+}
+
+/// Draws a single level of the Hi-Z chain.
+#[derive(Debug)]
+pub struct DrawHiZDownsample<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    previous: GraphImageSub<B>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawHiZDownsample<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) -> PrepareResult {
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.previous.bind(&self.pipeline_layout, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Overlay the Hi-Z occlusion test result of every visible entity.
+///
+/// Screen bounds of entities that would be culled are drawn red, the rest green. Entities too
+/// large to be tested against the chain, or crossing the camera plane, are not drawn.
+/// Expects every level of the chain bound as images of the group, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawHiZOcclusionDebugDesc {
+    levels: usize,
+}
+
+impl DrawHiZOcclusionDebugDesc {
+    /// Create instance of `DrawHiZOcclusionDebug` render group for a chain with given
+    /// number of levels.
+    pub fn new(levels: usize) -> Self {
+        Self { levels }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawHiZOcclusionDebugDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::VERTEX_SHADER); self.levels]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let mut sizes = Vec::with_capacity(images.len());
+        let mut levels = Vec::with_capacity(images.len());
+        for image in &images {
+            let extent = ctx
+                .get_image(image.id)
+                .ok_or_else(|| failure::format_err!("Hi-Z level {:?} not found", image.id))?
+                .kind()
+                .extent();
+            sizes.push((extent.width, extent.height));
+            levels.push(GraphImageSub::new(
+                ctx,
+                factory,
+                std::slice::from_ref(image),
+                Filter::Nearest,
+                pso::ShaderStageFlags::VERTEX,
+            )?);
+        }
+
+        let layout = levels
+            .first()
+            .ok_or_else(|| {
+                failure::format_err!("Hi-Z occlusion debug requires at least one level")
+            })?
+            .raw_layout();
+        let (pipeline, pipeline_layout) = build_hiz_pipeline(
+            factory,
+            "DrawHiZOcclusionDebug",
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![layout],
+            &[(
+                HiZOcclusionArgs::vertex(),
+                pso::VertexInputRate::Instance(1),
+            )],
+            hal::Primitive::TriangleStrip,
+            &super::HIZ_OCCLUSION_VERTEX,
+            &super::HIZ_OCCLUSION_FRAGMENT,
+            Some(pso::BlendState::ALPHA),
+        )?;
+
+        Ok(Box::new(DrawHiZOcclusionDebug::<B> {
+            pipeline,
+            pipeline_layout,
+            levels,
+            sizes,
+            vertex: DynamicVertexBuffer::new(),
+            batches: Default::default(),
+        }))
+    }
+}
+
+/// Draws Hi-Z occlusion test results.
+#[derive(Debug)]
+pub struct DrawHiZOcclusionDebug<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    levels: Vec<GraphImageSub<B>>,
+    sizes: Vec<(u32, u32)>,
+    vertex: DynamicVertexBuffer<B, HiZOcclusionArgs>,
+    batches: OneLevelBatch<usize, HiZOcclusionArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawHiZOcclusionDebug<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (visibility, cameras, transforms, spheres) = <(
+            ReadExpect<'_, Visibility>,
+            ReadStorage<'_, Camera>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, BoundingSphere>,
+        )>::fetch(world);
+
+        self.batches.clear_inner();
+
+        // Test what the frozen camera would see, as the visible set was culled with it.
+        let frozen = <Option<Read<'_, FrozenCamera>>>::fetch(world);
+        let proj_view = match frozen.as_ref().and_then(|f| f.snapshot()) {
+            Some((camera, transform)) => Some(convert::<_, Matrix4<f32>>(
+                camera.matrix * transform.global_view_matrix(),
+            )),
+            None => CameraGatherer::gather_camera_entity(world).and_then(|entity| {
+                let camera = cameras.get(entity)?;
+                let view = transforms.get(entity)?.global_view_matrix();
+                Some(convert::<_, Matrix4<f32>>(camera.matrix * view))
+            }),
+        };
+
+        if let Some(proj_view) = proj_view {
+            let origin = Point3::origin();
+            let sizes = &self.sizes;
+            let batches_ref = &mut self.batches;
+            (&transforms, spheres.maybe(), &visibility.visible_unordered)
+                .join()
+                .filter_map(|(transform, sphere, _)| {
+                    let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+                    let center = matrix.transform_point(sphere.map_or(&origin, |s| &s.center));
+                    let radius = sphere.map_or(1.0, |s| s.radius)
+                        * matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]);
+                    let bounds = HiZBounds::project(&proj_view, &center, radius)?;
+                    Some((bounds.level(sizes)?, HiZOcclusionArgs::from(&bounds)))
+                })
+                .for_each_group(|level, data| batches_ref.insert(level, data.drain(..)));
+        }
+
+        self.batches.prune();
+        self.vertex.write(
+            factory,
+            index,
+            self.batches.count() as u64,
+            self.batches.data(),
+        );
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        if self.vertex.bind(index, 0, 0, &mut encoder) {
+            for (&level, range) in self.batches.iter() {
+                self.levels[level].bind(&self.pipeline_layout, 0, &mut encoder);
+                unsafe {
+                    encoder.draw(0..4, range);
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_hiz_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    pass: &'static str,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+    vertex_desc: &[(rendy::mesh::VertexFormat, pso::VertexInputRate)],
+    primitive: hal::Primitive,
+    vertex: &rendy::shader::SpirvShader,
+    fragment: &rendy::shader::SpirvShader,
+    blend: Option<pso::BlendState>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] =
+        match unsafe { util::shader_modules(factory, pass, [vertex, fragment]) } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(vertex_desc)
+                .with_input_assembler(pso::InputAssemblerDesc::new(primitive))
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines(pass, e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -0,0 +1,533 @@
+use crate::{
+    batch::{GroupIterator, OneLevelBatch},
+    bundle::Target,
+    error::PassError,
+    impostor::{Impostor, ImpostorAtlas, ImpostorBakeSettings},
+    mtl::{Material, TexAlbedo},
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{ImpostorArgs, ImpostorBakeArgs},
+    resources::Tint,
+    submodules::{
+        gather::CameraGatherer, sampled_image_access, DynamicVertexBuffer, FlatEnvironmentSub,
+        GraphImageSub, MaterialId, MaterialSub, TextureId, TextureSub,
+    },
+    types::{Backend, Mesh},
+    util,
+    visibility::Visibility,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    math::Vector3,
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+    mesh::{AsVertex, Normal, Position, TexCoord, VertexFormat},
+    shader::SpirvShader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw distant entities with an [Impostor] component as camera-facing billboards.
+///
+/// Atlases baked into other targets are read from the images bound to the group, one color
+/// image per target passed `with_baked`, in the same order.
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawImpostorsDesc {
+    baked: Vec<Target>,
+}
+
+impl DrawImpostorsDesc {
+    /// Create instance of `DrawImpostors` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Draw the impostors whose atlases are baked into the color image of given targets.
+    pub fn with_baked(mut self, targets: Vec<Target>) -> Self {
+        self.baked = targets;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawImpostorsDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER); self.baked.len()]
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = FlatEnvironmentSub::new(factory)?;
+        let textures = TextureSub::new(factory)?;
+        let vertex = DynamicVertexBuffer::new();
+        // One set per atlas, compatible with the layout of the texture sets.
+        let baked = self
+            .baked
+            .iter()
+            .zip(images.chunks(1))
+            .map(|(&target, image)| {
+                GraphImageSub::new(
+                    ctx,
+                    factory,
+                    image,
+                    Filter::Linear,
+                    pso::ShaderStageFlags::FRAGMENT,
+                )
+                .map(|sub| (target, sub))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (pipeline, pipeline_layout) = build_impostor_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![env.raw_layout(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawImpostors::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            textures,
+            baked,
+            vertex,
+            impostors: Default::default(),
+        }))
+    }
+}
+
+/// Atlas of a batch of impostors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum AtlasId {
+    Texture(TextureId),
+    Baked(usize),
+}
+
+/// Draws impostors with alpha cutout.
+#[derive(Debug)]
+pub struct DrawImpostors<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: FlatEnvironmentSub<B>,
+    textures: TextureSub<B>,
+    baked: Vec<(Target, GraphImageSub<B>)>,
+    vertex: DynamicVertexBuffer<B, ImpostorArgs>,
+    impostors: OneLevelBatch<AtlasId, ImpostorArgs>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawImpostors<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (visibility, impostors, transforms, tints) = <(
+            ReadExpect<'_, Visibility>,
+            ReadStorage<'_, Impostor>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Tint>,
+        )>::fetch(world);
+
+        self.env.process(factory, index, world);
+        let camera = CameraGatherer::gather(world);
+        let camera_position: &[f32; 3] = camera.camera_position.as_ref();
+        let camera_position = Vector3::from(*camera_position);
+
+        let impostors_ref = &mut self.impostors;
+        let textures_ref = &mut self.textures;
+        let baked_ref = &self.baked;
+
+        impostors_ref.clear_inner();
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("gather_impostors");
+
+            (
+                &impostors,
+                &transforms,
+                tints.maybe(),
+                &visibility.impostors,
+            )
+                .join()
+                .filter_map(|(impostor, transform, tint, _)| {
+                    let atlas = match &impostor.atlas {
+                        ImpostorAtlas::Texture(texture) => {
+                            let (tex_id, _) = textures_ref.insert(
+                                factory,
+                                world,
+                                texture,
+                                hal::image::Layout::ShaderReadOnlyOptimal,
+                            )?;
+                            AtlasId::Texture(tex_id)
+                        }
+                        ImpostorAtlas::Baked(target) => {
+                            AtlasId::Baked(baked_ref.iter().position(|(baked, _)| baked == target)?)
+                        }
+                    };
+                    Some((
+                        atlas,
+                        ImpostorArgs::from_object_data(impostor, transform, tint, camera_position),
+                    ))
+                })
+                .for_each_group(|atlas, batch_data| {
+                    impostors_ref.insert(atlas, batch_data.drain(..))
+                });
+        }
+
+        self.textures.maintain(factory, world);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            impostors_ref.prune();
+            self.vertex.write(
+                factory,
+                index,
+                self.impostors.count() as u64,
+                self.impostors.data(),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, layout, 0, &mut encoder);
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        for (&atlas, range) in self.impostors.iter() {
+            match atlas {
+                AtlasId::Texture(tex) if self.textures.loaded(tex) => {
+                    self.textures.bind(layout, 1, tex, &mut encoder);
+                }
+                AtlasId::Baked(baked) => self.baked[baked].1.bind(layout, 1, &mut encoder),
+                _ => continue,
+            }
+            unsafe {
+                encoder.draw(0..4, range);
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Bake the impostor atlas of the entities whose [Impostor] is `ImpostorAtlas::Baked` into the
+/// target of the group, drawing the mesh of the first of them with its own material, once per
+/// view, lit by the key light of the settings.
+///
+/// The atlas is drawn again every frame the target is read, so it follows changes of the
+/// material. It must be added to a target with a depth output, sized as
+/// `ImpostorBakeSettings::atlas_size`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawImpostorBakeDesc {
+    target: Target,
+    settings: ImpostorBakeSettings,
+}
+
+impl DrawImpostorBakeDesc {
+    /// Create instance of `DrawImpostorBake` render group baking the atlas of `target`.
+    pub fn new(target: Target, settings: ImpostorBakeSettings) -> Self {
+        Self { target, settings }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawImpostorBakeDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let materials = MaterialSub::new(factory)?;
+        let mut vertex_format = vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()];
+        vertex_format.sort();
+        let vertex_desc = vertex_format
+            .iter()
+            .map(|f| (f.clone(), pso::VertexInputRate::Vertex))
+            .chain(Some((
+                ImpostorBakeArgs::vertex(),
+                pso::VertexInputRate::Instance(1),
+            )))
+            .collect::<Vec<_>>();
+
+        let (pipeline, pipeline_layout) = build_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![materials.raw_layout()],
+            "DrawImpostorBake",
+            [&super::IMPOSTOR_BAKE_VERTEX, &super::IMPOSTOR_BAKE_FRAGMENT],
+            &vertex_desc,
+            hal::Primitive::TriangleList,
+        )?;
+
+        Ok(Box::new(DrawImpostorBake::<B> {
+            pipeline,
+            pipeline_layout,
+            target: self.target,
+            settings: self.settings,
+            materials,
+            vertex_format,
+            views: DynamicVertexBuffer::new(),
+            view_args: Vec::new(),
+            source: None,
+            warned_incompatible: false,
+        }))
+    }
+}
+
+/// Draws the views of an impostor atlas.
+#[derive(Debug)]
+pub struct DrawImpostorBake<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    target: Target,
+    settings: ImpostorBakeSettings,
+    materials: MaterialSub<B, TexAlbedo>,
+    vertex_format: Vec<VertexFormat>,
+    views: DynamicVertexBuffer<B, ImpostorBakeArgs>,
+    view_args: Vec<ImpostorBakeArgs>,
+    source: Option<(u32, MaterialId)>,
+    warned_incompatible: bool,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawImpostorBake<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (mesh_storage, impostors, meshes, materials) = <(
+            Read<'_, AssetStorage<Mesh>>,
+            ReadStorage<'_, Impostor>,
+            ReadStorage<'_, Handle<Mesh>>,
+            ReadStorage<'_, Handle<Material>>,
+        )>::fetch(world);
+
+        self.materials.maintain();
+        self.source = None;
+        let target = self.target;
+        let source = (&impostors, &meshes, &materials)
+            .join()
+            .find(|(impostor, _, _)| impostor.atlas == ImpostorAtlas::Baked(target));
+        if let Some((impostor, mesh, material)) = source {
+            if mesh_storage.contains(mesh) {
+                if let Some((material, _)) = self.materials.insert(factory, world, material) {
+                    self.view_args.clear();
+                    self.view_args
+                        .extend(ImpostorBakeArgs::views(&impostor.layout, &self.settings));
+                    self.views.write(
+                        factory,
+                        index,
+                        self.view_args.len() as u64,
+                        Some(&self.view_args),
+                    );
+                    self.source = Some((mesh.id(), material));
+                }
+            }
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let (mesh, material) = match self.source {
+            Some(source) if self.materials.loaded(source.1) => source,
+            _ => return,
+        };
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(world);
+        let mesh = match mesh_storage.get_by_id(mesh).and_then(B::unwrap_mesh) {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.materials
+            .bind(&self.pipeline_layout, 0, material, &mut encoder);
+        let views_loc = self.vertex_format.len() as u32;
+        if self.views.bind(index, views_loc, 0, &mut encoder) {
+            let views = self.view_args.len() as u32;
+            let drawn = mesh.bind_and_draw(0, &self.vertex_format, 0..views, &mut encoder);
+            if let (Err(error), false) = (drawn, self.warned_incompatible) {
+                self.warned_incompatible = true;
+                log::warn!(
+                    "Impostor of {:?} can't be baked, its mesh lacks a vertex attribute: {}",
+                    self.target,
+                    error
+                );
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_impostor_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    build_pipeline(
+        factory,
+        subpass,
+        framebuffer_width,
+        framebuffer_height,
+        layouts,
+        "DrawImpostors",
+        [&super::IMPOSTOR_VERTEX, &super::IMPOSTOR_FRAGMENT],
+        &[(ImpostorArgs::vertex(), pso::VertexInputRate::Instance(1))],
+        hal::Primitive::TriangleStrip,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+    name: &'static str,
+    shaders: [&SpirvShader; 2],
+    vertex_desc: &[(VertexFormat, pso::VertexInputRate)],
+    primitive: hal::Primitive,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] =
+        match unsafe { util::shader_modules(factory, name, shaders) } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(vertex_desc)
+                .with_input_assembler(pso::InputAssemblerDesc::new(primitive))
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }])
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Greater,
+                    write: true,
+                }),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines(name, e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
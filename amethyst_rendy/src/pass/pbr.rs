@@ -17,9 +17,52 @@ impl Base3DPassDef for PbrPassDef {
     fn vertex_skinned_shader() -> &'static SpirvShader {
         &super::POS_NORM_TANG_TEX_SKIN_VERTEX
     }
+    fn vertex_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TANG_TEX_MORPH_VERTEX)
+    }
+    fn vertex_skinned_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TANG_TEX_SKIN_MORPH_VERTEX)
+    }
+    fn vertex_uv_transform_shader(skinned: bool, morphed: bool) -> Option<&'static SpirvShader> {
+        Some(match (skinned, morphed) {
+            (false, false) => &super::POS_NORM_TANG_TEX_UV_VERTEX,
+            (true, false) => &super::POS_NORM_TANG_TEX_SKIN_UV_VERTEX,
+            (false, true) => &super::POS_NORM_TANG_TEX_MORPH_UV_VERTEX,
+            (true, true) => &super::POS_NORM_TANG_TEX_SKIN_MORPH_UV_VERTEX,
+        })
+    }
     fn fragment_shader() -> &'static SpirvShader {
         &super::PBR_FRAGMENT
     }
+    fn fragment_triplanar_shader() -> Option<&'static SpirvShader> {
+        Some(&super::PBR_TRIPLANAR_FRAGMENT)
+    }
+    fn fragment_surface_layer_shader(triplanar: bool) -> Option<&'static SpirvShader> {
+        Some(if triplanar {
+            &super::PBR_TRIPLANAR_LAYER_FRAGMENT
+        } else {
+            &super::PBR_LAYER_FRAGMENT
+        })
+    }
+    fn fragment_subsurface_shader() -> Option<&'static SpirvShader> {
+        Some(&super::PBR_SUBSURFACE_FRAGMENT)
+    }
+    fn fragment_early_depth_shader(
+        subsurface: bool,
+        triplanar: bool,
+        surface_layer: bool,
+    ) -> Option<&'static SpirvShader> {
+        Some(match (subsurface, triplanar, surface_layer) {
+            (true, _, _) => &super::PBR_SUBSURFACE_EARLY_DEPTH_FRAGMENT,
+            (false, true, true) => &super::PBR_TRIPLANAR_LAYER_EARLY_DEPTH_FRAGMENT,
+            (false, false, true) => &super::PBR_LAYER_EARLY_DEPTH_FRAGMENT,
+            (false, true, false) => &super::PBR_TRIPLANAR_EARLY_DEPTH_FRAGMENT,
+            (false, false, false) => &super::PBR_EARLY_DEPTH_FRAGMENT,
+        })
+    }
+    fn fragment_source() -> Option<(&'static str, &'static str)> {
+        Some(("pbr.frag", include_str!("../../shaders/fragment/pbr.frag")))
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![
             Position::vertex(),
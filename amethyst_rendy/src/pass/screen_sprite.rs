@@ -0,0 +1,273 @@
+use crate::{
+    batch::OrderedOneLevelBatch,
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::{SpriteArgs, ViewArgs},
+    screen_sprite::{screen_projection, ScreenSprites},
+    submodules::{DynamicUniform, DynamicVertexBuffer, TextureId, TextureSub},
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{SystemData, World, Write},
+    math::Matrix4,
+};
+use glsl_layout::AsStd140;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::AsVertex,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the sprites of the `ScreenSprites` resource over the target, in pixels.
+///
+/// Sprites are projected with `screen_projection` of the size of the target, blended over it in
+/// submission order without depth test, and their corners are snapped to whole pixels unless
+/// created `with_pixel_snapping(false)`. Consecutive sprites sampling the same texture are
+/// drawn together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawScreenSpritesDesc {
+    snap: bool,
+}
+
+impl Default for DrawScreenSpritesDesc {
+    fn default() -> Self {
+        Self { snap: true }
+    }
+}
+
+impl DrawScreenSpritesDesc {
+    /// Create instance of `DrawScreenSprites` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set whether the corners of the sprites are rounded to whole pixels.
+    pub fn with_pixel_snapping(mut self, snap: bool) -> Self {
+        self.snap = snap;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawScreenSpritesDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = DynamicUniform::new(factory, pso::ShaderStageFlags::VERTEX)?;
+        let textures = TextureSub::new(factory)?;
+        let vertex = DynamicVertexBuffer::new();
+
+        let (pipeline, pipeline_layout) = build_screen_sprite_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![env.raw_layout(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawScreenSprites::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            textures,
+            vertex,
+            sprites: Default::default(),
+            snap: self.snap,
+            framebuffer_width,
+            framebuffer_height,
+        }))
+    }
+}
+
+/// Draws screen space sprites.
+#[derive(Debug)]
+pub struct DrawScreenSprites<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: DynamicUniform<B, ViewArgs>,
+    textures: TextureSub<B>,
+    vertex: DynamicVertexBuffer<B, SpriteArgs>,
+    sprites: OrderedOneLevelBatch<TextureId, SpriteArgs>,
+    snap: bool,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawScreenSprites<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let proj: [[f32; 4]; 4] =
+            screen_projection(self.framebuffer_width, self.framebuffer_height).into();
+        let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+        self.env.write(
+            factory,
+            index,
+            ViewArgs {
+                proj: proj.into(),
+                view: identity.into(),
+                proj_view: proj.into(),
+            }
+            .std140(),
+        );
+
+        self.sprites.swap_clear();
+        if let Some(mut screen_sprites) = <Option<Write<'_, ScreenSprites>>>::fetch(world) {
+            for sprite in screen_sprites.drain() {
+                if let Some((tex_id, _)) = self.textures.insert(
+                    factory,
+                    world,
+                    &sprite.texture,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                ) {
+                    self.sprites
+                        .insert(tex_id, std::iter::once(sprite.args(self.snap)));
+                }
+            }
+        }
+        self.textures.maintain(factory, world);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            self.vertex.write(
+                factory,
+                index,
+                self.sprites.count() as u64,
+                Some(self.sprites.data()),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if self.sprites.count() == 0 {
+            return;
+        }
+
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, layout, 0, &mut encoder);
+        self.vertex.bind(index, 0, 0, &mut encoder);
+        for (&tex, range) in self.sprites.iter() {
+            if self.textures.loaded(tex) {
+                self.textures.bind(layout, 1, tex, &mut encoder);
+                unsafe {
+                    encoder.draw(0..4, range);
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_screen_sprite_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawScreenSprites",
+            [&super::SPRITE_VERTEX, &super::SPRITE_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&[(SpriteArgs::vertex(), pso::VertexInputRate::Instance(1))])
+                .with_input_assembler(pso::InputAssemblerDesc::new(hal::Primitive::TriangleStrip))
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: Some(pso::BlendState::PREMULTIPLIED_ALPHA),
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawScreenSprites", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
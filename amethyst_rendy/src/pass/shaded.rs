@@ -1,10 +1,10 @@
 use super::base_3d::*;
 use crate::{
-    mtl::{TexAlbedo, TexEmission},
+    mtl::{TexAlbedo, TexDetail, TexDissolveNoise, TexEmission, TexLayerMask, TexNormal},
     skinning::JointCombined,
 };
 use rendy::{
-    mesh::{AsVertex, Normal, Position, TexCoord, VertexFormat},
+    mesh::{AsVertex, Normal, Position, Tangent, TexCoord, VertexFormat},
     shader::SpirvShader,
 };
 
@@ -13,16 +13,68 @@ use rendy::{
 pub struct ShadedPassDef;
 impl Base3DPassDef for ShadedPassDef {
     const NAME: &'static str = "Shaded";
-    type TextureSet = (TexAlbedo, TexEmission);
+    type TextureSet = (
+        TexAlbedo,
+        TexEmission,
+        TexDissolveNoise,
+        TexDetail,
+        TexLayerMask,
+    );
     fn vertex_shader() -> &'static SpirvShader {
         &super::POS_NORM_TEX_VERTEX
     }
     fn vertex_skinned_shader() -> &'static SpirvShader {
         &super::POS_NORM_TEX_SKIN_VERTEX
     }
+    fn vertex_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TEX_MORPH_VERTEX)
+    }
+    fn vertex_skinned_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TEX_SKIN_MORPH_VERTEX)
+    }
+    fn vertex_uv_transform_shader(skinned: bool, morphed: bool) -> Option<&'static SpirvShader> {
+        Some(match (skinned, morphed) {
+            (false, false) => &super::POS_NORM_TEX_UV_VERTEX,
+            (true, false) => &super::POS_NORM_TEX_SKIN_UV_VERTEX,
+            (false, true) => &super::POS_NORM_TEX_MORPH_UV_VERTEX,
+            (true, true) => &super::POS_NORM_TEX_SKIN_MORPH_UV_VERTEX,
+        })
+    }
     fn fragment_shader() -> &'static SpirvShader {
         &super::SHADED_FRAGMENT
     }
+    fn fragment_triplanar_shader() -> Option<&'static SpirvShader> {
+        Some(&super::SHADED_TRIPLANAR_FRAGMENT)
+    }
+    fn fragment_surface_layer_shader(triplanar: bool) -> Option<&'static SpirvShader> {
+        Some(if triplanar {
+            &super::SHADED_TRIPLANAR_LAYER_FRAGMENT
+        } else {
+            &super::SHADED_LAYER_FRAGMENT
+        })
+    }
+    fn fragment_subsurface_shader() -> Option<&'static SpirvShader> {
+        Some(&super::SHADED_SUBSURFACE_FRAGMENT)
+    }
+    fn fragment_early_depth_shader(
+        subsurface: bool,
+        triplanar: bool,
+        surface_layer: bool,
+    ) -> Option<&'static SpirvShader> {
+        Some(match (subsurface, triplanar, surface_layer) {
+            (true, _, _) => &super::SHADED_SUBSURFACE_EARLY_DEPTH_FRAGMENT,
+            (false, true, true) => &super::SHADED_TRIPLANAR_LAYER_EARLY_DEPTH_FRAGMENT,
+            (false, false, true) => &super::SHADED_LAYER_EARLY_DEPTH_FRAGMENT,
+            (false, true, false) => &super::SHADED_TRIPLANAR_EARLY_DEPTH_FRAGMENT,
+            (false, false, false) => &super::SHADED_EARLY_DEPTH_FRAGMENT,
+        })
+    }
+    fn fragment_source() -> Option<(&'static str, &'static str)> {
+        Some((
+            "shaded.frag",
+            include_str!("../../shaders/fragment/shaded.frag"),
+        ))
+    }
     fn base_format() -> Vec<VertexFormat> {
         vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()]
     }
@@ -44,3 +96,100 @@ pub type DrawShaded<B> = DrawBase3D<B, ShadedPassDef>;
 pub type DrawShadedTransparentDesc<B> = DrawBase3DTransparentDesc<B, ShadedPassDef>;
 /// Draws a simple shaded 3D pass with transparency
 pub type DrawShadedTransparent<B> = DrawBase3DTransparent<B, ShadedPassDef>;
+
+/// Implementation of `Base3DPassDef` describing a shaded 3D pass perturbing the normals of
+/// meshes by the normal map of their material, in the basis of their tangents.
+///
+/// Meshes must have tangents, see [crate::tangent]. The default normal map of materials is
+/// flat, so materials without normal map are shaded like in the simple shaded pass.
+#[derive(Debug)]
+pub struct ShadedNormalMappedPassDef;
+impl Base3DPassDef for ShadedNormalMappedPassDef {
+    const NAME: &'static str = "ShadedNormalMapped";
+    type TextureSet = (
+        TexAlbedo,
+        TexEmission,
+        TexDissolveNoise,
+        TexDetail,
+        TexLayerMask,
+        TexNormal,
+    );
+    fn vertex_shader() -> &'static SpirvShader {
+        &super::POS_NORM_TANG_TEX_VERTEX
+    }
+    fn vertex_skinned_shader() -> &'static SpirvShader {
+        &super::POS_NORM_TANG_TEX_SKIN_VERTEX
+    }
+    fn vertex_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TANG_TEX_MORPH_VERTEX)
+    }
+    fn vertex_skinned_morphed_shader() -> Option<&'static SpirvShader> {
+        Some(&super::POS_NORM_TANG_TEX_SKIN_MORPH_VERTEX)
+    }
+    fn vertex_uv_transform_shader(skinned: bool, morphed: bool) -> Option<&'static SpirvShader> {
+        Some(match (skinned, morphed) {
+            (false, false) => &super::POS_NORM_TANG_TEX_UV_VERTEX,
+            (true, false) => &super::POS_NORM_TANG_TEX_SKIN_UV_VERTEX,
+            (false, true) => &super::POS_NORM_TANG_TEX_MORPH_UV_VERTEX,
+            (true, true) => &super::POS_NORM_TANG_TEX_SKIN_MORPH_UV_VERTEX,
+        })
+    }
+    fn fragment_shader() -> &'static SpirvShader {
+        &super::SHADED_NORMAL_MAPPED_FRAGMENT
+    }
+    fn fragment_triplanar_shader() -> Option<&'static SpirvShader> {
+        Some(&super::SHADED_NORMAL_MAPPED_TRIPLANAR_FRAGMENT)
+    }
+    fn fragment_surface_layer_shader(triplanar: bool) -> Option<&'static SpirvShader> {
+        Some(if triplanar {
+            &super::SHADED_NORMAL_MAPPED_TRIPLANAR_LAYER_FRAGMENT
+        } else {
+            &super::SHADED_NORMAL_MAPPED_LAYER_FRAGMENT
+        })
+    }
+    fn fragment_subsurface_shader() -> Option<&'static SpirvShader> {
+        Some(&super::SHADED_NORMAL_MAPPED_SUBSURFACE_FRAGMENT)
+    }
+    fn fragment_early_depth_shader(
+        subsurface: bool,
+        triplanar: bool,
+        surface_layer: bool,
+    ) -> Option<&'static SpirvShader> {
+        Some(match (subsurface, triplanar, surface_layer) {
+            (true, _, _) => &super::SHADED_NORMAL_MAPPED_SUBSURFACE_EARLY_DEPTH_FRAGMENT,
+            (false, true, true) => {
+                &super::SHADED_NORMAL_MAPPED_TRIPLANAR_LAYER_EARLY_DEPTH_FRAGMENT
+            }
+            (false, false, true) => &super::SHADED_NORMAL_MAPPED_LAYER_EARLY_DEPTH_FRAGMENT,
+            (false, true, false) => &super::SHADED_NORMAL_MAPPED_TRIPLANAR_EARLY_DEPTH_FRAGMENT,
+            (false, false, false) => &super::SHADED_NORMAL_MAPPED_EARLY_DEPTH_FRAGMENT,
+        })
+    }
+    fn base_format() -> Vec<VertexFormat> {
+        vec![
+            Position::vertex(),
+            Normal::vertex(),
+            Tangent::vertex(),
+            TexCoord::vertex(),
+        ]
+    }
+    fn skinned_format() -> Vec<VertexFormat> {
+        vec![
+            Position::vertex(),
+            Normal::vertex(),
+            Tangent::vertex(),
+            TexCoord::vertex(),
+            JointCombined::vertex(),
+        ]
+    }
+}
+
+/// Describes a normal mapped shaded 3D pass.
+pub type DrawShadedNormalMappedDesc<B> = DrawBase3DDesc<B, ShadedNormalMappedPassDef>;
+/// Draws a normal mapped shaded 3D pass.
+pub type DrawShadedNormalMapped<B> = DrawBase3D<B, ShadedNormalMappedPassDef>;
+/// Describes a normal mapped shaded 3D pass with transparency
+pub type DrawShadedNormalMappedTransparentDesc<B> =
+    DrawBase3DTransparentDesc<B, ShadedNormalMappedPassDef>;
+/// Draws a normal mapped shaded 3D pass with transparency
+pub type DrawShadedNormalMappedTransparent<B> = DrawBase3DTransparent<B, ShadedNormalMappedPassDef>;
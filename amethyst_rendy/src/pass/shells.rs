@@ -0,0 +1,314 @@
+use crate::{
+    batch::{GroupIterator, TwoLevelBatch},
+    conventions::Conventions,
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::ShellArgs,
+    shells::Shells,
+    submodules::{DynamicVertexBuffer, EnvironmentSub, TextureId, TextureSub},
+    types::{Backend, Mesh},
+    util,
+    visibility::Visibility,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{Join, Read, ReadExpect, ReadStorage, SystemData, World},
+    transform::Transform,
+};
+use derivative::Derivative;
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, pso},
+    mesh::{AsVertex, Normal, Position, TexCoord, VertexFormat},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Draw the shells of visible entities with a [Shells] component, see [crate::shells].
+#[derive(Clone, Debug, PartialEq, Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct DrawShellsDesc;
+
+impl DrawShellsDesc {
+    /// Create instance of `DrawShells` render group
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawShellsDesc {
+    fn build(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        _images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let env = EnvironmentSub::new(
+            factory,
+            [
+                hal::pso::ShaderStageFlags::VERTEX,
+                hal::pso::ShaderStageFlags::FRAGMENT,
+            ],
+        )?;
+        let textures = TextureSub::new(factory)?;
+        let vertex_format = vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()];
+
+        let (pipeline, pipeline_layout) = build_shells_pipeline(
+            factory,
+            subpass,
+            Conventions::of(aux).rasterizer_front_face(),
+            framebuffer_width,
+            framebuffer_height,
+            &vertex_format,
+            vec![env.raw_layout(), textures.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawShells::<B> {
+            pipeline,
+            pipeline_layout,
+            env,
+            textures,
+            vertex_format,
+            shells: DynamicVertexBuffer::new(),
+            batches: Default::default(),
+        }))
+    }
+}
+
+/// Draws the shells of every entity with an instance per shell.
+#[derive(Debug)]
+pub struct DrawShells<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    env: EnvironmentSub<B>,
+    textures: TextureSub<B>,
+    vertex_format: Vec<VertexFormat>,
+    shells: DynamicVertexBuffer<B, ShellArgs>,
+    batches: TwoLevelBatch<TextureId, u32, Vec<ShellArgs>>,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawShells<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (visibility, mesh_storage, meshes, shells, transforms) = <(
+            ReadExpect<'_, Visibility>,
+            Read<'_, AssetStorage<Mesh>>,
+            ReadStorage<'_, Handle<Mesh>>,
+            ReadStorage<'_, Shells>,
+            ReadStorage<'_, Transform>,
+        )>::fetch(world);
+
+        self.env.process(factory, index, world);
+
+        let batches_ref = &mut self.batches;
+        let textures_ref = &mut self.textures;
+
+        batches_ref.clear_inner();
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("gather_shells");
+
+            (&meshes, &shells, &transforms, &visibility.visible_unordered)
+                .join()
+                .filter_map(|(mesh, shells, transform, _)| {
+                    if !mesh_storage.contains_id(mesh.id()) {
+                        return None;
+                    }
+                    let (tex_id, _) = textures_ref.insert(
+                        factory,
+                        world,
+                        &shells.density,
+                        hal::image::Layout::ShaderReadOnlyOptimal,
+                    )?;
+                    Some(((tex_id, mesh.id()), (shells, transform)))
+                })
+                .for_each_group(|(tex_id, mesh_id), data| {
+                    batches_ref.insert(
+                        tex_id,
+                        mesh_id,
+                        data.drain(..).flat_map(|(shells, transform)| {
+                            ShellArgs::from_object_data(shells, transform)
+                        }),
+                    )
+                });
+        }
+
+        self.textures.maintain(factory, world);
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("write");
+
+            batches_ref.prune();
+            self.shells.write(
+                factory,
+                index,
+                self.batches.count() as u64,
+                self.batches.data(),
+            );
+        }
+
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        let mesh_storage = <Read<'_, AssetStorage<Mesh>>>::fetch(world);
+        let layout = &self.pipeline_layout;
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.env.bind(index, layout, 0, &mut encoder);
+        if !self
+            .shells
+            .bind(index, self.vertex_format.len() as u32, 0, &mut encoder)
+        {
+            return;
+        }
+
+        let mut instances_drawn = 0;
+        for (&tex, batches) in self.batches.iter() {
+            let loaded = self.textures.loaded(tex);
+            if loaded {
+                self.textures.bind(layout, 1, tex, &mut encoder);
+            }
+            for (mesh_id, batch_data) in batches {
+                let instances = instances_drawn..instances_drawn + batch_data.len() as u32;
+                instances_drawn = instances.end;
+                if !loaded {
+                    continue;
+                }
+                debug_assert!(mesh_storage.contains_id(*mesh_id));
+                if let Some(mesh) =
+                    B::unwrap_mesh(unsafe { mesh_storage.get_by_id_unchecked(*mesh_id) })
+                {
+                    if let Err(error) =
+                        mesh.bind_and_draw(0, &self.vertex_format, instances, &mut encoder)
+                    {
+                        log::warn!("Mesh can't be drawn with shells: {}", error);
+                    }
+                }
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_shells_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    front_face: pso::FrontFace,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    vertex_format: &[VertexFormat],
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawShells",
+            [&super::SHELLS_VERTEX, &super::SHELLS_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let vertex_desc = vertex_format
+        .iter()
+        .map(|format| (format.clone(), pso::VertexInputRate::Vertex))
+        .chain(Some((
+            ShellArgs::vertex(),
+            pso::VertexInputRate::Instance(1),
+        )))
+        .collect::<Vec<_>>();
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_vertex_desc(&vertex_desc)
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_face_culling(pso::Face::BACK)
+                .with_front_face(front_face)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }])
+                .with_depth_test(pso::DepthTest {
+                    fun: pso::Comparison::Greater,
+                    write: true,
+                }),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawShells", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -1,12 +1,17 @@
 use crate::{
+    error::PassError,
     palette::Srgb,
     pipeline::{PipelineDescBuilder, PipelinesBuilder},
-    pod::IntoPod,
+    pod::{self, IntoPod},
     shape::Shape,
-    submodules::{DynamicUniform, FlatEnvironmentSub},
-    types::Backend,
+    submodules::{
+        gather::{CameraGatherer, FogGatherer},
+        DynamicUniform, FlatEnvironmentSub, TextureId, TextureSub,
+    },
+    types::{Backend, Texture},
     util,
 };
+use amethyst_assets::Handle;
 use amethyst_core::ecs::{Read, SystemData, World};
 use derivative::Derivative;
 use glsl_layout::{vec3, AsStd140};
@@ -19,16 +24,21 @@ use rendy::{
     },
     hal::{self, device::Device, pso},
     mesh::{AsVertex, Mesh, PosTex},
-    shader::Shader,
+    shader::SpirvShader,
 };
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
-#[derive(Clone, Debug, PartialEq)]
-struct SkyboxSettings {
-    nadir_color: Srgb,
-    zenith_color: Srgb,
+/// Resource overriding the colors of the skybox, the ones it was created with otherwise.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SkyboxSettings {
+    /// Color of the sky straight down.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub nadir_color: Srgb,
+    /// Color of the sky straight up.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub zenith_color: Srgb,
 }
 
 impl Default for SkyboxSettings {
@@ -40,12 +50,25 @@ impl Default for SkyboxSettings {
     }
 }
 
+/// Resource selecting the cubemap drawn as the skybox, overriding the one it was created with.
+///
+/// The texture must be a cubemap, see `formats::texture::CubemapFormat` to load one from the
+/// images of its faces. The gradient of the [SkyboxSettings] is drawn until it is loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkyboxCubemap(pub Handle<Texture>);
+
 #[derive(Clone, Debug, PartialEq, AsStd140)]
 pub(crate) struct SkyboxUniform {
     nadir_color: vec3,
     zenith_color: vec3,
 }
 
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct SkyboxFogUniform {
+    fog: pod::Fog,
+    camera_position: vec3,
+}
+
 impl SkyboxSettings {
     pub(crate) fn uniform(&self) -> <SkyboxUniform as AsStd140>::Std140 {
         SkyboxUniform {
@@ -57,10 +80,15 @@ impl SkyboxSettings {
 }
 
 /// Describe drawing a skybox around the camera view
+///
+/// The skybox is drawn at the far plane without writing depth, so it ends up behind the scene
+/// whether it is drawn before or after the opaque passes. It follows the rotation of the camera
+/// but not its position.
 #[derive(Clone, Debug, PartialEq, Derivative)]
 #[derivative(Default(bound = ""))]
 pub struct DrawSkyboxDesc {
     default_settings: SkyboxSettings,
+    cubemap: Option<Handle<Texture>>,
 }
 
 impl DrawSkyboxDesc {
@@ -76,8 +104,16 @@ impl DrawSkyboxDesc {
                 nadir_color,
                 zenith_color,
             },
+            cubemap: None,
         }
     }
+
+    /// Draw given cubemap instead of the gradient, unless a [SkyboxCubemap] resource overrides
+    /// it.
+    pub fn with_cubemap(mut self, cubemap: Handle<Texture>) -> Self {
+        self.cubemap = Some(cubemap);
+        self
+    }
 }
 
 impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
@@ -98,6 +134,8 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
 
         let env = FlatEnvironmentSub::new(factory)?;
         let colors = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        let fog = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+        let textures = TextureSub::new(factory)?;
         let mesh = Shape::Sphere(16, 16)
             .generate::<Vec<PosTex>>(None)
             .build(queue, factory)?;
@@ -107,16 +145,40 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
             subpass,
             framebuffer_width,
             framebuffer_height,
-            vec![env.raw_layout(), colors.raw_layout()],
+            &super::SKYBOX_FRAGMENT,
+            vec![env.raw_layout(), colors.raw_layout(), fog.raw_layout()],
         )?;
+        let (cubemap_pipeline, cubemap_pipeline_layout) = match build_skybox_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            &super::SKYBOX_CUBEMAP_FRAGMENT,
+            vec![env.raw_layout(), textures.raw_layout(), fog.raw_layout()],
+        ) {
+            Ok(built) => built,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_graphics_pipeline(pipeline);
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e);
+            }
+        };
 
         Ok(Box::new(DrawSkybox::<B> {
             pipeline,
             pipeline_layout,
+            cubemap_pipeline,
+            cubemap_pipeline_layout,
             env,
             colors,
+            fog,
+            textures,
+            cubemap: None,
             mesh,
             default_settings: self.default_settings,
+            default_cubemap: self.cubemap,
         }))
     }
 }
@@ -126,10 +188,16 @@ impl<B: Backend> RenderGroupDesc<B, World> for DrawSkyboxDesc {
 pub struct DrawSkybox<B: Backend> {
     pipeline: B::GraphicsPipeline,
     pipeline_layout: B::PipelineLayout,
+    cubemap_pipeline: B::GraphicsPipeline,
+    cubemap_pipeline_layout: B::PipelineLayout,
     env: FlatEnvironmentSub<B>,
     colors: DynamicUniform<B, SkyboxUniform>,
+    fog: DynamicUniform<B, SkyboxFogUniform>,
+    textures: TextureSub<B>,
+    cubemap: Option<TextureId>,
     mesh: Mesh<B>,
     default_settings: SkyboxSettings,
+    default_cubemap: Option<Handle<Texture>>,
 }
 
 impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
@@ -149,7 +217,30 @@ impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
             .unwrap_or_else(|| self.default_settings.uniform());
 
         self.env.process(factory, index, resources);
+        let fog = SkyboxFogUniform {
+            fog: FogGatherer::gather(resources),
+            camera_position: CameraGatherer::gather(resources).camera_position,
+        }
+        .std140();
         let changed = self.colors.write(factory, index, settings);
+        let changed = self.fog.write(factory, index, fog) || changed;
+
+        self.textures.maintain(factory, resources);
+        let handle = <Option<Read<'_, SkyboxCubemap>>>::fetch(resources)
+            .map(|c| c.0.clone())
+            .or_else(|| self.default_cubemap.clone());
+        let cubemap = handle.and_then(|handle| {
+            self.textures
+                .insert(
+                    factory,
+                    resources,
+                    &handle,
+                    hal::image::Layout::ShaderReadOnlyOptimal,
+                )
+                .map(|(id, _)| id)
+        });
+        let changed = changed || cubemap != self.cubemap;
+        self.cubemap = cubemap;
 
         if changed {
             PrepareResult::DrawRecord
@@ -167,10 +258,19 @@ impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("draw");
-        encoder.bind_graphics_pipeline(&self.pipeline);
-        self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
-        self.colors
-            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        if let Some(cubemap) = self.cubemap {
+            let layout = &self.cubemap_pipeline_layout;
+            encoder.bind_graphics_pipeline(&self.cubemap_pipeline);
+            self.env.bind(index, layout, 0, &mut encoder);
+            self.textures.bind(layout, 1, cubemap, &mut encoder);
+            self.fog.bind(index, layout, 2, &mut encoder);
+        } else {
+            encoder.bind_graphics_pipeline(&self.pipeline);
+            self.env.bind(index, &self.pipeline_layout, 0, &mut encoder);
+            self.colors
+                .bind(index, &self.pipeline_layout, 1, &mut encoder);
+            self.fog.bind(index, &self.pipeline_layout, 2, &mut encoder);
+        }
         self.mesh
             .bind(0, &[PosTex::vertex()], &mut encoder)
             .unwrap();
@@ -185,6 +285,12 @@ impl<B: Backend> RenderGroup<B, World> for DrawSkybox<B> {
             factory
                 .device()
                 .destroy_pipeline_layout(self.pipeline_layout);
+            factory
+                .device()
+                .destroy_graphics_pipeline(self.cubemap_pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.cubemap_pipeline_layout);
         }
     }
 }
@@ -194,6 +300,7 @@ fn build_skybox_pipeline<B: Backend>(
     subpass: hal::pass::Subpass<'_, B>,
     framebuffer_width: u32,
     framebuffer_height: u32,
+    fragment: &SpirvShader,
     layouts: Vec<&B::DescriptorSetLayout>,
 ) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
     let pipeline_layout = unsafe {
@@ -202,8 +309,17 @@ fn build_skybox_pipeline<B: Backend>(
             .create_pipeline_layout(layouts, None as Option<(_, _)>)
     }?;
 
-    let shader_vertex = unsafe { super::SKYBOX_VERTEX.module(factory).unwrap() };
-    let shader_fragment = unsafe { super::SKYBOX_FRAGMENT.module(factory).unwrap() };
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(factory, "DrawSkybox", [&super::SKYBOX_VERTEX, fragment])
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
 
     let pipes = PipelinesBuilder::new()
         .with_pipeline(
@@ -237,7 +353,7 @@ fn build_skybox_pipeline<B: Backend>(
             unsafe {
                 factory.device().destroy_pipeline_layout(pipeline_layout);
             }
-            Err(e)
+            Err(PassError::pipelines("DrawSkybox", e).into())
         }
         Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
     }
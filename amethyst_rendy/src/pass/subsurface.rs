@@ -0,0 +1,457 @@
+use crate::{
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    submodules::{gather::CameraGatherer, sampled_image_access, DynamicUniform, GraphImageSub},
+    types::Backend,
+    util,
+    view_mode::{active_view_mode, ViewMode},
+};
+use amethyst_core::{ecs::World, math::Matrix4};
+use glsl_layout::{float, int, mat4, vec2, vec4, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+    shader::SpirvShader,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Maximum number of taps of a `SubsurfaceProfile` kernel.
+pub const MAX_SUBSURFACE_TAPS: usize = 25;
+
+/// Per channel falloff of the scattering of the skin profile, red scatters the furthest.
+const SKIN_FALLOFF: [f32; 3] = [1.0, 0.37, 0.3];
+
+/// Separable kernel blurring the diffuse lighting of materials with a `subsurface` strength,
+/// see `RenderBase3D::with_subsurface`.
+///
+/// Every tap has a linear RGB weight and, in alpha, an offset from the center in fractions of
+/// the scattering width, between -1 and 1. The weights of every channel sum to 1, so the light
+/// is spread without being gained or lost.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubsurfaceProfile {
+    /// Distance the light scatters under the surface in world units, at full strength.
+    pub width: f32,
+    kernel: Vec<[f32; 4]>,
+}
+
+impl SubsurfaceProfile {
+    /// Human skin with 17 taps and a width of 1.2 centimeters for a meter per world unit.
+    ///
+    /// The kernel is sampled from the sum of gaussians fitted to the diffusion profile of skin
+    /// by d'Eon and Luebke, with its narrowest gaussian left out as it barely leaves the pixel.
+    pub fn skin() -> Self {
+        Self::from_kernel(0.012, skin_kernel(17, SKIN_FALLOFF))
+    }
+
+    /// Use a custom kernel, normalizing its weights. Taps past `MAX_SUBSURFACE_TAPS` are
+    /// dropped.
+    pub fn from_kernel(width: f32, mut kernel: Vec<[f32; 4]>) -> Self {
+        kernel.truncate(MAX_SUBSURFACE_TAPS);
+        for channel in 0..3 {
+            let sum = kernel.iter().map(|tap| tap[channel]).sum::<f32>();
+            if sum > 0.0 {
+                kernel.iter_mut().for_each(|tap| tap[channel] /= sum);
+            }
+        }
+        Self { width, kernel }
+    }
+
+    /// Taps of the kernel, see the [type documentation](SubsurfaceProfile).
+    pub fn kernel(&self) -> &[[f32; 4]] {
+        &self.kernel
+    }
+}
+
+impl Default for SubsurfaceProfile {
+    fn default() -> Self {
+        Self::skin()
+    }
+}
+
+/// Diffusion profile of skin at given distance in millimeters, per channel.
+fn skin_profile(distance: f32, falloff: [f32; 3]) -> [f32; 3] {
+    const GAUSSIANS: [(f32, f32); 5] = [
+        (0.100, 0.0484),
+        (0.118, 0.187),
+        (0.113, 0.567),
+        (0.358, 1.99),
+        (0.078, 7.41),
+    ];
+    let mut profile = [0.0; 3];
+    for (channel, value) in profile.iter_mut().enumerate() {
+        let r = distance / (0.001 + falloff[channel]);
+        *value = GAUSSIANS
+            .iter()
+            .map(|&(weight, variance)| {
+                weight * (-r * r / (2.0 * variance)).exp() / (2.0 * std::f32::consts::PI * variance)
+            })
+            .sum();
+    }
+    profile
+}
+
+/// Sample the skin profile at `taps` offsets, denser towards the center.
+fn skin_kernel(taps: usize, falloff: [f32; 3]) -> Vec<[f32; 4]> {
+    let range = if taps > 20 { 3.0 } else { 2.0 };
+    let step = 2.0 * range / (taps - 1) as f32;
+    let offsets = (0..taps)
+        .map(|tap| {
+            let offset = -range + tap as f32 * step;
+            offset * offset.abs() / range
+        })
+        .collect::<Vec<f32>>();
+
+    (0..taps)
+        .map(|tap| {
+            // Weight every tap by the area of the profile it covers.
+            let before = tap
+                .checked_sub(1)
+                .map_or(0.0, |prev| offsets[tap] - offsets[prev]);
+            let after = offsets.get(tap + 1).map_or(0.0, |next| next - offsets[tap]);
+            let area = (before + after) / 2.0;
+            let [r, g, b] = skin_profile(offsets[tap].abs(), falloff);
+            [area * r, area * g, area * b, offsets[tap] / range]
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct SubsurfaceUniform {
+    inverse_proj: mat4,
+    step_scale: vec2,
+    direction: vec2,
+    width: float,
+    taps: int,
+    kernel: [vec4; MAX_SUBSURFACE_TAPS],
+}
+
+/// Blur the diffuse lighting drawn by `DrawBase3DDesc::with_subsurface` along one axis with a
+/// `SubsurfaceProfile`, following the surface with its depth.
+///
+/// The horizontal blur expects the scattering input and its depth bound as the images of the
+/// group. The vertical blur composites onto the target, so the unblurred input follows them:
+/// it is subtracted from the target before the scattered light is added. Only pixels of
+/// materials with a `subsurface` strength are touched. The width is projected with the
+/// perspective of the `ActiveCamera`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawSubsurfaceDesc {
+    profile: SubsurfaceProfile,
+    composite: bool,
+    depth: bool,
+}
+
+impl DrawSubsurfaceDesc {
+    /// Create instance of the horizontal `DrawSubsurface` render group.
+    pub fn horizontal(profile: SubsurfaceProfile) -> Self {
+        Self {
+            profile,
+            composite: false,
+            depth: false,
+        }
+    }
+
+    /// Create instance of the vertical `DrawSubsurface` render group, compositing onto the
+    /// target.
+    pub fn composite(profile: SubsurfaceProfile) -> Self {
+        Self {
+            profile,
+            composite: true,
+            depth: false,
+        }
+    }
+
+    /// Set whether the target this group is added to has a depth output.
+    pub fn with_target_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawSubsurfaceDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        let count = if self.composite { 3 } else { 2 };
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER); count]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let images = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let (pipelines, pipeline_layout) = build_subsurface_pipelines(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            self.composite,
+            vec![images.raw_layout(), args.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawSubsurface::<B> {
+            pipelines,
+            pipeline_layout,
+            images,
+            args,
+            profile: self.profile,
+            composite: self.composite,
+            lit: true,
+            direction: if self.composite {
+                [0.0, 1.0]
+            } else {
+                [1.0, 0.0]
+            },
+        }))
+    }
+}
+
+/// Draws a subsurface scattering blur.
+#[derive(Debug)]
+pub struct DrawSubsurface<B: Backend> {
+    pipelines: Vec<B::GraphicsPipeline>,
+    pipeline_layout: B::PipelineLayout,
+    images: GraphImageSub<B>,
+    args: DynamicUniform<B, SubsurfaceUniform>,
+    profile: SubsurfaceProfile,
+    composite: bool,
+    lit: bool,
+    direction: [f32; 2],
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawSubsurface<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        // Other view modes don't draw the scattered lighting, keep it off the target.
+        let lit = !self.composite || active_view_mode(world).draws(ViewMode::Lit);
+        let toggled = lit != self.lit;
+        self.lit = lit;
+
+        let proj = CameraGatherer::gather_proj(world);
+        let inverse_proj: [[f32; 4]; 4] =
+            proj.try_inverse().unwrap_or_else(Matrix4::identity).into();
+
+        let mut kernel = [[0.0; 4].into(); MAX_SUBSURFACE_TAPS];
+        for (gathered, tap) in kernel.iter_mut().zip(self.profile.kernel()) {
+            *gathered = (*tap).into();
+        }
+
+        // Half of the width on screen per unit of view depth, in texture coordinates.
+        let half_width = 0.5 * self.profile.width;
+        let uniform = SubsurfaceUniform {
+            inverse_proj: inverse_proj.into(),
+            step_scale: [
+                half_width * proj[(0, 0)].abs(),
+                half_width * proj[(1, 1)].abs(),
+            ]
+            .into(),
+            direction: self.direction.into(),
+            width: self.profile.width,
+            taps: self.profile.kernel().len() as i32,
+            kernel,
+        }
+        .std140();
+
+        if self.args.write(factory, index, uniform) || toggled {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if !self.lit {
+            return;
+        }
+
+        for pipeline in &self.pipelines {
+            encoder.bind_graphics_pipeline(pipeline);
+            self.images.bind(&self.pipeline_layout, 0, &mut encoder);
+            self.args
+                .bind(index, &self.pipeline_layout, 1, &mut encoder);
+            unsafe {
+                encoder.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            for pipeline in self.pipelines {
+                factory.device().destroy_graphics_pipeline(pipeline);
+            }
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Build the blur pipeline, preceded by the removal of the unblurred input when compositing.
+fn build_subsurface_pipelines<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    composite: bool,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(Vec<B::GraphicsPipeline>, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let keep_alpha = pso::BlendOp::Add {
+        src: pso::Factor::Zero,
+        dst: pso::Factor::One,
+    };
+    let passes: Vec<(&SpirvShader, Option<pso::BlendState>)> = if composite {
+        vec![
+            (
+                &super::SUBSURFACE_SOURCE_FRAGMENT,
+                Some(pso::BlendState {
+                    color: pso::BlendOp::RevSub {
+                        src: pso::Factor::One,
+                        dst: pso::Factor::One,
+                    },
+                    alpha: keep_alpha,
+                }),
+            ),
+            (
+                &super::SUBSURFACE_FRAGMENT,
+                Some(pso::BlendState {
+                    color: pso::BlendOp::ADD,
+                    alpha: keep_alpha,
+                }),
+            ),
+        ]
+    } else {
+        vec![(&super::SUBSURFACE_FRAGMENT, None)]
+    };
+
+    let shaders = std::iter::once(&*super::FULLSCREEN_VERTEX)
+        .chain(passes.iter().map(|&(shader, _)| shader))
+        .collect::<Vec<_>>();
+    let mut shader_fragments =
+        match unsafe { util::shader_module_vec(factory, "DrawSubsurface", &shaders) } {
+            Ok(modules) => modules,
+            Err(e) => {
+                unsafe {
+                    factory.device().destroy_pipeline_layout(pipeline_layout);
+                }
+                return Err(e.into());
+            }
+        };
+    let shader_vertex = shader_fragments.remove(0);
+
+    let mut builder = PipelinesBuilder::new();
+    for (shader_fragment, &(_, blend)) in shader_fragments.iter().zip(&passes) {
+        builder = builder.with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend,
+                }]),
+        );
+    }
+    let pipes = builder.build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        for shader_fragment in shader_fragments {
+            factory.destroy_shader_module(shader_fragment);
+        }
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawSubsurface", e).into())
+        }
+        Ok(pipes) => Ok((pipes, pipeline_layout)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skin_kernel_keeps_the_light() {
+        let profile = SubsurfaceProfile::skin();
+        let kernel = profile.kernel();
+        assert_eq!(kernel.len(), 17);
+
+        for channel in 0..3 {
+            let sum = kernel.iter().map(|tap| tap[channel]).sum::<f32>();
+            assert!((sum - 1.0).abs() < 1.0e-5);
+        }
+        // Symmetric around the center tap, spanning the whole width.
+        assert_eq!(kernel[8][3], 0.0);
+        assert_eq!(kernel[0][3], -1.0);
+        assert!((kernel[16][3] - 1.0).abs() < 1.0e-6);
+        for (low, high) in kernel.iter().zip(kernel.iter().rev()) {
+            assert!((low[3] + high[3]).abs() < 1.0e-6);
+            assert!((low[0] - high[0]).abs() < 1.0e-6);
+        }
+        // Red scatters further than blue.
+        assert!(kernel[4][0] > kernel[4][2]);
+        assert!(kernel[8][0] < kernel[8][2]);
+    }
+}
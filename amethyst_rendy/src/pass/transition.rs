@@ -0,0 +1,371 @@
+use super::FullscreenPipeline;
+use crate::{
+    submodules::{sampled_image_access, DynamicUniform, GraphImageSub},
+    transition::{Transition, TransitionPattern},
+    types::Backend,
+};
+use amethyst_core::ecs::{SystemData, World, Write};
+use glsl_layout::{float, int, vec2, vec3, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct TransitionUniform {
+    direction: vec2,
+    progress: float,
+    pattern: int,
+    edge_color: vec3,
+    edge_width: float,
+    cell_size: float,
+}
+
+impl TransitionPattern {
+    pub(crate) fn uniform(&self, progress: f32) -> TransitionUniform {
+        let mut uniform = TransitionUniform {
+            direction: [1.0, 0.0].into(),
+            progress,
+            pattern: 0,
+            edge_color: [0.0; 3].into(),
+            edge_width: 0.0,
+            cell_size: 1.0,
+        };
+        match *self {
+            TransitionPattern::Fade => {}
+            TransitionPattern::Wipe(direction) => {
+                uniform.pattern = 1;
+                if let Some(direction) = direction.try_normalize(f32::EPSILON) {
+                    uniform.direction = [direction.x, direction.y].into();
+                }
+            }
+            TransitionPattern::Dissolve {
+                cell_size,
+                edge_width,
+                edge_color,
+            } => {
+                uniform.pattern = 2;
+                uniform.edge_color = edge_color.into();
+                uniform.edge_width = edge_width.max(0.0);
+                uniform.cell_size = cell_size.max(1.0);
+            }
+        }
+        uniform
+    }
+}
+
+/// Composite the frame captured at the beginning of the scene [Transition] over the target,
+/// from the capture bound as the only image of the group. Only draws while a transition is in
+/// progress.
+///
+/// The capture must match the size of the target. It is captured by [DrawTransitionCaptureDesc]
+/// after this group is drawn, so this group must not be drawn again on the target after the
+/// capture.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawTransitionDesc {
+    depth: bool,
+}
+
+impl DrawTransitionDesc {
+    /// Create instance of `DrawTransition` render group.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set whether the target this group is added to has a depth output.
+    pub fn with_target_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawTransitionDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let captured = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let (pipeline, pipeline_layout) =
+            FullscreenPipeline::new("DrawTransition", &super::TRANSITION_FRAGMENT)
+                .with_blend(Some(pso::BlendState::ALPHA))
+                .build(
+                    factory,
+                    subpass,
+                    framebuffer_width,
+                    framebuffer_height,
+                    vec![captured.raw_layout(), args.raw_layout()],
+                )?;
+
+        Ok(Box::new(DrawTransition::<B> {
+            pipeline,
+            pipeline_layout,
+            captured,
+            args,
+            size: (framebuffer_width, framebuffer_height),
+            built: true,
+            enabled: false,
+        }))
+    }
+}
+
+/// Draws the capture of a scene transition.
+#[derive(Debug)]
+pub struct DrawTransition<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    captured: GraphImageSub<B>,
+    args: DynamicUniform<B, TransitionUniform>,
+    size: (u32, u32),
+    built: bool,
+    enabled: bool,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawTransition<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let composite = <Option<Write<'_, Transition>>>::fetch(world).and_then(|mut transition| {
+            // A transition running when the group is built was captured in the images of the
+            // previous graph.
+            if std::mem::replace(&mut self.built, false) && transition.progress().is_some() {
+                transition.abort();
+            }
+            transition.advance(self.size)
+        });
+        let toggled = composite.is_some() != self.enabled;
+        self.enabled = composite.is_some();
+
+        if let Some((pattern, progress)) = composite {
+            self.args
+                .write(factory, index, pattern.uniform(progress).std140());
+            PrepareResult::DrawRecord
+        } else if toggled {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if !self.enabled {
+            return;
+        }
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.captured.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+/// Copy the image bound as the only image of the group to the target when the scene
+/// [Transition] requests a capture, keeping the previous contents of the target otherwise.
+///
+/// The target must be an image without clear value and depth, matching the size of the image,
+/// which is then composited with [DrawTransitionDesc].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawTransitionCaptureDesc;
+
+impl DrawTransitionCaptureDesc {
+    /// Create instance of `DrawTransitionCapture` render group.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawTransitionCaptureDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        false
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let source = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+
+        let (pipeline, pipeline_layout) =
+            FullscreenPipeline::new("DrawTransitionCapture", &super::BLIT_FRAGMENT).build(
+                factory,
+                subpass,
+                framebuffer_width,
+                framebuffer_height,
+                vec![source.raw_layout()],
+            )?;
+
+        Ok(Box::new(DrawTransitionCapture::<B> {
+            pipeline,
+            pipeline_layout,
+            source,
+            size: (framebuffer_width, framebuffer_height),
+            capturing: false,
+        }))
+    }
+}
+
+/// Captures the frame a scene transition begins with.
+#[derive(Debug)]
+pub struct DrawTransitionCapture<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    source: GraphImageSub<B>,
+    size: (u32, u32),
+    capturing: bool,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawTransitionCapture<B> {
+    fn prepare(
+        &mut self,
+        _factory: &Factory<B>,
+        _queue: QueueId,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        let capturing = <Option<Write<'_, Transition>>>::fetch(world)
+            .is_some_and(|mut transition| transition.capture(self.size));
+        let toggled = capturing != self.capturing;
+        self.capturing = capturing;
+        if toggled {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        _index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        if !self.capturing {
+            return;
+        }
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.source.bind(&self.pipeline_layout, 0, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::Vector2;
+
+    #[test]
+    fn wipe_directions_are_normalized() {
+        let uniform = TransitionPattern::Wipe(Vector2::new(0.0, -3.0)).uniform(0.5);
+        assert_eq!((uniform.pattern, uniform.progress), (1, 0.5));
+        let direction: &[f32; 2] = uniform.direction.as_ref();
+        assert_eq!(direction, &[0.0, -1.0]);
+
+        // Without direction, the wipe goes right.
+        let uniform = TransitionPattern::Wipe(Vector2::zeros()).uniform(0.5);
+        let direction: &[f32; 2] = uniform.direction.as_ref();
+        assert_eq!(direction, &[1.0, 0.0]);
+
+        let uniform = TransitionPattern::dissolve().uniform(0.25);
+        assert_eq!(uniform.pattern, 2);
+        assert_eq!(uniform.cell_size, 16.0);
+    }
+}
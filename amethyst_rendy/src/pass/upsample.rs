@@ -0,0 +1,316 @@
+use crate::{
+    error::PassError,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    submodules::{sampled_image_access, DynamicUniform, GraphImageSub},
+    types::Backend,
+    util,
+};
+use amethyst_core::ecs::{Read, SystemData, World};
+use glsl_layout::{float, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Parameters of the bilateral upsample.
+///
+/// Add as a resource to override the settings the render group was created with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BilateralUpsampleSettings {
+    /// Relative difference in view distance at which a half resolution sample loses most of its
+    /// weight. Lower values keep effect edges tighter to geometry, but make them more aliased.
+    pub depth_sigma: f32,
+    /// Exponent applied to the cosine between the full and half resolution normals. Only used
+    /// when the render group is created with normals.
+    pub normal_power: f32,
+}
+
+impl Default for BilateralUpsampleSettings {
+    fn default() -> Self {
+        Self {
+            depth_sigma: 0.05,
+            normal_power: 8.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, AsStd140)]
+pub(crate) struct BilateralUpsampleUniform {
+    depth_sigma: float,
+    normal_power: float,
+}
+
+impl BilateralUpsampleSettings {
+    pub(crate) fn uniform(&self) -> <BilateralUpsampleUniform as AsStd140>::Std140 {
+        BilateralUpsampleUniform {
+            depth_sigma: self.depth_sigma.max(1.0e-4),
+            normal_power: self.normal_power,
+        }
+        .std140()
+    }
+}
+
+/// How the upsampled effect is combined with the target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpsampleBlend {
+    /// Overwrite the target.
+    Replace,
+    /// Multiply the target, e.g. for ambient occlusion.
+    Multiply,
+    /// Add to the target, e.g. for volumetric light.
+    Add,
+    /// Blend over the target using the effect alpha, e.g. for reflections.
+    Alpha,
+}
+
+impl UpsampleBlend {
+    fn state(self) -> Option<pso::BlendState> {
+        match self {
+            UpsampleBlend::Replace => None,
+            UpsampleBlend::Multiply => Some(pso::BlendState::MULTIPLY),
+            UpsampleBlend::Add => Some(pso::BlendState::ADD),
+            UpsampleBlend::Alpha => Some(pso::BlendState::ALPHA),
+        }
+    }
+}
+
+/// Upsample a half resolution effect to the full resolution target, weighting the samples by
+/// depth similarity so that effect edges stay on geometry edges.
+///
+/// Expects the images bound in order: the half resolution effect, the full resolution depth
+/// and the half resolution depth, followed by the full and half resolution normals when
+/// created `with_normals`. Normals are sampled from the `rgb` channels, encoded as
+/// `normal * 0.5 + 0.5`. The full resolution depth must match the size of the target.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawBilateralUpsampleDesc {
+    settings: BilateralUpsampleSettings,
+    blend: UpsampleBlend,
+    normals: bool,
+    depth: bool,
+}
+
+impl DrawBilateralUpsampleDesc {
+    /// Create instance of `DrawBilateralUpsample` render group combining the effect with
+    /// given blend mode.
+    pub fn new(blend: UpsampleBlend) -> Self {
+        Self {
+            settings: Default::default(),
+            blend,
+            normals: false,
+            depth: true,
+        }
+    }
+
+    /// Set the default upsample settings.
+    pub fn with_settings(mut self, settings: BilateralUpsampleSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Also weight the samples by normal similarity.
+    pub fn with_normals(mut self) -> Self {
+        self.normals = true;
+        self
+    }
+
+    /// Set whether the target this group is added to has a depth output.
+    pub fn with_target_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawBilateralUpsampleDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        let count = if self.normals { 5 } else { 3 };
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER); count]
+    }
+
+    fn depth(&self) -> bool {
+        self.depth
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let inputs = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let (pipeline, pipeline_layout) = build_upsample_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![inputs.raw_layout(), args.raw_layout()],
+            self.normals,
+            self.blend.state(),
+        )?;
+
+        Ok(Box::new(DrawBilateralUpsample::<B> {
+            pipeline,
+            pipeline_layout,
+            inputs,
+            args,
+            default_settings: self.settings,
+        }))
+    }
+}
+
+/// Draws a half resolution effect upsampled to the full resolution target.
+#[derive(Debug)]
+pub struct DrawBilateralUpsample<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    inputs: GraphImageSub<B>,
+    args: DynamicUniform<B, BilateralUpsampleUniform>,
+    default_settings: BilateralUpsampleSettings,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawBilateralUpsample<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let settings = <Option<Read<'_, BilateralUpsampleSettings>>>::fetch(world)
+            .map(|s| s.uniform())
+            .unwrap_or_else(|| self.default_settings.uniform());
+
+        if self.args.write(factory, index, settings) {
+            PrepareResult::DrawRecord
+        } else {
+            PrepareResult::DrawReuse
+        }
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.inputs.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn build_upsample_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+    normals: bool,
+    blend: Option<pso::BlendState>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let fragment: &rendy::shader::SpirvShader = if normals {
+        &super::BILATERAL_UPSAMPLE_NORMALS_FRAGMENT
+    } else {
+        &super::BILATERAL_UPSAMPLE_FRAGMENT
+    };
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawBilateralUpsample",
+            [&super::FULLSCREEN_VERTEX, fragment],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawBilateralUpsample", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -0,0 +1,148 @@
+use super::base_3d::*;
+use crate::{mtl::TexAlbedo, skinning::JointCombined};
+use rendy::{
+    hal::pso,
+    mesh::{AsVertex, Normal, Position, TexCoord, VertexFormat},
+    shader::SpirvShader,
+};
+
+/// Defines a `Base3DPassDef` drawing a debug view of the scene with given fragment shader, on
+/// top of the vertex shaders of the shaded pass.
+macro_rules! view_mode_pass_def {
+    ($(#[$meta:meta])* $def:ident, $name:literal, $fragment:ident $(, $item:item)*) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $def;
+        impl Base3DPassDef for $def {
+            const NAME: &'static str = $name;
+            type TextureSet = TexAlbedo;
+            fn vertex_shader() -> &'static SpirvShader {
+                &super::POS_NORM_TEX_VERTEX
+            }
+            fn vertex_skinned_shader() -> &'static SpirvShader {
+                &super::POS_NORM_TEX_SKIN_VERTEX
+            }
+            fn vertex_morphed_shader() -> Option<&'static SpirvShader> {
+                Some(&super::POS_NORM_TEX_MORPH_VERTEX)
+            }
+            fn vertex_skinned_morphed_shader() -> Option<&'static SpirvShader> {
+                Some(&super::POS_NORM_TEX_SKIN_MORPH_VERTEX)
+            }
+            fn fragment_shader() -> &'static SpirvShader {
+                &super::$fragment
+            }
+            fn base_format() -> Vec<VertexFormat> {
+                vec![Position::vertex(), Normal::vertex(), TexCoord::vertex()]
+            }
+            fn skinned_format() -> Vec<VertexFormat> {
+                vec![
+                    Position::vertex(),
+                    Normal::vertex(),
+                    TexCoord::vertex(),
+                    JointCombined::vertex(),
+                ]
+            }
+            $($item)*
+        }
+    };
+}
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` drawing the edges of the triangles, for
+    /// `ViewMode::Wireframe`.
+    ViewWireframePassDef,
+    "View wireframe",
+    VIEW_MODE_WIREFRAME_FRAGMENT,
+    fn polygon_mode() -> pso::PolygonMode {
+        pso::PolygonMode::Line(pso::State::Static(1.0))
+    }
+);
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` drawing the visible edges of the triangles over the
+    /// lit scene, for `ViewMode::WireframeOverlay`.
+    ViewWireframeOverlayPassDef,
+    "View wireframe overlay",
+    VIEW_MODE_WIREFRAME_FRAGMENT,
+    fn polygon_mode() -> pso::PolygonMode {
+        pso::PolygonMode::Line(pso::State::Static(1.0))
+    },
+    fn overlay() -> bool {
+        true
+    }
+);
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` drawing the world space normals, for
+    /// `ViewMode::Normals`.
+    ViewNormalsPassDef,
+    "View normals",
+    VIEW_MODE_NORMALS_FRAGMENT
+);
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` adding up the fragments drawn on every pixel, for
+    /// `ViewMode::Overdraw`.
+    ViewOverdrawPassDef,
+    "View overdraw",
+    VIEW_MODE_OVERDRAW_FRAGMENT,
+    fn additive() -> bool {
+        true
+    }
+);
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` drawing the number of lights reaching every surface,
+    /// for `ViewMode::LightComplexity`.
+    ViewLightComplexityPassDef,
+    "View light complexity",
+    VIEW_MODE_LIGHT_COMPLEXITY_FRAGMENT
+);
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` drawing the distance to the camera, for
+    /// `ViewMode::Depth`.
+    ViewDepthPassDef,
+    "View depth",
+    VIEW_MODE_DEPTH_FRAGMENT
+);
+
+view_mode_pass_def!(
+    /// Implementation of `Base3DPassDef` drawing the surfaces covered by the shadow map and
+    /// their shadows, for `ViewMode::ShadowCascades`.
+    ViewShadowCascadesPassDef,
+    "View shadow cascades",
+    VIEW_MODE_SHADOW_CASCADES_FRAGMENT
+);
+
+/// Describes a wireframe view of the scene.
+pub type DrawViewWireframeDesc<B> = DrawBase3DDesc<B, ViewWireframePassDef>;
+/// Describes a wireframe view of the transparent objects of the scene.
+pub type DrawViewWireframeTransparentDesc<B> = DrawBase3DTransparentDesc<B, ViewWireframePassDef>;
+/// Describes the edges of the scene drawn over it.
+pub type DrawViewWireframeOverlayDesc<B> = DrawBase3DDesc<B, ViewWireframeOverlayPassDef>;
+/// Describes the edges of the transparent objects of the scene drawn over them.
+pub type DrawViewWireframeOverlayTransparentDesc<B> =
+    DrawBase3DTransparentDesc<B, ViewWireframeOverlayPassDef>;
+/// Describes a view of the normals of the scene.
+pub type DrawViewNormalsDesc<B> = DrawBase3DDesc<B, ViewNormalsPassDef>;
+/// Describes a view of the normals of the transparent objects of the scene.
+pub type DrawViewNormalsTransparentDesc<B> = DrawBase3DTransparentDesc<B, ViewNormalsPassDef>;
+/// Describes a view of the overdraw of the scene.
+pub type DrawViewOverdrawDesc<B> = DrawBase3DDesc<B, ViewOverdrawPassDef>;
+/// Describes a view of the overdraw of the transparent objects of the scene.
+pub type DrawViewOverdrawTransparentDesc<B> = DrawBase3DTransparentDesc<B, ViewOverdrawPassDef>;
+/// Describes a view of the light complexity of the scene.
+pub type DrawViewLightComplexityDesc<B> = DrawBase3DDesc<B, ViewLightComplexityPassDef>;
+/// Describes a view of the light complexity of the transparent objects of the scene.
+pub type DrawViewLightComplexityTransparentDesc<B> =
+    DrawBase3DTransparentDesc<B, ViewLightComplexityPassDef>;
+/// Describes a view of the depth of the scene.
+pub type DrawViewDepthDesc<B> = DrawBase3DDesc<B, ViewDepthPassDef>;
+/// Describes a view of the depth of the transparent objects of the scene.
+pub type DrawViewDepthTransparentDesc<B> = DrawBase3DTransparentDesc<B, ViewDepthPassDef>;
+/// Describes a view of the shadow map coverage of the scene.
+pub type DrawViewShadowCascadesDesc<B> = DrawBase3DDesc<B, ViewShadowCascadesPassDef>;
+/// Describes a view of the shadow map coverage of the transparent objects of the scene.
+pub type DrawViewShadowCascadesTransparentDesc<B> =
+    DrawBase3DTransparentDesc<B, ViewShadowCascadesPassDef>;
@@ -0,0 +1,358 @@
+use crate::{
+    determinism::RenderDeterminism,
+    error::PassError,
+    light::Light,
+    pipeline::{PipelineDescBuilder, PipelinesBuilder},
+    pod::IntoPod,
+    submodules::{gather::CameraGatherer, sampled_image_access, DynamicUniform, GraphImageSub},
+    types::Backend,
+    util,
+};
+use amethyst_core::{
+    ecs::{Join, Read, ReadStorage, SystemData, World},
+    math::{convert, Vector3},
+    timing::Time,
+    transform::Transform,
+};
+use glsl_layout::{float, int, mat4, uint, vec3, AsStd140};
+use rendy::{
+    command::{QueueId, RenderPassEncoder},
+    factory::Factory,
+    graph::{
+        render::{PrepareResult, RenderGroup, RenderGroupDesc},
+        GraphContext, ImageAccess, NodeBuffer, NodeImage,
+    },
+    hal::{self, device::Device, image::Filter, pso},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Maximum number of lights scattered by the volumetric pass. Further lights are ignored.
+pub const MAX_VOLUMETRIC_LIGHTS: usize = 8;
+
+/// Parameters of the participating medium the volumetric pass marches through.
+///
+/// Add as a resource to override the settings the render group was created with.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumetricSettings {
+    /// Number of samples along every ray.
+    pub steps: u32,
+    /// Scattering coefficient of the medium at or below `base_height`.
+    pub density: f32,
+    /// Henyey-Greenstein asymmetry factor `g`, between -1 and 1. Positive values scatter
+    /// more light forward, making shafts brighter when looking towards the light.
+    pub anisotropy: f32,
+    /// Rate at which the density decreases above `base_height`. Zero gives a constant medium.
+    pub height_falloff: f32,
+    /// Height below which the medium has full density.
+    pub base_height: f32,
+    /// Rays are not marched further than this distance from the camera.
+    pub max_distance: f32,
+}
+
+impl Default for VolumetricSettings {
+    fn default() -> Self {
+        Self {
+            steps: 32,
+            density: 0.02,
+            anisotropy: 0.3,
+            height_falloff: 0.0,
+            base_height: 0.0,
+            max_distance: 100.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct VolumetricLight {
+    position: vec3,
+    range: float,
+    color: vec3,
+    cos_angle: float,
+    direction: vec3,
+    smoothness: float,
+}
+
+impl Default for VolumetricLight {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3].into(),
+            range: 0.0,
+            color: [0.0; 3].into(),
+            cos_angle: -1.0,
+            direction: [0.0, -1.0, 0.0].into(),
+            smoothness: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub(crate) struct VolumetricUniform {
+    inverse_proj_view: mat4,
+    camera_position: vec3,
+    steps: int,
+    density: float,
+    anisotropy: float,
+    height_falloff: float,
+    base_height: float,
+    max_distance: float,
+    frame: uint,
+    light_count: int,
+    lights: [VolumetricLight; MAX_VOLUMETRIC_LIGHTS],
+}
+
+/// Ray march in-scattering of point and spot lights marked `volumetric` through a participating
+/// medium, up to the scene depth.
+///
+/// Meant to be rendered at half resolution and composited with `DrawBilateralUpsampleDesc`.
+/// Expects the scene depth of the same size as the target bound as the only image of the group.
+/// Lights don't cast shadows yet, so shafts are not occluded by geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawVolumetricsDesc {
+    settings: VolumetricSettings,
+}
+
+impl DrawVolumetricsDesc {
+    /// Create instance of `DrawVolumetrics` render group with given default settings.
+    pub fn new(settings: VolumetricSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl<B: Backend> RenderGroupDesc<B, World> for DrawVolumetricsDesc {
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![sampled_image_access(pso::PipelineStage::FRAGMENT_SHADER)]
+    }
+
+    fn depth(&self) -> bool {
+        false
+    }
+
+    fn build(
+        self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        _queue: QueueId,
+        _aux: &World,
+        framebuffer_width: u32,
+        framebuffer_height: u32,
+        subpass: hal::pass::Subpass<'_, B>,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Box<dyn RenderGroup<B, World>>, failure::Error> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("build");
+
+        let depth = GraphImageSub::new(
+            ctx,
+            factory,
+            &images,
+            Filter::Nearest,
+            pso::ShaderStageFlags::FRAGMENT,
+        )?;
+        let args = DynamicUniform::new(factory, pso::ShaderStageFlags::FRAGMENT)?;
+
+        let (pipeline, pipeline_layout) = build_volumetric_pipeline(
+            factory,
+            subpass,
+            framebuffer_width,
+            framebuffer_height,
+            vec![depth.raw_layout(), args.raw_layout()],
+        )?;
+
+        Ok(Box::new(DrawVolumetrics::<B> {
+            pipeline,
+            pipeline_layout,
+            depth,
+            args,
+            default_settings: self.settings,
+        }))
+    }
+}
+
+/// Draws volumetric light scattering.
+#[derive(Debug)]
+pub struct DrawVolumetrics<B: Backend> {
+    pipeline: B::GraphicsPipeline,
+    pipeline_layout: B::PipelineLayout,
+    depth: GraphImageSub<B>,
+    args: DynamicUniform<B, VolumetricUniform>,
+    default_settings: VolumetricSettings,
+}
+
+impl<B: Backend> RenderGroup<B, World> for DrawVolumetrics<B> {
+    fn prepare(
+        &mut self,
+        factory: &Factory<B>,
+        _queue: QueueId,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        world: &World,
+    ) -> PrepareResult {
+        #[cfg(feature = "profiler")]
+        profile_scope!("prepare");
+
+        let (settings, time, determinism, transforms, lights) = <(
+            Option<Read<'_, VolumetricSettings>>,
+            Read<'_, Time>,
+            Option<Read<'_, RenderDeterminism>>,
+            ReadStorage<'_, Transform>,
+            ReadStorage<'_, Light>,
+        )>::fetch(world);
+        let settings = settings.as_deref().unwrap_or(&self.default_settings);
+
+        let camera = CameraGatherer::gather(world);
+        let inverse_proj_view = CameraGatherer::gather_inverse_proj_view(world);
+
+        let mut gathered = [VolumetricLight::default(); MAX_VOLUMETRIC_LIGHTS];
+        let mut light_count = 0;
+        for (light, transform) in (&lights, &transforms).join() {
+            if light_count == MAX_VOLUMETRIC_LIGHTS {
+                break;
+            }
+            let position: vec3 =
+                convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz()).into_pod();
+            gathered[light_count] = match light {
+                Light::Point(light) if light.volumetric => VolumetricLight {
+                    position,
+                    range: light.radius,
+                    color: scaled_color(light.color, light.intensity),
+                    ..Default::default()
+                },
+                Light::Spot(light) if light.volumetric => VolumetricLight {
+                    position,
+                    range: light.range,
+                    color: scaled_color(light.color, light.intensity),
+                    cos_angle: light.angle.cos(),
+                    direction: light.direction.into_pod(),
+                    smoothness: light.smoothness,
+                },
+                _ => continue,
+            };
+            light_count += 1;
+        }
+
+        let matrix: [[f32; 4]; 4] = inverse_proj_view.into();
+        let uniform = VolumetricUniform {
+            inverse_proj_view: matrix.into(),
+            camera_position: camera.camera_position,
+            steps: settings.steps as i32,
+            density: settings.density,
+            anisotropy: settings.anisotropy.clamp(-0.99, 0.99),
+            height_falloff: settings.height_falloff,
+            base_height: settings.base_height,
+            max_distance: settings.max_distance,
+            frame: determinism
+                .and_then(|d| d.frame_seed(time.frame_number()))
+                .unwrap_or(time.frame_number() as u32),
+            light_count: light_count as i32,
+            lights: gathered,
+        }
+        .std140();
+
+        self.args.write(factory, index, uniform);
+        PrepareResult::DrawRecord
+    }
+
+    fn draw_inline(
+        &mut self,
+        mut encoder: RenderPassEncoder<'_, B>,
+        index: usize,
+        _subpass: hal::pass::Subpass<'_, B>,
+        _world: &World,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("draw");
+
+        encoder.bind_graphics_pipeline(&self.pipeline);
+        self.depth.bind(&self.pipeline_layout, 0, &mut encoder);
+        self.args
+            .bind(index, &self.pipeline_layout, 1, &mut encoder);
+        unsafe {
+            encoder.draw(0..3, 0..1);
+        }
+    }
+
+    fn dispose(self: Box<Self>, factory: &mut Factory<B>, _world: &World) {
+        unsafe {
+            factory.device().destroy_graphics_pipeline(self.pipeline);
+            factory
+                .device()
+                .destroy_pipeline_layout(self.pipeline_layout);
+        }
+    }
+}
+
+fn scaled_color(color: palette::Srgb, intensity: f32) -> vec3 {
+    [
+        color.red * intensity,
+        color.green * intensity,
+        color.blue * intensity,
+    ]
+    .into()
+}
+
+fn build_volumetric_pipeline<B: Backend>(
+    factory: &Factory<B>,
+    subpass: hal::pass::Subpass<'_, B>,
+    framebuffer_width: u32,
+    framebuffer_height: u32,
+    layouts: Vec<&B::DescriptorSetLayout>,
+) -> Result<(B::GraphicsPipeline, B::PipelineLayout), failure::Error> {
+    let pipeline_layout = unsafe {
+        factory
+            .device()
+            .create_pipeline_layout(layouts, None as Option<(_, _)>)
+    }?;
+
+    let [shader_vertex, shader_fragment] = match unsafe {
+        util::shader_modules(
+            factory,
+            "DrawVolumetrics",
+            [&super::FULLSCREEN_VERTEX, &super::VOLUMETRIC_FRAGMENT],
+        )
+    } {
+        Ok(modules) => modules,
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            return Err(e.into());
+        }
+    };
+
+    let pipes = PipelinesBuilder::new()
+        .with_pipeline(
+            PipelineDescBuilder::new()
+                .with_shaders(util::simple_shader_set(
+                    &shader_vertex,
+                    Some(&shader_fragment),
+                ))
+                .with_layout(&pipeline_layout)
+                .with_subpass(subpass)
+                .with_framebuffer_size(framebuffer_width, framebuffer_height)
+                .with_blend_targets(vec![pso::ColorBlendDesc {
+                    mask: pso::ColorMask::ALL,
+                    blend: None,
+                }]),
+        )
+        .build(factory, None);
+
+    unsafe {
+        factory.destroy_shader_module(shader_vertex);
+        factory.destroy_shader_module(shader_fragment);
+    }
+
+    match pipes {
+        Err(e) => {
+            unsafe {
+                factory.device().destroy_pipeline_layout(pipeline_layout);
+            }
+            Err(PassError::pipelines("DrawVolumetrics", e).into())
+        }
+        Ok(mut pipes) => Ok((pipes.remove(0), pipeline_layout)),
+    }
+}
@@ -7,10 +7,10 @@ use rendy::{
         device::Device,
         pass::Subpass,
         pso::{
-            AttributeDesc, BakedStates, BasePipeline, BlendDesc, ColorBlendDesc, DepthStencilDesc,
-            DepthTest, Face, GraphicsPipelineDesc, GraphicsShaderSet, InputAssemblerDesc,
-            Multisampling, PipelineCreationFlags, Rasterizer, Rect, VertexBufferDesc,
-            VertexInputRate, Viewport,
+            AttributeDesc, BakedStates, BasePipeline, BlendDesc, ColorBlendDesc, DepthBias,
+            DepthStencilDesc, DepthTest, Face, FrontFace, GraphicsPipelineDesc, GraphicsShaderSet,
+            InputAssemblerDesc, Multisampling, PipelineCreationFlags, PolygonMode, Rasterizer,
+            Rect, State, VertexBufferDesc, VertexInputRate, Viewport,
         },
         Primitive,
     },
@@ -213,6 +213,24 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
         })
     }
 
+    /// Build restricted to the provided viewport rectangle, set after the framebuffer size.
+    pub fn with_viewport(mut self, rect: Rect) -> Self {
+        self.set_viewport(rect);
+        self
+    }
+    /// Set to be restricted to the provided viewport rectangle.
+    pub fn set_viewport(&mut self, rect: Rect) {
+        let old_baked_states = self.baked_states.clone();
+        self.set_baked_states(BakedStates {
+            viewport: Some(Viewport {
+                rect,
+                depth: old_baked_states.viewport.map_or(0.0..1.0, |v| v.depth),
+            }),
+            scissor: Some(rect),
+            ..old_baked_states
+        })
+    }
+
     /// Build with the provided `DepthTest`
     pub fn with_depth_test(mut self, depth_test: DepthTest) -> Self {
         self.set_depth_test(depth_test);
@@ -223,6 +241,16 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
         self.depth_stencil.depth = Some(depth_test);
     }
 
+    /// Build with the provided `DepthBias`, or without bias for `None`.
+    pub fn with_depth_bias(mut self, depth_bias: Option<State<DepthBias>>) -> Self {
+        self.set_depth_bias(depth_bias);
+        self
+    }
+    /// Set to use the provided `DepthBias`, or no bias for `None`.
+    pub fn set_depth_bias(&mut self, depth_bias: Option<State<DepthBias>>) {
+        self.rasterizer.depth_bias = depth_bias;
+    }
+
     /// Build with the provided `Face` culling.
     pub fn with_face_culling(mut self, cull_face: Face) -> Self {
         self.set_face_culling(cull_face);
@@ -233,6 +261,26 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
         self.rasterizer.cull_face = cull_face;
     }
 
+    /// Build with the provided `FrontFace`.
+    pub fn with_front_face(mut self, front_face: FrontFace) -> Self {
+        self.set_front_face(front_face);
+        self
+    }
+    /// Set to use the provided `FrontFace`.
+    pub fn set_front_face(&mut self, front_face: FrontFace) {
+        self.rasterizer.front_face = front_face;
+    }
+
+    /// Build with the provided `PolygonMode`.
+    pub fn with_polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.set_polygon_mode(polygon_mode);
+        self
+    }
+    /// Set to use the provided `PolygonMode`.
+    pub fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) {
+        self.rasterizer.polygon_mode = polygon_mode;
+    }
+
     /// Build with the provided vertex description.
     pub fn with_vertex_desc(mut self, desc: &[(VertexFormat, VertexInputRate)]) -> Self {
         self.set_vertex_desc(desc);
@@ -255,7 +303,21 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
         self.blender.targets = targets;
     }
     /// Finalize and construct the `GraphicsPipelineDesc`
+    ///
+    /// Without `Multisampling`, the pipeline rasterizes as many samples as the target it's built
+    /// for, see [crate::msaa::target_samples].
     pub fn build(self) -> GraphicsPipelineDesc<'a, B> {
+        let samples = crate::msaa::target_samples();
+        let multisampling = match self.multisampling {
+            None if samples > 1 => Some(Multisampling {
+                rasterization_samples: samples,
+                sample_shading: None,
+                sample_mask: !0,
+                alpha_coverage: false,
+                alpha_to_one: false,
+            }),
+            multisampling => multisampling,
+        };
         GraphicsPipelineDesc {
             shaders: self.shaders.expect("Pipeline is missing shaders"),
             rasterizer: self.rasterizer,
@@ -264,7 +326,7 @@ impl<'a, B: Backend> PipelineDescBuilder<'a, B> {
             input_assembler: self.input_assembler,
             blender: self.blender,
             depth_stencil: self.depth_stencil,
-            multisampling: self.multisampling,
+            multisampling,
             baked_states: self.baked_states,
             layout: self.layout.expect("Pipeline is missing layout"),
             subpass: self.subpass.expect("Pipeline is missing subpass"),
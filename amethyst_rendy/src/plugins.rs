@@ -1,25 +1,58 @@
 //! Set of predefined implementations of `RenderPlugin` for use with `RenderingBundle`.
 
 use crate::{
-    bundle::{RenderOrder, RenderPlan, RenderPlugin, Target},
+    blob_shadow::BlobShadow,
+    bundle::{
+        ImageOptions, OutputColor, RenderOrder, RenderPlan, RenderPlugin, Target, TargetImage,
+        TargetPlanContext, TargetPlanOutputs, WorkingSpace,
+    },
+    error::RenderError,
+    gpu_particles::{
+        gpu_particle_state_size, GpuParticleEmitter, GpuParticleEmitterSystem, GpuParticles,
+    },
+    hiz::{hiz_image, hiz_level_sizes, hiz_target, HIZ_DEPTH_TARGET},
+    impostor::{Impostor, ImpostorBakeSettings},
+    material_shader::MaterialShader,
     pass::*,
+    shadow::ShadowSettings,
+    shells::Shells,
     sprite_visibility::SpriteVisibilitySortingSystem,
+    transition::Transition,
+    types::Texture,
+    view::{ViewClear, Viewport, Views},
+    view_mode::{ViewMode, ViewModes},
     visibility::VisibilitySortingSystem,
-    Backend, Factory,
+    Backend, Factory, Format, Kind,
 };
-use amethyst_core::ecs::{DispatcherBuilder, World};
-use amethyst_error::Error;
+use amethyst_assets::Handle;
+use amethyst_core::ecs::{DispatcherBuilder, World, WorldExt};
+use amethyst_error::{format_err, Error};
 use palette::Srgb;
-use rendy::graph::render::RenderGroupDesc;
+use rendy::{
+    graph::{
+        render::{RenderGroupDesc, SubpassBuilder},
+        ImageAccess, ImageId,
+    },
+    hal::{
+        self,
+        command::{ClearColor, ClearDepthStencil, ClearValue},
+        PhysicalDevice,
+    },
+};
+use std::sync::{Arc, Mutex};
 
 #[cfg(feature = "window")]
 pub use window::RenderToWindow;
 
+/// Target presented to the window when rendering with `WorkingSpace::LinearHdr` or MSAA.
+pub const DISPLAY_TARGET: Target = Target::Custom("display");
+
 #[cfg(feature = "window")]
 mod window {
     use super::*;
     use crate::{
         bundle::{ImageOptions, OutputColor},
+        msaa::{device_samples, DEFAULT_MSAA_SAMPLES},
         Format, Kind,
     };
     use amethyst_config::{Config, ConfigError};
@@ -28,12 +61,22 @@ mod window {
         SystemBundle,
     };
     use amethyst_window::{DisplayConfig, ScreenDimensions, Window, WindowBundle};
-    use rendy::hal::command::{ClearColor, ClearDepthStencil, ClearValue};
+    use rendy::hal::{
+        command::{ClearColor, ClearDepthStencil, ClearValue},
+        image::NumSamples,
+    };
     use std::path::Path;
 
     /// A [RenderPlugin] for opening a window and displaying a render target to it.
     ///
     /// When you provide [`DisplayConfig`], it opens a window for you using [`WindowBundle`].
+    ///
+    /// With `WorkingSpace::LinearHdr`, the target is a linear `Rgba16Sfloat` image displayed on
+    /// [DISPLAY_TARGET], which UI and debug drawing are moved to. It is tone mapped when
+    /// displayed, following the `ToneMapping` resource.
+    ///
+    /// With MSAA, the target is multisampled and its resolved color is displayed on
+    /// [DISPLAY_TARGET] in either working space, see [crate::msaa].
     #[derive(Default, Debug)]
     pub struct RenderToWindow {
         target: Target,
@@ -41,6 +84,8 @@ mod window {
         dimensions: Option<ScreenDimensions>,
         dirty: bool,
         clear: Option<ClearColor>,
+        tone_mapping: ToneMapping,
+        msaa: Option<NumSamples>,
     }
 
     impl RenderToWindow {
@@ -86,6 +131,25 @@ mod window {
             self.clear = Some(clear.into());
             self
         }
+
+        /// Tone map the linear target with given settings when the world has no `ToneMapping`
+        /// resource. Only used with `WorkingSpace::LinearHdr`.
+        pub fn with_tone_mapping(mut self, tone_mapping: ToneMapping) -> Self {
+            self.tone_mapping = tone_mapping;
+            self
+        }
+
+        /// Multisample the target with `DEFAULT_MSAA_SAMPLES` samples per pixel.
+        pub fn with_msaa(self) -> Self {
+            self.with_msaa_samples(DEFAULT_MSAA_SAMPLES)
+        }
+
+        /// Multisample the target with given number of samples per pixel, or the largest number
+        /// below it the device supports. The target isn't multisampled with 1 sample.
+        pub fn with_msaa_samples(mut self, samples: NumSamples) -> Self {
+            self.msaa = Some(samples).filter(|&samples| samples > 1);
+            self
+        }
     }
 
     impl<B: Backend> RenderPlugin<B> for RenderToWindow {
@@ -132,17 +196,131 @@ mod window {
                 clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
             };
 
-            plan.add_root(Target::Main);
-            plan.define_pass(
-                self.target,
-                crate::bundle::TargetPlanOutputs {
-                    colors: vec![OutputColor::Surface(
-                        surface,
-                        self.clear.map(ClearValue::Color),
-                    )],
-                    depth: Some(depth_options),
-                },
-            )?;
+            let surface_format = factory.get_surface_format(&surface);
+            let surface_srgb = encodes_srgb(surface_format);
+
+            let scene_format = match plan.working_space() {
+                WorkingSpace::SrgbFramebuffer => surface_format,
+                WorkingSpace::LinearHdr => Format::Rgba16Sfloat,
+            };
+            let samples = self.msaa.map_or(1, |samples| {
+                device_samples(factory, samples, scene_format, Some(Format::D32Sfloat))
+            });
+            let scene_kind = Kind::D2(
+                dimensions.width() as u32,
+                dimensions.height() as u32,
+                1,
+                samples,
+            );
+            let scene_depth_options = ImageOptions {
+                kind: scene_kind,
+                ..depth_options.clone()
+            };
+
+            match plan.working_space() {
+                WorkingSpace::SrgbFramebuffer if samples > 1 => {
+                    if !surface_srgb {
+                        log::warn!(
+                            "Window surface doesn't support an sRGB format, colors will be blended \
+                            and displayed in gamma space. Use `WorkingSpace::LinearHdr` to convert \
+                            them when displaying."
+                        );
+                    }
+                    plan.add_root(DISPLAY_TARGET);
+                    plan.define_pass(
+                        self.target,
+                        crate::bundle::TargetPlanOutputs {
+                            colors: vec![OutputColor::Image(ImageOptions {
+                                kind: scene_kind,
+                                levels: 1,
+                                format: surface_format,
+                                clear: self.clear.map(ClearValue::Color),
+                            })],
+                            depth: Some(scene_depth_options),
+                        },
+                    )?;
+                    plan.define_pass(
+                        DISPLAY_TARGET,
+                        crate::bundle::TargetPlanOutputs {
+                            colors: vec![OutputColor::Surface(surface, None)],
+                            depth: Some(depth_options),
+                        },
+                    )?;
+                    plan.set_display_target(self.target, DISPLAY_TARGET);
+
+                    let target = self.target;
+                    plan.extend_target(DISPLAY_TARGET, move |ctx| {
+                        let scene = ctx.get_image(TargetImage::Color(target, 0))?;
+                        ctx.add(
+                            RenderOrder::ToneMap,
+                            DrawBlitDesc::new()
+                                .with_filter(hal::image::Filter::Nearest)
+                                .with_target_depth(ctx.depth())
+                                .builder()
+                                .with_image(scene),
+                        )?;
+                        Ok(())
+                    });
+                }
+                WorkingSpace::SrgbFramebuffer => {
+                    if !surface_srgb {
+                        log::warn!(
+                            "Window surface doesn't support an sRGB format, colors will be blended \
+                            and displayed in gamma space. Use `WorkingSpace::LinearHdr` to convert \
+                            them when displaying."
+                        );
+                    }
+                    plan.add_root(Target::Main);
+                    plan.define_pass(
+                        self.target,
+                        crate::bundle::TargetPlanOutputs {
+                            colors: vec![OutputColor::Surface(
+                                surface,
+                                self.clear.map(ClearValue::Color),
+                            )],
+                            depth: Some(depth_options),
+                        },
+                    )?;
+                }
+                WorkingSpace::LinearHdr => {
+                    plan.add_root(DISPLAY_TARGET);
+                    plan.define_pass(
+                        self.target,
+                        crate::bundle::TargetPlanOutputs {
+                            colors: vec![OutputColor::Image(ImageOptions {
+                                kind: scene_kind,
+                                levels: 1,
+                                format: Format::Rgba16Sfloat,
+                                clear: self.clear.map(ClearValue::Color),
+                            })],
+                            depth: Some(scene_depth_options),
+                        },
+                    )?;
+                    plan.define_pass(
+                        DISPLAY_TARGET,
+                        crate::bundle::TargetPlanOutputs {
+                            colors: vec![OutputColor::Surface(surface, None)],
+                            depth: Some(depth_options),
+                        },
+                    )?;
+                    plan.set_display_target(self.target, DISPLAY_TARGET);
+
+                    let (target, tone_mapping) = (self.target, self.tone_mapping.clone());
+                    plan.extend_target(DISPLAY_TARGET, move |ctx| {
+                        let scene = ctx.get_image(TargetImage::Color(target, 0))?;
+                        ctx.add(
+                            RenderOrder::ToneMap,
+                            DrawDisplayDesc::new()
+                                .with_srgb_encoding(!surface_srgb)
+                                .with_target_depth(ctx.depth())
+                                .with_tone_mapping(tone_mapping.clone())
+                                .builder()
+                                .with_image(scene),
+                        )?;
+                        Ok(())
+                    });
+                }
+            }
 
             Ok(())
         }
@@ -153,9 +331,18 @@ mod window {
 pub type RenderFlat3D = RenderBase3D<crate::pass::FlatPassDef>;
 /// A `RenderPlugin` for forward rendering of 3d objects using shaded shading.
 pub type RenderShaded3D = RenderBase3D<crate::pass::ShadedPassDef>;
+/// A `RenderPlugin` for forward rendering of 3d objects using shaded shading perturbed by the
+/// normal maps of their materials.
+pub type RenderShadedNormalMapped3D = RenderBase3D<crate::pass::ShadedNormalMappedPassDef>;
 /// A `RenderPlugin` for forward rendering of 3d objects using physically-based shading.
 pub type RenderPbr3D = RenderBase3D<crate::pass::PbrPassDef>;
 
+/// Target the diffuse lighting of subsurface scattering materials is drawn to, see
+/// `RenderBase3D::with_subsurface`.
+pub const SUBSURFACE_TARGET: Target = Target::Custom("subsurface");
+/// Target of the horizontal blur of the subsurface scattering.
+pub const SUBSURFACE_BLUR_TARGET: Target = Target::Custom("subsurface_blur");
+
 /// A `RenderPlugin` for forward rendering of 3d objects.
 /// Generic over 3d pass rendering method.
 #[derive(derivative::Derivative)]
@@ -163,6 +350,15 @@ pub type RenderPbr3D = RenderBase3D<crate::pass::PbrPassDef>;
 pub struct RenderBase3D<D: Base3DPassDef> {
     target: Target,
     skinning: bool,
+    morphing: bool,
+    uv_transform: bool,
+    triplanar: bool,
+    surface_layer: bool,
+    indirect_draws: bool,
+    retained_draw_list: bool,
+    subsurface: Option<SubsurfaceProfile>,
+    material_shader: Option<MaterialShader>,
+    shadows: bool,
     marker: std::marker::PhantomData<D>,
 }
 
@@ -180,6 +376,74 @@ impl<D: Base3DPassDef> RenderBase3D<D> {
         self.skinning = true;
         self
     }
+
+    /// Enable rendering of meshes with `BlendShapes`, morphed before being skinned.
+    pub fn with_morphing(mut self) -> Self {
+        self.morphing = true;
+        self
+    }
+
+    /// Transform the texture coordinates of meshes by the `UvTransform`s of their material.
+    pub fn with_uv_transform(mut self) -> Self {
+        self.uv_transform = true;
+        self
+    }
+
+    /// Sample the maps of materials with a `triplanar_scale` along the world axes.
+    pub fn with_triplanar(mut self) -> Self {
+        self.triplanar = true;
+        self
+    }
+
+    /// Blend the surface layer of materials over them, e.g. to make them wet with the scene
+    /// `SurfaceLayer`.
+    pub fn with_surface_layer(mut self) -> Self {
+        self.surface_layer = true;
+        self
+    }
+
+    /// Draw opaque meshes with indirect draws where the device supports them.
+    ///
+    /// NOTE: Every opaque mesh which isn't deformed must have indices.
+    pub fn with_indirect_draws(mut self) -> Self {
+        self.indirect_draws = true;
+        self
+    }
+
+    /// Keep the batches of opaque meshes between frames, only updating the entries of the
+    /// entities that changed.
+    pub fn with_retained_draw_list(mut self) -> Self {
+        self.retained_draw_list = true;
+        self
+    }
+
+    /// Scatter the diffuse lighting of materials with a `subsurface` strength under their
+    /// surface with given profile, e.g. `SubsurfaceProfile::skin()`.
+    ///
+    /// The opaque meshes are drawn a second time into `SUBSURFACE_TARGET`, the size of the
+    /// target which must be defined by a plugin registered before this one, then blurred with
+    /// [DrawSubsurfaceDesc] and swapped for their unblurred lighting after the opaque pass.
+    /// Forward fog and surface layers aren't scattered.
+    pub fn with_subsurface(mut self, profile: SubsurfaceProfile) -> Self {
+        self.subsurface = Some(profile);
+        self
+    }
+
+    /// Modify the materials of opaque and transparent meshes with given GLSL snippet before
+    /// lighting them, e.g. to tint them with a fresnel term.
+    ///
+    /// NOTE: Requires the `shader-compiler` feature, or the render graph fails to build.
+    pub fn with_material_shader(mut self, shader: MaterialShader) -> Self {
+        self.material_shader = Some(shader);
+        self
+    }
+
+    /// Darken the light casting shadows with the shadow map of the `RenderShadows` plugin,
+    /// which must also be registered. Only the shaded and PBR passes are shadowed.
+    pub fn with_shadows(mut self) -> Self {
+        self.shadows = true;
+        self
+    }
 }
 
 impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
@@ -195,29 +459,269 @@ impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderBase3D<D> {
     fn on_plan(
         &mut self,
         plan: &mut RenderPlan<B>,
-        _factory: &mut Factory<B>,
+        factory: &mut Factory<B>,
         _world: &World,
     ) -> Result<(), Error> {
-        let skinning = self.skinning;
+        if let Some(profile) = self.subsurface.clone() {
+            self.plan_subsurface(plan, factory, profile)?;
+        }
+
+        let (skinning, morphing) = (self.skinning, self.morphing);
+        let (uv_transform, triplanar) = (self.uv_transform, self.triplanar);
+        let surface_layer = self.surface_layer;
+        let (indirect_draws, retained_draw_list) = (self.indirect_draws, self.retained_draw_list);
+        let material_shader = self.material_shader.clone();
+        let shadows = self.shadows;
         plan.extend_target(self.target, move |ctx| {
-            ctx.add(
-                RenderOrder::Opaque,
-                DrawBase3DDesc::<B, D>::new()
-                    .with_skinning(skinning)
-                    .builder(),
-            )?;
-            ctx.add(
-                RenderOrder::Transparent,
-                DrawBase3DTransparentDesc::<B, D>::new()
-                    .with_skinning(skinning)
-                    .builder(),
-            )?;
+            let mut opaque = DrawBase3DDesc::<B, D>::new()
+                .with_skinning(skinning)
+                .with_morphing(morphing)
+                .with_uv_transform(uv_transform)
+                .with_triplanar(triplanar)
+                .with_surface_layer(surface_layer)
+                .with_indirect_draws(indirect_draws)
+                .with_retained_draw_list(retained_draw_list);
+            let mut transparent = DrawBase3DTransparentDesc::<B, D>::new()
+                .with_skinning(skinning)
+                .with_morphing(morphing)
+                .with_uv_transform(uv_transform)
+                .with_triplanar(triplanar)
+                .with_surface_layer(surface_layer);
+            if let Some(shader) = material_shader {
+                opaque = opaque.with_material_shader(shader.clone());
+                transparent = transparent.with_material_shader(shader);
+            }
+            let (mut opaque, mut transparent) = (
+                opaque.with_shadows(shadows).builder(),
+                transparent.with_shadows(shadows).builder(),
+            );
+            if shadows {
+                let shadow_map = ctx.get_image(TargetImage::Depth(SHADOW_MAP_TARGET))?;
+                opaque = opaque.with_image(shadow_map);
+                transparent = transparent.with_image(shadow_map);
+            }
+            ctx.add(RenderOrder::Opaque, opaque)?;
+            ctx.add(RenderOrder::Transparent, transparent)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+impl<D: Base3DPassDef> RenderBase3D<D> {
+    fn plan_subsurface<B: Backend>(
+        &self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        profile: SubsurfaceProfile,
+    ) -> Result<(), Error> {
+        if D::fragment_subsurface_shader().is_none() {
+            log::warn!(
+                "Pass {} has no subsurface fragment shader, materials aren't scattered.",
+                D::NAME
+            );
+            return Ok(());
+        }
+        let metadata = plan.target_metadata(self.target, factory).ok_or_else(|| {
+            RenderError::MissingTarget {
+                target: self.target,
+                plugin: "RenderBase3D with subsurface scattering",
+            }
+        })?;
+        let kind = Kind::D2(metadata.width(), metadata.height(), 1, 1);
+
+        plan.define_pass(
+            SUBSURFACE_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba16Sfloat,
+                    clear: Some(ClearValue::Color(ClearColor::Sfloat([0.0; 4]))),
+                })],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                }),
+            },
+        )?;
+        let (skinning, morphing, uv_transform) = (self.skinning, self.morphing, self.uv_transform);
+        let material_shader = self.material_shader.clone();
+        plan.extend_target(SUBSURFACE_TARGET, move |ctx| {
+            let mut desc = DrawBase3DDesc::<B, D>::new()
+                .with_skinning(skinning)
+                .with_morphing(morphing)
+                .with_uv_transform(uv_transform)
+                .with_subsurface(true);
+            if let Some(shader) = material_shader {
+                desc = desc.with_material_shader(shader);
+            }
+            ctx.add(RenderOrder::Opaque, desc.builder())?;
+            Ok(())
+        });
+
+        plan.define_pass(
+            SUBSURFACE_BLUR_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba16Sfloat,
+                    clear: None,
+                })],
+                depth: None,
+            },
+        )?;
+        let horizontal = DrawSubsurfaceDesc::horizontal(profile.clone());
+        plan.extend_target(SUBSURFACE_BLUR_TARGET, move |ctx| {
+            let mut group = horizontal.builder();
+            for &image in &[
+                TargetImage::Color(SUBSURFACE_TARGET, 0),
+                TargetImage::Depth(SUBSURFACE_TARGET),
+            ] {
+                group = group.with_image(ctx.get_image(image)?);
+            }
+            ctx.add(RenderOrder::Opaque, group)?;
+            Ok(())
+        });
+
+        let composite = DrawSubsurfaceDesc::composite(profile);
+        plan.extend_target(self.target, move |ctx| {
+            let mut group = composite.with_target_depth(ctx.depth()).builder();
+            for &image in &[
+                TargetImage::Color(SUBSURFACE_BLUR_TARGET, 0),
+                TargetImage::Depth(SUBSURFACE_TARGET),
+                TargetImage::Color(SUBSURFACE_TARGET, 0),
+            ] {
+                group = group.with_image(ctx.get_image(image)?);
+            }
+            ctx.add(RenderOrder::AfterOpaque, group)?;
             Ok(())
         });
         Ok(())
     }
 }
 
+/// A `RenderPlugin` for forward rendering of 3d objects through every view in the `Views`
+/// resource, instead of a single target seen by the `ActiveCamera`. Use in place of
+/// `RenderBase3D`, not along with it.
+/// Generic over 3d pass rendering method.
+///
+/// The first view drawn to a target renders at the same orders as `RenderBase3D`, so it
+/// composes with the skybox and post effects of that target. Further views on the same target
+/// are drawn over it after its transparent pass, one after another. Other passes, like the
+/// skybox, are not view aware and only drawn once per target with the `ActiveCamera`.
+/// The projection of every view camera should match the aspect ratio of its viewport.
+#[derive(derivative::Derivative)]
+#[derivative(Default(bound = ""), Debug(bound = ""))]
+pub struct RenderViews<D: Base3DPassDef> {
+    skinning: bool,
+    morphing: bool,
+    views: Views,
+    marker: std::marker::PhantomData<D>,
+}
+
+impl<D: Base3DPassDef> RenderViews<D> {
+    /// Enable rendering for skinned meshes.
+    ///
+    /// NOTE: You must register `VertexSkinningBundle` yourself.
+    pub fn with_skinning(mut self) -> Self {
+        self.skinning = true;
+        self
+    }
+
+    /// Enable rendering of meshes with `BlendShapes`, morphed before being skinned.
+    pub fn with_morphing(mut self) -> Self {
+        self.morphing = true;
+        self
+    }
+}
+
+impl<B: Backend, D: Base3DPassDef> RenderPlugin<B> for RenderViews<D> {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(VisibilitySortingSystem::new(), "visibility_system", &[]);
+        Ok(())
+    }
+
+    fn should_rebuild(&mut self, world: &World) -> bool {
+        let views = world.try_fetch::<Views>();
+        let empty = Views::default();
+        let views = views.as_deref().unwrap_or(&empty);
+        if &self.views != views {
+            self.views = views.clone();
+            return true;
+        }
+        false
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        if let Some(views) = world.try_fetch::<Views>() {
+            self.views = (*views).clone();
+        }
+        let order = self.views.ordered()?;
+
+        let mut drawn_targets = Vec::new();
+        for index in order {
+            let view = self.views.0[index].clone();
+            let (skinning, morphing) = (self.skinning, self.morphing);
+            let first = !drawn_targets.contains(&view.target);
+            drawn_targets.push(view.target);
+
+            let (clear_order, opaque_order, transparent_order) = if first {
+                (
+                    RenderOrder::BeforeOpaque,
+                    RenderOrder::Opaque,
+                    RenderOrder::Transparent,
+                )
+            } else {
+                (
+                    RenderOrder::AfterTransparent,
+                    RenderOrder::AfterTransparent,
+                    RenderOrder::AfterTransparent,
+                )
+            };
+
+            plan.extend_target(view.target, move |ctx| {
+                if let ViewClear::Clear(color) = view.clear {
+                    ctx.add(
+                        clear_order,
+                        DrawViewClearDesc::new(color, view.viewport).builder(),
+                    )?;
+                }
+                ctx.add(
+                    opaque_order,
+                    DrawBase3DDesc::<B, D>::new()
+                        .with_skinning(skinning)
+                        .with_morphing(morphing)
+                        .with_view(index, &view)
+                        .builder(),
+                )?;
+                ctx.add(
+                    transparent_order,
+                    DrawBase3DTransparentDesc::<B, D>::new()
+                        .with_skinning(skinning)
+                        .with_morphing(morphing)
+                        .with_view(index, &view)
+                        .builder(),
+                )?;
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+}
+
 /// A [RenderPlugin] for drawing 2d objects with flat shading.
 /// Required to display sprites defined with [SpriteRender] component.
 #[derive(Default, Debug)]
@@ -266,7 +770,10 @@ impl<B: Backend> RenderPlugin<B> for RenderFlat2D {
 }
 
 /// A [RenderPlugin] for drawing debug lines.
-/// Use with [debug_drawing::DebugLines] resource or [debug_drawing::DebugLinesComponent].
+/// Use with [debug_drawing::DebugLines] resource, [debug_drawing::DebugLinesComponent] or
+/// [debug_drawing::DebugShapesComponent].
+///
+/// Lines are drawn on the display target of the selected target, after tonemapping.
 #[derive(Default, Debug)]
 pub struct RenderDebugLines {
     target: Target,
@@ -287,7 +794,7 @@ impl<B: Backend> RenderPlugin<B> for RenderDebugLines {
         _factory: &mut Factory<B>,
         _world: &World,
     ) -> Result<(), Error> {
-        plan.extend_target(self.target, |ctx| {
+        plan.extend_display_target(self.target, |ctx| {
             ctx.add(
                 RenderOrder::BeforeTransparent,
                 DrawDebugLinesDesc::new().builder(),
@@ -298,43 +805,1216 @@ impl<B: Backend> RenderPlugin<B> for RenderDebugLines {
     }
 }
 
-/// RenderPlugin for rendering skyboxes.
+/// A [RenderPlugin] for drawing the sprites of the [crate::screen_sprite::ScreenSprites]
+/// resource in pixels, over everything else on the display target of the selected target.
 #[derive(Default, Debug)]
-pub struct RenderSkybox {
+pub struct RenderScreenSprites {
     target: Target,
-    colors: Option<(Srgb, Srgb)>,
 }
 
-impl RenderSkybox {
-    /// Create skybox with specified nadir and zenith colors.
-    pub fn with_colors(nadir_color: Srgb, zenith_color: Srgb) -> Self {
-        Self {
-            target: Default::default(),
-            colors: Some((nadir_color, zenith_color)),
+impl RenderScreenSprites {
+    /// Set target to which screen sprites will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderScreenSprites {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_display_target(self.target, |ctx| {
+            ctx.add(RenderOrder::Overlay, DrawScreenSpritesDesc::new().builder())?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] for drawing distant entities with an [impostor::Impostor] component.
+/// Entities are switched to impostors by the `VisibilitySortingSystem`, so this plugin must
+/// be used together with one of the 3D plugins.
+#[derive(Default, Debug)]
+pub struct RenderImpostors {
+    target: Target,
+    bakes: Vec<(Target, ImpostorBakeSettings)>,
+}
+
+impl RenderImpostors {
+    /// Set target to which impostors will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Bake the atlas of the impostors created `Impostor::baked` with `target` into an offscreen
+    /// target defined as by `RenderToTexture`, see [DrawImpostorBakeDesc].
+    pub fn with_bake(mut self, target: Target, settings: ImpostorBakeSettings) -> Self {
+        self.bakes.push((target, settings));
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderImpostors {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<Impostor>();
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        for (target, settings) in &self.bakes {
+            let (width, height) = settings.atlas_size();
+            RenderPlugin::<B>::on_plan(
+                &mut RenderToTexture::new(*target, width, height),
+                plan,
+                factory,
+                world,
+            )?;
+            let desc = DrawImpostorBakeDesc::new(*target, settings.clone());
+            plan.extend_target(*target, move |ctx| {
+                ctx.add(RenderOrder::Opaque, desc.builder())?;
+                Ok(())
+            });
         }
+
+        let baked = self
+            .bakes
+            .iter()
+            .map(|&(target, _)| target)
+            .collect::<Vec<_>>();
+        plan.extend_target(self.target, move |ctx| {
+            let mut group = DrawImpostorsDesc::new().with_baked(baked.clone()).builder();
+            for target in baked {
+                group = group.with_image(ctx.get_image(TargetImage::Color(target, 0))?);
+            }
+            ctx.add(RenderOrder::Opaque, group)?;
+            Ok(())
+        });
+        Ok(())
     }
+}
 
-    /// Set target to which skybox will be rendered.
+/// A [RenderPlugin] for drawing the blob shadows of entities with a [BlobShadow] component
+/// on the ground, over the opaque objects drawn by the other plugins.
+#[derive(Default, Debug)]
+pub struct RenderBlobShadows {
+    target: Target,
+}
+
+impl RenderBlobShadows {
+    /// Set target to which blob shadows will be rendered.
     pub fn with_target(mut self, target: Target) -> Self {
         self.target = target;
         self
     }
 }
 
-impl<B: Backend> RenderPlugin<B> for RenderSkybox {
+impl<B: Backend> RenderPlugin<B> for RenderBlobShadows {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<BlobShadow>();
+        Ok(())
+    }
+
     fn on_plan(
         &mut self,
         plan: &mut RenderPlan<B>,
         _factory: &mut Factory<B>,
         _world: &World,
     ) -> Result<(), Error> {
-        let colors = self.colors;
+        plan.extend_target(self.target, |ctx| {
+            ctx.add(
+                RenderOrder::BeforeTransparent,
+                DrawBlobShadowsDesc::new().builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] drawing into a target with raw commands of an [ExternalDraw], at a
+/// given order among the passes of the target, see [DrawExternalDesc].
+///
+/// The images of other targets the pass reads must be declared with `with_image`, so the
+/// render graph draws them first.
+pub struct RenderExternal<B: Backend> {
+    target: Target,
+    order: i32,
+    draw: Arc<Mutex<dyn ExternalDraw<B>>>,
+    images: Vec<(TargetImage, ImageAccess)>,
+}
+
+impl<B: Backend> std::fmt::Debug for RenderExternal<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RenderExternal")
+            .field("target", &self.target)
+            .field("order", &self.order)
+            .field("images", &self.images)
+            .finish()
+    }
+}
+
+impl<B: Backend> RenderExternal<B> {
+    /// Draw into `target` with given `ExternalDraw`, at given order, e.g.
+    /// `RenderOrder::AfterOpaque`.
+    pub fn new(target: Target, order: impl Into<i32>, draw: impl ExternalDraw<B>) -> Self {
+        Self {
+            target,
+            order: order.into(),
+            draw: Arc::new(Mutex::new(draw)),
+            images: Vec::new(),
+        }
+    }
+
+    /// Read given image of another target with given access, e.g.
+    /// `sampled_image_access(PipelineStage::FRAGMENT_SHADER)`. The images are passed to the
+    /// pass in the order they were declared.
+    pub fn with_image(mut self, image: TargetImage, access: ImageAccess) -> Self {
+        self.images.push((image, access));
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderExternal<B> {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let (order, images) = (self.order, self.images.clone());
+        let desc = DrawExternalDesc::shared(self.draw.clone());
         plan.extend_target(self.target, move |ctx| {
-            let group = if let Some((nadir, zenith)) = colors {
-                DrawSkyboxDesc::with_colors(nadir, zenith).builder()
-            } else {
-                DrawSkyboxDesc::new().builder()
-            };
+            let mut desc = desc.with_colors(ctx.colors()).with_depth(ctx.depth());
+            for &(_, access) in &images {
+                desc = desc.with_image(access);
+            }
+            let mut group = desc.builder();
+            for &(image, _) in &images {
+                group = group.with_image(ctx.get_image(image)?);
+            }
+            ctx.add(order, group)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] defining an offscreen target, with one color image and optionally a depth
+/// image, e.g. to draw the scene from a second camera onto a screen in the world.
+///
+/// The plugins drawing the scene draw into it when created `with_target`, and other targets
+/// read its color as `TargetImage::Color(target, 0)`, e.g. with the `RenderBlit` plugin. The
+/// images keep their size when the window is resized, but are created again whenever the
+/// render graph is rebuilt, so they don't keep what was drawn the frames before.
+///
+/// The target is only drawn when another target reads it.
+#[derive(Debug, Clone)]
+pub struct RenderToTexture {
+    target: Target,
+    width: u32,
+    height: u32,
+    format: Format,
+    clear: Option<ClearColor>,
+    depth: bool,
+}
+
+impl RenderToTexture {
+    /// Define `target` with images of given size in pixels, with a linear `Rgba16Sfloat` color
+    /// cleared to transparent black and a depth image.
+    pub fn new(target: Target, width: u32, height: u32) -> Self {
+        Self {
+            target,
+            width: width.max(1),
+            height: height.max(1),
+            format: Format::Rgba16Sfloat,
+            clear: Some(ClearColor::Sfloat([0.0; 4])),
+            depth: true,
+        }
+    }
+
+    /// Set the format of the color image.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Select the color the color image is cleared to every frame.
+    pub fn with_clear(mut self, clear: impl Into<ClearColor>) -> Self {
+        self.clear = Some(clear.into());
+        self
+    }
+
+    /// Don't clear the color image, e.g. when a pass covers all of it.
+    pub fn without_clear(mut self) -> Self {
+        self.clear = None;
+        self
+    }
+
+    /// Set whether the target has a depth image, to draw 3D scenes into it.
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderToTexture {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let kind = Kind::D2(self.width, self.height, 1, 1);
+        plan.define_pass(
+            self.target,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: self.format,
+                    clear: self.clear.map(ClearValue::Color),
+                })],
+                depth: if self.depth {
+                    Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    })
+                } else {
+                    None
+                },
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] drawing the color of another target over a viewport of a target, see
+/// [DrawBlitDesc].
+///
+/// The image is drawn in the overlay of the target by default, and replaces its colors unless
+/// blended.
+#[derive(Debug, Clone)]
+pub struct RenderBlit {
+    source: Target,
+    target: Target,
+    order: i32,
+    desc: DrawBlitDesc,
+}
+
+impl RenderBlit {
+    /// Draw the first color image of `source` over the whole `target`.
+    pub fn new(source: Target, target: Target) -> Self {
+        Self {
+            source,
+            target,
+            order: RenderOrder::Overlay.into(),
+            desc: DrawBlitDesc::new(),
+        }
+    }
+
+    /// Draw the image over given viewport of the target.
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.desc = self.desc.with_viewport(viewport);
+        self
+    }
+
+    /// Draw the image at given order among the passes of the target.
+    pub fn with_order(mut self, order: impl Into<i32>) -> Self {
+        self.order = order.into();
+        self
+    }
+
+    /// Blend the image over the target with its alpha if true is passed.
+    pub fn with_blending(mut self, blending: bool) -> Self {
+        self.desc = self.desc.with_blending(blending);
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderBlit {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let (source, order, desc) = (self.source, self.order, self.desc.clone());
+        plan.extend_target(self.target, move |ctx| {
+            let image = ctx.get_image(TargetImage::Color(source, 0))?;
+            ctx.add(
+                order,
+                desc.with_target_depth(ctx.depth())
+                    .builder()
+                    .with_image(image),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// Target the shadow map of the `RenderShadows` plugin is drawn to, with only a depth image.
+pub const SHADOW_MAP_TARGET: Target = Target::Custom("shadow_map");
+
+/// A [RenderPlugin] drawing the shadow map of the directional light selected by the
+/// [ShadowSettings] resource into `SHADOW_MAP_TARGET`, see [crate::shadow].
+///
+/// The 3D plugins sample it when built `with_shadows`, and the `RenderViewModes` plugin shows
+/// its coverage in `ViewMode::ShadowCascades` when registered after this plugin.
+#[derive(Debug)]
+pub struct RenderShadows {
+    resolution: u32,
+    settings: ShadowSettings,
+}
+
+impl Default for RenderShadows {
+    fn default() -> Self {
+        Self {
+            resolution: 2048,
+            settings: ShadowSettings::default(),
+        }
+    }
+}
+
+impl RenderShadows {
+    /// Set the width and height of the shadow map in texels, 2048 by default.
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    /// Set the `ShadowSettings` inserted in the world, unless the world already has some.
+    pub fn with_settings(mut self, settings: ShadowSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderShadows {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        let settings = self.settings.clone();
+        world
+            .entry::<ShadowSettings>()
+            .or_insert_with(move || settings);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.define_pass(
+            SHADOW_MAP_TARGET,
+            TargetPlanOutputs {
+                colors: vec![],
+                depth: Some(ImageOptions {
+                    kind: Kind::D2(self.resolution, self.resolution, 1, 1),
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                }),
+            },
+        )?;
+        plan.extend_target(SHADOW_MAP_TARGET, |ctx| {
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawDepthDesc::for_shadow_map().builder(),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] simulating the particles of entities with a [GpuParticleEmitter] on the
+/// GPU, and drawing them after the transparent objects of the target, see
+/// [crate::gpu_particles].
+///
+/// The state takes 96 bytes per particle of the capacity, and every particle of the capacity
+/// is drawn every frame, dead or alive.
+#[derive(Debug)]
+pub struct RenderGpuParticles {
+    target: Target,
+    capacity: u32,
+    additive: bool,
+}
+
+impl Default for RenderGpuParticles {
+    fn default() -> Self {
+        Self {
+            target: Default::default(),
+            capacity: GpuParticles::default().capacity(),
+            additive: false,
+        }
+    }
+}
+
+impl RenderGpuParticles {
+    /// Set target to which particles will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the maximum number of particles alive at once.
+    pub fn with_capacity(mut self, capacity: u32) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Add the particles to the target instead of blending them with their alpha.
+    pub fn with_additive(mut self, additive: bool) -> Self {
+        self.additive = additive;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderGpuParticles {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<GpuParticleEmitter>();
+        world.insert(GpuParticles::new(self.capacity));
+        builder.add(GpuParticleEmitterSystem, "gpu_particle_emitter_system", &[]);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let (width, height) = gpu_particle_state_size(self.capacity);
+        let additive = self.additive;
+        plan.extend_target(self.target, move |ctx| {
+            // Every frame simulates the state into the next state, copied back into the state
+            // read by the next frame and the billboards.
+            let kind = Kind::D2(width, height, 1, 1);
+            let formats = [
+                Format::Rgba32Sfloat,
+                Format::Rgba32Sfloat,
+                Format::Rgba32Uint,
+            ];
+            let graph = ctx.graph();
+            let mut create_state = || -> Vec<ImageId> {
+                formats
+                    .iter()
+                    .map(|&format| graph.create_image(kind, 1, format, None))
+                    .collect()
+            };
+            let (state, next) = (create_state(), create_state());
+
+            let mut simulation = DrawGpuParticleSimulationDesc::new().builder();
+            let mut copy = DrawGpuParticleCopyDesc::new().builder();
+            let mut draw = DrawGpuParticlesDesc::new()
+                .with_additive(additive)
+                .builder();
+            for (&state, &next) in state.iter().zip(&next) {
+                simulation = simulation.with_image(state);
+                copy = copy.with_image(next);
+                draw = draw.with_image(state);
+            }
+
+            let mut simulation = SubpassBuilder::new().with_group(simulation);
+            for &image in &next {
+                simulation.add_color(image);
+            }
+            let simulation = graph.add_node(simulation.into_pass());
+            let mut copy = SubpassBuilder::new()
+                .with_group(copy)
+                .with_dependency(simulation);
+            for &image in &state {
+                copy.add_color(image);
+            }
+            let copy = graph.add_node(copy.into_pass());
+
+            ctx.add_dep(copy);
+            ctx.add(RenderOrder::AfterTransparent, draw)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] drawing the debug views of the scene selected with the [ViewModes]
+/// resource, see [crate::view_mode].
+///
+/// The passes of every supported mode are built once and skip drawing while another mode is
+/// selected. `ViewMode::Wireframe` and `ViewMode::WireframeOverlay` are only supported on
+/// devices with non-fill polygon modes, and `ViewMode::ShadowCascades` when the
+/// `RenderShadows` plugin is registered before this one.
+#[derive(derivative::Derivative, Debug)]
+#[derivative(Default)]
+pub struct RenderViewModes {
+    target: Target,
+    #[derivative(Default(value = "1.0"))]
+    wireframe_width: f32,
+}
+
+impl RenderViewModes {
+    /// Set target to which the debug views will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the width in pixels of the lines of the wireframe modes, 1 by default. Devices
+    /// without support for wide lines draw them 1 pixel wide.
+    pub fn with_wireframe_width(mut self, width: f32) -> Self {
+        self.wireframe_width = width;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderViewModes {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<ViewModes>()
+            .or_insert_with(ViewModes::default);
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        let wireframe = factory
+            .physical()
+            .features()
+            .contains(hal::Features::NON_FILL_POLYGON_MODE);
+        if !wireframe {
+            log::warn!("Device doesn't support non-fill polygon modes, wireframe view disabled.");
+        }
+        let wireframe_width = if self.wireframe_width != 1.0
+            && !factory
+                .physical()
+                .features()
+                .contains(hal::Features::LINE_WIDTH)
+        {
+            log::warn!("Device doesn't support wide lines, wireframe drawn 1 pixel wide.");
+            1.0
+        } else {
+            self.wireframe_width
+        };
+
+        let mut supported = vec![
+            ViewMode::Unlit,
+            ViewMode::Normals,
+            ViewMode::Overdraw,
+            ViewMode::LightComplexity,
+            ViewMode::Depth,
+        ];
+        if wireframe {
+            supported.push(ViewMode::Wireframe);
+            supported.push(ViewMode::WireframeOverlay);
+        }
+        let shadows = plan.target_metadata(SHADOW_MAP_TARGET, factory).is_some();
+        if shadows {
+            supported.push(ViewMode::ShadowCascades);
+        }
+        if let Some(mut modes) = world.try_fetch_mut::<ViewModes>() {
+            modes.set_supported(supported);
+        }
+
+        plan.extend_target(self.target, move |ctx| {
+            fn add_mode<B: Backend, D: Base3DPassDef>(
+                ctx: &mut TargetPlanContext<'_, B>,
+                mode: ViewMode,
+                shadows: bool,
+                line_width: f32,
+            ) -> Result<(), Error> {
+                let mut opaque = DrawBase3DDesc::<B, D>::new()
+                    .with_skinning(true)
+                    .with_morphing(true)
+                    .with_view_mode(mode)
+                    .with_shadows(shadows)
+                    .with_line_width(line_width)
+                    .builder();
+                let mut transparent = DrawBase3DTransparentDesc::<B, D>::new()
+                    .with_skinning(true)
+                    .with_morphing(true)
+                    .with_view_mode(mode)
+                    .with_shadows(shadows)
+                    .with_line_width(line_width)
+                    .builder();
+                if shadows {
+                    let shadow_map = ctx.get_image(TargetImage::Depth(SHADOW_MAP_TARGET))?;
+                    opaque = opaque.with_image(shadow_map);
+                    transparent = transparent.with_image(shadow_map);
+                }
+                // Overlays draw over the transparent surfaces of the lit scene.
+                let (opaque_order, transparent_order) = if D::overlay() {
+                    (RenderOrder::AfterTransparent, RenderOrder::AfterTransparent)
+                } else {
+                    (RenderOrder::Opaque, RenderOrder::Transparent)
+                };
+                ctx.add(opaque_order, opaque)?;
+                ctx.add(transparent_order, transparent)?;
+                Ok(())
+            }
+
+            add_mode::<B, FlatPassDef>(ctx, ViewMode::Unlit, false, 1.0)?;
+            add_mode::<B, ViewNormalsPassDef>(ctx, ViewMode::Normals, false, 1.0)?;
+            add_mode::<B, ViewOverdrawPassDef>(ctx, ViewMode::Overdraw, false, 1.0)?;
+            add_mode::<B, ViewLightComplexityPassDef>(ctx, ViewMode::LightComplexity, false, 1.0)?;
+            add_mode::<B, ViewDepthPassDef>(ctx, ViewMode::Depth, false, 1.0)?;
+            if wireframe {
+                add_mode::<B, ViewWireframePassDef>(
+                    ctx,
+                    ViewMode::Wireframe,
+                    false,
+                    wireframe_width,
+                )?;
+                add_mode::<B, ViewWireframeOverlayPassDef>(
+                    ctx,
+                    ViewMode::WireframeOverlay,
+                    false,
+                    wireframe_width,
+                )?;
+            }
+            if shadows {
+                add_mode::<B, ViewShadowCascadesPassDef>(ctx, ViewMode::ShadowCascades, true, 1.0)?;
+            }
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] for drawing the shells of entities with a [shells::Shells] component, on
+/// top of the mesh drawn by one of the 3D plugins.
+#[derive(Default, Debug)]
+pub struct RenderShells {
+    target: Target,
+}
+
+impl RenderShells {
+    /// Set target to which shells will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderShells {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.register::<Shells>();
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(self.target, |ctx| {
+            ctx.add(RenderOrder::Opaque, DrawShellsDesc::new().builder())?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] building the hierarchical depth buffer of the opaque scene, see
+/// [crate::hiz].
+///
+/// The chain has the size of the target selected with `with_size_of`, `Target::Main` by
+/// default, which must be defined by a plugin registered before this one. Levels are only
+/// rendered when a consumer requests them, e.g. the occlusion overlay enabled with
+/// `with_occlusion_debug`.
+#[derive(Default, Debug)]
+pub struct RenderHiZ {
+    size_of: Target,
+    occlusion_debug: Option<Target>,
+}
+
+impl RenderHiZ {
+    /// Match the size of the chain to given target.
+    pub fn with_size_of(mut self, target: Target) -> Self {
+        self.size_of = target;
+        self
+    }
+
+    /// Overlay the Hi-Z occlusion test result of every visible entity on the display target
+    /// of given target.
+    pub fn with_occlusion_debug(mut self, target: Target) -> Self {
+        self.occlusion_debug = Some(target);
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderHiZ {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let metadata = plan.target_metadata(self.size_of, factory).ok_or_else(|| {
+            RenderError::MissingTarget {
+                target: self.size_of,
+                plugin: "RenderHiZ",
+            }
+        })?;
+        let sizes = hiz_level_sizes(metadata.width(), metadata.height());
+
+        let (width, height) = sizes[0];
+        plan.define_pass(
+            HIZ_DEPTH_TARGET,
+            TargetPlanOutputs {
+                colors: vec![],
+                depth: Some(ImageOptions {
+                    kind: Kind::D2(width, height, 1, 1),
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                }),
+            },
+        )?;
+        plan.extend_target(HIZ_DEPTH_TARGET, |ctx| {
+            ctx.add(RenderOrder::Opaque, DrawDepthDesc::new().builder())?;
+            Ok(())
+        });
+
+        for (level, &(width, height)) in sizes.iter().enumerate().skip(1) {
+            plan.define_pass(
+                hiz_target(level),
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind: Kind::D2(width, height, 1, 1),
+                        levels: 1,
+                        format: Format::R32Sfloat,
+                        clear: None,
+                    })],
+                    depth: None,
+                },
+            )?;
+            plan.extend_target(hiz_target(level), move |ctx| {
+                let previous = ctx.get_image(hiz_image(level - 1))?;
+                ctx.add(
+                    RenderOrder::Opaque,
+                    DrawHiZDownsampleDesc::new().builder().with_image(previous),
+                )?;
+                Ok(())
+            });
+        }
+
+        if let Some(target) = self.occlusion_debug {
+            let levels = sizes.len();
+            plan.extend_display_target(target, move |ctx| {
+                let mut group = DrawHiZOcclusionDebugDesc::new(levels).builder();
+                for level in 0..levels {
+                    group = group.with_image(ctx.get_image(hiz_image(level))?);
+                }
+                ctx.add(RenderOrder::Overlay, group)?;
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] compositing a half resolution effect onto a full resolution target with
+/// [DrawBilateralUpsampleDesc].
+///
+/// When used with `RenderHiZ`, `hiz_image(0)` and `hiz_image(1)` provide the full and roughly
+/// half resolution depth of the opaque scene.
+#[derive(Debug)]
+pub struct RenderBilateralUpsample {
+    target: Target,
+    order: i32,
+    effect: TargetImage,
+    full_depth: TargetImage,
+    half_depth: TargetImage,
+    normals: Option<(TargetImage, TargetImage)>,
+    blend: UpsampleBlend,
+    settings: BilateralUpsampleSettings,
+}
+
+impl RenderBilateralUpsample {
+    /// Upsample the `effect` image using given full and half resolution depth.
+    pub fn new(
+        effect: TargetImage,
+        full_depth: TargetImage,
+        half_depth: TargetImage,
+        blend: UpsampleBlend,
+    ) -> Self {
+        Self {
+            target: Default::default(),
+            order: RenderOrder::AfterOpaque.into(),
+            effect,
+            full_depth,
+            half_depth,
+            normals: None,
+            blend,
+            settings: Default::default(),
+        }
+    }
+
+    /// Set target to which the effect will be composited.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the order of the composition in the target, `RenderOrder::AfterOpaque` by default.
+    pub fn with_order(mut self, order: impl Into<i32>) -> Self {
+        self.order = order.into();
+        self
+    }
+
+    /// Also weight the samples by similarity of given full and half resolution normals.
+    pub fn with_normals(mut self, full_normals: TargetImage, half_normals: TargetImage) -> Self {
+        self.normals = Some((full_normals, half_normals));
+        self
+    }
+
+    /// Set the upsample settings.
+    pub fn with_settings(mut self, settings: BilateralUpsampleSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderBilateralUpsample {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let order = self.order;
+        let images = [self.effect, self.full_depth, self.half_depth];
+        let normals = self.normals;
+        let mut desc =
+            DrawBilateralUpsampleDesc::new(self.blend).with_settings(self.settings.clone());
+        if normals.is_some() {
+            desc = desc.with_normals();
+        }
+
+        plan.extend_target(self.target, move |ctx| {
+            let mut group = desc.with_target_depth(ctx.depth()).builder();
+            let normals = normals.iter().flat_map(|&(full, half)| vec![full, half]);
+            for image in images.iter().copied().chain(normals) {
+                group = group.with_image(ctx.get_image(image)?);
+            }
+            ctx.add(order, group)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] applying the scene `FogSettings` from depth with [DrawFogDesc], when their
+/// mode is `FogMode::PostPass`.
+///
+/// Uses the depth of `RenderHiZ`, which must be registered before this one, so only opaque
+/// static meshes occlude the fog. The target must have the size of the target selected with
+/// `RenderHiZ::with_size_of`. Fog is applied before the linear post effects.
+#[derive(Default, Debug)]
+pub struct RenderFog {
+    target: Target,
+}
+
+impl RenderFog {
+    /// Set target to which the fog will be applied.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderFog {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        plan.extend_target(self.target, |ctx| {
+            let depth = ctx.get_image(hiz_image(0))?;
+            ctx.add(
+                RenderOrder::LinearPostEffects,
+                DrawFogDesc::new()
+                    .with_target_depth(ctx.depth())
+                    .builder()
+                    .with_image(depth),
+            )?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// A [RenderPlugin] compositing the frame captured when the scene [Transition]
+/// begins over the following frames of the target, see [crate::transition].
+///
+/// The target must be rendered to an image displayed on another target, like the target of
+/// `RenderToWindow` with `WorkingSpace::LinearHdr`. The capture is kept in an image of the size
+/// of the target, and composited after the linear post effects of the plugins registered
+/// before this one.
+#[derive(Default, Debug)]
+pub struct RenderTransitions {
+    target: Target,
+}
+
+impl RenderTransitions {
+    /// Set target whose frames are captured and composited.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderTransitions {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        _builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.insert(Transition::default());
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        if plan.working_space() != WorkingSpace::LinearHdr {
+            return Err(format_err!(
+                "RenderTransitions plugin requires `WorkingSpace::LinearHdr`."
+            ));
+        }
+
+        let target = self.target;
+        let captured = Arc::new(Mutex::new(None));
+        let composited = captured.clone();
+        plan.extend_target(target, move |ctx| {
+            let metadata =
+                ctx.target_metadata(target)
+                    .ok_or_else(|| RenderError::MissingTarget {
+                        target,
+                        plugin: "RenderTransitions",
+                    })?;
+            let kind = Kind::D2(metadata.width(), metadata.height(), 1, 1);
+            let image = ctx
+                .graph()
+                .create_image(kind, 1, Format::Rgba16Sfloat, None);
+            *composited.lock().unwrap() = Some(image);
+            ctx.add(
+                RenderOrder::LinearPostEffects,
+                DrawTransitionDesc::new()
+                    .with_target_depth(ctx.depth())
+                    .builder()
+                    .with_image(image),
+            )?;
+            Ok(())
+        });
+        plan.extend_display_target(target, move |ctx| {
+            // The capture is overwritten once the target composited the previous one.
+            let scene_node = ctx.get_node(target)?;
+            let scene = ctx.get_image(TargetImage::Color(target, 0))?;
+            let image = captured
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| format_err!("Target {:?} wasn't planned.", target))?;
+            let capture = SubpassBuilder::new()
+                .with_group(DrawTransitionCaptureDesc::new().builder().with_image(scene))
+                .with_color(image)
+                .with_dependency(scene_node);
+            let capture = ctx.graph().add_node(capture.into_pass());
+            ctx.add_dep(capture);
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// Target the volumetric light scattering is rendered to at half resolution.
+pub const VOLUMETRICS_TARGET: Target = Target::Custom("volumetrics");
+
+/// A [RenderPlugin] scattering point and spot lights marked `volumetric` through a
+/// participating medium, see [DrawVolumetricsDesc].
+///
+/// Scattering is ray marched at half resolution against the depth of `RenderHiZ`, which must be
+/// registered before this one, then upsampled with [DrawBilateralUpsampleDesc] and added to the
+/// target before the linear post effects, so it is tonemapped with the rest of the scene.
+/// The target must have the size of the target selected with `RenderHiZ::with_size_of`.
+#[derive(Default, Debug)]
+pub struct RenderVolumetrics {
+    target: Target,
+    settings: VolumetricSettings,
+    upsample: BilateralUpsampleSettings,
+}
+
+impl RenderVolumetrics {
+    /// Set target to which the scattered light will be added.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the default medium settings.
+    pub fn with_settings(mut self, settings: VolumetricSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Set the settings of the upsample to the target.
+    pub fn with_upsample_settings(mut self, settings: BilateralUpsampleSettings) -> Self {
+        self.upsample = settings;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderVolumetrics {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let metadata = plan
+            .target_metadata(HIZ_DEPTH_TARGET, factory)
+            .ok_or_else(|| format_err!("RenderHiZ must be added before RenderVolumetrics."))?;
+        let sizes = hiz_level_sizes(metadata.width(), metadata.height());
+        let half_level = sizes.len().min(2) - 1;
+        let (width, height) = sizes[half_level];
+
+        plan.define_pass(
+            VOLUMETRICS_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind: Kind::D2(width, height, 1, 1),
+                    levels: 1,
+                    format: Format::Rgba16Sfloat,
+                    clear: None,
+                })],
+                depth: None,
+            },
+        )?;
+        let settings = self.settings.clone();
+        plan.extend_target(VOLUMETRICS_TARGET, move |ctx| {
+            let depth = ctx.get_image(hiz_image(half_level))?;
+            ctx.add(
+                RenderOrder::Opaque,
+                DrawVolumetricsDesc::new(settings)
+                    .builder()
+                    .with_image(depth),
+            )?;
+            Ok(())
+        });
+
+        let upsample =
+            DrawBilateralUpsampleDesc::new(UpsampleBlend::Add).with_settings(self.upsample.clone());
+        plan.extend_target(self.target, move |ctx| {
+            let mut group = upsample.with_target_depth(ctx.depth()).builder();
+            for &image in &[
+                TargetImage::Color(VOLUMETRICS_TARGET, 0),
+                hiz_image(0),
+                hiz_image(half_level),
+            ] {
+                group = group.with_image(ctx.get_image(image)?);
+            }
+            ctx.add(RenderOrder::LinearPostEffects, group)?;
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+/// RenderPlugin for rendering skyboxes.
+#[derive(Default, Debug)]
+pub struct RenderSkybox {
+    target: Target,
+    colors: Option<(Srgb, Srgb)>,
+    cubemap: Option<Handle<Texture>>,
+}
+
+impl RenderSkybox {
+    /// Create skybox with specified nadir and zenith colors.
+    pub fn with_colors(nadir_color: Srgb, zenith_color: Srgb) -> Self {
+        Self {
+            colors: Some((nadir_color, zenith_color)),
+            ..Default::default()
+        }
+    }
+
+    /// Draw given cubemap instead of the colors, see `DrawSkyboxDesc::with_cubemap`.
+    pub fn with_cubemap(mut self, cubemap: Handle<Texture>) -> Self {
+        self.cubemap = Some(cubemap);
+        self
+    }
+
+    /// Set target to which skybox will be rendered.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderSkybox {
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        _factory: &mut Factory<B>,
+        _world: &World,
+    ) -> Result<(), Error> {
+        let colors = self.colors;
+        let cubemap = self.cubemap.clone();
+        plan.extend_target(self.target, move |ctx| {
+            let mut desc = if let Some((nadir, zenith)) = colors {
+                DrawSkyboxDesc::with_colors(nadir, zenith)
+            } else {
+                DrawSkyboxDesc::new()
+            };
+            if let Some(cubemap) = cubemap.clone() {
+                desc = desc.with_cubemap(cubemap);
+            }
+            let group = desc.builder();
 
             ctx.add(RenderOrder::AfterOpaque, group)?;
             Ok(())
@@ -1,13 +1,18 @@
 //! GPU POD data types.
 use crate::{
+    blob_shadow::BlobShadow,
+    hiz::HiZBounds,
+    impostor::{Impostor, ImpostorBakeSettings, ImpostorLayout},
+    material_shader::MAX_MATERIAL_SHADER_PARAMS,
     mtl,
-    resources::Tint as TintComponent,
+    resources::{InstanceData as InstanceDataComponent, Tint as TintComponent},
+    shells::Shells,
     sprite::{SpriteRender, SpriteSheet},
     types::Texture,
 };
 use amethyst_assets::{AssetStorage, Handle};
 use amethyst_core::{
-    math::{convert, Matrix4, Vector4},
+    math::{convert, Matrix4, Vector3, Vector4},
     Transform,
 };
 use glsl_layout::*;
@@ -77,10 +82,27 @@ impl AsAttribute for Tint {
     const FORMAT: Format = Format::Rgba32Sfloat;
 }
 
+/// Instance-rate custom data, see the `InstanceData` component
+/// ```glsl,ignore
+/// vec4 instance_data;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, AsStd140)]
+#[repr(C, align(16))]
+pub struct InstanceData {
+    /// Custom data as `Rgba32Sfloat`
+    pub instance_data: vec4,
+}
+
+impl AsAttribute for InstanceData {
+    const NAME: &'static str = "instance_data";
+    const FORMAT: Format = Format::Rgba32Sfloat;
+}
+
 /// Instance-rate vertex arguments
 /// ```glsl,ignore
 ///  mat4 model;
 ///  vec4 tint;
+///  vec4 instance_data;
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 #[repr(C, align(16))]
@@ -89,6 +111,8 @@ pub struct VertexArgs {
     pub model: mat4,
     /// Instance-rate model `Tint`
     pub tint: vec4,
+    /// Instance-rate `InstanceData`
+    pub instance_data: vec4,
 }
 
 impl VertexArgs {
@@ -104,13 +128,23 @@ impl VertexArgs {
                 let (r, g, b, a) = t.0.into_linear().into_components();
                 [r, g, b, a].into()
             }),
+            instance_data: [0.0; 4].into(),
+        }
+    }
+
+    /// Set the custom data of the instance, left zeroed without `InstanceData`.
+    #[inline]
+    pub fn with_instance_data(mut self, data: Option<&InstanceDataComponent>) -> Self {
+        if let Some(data) = data {
+            self.instance_data = data.0.into();
         }
+        self
     }
 }
 
 impl AsVertex for VertexArgs {
     fn vertex() -> VertexFormat {
-        VertexFormat::new((Model::vertex(), Tint::vertex()))
+        VertexFormat::new((Model::vertex(), Tint::vertex(), InstanceData::vertex()))
     }
 }
 
@@ -130,11 +164,30 @@ impl AsAttribute for JointsOffset {
     const FORMAT: Format = Format::R32Uint;
 }
 
-/// Skinned Instance-rate vertex arguments.
+/// Instance-rate morph target offsets: index of the first weight, index of the first delta and
+/// number of targets.
+/// ```glsl,ignore
+///  uvec3 morph_offsets;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct MorphOffsets {
+    /// Offsets of the weights and deltas, and number of targets
+    pub morph_offsets: [u32; 3],
+}
+
+impl AsAttribute for MorphOffsets {
+    const NAME: &'static str = "morph_offsets";
+    const FORMAT: Format = Format::Rgb32Uint;
+}
+
+/// Instance-rate vertex arguments of deformed meshes, which are skinned, morphed or both.
 /// ```glsl,ignore
 ///  mat4 model;
 ///  vec4 tint;
-///  uint joints_offset:
+///  vec4 instance_data;
+///  uint joints_offset;
+///  uvec3 morph_offsets;
 /// ```
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 #[repr(C, packed)]
@@ -143,13 +196,23 @@ pub struct SkinnedVertexArgs {
     pub model: mat4,
     /// Instance-rate `Tint`
     pub tint: vec4,
+    /// Instance-rate `InstanceData`
+    pub instance_data: vec4,
     /// Instance-rate joint offset as `u32`
     pub joints_offset: u32,
+    /// Instance-rate morph target offsets, see `MorphOffsets`
+    pub morph_offsets: [u32; 3],
 }
 
 impl AsVertex for SkinnedVertexArgs {
     fn vertex() -> VertexFormat {
-        VertexFormat::new((Model::vertex(), Tint::vertex(), JointsOffset::vertex()))
+        VertexFormat::new((
+            Model::vertex(),
+            Tint::vertex(),
+            InstanceData::vertex(),
+            JointsOffset::vertex(),
+            MorphOffsets::vertex(),
+        ))
     }
 }
 
@@ -169,8 +232,26 @@ impl SkinnedVertexArgs {
                 let (r, g, b, a) = t.0.into_linear().into_components();
                 [r, g, b, a].into()
             }),
+            instance_data: [0.0; 4].into(),
             joints_offset,
+            morph_offsets: [0; 3],
+        }
+    }
+
+    /// Set the custom data of the instance, left zeroed without `InstanceData`.
+    #[inline]
+    pub fn with_instance_data(mut self, data: Option<&InstanceDataComponent>) -> Self {
+        if let Some(data) = data {
+            self.instance_data = data.0.into();
         }
+        self
+    }
+
+    /// Set the morph target offsets of a morphed mesh.
+    #[inline]
+    pub fn with_morph_offsets(mut self, morph_offsets: [u32; 3]) -> Self {
+        self.morph_offsets = morph_offsets;
+        self
     }
 }
 
@@ -178,6 +259,7 @@ impl SkinnedVertexArgs {
 /// ```glsl,ignore
 /// struct PointLight {
 ///    vec3 position;
+///    uint channels;
 ///    vec3 color;
 ///    float intensity;
 /// };
@@ -186,6 +268,8 @@ impl SkinnedVertexArgs {
 pub struct PointLight {
     /// Light world position
     pub position: vec3,
+    /// Light channels bitmask
+    pub channels: uint,
     /// Light color
     pub color: vec3,
     /// Light intensity (0 - infinity)
@@ -198,6 +282,7 @@ pub struct PointLight {
 ///    vec3 color;
 ///    float intensity;
 ///    vec3 direction;
+///    uint channels;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -208,12 +293,15 @@ pub struct DirectionalLight {
     pub intensity: float,
     /// light cast direction vector
     pub direction: vec3,
+    /// Light channels bitmask
+    pub channels: uint,
 }
 
 /// spot light struct
 /// ```glsl,ignore
 /// struct SpotLight {
 ///    vec3 position;
+///    uint channels;
 ///    vec3 color;
 ///    vec3 direction;
 ///    float angle;
@@ -226,6 +314,8 @@ pub struct DirectionalLight {
 pub struct SpotLight {
     /// Light world position
     pub position: vec3,
+    /// Light channels bitmask
+    pub channels: uint,
     /// Light Color
     pub color: vec3,
     /// Light direction
@@ -248,6 +338,8 @@ pub struct SpotLight {
 ///    int point_light_count;
 ///    int directional_light_count;
 ///    int spot_light_count;
+///    float surface_layer;
+///    int capsule_count;
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -262,6 +354,88 @@ pub struct Environment {
     pub directional_light_count: int,
     /// Number of spot lights
     pub spot_light_count: int,
+    /// Amount of the surface layer over the scene, see `SurfaceLayer`
+    pub surface_layer: float,
+    /// Number of capsule occluders
+    pub capsule_count: int,
+}
+
+/// Capsule occluder struct
+/// ```glsl,ignore
+/// struct CapsuleOccluder {
+///    vec3 start;
+///    float radius;
+///    vec3 end;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct CapsuleOccluder {
+    /// World position of one end of the axis
+    pub start: vec3,
+    /// Capsule radius
+    pub radius: float,
+    /// World position of the other end of the axis
+    pub end: vec3,
+}
+
+/// Fog Uniform
+/// ```glsl,ignore
+/// struct Fog {
+///    vec3 color;
+///    float density;
+///    vec3 inscattering_color;
+///    float inscattering_exponent;
+///    vec3 inscattering_direction;
+///    float base_height;
+///    float height_falloff;
+///    float max_opacity;
+///    int mode;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct Fog {
+    /// Fog color away from the sun
+    pub color: vec3,
+    /// Fog extinction at the base height
+    pub density: float,
+    /// Fog color towards the sun
+    pub inscattering_color: vec3,
+    /// Sharpness of the inscattering lobe
+    pub inscattering_exponent: float,
+    /// Normalized direction of the sun light
+    pub inscattering_direction: vec3,
+    /// Height below which the density is constant
+    pub base_height: float,
+    /// Density falloff above the base height
+    pub height_falloff: float,
+    /// Upper bound of the fog opacity
+    pub max_opacity: float,
+    /// 0 when disabled, 1 for forward shading, 2 for the post pass
+    pub mode: int,
+}
+
+/// Shadow map lookup of the light casting shadows
+/// ```glsl,ignore
+/// uniform ShadowArgs {
+///    mat4 proj_view;
+///    float depth_bias;
+///    float slope_bias;
+///    int light;
+///    int filter_radius;
+/// };
+/// ```
+#[derive(Clone, Copy, Debug, AsStd140)]
+pub struct ShadowArgs {
+    /// Premultiplied Proj-View matrix of the shadow map
+    pub proj_view: mat4,
+    /// Depth bias, in depth of the shadow map
+    pub depth_bias: float,
+    /// Slope bias, in depth of the shadow map
+    pub slope_bias: float,
+    /// Index of the directional light casting shadows, -1 without shadow map
+    pub light: int,
+    /// Distance of the farthest lookups from the center of the filter, in texels
+    pub filter_radius: int,
 }
 
 /// Material Uniform
@@ -269,6 +443,28 @@ pub struct Environment {
 /// uniform Material {
 ///    UvOffset uv_offset;
 ///    float alpha_cutoff;
+///    vec3 emission_factor;
+///    vec3 uv_transform_u;
+///    vec3 uv_transform_v;
+///    vec3 normal_uv_transform_u;
+///    vec3 normal_uv_transform_v;
+///    float dissolve_amount;
+///    float dissolve_edge_width;
+///    vec3 dissolve_edge_color;
+///    float triplanar_scale;
+///    float triplanar_sharpness;
+///    float detail_scale;
+///    float detail_fade_distance;
+///    uint detail_blend;
+///    float layer_amount;
+///    float layer_scene_factor;
+///    float layer_roughness;
+///    vec3 layer_color;
+///    uint light_channels;
+///    float subsurface;
+///    vec3 specular;
+///    float shininess;
+///    vec4 shader_params[MAX_MATERIAL_SHADER_PARAMS];
 /// };
 /// ```
 #[derive(Clone, Copy, Debug, AsStd140)]
@@ -278,14 +474,85 @@ pub struct Material {
     pub uv_offset: TextureOffset,
     /// Material alpha cutoff
     pub alpha_cutoff: float,
+    /// Linear RGB factor of the emission map
+    pub emission_factor: vec3,
+    /// First row of the UV transform of the material
+    pub uv_transform_u: vec3,
+    /// Second row of the UV transform of the material
+    pub uv_transform_v: vec3,
+    /// First row of the UV transform of the normal map of the material
+    pub normal_uv_transform_u: vec3,
+    /// Second row of the UV transform of the normal map of the material
+    pub normal_uv_transform_v: vec3,
+    /// Dissolve amount of the material
+    pub dissolve_amount: float,
+    /// Width of the edge of the dissolve of the material
+    pub dissolve_edge_width: float,
+    /// Linear RGB color of the edge of the dissolve of the material
+    pub dissolve_edge_color: vec3,
+    /// Triplanar mapping scale of the material
+    pub triplanar_scale: float,
+    /// Triplanar mapping blend sharpness of the material
+    pub triplanar_sharpness: float,
+    /// Detail map scale of the material
+    pub detail_scale: float,
+    /// Detail map fade distance of the material
+    pub detail_fade_distance: float,
+    /// Detail map blending of the material, 0 for overlay and 1 for linear
+    pub detail_blend: uint,
+    /// Surface layer amount of the material
+    pub layer_amount: float,
+    /// Fraction of the scene surface layer amount added to the material amount
+    pub layer_scene_factor: float,
+    /// Roughness under the surface layer
+    pub layer_roughness: float,
+    /// Albedo factor under the surface layer
+    pub layer_color: vec3,
+    /// Light channels bitmask of the material
+    pub light_channels: uint,
+    /// Subsurface scattering strength of the material
+    pub subsurface: float,
+    /// Specular color of the material
+    pub specular: vec3,
+    /// Specular exponent of the material
+    pub shininess: float,
+    /// Parameters of the material shader of the passes drawing the material
+    pub shader_params: [vec4; MAX_MATERIAL_SHADER_PARAMS],
 }
 
 impl Material {
     /// Helper function from amethyst_rendy 'proper' type to POD type.
     pub fn from_material(mat: &mtl::Material) -> Self {
+        let [uv_transform_u, uv_transform_v] = mat.uv_transform.rows();
+        let [normal_uv_transform_u, normal_uv_transform_v] = mat.normal_uv_transform.rows();
         Material {
             uv_offset: TextureOffset::from_offset(&mat.uv_offset),
             alpha_cutoff: mat.alpha_cutoff,
+            emission_factor: mat.emission_factor.into(),
+            uv_transform_u: uv_transform_u.into(),
+            uv_transform_v: uv_transform_v.into(),
+            normal_uv_transform_u: normal_uv_transform_u.into(),
+            normal_uv_transform_v: normal_uv_transform_v.into(),
+            dissolve_amount: mat.dissolve_amount,
+            dissolve_edge_width: mat.dissolve_edge_width,
+            dissolve_edge_color: mat.dissolve_edge_color.into(),
+            triplanar_scale: mat.triplanar_scale,
+            triplanar_sharpness: mat.triplanar_sharpness,
+            detail_scale: mat.detail_scale,
+            detail_fade_distance: mat.detail_fade_distance,
+            detail_blend: match mat.detail_blend {
+                mtl::DetailBlend::Overlay => 0,
+                mtl::DetailBlend::Linear => 1,
+            },
+            layer_amount: mat.layer_amount,
+            layer_scene_factor: mat.layer_scene_factor,
+            layer_roughness: mat.layer_roughness,
+            layer_color: mat.layer_color.into(),
+            light_channels: mat.light_channels,
+            subsurface: mat.subsurface,
+            specular: mat.specular.into(),
+            shininess: mat.shininess,
+            shader_params: mat.shader_params.map(Into::into),
         }
     }
 }
@@ -379,6 +646,363 @@ impl SpriteArgs {
     }
 }
 
+/// Instance-rate impostor arguments
+/// ```glsl,ignore
+///  vec3 center;
+///  vec3 right;
+///  vec3 up;
+///  vec2 u_offset;
+///  vec2 u_offset_next;
+///  float blend;
+///  float alpha_cutoff;
+///  vec4 tint;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct ImpostorArgs {
+    /// Center of the quad in world space
+    pub center: vec3,
+    /// Half of the quad width along its horizontal axis
+    pub right: vec3,
+    /// Half of the quad height along its vertical axis
+    pub up: vec3,
+    /// Left and right coordinate of the view in the atlas
+    pub u_offset: vec2,
+    /// Left and right coordinate of the view blended with the first one
+    pub u_offset_next: vec2,
+    /// Weight of the second view
+    pub blend: float,
+    /// Pixels with lower alpha are discarded
+    pub alpha_cutoff: float,
+    /// Tint of the impostor
+    pub tint: vec4,
+}
+
+impl AsVertex for ImpostorArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgb32Sfloat, "center"),
+            (Format::Rgb32Sfloat, "right"),
+            (Format::Rgb32Sfloat, "up"),
+            (Format::Rg32Sfloat, "u_offset"),
+            (Format::Rg32Sfloat, "u_offset_next"),
+            (Format::R32Sfloat, "blend"),
+            (Format::R32Sfloat, "alpha_cutoff"),
+            (Format::Rgba32Sfloat, "tint"),
+        ))
+    }
+}
+
+impl ImpostorArgs {
+    /// Compute the camera-facing quad of an impostor seen from the given camera position.
+    pub fn from_object_data(
+        impostor: &Impostor,
+        transform: &Transform,
+        tint: Option<&TintComponent>,
+        camera_position: Vector3<f32>,
+    ) -> Self {
+        let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+        let layout = &impostor.layout;
+        let center = (matrix * Vector3::from(layout.center).push(1.0)).xyz();
+        let scale = matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]);
+        let radius = layout.radius * scale;
+
+        let axis_x = matrix.column(0).xyz();
+        let axis_y = matrix.column(1).xyz();
+        let axis_z = matrix.column(2).xyz();
+        let to_camera = camera_position - center;
+        let up = axis_y.normalize();
+        let right = up
+            .cross(&to_camera)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| axis_x.normalize());
+
+        let to_camera = Vector3::new(
+            to_camera.dot(&axis_x),
+            to_camera.dot(&axis_y),
+            to_camera.dot(&axis_z),
+        );
+        let (view, next, blend) = if impostor.blend_views {
+            layout.nearest_views(to_camera)
+        } else {
+            let view = layout.nearest_view(to_camera);
+            (view, view, 0.0)
+        };
+
+        ImpostorArgs {
+            center: center.into_pod(),
+            right: (right * radius).into_pod(),
+            up: (up * radius).into_pod(),
+            u_offset: layout.view_u_range(view).into(),
+            u_offset_next: layout.view_u_range(next).into(),
+            blend,
+            alpha_cutoff: impostor.alpha_cutoff,
+            tint: tint.map_or([1.0; 4].into(), |t| {
+                // Shaders expect linear RGBA; convert sRGBA to linear RGBA
+                let (r, g, b, a) = t.0.into_linear().into_components();
+                [r, g, b, a].into()
+            }),
+        }
+    }
+}
+
+/// Instance-rate arguments of a view of a baked impostor
+/// ```glsl,ignore
+///  mat4 view_transform;
+///  vec4 light;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(16))]
+pub struct ImpostorBakeArgs {
+    /// Object space to the clip space of the slot of the view in the atlas
+    pub view_transform: mat4,
+    /// Direction towards the key light in object space, and ambient fraction
+    pub light: vec4,
+}
+
+impl AsVertex for ImpostorBakeArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgba32Sfloat, "view_transform"),
+            (Format::Rgba32Sfloat, "view_transform"),
+            (Format::Rgba32Sfloat, "view_transform"),
+            (Format::Rgba32Sfloat, "view_transform"),
+            (Format::Rgba32Sfloat, "light"),
+        ))
+    }
+}
+
+impl ImpostorBakeArgs {
+    /// Arguments of every view of an atlas baked with given settings.
+    pub fn views(
+        layout: &ImpostorLayout,
+        settings: &ImpostorBakeSettings,
+    ) -> impl Iterator<Item = Self> {
+        let light = -Vector3::from(settings.light_direction)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+        let light: vec4 = [light.x, light.y, light.z, settings.ambient].into();
+        let layout = ImpostorLayout {
+            angles: settings.angles.max(1),
+            ..*layout
+        };
+        (0..layout.angles).map(move |view| {
+            let transform: [[f32; 4]; 4] = layout.view_transform(view).into();
+            ImpostorBakeArgs {
+                view_transform: transform.into(),
+                light,
+            }
+        })
+    }
+}
+
+/// Instance-rate blob shadow arguments
+/// ```glsl,ignore
+///  vec3 center;
+///  vec3 axis_x;
+///  vec3 axis_z;
+///  float opacity;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct BlobShadowArgs {
+    /// Center of the ellipse on the ground in world space
+    pub center: vec3,
+    /// First radius of the ellipse, along the ground
+    pub axis_x: vec3,
+    /// Second radius of the ellipse, along the ground
+    pub axis_z: vec3,
+    /// Darkening of the center of the ellipse
+    pub opacity: float,
+}
+
+impl AsVertex for BlobShadowArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgb32Sfloat, "center"),
+            (Format::Rgb32Sfloat, "axis_x"),
+            (Format::Rgb32Sfloat, "axis_z"),
+            (Format::R32Sfloat, "opacity"),
+        ))
+    }
+}
+
+impl BlobShadowArgs {
+    /// Compute the ellipse of a blob shadow on a ground plane at height `ground`, or `None`
+    /// when the entity casts no shadow on it.
+    pub fn from_object_data(
+        shadow: &BlobShadow,
+        transform: &Transform,
+        ground: f32,
+    ) -> Option<Self> {
+        let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+        let position = matrix.column(3).xyz();
+        let (radius, opacity) = shadow.at_height(position.y - ground)?;
+
+        // The ellipse turns with the yaw of the entity.
+        let flat =
+            |axis: Vector3<f32>| Vector3::new(axis.x, 0.0, axis.z).try_normalize(f32::EPSILON);
+        let axis_x = flat(matrix.column(0).xyz())
+            .or_else(|| flat(matrix.column(2).xyz()).map(|z| Vector3::new(z.z, 0.0, -z.x)))
+            .unwrap_or_else(Vector3::x);
+        let axis_z = axis_x.cross(&Vector3::y());
+
+        Some(BlobShadowArgs {
+            center: [position.x, ground, position.z].into(),
+            axis_x: (axis_x * radius[0]).into_pod(),
+            axis_z: (axis_z * radius[1]).into_pod(),
+            opacity,
+        })
+    }
+}
+
+/// Instance-rate arguments of a GPU particle written into the simulation state, an instance
+/// per emitted particle
+/// ```glsl,ignore
+///  uint slot;
+///  vec3 position;
+///  vec3 velocity;
+///  float spread;
+///  float lifetime;
+///  uint seed;
+///  vec4 start_color;
+///  vec4 end_color;
+///  vec2 size;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct GpuParticleSpawnArgs {
+    /// Texel of the particle in the simulation state, in row-major order
+    pub slot: uint,
+    /// Position in world space
+    pub position: vec3,
+    /// Initial velocity in world space
+    pub velocity: vec3,
+    /// Angle around the velocity the direction is randomly picked in
+    pub spread: float,
+    /// Seconds the particle lives
+    pub lifetime: float,
+    /// Seed of the random direction
+    pub seed: uint,
+    /// Linear color when emitted
+    pub start_color: vec4,
+    /// Linear color at the end of the lifetime
+    pub end_color: vec4,
+    /// Diameter when emitted and at the end of the lifetime
+    pub size: vec2,
+}
+
+impl AsVertex for GpuParticleSpawnArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::R32Uint, "slot"),
+            (Format::Rgb32Sfloat, "position"),
+            (Format::Rgb32Sfloat, "velocity"),
+            (Format::R32Sfloat, "spread"),
+            (Format::R32Sfloat, "lifetime"),
+            (Format::R32Uint, "seed"),
+            (Format::Rgba32Sfloat, "start_color"),
+            (Format::Rgba32Sfloat, "end_color"),
+            (Format::Rg32Sfloat, "size"),
+        ))
+    }
+}
+
+/// Instance-rate shell arguments, an instance per shell
+/// ```glsl,ignore
+///  mat4 model;
+///  vec4 color;
+///  vec3 offset;
+///  float density_scale;
+///  float height;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, packed)]
+pub struct ShellArgs {
+    /// Instance-rate model matrix
+    pub model: mat4,
+    /// Linear color of the strands at the height of the shell
+    pub color: vec4,
+    /// Offset of the shell from the mesh: object space length along the normals in `w`, world
+    /// space comb offset in `xyz`
+    pub offset: vec4,
+    /// Repeats of the density map per repeat of the texture coordinates
+    pub density_scale: float,
+    /// Height of the shell as a fraction of the strand length
+    pub height: float,
+}
+
+impl AsVertex for ShellArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            Model::vertex(),
+            (Format::Rgba32Sfloat, "color"),
+            (Format::Rgba32Sfloat, "offset"),
+            (Format::R32Sfloat, "density_scale"),
+            (Format::R32Sfloat, "height"),
+        ))
+    }
+}
+
+impl ShellArgs {
+    /// Arguments of every shell of an entity.
+    pub fn from_object_data<'a>(
+        shells: &'a Shells,
+        transform: &Transform,
+    ) -> impl Iterator<Item = Self> + 'a {
+        let model: [[f32; 4]; 4] = convert::<_, Matrix4<f32>>(*transform.global_matrix()).into();
+        // Shaders expect linear RGBA; convert sRGBA to linear RGBA
+        let linear = |color: palette::Srgba| {
+            let (r, g, b, a) = color.into_linear().into_components();
+            Vector4::new(r, g, b, a)
+        };
+        let (root, tip) = (linear(shells.root_color), linear(shells.tip_color));
+        shells.heights().map(move |height| {
+            let color: [f32; 4] = root.lerp(&tip, height).into();
+            let comb = Vector3::from(shells.comb) * shells.length * height * height;
+            ShellArgs {
+                model: model.into(),
+                color: color.into(),
+                offset: [comb.x, comb.y, comb.z, shells.length * height].into(),
+                density_scale: shells.density_scale,
+                height,
+            }
+        })
+    }
+}
+
+/// Instance-rate Hi-Z occlusion test arguments
+/// ```glsl,ignore
+///  vec4 rect;
+///  float nearest;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[repr(C, align(4))]
+pub struct HiZOcclusionArgs {
+    /// Screen bounds in normalized device coordinates, top left corner followed by bottom right
+    pub rect: vec4,
+    /// Depth of the point nearest to the camera
+    pub nearest: float,
+}
+
+impl AsVertex for HiZOcclusionArgs {
+    fn vertex() -> VertexFormat {
+        VertexFormat::new((
+            (Format::Rgba32Sfloat, "rect"),
+            (Format::R32Sfloat, "nearest"),
+        ))
+    }
+}
+
+impl From<&HiZBounds> for HiZOcclusionArgs {
+    fn from(bounds: &HiZBounds) -> Self {
+        HiZOcclusionArgs {
+            rect: [bounds.min[0], bounds.min[1], bounds.max[0], bounds.max[1]].into(),
+            nearest: bounds.nearest,
+        }
+    }
+}
+
 /// Trait for auto conversion into standard GLSL POD types.
 pub trait IntoPod<T> {
     /// Converts `Self` to the supplied `T` GLSL type.
@@ -426,3 +1050,29 @@ impl IntoPod<[f32; 4]> for palette::Srgba {
         [r, g, b, a]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_channels_fill_the_std140_padding() {
+        // The channels take the slot std140 already reserves after a `vec3`, so the lights
+        // keep the size the shaders index them with.
+        assert_eq!(std::mem::size_of::<<PointLight as AsStd140>::Std140>(), 32);
+        assert_eq!(
+            std::mem::size_of::<<DirectionalLight as AsStd140>::Std140>(),
+            32
+        );
+        assert_eq!(std::mem::size_of::<<SpotLight as AsStd140>::Std140>(), 64);
+    }
+
+    #[test]
+    fn capsule_count_fills_the_environment_padding() {
+        assert_eq!(std::mem::size_of::<<Environment as AsStd140>::Std140>(), 48);
+        assert_eq!(
+            std::mem::size_of::<<CapsuleOccluder as AsStd140>::Std140>(),
+            32
+        );
+    }
+}
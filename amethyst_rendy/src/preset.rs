@@ -0,0 +1,442 @@
+//! Curated starting points for the 3D render path.
+//!
+//! A [RenderPathPreset] is a quality level, turned into a [RenderPath] listing the plugins of
+//! the 3D render path and their settings. The path is plain data, so a preset can be inspected
+//! and modified before registering the path as a plugin of the `RenderingBundle`, after the
+//! plugin defining its target, e.g. `RenderToWindow`:
+//!
+//! ```
+//! # use amethyst_rendy::preset::RenderPathPreset;
+//! let mut path = RenderPathPreset::High.path();
+//! path.fog = false;
+//! path.light_limits.point = 16;
+//! ```
+//!
+//! When the render graph is planned, the path is validated against the [Capabilities] of the
+//! device, and features it can't render are disabled with a warning, see
+//! [RenderPath::validate].
+
+use crate::{
+    bundle::{RenderPlan, RenderPlugin, Target},
+    light::LightLimits,
+    pass::{SubsurfaceProfile, VolumetricSettings},
+    plugins::{
+        RenderBlobShadows, RenderFog, RenderHiZ, RenderImpostors, RenderPbr3D, RenderShaded3D,
+        RenderShadows, RenderSkybox, RenderVolumetrics,
+    },
+    visibility::DrawDistanceSettings,
+    Backend, Factory, Format,
+};
+use amethyst_core::ecs::{DispatcherBuilder, World};
+use amethyst_error::Error;
+use rendy::hal::{format::ImageFeature, PhysicalDevice};
+
+/// Quality level of a [RenderPath].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenderPathPreset {
+    /// Shaded lighting and blob shadows, with short draw distances and few lights.
+    Low,
+    /// Physically-based lighting and blob shadows.
+    Medium,
+    /// Adds a shadow map, the Hi-Z depth chain, post-pass fog, indirect draws and a retained
+    /// draw list.
+    High,
+    /// Adds volumetric lighting, with a sharper shadow map, long draw distances and every
+    /// light.
+    Ultra,
+}
+
+impl RenderPathPreset {
+    /// Every preset, from the lowest quality to the highest.
+    pub const ALL: [RenderPathPreset; 4] = [
+        RenderPathPreset::Low,
+        RenderPathPreset::Medium,
+        RenderPathPreset::High,
+        RenderPathPreset::Ultra,
+    ];
+
+    /// Render path of the preset, drawing to `Target::Main`.
+    pub fn path(self) -> RenderPath {
+        let low = RenderPath {
+            target: Target::Main,
+            lighting: Lighting::Shaded,
+            skybox: true,
+            blob_shadows: true,
+            shadow_map: None,
+            impostors: true,
+            hiz: false,
+            fog: false,
+            volumetrics: None,
+            subsurface: None,
+            indirect_draws: false,
+            retained_draw_list: false,
+            draw_distance_scale: 0.5,
+            light_limits: LightLimits {
+                point: 16,
+                directional: 2,
+                spot: 8,
+            },
+        };
+        match self {
+            RenderPathPreset::Low => low,
+            RenderPathPreset::Medium => RenderPath {
+                lighting: Lighting::Pbr,
+                draw_distance_scale: 0.75,
+                light_limits: LightLimits {
+                    point: 32,
+                    directional: 4,
+                    spot: 16,
+                },
+                ..low
+            },
+            RenderPathPreset::High => RenderPath {
+                lighting: Lighting::Pbr,
+                shadow_map: Some(2048),
+                hiz: true,
+                fog: true,
+                indirect_draws: true,
+                retained_draw_list: true,
+                draw_distance_scale: 1.0,
+                light_limits: LightLimits {
+                    point: 64,
+                    directional: 8,
+                    spot: 32,
+                },
+                ..low
+            },
+            RenderPathPreset::Ultra => RenderPath {
+                lighting: Lighting::Pbr,
+                shadow_map: Some(4096),
+                hiz: true,
+                fog: true,
+                volumetrics: Some(VolumetricSettings::default()),
+                indirect_draws: true,
+                retained_draw_list: true,
+                draw_distance_scale: 1.5,
+                light_limits: LightLimits::default(),
+                ..low
+            },
+        }
+    }
+}
+
+/// Lighting model of the 3D passes of a [RenderPath].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lighting {
+    /// `RenderShaded3D`.
+    Shaded,
+    /// `RenderPbr3D`.
+    Pbr,
+}
+
+/// Plugins of a 3D render path and their settings, see the [module documentation](self).
+///
+/// A [RenderPlugin] adding the enabled plugins, and inserting the `DrawDistanceSettings` scale
+/// and the `LightLimits` of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderPath {
+    /// Target the path draws to, which must be defined by a plugin registered before this one.
+    pub target: Target,
+    /// Lighting model of the 3D passes.
+    pub lighting: Lighting,
+    /// Draw the skybox.
+    pub skybox: bool,
+    /// Draw the blob shadows of entities with a `BlobShadow`.
+    pub blob_shadows: bool,
+    /// Draw a shadow map of given resolution for the light selected by `ShadowSettings`,
+    /// replacing the blob shadows while a light casts shadows.
+    pub shadow_map: Option<u32>,
+    /// Draw distant entities with an `Impostor` as impostors.
+    pub impostors: bool,
+    /// Build the hierarchical depth buffer of the opaque scene, required by `fog` and
+    /// `volumetrics`.
+    pub hiz: bool,
+    /// Apply the `FogSettings` in a post pass, when their mode is `FogMode::PostPass`.
+    pub fog: bool,
+    /// Scatter the volumetric lights through a medium with given default settings.
+    pub volumetrics: Option<VolumetricSettings>,
+    /// Scatter the lighting of subsurface materials with given profile.
+    pub subsurface: Option<SubsurfaceProfile>,
+    /// Draw opaque meshes with indirect draws.
+    pub indirect_draws: bool,
+    /// Keep the batches of opaque meshes between frames.
+    pub retained_draw_list: bool,
+    /// `DrawDistanceSettings::scale` applied to all draw distances.
+    pub draw_distance_scale: f32,
+    /// Lights drawn by the 3D passes.
+    pub light_limits: LightLimits,
+}
+
+impl Default for RenderPath {
+    fn default() -> Self {
+        RenderPathPreset::High.path()
+    }
+}
+
+impl RenderPath {
+    /// Adjust the path to what it requires and what the device can render, returning a
+    /// message per changed setting.
+    ///
+    /// `fog` and `volumetrics` enable `hiz`. Without float color targets, `hiz`, `fog` and
+    /// `volumetrics` are disabled, and without half float color targets, `volumetrics` and
+    /// `subsurface` are. `indirect_draws` are disabled when the device can't draw indirectly.
+    pub fn validate(&mut self, capabilities: &Capabilities) -> Vec<String> {
+        let mut changes = Vec::new();
+        if !self.hiz && (self.fog || self.volumetrics.is_some()) {
+            self.hiz = true;
+            changes.push("Fog and volumetrics require the Hi-Z chain, enabled it".to_string());
+        }
+        if self.hiz && !capabilities.float_targets {
+            self.hiz = false;
+            self.fog = false;
+            self.volumetrics = None;
+            changes.push(
+                "Device can't render float color targets, disabled the Hi-Z chain, fog and \
+                 volumetrics"
+                    .to_string(),
+            );
+        }
+        if (self.volumetrics.is_some() || self.subsurface.is_some())
+            && !capabilities.half_float_targets
+        {
+            self.volumetrics = None;
+            self.subsurface = None;
+            changes.push(
+                "Device can't render half float color targets, disabled volumetrics and \
+                 subsurface scattering"
+                    .to_string(),
+            );
+        }
+        if self.indirect_draws && !capabilities.indirect_draws {
+            self.indirect_draws = false;
+            changes.push("Device can't draw indirectly, disabled indirect draws".to_string());
+        }
+        changes
+    }
+
+    fn plugins<B: Backend>(&self) -> Vec<Box<dyn RenderPlugin<B>>> {
+        let mut plugins: Vec<Box<dyn RenderPlugin<B>>> = Vec::new();
+        if self.skybox {
+            plugins.push(Box::new(RenderSkybox::default().with_target(self.target)));
+        }
+        if let Some(resolution) = self.shadow_map {
+            plugins.push(Box::new(
+                RenderShadows::default().with_resolution(resolution),
+            ));
+        }
+        match self.lighting {
+            Lighting::Shaded => plugins.push(Box::new(self.base_3d(RenderShaded3D::default()))),
+            Lighting::Pbr => plugins.push(Box::new(self.base_3d(RenderPbr3D::default()))),
+        }
+        if self.blob_shadows {
+            plugins.push(Box::new(
+                RenderBlobShadows::default().with_target(self.target),
+            ));
+        }
+        if self.impostors {
+            plugins.push(Box::new(
+                RenderImpostors::default().with_target(self.target),
+            ));
+        }
+        if self.hiz {
+            plugins.push(Box::new(RenderHiZ::default().with_size_of(self.target)));
+            if self.fog {
+                plugins.push(Box::new(RenderFog::default().with_target(self.target)));
+            }
+            if let Some(settings) = self.volumetrics.clone() {
+                plugins.push(Box::new(
+                    RenderVolumetrics::default()
+                        .with_target(self.target)
+                        .with_settings(settings),
+                ));
+            }
+        }
+        plugins
+    }
+
+    fn base_3d<D: crate::pass::Base3DPassDef>(
+        &self,
+        mut plugin: crate::plugins::RenderBase3D<D>,
+    ) -> crate::plugins::RenderBase3D<D> {
+        plugin = plugin.with_target(self.target);
+        if self.indirect_draws {
+            plugin = plugin.with_indirect_draws();
+        }
+        if self.retained_draw_list {
+            plugin = plugin.with_retained_draw_list();
+        }
+        if let Some(profile) = self.subsurface.clone() {
+            plugin = plugin.with_subsurface(profile);
+        }
+        if self.shadow_map.is_some() {
+            plugin = plugin.with_shadows();
+        }
+        plugin
+    }
+}
+
+impl<B: Backend> RenderPlugin<B> for RenderPath {
+    fn on_build<'a, 'b>(
+        &mut self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world
+            .entry::<DrawDistanceSettings>()
+            .or_insert_with(DrawDistanceSettings::default)
+            .scale = self.draw_distance_scale;
+        world.insert(self.light_limits.clone());
+        for mut plugin in self.plugins::<B>() {
+            plugin.on_build(world, builder)?;
+        }
+        Ok(())
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<B>,
+        factory: &mut Factory<B>,
+        world: &World,
+    ) -> Result<(), Error> {
+        let mut path = self.clone();
+        for change in path.validate(&Capabilities::of(factory)) {
+            log::warn!("{}.", change);
+        }
+        for mut plugin in path.plugins::<B>() {
+            plugin.on_plan(plan, factory, world)?;
+        }
+        Ok(())
+    }
+}
+
+/// Features of the device a [RenderPath] depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Sampled `R32Sfloat` color attachments, for the Hi-Z chain.
+    pub float_targets: bool,
+    /// Sampled `Rgba16Sfloat` color attachments, for volumetrics and subsurface scattering.
+    pub half_float_targets: bool,
+    /// Indirect draws with a first instance.
+    pub indirect_draws: bool,
+}
+
+impl Capabilities {
+    /// A device supporting every feature.
+    pub fn all() -> Self {
+        Self {
+            float_targets: true,
+            half_float_targets: true,
+            indirect_draws: true,
+        }
+    }
+
+    /// Features supported by the device of given factory.
+    pub fn of<B: Backend>(factory: &Factory<B>) -> Self {
+        let physical = factory.physical();
+        let target = |format| {
+            physical
+                .format_properties(Some(format))
+                .optimal_tiling
+                .contains(ImageFeature::COLOR_ATTACHMENT | ImageFeature::SAMPLED)
+        };
+        Self {
+            float_targets: target(Format::R32Sfloat),
+            half_float_targets: target(Format::Rgba16Sfloat),
+            indirect_draws: physical
+                .features()
+                .contains(rendy::hal::Features::DRAW_INDIRECT_FIRST_INSTANCE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bundle::{ImageOptions, OutputColor, TargetPlanOutputs},
+        types::DefaultBackend,
+        Kind,
+    };
+    use rendy::hal::command::{ClearDepthStencil, ClearValue};
+
+    #[test]
+    fn presets_are_valid_on_a_capable_device() {
+        for &preset in RenderPathPreset::ALL.iter() {
+            let mut path = preset.path();
+            assert!(
+                path.validate(&Capabilities::all()).is_empty(),
+                "{:?}",
+                preset
+            );
+            assert_eq!(path, preset.path());
+        }
+    }
+
+    #[test]
+    fn missing_features_are_downgraded() {
+        let mut path = RenderPathPreset::Ultra.path();
+        path.subsurface = Some(SubsurfaceProfile::skin());
+        let changes = path.validate(&Capabilities {
+            float_targets: true,
+            half_float_targets: false,
+            indirect_draws: false,
+        });
+        assert_eq!(changes.len(), 2);
+        assert!(path.hiz && path.fog);
+        assert!(path.volumetrics.is_none() && path.subsurface.is_none());
+        assert!(!path.indirect_draws);
+
+        let mut path = RenderPathPreset::Ultra.path();
+        path.validate(&Capabilities {
+            float_targets: false,
+            ..Capabilities::all()
+        });
+        assert!(!path.hiz && !path.fog && path.volumetrics.is_none());
+    }
+
+    #[test]
+    fn dependencies_are_enabled() {
+        let mut path = RenderPathPreset::Low.path();
+        path.fog = true;
+        assert_eq!(path.validate(&Capabilities::all()).len(), 1);
+        assert!(path.hiz);
+    }
+
+    #[test]
+    #[ignore] // CI can't run tests requiring actual backend
+    fn presets_plan_headlessly() {
+        let config: rendy::factory::Config = Default::default();
+        let (mut factory, _families): (Factory<DefaultBackend>, _) =
+            rendy::factory::init(config).unwrap();
+        let world = World::default();
+
+        for &preset in RenderPathPreset::ALL.iter() {
+            let mut plan = RenderPlan::<DefaultBackend>::new();
+            let kind = Kind::D2(1280, 720, 1, 1);
+            plan.add_root(Target::Main);
+            plan.define_pass(
+                Target::Main,
+                TargetPlanOutputs {
+                    colors: vec![OutputColor::Image(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::Rgba8Srgb,
+                        clear: None,
+                    })],
+                    depth: Some(ImageOptions {
+                        kind,
+                        levels: 1,
+                        format: Format::D32Sfloat,
+                        clear: Some(ClearValue::DepthStencil(ClearDepthStencil(0.0, 0))),
+                    }),
+                },
+            )
+            .unwrap();
+            let mut path = preset.path();
+            RenderPlugin::<DefaultBackend>::on_plan(&mut path, &mut plan, &mut factory, &world)
+                .unwrap();
+            plan.build(&factory)
+                .unwrap_or_else(|e| panic!("{:?} preset failed to plan: {}", preset, e));
+        }
+    }
+}
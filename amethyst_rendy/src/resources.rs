@@ -2,7 +2,10 @@
 //!
 
 use amethyst_assets::PrefabData;
-use amethyst_core::ecs::{Component, DenseVecStorage, Entity, Write};
+use amethyst_core::{
+    ecs::{Component, DenseVecStorage, Entity, Write},
+    math::Vector3,
+};
 use amethyst_error::Error;
 
 /// The ambient color of a scene
@@ -31,6 +34,182 @@ impl<'a> PrefabData<'a> for AmbientColor {
     }
 }
 
+/// Amount of the surface layer of materials over the whole scene, in [0, 1], e.g. how wet
+/// everything is when it rains. Scaled by the `layer_scene_factor` of every material.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SurfaceLayer(pub f32);
+
+/// Where the fog of a scene is applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FogMode {
+    /// No fog is drawn.
+    Disabled,
+    /// Fog is applied by the lit forward shaders and the skybox. Transparent surfaces are
+    /// fogged by their own distance.
+    Forward,
+    /// Fog is applied by the depth based pass of the `RenderFog` plugin. Only the opaque scene
+    /// depth is known, so transparent surfaces are fogged as what is behind them.
+    PostPass,
+}
+
+/// Exponential height fog of a scene, shared by all passes drawing it.
+///
+/// The density is constant below `base_height` and decreases exponentially above it, so rays
+/// looking up eventually leave the fog, while rays towards the horizon are fully fogged
+/// throughout. Light scattered from the sun tints the fog towards `inscattering_color` when
+/// looking along `inscattering_direction`.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FogSettings {
+    /// Where the fog is applied.
+    pub mode: FogMode,
+    /// Color of the fog away from the sun.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub color: palette::Srgb,
+    /// Extinction per unit of distance at or below `base_height`.
+    pub density: f32,
+    /// Height below which the fog has full density.
+    pub base_height: f32,
+    /// Rate at which the density decreases above `base_height`. Zero gives a uniform fog.
+    pub height_falloff: f32,
+    /// Upper bound of the fog opacity, below 1 to keep distant geometry and the sky visible.
+    pub max_opacity: f32,
+    /// Color of the fog when looking towards the sun.
+    #[serde(with = "crate::serde_shim::srgb")]
+    pub inscattering_color: palette::Srgb,
+    /// Direction the sun light travels in, usually that of the scene's directional light.
+    pub inscattering_direction: Vector3<f32>,
+    /// Sharpness of the inscattering lobe around the sun.
+    pub inscattering_exponent: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::Forward,
+            color: palette::Srgb::new(0.6, 0.65, 0.7),
+            density: 0.02,
+            base_height: 0.0,
+            height_falloff: 0.1,
+            max_opacity: 1.0,
+            inscattering_color: palette::Srgb::new(1.0, 0.9, 0.7),
+            inscattering_direction: [-1.0, -1.0, -1.0].into(),
+            inscattering_exponent: 8.0,
+        }
+    }
+}
+
+impl<'a> PrefabData<'a> for FogSettings {
+    type SystemData = Write<'a, FogSettings>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        _: Entity,
+        fog: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        **fog = self.clone();
+        Ok(())
+    }
+}
+
+/// Depth behavior of a mesh, overriding the default of the pass drawing it.
+///
+/// Opaque meshes default to `TestWrite` and `Transparent` meshes to `TestOnly`.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub enum DepthMode {
+    /// Test against and write to the depth buffer.
+    TestWrite,
+    /// Only test against the depth buffer, e.g. for pre-sorted geometry.
+    TestOnly,
+    /// Neither test nor write depth, e.g. for x-ray layers. The result depends on the draw order,
+    /// so these meshes should also be `Transparent` to be sorted.
+    Off,
+    /// Like `Off`, but drawn after all other meshes of the pass, e.g. for markers.
+    AlwaysOnTop,
+}
+
+impl DepthMode {
+    /// All depth modes, in the order they are drawn within a pass.
+    pub const ALL: [DepthMode; 4] = [
+        DepthMode::TestWrite,
+        DepthMode::TestOnly,
+        DepthMode::Off,
+        DepthMode::AlwaysOnTop,
+    ];
+
+    /// Position of this mode in `DepthMode::ALL`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Depth test of pipelines drawing with this mode, with the reversed depth range used by
+    /// the renderer.
+    pub fn depth_test(self) -> rendy::hal::pso::DepthTest {
+        use rendy::hal::pso::{Comparison, DepthTest};
+        match self {
+            DepthMode::TestWrite => DepthTest {
+                fun: Comparison::Greater,
+                write: true,
+            },
+            DepthMode::TestOnly => DepthTest {
+                fun: Comparison::Greater,
+                write: false,
+            },
+            DepthMode::Off | DepthMode::AlwaysOnTop => DepthTest {
+                fun: Comparison::Always,
+                write: false,
+            },
+        }
+    }
+}
+
+impl Component for DepthMode {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Depth bias of a mesh, to draw coplanar geometry such as decals over its base surface.
+///
+/// The bias is in units of the backend's depth bias scale and is applied both as constant and
+/// slope scaled factor. Positive values move the mesh towards the camera. Every distinct value
+/// needs its own pipelines in the passes drawing it, so only a handful should be in use.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct DepthBias(pub i32);
+
+impl DepthBias {
+    /// Rasterizer depth bias state of pipelines drawing with this bias.
+    pub fn state(self) -> Option<rendy::hal::pso::State<rendy::hal::pso::DepthBias>> {
+        if self.0 == 0 {
+            return None;
+        }
+        Some(rendy::hal::pso::State::Static(rendy::hal::pso::DepthBias {
+            const_factor: self.0 as f32,
+            clamp: 0.0,
+            slope_factor: self.0 as f32,
+        }))
+    }
+}
+
+impl Component for DepthBias {
+    type Storage = DenseVecStorage<Self>;
+}
+
 /// A single object tinting applied in multiplicative mode (modulation)
 #[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Tint(#[serde(with = "crate::serde_shim::srgba")] pub palette::Srgba);
@@ -45,3 +224,15 @@ impl Into<[f32; 4]> for Tint {
         [r, g, b, a]
     }
 }
+
+/// Custom per-instance data of a mesh, e.g. a growth stage or an animation phase.
+///
+/// The 3D passes pass it to their vertex shaders as the instance-rate `vec4 instance_data`
+/// attribute, following `model` and `tint`, so custom `Base3DPassDef` shaders can read it.
+/// Meshes without it have zeroed data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct InstanceData(pub [f32; 4]);
+
+impl Component for InstanceData {
+    type Storage = DenseVecStorage<Self>;
+}
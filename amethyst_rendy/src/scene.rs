@@ -0,0 +1,582 @@
+//! Statistics of the scene drawn by the 3D passes, for tooling.
+//!
+//! The `SceneStatsSystem` keeps the [Scene] resource up to date every frame. Like the retained
+//! draw lists, entities are compared with what was counted for them during the last frame and
+//! only the ones whose mesh, materials, lights or bounds changed are counted again, so
+//! [Scene::statistics] only copies running totals instead of going over the whole scene.
+//!
+//! [dump_stats] pretty-prints the statistics along with the `RenderStats` of the last frame, to
+//! be logged by a debug command or shown by an overlay. `FrameGraph::to_dot_with_scene` adds
+//! them to the frame dump.
+
+use crate::{
+    light::Light,
+    mtl::Material,
+    stats::RenderStats,
+    submesh::SubMeshes,
+    types::{Backend, Mesh},
+    visibility::BoundingSphere,
+};
+use amethyst_assets::{AssetStorage, Handle};
+use amethyst_core::{
+    ecs::{hibitset::BitSetOr, Entities, Join, Read, ReadStorage, System, World, Write},
+    math::{Point3, Vector3},
+    transform::Transform,
+};
+use fnv::FnvHashMap;
+use rendy::hal::Primitive;
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Write as _},
+    marker::PhantomData,
+};
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Number of the biggest fragments kept in `SceneStats::largest`.
+pub const TOP_FRAGMENTS: usize = 10;
+
+/// Part of an entity drawn with a single material: its whole mesh, or one of its `SubMeshes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Fragment {
+    /// Id of the entity.
+    pub entity: u32,
+    /// Index of the sub-mesh, 0 for entities drawn without `SubMeshes`.
+    pub index: u32,
+    /// Id of the material.
+    pub material: u32,
+    /// Number of vertices drawn. Indexed meshes count their indices, as built meshes don't keep
+    /// their vertex count.
+    pub vertices: u32,
+    /// Number of triangles drawn, 0 for meshes of points or lines.
+    pub triangles: u32,
+}
+
+/// Type of a `Light`, counted by `SceneStats::lights`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum LightKind {
+    /// `Light::Area`.
+    Area,
+    /// `Light::Directional`.
+    Directional,
+    /// `Light::Point`.
+    Point,
+    /// `Light::Spot`.
+    Spot,
+    /// `Light::Sun`.
+    Sun,
+}
+
+impl From<&Light> for LightKind {
+    fn from(light: &Light) -> Self {
+        match light {
+            Light::Area => LightKind::Area,
+            Light::Directional(_) => LightKind::Directional,
+            Light::Point(_) => LightKind::Point,
+            Light::Spot(_) => LightKind::Spot,
+            Light::Sun(_) => LightKind::Sun,
+        }
+    }
+}
+
+/// Axis aligned box bounding the bounding spheres of the drawn entities.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SceneBounds {
+    /// Corner with the smallest coordinates.
+    pub min: Point3<f32>,
+    /// Corner with the biggest coordinates.
+    pub max: Point3<f32>,
+}
+
+impl SceneBounds {
+    /// Box bounding a sphere.
+    pub fn sphere(center: Point3<f32>, radius: f32) -> Self {
+        let extent = Vector3::repeat(radius);
+        Self {
+            min: center - extent,
+            max: center + extent,
+        }
+    }
+
+    /// Smallest box bounding both boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.coords.zip_map(&other.min.coords, f32::min).into(),
+            max: self.max.coords.zip_map(&other.max.coords, f32::max).into(),
+        }
+    }
+
+    /// Whether a side of the box is on a side of `outer`, which bounds it.
+    fn touches(&self, outer: &Self) -> bool {
+        (0..3).any(|i| self.min[i] <= outer.min[i] || self.max[i] >= outer.max[i])
+    }
+}
+
+/// Statistics of the drawn scene, see [Scene::statistics].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SceneStats {
+    /// Number of fragments drawn.
+    pub fragments: usize,
+    /// Number of fragments drawn with each material, by material id.
+    pub fragments_per_material: BTreeMap<u32, usize>,
+    /// Number of vertices drawn, see `Fragment::vertices`.
+    pub vertices: u64,
+    /// Number of triangles drawn.
+    pub triangles: u64,
+    /// Number of lights of each type.
+    pub lights: BTreeMap<LightKind, usize>,
+    /// Box bounding every drawn entity, `None` when nothing is drawn.
+    pub bounds: Option<SceneBounds>,
+    /// The `TOP_FRAGMENTS` fragments with the most triangles, by decreasing triangle count.
+    pub largest: Vec<Fragment>,
+}
+
+impl fmt::Display for SceneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} fragments, {} vertices, {} triangles",
+            self.fragments, self.vertices, self.triangles
+        )?;
+        match &self.bounds {
+            Some(b) => writeln!(
+                f,
+                "bounds: ({:.2}, {:.2}, {:.2}) to ({:.2}, {:.2}, {:.2})",
+                b.min.x, b.min.y, b.min.z, b.max.x, b.max.y, b.max.z
+            )?,
+            None => writeln!(f, "bounds: empty")?,
+        }
+        let lights = self
+            .lights
+            .iter()
+            .map(|(kind, count)| format!("{} {:?}", count, kind))
+            .collect::<Vec<_>>();
+        writeln!(f, "lights: {}", list_or_none(lights))?;
+        writeln!(f, "materials:")?;
+        for (material, count) in &self.fragments_per_material {
+            writeln!(f, "    material {}: {} fragments", material, count)?;
+        }
+        writeln!(f, "largest fragments:")?;
+        for fragment in &self.largest {
+            writeln!(
+                f,
+                "    entity {} part {}: {} triangles, {} vertices, material {}",
+                fragment.entity,
+                fragment.index,
+                fragment.triangles,
+                fragment.vertices,
+                fragment.material
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn list_or_none(items: Vec<String>) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+/// What an entity adds to the [Scene].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SceneEntry {
+    /// Fragments of the entity, empty if it isn't drawn.
+    pub fragments: Vec<Fragment>,
+    /// Box bounding the entity, if drawn.
+    pub bounds: Option<SceneBounds>,
+    /// Type of the light of the entity, if any.
+    pub light: Option<LightKind>,
+}
+
+#[derive(Debug)]
+struct CountedEntry {
+    entry: SceneEntry,
+    frame: u64,
+}
+
+/// Resource keeping running totals of the drawn scene, updated by the `SceneStatsSystem`.
+///
+/// Every frame starts with `begin`, updates every entity drawn or lighting the scene, and ends
+/// with `finish`. Entities whose entry didn't change aren't counted again, and entities not
+/// updated during the frame are removed.
+#[derive(Debug, Default)]
+pub struct Scene {
+    entries: FnvHashMap<u32, CountedEntry>,
+    frame: u64,
+    touched: usize,
+    fragments: usize,
+    materials: BTreeMap<u32, usize>,
+    vertices: u64,
+    triangles: u64,
+    lights: BTreeMap<LightKind, usize>,
+    largest: BTreeSet<(Reverse<u32>, u32, u32)>,
+    bounds: Option<SceneBounds>,
+    bounds_dirty: bool,
+}
+
+impl Scene {
+    /// Statistics of the scene at the end of the last frame.
+    pub fn statistics(&self) -> SceneStats {
+        SceneStats {
+            fragments: self.fragments,
+            fragments_per_material: self.materials.clone(),
+            vertices: self.vertices,
+            triangles: self.triangles,
+            lights: self.lights.clone(),
+            bounds: self.bounds,
+            largest: self
+                .largest
+                .iter()
+                .take(TOP_FRAGMENTS)
+                .map(|&(_, entity, index)| self.entries[&entity].entry.fragments[index as usize])
+                .collect(),
+        }
+    }
+
+    /// Starts a frame of updates.
+    pub fn begin(&mut self) {
+        self.frame += 1;
+        self.touched = 0;
+    }
+
+    /// Updates the entry of an entity, counting it if it's new or changed. Every entity is
+    /// updated at most once per frame.
+    pub fn update(&mut self, entity: u32, entry: SceneEntry) {
+        let frame = self.frame;
+        if let Some(counted) = self.entries.get_mut(&entity) {
+            counted.frame = frame;
+            if counted.entry == entry {
+                return;
+            }
+        }
+        self.remove(entity);
+        self.touched += 1;
+
+        for fragment in &entry.fragments {
+            self.fragments += 1;
+            *self.materials.entry(fragment.material).or_insert(0) += 1;
+            self.vertices += u64::from(fragment.vertices);
+            self.triangles += u64::from(fragment.triangles);
+            self.largest
+                .insert((Reverse(fragment.triangles), entity, fragment.index));
+        }
+        if let Some(kind) = entry.light {
+            *self.lights.entry(kind).or_insert(0) += 1;
+        }
+        if let (Some(bounds), false) = (&entry.bounds, self.bounds_dirty) {
+            self.bounds = Some(self.bounds.map_or(*bounds, |b| b.union(bounds)));
+        }
+        self.entries.insert(entity, CountedEntry { entry, frame });
+    }
+
+    /// Ends a frame of updates, removing the entities that weren't updated.
+    pub fn finish(&mut self) {
+        let frame = self.frame;
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(_, counted)| counted.frame != frame)
+            .map(|(&entity, _)| entity)
+            .collect::<Vec<_>>();
+        for entity in stale {
+            self.touched += 1;
+            self.remove(entity);
+        }
+
+        // Removed boxes on a side of the bounds may have been the only ones reaching it.
+        if self.bounds_dirty {
+            self.bounds = self
+                .entries
+                .values()
+                .filter_map(|counted| counted.entry.bounds)
+                .fold(None, |acc, b| {
+                    Some(acc.map_or(b, |a: SceneBounds| a.union(&b)))
+                });
+            self.bounds_dirty = false;
+        }
+    }
+
+    fn remove(&mut self, entity: u32) {
+        let entry = match self.entries.remove(&entity) {
+            Some(counted) => counted.entry,
+            None => return,
+        };
+        for fragment in &entry.fragments {
+            self.fragments -= 1;
+            decrement(&mut self.materials, fragment.material);
+            self.vertices -= u64::from(fragment.vertices);
+            self.triangles -= u64::from(fragment.triangles);
+            self.largest
+                .remove(&(Reverse(fragment.triangles), entity, fragment.index));
+        }
+        if let Some(kind) = entry.light {
+            decrement(&mut self.lights, kind);
+        }
+        if let (Some(bounds), Some(outer)) = (&entry.bounds, &self.bounds) {
+            self.bounds_dirty |= bounds.touches(outer);
+        }
+    }
+
+    /// Returns the number of entities counted, or removed, during the last frame.
+    pub fn touched(&self) -> usize {
+        self.touched
+    }
+}
+
+fn decrement<K: Ord>(counts: &mut BTreeMap<K, usize>, key: K) {
+    if let Some(count) = counts.get_mut(&key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(&key);
+        }
+    }
+}
+
+/// Number of triangles of a mesh drawing `vertices` vertices with given primitive.
+fn triangles(primitive: Primitive, vertices: u32) -> u32 {
+    match primitive {
+        Primitive::TriangleList | Primitive::TriangleListAdjacency => vertices / 3,
+        Primitive::TriangleStrip | Primitive::TriangleStripAdjacency => vertices.saturating_sub(2),
+        _ => 0,
+    }
+}
+
+/// System updating the [Scene] resource, added by the `RenderingBundle`.
+///
+/// Entities with a loaded mesh, a `Transform` and a `Handle<Material>` or `SubMeshes` are
+/// drawn, entities with a `Light` light the scene.
+#[derive(Debug, derivative::Derivative)]
+#[derivative(Default(bound = ""))]
+pub struct SceneStatsSystem<B: Backend>(PhantomData<B>);
+
+impl<'a, B: Backend> System<'a> for SceneStatsSystem<B> {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Scene>,
+        Read<'a, AssetStorage<Mesh>>,
+        ReadStorage<'a, Handle<Mesh>>,
+        ReadStorage<'a, Handle<Material>>,
+        ReadStorage<'a, SubMeshes>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, Light>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut scene,
+            mesh_storage,
+            meshes,
+            materials,
+            submeshes,
+            transforms,
+            spheres,
+            lights,
+        ): Self::SystemData,
+    ) {
+        #[cfg(feature = "profiler")]
+        profile_scope!("scene_stats");
+
+        scene.begin();
+        for (entity, _, mesh, material, parts, transform, sphere, light) in (
+            &entities,
+            BitSetOr(meshes.mask(), lights.mask()),
+            meshes.maybe(),
+            materials.maybe(),
+            submeshes.maybe(),
+            transforms.maybe(),
+            spheres.maybe(),
+            lights.maybe(),
+        )
+            .join()
+        {
+            let id = entity.id();
+            let mut entry = SceneEntry {
+                light: light.map(LightKind::from),
+                ..Default::default()
+            };
+
+            let drawn = mesh
+                .and_then(|h| mesh_storage.get(h))
+                .and_then(B::unwrap_mesh)
+                .zip(transform);
+            if let Some((mesh, transform)) = drawn {
+                let primitive = mesh.primitive();
+                let fragment = |index, material: &Handle<Material>, vertices| Fragment {
+                    entity: id,
+                    index,
+                    material: material.id(),
+                    vertices,
+                    triangles: triangles(primitive, vertices),
+                };
+                if let Some(parts) = parts {
+                    entry.fragments = parts
+                        .0
+                        .iter()
+                        .enumerate()
+                        .map(|(i, part)| {
+                            let end = part.indices.end.min(mesh.len());
+                            let vertices = end.saturating_sub(part.indices.start);
+                            fragment(i as u32, &part.material, vertices)
+                        })
+                        .collect();
+                } else if let Some(material) = material {
+                    entry.fragments = vec![fragment(0, material, mesh.len())];
+                }
+
+                if !entry.fragments.is_empty() {
+                    let matrix = transform.global_matrix();
+                    let center =
+                        matrix.transform_point(&sphere.map_or(Point3::origin(), |s| s.center));
+                    let radius = sphere.map_or(1.0, |s| s.radius)
+                        * matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]);
+                    entry.bounds = Some(SceneBounds::sphere(center, radius));
+                }
+            }
+
+            if !entry.fragments.is_empty() || entry.light.is_some() {
+                scene.update(id, entry);
+            }
+        }
+        scene.finish();
+    }
+}
+
+/// Pretty-print the `Scene` statistics and the `RenderStats` of the last frame, for a
+/// `dump_stats` debug command or a stats overlay. Missing resources are left out.
+pub fn dump_stats(world: &World) -> String {
+    let mut dump = String::new();
+    if let Some(stats) = world.try_fetch::<RenderStats>() {
+        writeln!(
+            dump,
+            "view mode: {}\ngraph time: {:.3}ms",
+            stats.view_mode.name(),
+            stats.graph_time.as_secs_f64() * 1000.0
+        )
+        .unwrap();
+        writeln!(
+            dump,
+            "culled: {} by frustum, {} by distance",
+            stats.frustum_culled, stats.distance_culled
+        )
+        .unwrap();
+        let memory = &stats.gpu_memory;
+        writeln!(
+            dump,
+            "GPU memory: {} bytes, {} in meshes, {} in textures",
+            memory.total(),
+            memory.meshes,
+            memory.textures
+        )
+        .unwrap();
+    }
+    if let Some(scene) = world.try_fetch::<Scene>() {
+        write!(dump, "{}", scene.statistics()).unwrap();
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Statistics counted from scratch from every entry.
+    fn brute_force(entries: &BTreeMap<u32, SceneEntry>) -> SceneStats {
+        let mut stats = SceneStats::default();
+        let mut fragments = Vec::new();
+        for entry in entries.values() {
+            for fragment in &entry.fragments {
+                stats.fragments += 1;
+                *stats
+                    .fragments_per_material
+                    .entry(fragment.material)
+                    .or_insert(0) += 1;
+                stats.vertices += u64::from(fragment.vertices);
+                stats.triangles += u64::from(fragment.triangles);
+                fragments.push(*fragment);
+            }
+            if let Some(kind) = entry.light {
+                *stats.lights.entry(kind).or_insert(0) += 1;
+            }
+            if let Some(b) = entry.bounds {
+                stats.bounds = Some(stats.bounds.map_or(b, |a| a.union(&b)));
+            }
+        }
+        fragments.sort_by_key(|f| (Reverse(f.triangles), f.entity, f.index));
+        fragments.truncate(TOP_FRAGMENTS);
+        stats.largest = fragments;
+        stats
+    }
+
+    fn entry(entity: u32, seed: u32) -> SceneEntry {
+        let kinds = [LightKind::Point, LightKind::Spot, LightKind::Sun];
+        let position = Point3::new((seed % 7) as f32, (seed % 5) as f32, -((seed % 3) as f32));
+        SceneEntry {
+            fragments: (0..seed % 3)
+                .map(|index| Fragment {
+                    entity,
+                    index,
+                    material: (seed + index) % 4,
+                    vertices: seed % 11 * 3,
+                    triangles: seed % 11,
+                })
+                .collect(),
+            bounds: Some(SceneBounds::sphere(position, 0.5)).filter(|_| seed % 3 != 1),
+            light: Some(kinds[(seed % 3) as usize]).filter(|_| seed % 4 == 1),
+        }
+    }
+
+    #[test]
+    fn incremental_statistics_match_brute_force() {
+        let mut scene = Scene::default();
+        let mut entries = BTreeMap::new();
+        let mut seed = 1u32;
+        let mut rand = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            seed >> 8
+        };
+
+        for _ in 0..50 {
+            for _ in 0..8 {
+                let entity = rand() % 32;
+                if rand() % 4 == 0 {
+                    entries.remove(&entity);
+                } else {
+                    entries.insert(entity, entry(entity, rand()));
+                }
+            }
+            scene.begin();
+            for (&entity, entry) in &entries {
+                scene.update(entity, entry.clone());
+            }
+            scene.finish();
+            assert_eq!(scene.statistics(), brute_force(&entries));
+        }
+
+        scene.begin();
+        for (&entity, entry) in &entries {
+            scene.update(entity, entry.clone());
+        }
+        scene.finish();
+        assert_eq!(scene.touched(), 0);
+
+        scene.begin();
+        scene.finish();
+        assert_eq!(scene.statistics(), SceneStats::default());
+    }
+
+    #[test]
+    fn triangles_follow_the_primitive() {
+        assert_eq!(triangles(Primitive::TriangleList, 9), 3);
+        assert_eq!(triangles(Primitive::TriangleStrip, 5), 3);
+        assert_eq!(triangles(Primitive::LineList, 6), 0);
+    }
+}
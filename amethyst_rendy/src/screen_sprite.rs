@@ -0,0 +1,202 @@
+//! Textured quads positioned in pixels on the target, for HUDs, health bars or debug text.
+//!
+//! Quads submitted to the [ScreenSprites] resource are drawn for one frame by the
+//! `DrawScreenSprites` pass, added with the `RenderScreenSprites` plugin. The pass projects
+//! them with [screen_projection] of the size of its target, without a camera, so they keep
+//! their size in pixels when the window is resized.
+
+use crate::{
+    pod::{IntoPod, SpriteArgs},
+    sprite::TextureCoordinates,
+    types::Texture,
+};
+use amethyst_assets::Handle;
+use amethyst_core::math::{Matrix4, Point2, Vector2};
+use palette::Srgba;
+
+/// A quad drawn on the target, see [crate::screen_sprite].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenSprite {
+    /// Texture sampled by the quad.
+    pub texture: Handle<Texture>,
+    /// Position of the top left corner of the quad, in pixels from the top left corner of the
+    /// target.
+    pub position: Point2<f32>,
+    /// Width and height of the quad, in pixels.
+    pub size: Vector2<f32>,
+    /// Region of the texture drawn, from 0 at the top left corner of the texture to 1 at the
+    /// bottom right corner.
+    pub tex_coords: TextureCoordinates,
+    /// Color the texture is multiplied with.
+    pub tint: Srgba,
+}
+
+impl ScreenSprite {
+    /// Quad drawing the whole texture at given position and size, in pixels.
+    pub fn new(texture: Handle<Texture>, position: Point2<f32>, size: Vector2<f32>) -> Self {
+        Self {
+            texture,
+            position,
+            size,
+            tex_coords: TextureCoordinates {
+                left: 0.0,
+                right: 1.0,
+                bottom: 1.0,
+                top: 0.0,
+            },
+            tint: Srgba::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Draw given region of the texture.
+    pub fn with_tex_coords(mut self, tex_coords: TextureCoordinates) -> Self {
+        self.tex_coords = tex_coords;
+        self
+    }
+
+    /// Multiply the texture with given color.
+    pub fn with_tint(mut self, tint: Srgba) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    /// Corners of the quad, in pixels, rounded to whole pixels when `snap` is set.
+    ///
+    /// Pixels are covered when their center is inside the quad, so a quad with whole pixel
+    /// corners covers exactly the pixels between them, without blurred or doubled edges.
+    pub fn corners(&self, snap: bool) -> (Point2<f32>, Point2<f32>) {
+        let min = self.position;
+        let max = self.position + self.size;
+        if snap {
+            (
+                Point2::from(min.coords.map(f32::round)),
+                Point2::from(max.coords.map(f32::round)),
+            )
+        } else {
+            (min, max)
+        }
+    }
+
+    /// Instance arguments of the sprite pipeline, in pixels.
+    pub(crate) fn args(&self, snap: bool) -> SpriteArgs {
+        let (min, max) = self.corners(snap);
+        let size = max - min;
+        let (r, g, b, a) = self.tint.into_linear().into_components();
+        SpriteArgs {
+            dir_x: Vector2::new(size.x, 0.0).into_pod(),
+            dir_y: Vector2::new(0.0, size.y).into_pod(),
+            pos: (min.coords + size * 0.5).into_pod(),
+            u_offset: [self.tex_coords.left, self.tex_coords.right].into(),
+            v_offset: [self.tex_coords.top, self.tex_coords.bottom].into(),
+            depth: 0.0,
+            tint: [r, g, b, a].into(),
+        }
+    }
+}
+
+/// Resource collecting the [ScreenSprite]s drawn during the next frame, in drawing order.
+///
+/// The sprites are cleared once drawn, so they must be submitted again every frame.
+#[derive(Debug, Clone, Default)]
+pub struct ScreenSprites {
+    sprites: Vec<ScreenSprite>,
+}
+
+impl ScreenSprites {
+    /// Submits a sprite drawn over the sprites submitted before it.
+    pub fn draw(&mut self, sprite: ScreenSprite) {
+        self.sprites.push(sprite);
+    }
+
+    /// Sprites submitted for the next frame.
+    pub fn sprites(&self) -> &[ScreenSprite] {
+        &self.sprites
+    }
+
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = ScreenSprite> + '_ {
+        self.sprites.drain(..)
+    }
+}
+
+/// Orthographic projection of pixel coordinates on a target of given size, from the top left
+/// corner of the target to the bottom right one.
+pub fn screen_projection(width: u32, height: u32) -> Matrix4<f32> {
+    Matrix4::new(
+        2.0 / width as f32,
+        0.0,
+        0.0,
+        -1.0,
+        0.0,
+        2.0 / height as f32,
+        0.0,
+        -1.0,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::uv_gradient_data;
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::math::Vector4;
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    /// Pixels of a target whose centers are covered by the quad drawn by the sprite pipeline,
+    /// one range per axis.
+    fn covered(sprite: &ScreenSprite, width: u32, height: u32) -> (Vec<u32>, Vec<u32>) {
+        let args = sprite.args(true);
+        let (pos, dir_x, dir_y): (&[f32; 2], &[f32; 2], &[f32; 2]) =
+            (args.pos.as_ref(), args.dir_x.as_ref(), args.dir_y.as_ref());
+        let proj = screen_projection(width, height);
+        // Corners of the triangle strip, as the vertex shader places them.
+        let pixel = |u: f32, v: f32| {
+            let x = pos[0] + u * dir_x[0] + v * dir_y[0];
+            let y = pos[1] + u * dir_x[1] + v * dir_y[1];
+            let clip = proj * Vector4::new(x, y, 0.0, 1.0);
+            (
+                (clip.x + 1.0) * 0.5 * width as f32,
+                (clip.y + 1.0) * 0.5 * height as f32,
+            )
+        };
+        let (x0, y0) = pixel(-0.5, -0.5);
+        let (x1, y1) = pixel(0.5, 0.5);
+        let inside = |min: f32, max: f32, i: u32| (i as f32 + 0.5) >= min && (i as f32 + 0.5) < max;
+        (
+            (0..width).filter(|&i| inside(x0, x1, i)).collect(),
+            (0..height).filter(|&i| inside(y0, y1, i)).collect(),
+        )
+    }
+
+    #[test]
+    fn sprites_cover_exactly_their_pixels() {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let texture = loader.load_from_data(uv_gradient_data(4), (), &AssetStorage::new());
+        let sprite = ScreenSprite::new(texture, Point2::new(5.0, 5.0), Vector2::new(10.0, 10.0));
+        for &(width, height) in &[(100, 100), (37, 23), (1920, 1080)] {
+            let (xs, ys) = covered(&sprite, width, height);
+            assert_eq!(xs, (5..15).collect::<Vec<_>>(), "{}x{}", width, height);
+            assert_eq!(ys, (5..15).collect::<Vec<_>>(), "{}x{}", width, height);
+        }
+
+        let off_grid = ScreenSprite {
+            position: Point2::new(5.4, 4.6),
+            size: Vector2::new(10.2, 9.8),
+            ..sprite
+        };
+        assert_eq!(
+            off_grid.corners(true),
+            (Point2::new(5.0, 5.0), Point2::new(16.0, 14.0))
+        );
+        let (xs, ys) = covered(&off_grid, 64, 64);
+        assert_eq!((xs.len(), ys.len()), (11, 9));
+    }
+}
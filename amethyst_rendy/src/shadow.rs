@@ -0,0 +1,198 @@
+//! Shadow map of a directional light.
+//!
+//! With the `RenderShadows` plugin, the opaque static meshes close enough to the camera to cast
+//! shadows, see `DrawDistance::max_shadow`, are drawn from the light selected by the
+//! [ShadowSettings] resource into the depth of `SHADOW_MAP_TARGET`. The 3D passes built with
+//! shadows then compare the depth of their fragments seen from the light with the map, and
+//! darken the light where something is closer to it.
+//!
+//! The map covers a box aligned with the light around the active camera, moved by whole texels
+//! of the map so that the edges of the shadows don't shimmer while the camera moves. Blob
+//! shadows aren't drawn while a light casts shadows.
+//!
+//! Skinned and morphed meshes don't cast shadows, and neither do transparent meshes. Materials
+//! aren't sampled when drawing the casters, so alpha cutoff and dissolve don't cut holes in
+//! the shadows.
+
+use crate::{
+    camera::Camera,
+    conventions::{mirror_z, Conventions, Handedness},
+};
+use amethyst_core::{
+    ecs::Entity,
+    math::{Matrix4, Point3, Vector3},
+};
+
+/// Filtering of the shadow map lookups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShadowFilter {
+    /// A single lookup, with hard aliased edges.
+    Hard,
+    /// Average of a 3x3 block of lookups.
+    Pcf3x3,
+    /// Average of a 5x5 block of lookups.
+    Pcf5x5,
+}
+
+impl ShadowFilter {
+    /// Distance of the farthest lookups from the center of the block, in texels.
+    pub fn radius(self) -> i32 {
+        match self {
+            ShadowFilter::Hard => 0,
+            ShadowFilter::Pcf3x3 => 1,
+            ShadowFilter::Pcf5x5 => 2,
+        }
+    }
+}
+
+/// Resource selecting the light casting shadows, and how its shadow map is drawn and looked up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowSettings {
+    /// Entity of the directional light casting shadows. When `None`, or not a directional
+    /// light, the first directional light casts them.
+    pub light: Option<Entity>,
+    /// Half of the width of the square covered by the map around the camera, in world units.
+    pub extent: f32,
+    /// Depth of the box covered by the map along the light, centered on the camera, in world
+    /// units. Casters out of the box don't cast shadows.
+    pub depth: f32,
+    /// Distance towards the light by which fragments are moved before being compared with the
+    /// map, in world units, to keep surfaces from shadowing themselves.
+    pub depth_bias: f32,
+    /// Additional bias for surfaces at grazing angles to the light, in world units at 45
+    /// degrees, growing with the tangent of the angle.
+    pub slope_bias: f32,
+    /// Filtering of the lookups.
+    pub filter: ShadowFilter,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            light: None,
+            extent: 20.0,
+            depth: 100.0,
+            depth_bias: 0.05,
+            slope_bias: 0.05,
+            filter: ShadowFilter::Pcf3x3,
+        }
+    }
+}
+
+impl ShadowSettings {
+    /// Projection and view matrices of the map of a `resolution` texels wide shadow map, for a
+    /// light shining along `direction` around `center`. `None` when the direction is zero.
+    pub fn light_matrices(
+        &self,
+        direction: &Vector3<f32>,
+        center: &Point3<f32>,
+        resolution: u32,
+    ) -> Option<(Matrix4<f32>, Matrix4<f32>)> {
+        let direction = direction.try_normalize(f32::EPSILON)?;
+        let up = if direction.y.abs() > 0.99 {
+            Vector3::z()
+        } else {
+            Vector3::y()
+        };
+        let view = Matrix4::look_at_rh(&Point3::origin(), &Point3::from(direction), &up);
+
+        let texel = 2.0 * self.extent / resolution.max(1) as f32;
+        let center = view.transform_point(center);
+        let (x, y) = (
+            (center.x / texel).round() * texel,
+            (center.y / texel).round() * texel,
+        );
+        let proj = Camera::orthographic(
+            x - self.extent,
+            x + self.extent,
+            y - self.extent,
+            y + self.extent,
+            -center.z - self.depth * 0.5,
+            -center.z + self.depth * 0.5,
+        )
+        .matrix;
+        Some((proj, view))
+    }
+
+    /// Like `light_matrices`, in the world of given conventions. The matrices of a left handed
+    /// world are the mirror of the right handed ones, drawing the same map.
+    pub fn light_matrices_in(
+        &self,
+        conventions: &Conventions,
+        direction: &Vector3<f32>,
+        center: &Point3<f32>,
+        resolution: u32,
+    ) -> Option<(Matrix4<f32>, Matrix4<f32>)> {
+        match conventions.handedness {
+            Handedness::Right => self.light_matrices(direction, center, resolution),
+            Handedness::Left => {
+                let mirror = mirror_z();
+                let (proj, view) = self.light_matrices(
+                    &mirror.transform_vector(direction),
+                    &mirror.transform_point(center),
+                    resolution,
+                )?;
+                Some((proj * mirror, mirror * view * mirror))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::math::Vector4;
+
+    fn project(matrix: &Matrix4<f32>, point: Point3<f32>) -> Vector4<f32> {
+        matrix * point.to_homogeneous()
+    }
+
+    #[test]
+    fn map_covers_the_box_around_the_center() {
+        let settings = ShadowSettings {
+            extent: 10.0,
+            depth: 40.0,
+            ..Default::default()
+        };
+        let center = Point3::new(0.0, 0.0, 0.0);
+        let (proj, view) = settings
+            .light_matrices(&Vector3::new(0.0, -1.0, 0.0), &center, 1024)
+            .unwrap();
+        let matrix = proj * view;
+
+        let clip = project(&matrix, center);
+        assert!(clip.x.abs() < 1.0e-5 && clip.y.abs() < 1.0e-5);
+        assert!((clip.z - 0.5).abs() < 1.0e-5);
+        // Depth goes from 1 towards the light to 0 away from it.
+        assert!((project(&matrix, Point3::new(0.0, 20.0, 0.0)).z - 1.0).abs() < 1.0e-5);
+        assert!(project(&matrix, Point3::new(0.0, -20.0, 0.0)).z.abs() < 1.0e-5);
+        let corner = project(&matrix, Point3::new(10.0, 0.0, 10.0));
+        assert!((corner.x.abs() - 1.0).abs() < 1.0e-5 && (corner.y.abs() - 1.0).abs() < 1.0e-5);
+
+        assert!(settings
+            .light_matrices(&Vector3::zeros(), &center, 1024)
+            .is_none());
+    }
+
+    #[test]
+    fn map_moves_by_whole_texels() {
+        let settings = ShadowSettings {
+            extent: 8.0,
+            ..Default::default()
+        };
+        let direction = Vector3::new(-0.3, -1.0, -0.5);
+        let point = Point3::new(1.0, 0.0, 2.0);
+        let texel_coords = |center: Point3<f32>| {
+            let (proj, view) = settings.light_matrices(&direction, &center, 64).unwrap();
+            let clip = project(&(proj * view), point);
+            ((clip.x + 1.0) * 32.0, (clip.y + 1.0) * 32.0)
+        };
+
+        let (x0, y0) = texel_coords(Point3::new(0.0, 0.0, 0.0));
+        for step in 1..10 {
+            let (x, y) = texel_coords(Point3::new(step as f32 * 0.037, 0.0, step as f32 * 0.021));
+            assert!(((x - x0) - (x - x0).round()).abs() < 1.0e-2);
+            assert!(((y - y0) - (y - y0).round()).abs() < 1.0e-2);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Shell fur and grass.
+//!
+//! Entities with a [Shells] component are drawn again by the `RenderShells` plugin on top of
+//! their mesh, as `count` copies of it pushed outward along the vertex normals up to `length`.
+//! Every copy is a shell cutting the strands through at its height: the red channel of the
+//! `density` map is the height of the strand over a texel, so a shell at height `h` keeps the
+//! texels whose density is above `h`. Strands thin out towards their tips with a noise density
+//! map, such as [crate::texture::value_noise_data].
+//!
+//! All shells of an entity are drawn with a single instanced draw, an instance per shell.
+//! Shells are cut out rather than blended, so they don't need sorting.
+//!
+//! Shells are only visible where they face the camera. At grazing angles, around the
+//! silhouette, the view passes between the shells and the strands show up as separate layers
+//! with gaps between them. The gaps shrink with more shells or shorter strands, 16 shells is a
+//! reasonable start for a character. The mesh needs `Position`, `Normal` and `TexCoord`
+//! attributes.
+
+use crate::types::Texture;
+use amethyst_assets::Handle;
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+use palette::Srgba;
+
+/// Draw an entity mesh with shells of fur or grass, see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shells {
+    /// Number of shells, at least 1.
+    pub count: u32,
+    /// Distance of the outermost shell from the mesh surface, in object space.
+    pub length: f32,
+    /// Height of the strands over the texture coordinates, red channel.
+    pub density: Handle<Texture>,
+    /// Repeats of the density map per repeat of the texture coordinates.
+    pub density_scale: f32,
+    /// World space offset of the strand tips per unit of length, bending them towards it.
+    /// Strands bend quadratically along their height, so the roots stay in place.
+    pub comb: [f32; 3],
+    /// Color of the strands at their roots.
+    pub root_color: Srgba,
+    /// Color of the strands at their tips.
+    pub tip_color: Srgba,
+}
+
+impl Shells {
+    /// 16 shells of given length and density map with straight, white strands darkening
+    /// towards their roots.
+    pub fn new(length: f32, density: Handle<Texture>) -> Self {
+        Shells {
+            count: 16,
+            length,
+            density,
+            density_scale: 1.0,
+            comb: [0.0; 3],
+            root_color: Srgba::new(0.3, 0.3, 0.3, 1.0),
+            tip_color: Srgba::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Set the number of shells.
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Set the bending of the strands, see `comb`.
+    pub fn with_comb(mut self, comb: [f32; 3]) -> Self {
+        self.comb = comb;
+        self
+    }
+
+    /// Set the colors of the strands at their roots and tips.
+    pub fn with_colors(mut self, root_color: Srgba, tip_color: Srgba) -> Self {
+        self.root_color = root_color;
+        self.tip_color = tip_color;
+        self
+    }
+
+    /// Height of every shell as a fraction of the length, from the innermost shell off the
+    /// surface to the outermost one at 1.
+    pub fn heights(&self) -> impl Iterator<Item = f32> {
+        let count = self.count.max(1);
+        (1..=count).map(move |shell| shell as f32 / count as f32)
+    }
+}
+
+impl Component for Shells {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pod::ShellArgs, texture::value_noise_data};
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::Transform;
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn shells_reach_the_strand_length() {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let storage = AssetStorage::<Texture>::new();
+        let density = loader.load_from_data(value_noise_data(4, 2, 0), (), &storage);
+        let shells = Shells::new(0.2, density)
+            .with_count(4)
+            .with_comb([0.0, -1.0, 0.0]);
+
+        let heights = shells.heights().collect::<Vec<_>>();
+        assert_eq!(heights, vec![0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(shells.clone().with_count(0).heights().count(), 1);
+
+        let args = ShellArgs::from_object_data(&shells, &Transform::default()).collect::<Vec<_>>();
+        assert_eq!(args.len(), 4);
+        let (innermost, outermost) = (args[0].offset, args[3].offset);
+        assert_eq!(outermost, [0.0, -0.2, 0.0, 0.2].into());
+        // The comb bends the tips more than the roots.
+        assert_eq!(innermost, [0.0, -0.2 * 0.25 * 0.25, 0.0, 0.2 * 0.25].into());
+    }
+}
@@ -1,8 +1,11 @@
 //! Skinned mesh and bone implementation for renderer.
 use amethyst_assets::PrefabData;
 use amethyst_core::{
-    ecs::prelude::{Component, DenseVecStorage, Entity, FlaggedStorage, WriteStorage},
-    math::Matrix4,
+    ecs::prelude::{
+        Component, DenseVecStorage, Entity, FlaggedStorage, Join, ReadStorage, System, WriteStorage,
+    },
+    math::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3},
+    Transform,
 };
 use amethyst_error::Error;
 use rendy::{
@@ -61,6 +64,23 @@ impl JointCombined {
             joint_weights: weights.into(),
         }
     }
+
+    /// Blend of the joint matrices influencing the vertex, as computed by the vertex shaders.
+    /// Joints missing from `palette` don't move the vertex.
+    pub fn transform(&self, palette: &[Matrix4<f32>]) -> Matrix4<f32> {
+        let ids = self.joint_ids.0;
+        let weights = self.joint_weights.0;
+        ids.iter()
+            .zip(weights.iter())
+            .map(|(&id, &weight)| {
+                palette
+                    .get(id as usize)
+                    .copied()
+                    .unwrap_or_else(Matrix4::identity)
+                    * weight
+            })
+            .fold(Matrix4::zeros(), |sum, m| sum + m)
+    }
 }
 
 impl AsVertex for JointCombined {
@@ -120,3 +140,193 @@ impl<'a> PrefabData<'a> for JointTransformsPrefab {
         Ok(())
     }
 }
+
+/// Named attachment point of a skinned mesh, following one of its joints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Socket {
+    /// Name the socket is looked up by.
+    pub name: String,
+    /// Index of the joint in the skin.
+    pub joint: usize,
+    /// Transform of the socket relative to the mesh in its bind pose.
+    pub bind_transform: Matrix4<f32>,
+}
+
+/// Attachment points of a skinned mesh, placed next to its `JointTransforms`.
+///
+/// The `SocketSystem` resolves the world transform of every socket from the joint matrices of
+/// the mesh. Run it after the `VertexSkinningSystem` for the sockets to follow the animation of
+/// the same frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sockets {
+    sockets: Vec<Socket>,
+    world: Vec<Matrix4<f32>>,
+}
+
+impl Component for Sockets {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Sockets {
+    /// Create the sockets of a skinned mesh. They resolve to the bind pose until the
+    /// `SocketSystem` runs.
+    pub fn new(sockets: Vec<Socket>) -> Self {
+        let world = sockets.iter().map(|s| s.bind_transform).collect();
+        Sockets { sockets, world }
+    }
+
+    /// The sockets of the mesh.
+    pub fn sockets(&self) -> &[Socket] {
+        &self.sockets
+    }
+
+    /// World transform of the socket with given name.
+    pub fn world_matrix(&self, name: &str) -> Option<&Matrix4<f32>> {
+        let index = self.sockets.iter().position(|s| s.name == name)?;
+        self.world.get(index)
+    }
+
+    /// Resolve the world transforms from the global matrix and the joint matrices of the mesh.
+    /// Sockets of missing joints keep their previous transform.
+    pub fn resolve(&mut self, mesh_global: &Matrix4<f32>, joint_matrices: &[Matrix4<f32>]) {
+        for (socket, world) in self.sockets.iter().zip(&mut self.world) {
+            if let Some(joint) = joint_matrices.get(socket.joint) {
+                *world = mesh_global * joint * socket.bind_transform;
+            }
+        }
+    }
+}
+
+/// Places its entity at a socket of a skinned mesh.
+///
+/// The entity `Transform` is replaced by the world transform of the socket, so the entity
+/// shouldn't have a `Parent`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketAttachment {
+    /// Skinned mesh entity with the `Sockets`.
+    pub mesh: Entity,
+    /// Name of the socket.
+    pub socket: String,
+}
+
+impl Component for SocketAttachment {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Resolves `Sockets` and moves the entities of every `SocketAttachment` to their socket.
+///
+/// Resolution happens in the same frame: the world transforms of the sockets and the global
+/// matrices of attached entities are updated right away, so the attachments are drawn where
+/// the joints are drawn. Children of attached entities are only updated by the next run of the
+/// `TransformSystem`, which lags them one frame behind.
+///
+/// Needs to run after the `VertexSkinningSystem`.
+#[derive(Debug, Default)]
+pub struct SocketSystem;
+
+impl<'a> System<'a> for SocketSystem {
+    type SystemData = (
+        ReadStorage<'a, JointTransforms>,
+        WriteStorage<'a, Sockets>,
+        ReadStorage<'a, SocketAttachment>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (joints, mut sockets, attachments, mut transforms): Self::SystemData) {
+        for (joints, sockets, transform) in (&joints, &mut sockets, &transforms).join() {
+            sockets.resolve(transform.global_matrix(), &joints.matrices);
+        }
+
+        for (attachment, transform) in (&attachments, &mut transforms).join() {
+            if let Some(world) = sockets
+                .get(attachment.mesh)
+                .and_then(|sockets| sockets.world_matrix(&attachment.socket))
+            {
+                set_matrix(transform, world);
+                transform.copy_local_to_global();
+            }
+        }
+    }
+}
+
+/// Set translation, rotation and scale of a transform from an affine matrix without shear.
+fn set_matrix(transform: &mut Transform, matrix: &Matrix4<f32>) {
+    let columns = [
+        matrix.column(0).xyz(),
+        matrix.column(1).xyz(),
+        matrix.column(2).xyz(),
+    ];
+    let scale = Vector3::new(columns[0].norm(), columns[1].norm(), columns[2].norm());
+    if scale.iter().any(|s| *s == 0.0) {
+        return;
+    }
+    let rotation = Matrix3::from_columns(&[
+        columns[0] / scale.x,
+        columns[1] / scale.y,
+        columns[2] / scale.z,
+    ]);
+    transform.set_translation(matrix.column(3).xyz());
+    *transform.rotation_mut() =
+        UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+    transform.set_scale(scale);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, RunNow, World, WorldExt};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn attachments_follow_sockets_in_the_same_frame() {
+        let mut world = World::new();
+        world.register::<JointTransforms>();
+        world.register::<Sockets>();
+        world.register::<SocketAttachment>();
+        world.register::<Transform>();
+
+        let mut mesh_transform = Transform::default();
+        mesh_transform.set_translation_xyz(10.0, 0.0, 0.0);
+        mesh_transform.copy_local_to_global();
+        // The hand bone is raised by one unit and turned a quarter around Y.
+        let joint = Matrix4::new_translation(&Vector3::<f32>::y())
+            * Matrix4::from_axis_angle(&Vector3::<f32>::y_axis(), std::f32::consts::FRAC_PI_2);
+        let socket = Socket {
+            name: "hand".into(),
+            joint: 1,
+            bind_transform: Matrix4::new_translation(&Vector3::new(0.0, 0.0, 2.0)),
+        };
+
+        let skin = world.create_entity().build();
+        let mesh = world
+            .create_entity()
+            .with(mesh_transform)
+            .with(JointTransforms {
+                skin,
+                matrices: vec![Matrix4::identity(), joint],
+            })
+            .with(Sockets::new(vec![socket]))
+            .build();
+        let sword = world
+            .create_entity()
+            .with(Transform::default())
+            .with(SocketAttachment {
+                mesh,
+                socket: "hand".into(),
+            })
+            .build();
+
+        SocketSystem.run_now(&world);
+
+        let expected = Vector3::new(12.0, 1.0, 0.0);
+        let sockets = world.read_storage::<Sockets>();
+        let world_matrix = sockets.get(mesh).unwrap().world_matrix("hand").unwrap();
+        assert_relative_eq!(world_matrix.column(3).xyz(), expected, epsilon = 1e-5);
+        assert!(sockets.get(mesh).unwrap().world_matrix("foot").is_none());
+
+        let transforms = world.read_storage::<Transform>();
+        let sword = transforms.get(sword).unwrap();
+        assert_relative_eq!(*sword.translation(), expected, epsilon = 1e-5);
+        assert_relative_eq!(sword.global_matrix(), world_matrix, epsilon = 1e-5);
+    }
+}
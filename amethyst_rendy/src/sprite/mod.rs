@@ -490,8 +490,7 @@ impl Format<SpriteSheet> for SpriteSheetFormat {
     }
 
     fn import_simple(&self, bytes: Vec<u8>) -> Result<SpriteSheet, Error> {
-        let sprites: Sprites =
-            from_ron_bytes(&bytes).map_err(error::Error::LoadSpritesheetError)?;
+        let sprites: Sprites = from_ron_bytes(&bytes).map_err(error::RenderError::SpriteSheet)?;
 
         Ok(SpriteSheet {
             texture: self.0.clone(),
@@ -0,0 +1,196 @@
+//! Rendering statistics collected while the game is running.
+
+use crate::{memory::GpuMemory, view_mode::ViewMode};
+use amethyst_core::ecs::World;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Statistics about the renderer state, updated every frame by the `RenderingBundle`.
+///
+/// Useful for debug overlays and for catching performance or memory regressions.
+#[derive(Debug, Clone, Default)]
+pub struct RenderStats {
+    /// Approximate GPU memory usage.
+    pub gpu_memory: GpuMemory,
+    /// Number of textures that didn't fit into the `TextureUploadBudget` during the last frame.
+    pub deferred_textures: usize,
+    /// Number of entities outside of the camera frustum during the last frame.
+    pub frustum_culled: usize,
+    /// Number of entities beyond their `DrawDistance` during the last frame.
+    pub distance_culled: usize,
+    /// Number of entries of retained draw lists added, removed, moved to another batch or
+    /// updated during the last frame, summed over all passes keeping one.
+    pub draw_list_touched: usize,
+    /// Number of draws of the 3D passes with a fragment shader which may discard fragments,
+    /// losing the depth test before shading, during the last frame. See `Material::discards`.
+    pub discard_draws: usize,
+    /// Number of draw calls recorded by the 3D passes and the depth passes, like the one of the
+    /// shadow map, during the last frame. An indirect draw counts once, however many draws it issues.
+    pub draw_calls: usize,
+    /// Number of per-frame GPU buffers allocated or grown during the last frame, see
+    /// `util::ensure_buffer`. Zero once a scene reached its steady state. Counted for all the
+    /// renderers of the process together.
+    pub buffer_allocations: usize,
+    /// Number of light buffers written during the last frame, one per 3D pass whose lights
+    /// changed since it last drew the same frame in flight.
+    pub light_uploads: usize,
+    /// CPU time spent recording the draws counted by `draw_calls` during the last frame.
+    pub encode_time: Duration,
+    /// CPU time spent preparing, recording and submitting the render graph during the last
+    /// frame. Doesn't include rebuilding the graph.
+    pub graph_time: Duration,
+    /// View mode the scene was drawn in during the last frame, to label the other statistics.
+    pub view_mode: ViewMode,
+}
+
+impl RenderStats {
+    /// Count the draws recorded by a pass, and the time spent recording them.
+    pub(crate) fn add_draws(world: &World, draw_calls: usize, encode_time: Duration) {
+        if let Some(mut stats) = world.try_fetch_mut::<RenderStats>() {
+            stats.draw_calls += draw_calls;
+            stats.encode_time += encode_time;
+        }
+    }
+}
+
+/// Limits on `RenderStats`, e.g. loaded from a checked-in file by performance regression tests,
+/// so that an intentional regression shows up as an explicit change of the budget.
+///
+/// Limits left out are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderBudget {
+    /// Maximum `graph_time` in milliseconds.
+    pub max_graph_time_ms: Option<f64>,
+    /// Maximum `encode_time` in milliseconds.
+    pub max_encode_time_ms: Option<f64>,
+    /// Maximum number of draw calls, to catch batching or culling that silently stopped working.
+    pub max_draw_calls: Option<usize>,
+    /// Maximum number of per-frame buffers allocated or grown.
+    pub max_buffer_allocations: Option<usize>,
+    /// Maximum number of light buffers written.
+    pub max_light_uploads: Option<usize>,
+    /// Maximum total GPU memory in bytes.
+    pub max_gpu_memory_bytes: Option<u64>,
+    /// Maximum number of textures deferred by the `TextureUploadBudget`.
+    pub max_deferred_textures: Option<usize>,
+    /// Maximum number of draws with a fragment shader which may discard fragments, to catch
+    /// opaque materials of a scene silently losing their early depth test.
+    pub max_discard_draws: Option<usize>,
+    /// Minimum number of entities culled by the camera frustum, to catch culling that silently
+    /// stopped working in a scene built to have entities out of view.
+    pub min_frustum_culled: Option<usize>,
+}
+
+impl RenderBudget {
+    /// Describe every limit exceeded by the given stats, empty if the stats are within budget.
+    pub fn violations(&self, stats: &RenderStats) -> Vec<String> {
+        let mut violations = Vec::new();
+        let graph_time_ms = stats.graph_time.as_secs_f64() * 1000.0;
+        if let Some(max) = self.max_graph_time_ms.filter(|&max| graph_time_ms > max) {
+            violations.push(format!(
+                "graph time {:.3}ms exceeds {:.3}ms",
+                graph_time_ms, max
+            ));
+        }
+        let encode_time_ms = stats.encode_time.as_secs_f64() * 1000.0;
+        if let Some(max) = self.max_encode_time_ms.filter(|&max| encode_time_ms > max) {
+            violations.push(format!(
+                "encode time {:.3}ms exceeds {:.3}ms",
+                encode_time_ms, max
+            ));
+        }
+        if let Some(max) = self.max_draw_calls.filter(|&max| stats.draw_calls > max) {
+            violations.push(format!("{} draw calls exceed {}", stats.draw_calls, max));
+        }
+        if let Some(max) = self
+            .max_buffer_allocations
+            .filter(|&max| stats.buffer_allocations > max)
+        {
+            violations.push(format!(
+                "{} buffer allocations exceed {}",
+                stats.buffer_allocations, max
+            ));
+        }
+        if let Some(max) = self
+            .max_light_uploads
+            .filter(|&max| stats.light_uploads > max)
+        {
+            violations.push(format!(
+                "{} light uploads exceed {}",
+                stats.light_uploads, max
+            ));
+        }
+        let gpu_memory = stats.gpu_memory.total();
+        if let Some(max) = self.max_gpu_memory_bytes.filter(|&max| gpu_memory > max) {
+            violations.push(format!(
+                "GPU memory of {} bytes exceeds {} bytes",
+                gpu_memory, max
+            ));
+        }
+        if let Some(max) = self
+            .max_deferred_textures
+            .filter(|&max| stats.deferred_textures > max)
+        {
+            violations.push(format!(
+                "{} deferred textures exceed {}",
+                stats.deferred_textures, max
+            ));
+        }
+        if let Some(max) = self
+            .max_discard_draws
+            .filter(|&max| stats.discard_draws > max)
+        {
+            violations.push(format!(
+                "{} draws which may discard fragments exceed {}",
+                stats.discard_draws, max
+            ));
+        }
+        if let Some(min) = self
+            .min_frustum_culled
+            .filter(|&min| stats.frustum_culled < min)
+        {
+            violations.push(format!(
+                "{} frustum culled entities are fewer than {}",
+                stats.frustum_culled, min
+            ));
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn checked_in_budgets_parse_and_detect_regressions() {
+        let budgets: HashMap<String, RenderBudget> =
+            toml::from_str(include_str!("../perf_budgets.toml")).unwrap();
+        let budget = &budgets["heavy_scene"];
+
+        let mut stats = RenderStats {
+            frustum_culled: budget.min_frustum_culled.unwrap_or(0),
+            ..Default::default()
+        };
+        assert!(budget.violations(&stats).is_empty());
+
+        stats.graph_time = Duration::from_secs(10);
+        stats.deferred_textures = budget.max_deferred_textures.unwrap() + 1;
+        assert_eq!(budget.violations(&stats).len(), 2);
+
+        stats.encode_time = Duration::from_secs(10);
+        stats.draw_calls = budget.max_draw_calls.unwrap() + 1;
+        stats.buffer_allocations = 1;
+        stats.light_uploads = 1;
+        assert_eq!(budget.violations(&stats).len(), 6);
+
+        let cutout = RenderBudget {
+            max_discard_draws: Some(10),
+            ..Default::default()
+        };
+        stats.discard_draws = 11;
+        assert_eq!(cutout.violations(&stats).len(), 1);
+    }
+}
@@ -0,0 +1,592 @@
+//! Streaming of large textures to the GPU over several frames.
+//!
+//! A texture loaded from `TextureData` is uploaded in one go, so a single big texture stalls the
+//! frame it's uploaded in, however small the `TextureUploadBudget` is. Textures loaded through
+//! `TextureStreams::stream` are split into chunks of rows of their mip levels instead. The
+//! `TextureProcessorSystem` uploads these chunks against the same budget, smallest mip level
+//! first, so a blurry version of the texture is usable after a single frame and sharpens while
+//! the bigger levels arrive.
+//!
+//! Materials clamp the sampled LOD of a streamed texture to the levels already uploaded, see
+//! `TextureStreamProgress::resident_level`. Other users of the texture, like sprites, sample
+//! whatever the missing levels contain until the stream is complete.
+
+use crate::types::{Backend, Texture, TextureData};
+use amethyst_assets::{AssetStorage, Handle, Loader, WeakHandle};
+use fnv::FnvHashMap;
+use rendy::{
+    factory::{Factory, ImageState},
+    hal::{
+        format::{Aspects, Format, Swizzle},
+        image::{
+            Extent, Kind, Layout, Lod, Offset, SamplerInfo, SubresourceLayers, SubresourceRange,
+            Tiling, Usage, ViewCapabilities, ViewKind,
+        },
+    },
+    memory::Data,
+    resource::{Handle as RendyHandle, Image, ImageInfo, ImageViewInfo},
+    texture::TextureBuilder,
+};
+use std::sync::{
+    atomic::{AtomicU64, AtomicU8, Ordering},
+    Arc,
+};
+
+/// Maximum size of a single streamed chunk in bytes. A chunk is made of whole rows of a single
+/// mip level, so levels with rows bigger than this are uploaded a row at a time.
+pub const STREAM_CHUNK_BYTES: u64 = 256 * 1024;
+
+/// Decoded pixels of a texture to stream, with its whole mip chain.
+#[derive(Debug, Clone)]
+pub struct StreamedTextureData {
+    width: u32,
+    height: u32,
+    format: Format,
+    /// Pixels of every mip level, starting from the full size one.
+    levels: Vec<Vec<u8>>,
+    sampler_info: SamplerInfo,
+}
+
+impl StreamedTextureData {
+    /// Texture data from tightly packed RGBA8 pixels, row by row. Its mip levels are generated by
+    /// averaging 2x2 texels of the previous level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the texture is empty or `pixels` doesn't hold `width * height` pixels.
+    pub fn from_rgba8(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        srgb: bool,
+        sampler_info: SamplerInfo,
+    ) -> Self {
+        assert!(width > 0 && height > 0, "Can't stream an empty texture");
+        assert_eq!(
+            pixels.len() as u64,
+            u64::from(width) * u64::from(height) * 4
+        );
+
+        let mut levels = vec![pixels];
+        let (mut w, mut h) = (width, height);
+        while w > 1 || h > 1 {
+            let next = downsample(levels.last().unwrap(), w, h);
+            w = (w / 2).max(1);
+            h = (h / 2).max(1);
+            levels.push(next);
+        }
+        Self {
+            width,
+            height,
+            format: if srgb {
+                Format::Rgba8Srgb
+            } else {
+                Format::Rgba8Unorm
+            },
+            levels,
+            sampler_info,
+        }
+    }
+
+    /// Number of mip levels.
+    pub fn levels(&self) -> u8 {
+        self.levels.len() as u8
+    }
+
+    /// Size in bytes of the whole mip chain.
+    pub fn bytes(&self) -> u64 {
+        self.levels.iter().map(|level| level.len() as u64).sum()
+    }
+
+    fn level_extent(&self, level: u8) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    fn level_builder(&self, level: u8) -> TextureBuilder<'static> {
+        let (width, height) = self.level_extent(level);
+        TextureBuilder::new()
+            .with_kind(Kind::D2(width, height, 1, 1))
+            .with_view_kind(ViewKind::D2)
+            .with_data_width(width)
+            .with_data_height(height)
+            .with_sampler_info(self.sampler_info.clone())
+            .with_raw_data(self.levels[level as usize].clone(), self.format)
+    }
+
+    /// Create the image of the whole mip chain, without uploading anything to it.
+    ///
+    /// `rendy` only builds textures by uploading them, so this builds a texture of the 1x1
+    /// level and swaps its view for one of the new image.
+    fn create<B: Backend>(
+        &self,
+        factory: &mut Factory<B>,
+        next_state: ImageState,
+    ) -> Result<Texture, failure::Error> {
+        let levels = self.levels();
+        let range = SubresourceRange {
+            aspects: Aspects::COLOR,
+            levels: 0..levels,
+            layers: 0..1,
+        };
+        let image: RendyHandle<Image<B>> = factory
+            .create_image(
+                ImageInfo {
+                    kind: Kind::D2(self.width, self.height, 1, 1),
+                    levels,
+                    format: self.format,
+                    tiling: Tiling::Optimal,
+                    view_caps: ViewCapabilities::empty(),
+                    usage: Usage::SAMPLED | Usage::TRANSFER_DST,
+                },
+                Data,
+            )?
+            .into();
+        // The image was just created on this factory, nothing else uses it yet.
+        unsafe {
+            factory.transition_image(image.clone(), range.clone(), Layout::Undefined, next_state);
+        }
+        let mut view = factory.create_image_view(
+            image,
+            ImageViewInfo {
+                view_kind: ViewKind::D2,
+                format: self.format,
+                swizzle: Swizzle::NO,
+                range,
+            },
+        )?;
+
+        let mut texture = self.level_builder(levels - 1).build(next_state, factory)?;
+        // The view of the 1x1 image is destroyed once `view` is dropped.
+        std::mem::swap(texture.view_mut(), &mut *view);
+        Ok(B::wrap_texture(texture))
+    }
+}
+
+/// Average 2x2 texels of a RGBA8 level into the next one.
+fn downsample(pixels: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (next_w, next_h) = ((width / 2).max(1), (height / 2).max(1));
+    let texel = |x: u32, y: u32, c: usize| {
+        u32::from(pixels[((y.min(height - 1) * width + x.min(width - 1)) * 4) as usize + c])
+    };
+    let mut next = Vec::with_capacity((next_w * next_h * 4) as usize);
+    for y in 0..next_h {
+        for x in 0..next_w {
+            let (x0, y0) = (x * 2, y * 2);
+            for c in 0..4 {
+                let sum = texel(x0, y0, c)
+                    + texel(x0 + 1, y0, c)
+                    + texel(x0, y0 + 1, c)
+                    + texel(x0 + 1, y0 + 1, c);
+                next.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+    next
+}
+
+#[derive(Debug)]
+struct StreamProgress {
+    levels: u8,
+    total_bytes: u64,
+    resident_level: AtomicU8,
+    uploaded_bytes: AtomicU64,
+}
+
+/// Progress of a streamed texture, shared with the `TextureProcessorSystem` uploading it.
+#[derive(Debug, Clone)]
+pub struct TextureStreamProgress(Arc<StreamProgress>);
+
+impl TextureStreamProgress {
+    fn new(levels: u8, total_bytes: u64) -> Self {
+        Self(Arc::new(StreamProgress {
+            levels,
+            total_bytes,
+            resident_level: AtomicU8::new(levels),
+            uploaded_bytes: AtomicU64::new(0),
+        }))
+    }
+
+    /// Number of mip levels of the texture.
+    pub fn levels(&self) -> u8 {
+        self.0.levels
+    }
+
+    /// Most detailed mip level uploaded so far, and all the smaller ones with it. Equal to
+    /// `levels` until the first level is uploaded, and `0` once the stream is complete.
+    pub fn resident_level(&self) -> u8 {
+        self.0.resident_level.load(Ordering::Acquire)
+    }
+
+    /// Bytes uploaded so far.
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.0.uploaded_bytes.load(Ordering::Acquire)
+    }
+
+    /// Size in bytes of the whole mip chain.
+    pub fn total_bytes(&self) -> u64 {
+        self.0.total_bytes
+    }
+
+    /// Whether the whole mip chain was uploaded.
+    pub fn is_complete(&self) -> bool {
+        self.resident_level() == 0
+    }
+
+    fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Rows of a single mip level uploaded at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StreamChunk {
+    level: u8,
+    row: u32,
+    rows: u32,
+    bytes: u64,
+}
+
+/// Uploads of a streamed texture not done yet, from the smallest mip level up.
+#[derive(Debug)]
+struct ChunkedUpload {
+    data: StreamedTextureData,
+    progress: TextureStreamProgress,
+    level: u8,
+    row: u32,
+}
+
+impl ChunkedUpload {
+    fn new(data: StreamedTextureData, progress: TextureStreamProgress) -> Self {
+        Self {
+            level: data.levels() - 1,
+            row: 0,
+            data,
+            progress,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.progress.is_complete()
+    }
+
+    /// Plan the next chunks for as long as `admit` accepts their size, marking them uploaded.
+    fn next_chunks(&mut self, admit: &mut impl FnMut(u64) -> bool) -> Vec<StreamChunk> {
+        let mut chunks = Vec::new();
+        while !self.is_complete() {
+            let (width, height) = self.data.level_extent(self.level);
+            let row_bytes = u64::from(width) * 4;
+            let rows = ((STREAM_CHUNK_BYTES / row_bytes).max(1) as u32).min(height - self.row);
+            let bytes = row_bytes * u64::from(rows);
+            if !admit(bytes) {
+                break;
+            }
+            chunks.push(StreamChunk {
+                level: self.level,
+                row: self.row,
+                rows,
+                bytes,
+            });
+            self.progress
+                .0
+                .uploaded_bytes
+                .fetch_add(bytes, Ordering::AcqRel);
+            self.row += rows;
+            if self.row == height {
+                self.progress
+                    .0
+                    .resident_level
+                    .store(self.level, Ordering::Release);
+                self.level = self.level.saturating_sub(1);
+                self.row = 0;
+            }
+        }
+        chunks
+    }
+}
+
+#[derive(Debug)]
+struct TextureStream {
+    id: u32,
+    handle: WeakHandle<Texture>,
+    upload: ChunkedUpload,
+    started: bool,
+}
+
+impl TextureStream {
+    fn write<B: Backend>(
+        &mut self,
+        factory: &mut Factory<B>,
+        storage: &mut AssetStorage<Texture>,
+        handle: &Handle<Texture>,
+        chunks: &[StreamChunk],
+        next_state: ImageState,
+    ) -> Result<(), failure::Error> {
+        if !self.started {
+            let texture = self.upload.data.create(factory, next_state)?;
+            storage.replace(handle, texture);
+            self.started = true;
+        }
+        let image = storage
+            .get(handle)
+            .and_then(B::unwrap_texture)
+            .map(|texture| texture.view().image().clone())
+            .ok_or_else(|| failure::format_err!("Streamed texture of another backend"))?;
+
+        let data = &self.upload.data;
+        for chunk in chunks {
+            let (width, _) = data.level_extent(chunk.level);
+            let start = (u64::from(chunk.row) * u64::from(width) * 4) as usize;
+            // The image was created on this factory, and materials don't sample the levels
+            // that aren't uploaded yet.
+            unsafe {
+                factory.upload_image(
+                    image.clone(),
+                    width,
+                    chunk.rows,
+                    SubresourceLayers {
+                        aspects: Aspects::COLOR,
+                        level: chunk.level,
+                        layers: 0..1,
+                    },
+                    Offset {
+                        x: 0,
+                        y: chunk.row as i32,
+                        z: 0,
+                    },
+                    Extent {
+                        width,
+                        height: chunk.rows,
+                        depth: 1,
+                    },
+                    &data.levels[chunk.level as usize][start..start + chunk.bytes as usize],
+                    next_state,
+                    next_state,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct StreamEntry {
+    progress: TextureStreamProgress,
+    sampler_info: SamplerInfo,
+}
+
+/// Streamed textures still being uploaded, see the [module documentation](index.html).
+#[derive(Debug, Default)]
+pub struct TextureStreams {
+    pending: Vec<TextureStream>,
+    entries: FnvHashMap<u32, StreamEntry>,
+    generation: u64,
+}
+
+impl TextureStreams {
+    /// Load a texture to stream.
+    ///
+    /// The handle points to the 1x1 mip level until the `TextureProcessorSystem` starts
+    /// streaming the texture, which it does once it has uploaded the pending textures of the
+    /// frame, if the `TextureUploadBudget` allows it.
+    pub fn stream(
+        &mut self,
+        data: StreamedTextureData,
+        loader: &Loader,
+        storage: &AssetStorage<Texture>,
+    ) -> (Handle<Texture>, TextureStreamProgress) {
+        let placeholder = TextureData(data.level_builder(data.levels() - 1));
+        let handle = loader.load_from_data(placeholder, (), storage);
+        let progress = TextureStreamProgress::new(data.levels(), data.bytes());
+        self.entries.insert(
+            handle.id(),
+            StreamEntry {
+                progress: progress.clone(),
+                sampler_info: data.sampler_info.clone(),
+            },
+        );
+        self.pending.push(TextureStream {
+            id: handle.id(),
+            handle: handle.downgrade(),
+            upload: ChunkedUpload::new(data, progress.clone()),
+            started: false,
+        });
+        (handle, progress)
+    }
+
+    /// Progress of the texture of given handle, if it's still being streamed.
+    pub fn progress(&self, handle: &Handle<Texture>) -> Option<&TextureStreamProgress> {
+        self.entries.get(&handle.id()).map(|entry| &entry.progress)
+    }
+
+    /// Most detailed mip level of the texture of given handle which can be sampled, `0` if the
+    /// texture isn't streamed or its stream is complete.
+    pub fn resident_level(&self, handle: &Handle<Texture>) -> u8 {
+        self.progress(handle)
+            .map_or(0, TextureStreamProgress::resident_level)
+    }
+
+    /// Sampler of the texture of given handle clamping its LOD to its resident levels, if it's
+    /// partially streamed.
+    pub fn clamped_sampler(&self, handle: &Handle<Texture>) -> Option<SamplerInfo> {
+        let entry = self.entries.get(&handle.id())?;
+        let resident = entry.progress.resident_level();
+        if resident == 0 || resident == entry.progress.levels() {
+            return None;
+        }
+        Some(SamplerInfo {
+            lod_range: Lod::from(f32::from(resident))..entry.sampler_info.lod_range.end,
+            ..entry.sampler_info.clone()
+        })
+    }
+
+    /// Incremented every time the resident level of a streamed texture changes.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Number of textures still being streamed.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no texture is being streamed.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Upload the next chunks of the pending streams, in order, while `admit` accepts their size.
+    pub(crate) fn upload<B: Backend>(
+        &mut self,
+        factory: &mut Factory<B>,
+        storage: &mut AssetStorage<Texture>,
+        next_state: ImageState,
+        mut admit: impl FnMut(u64) -> bool,
+    ) {
+        let entries = &mut self.entries;
+        let generation = &mut self.generation;
+        let mut exhausted = false;
+        self.pending.retain_mut(|stream| {
+            let handle = match stream.handle.upgrade() {
+                Some(handle) if !storage.is_failed(&handle) => handle,
+                _ => {
+                    remove_entry(entries, stream);
+                    return false;
+                }
+            };
+            // The placeholder isn't loaded yet.
+            if exhausted || !storage.contains(&handle) {
+                return true;
+            }
+
+            let resident = stream.upload.progress.resident_level();
+            let chunks = stream.upload.next_chunks(&mut admit);
+            if chunks.is_empty() {
+                exhausted = true;
+                return true;
+            }
+            if let Err(err) = stream.write(factory, storage, &handle, &chunks, next_state) {
+                log::error!("Failed to stream texture {}: {}", handle.id(), err);
+                remove_entry(entries, stream);
+                *generation += 1;
+                return false;
+            }
+            if stream.upload.progress.resident_level() != resident {
+                *generation += 1;
+            }
+            if stream.upload.is_complete() {
+                remove_entry(entries, stream);
+                false
+            } else {
+                exhausted = true;
+                true
+            }
+        });
+    }
+}
+
+fn remove_entry(entries: &mut FnvHashMap<u32, StreamEntry>, stream: &TextureStream) {
+    if entries
+        .get(&stream.id)
+        .is_some_and(|entry| entry.progress.ptr_eq(&stream.upload.progress))
+    {
+        entries.remove(&stream.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{FrameUploads, TextureUploadBudget};
+    use rendy::hal::image::{Filter, WrapMode};
+    use std::time::Duration;
+
+    fn data(size: u32) -> StreamedTextureData {
+        let pixels = (0..size * size)
+            .flat_map(|i| vec![(i % 251) as u8, (i % 13) as u8, 0, 255])
+            .collect();
+        StreamedTextureData::from_rgba8(
+            size,
+            size,
+            pixels,
+            true,
+            SamplerInfo::new(Filter::Linear, WrapMode::Tile),
+        )
+    }
+
+    #[test]
+    fn mip_chain_averages_texels() {
+        let data = StreamedTextureData::from_rgba8(
+            2,
+            1,
+            vec![0, 10, 255, 255, 100, 20, 255, 255],
+            false,
+            SamplerInfo::new(Filter::Linear, WrapMode::Clamp),
+        );
+        assert_eq!(data.levels(), 2);
+        assert_eq!(data.levels[1], vec![50, 15, 255, 255]);
+        assert_eq!(data.bytes(), 12);
+        assert_eq!(self::data(1024).levels(), 11);
+    }
+
+    #[test]
+    fn texture_bigger_than_the_budget_streams_over_several_frames() {
+        let data = data(1024);
+        let total = data.bytes();
+        let budget = TextureUploadBudget::default().with_max_bytes(1 << 20);
+        assert!(total > 4 << 20);
+
+        let progress = TextureStreamProgress::new(data.levels(), total);
+        let mut upload = ChunkedUpload::new(data, progress.clone());
+        let mut levels = Vec::new();
+        let mut frames = 0;
+        while !upload.is_complete() {
+            let mut uploads = FrameUploads::default();
+            let chunks = upload.next_chunks(&mut |bytes| {
+                if uploads.defer_chunk(&budget, Duration::from_secs(0)) {
+                    return false;
+                }
+                uploads.streamed(bytes);
+                true
+            });
+            assert!(!chunks.is_empty());
+            assert!(chunks.iter().all(|chunk| chunk.bytes <= STREAM_CHUNK_BYTES));
+            let bytes: u64 = chunks.iter().map(|chunk| chunk.bytes).sum();
+            assert!(bytes < (1 << 20) + STREAM_CHUNK_BYTES);
+            levels.extend(chunks.iter().map(|chunk| chunk.level));
+
+            frames += 1;
+            if frames == 1 {
+                // The whole tail of the mip chain is usable after the first frame.
+                assert!(progress.resident_level() <= 2);
+            }
+            assert_eq!(progress.uploaded_bytes() < total, !progress.is_complete());
+        }
+
+        assert!(frames > 4);
+        assert_eq!(progress.uploaded_bytes(), total);
+        assert_eq!(progress.resident_level(), 0);
+        // Smallest mips first.
+        assert_eq!(levels.first(), Some(&10));
+        assert!(levels.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+}
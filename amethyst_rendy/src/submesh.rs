@@ -0,0 +1,230 @@
+//! Meshes drawn with several materials.
+//!
+//! A mesh with several materials keeps one vertex buffer, and every [SubMesh] draws a range of
+//! its indices with its own material. The 3D passes batch sub-meshes by material like whole
+//! meshes, so sub-meshes of different entities sharing a material are drawn together.
+
+use crate::mtl::Material;
+use amethyst_assets::Handle;
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage};
+use rendy::{
+    command::RenderPassEncoder,
+    hal::Backend,
+    mesh::{Incompatible, Mesh, VertexFormat},
+};
+use std::ops::Range;
+
+/// Range of the indices of a mesh drawn with a material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubMesh {
+    /// Indices drawn, clamped to the indices of the mesh.
+    pub indices: Range<u32>,
+    /// Material the indices are drawn with.
+    pub material: Handle<Material>,
+}
+
+/// Parts of the entity `Handle<Mesh>` drawn with their own materials.
+///
+/// An entity with `SubMeshes` is drawn with the materials of its sub-meshes, its
+/// `Handle<Material>` is ignored. The mesh must have an index buffer, the ranges index into it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubMeshes(pub Vec<SubMesh>);
+
+impl Component for SubMeshes {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl SubMeshes {
+    /// Sub-meshes of given index ranges and materials.
+    pub fn new(parts: impl IntoIterator<Item = (Range<u32>, Handle<Material>)>) -> Self {
+        SubMeshes(
+            parts
+                .into_iter()
+                .map(|(indices, material)| SubMesh { indices, material })
+                .collect(),
+        )
+    }
+}
+
+/// Mesh, or range of its indices, drawn by a batch.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MeshPart {
+    /// Id of the mesh.
+    pub mesh: u32,
+    /// Indices drawn, or `None` for the whole mesh.
+    pub indices: Option<Range<u32>>,
+}
+
+impl MeshPart {
+    /// The whole mesh of given id.
+    pub fn whole(mesh: u32) -> Self {
+        MeshPart {
+            mesh,
+            indices: None,
+        }
+    }
+
+    /// Indices drawn of a mesh with given number of indices.
+    pub fn index_range(&self, len: u32) -> Range<u32> {
+        match &self.indices {
+            None => 0..len,
+            Some(indices) => {
+                let end = indices.end.min(len);
+                indices.start.min(end)..end
+            }
+        }
+    }
+
+    /// Bind the buffers of the mesh of the part, unless they are already bound.
+    pub fn bind<B: Backend>(
+        &self,
+        mesh: &Mesh<B>,
+        bound: &mut Option<u32>,
+        formats: &[VertexFormat],
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) -> Result<(), Incompatible> {
+        if *bound != Some(self.mesh) {
+            mesh.bind(0, formats, encoder)?;
+            *bound = Some(self.mesh);
+        }
+        Ok(())
+    }
+
+    /// Draw the part with given instances.
+    ///
+    /// `bound` is the id of the mesh whose buffers are bound. Parts of the bound mesh are drawn
+    /// without binding it again. Reset it to `None` whenever the vertex formats change.
+    pub fn draw<B: Backend>(
+        &self,
+        mesh: &Mesh<B>,
+        bound: &mut Option<u32>,
+        formats: &[VertexFormat],
+        instances: Range<u32>,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) -> Result<(), Incompatible> {
+        match &self.indices {
+            None => {
+                mesh.bind_and_draw(0, formats, instances, encoder)?;
+                *bound = Some(self.mesh);
+            }
+            Some(_) => {
+                self.bind(mesh, bound, formats, encoder)?;
+                unsafe {
+                    encoder.draw_indexed(self.index_range(mesh.len()), 0, instances);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Material and part of every draw of an entity: a part per sub-mesh when it has `SubMeshes`,
+/// otherwise its whole mesh with its material, if any.
+pub(crate) fn mesh_parts<'a>(
+    mesh: u32,
+    material: Option<&'a Handle<Material>>,
+    sub_meshes: Option<&'a SubMeshes>,
+) -> impl Iterator<Item = (&'a Handle<Material>, MeshPart)> + 'a {
+    let whole = if sub_meshes.is_none() {
+        material.map(|material| (material, MeshPart::whole(mesh)))
+    } else {
+        None
+    };
+    let parts = sub_meshes.into_iter().flat_map(move |sub_meshes| {
+        sub_meshes.0.iter().map(move |part| {
+            (
+                &part.material,
+                MeshPart {
+                    mesh,
+                    indices: Some(part.indices.clone()),
+                },
+            )
+        })
+    });
+    whole.into_iter().chain(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        batch::TwoLevelBatch, material_shader::MAX_MATERIAL_SHADER_PARAMS,
+        texture::uv_gradient_data, types::Texture,
+    };
+    use amethyst_assets::{AssetStorage, Loader};
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    #[test]
+    fn sub_meshes_replace_entity_material() {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let tex_storage = AssetStorage::<Texture>::new();
+        let mat_storage = AssetStorage::<Material>::new();
+        let tex = loader.load_from_data(uv_gradient_data(4), (), &tex_storage);
+        let material = || {
+            let mat = Material {
+                alpha_cutoff: 0.01,
+                albedo: tex.clone(),
+                emission: tex.clone(),
+                normal: tex.clone(),
+                metallic_roughness: tex.clone(),
+                ambient_occlusion: tex.clone(),
+                cavity: tex.clone(),
+                uv_offset: Default::default(),
+                emission_factor: [1.0; 3],
+                uv_transform: Default::default(),
+                normal_uv_transform: Default::default(),
+                dissolve_noise: tex.clone(),
+                dissolve_amount: 0.0,
+                dissolve_edge_width: 0.0,
+                dissolve_edge_color: [0.0; 3],
+                triplanar_scale: 0.0,
+                triplanar_sharpness: 1.0,
+                detail: tex.clone(),
+                detail_scale: 1.0,
+                detail_blend: Default::default(),
+                detail_fade_distance: 0.0,
+                layer_mask: tex.clone(),
+                layer_color: [1.0; 3],
+                layer_roughness: 0.0,
+                layer_amount: 0.0,
+                layer_scene_factor: 0.0,
+                light_channels: !0,
+                subsurface: 0.0,
+                specular: [0.0; 3],
+                shininess: 1.0,
+                shader_params: [[0.0; 4]; MAX_MATERIAL_SHADER_PARAMS],
+            };
+            loader.load_from_data(mat, (), &mat_storage)
+        };
+        let (red, blue) = (material(), material());
+
+        let whole = mesh_parts(1, Some(&red), None).collect::<Vec<_>>();
+        assert_eq!(whole, vec![(&red, MeshPart::whole(1))]);
+        assert_eq!(mesh_parts(1, None, None).count(), 0);
+
+        let sub_meshes = SubMeshes::new(vec![(0..6, blue.clone()), (6..12, red.clone())]);
+        let parts = mesh_parts(2, Some(&blue), Some(&sub_meshes)).collect::<Vec<_>>();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].0, &blue);
+        assert_eq!(parts[1].0, &red);
+        assert_eq!(parts[1].1.indices, Some(6..12));
+        assert_eq!(parts[1].1.index_range(9), 6..9);
+        assert_eq!(MeshPart::whole(1).index_range(9), 0..9);
+
+        // Parts sharing a material are batched together, identical parts share instances.
+        let mut batch = TwoLevelBatch::<u32, MeshPart, Vec<u32>>::default();
+        for (instance, (mat, part)) in whole.into_iter().chain(parts.clone()).enumerate() {
+            batch.insert(mat.id(), part, Some(instance as u32));
+        }
+        batch.insert(parts[0].0.id(), parts[0].1.clone(), Some(3));
+        assert_eq!(batch.count(), 4);
+        let red_parts = batch
+            .iter()
+            .find(|(&mat, _)| mat == red.id())
+            .unwrap()
+            .1
+            .count();
+        assert_eq!(red_parts, 2);
+    }
+}
@@ -1,32 +1,47 @@
 //! Environment submodule for shared environmental descriptor set data.
 //! Fetches and sets projection and lighting descriptor set information.
 use crate::{
-    light::Light,
+    capsule::{CapsuleOccluder, MAX_CAPSULE_OCCLUDERS},
+    light::{Light, LightLimits},
     pod::{self, IntoPod},
     rendy::{
-        command::RenderPassEncoder,
-        factory::Factory,
-        hal::{self, adapter::PhysicalDevice, device::Device, pso::Descriptor},
+        command::{QueueId, RenderPassEncoder},
+        factory::{Factory, ImageState},
+        graph::{GraphContext, NodeImage},
+        hal::{self, adapter::PhysicalDevice, device::Device, format::Aspects, pso::Descriptor},
         memory::Write as _,
-        resource::{Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
+        resource::{
+            Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+            ImageViewInfo, Sampler,
+        },
+        texture::{pixel::Rgba8Unorm, Texture, TextureBuilder},
     },
-    submodules::gather::{AmbientGatherer, CameraGatherer},
+    resources::SurfaceLayer,
+    shadow::ShadowSettings,
+    stats::RenderStats,
+    submodules::gather::{AmbientGatherer, CameraGatherer, FogGatherer, ShadowGatherer},
     types::Backend,
     util::{self, TapCountIter},
 };
 use amethyst_core::{
-    ecs::{Join, ReadStorage, SystemData, World},
-    math::{convert, Vector3},
+    ecs::{Entities, Entity, Join, Read, ReadStorage, SystemData, World},
+    math::{convert, Matrix4, Point3, Vector3},
     transform::Transform,
 };
 use glsl_layout::*;
+use std::{cmp::Ordering, ops::Range};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
-const MAX_POINT_LIGHTS: usize = 128;
-const MAX_DIR_LIGHTS: usize = 16;
-const MAX_SPOT_LIGHTS: usize = 128;
+/// Maximum number of point lights lighting the 3D passes. When the scene has more, only the
+/// ones nearest to the camera are drawn.
+pub const MAX_POINT_LIGHTS: usize = 128;
+/// Maximum number of directional lights lighting the 3D passes. Further lights are ignored.
+pub const MAX_DIR_LIGHTS: usize = 16;
+/// Maximum number of spot lights lighting the 3D passes. When the scene has more, only the
+/// ones nearest to the camera are drawn.
+pub const MAX_SPOT_LIGHTS: usize = 128;
 
 /// Submodule for loading and binding descriptor sets for a 3D, lit environment.
 /// This also abstracts away the need for handling multiple images in flight, as it provides
@@ -35,6 +50,45 @@ const MAX_SPOT_LIGHTS: usize = 128;
 pub struct EnvironmentSub<B: Backend> {
     layout: RendyHandle<DescriptorSetLayout<B>>,
     per_image: Vec<PerImageEnvironmentSub<B>>,
+    camera: Option<Entity>,
+    warned_lights: bool,
+    shadow_map: Option<ShadowMap<B>>,
+}
+
+/// Shadow map sampled by the environment set, see [crate::shadow].
+#[derive(Debug)]
+enum ShadowMap<B: Backend> {
+    /// Depth of the shadow map graph image, its layout and its resolution.
+    Graph(
+        Escape<ImageView<B>>,
+        hal::image::Layout,
+        u32,
+        RendyHandle<Sampler<B>>,
+    ),
+    /// Texture bound by passes drawn without shadows.
+    Placeholder(Texture<B>),
+}
+
+impl<B: Backend> ShadowMap<B> {
+    fn descriptor(&self) -> Descriptor<'_, B> {
+        match self {
+            ShadowMap::Graph(view, layout, _, sampler) => {
+                Descriptor::CombinedImageSampler(view.raw(), *layout, sampler.raw())
+            }
+            ShadowMap::Placeholder(texture) => Descriptor::CombinedImageSampler(
+                texture.view().raw(),
+                hal::image::Layout::ShaderReadOnlyOptimal,
+                texture.sampler().raw(),
+            ),
+        }
+    }
+
+    fn resolution(&self) -> Option<u32> {
+        match self {
+            ShadowMap::Graph(_, _, resolution, _) => Some(*resolution),
+            ShadowMap::Placeholder(_) => None,
+        }
+    }
 }
 
 /// Submodule for loading and binding descriptor sets for a 3D, lit environment.
@@ -44,6 +98,9 @@ pub struct EnvironmentSub<B: Backend> {
 struct PerImageEnvironmentSub<B: Backend> {
     buffer: Option<Escape<Buffer<B>>>,
     set: Escape<DescriptorSet<B>>,
+    /// Lights last written to the buffer, and the lights of the current frame.
+    lights: Vec<u8>,
+    scratch: Vec<u8>,
 }
 
 impl<B: Backend> EnvironmentSub<B> {
@@ -54,11 +111,79 @@ impl<B: Backend> EnvironmentSub<B> {
         flags: [hal::pso::ShaderStageFlags; 2],
     ) -> Result<Self, failure::Error> {
         Ok(Self {
-            layout: set_layout! {factory, [1] UniformBuffer flags[0], [4] UniformBuffer flags[1]},
+            layout: set_layout! {factory, [1] UniformBuffer flags[0], [7] UniformBuffer flags[1], [1] CombinedImageSampler flags[1]},
             per_image: Vec::new(),
+            camera: None,
+            warned_lights: false,
+            shadow_map: None,
         })
     }
 
+    /// Sample the depth of the given shadow map graph image, or a placeholder when `None`.
+    ///
+    /// The environment set always has a shadow map binding, so this must be called before
+    /// processing the set.
+    pub fn with_shadow_map(
+        mut self,
+        ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        queue: QueueId,
+        image: Option<&NodeImage>,
+    ) -> Result<Self, failure::Error> {
+        let shadow_map = match image {
+            Some(node_image) => {
+                let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                    failure::format_err!("Graph image {:?} not found", node_image.id)
+                })?;
+                let resolution = image.kind().extent().width;
+                let view = factory.create_image_view(
+                    image.clone(),
+                    ImageViewInfo {
+                        view_kind: hal::image::ViewKind::D2,
+                        format: image.format(),
+                        swizzle: hal::format::Swizzle::NO,
+                        range: hal::image::SubresourceRange {
+                            aspects: Aspects::DEPTH,
+                            levels: 0..1,
+                            layers: 0..1,
+                        },
+                    },
+                )?;
+                let sampler = factory.get_sampler(hal::image::SamplerInfo::new(
+                    hal::image::Filter::Nearest,
+                    hal::image::WrapMode::Clamp,
+                ))?;
+                ShadowMap::Graph(view, node_image.layout, resolution, sampler)
+            }
+            None => ShadowMap::Placeholder(
+                TextureBuilder::new()
+                    .with_kind(hal::image::Kind::D2(1, 1, 1, 1))
+                    .with_view_kind(hal::image::ViewKind::D2)
+                    .with_data_width(1)
+                    .with_data_height(1)
+                    .with_data(vec![Rgba8Unorm { repr: [0; 4] }])
+                    .build(
+                        ImageState {
+                            queue,
+                            stage: hal::pso::PipelineStage::FRAGMENT_SHADER,
+                            access: hal::image::Access::SHADER_READ,
+                            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+                        },
+                        factory,
+                    )?,
+            ),
+        };
+        self.shadow_map = Some(shadow_map);
+        self.per_image.clear();
+        Ok(self)
+    }
+
+    /// Use the given camera entity instead of the `ActiveCamera`.
+    pub fn with_camera(mut self, camera: Option<Entity>) -> Self {
+        self.camera = camera;
+        self
+    }
+
     /// Returns the raw `DescriptorSetLayout` for this environment
     pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
         self.layout.raw()
@@ -71,12 +196,20 @@ impl<B: Backend> EnvironmentSub<B> {
 
         let this_image = {
             while self.per_image.len() <= index {
-                self.per_image
-                    .push(PerImageEnvironmentSub::new(factory, &self.layout));
+                self.per_image.push(PerImageEnvironmentSub::new(
+                    factory,
+                    &self.layout,
+                    self.shadow_map.as_ref(),
+                ));
             }
             &mut self.per_image[index]
         };
-        this_image.process(factory, world)
+        let shadow = self
+            .shadow_map
+            .as_ref()
+            .and_then(ShadowMap::resolution)
+            .and_then(|resolution| ShadowGatherer::gather(world, resolution));
+        this_image.process(factory, world, self.camera, shadow, &mut self.warned_lights)
     }
 
     /// Binds this environment set for all images.
@@ -93,10 +226,26 @@ impl<B: Backend> EnvironmentSub<B> {
 }
 
 impl<B: Backend> PerImageEnvironmentSub<B> {
-    fn new(factory: &Factory<B>, layout: &RendyHandle<DescriptorSetLayout<B>>) -> Self {
+    fn new(
+        factory: &Factory<B>,
+        layout: &RendyHandle<DescriptorSetLayout<B>>,
+        shadow_map: Option<&ShadowMap<B>>,
+    ) -> Self {
+        let set = factory.create_descriptor_set(layout.clone()).unwrap();
+        if let Some(shadow_map) = shadow_map {
+            unsafe {
+                factory.write_descriptor_sets(Some(util::desc_write(
+                    set.raw(),
+                    8,
+                    shadow_map.descriptor(),
+                )));
+            }
+        }
         Self {
             buffer: None,
-            set: factory.create_descriptor_set(layout.clone()).unwrap(),
+            set,
+            lights: Vec::new(),
+            scratch: Vec::new(),
         }
     }
 
@@ -117,7 +266,14 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
         }
     }
 
-    fn process(&mut self, factory: &Factory<B>, world: &World) -> bool {
+    fn process(
+        &mut self,
+        factory: &Factory<B>,
+        world: &World,
+        camera: Option<Entity>,
+        shadow: Option<ShadowGatherer>,
+        warned_lights: &mut bool,
+    ) -> bool {
         let align = factory
             .physical()
             .limits()
@@ -128,14 +284,22 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
         let plight_buf_size = util::align_size::<pod::PointLight>(align, MAX_POINT_LIGHTS);
         let dlight_buf_size = util::align_size::<pod::DirectionalLight>(align, MAX_DIR_LIGHTS);
         let slight_buf_size = util::align_size::<pod::SpotLight>(align, MAX_SPOT_LIGHTS);
+        let fog_buf_size = util::align_size::<pod::Fog>(align, 1);
+        let capsule_buf_size =
+            util::align_size::<pod::CapsuleOccluder>(align, MAX_CAPSULE_OCCLUDERS);
+        let shadow_buf_size = util::align_size::<pod::ShadowArgs>(align, 1);
 
         let projview_range = 0..projview_size;
         let env_range = util::next_range(&projview_range, env_buf_size);
         let plight_range = util::next_range(&env_range, plight_buf_size);
         let dlight_range = util::next_range(&plight_range, dlight_buf_size);
         let slight_range = util::next_range(&dlight_range, slight_buf_size);
+        let fog_range = util::next_range(&slight_range, fog_buf_size);
 
-        let whole_range = 0..slight_range.end;
+        let capsule_range = util::next_range(&fog_range, capsule_buf_size);
+        let shadow_range = util::next_range(&capsule_range, shadow_buf_size);
+
+        let whole_range = 0..shadow_range.end;
 
         let new_buffer = util::ensure_buffer(
             &factory,
@@ -156,6 +320,9 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
                 let desc_plight = Descriptor::Buffer(buffer, opt_range(plight_range.clone()));
                 let desc_dlight = Descriptor::Buffer(buffer, opt_range(dlight_range.clone()));
                 let desc_slight = Descriptor::Buffer(buffer, opt_range(slight_range.clone()));
+                let desc_fog = Descriptor::Buffer(buffer, opt_range(fog_range.clone()));
+                let desc_capsule = Descriptor::Buffer(buffer, opt_range(capsule_range.clone()));
+                let desc_shadow = Descriptor::Buffer(buffer, opt_range(shadow_range.clone()));
 
                 unsafe {
                     factory.write_descriptor_sets(vec![
@@ -164,6 +331,9 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
                         desc_write(env_set, 2, desc_plight),
                         desc_write(env_set, 3, desc_dlight),
                         desc_write(env_set, 4, desc_slight),
+                        desc_write(env_set, 5, desc_fog),
+                        desc_write(env_set, 6, desc_capsule),
+                        desc_write(env_set, 7, desc_shadow),
                     ]);
                 }
             }
@@ -171,7 +341,7 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
             let CameraGatherer {
                 camera_position,
                 projview,
-            } = CameraGatherer::gather(world);
+            } = CameraGatherer::gather_for(world, camera);
 
             let mut mapped = buffer.map(factory, whole_range.clone()).unwrap();
             let mut writer = unsafe { mapped.write::<u8>(factory, whole_range).unwrap() };
@@ -183,87 +353,246 @@ impl<B: Backend> PerImageEnvironmentSub<B> {
                 point_light_count: 0,
                 directional_light_count: 0,
                 spot_light_count: 0,
+                surface_layer: <Option<Read<'_, SurfaceLayer>>>::fetch(world).map_or(0.0, |l| l.0),
+                capsule_count: 0,
             }
             .std140();
 
-            let (lights, transforms) =
-                <(ReadStorage<'_, Light>, ReadStorage<'_, Transform>)>::fetch(world);
+            let (entities, lights, transforms) = <(
+                Entities<'_>,
+                ReadStorage<'_, Light>,
+                ReadStorage<'_, Transform>,
+            )>::fetch(world);
 
-            let point_lights = (&lights, &transforms)
-                .join()
-                .filter_map(|(light, transform)| match light {
-                    Light::Point(light) => Some(
-                        pod::PointLight {
-                            position: convert::<_, Vector3<f32>>(
-                                transform.global_matrix().column(3).xyz(),
-                            )
-                            .into_pod(),
-                            color: light.color.into_pod(),
-                            intensity: light.intensity,
+            let limits = <Option<Read<'_, LightLimits>>>::fetch(world)
+                .map_or_else(LightLimits::default, |limits| limits.clone());
+            let max_point_lights = limits.point.min(MAX_POINT_LIGHTS);
+            let max_dir_lights = limits.directional.min(MAX_DIR_LIGHTS);
+            let max_spot_lights = limits.spot.min(MAX_SPOT_LIGHTS);
+
+            let camera = Vector3::from(*AsRef::<[f32; 3]>::as_ref(&camera_position));
+            let light_position = |transform: &Transform| {
+                convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz())
+            };
+
+            let (point_lights, point_light_total) = nearest(
+                (&lights, &transforms)
+                    .join()
+                    .filter_map(|(light, transform)| match light {
+                        Light::Point(light) => {
+                            let position = light_position(transform);
+                            Some((
+                                (position - camera).norm_squared(),
+                                pod::PointLight {
+                                    position: position.into_pod(),
+                                    channels: light.channels,
+                                    color: light.color.into_pod(),
+                                    intensity: light.intensity,
+                                }
+                                .std140(),
+                            ))
                         }
-                        .std140(),
-                    ),
-                    _ => None,
-                })
-                .take(MAX_POINT_LIGHTS);
+                        _ => None,
+                    })
+                    .collect(),
+                max_point_lights,
+            );
 
-            let dir_lights = lights
+            let dir_light_total = lights
+                .join()
+                .filter(|light| matches!(light, Light::Directional(_)))
+                .count();
+            let dir_lights = (&entities, &lights)
                 .join()
-                .filter_map(|light| match light {
-                    Light::Directional(ref light) => Some(
+                .filter_map(|(entity, light)| match light {
+                    Light::Directional(ref light) => Some((
+                        entity,
                         pod::DirectionalLight {
                             color: light.color.into_pod(),
                             intensity: light.intensity,
                             direction: light.direction.into_pod(),
+                            channels: light.channels,
                         }
                         .std140(),
-                    ),
+                    )),
                     _ => None,
                 })
-                .take(MAX_DIR_LIGHTS);
+                .take(max_dir_lights)
+                .collect::<Vec<_>>();
+
+            // The light casting shadows may not be among the drawn lights.
+            let shadow = shadow.and_then(|shadow| {
+                let index = dir_lights
+                    .iter()
+                    .position(|(entity, _)| *entity == shadow.light)?;
+                let settings = <Read<'_, ShadowSettings>>::fetch(world);
+                let depth = settings.depth.max(f32::EPSILON);
+                let proj_view: [[f32; 4]; 4] = shadow.proj_view.into();
+                Some(pod::ShadowArgs {
+                    proj_view: proj_view.into(),
+                    depth_bias: settings.depth_bias / depth,
+                    slope_bias: settings.slope_bias / depth,
+                    light: index as i32,
+                    filter_radius: settings.filter.radius(),
+                })
+            });
+            let identity: [[f32; 4]; 4] = Matrix4::identity().into();
+            let shadow = shadow.unwrap_or(pod::ShadowArgs {
+                proj_view: identity.into(),
+                depth_bias: 0.0,
+                slope_bias: 0.0,
+                light: -1,
+                filter_radius: 0,
+            });
+
+            let (spot_lights, spot_light_total) = nearest(
+                (&lights, &transforms)
+                    .join()
+                    .filter_map(|(light, transform)| {
+                        if let Light::Spot(ref light) = *light {
+                            let position = light_position(transform);
+                            Some((
+                                (position - camera).norm_squared(),
+                                pod::SpotLight {
+                                    position: position.into_pod(),
+                                    channels: light.channels,
+                                    color: light.color.into_pod(),
+                                    direction: light.direction.into_pod(),
+                                    angle: light.angle.cos(),
+                                    intensity: light.intensity,
+                                    range: light.range,
+                                    smoothness: light.smoothness,
+                                }
+                                .std140(),
+                            ))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect(),
+                max_spot_lights,
+            );
 
-            let spot_lights = (&lights, &transforms)
+            // Only the capsules nearest to the camera fit in the buffer.
+            let mut capsules = (
+                &<ReadStorage<'_, CapsuleOccluder>>::fetch(world),
+                &transforms,
+            )
                 .join()
-                .filter_map(|(light, transform)| {
-                    if let Light::Spot(ref light) = *light {
-                        Some(
-                            pod::SpotLight {
-                                position: convert::<_, Vector3<f32>>(
-                                    transform.global_matrix().column(3).xyz(),
-                                )
-                                .into_pod(),
-                                color: light.color.into_pod(),
-                                direction: light.direction.into_pod(),
-                                angle: light.angle.cos(),
-                                intensity: light.intensity,
-                                range: light.range,
-                                smoothness: light.smoothness,
-                            }
-                            .std140(),
-                        )
-                    } else {
-                        None
-                    }
+                .map(|(capsule, transform)| {
+                    let matrix = convert::<_, Matrix4<f32>>(*transform.global_matrix());
+                    let axis = Vector3::new(0.0, capsule.half_length, 0.0);
+                    let start = matrix.transform_point(&Point3::from(axis)).coords;
+                    let end = matrix.transform_point(&Point3::from(-axis)).coords;
+                    let distance = ((start + end) * 0.5 - camera).norm_squared();
+                    (distance, start, capsule.radius, end)
                 })
-                .take(MAX_SPOT_LIGHTS);
+                .collect::<Vec<_>>();
+            capsules.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+            let capsules =
+                capsules
+                    .into_iter()
+                    .take(MAX_CAPSULE_OCCLUDERS)
+                    .map(|(_, start, radius, end)| {
+                        pod::CapsuleOccluder {
+                            start: start.into_pod(),
+                            radius,
+                            end: end.into_pod(),
+                        }
+                        .std140()
+                    });
 
             use util::{usize_range, write_into_slice};
+            // Lights are only written when they changed, into a copy of their part of the buffer.
+            let lights_range = plight_range.start..slight_range.end;
+            let local = |range: Range<u64>| {
+                usize_range(range.start - lights_range.start..range.end - lights_range.start)
+            };
+            self.scratch.clear();
+            self.scratch
+                .resize((lights_range.end - lights_range.start) as usize, 0);
             write_into_slice(
-                &mut dst_slice[usize_range(plight_range)],
-                point_lights.tap_count(&mut env.point_light_count),
+                &mut self.scratch[local(plight_range)],
+                point_lights
+                    .into_iter()
+                    .tap_count(&mut env.point_light_count),
             );
             write_into_slice(
-                &mut dst_slice[usize_range(dlight_range)],
-                dir_lights.tap_count(&mut env.directional_light_count),
+                &mut self.scratch[local(dlight_range)],
+                dir_lights
+                    .into_iter()
+                    .map(|(_, light)| light)
+                    .tap_count(&mut env.directional_light_count),
             );
             write_into_slice(
-                &mut dst_slice[usize_range(slight_range)],
-                spot_lights.tap_count(&mut env.spot_light_count),
+                &mut self.scratch[local(slight_range)],
+                spot_lights.into_iter().tap_count(&mut env.spot_light_count),
+            );
+            if new_buffer || self.scratch != self.lights {
+                dst_slice[usize_range(lights_range)].copy_from_slice(&self.scratch);
+                std::mem::swap(&mut self.lights, &mut self.scratch);
+                if let Some(mut stats) = world.try_fetch_mut::<RenderStats>() {
+                    stats.light_uploads += 1;
+                }
+            }
+
+            let dropped = point_light_total > max_point_lights
+                || dir_light_total > max_dir_lights
+                || spot_light_total > max_spot_lights;
+            if dropped && !*warned_lights {
+                log::warn!(
+                    "The scene has {} point, {} directional and {} spot lights, only {} point, {} \
+                     directional and {} spot lights are drawn",
+                    point_light_total,
+                    dir_light_total,
+                    spot_light_total,
+                    max_point_lights,
+                    max_dir_lights,
+                    max_spot_lights,
+                );
+            }
+            *warned_lights = dropped;
+            write_into_slice(
+                &mut dst_slice[usize_range(capsule_range)],
+                capsules.tap_count(&mut env.capsule_count),
             );
             write_into_slice(&mut dst_slice[usize_range(projview_range)], Some(projview));
             write_into_slice(&mut dst_slice[usize_range(env_range)], Some(env));
+            write_into_slice(
+                &mut dst_slice[usize_range(fog_range)],
+                Some(FogGatherer::gather(world).std140()),
+            );
+            write_into_slice(
+                &mut dst_slice[usize_range(shadow_range)],
+                Some(shadow.std140()),
+            );
         }
 
         new_buffer
     }
 }
+
+/// Lights of the scene fitting in a buffer of `max` lights, keeping the ones with the lowest
+/// distance when there are too many, and the number of lights of the scene.
+fn nearest<T>(mut lights: Vec<(f32, T)>, max: usize) -> (Vec<T>, usize) {
+    let total = lights.len();
+    if total > max {
+        lights.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        lights.truncate(max);
+    }
+    (lights.into_iter().map(|(_, light)| light).collect(), total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_lights_fill_the_buffer() {
+        let lights = |count: usize| (0..count).rev().map(|i| (i as f32, i)).collect();
+
+        assert_eq!(nearest::<usize>(Vec::new(), 4), (vec![], 0));
+        assert_eq!(nearest(lights(4), 4), (vec![3, 2, 1, 0], 4));
+        assert_eq!(nearest(lights(5), 4), (vec![0, 1, 2, 3], 5));
+    }
+}
@@ -3,7 +3,10 @@
 use crate::{
     pod::ViewArgs,
     rendy::{command::RenderPassEncoder, factory::Factory},
-    submodules::{gather::CameraGatherer, uniform::DynamicUniform},
+    submodules::{
+        gather::{CameraGatherer, ShadowGatherer},
+        uniform::DynamicUniform,
+    },
     types::Backend,
 };
 use amethyst_core::ecs::World;
@@ -17,6 +20,8 @@ use thread_profiler::profile_scope;
 #[derive(Debug)]
 pub struct FlatEnvironmentSub<B: Backend> {
     uniform: DynamicUniform<B, ViewArgs>,
+    culling: bool,
+    shadow_map: Option<u32>,
 }
 
 impl<B: Backend> FlatEnvironmentSub<B> {
@@ -24,9 +29,25 @@ impl<B: Backend> FlatEnvironmentSub<B> {
     pub fn new(factory: &Factory<B>) -> Result<Self, failure::Error> {
         Ok(Self {
             uniform: DynamicUniform::new(factory, rendy::hal::pso::ShaderStageFlags::VERTEX)?,
+            culling: false,
+            shadow_map: None,
         })
     }
 
+    /// Use the camera the scene is culled with, which differs from the drawing camera while
+    /// the `FrozenCamera` is frozen.
+    pub fn for_culling(mut self) -> Self {
+        self.culling = true;
+        self
+    }
+
+    /// Use the view of the light casting shadows, for a shadow map `resolution` texels wide,
+    /// see [crate::shadow]. Without a light casting shadows, the camera is used instead.
+    pub fn for_shadow_map(mut self, resolution: u32) -> Self {
+        self.shadow_map = Some(resolution);
+        self
+    }
+
     /// Returns the raw `DescriptorSetLayout` for this environment
     pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
         self.uniform.raw_layout()
@@ -36,7 +57,16 @@ impl<B: Backend> FlatEnvironmentSub<B> {
     pub fn process(&mut self, factory: &Factory<B>, index: usize, world: &World) {
         #[cfg(feature = "profiler")]
         profile_scope!("process");
-        let projview = CameraGatherer::gather(world).projview;
+        let shadow = self
+            .shadow_map
+            .and_then(|resolution| ShadowGatherer::gather(world, resolution));
+        let projview = if let Some(shadow) = shadow {
+            shadow.projview
+        } else if self.culling {
+            CameraGatherer::gather_culling(world).projview
+        } else {
+            CameraGatherer::gather(world).projview
+        };
         self.uniform.write(factory, index, projview);
     }
 
@@ -1,12 +1,15 @@
 //! Helper gatherer structures for collecting information about the world.
 use crate::{
-    camera::{ActiveCamera, Camera},
+    camera::{ActiveCamera, Camera, FrozenCamera},
+    conventions::Conventions,
+    light::Light,
     pod::{self, IntoPod},
-    resources::AmbientColor,
+    resources::{AmbientColor, FogMode, FogSettings},
+    shadow::ShadowSettings,
 };
 use amethyst_core::{
     ecs::{Entities, Entity, Join, Read, ReadStorage, SystemData, World},
-    math::{convert, Matrix4, Vector3},
+    math::{convert, Matrix4, Point3, Vector3},
     transform::Transform,
 };
 use glsl_layout::*;
@@ -60,12 +63,42 @@ impl CameraGatherer {
         }
     }
 
+    /// Collect the inverse of the projection and view matrix of the camera entity, used to
+    /// reconstruct world positions from depth. Returns identity when there is no camera.
+    pub fn gather_inverse_proj_view(world: &World) -> Matrix4<f32> {
+        let (cameras, transforms) =
+            <(ReadStorage<'_, Camera>, ReadStorage<'_, Transform>)>::fetch(world);
+
+        Self::gather_camera_entity(world)
+            .and_then(|entity| {
+                let view = transforms.get(entity)?.global_view_matrix();
+                convert::<_, Matrix4<f32>>(cameras.get(entity)?.matrix * view).try_inverse()
+            })
+            .unwrap_or_else(Matrix4::identity)
+    }
+
+    /// Collect the projection matrix of the camera entity, without its view. Returns identity
+    /// when there is no camera.
+    pub fn gather_proj(world: &World) -> Matrix4<f32> {
+        let cameras = <ReadStorage<'_, Camera>>::fetch(world);
+
+        Self::gather_camera_entity(world)
+            .and_then(|entity| cameras.get(entity))
+            .map_or_else(Matrix4::identity, |camera| camera.matrix)
+    }
+
     /// Collect `ActiveCamera` and `Camera` instances from the provided resource storage and selects
     /// the appropriate camera to use for projection, and returns the camera position and extracted
     /// projection matrix.
     ///
     /// The matrix returned is the camera's `Projection` matrix and the camera `Transform::global_view_matrix`
     pub fn gather(world: &World) -> Self {
+        Self::gather_for(world, None)
+    }
+
+    /// Like `gather`, but use the given camera entity instead of the `ActiveCamera` if it is
+    /// a valid camera, e.g. to render a `View`.
+    pub fn gather_for(world: &World, camera: Option<Entity>) -> Self {
         #[cfg(feature = "profiler")]
         profile_scope!("gather_cameras");
 
@@ -78,8 +111,9 @@ impl CameraGatherer {
         let defcam = Camera::standard_2d(1.0, 1.0);
         let identity = Transform::default();
 
-        let (camera, transform) = active_camera
-            .entity
+        let (camera, transform) = camera
+            .filter(|entity| cameras.contains(*entity))
+            .or(active_camera.entity)
             .as_ref()
             .and_then(|ac| {
                 cameras
@@ -93,6 +127,20 @@ impl CameraGatherer {
                     .unwrap_or((&defcam, &identity))
             });
 
+        Self::from_camera(camera, transform)
+    }
+
+    /// Like `gather`, but use the snapshot of the `FrozenCamera` while it's frozen, for passes
+    /// drawing what culling sees.
+    pub fn gather_culling(world: &World) -> Self {
+        let frozen = <Option<Read<'_, FrozenCamera>>>::fetch(world);
+        match frozen.as_ref().and_then(|f| f.snapshot()) {
+            Some((camera, transform)) => Self::from_camera(camera, transform),
+            None => Self::gather(world),
+        }
+    }
+
+    fn from_camera(camera: &Camera, transform: &Transform) -> Self {
         let camera_position =
             convert::<_, Vector3<f32>>(transform.global_matrix().column(3).xyz()).into_pod();
 
@@ -130,3 +178,103 @@ impl AmbientGatherer {
         })
     }
 }
+
+/// Helper `FogGatherer` for fetching the scene fog.
+#[derive(Debug)]
+pub struct FogGatherer;
+impl FogGatherer {
+    /// If a `FogSettings` exists in the world, return it - otherwise return disabled fog.
+    pub fn gather(world: &World) -> pod::Fog {
+        let settings = <Option<Read<'_, FogSettings>>>::fetch(world);
+        let disabled = FogSettings {
+            mode: FogMode::Disabled,
+            ..Default::default()
+        };
+        let settings = settings.as_deref().unwrap_or(&disabled);
+
+        let direction = settings
+            .inscattering_direction
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| -Vector3::y());
+        pod::Fog {
+            color: settings.color.into_pod(),
+            density: settings.density.max(0.0),
+            inscattering_color: settings.inscattering_color.into_pod(),
+            inscattering_exponent: settings.inscattering_exponent.max(0.0),
+            inscattering_direction: direction.into_pod(),
+            base_height: settings.base_height,
+            height_falloff: settings.height_falloff.max(0.0),
+            max_opacity: settings.max_opacity.clamp(0.0, 1.0),
+            mode: match settings.mode {
+                FogMode::Disabled => 0,
+                FogMode::Forward => 1,
+                FogMode::PostPass => 2,
+            },
+        }
+    }
+}
+
+/// Helper `ShadowGatherer` for fetching the light casting shadows and its shadow map.
+#[derive(Debug)]
+pub struct ShadowGatherer {
+    /// Entity of the directional light casting shadows.
+    pub light: Entity,
+    /// Projection and view of the shadow map.
+    pub projview: Std140<pod::ViewArgs>,
+    /// Premultiplied Proj-View matrix of the shadow map.
+    pub proj_view: Matrix4<f32>,
+}
+
+impl ShadowGatherer {
+    /// If a `ShadowSettings` exists in the world and a directional light casts shadows,
+    /// return the light and its shadow map `resolution` texels wide, see [crate::shadow].
+    pub fn gather(world: &World, resolution: u32) -> Option<Self> {
+        #[cfg(feature = "profiler")]
+        profile_scope!("gather_shadow");
+
+        let settings = <Option<Read<'_, ShadowSettings>>>::fetch(world)?;
+        let (entities, lights, transforms) = <(
+            Entities<'_>,
+            ReadStorage<'_, Light>,
+            ReadStorage<'_, Transform>,
+        )>::fetch(world);
+
+        let directional = |light: &Light| match light {
+            Light::Directional(light) => Some(light.direction),
+            _ => None,
+        };
+        let (light, direction) = settings
+            .light
+            .and_then(|entity| Some((entity, directional(lights.get(entity)?)?)))
+            .or_else(|| {
+                (&entities, &lights)
+                    .join()
+                    .find_map(|(entity, light)| Some((entity, directional(light)?)))
+            })?;
+
+        let center = CameraGatherer::gather_camera_entity(world)
+            .and_then(|camera| transforms.get(camera))
+            .map_or_else(Point3::origin, |transform| {
+                Point3::from(convert::<_, Vector3<f32>>(
+                    transform.global_matrix().column(3).xyz(),
+                ))
+            });
+        let (proj, view) =
+            settings.light_matrices_in(&Conventions::of(world), &direction, &center, resolution)?;
+
+        let proj_view = proj * view;
+        let (proj, view, matrix): ([[f32; 4]; 4], [[f32; 4]; 4], [[f32; 4]; 4]) =
+            (proj.into(), view.into(), proj_view.into());
+        let projview = pod::ViewArgs {
+            proj: proj.into(),
+            view: view.into(),
+            proj_view: matrix.into(),
+        }
+        .std140();
+        Some(Self {
+            light,
+            projview,
+            proj_view,
+        })
+    }
+}
@@ -0,0 +1,138 @@
+//! Graph image submodule for sampling images produced by other render graph nodes.
+use crate::{
+    rendy::{
+        command::RenderPassEncoder,
+        factory::Factory,
+        graph::{GraphContext, ImageAccess, NodeImage},
+        hal::{self, device::Device, format::Aspects, image::Filter, pso},
+        resource::{
+            DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle, ImageView,
+            ImageViewInfo, Sampler,
+        },
+    },
+    types::Backend,
+    util,
+};
+
+/// Image access for sampling a graph image in given shader stages.
+///
+/// Use with `RenderGroupDesc::images` for every image bound with `GraphImageSub`.
+pub fn sampled_image_access(stages: pso::PipelineStage) -> ImageAccess {
+    ImageAccess {
+        access: hal::image::Access::SHADER_READ,
+        usage: hal::image::Usage::SAMPLED,
+        layout: hal::image::Layout::ShaderReadOnlyOptimal,
+        stages,
+    }
+}
+
+/// Submodule binding images of other render graph nodes to a single descriptor set,
+/// one combined image sampler binding per image, in the order they were passed in.
+///
+/// Depth images are bound with their depth aspect, so the depth value can be read
+/// from the red channel.
+#[derive(Debug)]
+pub struct GraphImageSub<B: Backend> {
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    set: Escape<DescriptorSet<B>>,
+    views: Vec<Escape<ImageView<B>>>,
+    sampler: RendyHandle<Sampler<B>>,
+}
+
+impl<B: Backend> GraphImageSub<B> {
+    /// Create the descriptor set for the images passed to `RenderGroupDesc::build`.
+    pub fn new(
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        images: &[NodeImage],
+        filter: Filter,
+        stages: pso::ShaderStageFlags,
+    ) -> Result<Self, failure::Error> {
+        let layout: RendyHandle<DescriptorSetLayout<B>> = factory
+            .create_descriptor_set_layout(util::set_layout_bindings(Some((
+                images.len() as u32,
+                pso::DescriptorType::CombinedImageSampler,
+                stages,
+            ))))?
+            .into();
+        let sampler = factory.get_sampler(hal::image::SamplerInfo::new(
+            filter,
+            hal::image::WrapMode::Clamp,
+        ))?;
+
+        let views = images
+            .iter()
+            .map(|node_image| {
+                let image = ctx.get_image(node_image.id).ok_or_else(|| {
+                    failure::format_err!("Graph image {:?} not found", node_image.id)
+                })?;
+                let format = image.format();
+                let aspects = if format.is_depth() {
+                    Aspects::DEPTH
+                } else {
+                    Aspects::COLOR
+                };
+                factory.create_image_view(
+                    image.clone(),
+                    ImageViewInfo {
+                        view_kind: hal::image::ViewKind::D2,
+                        format,
+                        swizzle: hal::format::Swizzle::NO,
+                        range: hal::image::SubresourceRange {
+                            aspects,
+                            levels: 0..1,
+                            layers: 0..1,
+                        },
+                    },
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let set = factory.create_descriptor_set(layout.clone())?;
+        unsafe {
+            factory.write_descriptor_sets(views.iter().zip(images).enumerate().map(
+                |(binding, (view, node_image))| {
+                    util::desc_write(
+                        set.raw(),
+                        binding as u32,
+                        pso::Descriptor::CombinedImageSampler(
+                            view.raw(),
+                            node_image.layout,
+                            sampler.raw(),
+                        ),
+                    )
+                },
+            ));
+        }
+
+        Ok(Self {
+            layout,
+            set,
+            views,
+            sampler,
+        })
+    }
+
+    /// Returns the raw `DescriptorSetLayout` for the images
+    pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
+        self.layout.raw()
+    }
+
+    /// Bind the images descriptor set.
+    #[inline]
+    pub fn bind(
+        &self,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                pipeline_layout,
+                set_id,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+    }
+}
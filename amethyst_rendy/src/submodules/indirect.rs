@@ -0,0 +1,218 @@
+//! Per-image buffers of indirect draw commands.
+use crate::{
+    rendy::{
+        command::{DrawIndexedCommand, RenderPassEncoder},
+        factory::Factory,
+        hal::{self, PhysicalDevice},
+        memory::Write as _,
+        resource::{Buffer, Escape},
+    },
+    types::Backend,
+    util,
+};
+use std::ops::Range;
+
+/// Provides per-image abstraction for indexed draws reading their parameters from a buffer.
+///
+/// Commands are pushed on the CPU every frame in drawing order, and `draw` records a single
+/// draw of a range of consecutive commands, see `DrawRuns`. The buffer is also a storage buffer, so a compute pass culling
+/// on the GPU can later rewrite the `instance_count` of the commands before they're drawn.
+#[derive(Debug)]
+pub struct IndirectDrawSub<B: Backend> {
+    commands: Vec<DrawIndexedCommand>,
+    per_image: Vec<Option<Escape<Buffer<B>>>>,
+}
+
+impl<B: Backend> IndirectDrawSub<B> {
+    /// Create a new `IndirectDrawSub`, or `None` if the device can't draw indirectly with a
+    /// first instance, which instanced batches need.
+    pub fn new(factory: &Factory<B>) -> Option<Self> {
+        if !factory
+            .physical()
+            .features()
+            .contains(hal::Features::DRAW_INDIRECT_FIRST_INSTANCE)
+        {
+            return None;
+        }
+        Some(Self {
+            commands: Vec::new(),
+            per_image: Vec::new(),
+        })
+    }
+
+    /// Push the command of an indexed draw. Returns its index.
+    pub fn push(&mut self, indices: Range<u32>, instances: Range<u32>) -> u32 {
+        self.commands.push(DrawIndexedCommand {
+            index_count: indices.end - indices.start,
+            instance_count: instances.end - instances.start,
+            first_index: indices.start,
+            vertex_offset: 0,
+            first_instance: instances.start,
+        });
+        self.commands.len() as u32 - 1
+    }
+
+    /// Commands pushed since the last commit.
+    pub fn commands(&self) -> &[DrawIndexedCommand] {
+        &self.commands
+    }
+
+    /// Buffer of the commands of an image, once committed.
+    pub fn buffer(&self, index: usize) -> Option<&B::Buffer> {
+        self.per_image
+            .get(index)
+            .and_then(|b| b.as_ref())
+            .map(|b| b.raw())
+    }
+
+    /// Allocates and writes the pushed commands to GPU memory, and clears them.
+    pub fn commit(&mut self, factory: &Factory<B>, index: usize) {
+        while self.per_image.len() <= index {
+            self.per_image.push(None);
+        }
+        if !self.commands.is_empty() {
+            let data = util::slice_as_bytes(&self.commands);
+            let buffer = &mut self.per_image[index];
+            util::ensure_buffer(
+                factory,
+                buffer,
+                hal::buffer::Usage::INDIRECT | hal::buffer::Usage::STORAGE,
+                rendy::memory::Dynamic,
+                data.len() as u64,
+            )
+            .unwrap();
+            if let Some(buffer) = buffer.as_mut() {
+                let mut mapped = buffer.map(factory.device(), 0..data.len() as u64).unwrap();
+                let mut writer = unsafe {
+                    mapped
+                        .write(factory.device(), 0..data.len() as u64)
+                        .unwrap()
+                };
+                let dst_slice = unsafe { writer.slice() };
+                dst_slice.copy_from_slice(data);
+            }
+        }
+        self.commands.clear();
+    }
+
+    /// Draw a range of commands with one call, with the index and vertex buffers of their mesh
+    /// bound.
+    #[inline]
+    pub fn draw(&self, index: usize, commands: Range<u32>, encoder: &mut RenderPassEncoder<'_, B>) {
+        if let Some(buffer) = self.buffer(index) {
+            unsafe {
+                encoder.draw_indexed_indirect(
+                    buffer,
+                    u64::from(commands.start) * std::mem::size_of::<DrawIndexedCommand>() as u64,
+                    commands.end - commands.start,
+                    std::mem::size_of::<DrawIndexedCommand>() as u32,
+                );
+            }
+        }
+    }
+}
+
+/// Draw call recording a part of a list of draws.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawRun {
+    /// The draw at given index, recorded directly.
+    Direct(u32),
+    /// A range of consecutive draws, recorded by a single indirect call.
+    Indirect(Range<u32>),
+}
+
+/// Groups consecutive draws sharing the same pipeline and bindings into runs drawn by a single
+/// indirect call.
+///
+/// Draws are pushed in drawing order with the key of their state, or `None` when they must be
+/// drawn directly, like draws of meshes without an index buffer.
+#[derive(Debug)]
+pub struct DrawRuns<K> {
+    runs: Vec<DrawRun>,
+    last: Option<K>,
+    len: u32,
+}
+
+impl<K> Default for DrawRuns<K> {
+    fn default() -> Self {
+        Self {
+            runs: Vec::new(),
+            last: None,
+            len: 0,
+        }
+    }
+}
+
+impl<K: PartialEq> DrawRuns<K> {
+    /// Forget all pushed draws.
+    pub fn clear(&mut self) {
+        self.runs.clear();
+        self.last = None;
+        self.len = 0;
+    }
+
+    /// Push the next draw. Returns its index.
+    pub fn push(&mut self, key: Option<K>) -> u32 {
+        let draw = self.len;
+        self.len += 1;
+        match key {
+            Some(key) => {
+                match self.runs.last_mut() {
+                    Some(DrawRun::Indirect(run)) if self.last.as_ref() == Some(&key) => {
+                        run.end = draw + 1;
+                    }
+                    _ => self.runs.push(DrawRun::Indirect(draw..draw + 1)),
+                }
+                self.last = Some(key);
+            }
+            None => {
+                self.runs.push(DrawRun::Direct(draw));
+                self.last = None;
+            }
+        }
+        draw
+    }
+
+    /// Draw calls of the pushed draws, in drawing order.
+    pub fn runs(&self) -> &[DrawRun] {
+        &self.runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consecutive_draws_with_same_key_share_a_call() {
+        let mut runs = DrawRuns::default();
+        for key in &[
+            Some(1),
+            Some(1),
+            Some(1),
+            None,
+            Some(1),
+            Some(2),
+            Some(2),
+            None,
+            None,
+        ] {
+            runs.push(*key);
+        }
+        assert_eq!(
+            runs.runs(),
+            &[
+                DrawRun::Indirect(0..3),
+                DrawRun::Direct(3),
+                DrawRun::Indirect(4..5),
+                DrawRun::Indirect(5..7),
+                DrawRun::Direct(7),
+                DrawRun::Direct(8),
+            ]
+        );
+
+        runs.clear();
+        runs.push(Some(2));
+        assert_eq!(runs.runs(), &[DrawRun::Indirect(0..1)]);
+    }
+}
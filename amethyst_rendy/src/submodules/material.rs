@@ -1,6 +1,6 @@
 //! Material abstraction submodule.
 use crate::{
-    mtl::{Material, StaticTextureSet},
+    mtl::{Material, MaterialChanges, MaterialPlaceholders, StaticTextureSet},
     pod,
     rendy::{
         command::RenderPassEncoder,
@@ -11,11 +11,13 @@ use crate::{
             Buffer, BufferInfo, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle,
         },
     },
+    streaming::TextureStreams,
     types::{Backend, Texture},
     util,
 };
 use amethyst_assets::{AssetStorage, Handle, WeakHandle};
 use amethyst_core::ecs::{Read, SystemData, World};
+use fnv::FnvHashSet;
 use glsl_layout::*;
 
 #[cfg(feature = "profiler")]
@@ -130,9 +132,67 @@ enum MaterialState<B: Backend> {
         slot: usize,
         generation: u32,
         handle: WeakHandle<Material>,
+        version: u64,
+        discards: bool,
+        streams: u64,
+        resident: u32,
     },
 }
 
+/// Whether a material can be drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaterialStatus {
+    Ready,
+    Loading,
+    Missing,
+}
+
+/// Material is missing if it or any of its textures failed to load or was unloaded, and loading
+/// if it or any of its textures aren't loaded yet.
+fn material_status<B: Backend, T: for<'a> StaticTextureSet<'a>>(
+    mat_storage: &AssetStorage<Material>,
+    tex_storage: &AssetStorage<Texture>,
+    handle: &Handle<Material>,
+) -> MaterialStatus {
+    let mat = match mat_storage.get(handle) {
+        Some(mat) => mat,
+        None if mat_storage.is_failed(handle) => return MaterialStatus::Missing,
+        None => return MaterialStatus::Loading,
+    };
+    let mut status = MaterialStatus::Ready;
+    for tex in T::textures(mat) {
+        match tex_storage.get(tex) {
+            Some(tex) if B::unwrap_texture(tex).is_some() => {}
+            Some(_) => return MaterialStatus::Missing,
+            None if tex_storage.is_failed(tex) => return MaterialStatus::Missing,
+            None => status = MaterialStatus::Loading,
+        }
+    }
+    status
+}
+
+fn changes_version(world: &World, handle: &Handle<Material>) -> u64 {
+    world
+        .try_fetch::<MaterialChanges>()
+        .map_or(0, |changes| changes.version(handle))
+}
+
+fn streams_generation(world: &World) -> u64 {
+    world
+        .try_fetch::<TextureStreams>()
+        .map_or(0, |streams| streams.generation())
+}
+
+/// Sum of the resident levels of the streamed textures of a material. It only decreases while
+/// they stream, so it changes whenever one of their samplers must be clamped differently.
+fn resident_levels<T: for<'a> StaticTextureSet<'a>>(world: &World, mat: &Material) -> u32 {
+    world.try_fetch::<TextureStreams>().map_or(0, |streams| {
+        T::textures(mat)
+            .map(|tex| u32::from(streams.resident_level(tex)))
+            .sum()
+    })
+}
+
 /// Material ID newtype, preventing users from creating arbitrary `MaterialId`. Represented as a `u32`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MaterialId(u32);
@@ -146,6 +206,7 @@ pub struct MaterialSub<B: Backend, T: for<'a> StaticTextureSet<'a>> {
     allocator: SlotAllocator,
     buffers: Vec<SlottedBuffer<B>>,
     materials: Vec<MaterialState<B>>,
+    reported: FnvHashSet<u32>,
     marker: std::marker::PhantomData<T>,
 }
 
@@ -155,13 +216,14 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
         Ok(Self {
             layout: set_layout! {
                 factory,
-                [1] UniformBuffer hal::pso::ShaderStageFlags::FRAGMENT,
+                [1] UniformBuffer hal::pso::ShaderStageFlags::VERTEX | hal::pso::ShaderStageFlags::FRAGMENT,
                 [T::len()] CombinedImageSampler hal::pso::ShaderStageFlags::FRAGMENT
             },
             lookup: util::LookupBuilder::new(),
             allocator: SlotAllocator::new(1024),
             buffers: vec![Self::create_buffer(factory)?],
             materials: Vec::with_capacity(1024),
+            reported: FnvHashSet::default(),
             generation: 0,
             marker: std::marker::PhantomData,
         })
@@ -183,7 +245,7 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
 
     /// Increment the internal generation counter.
     pub fn maintain(&mut self) {
-        self.generation += self.generation.wrapping_add(1);
+        self.generation = self.generation.wrapping_add(1);
     }
 
     /// Releases any materials not used in the current generation.
@@ -213,7 +275,7 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
         #[cfg(feature = "profiler")]
         profile_scope!("try_insert");
 
-        use util::{desc_write, slice_as_bytes, texture_desc};
+        use util::{desc_write, slice_as_bytes, texture_desc, texture_desc_with_sampler};
         let (mat_storage, tex_storage) = <(
             Read<'_, AssetStorage<Material>>,
             Read<'_, AssetStorage<Texture>>,
@@ -230,6 +292,21 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
             return None;
         }
 
+        // Streamed textures are sampled only from their levels already uploaded.
+        let samplers = {
+            let streams = world.try_fetch::<TextureStreams>();
+            T::textures(mat)
+                .map(|t| {
+                    streams
+                        .as_ref()
+                        .and_then(|streams| streams.clamped_sampler(t))
+                        .map(|info| factory.get_sampler(info))
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .ok()?
+        };
+
         let pod = pod::Material::from_material(&mat).std140();
 
         if self.allocator.would_overflow() {
@@ -250,17 +327,18 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
         unsafe {
             let set = set.raw();
 
-            let tex_descs = T::textures(mat).enumerate().map(|(i, t)| {
-                desc_write(
-                    set,
-                    (i + 1) as u32,
-                    texture_desc(
-                        tex_storage.get(t).unwrap(),
-                        hal::image::Layout::ShaderReadOnlyOptimal,
-                    )
-                    .unwrap(),
-                )
-            });
+            let tex_descs = T::textures(mat)
+                .zip(&samplers)
+                .enumerate()
+                .map(|(i, (t, sampler))| {
+                    let texture = tex_storage.get(t).unwrap();
+                    let layout = hal::image::Layout::ShaderReadOnlyOptimal;
+                    let desc = match sampler {
+                        Some(sampler) => texture_desc_with_sampler(texture, layout, sampler.raw()),
+                        None => texture_desc(texture, layout),
+                    };
+                    desc_write(set, (i + 1) as u32, desc.unwrap())
+                });
 
             let desc_iter = std::iter::once(desc_write(set, 0, buf_desc)).chain(tex_descs);
             factory.write_descriptor_sets(desc_iter);
@@ -270,10 +348,20 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
             slot,
             generation: self.generation,
             handle: handle.downgrade(),
+            version: changes_version(world, handle),
+            discards: mat.discards(),
+            streams: streams_generation(world),
+            resident: resident_levels::<T>(world, mat),
         })
     }
 
     /// Inserts a new material to this collection.
+    ///
+    /// Materials that can't be drawn are substituted with the `MaterialPlaceholders`, if
+    /// present. Broken materials are reported once.
+    ///
+    /// Returns the id of the material, and whether draws recorded with it must be recorded
+    /// again, as it was just inserted or whether it discards fragments changed.
     pub fn insert(
         &mut self,
         factory: &Factory<B>,
@@ -283,21 +371,126 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
         #[cfg(feature = "profiler")]
         profile_scope!("insert");
 
+        // Materials already inserted this frame with the same streamed levels are still ready,
+        // skip walking their textures for every other draw using them.
+        if let Some(id) = self.lookup.get(&handle.id()) {
+            if let Some(MaterialState::Loaded {
+                generation,
+                handle,
+                streams,
+                ..
+            }) = self.materials.get(id)
+            {
+                if *generation == self.generation
+                    && !handle.is_dead()
+                    && *streams == streams_generation(world)
+                {
+                    return Some((MaterialId(id as u32), false));
+                }
+            }
+        }
+
+        let (mat_storage, tex_storage) = <(
+            Read<'_, AssetStorage<Material>>,
+            Read<'_, AssetStorage<Texture>>,
+        )>::fetch(world);
+        let status = material_status::<B, T>(&mat_storage, &tex_storage, handle);
+        if status == MaterialStatus::Ready {
+            drop((mat_storage, tex_storage));
+            return self.insert_ready(factory, world, handle);
+        }
+
+        // Textures of a material drawn before may be gone by now.
+        self.release(handle);
+        let placeholders = world.try_fetch::<MaterialPlaceholders>()?;
+        let placeholder = if status == MaterialStatus::Missing {
+            if self.reported.insert(handle.id()) {
+                log::warn!(
+                    "Material {} or one of its textures failed to load or was unloaded, \
+                     drawing it with the missing material placeholder",
+                    handle.id()
+                );
+            }
+            placeholders.missing.clone()
+        } else {
+            placeholders.loading.clone()
+        };
+        let status = material_status::<B, T>(&mat_storage, &tex_storage, &placeholder);
+        drop((mat_storage, tex_storage, placeholders));
+        if status == MaterialStatus::Ready {
+            self.insert_ready(factory, world, &placeholder)
+        } else {
+            None
+        }
+    }
+
+    /// Releases the material of given handle, if it was inserted.
+    fn release(&mut self, handle: &Handle<Material>) {
+        let id = match self.lookup.get(&handle.id()) {
+            Some(id) => id,
+            None => return,
+        };
+        if let Some(MaterialState::Loaded { slot, .. }) = self.materials.get(id) {
+            self.allocator.release(*slot);
+            self.materials[id] = MaterialState::Unloaded {
+                generation: self.generation.wrapping_sub(1),
+            };
+        }
+    }
+
+    /// Inserts a material with all textures loaded.
+    fn insert_ready(
+        &mut self,
+        factory: &Factory<B>,
+        world: &World,
+        handle: &Handle<Material>,
+    ) -> Option<(MaterialId, bool)> {
         let id = self.lookup.forward(handle.id());
+        let strong = handle;
         match self.materials.get_mut(id) {
             Some(MaterialState::Loaded {
                 slot,
                 generation,
                 handle,
+                version,
+                discards,
+                streams,
+                resident,
                 ..
             }) => {
+                // Rebind the textures when the levels of a streamed one sampled may change.
+                let streamed = streams_generation(world);
+                let restreamed = !handle.is_dead() && *streams != streamed && {
+                    *streams = streamed;
+                    let mat_storage = <Read<'_, AssetStorage<Material>>>::fetch(world);
+                    mat_storage
+                        .get(strong)
+                        .is_some_and(|mat| resident_levels::<T>(world, mat) != *resident)
+                };
                 // If handle is dead, new material was loaded (handle id reused)
-                if handle.is_dead() {
+                if handle.is_dead() || restreamed {
                     self.allocator.release(*slot);
                 } else {
                     // Material loaded and ready
                     *generation = self.generation;
-                    return Some((MaterialId(id as u32), false));
+                    let changed = changes_version(world, strong);
+                    let mut rerecord = false;
+                    if *version != changed {
+                        // Only the parameters changed, rewrite the region of the material.
+                        let mat_storage = <Read<'_, AssetStorage<Material>>>::fetch(world);
+                        if let Some(mat) = mat_storage.get(strong) {
+                            let pod = pod::Material::from_material(mat).std140();
+                            self.buffers[*slot / 1024].write(
+                                factory,
+                                *slot % 1024,
+                                util::slice_as_bytes(&[pod]),
+                            );
+                            rerecord = *discards != mat.discards();
+                            *discards = mat.discards();
+                        }
+                        *version = changed;
+                    }
+                    return Some((MaterialId(id as u32), rerecord));
                 }
             }
             Some(MaterialState::Unloaded { generation }) if *generation == self.generation => {
@@ -341,6 +534,16 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
         }
     }
 
+    /// Returns `true` if the loaded material of given `MaterialId` discards fragments, see
+    /// `Material::discards`.
+    #[inline]
+    pub fn discards(&self, material_id: MaterialId) -> bool {
+        match &self.materials[material_id.0 as usize] {
+            MaterialState::Loaded { discards, .. } => *discards,
+            _ => true,
+        }
+    }
+
     /// Binds all material descriptor sets and textures contained in this collection.
     #[inline]
     pub fn bind(
@@ -363,3 +566,147 @@ impl<B: Backend, T: for<'a> StaticTextureSet<'a>> MaterialSub<B, T> {
         };
     }
 }
+
+#[cfg(all(test, feature = "empty"))]
+mod tests {
+    use super::*;
+    use crate::{
+        material_shader::MAX_MATERIAL_SHADER_PARAMS, mtl::FullTextureSet,
+        rendy::factory::ImageState, texture::uv_gradient_data, types::DefaultBackend,
+    };
+    use amethyst_assets::Loader;
+    use amethyst_core::ecs::WorldExt;
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
+
+    fn material(tex: Handle<Texture>) -> Material {
+        Material {
+            alpha_cutoff: 0.01,
+            albedo: tex.clone(),
+            emission: tex.clone(),
+            normal: tex.clone(),
+            metallic_roughness: tex.clone(),
+            ambient_occlusion: tex.clone(),
+            cavity: tex.clone(),
+            uv_offset: Default::default(),
+            emission_factor: [1.0; 3],
+            uv_transform: Default::default(),
+            normal_uv_transform: Default::default(),
+            dissolve_noise: tex.clone(),
+            dissolve_amount: 0.0,
+            dissolve_edge_width: 0.0,
+            dissolve_edge_color: [0.0; 3],
+            triplanar_scale: 0.0,
+            triplanar_sharpness: 1.0,
+            detail: tex.clone(),
+            detail_scale: 1.0,
+            detail_blend: Default::default(),
+            detail_fade_distance: 0.0,
+            layer_mask: tex,
+            layer_color: [1.0; 3],
+            layer_roughness: 0.0,
+            layer_amount: 0.0,
+            layer_scene_factor: 0.0,
+            light_channels: !0,
+            subsurface: 0.0,
+            specular: [0.0; 3],
+            shininess: 1.0,
+            shader_params: [[0.0; 4]; MAX_MATERIAL_SHADER_PARAMS],
+        }
+    }
+
+    #[test]
+    fn unloaded_material_is_missing() {
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let tex_storage = AssetStorage::<Texture>::new();
+        let mut mat_storage = AssetStorage::<Material>::new();
+        let status = |mat_storage: &AssetStorage<Material>, handle| {
+            material_status::<rendy::empty::Backend, FullTextureSet>(
+                mat_storage,
+                &tex_storage,
+                handle,
+            )
+        };
+
+        let tex = loader.load_from_data(uv_gradient_data(4), (), &tex_storage);
+        let mat = material(tex);
+
+        let queued = loader.load_from_data(mat.clone(), (), &mat_storage);
+        assert_eq!(status(&mat_storage, &queued), MaterialStatus::Loading);
+
+        let handle = mat_storage.insert(mat);
+        assert_eq!(status(&mat_storage, &handle), MaterialStatus::Loading);
+
+        assert!(mat_storage.unload(&handle).is_some());
+        assert!(mat_storage.is_failed(&handle));
+        assert_eq!(status(&mat_storage, &handle), MaterialStatus::Missing);
+        assert!(!mat_storage.is_failed(&queued));
+    }
+
+    #[test]
+    #[ignore] // CI can't run tests requiring actual backend
+    fn unloading_a_texture_draws_the_missing_placeholder() {
+        let config: rendy::factory::Config = Default::default();
+        let (mut factory, families): (Factory<DefaultBackend>, _) =
+            rendy::factory::init(config).unwrap();
+        let next_state = ImageState {
+            queue: families.family_by_index(0).queue(0).id(),
+            stage: hal::pso::PipelineStage::FRAGMENT_SHADER,
+            access: hal::image::Access::SHADER_READ,
+            layout: hal::image::Layout::ShaderReadOnlyOptimal,
+        };
+
+        let mut tex_storage = AssetStorage::<Texture>::new();
+        let mut texture = || {
+            let texture = uv_gradient_data(4)
+                .0
+                .build(next_state, &mut factory)
+                .unwrap();
+            tex_storage.insert(DefaultBackend::wrap_texture(texture))
+        };
+        let (shared, albedo) = (texture(), texture());
+        let mut mat_storage = AssetStorage::<Material>::new();
+        let handle = mat_storage.insert(Material {
+            albedo: albedo.clone(),
+            ..material(shared.clone())
+        });
+        let missing = mat_storage.insert(material(shared.clone()));
+        let loading = mat_storage.insert(material(shared));
+
+        let mut world = World::new();
+        world.insert(tex_storage);
+        world.insert(mat_storage);
+        world.insert(MaterialPlaceholders {
+            missing: missing.clone(),
+            loading,
+        });
+        let mut materials = MaterialSub::<DefaultBackend, FullTextureSet>::new(&factory).unwrap();
+        materials.maintain();
+        let (loaded, _) = materials.insert(&factory, &world, &handle).unwrap();
+
+        assert!(world
+            .write_resource::<AssetStorage<Texture>>()
+            .unload(&albedo)
+            .is_some());
+        assert_eq!(
+            material_status::<DefaultBackend, FullTextureSet>(
+                &world.read_resource(),
+                &world.read_resource(),
+                &handle,
+            ),
+            MaterialStatus::Missing
+        );
+
+        // From the next frame the placeholder is drawn instead, reported once however many
+        // draws use the material.
+        materials.maintain();
+        let (drawn, _) = materials.insert(&factory, &world, &handle).unwrap();
+        let (again, _) = materials.insert(&factory, &world, &handle).unwrap();
+        let (placeholder, _) = materials.insert(&factory, &world, &missing).unwrap();
+        assert_ne!(drawn, loaded);
+        assert_eq!(drawn, placeholder);
+        assert_eq!(again, placeholder);
+        assert_eq!(materials.reported.len(), 1);
+        assert!(materials.reported.contains(&handle.id()));
+    }
+}
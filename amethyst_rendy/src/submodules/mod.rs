@@ -1,7 +1,10 @@
 //! Various helpers and implementations for sub functions of render passes.
 mod environment;
 mod flat_environment;
+mod graph_image;
+mod indirect;
 mod material;
+mod morph;
 mod skinning;
 mod texture;
 mod uniform;
@@ -11,7 +14,10 @@ pub mod gather;
 
 pub use environment::*;
 pub use flat_environment::*;
+pub use graph_image::*;
+pub use indirect::*;
 pub use material::*;
+pub use morph::*;
 pub use skinning::*;
 pub use texture::*;
 pub use uniform::*;
@@ -0,0 +1,228 @@
+//! 3D morph target per-image buffer handling.
+use crate::{
+    morph::{BlendShapes, MorphDelta, MorphTargets},
+    rendy::{
+        command::RenderPassEncoder,
+        factory::Factory,
+        hal::{self, device::Device, pso::Descriptor},
+        memory::Write as _,
+        resource::{Buffer, DescriptorSet, DescriptorSetLayout, Escape, Handle as RendyHandle},
+    },
+    types::Backend,
+    util,
+};
+use fnv::FnvHashMap;
+use std::sync::Arc;
+
+#[cfg(feature = "profiler")]
+use thread_profiler::profile_scope;
+
+/// Provides per-image abstraction for submitting the morph targets and weights of blend shapes.
+///
+/// The deltas of all `MorphTargets` drawn in a frame are packed into one storage buffer, which
+/// is only uploaded again when the drawn targets change. The weights of every entity are
+/// packed into a second buffer every frame. `insert` returns the `MorphOffsets` of an entity,
+/// passed to the vertex shader per instance.
+#[derive(Debug)]
+pub struct MorphSub<B: Backend> {
+    layout: RendyHandle<DescriptorSetLayout<B>>,
+    targets: Vec<Arc<MorphTargets>>,
+    target_offsets: FnvHashMap<usize, u32>,
+    next_delta: u32,
+    weights: Vec<f32>,
+    uploaded: Vec<Arc<MorphTargets>>,
+    deltas: Vec<MorphDelta>,
+    version: u64,
+    per_image: Vec<PerImageMorphSub<B>>,
+}
+
+#[derive(Debug)]
+struct PerImageMorphSub<B: Backend> {
+    deltas: Option<Escape<Buffer<B>>>,
+    weights: Option<Escape<Buffer<B>>>,
+    version: u64,
+    set: Escape<DescriptorSet<B>>,
+}
+
+impl<B: Backend> MorphSub<B> {
+    /// Create a new `MorphSub`, allocating using the provided `Factory`
+    pub fn new(factory: &Factory<B>) -> Result<Self, failure::Error> {
+        Ok(Self {
+            layout: set_layout! {
+                factory,
+                [1] StorageBuffer hal::pso::ShaderStageFlags::VERTEX,
+                [1] StorageBuffer hal::pso::ShaderStageFlags::VERTEX
+            },
+            targets: Vec::new(),
+            target_offsets: Default::default(),
+            next_delta: 0,
+            weights: Vec::new(),
+            uploaded: Vec::new(),
+            deltas: Vec::new(),
+            version: 0,
+            per_image: Vec::new(),
+        })
+    }
+
+    /// Returns the raw `DescriptorSetLayout` of a morph target submission.
+    pub fn raw_layout(&self) -> &B::DescriptorSetLayout {
+        self.layout.raw()
+    }
+
+    /// Insert the blend shapes of an entity for submission. Returns the offsets of its weights
+    /// and deltas, and its number of targets.
+    pub fn insert(&mut self, shapes: &BlendShapes) -> [u32; 3] {
+        #[cfg(feature = "profiler")]
+        profile_scope!("insert");
+
+        let targets = &mut self.targets;
+        let next_delta = &mut self.next_delta;
+        let delta_offset = *self
+            .target_offsets
+            .entry(Arc::as_ptr(&shapes.targets) as usize)
+            .or_insert_with(|| {
+                let offset = *next_delta;
+                *next_delta += shapes.targets.deltas().len() as u32;
+                targets.push(shapes.targets.clone());
+                offset
+            });
+        let weights_offset = self.weights.len() as u32;
+        self.weights.extend(shapes.target_weights());
+        [
+            weights_offset,
+            delta_offset,
+            shapes.targets.targets() as u32,
+        ]
+    }
+
+    /// Allocates and writes the morph targets and weights to GPU memory
+    pub fn commit(&mut self, factory: &Factory<B>, index: usize) {
+        let unchanged = self.targets.len() == self.uploaded.len()
+            && self
+                .targets
+                .iter()
+                .zip(&self.uploaded)
+                .all(|(a, b)| Arc::ptr_eq(a, b));
+        if unchanged {
+            self.targets.clear();
+        } else {
+            self.deltas.clear();
+            for targets in &self.targets {
+                self.deltas.extend_from_slice(targets.deltas());
+            }
+            self.uploaded = std::mem::take(&mut self.targets);
+            self.version += 1;
+        }
+
+        let this_image = {
+            while self.per_image.len() <= index {
+                self.per_image
+                    .push(PerImageMorphSub::new(factory, &self.layout));
+            }
+            &mut self.per_image[index]
+        };
+        this_image.commit(
+            factory,
+            util::slice_as_bytes(&self.deltas),
+            self.version,
+            util::slice_as_bytes(&self.weights),
+        );
+
+        self.weights.clear();
+        self.target_offsets.clear();
+        self.next_delta = 0;
+    }
+
+    /// Bind the morph targets and weights.
+    #[inline]
+    pub fn bind(
+        &self,
+        index: usize,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        self.per_image[index].bind(pipeline_layout, set_id, encoder);
+    }
+}
+
+impl<B: Backend> PerImageMorphSub<B> {
+    fn new(factory: &Factory<B>, layout: &RendyHandle<DescriptorSetLayout<B>>) -> Self {
+        Self {
+            deltas: None,
+            weights: None,
+            version: 0,
+            set: factory.create_descriptor_set(layout.clone()).unwrap(),
+        }
+    }
+
+    fn commit(&mut self, factory: &Factory<B>, deltas: &[u8], version: u64, weights: &[u8]) {
+        if weights.is_empty() {
+            return;
+        }
+        if self.version != version {
+            write_buffer(factory, &self.set, 0, &mut self.deltas, deltas);
+            self.version = version;
+        }
+        write_buffer(factory, &self.set, 1, &mut self.weights, weights);
+    }
+
+    #[inline]
+    fn bind(
+        &self,
+        pipeline_layout: &B::PipelineLayout,
+        set_id: u32,
+        encoder: &mut RenderPassEncoder<'_, B>,
+    ) {
+        unsafe {
+            encoder.bind_graphics_descriptor_sets(
+                pipeline_layout,
+                set_id,
+                Some(self.set.raw()),
+                std::iter::empty(),
+            );
+        }
+    }
+}
+
+fn write_buffer<B: Backend>(
+    factory: &Factory<B>,
+    set: &DescriptorSet<B>,
+    binding: u32,
+    buffer: &mut Option<Escape<Buffer<B>>>,
+    data: &[u8],
+) {
+    if data.is_empty() {
+        return;
+    }
+
+    let allocated = util::ensure_buffer(
+        factory,
+        buffer,
+        hal::buffer::Usage::STORAGE,
+        rendy::memory::Dynamic,
+        data.len() as u64,
+    )
+    .unwrap();
+
+    if let Some(buffer) = buffer.as_mut() {
+        if allocated {
+            unsafe {
+                factory.write_descriptor_sets(Some(util::desc_write(
+                    set.raw(),
+                    binding,
+                    Descriptor::Buffer(buffer.raw(), Some(0)..None),
+                )));
+            }
+        }
+
+        let mut mapped = buffer.map(factory.device(), 0..data.len() as u64).unwrap();
+        let mut writer = unsafe {
+            mapped
+                .write(factory.device(), 0..data.len() as u64)
+                .unwrap()
+        };
+        let dst_slice = unsafe { writer.slice() };
+        dst_slice.copy_from_slice(data);
+    }
+}
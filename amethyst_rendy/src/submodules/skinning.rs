@@ -17,6 +17,12 @@ use fnv::FnvHashMap;
 use thread_profiler::profile_scope;
 
 /// Provides per-image abstraction for submitting skinned mesh skeletal information.
+///
+/// The joint matrices of all skins drawn in a frame are packed into a single storage buffer.
+/// `insert` returns the offset of the palette of a skin in that buffer, which is passed to the
+/// vertex shader per instance in `SkinnedVertexArgs::joints_offset`. Instances with different
+/// poses are thus drawn in the same instanced draw call, and meshes sharing a skin share its
+/// palette.
 #[derive(Debug)]
 pub struct SkinningSub<B: Backend> {
     layout: RendyHandle<DescriptorSetLayout<B>>,
@@ -114,6 +114,7 @@ impl<B: Backend> IndexData<B, u32> {
 #[derive(Debug, Default)]
 pub struct DynamicVertexData<B: Backend, V: VertexDataBufferType, T: 'static> {
     per_image: Vec<PerImageDynamicVertexData<B, V>>,
+    partial_updates: bool,
     marker: PhantomData<T>,
 }
 
@@ -122,10 +123,21 @@ impl<B: Backend, V: VertexDataBufferType, T: 'static> DynamicVertexData<B, V, T>
     pub fn new() -> Self {
         Self {
             per_image: Vec::new(),
+            partial_updates: false,
             marker: PhantomData,
         }
     }
 
+    /// Only write the items which changed since the buffer of the same image was last written.
+    ///
+    /// A copy of the written data is kept per image to compare with. This pays off when most
+    /// items are the same every frame, like the instances of a mostly static scene, as writes
+    /// to mapped memory are slower than comparing.
+    pub fn with_partial_updates(mut self) -> Self {
+        self.partial_updates = true;
+        self
+    }
+
     /// Write to the allocated rendy buffer for the specified frame index.
     pub fn write<I>(
         &mut self,
@@ -150,10 +162,32 @@ impl<B: Backend, V: VertexDataBufferType, T: 'static> DynamicVertexData<B, V, T>
         };
 
         let buf_size = max_num_items * core::mem::size_of::<T>() as u64;
+        let mut written = std::mem::take(&mut this_image.written);
+        let partial_updates = self.partial_updates;
         if let Some((allocated, mut mapped)) = this_image.map(factory, 0..buf_size) {
             let mut writer = unsafe { mapped.write::<u8>(factory.device(), 0..buf_size).unwrap() };
             let mut slice = unsafe { writer.slice() };
 
+            if partial_updates {
+                let mut offset = 0;
+                for data in iter {
+                    let data_slice = util::slice_as_bytes(data.as_ref());
+                    write_changed(
+                        &mut written,
+                        slice,
+                        offset,
+                        data_slice,
+                        core::mem::size_of::<T>(),
+                        allocated,
+                    );
+                    offset += data_slice.len();
+                }
+                written.truncate(offset);
+                drop(writer);
+                this_image.written = written;
+                return allocated;
+            }
+
             iter.into_iter().for_each(|data| {
                 let data_slice = util::slice_as_bytes(data.as_ref());
                 let tmp = std::mem::replace(&mut slice, &mut []);
@@ -210,6 +244,8 @@ impl<B: Backend> DynamicVertexData<B, IndexData<B, u32>, u32> {
 #[derive(Debug)]
 struct PerImageDynamicVertexData<B: Backend, V: VertexDataBufferType> {
     buffer: Option<Escape<Buffer<B>>>,
+    /// Copy of the data last written with partial updates.
+    written: Vec<u8>,
     marker: PhantomData<V>,
 }
 
@@ -218,6 +254,7 @@ impl<B: Backend, V: VertexDataBufferType> PerImageDynamicVertexData<B, V> {
     fn new() -> Self {
         Self {
             buffer: None,
+            written: Vec::new(),
             marker: PhantomData,
         }
     }
@@ -249,3 +286,62 @@ impl<B: Backend, V: VertexDataBufferType> PerImageDynamicVertexData<B, V> {
         }
     }
 }
+
+/// Copy the items of `src` differing from the `written` copy of `dst` at `offset`, or all of
+/// them when `all` is set, updating the copy. Returns the number of bytes copied.
+fn write_changed(
+    written: &mut Vec<u8>,
+    dst: &mut [u8],
+    offset: usize,
+    src: &[u8],
+    item_size: usize,
+    all: bool,
+) -> usize {
+    let known = written.len();
+    written.resize(known.max(offset + src.len()), 0);
+    let mut copied = 0;
+    for (i, item) in src.chunks(item_size).enumerate() {
+        let range = offset + i * item_size..offset + i * item_size + item.len();
+        if all || range.end > known || written[range.clone()] != *item {
+            dst[range.clone()].copy_from_slice(item);
+            written[range].copy_from_slice(item);
+            copied += item.len();
+        }
+    }
+    copied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_updates_copy_changed_items() {
+        let mut written = Vec::new();
+        let mut dst = [0u8; 8];
+        assert_eq!(
+            write_changed(&mut written, &mut dst, 0, &[1, 2, 3, 4], 2, false),
+            4
+        );
+        assert_eq!(
+            write_changed(&mut written, &mut dst, 4, &[5, 6], 2, false),
+            2
+        );
+        assert_eq!(dst, [1, 2, 3, 4, 5, 6, 0, 0]);
+
+        // Only the second item changed.
+        dst = [0; 8];
+        assert_eq!(
+            write_changed(&mut written, &mut dst, 0, &[1, 2, 9, 9], 2, false),
+            2
+        );
+        assert_eq!(dst, [0, 0, 9, 9, 0, 0, 0, 0]);
+
+        // A reallocated buffer is written entirely.
+        assert_eq!(
+            write_changed(&mut written, &mut dst, 0, &[1, 2, 9, 9, 5, 6], 2, true),
+            6
+        );
+        assert_eq!(dst, [1, 2, 9, 9, 5, 6, 0, 0]);
+    }
+}
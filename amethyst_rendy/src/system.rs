@@ -1,20 +1,37 @@
 //! Renderer system
 use crate::{
     camera::{ActiveCamera, Camera},
+    capsule::CapsuleOccluder,
+    conventions::Conventions,
     debug_drawing::DebugLinesComponent,
+    determinism::RenderDeterminism,
+    error::RenderGraphError,
     light::Light,
-    mtl::{Material, MaterialDefaults},
-    resources::Tint,
+    material_shader::MAX_MATERIAL_SHADER_PARAMS,
+    memory::image_bytes,
+    morph::BlendShapes,
+    mtl::{Material, MaterialDefaults, MaterialPlaceholders},
+    resources::{DepthBias, DepthMode, InstanceData, Tint},
     skinning::JointTransforms,
     sprite::SpriteRender,
+    stats::RenderStats,
+    streaming::TextureStreams,
+    submesh::SubMeshes,
+    submodules::gather::CameraGatherer,
+    texture::checkerboard_data,
     transparent::Transparent,
     types::{Backend, Mesh, Texture},
+    util,
+    view_mode::active_view_mode,
     visibility::Visibility,
 };
 use amethyst_assets::{AssetStorage, Handle, HotReloadStrategy, ProcessingState, ThreadPool};
 use amethyst_core::{
     components::Transform,
-    ecs::{Read, ReadExpect, ReadStorage, RunNow, System, SystemData, World, Write, WriteExpect},
+    ecs::{
+        Entity, Read, ReadExpect, ReadStorage, RunNow, System, SystemData, World, Write,
+        WriteExpect,
+    },
     timing::Time,
     Hidden, HiddenPropagate,
 };
@@ -25,7 +42,11 @@ use rendy::{
     graph::{Graph, GraphBuilder},
     texture::palette::{load_from_linear_rgba, load_from_srgba},
 };
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    marker::PhantomData,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
@@ -51,6 +72,8 @@ where
     graph: Option<Graph<B, World>>,
     families: Option<Families<B>>,
     graph_creator: G,
+    failed: bool,
+    mismatched_camera: Option<Entity>,
 }
 
 impl<B, G> RenderingSystem<B, G>
@@ -64,6 +87,8 @@ where
             graph: None,
             families: None,
             graph_creator,
+            failed: false,
+            mismatched_camera: None,
         }
     }
 }
@@ -84,6 +109,12 @@ type SetupData<'a> = (
     Option<Read<'a, Visibility>>,
     Read<'a, ActiveCamera>,
     ReadStorage<'a, JointTransforms>,
+    ReadStorage<'a, DepthMode>,
+    ReadStorage<'a, DepthBias>,
+    ReadStorage<'a, SubMeshes>,
+    ReadStorage<'a, BlendShapes>,
+    ReadStorage<'a, InstanceData>,
+    ReadStorage<'a, CapsuleOccluder>,
 );
 
 impl<B, G> RenderingSystem<B, G>
@@ -112,21 +143,49 @@ where
         let graph = {
             #[cfg(feature = "profiler")]
             profile_scope!("build_graph");
-            builder
-                .build(&mut factory, self.families.as_mut().unwrap(), world)
-                .unwrap()
+            builder.build(&mut factory, self.families.as_mut().unwrap(), world)
         };
 
-        self.graph = Some(graph);
+        let mut error = world.fetch_mut::<RenderGraphError>();
+        match graph {
+            Ok(graph) => {
+                self.graph = Some(graph);
+                self.failed = false;
+                error.set(None);
+            }
+            Err(e) => {
+                log::error!("Failed to build the render graph: {}", e);
+                self.failed = true;
+                error.set(Some(e));
+            }
+        }
+    }
+
+    /// Log an error when the active camera doesn't follow the `Conventions`, once per camera.
+    fn check_conventions(&mut self, world: &World) {
+        let handedness = Conventions::of(world).handedness;
+        let cameras = <ReadStorage<'_, Camera>>::fetch(world);
+        let mismatched = CameraGatherer::gather_camera_entity(world).filter(|&entity| {
+            cameras
+                .get(entity)
+                .is_some_and(|camera| camera.handedness != handedness)
+        });
+        if mismatched.is_some() && mismatched != self.mismatched_camera {
+            log::error!(
+                "The active camera isn't {:?} handed like the world, mixing conventions is \
+                unsupported. Convert it with `Camera::with_conventions`.",
+                handedness,
+            );
+        }
+        self.mismatched_camera = mismatched;
     }
 
     fn run_graph(&mut self, world: &World) {
         let mut factory = world.fetch_mut::<Factory<B>>();
         factory.maintain(self.families.as_mut().unwrap());
-        self.graph
-            .as_mut()
-            .unwrap()
-            .run(&mut factory, self.families.as_mut().unwrap(), world)
+        if let Some(graph) = self.graph.as_mut() {
+            graph.run(&mut factory, self.families.as_mut().unwrap(), world)
+        }
     }
 }
 
@@ -137,10 +196,24 @@ where
 {
     fn run_now(&mut self, world: &'a World) {
         let rebuild = self.graph_creator.rebuild(world);
-        if self.graph.is_none() || rebuild {
+        if (self.graph.is_none() && !self.failed) || rebuild {
             self.rebuild_graph(world);
         }
+        if let Some(mut stats) = world.try_fetch_mut::<RenderStats>() {
+            stats.draw_list_touched = 0;
+            stats.discard_draws = 0;
+            stats.draw_calls = 0;
+            stats.light_uploads = 0;
+            stats.encode_time = Duration::default();
+            stats.view_mode = active_view_mode(world);
+        }
+        self.check_conventions(world);
+        let start = Instant::now();
         self.run_graph(world);
+        if let Some(mut stats) = world.try_fetch_mut::<RenderStats>() {
+            stats.graph_time = start.elapsed();
+            stats.buffer_allocations = util::take_buffer_allocations();
+        }
     }
 
     fn setup(&mut self, world: &mut World) {
@@ -155,11 +228,14 @@ where
         self.families = Some(families);
         world.insert(factory);
         world.insert(queue_id);
+        world.insert(RenderGraphError::default());
 
         SetupData::setup(world);
 
         let mat = create_default_mat::<B>(world);
+        let placeholders = create_placeholder_mats(world, &mat);
         world.insert(MaterialDefaults(mat));
+        world.insert(placeholders);
     }
 
     fn dispose(mut self: Box<Self>, world: &mut World) {
@@ -208,8 +284,9 @@ impl<'a, B: Backend> System<'a> for MeshProcessorSystem<B> {
                 #[cfg(feature = "profiler")]
                 profile_scope!("process_mesh");
 
+                let indexed = b.is_indexed();
                 b.0.build(*queue_id, &factory)
-                    .map(B::wrap_mesh)
+                    .map(|mesh| B::wrap_indexed_mesh(mesh, indexed))
                     .map(ProcessingState::Loaded)
                     .map_err(|e| e.compat().into())
             },
@@ -220,6 +297,82 @@ impl<'a, B: Backend> System<'a> for MeshProcessorSystem<B> {
     }
 }
 
+/// Limits how much texture data the `TextureProcessorSystem` uploads to the GPU in a single frame.
+///
+/// Textures that don't fit into the budget stay queued and are uploaded during the next frames,
+/// which spreads the cost of loading many textures at once instead of stalling a single frame.
+/// Materials using textures that aren't uploaded yet are skipped when drawing, and loading
+/// progress is still reported through the usual `ProgressCounter`.
+///
+/// At least one texture is uploaded every frame, so a single texture bigger than the budget is
+/// still uploaded in one go. Stream such textures with `TextureStreams` instead: their chunks are
+/// uploaded with the budget left once the queued textures are done, at least one chunk a frame.
+///
+/// By default there is no limit. The time limit is ignored while a seeded `RenderDeterminism`
+/// resource is present.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TextureUploadBudget {
+    /// Stop uploading textures once this much time was spent on it during the current frame.
+    pub max_time: Option<Duration>,
+    /// Stop uploading textures once this many bytes were uploaded during the current frame.
+    pub max_bytes: Option<u64>,
+}
+
+impl TextureUploadBudget {
+    /// Limit the time spent uploading textures per frame.
+    pub fn with_max_time(mut self, max_time: Duration) -> Self {
+        self.max_time = Some(max_time);
+        self
+    }
+
+    /// Limit the amount of bytes uploaded per frame.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    fn exceeded(&self, elapsed: Duration, bytes: u64) -> bool {
+        self.max_time.is_some_and(|max| elapsed >= max)
+            || self.max_bytes.is_some_and(|max| bytes >= max)
+    }
+}
+
+/// Textures uploaded during the current frame, checked against a `TextureUploadBudget`.
+#[derive(Debug, Default)]
+pub(crate) struct FrameUploads {
+    textures: usize,
+    chunks: usize,
+    bytes: u64,
+    deferred: usize,
+}
+
+impl FrameUploads {
+    /// Whether the next texture must wait for the next frame, given the time spent uploading
+    /// during this frame. Counts the deferred textures.
+    fn defer(&mut self, budget: &TextureUploadBudget, elapsed: Duration) -> bool {
+        let defer = self.textures > 0 && budget.exceeded(elapsed, self.bytes);
+        if defer {
+            self.deferred += 1;
+        }
+        defer
+    }
+
+    fn uploaded(&mut self, bytes: u64) {
+        self.textures += 1;
+        self.bytes += bytes;
+    }
+
+    /// Whether the next chunk of a streamed texture must wait for the next frame.
+    pub(crate) fn defer_chunk(&self, budget: &TextureUploadBudget, elapsed: Duration) -> bool {
+        self.textures + self.chunks > 0 && budget.exceeded(elapsed, self.bytes)
+    }
+
+    pub(crate) fn streamed(&mut self, bytes: u64) {
+        self.chunks += 1;
+        self.bytes += bytes;
+    }
+}
+
 /// Asset processing system for `Texture` asset type.
 #[derive(Debug, derivative::Derivative)]
 #[derivative(Default(bound = ""))]
@@ -232,43 +385,92 @@ impl<'a, B: Backend> System<'a> for TextureProcessorSystem<B> {
         ReadExpect<'a, Arc<ThreadPool>>,
         Option<Read<'a, HotReloadStrategy>>,
         WriteExpect<'a, Factory<B>>,
+        Read<'a, TextureUploadBudget>,
+        Read<'a, RenderDeterminism>,
+        Write<'a, RenderStats>,
+        Write<'a, TextureStreams>,
     );
 
     fn run(
         &mut self,
-        (mut texture_storage, queue_id, time, pool, strategy, mut factory): Self::SystemData,
+        (
+            mut texture_storage,
+            queue_id,
+            time,
+            pool,
+            strategy,
+            mut factory,
+            budget,
+            determinism,
+            mut stats,
+            mut streams,
+        ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("texture_processor");
 
+        let start = Instant::now();
+        let elapsed = || {
+            if determinism.is_seeded() {
+                Duration::from_secs(0)
+            } else {
+                start.elapsed()
+            }
+        };
+        let next_state = ImageState {
+            queue: *queue_id,
+            stage: rendy::hal::pso::PipelineStage::VERTEX_SHADER
+                | rendy::hal::pso::PipelineStage::FRAGMENT_SHADER,
+            access: rendy::hal::image::Access::SHADER_READ,
+            layout: rendy::hal::image::Layout::ShaderReadOnlyOptimal,
+        };
+        let mut uploads = FrameUploads::default();
+
         texture_storage.process(
             |b| {
                 #[cfg(feature = "profiler")]
                 profile_scope!("process_texture");
 
-                b.0.build(
-                    ImageState {
-                        queue: *queue_id,
-                        stage: rendy::hal::pso::PipelineStage::VERTEX_SHADER
-                            | rendy::hal::pso::PipelineStage::FRAGMENT_SHADER,
-                        access: rendy::hal::image::Access::SHADER_READ,
-                        layout: rendy::hal::image::Layout::ShaderReadOnlyOptimal,
-                    },
-                    &mut factory,
-                )
-                .map(B::wrap_texture)
-                .map(ProcessingState::Loaded)
-                .map_err(|e| e.compat().into())
+                if uploads.defer(&budget, elapsed()) {
+                    return Ok(ProcessingState::Loading(b));
+                }
+
+                b.0.build(next_state, &mut factory)
+                    .map(|texture| {
+                        let image = texture.image();
+                        uploads.uploaded(image_bytes(image.kind(), image.levels(), image.format()));
+                        B::wrap_texture(texture)
+                    })
+                    .map(ProcessingState::Loaded)
+                    .map_err(|e| e.compat().into())
             },
             time.frame_number(),
             &**pool,
             strategy.as_deref(),
         );
+
+        {
+            #[cfg(feature = "profiler")]
+            profile_scope!("stream_textures");
+
+            streams.upload(&mut factory, &mut texture_storage, next_state, |bytes| {
+                if uploads.defer_chunk(&budget, elapsed()) {
+                    return false;
+                }
+                uploads.streamed(bytes);
+                true
+            });
+        }
+
+        stats.deferred_textures = uploads.deferred;
     }
 }
 
-fn create_default_mat<B: Backend>(world: &mut World) -> Material {
-    use crate::mtl::TextureOffset;
+pub(crate) fn create_default_mat<B: Backend>(world: &mut World) -> Material {
+    use crate::mtl::{
+        DetailBlend, TextureOffset, UvTransform, DETAIL_SCALE, DISSOLVE_EDGE_COLOR,
+        DISSOLVE_EDGE_WIDTH, LAYER_COLOR, LAYER_ROUGHNESS, SHININESS, TRIPLANAR_SHARPNESS,
+    };
 
     use amethyst_assets::Loader;
 
@@ -280,6 +482,9 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
     let metallic_roughness = load_from_linear_rgba(LinSrgba::new(0.0, 0.5, 0.0, 0.0));
     let ambient_occlusion = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
     let cavity = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
+    let dissolve_noise = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
+    let detail = load_from_linear_rgba(LinSrgba::new(0.5, 0.5, 0.5, 1.0));
+    let layer_mask = load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0));
 
     let tex_storage = world.fetch();
 
@@ -289,6 +494,9 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
     let metallic_roughness = loader.load_from_data(metallic_roughness.into(), (), &tex_storage);
     let ambient_occlusion = loader.load_from_data(ambient_occlusion.into(), (), &tex_storage);
     let cavity = loader.load_from_data(cavity.into(), (), &tex_storage);
+    let dissolve_noise = loader.load_from_data(dissolve_noise.into(), (), &tex_storage);
+    let detail = loader.load_from_data(detail.into(), (), &tex_storage);
+    let layer_mask = loader.load_from_data(layer_mask.into(), (), &tex_storage);
 
     Material {
         alpha_cutoff: 0.01,
@@ -299,5 +507,108 @@ fn create_default_mat<B: Backend>(world: &mut World) -> Material {
         ambient_occlusion,
         cavity,
         uv_offset: TextureOffset::default(),
+        emission_factor: [1.0; 3],
+        uv_transform: UvTransform::default(),
+        normal_uv_transform: UvTransform::default(),
+        dissolve_noise,
+        dissolve_amount: 0.0,
+        dissolve_edge_width: DISSOLVE_EDGE_WIDTH,
+        dissolve_edge_color: DISSOLVE_EDGE_COLOR,
+        triplanar_scale: 0.0,
+        triplanar_sharpness: TRIPLANAR_SHARPNESS,
+        detail,
+        detail_scale: DETAIL_SCALE,
+        detail_blend: DetailBlend::default(),
+        detail_fade_distance: 0.0,
+        layer_mask,
+        layer_color: LAYER_COLOR,
+        layer_roughness: LAYER_ROUGHNESS,
+        layer_amount: 0.0,
+        layer_scene_factor: 0.0,
+        light_channels: !0,
+        subsurface: 0.0,
+        specular: [0.0; 3],
+        shininess: SHININESS,
+        shader_params: [[0.0; 4]; MAX_MATERIAL_SHADER_PARAMS],
+    }
+}
+
+fn create_placeholder_mats(world: &mut World, defaults: &Material) -> MaterialPlaceholders {
+    use amethyst_assets::Loader;
+
+    world
+        .entry::<AssetStorage<Material>>()
+        .or_insert_with(AssetStorage::new);
+
+    let loader = world.fetch::<Loader>();
+    let checker = checkerboard_data(
+        64,
+        8,
+        Srgba::new(1.0, 0.0, 1.0, 1.0),
+        Srgba::new(0.0, 0.0, 0.0, 1.0),
+    );
+    let albedo = loader.load_from_data(checker, (), &world.fetch());
+
+    let mat_storage = world.fetch();
+    MaterialPlaceholders {
+        missing: loader.load_from_data(
+            Material {
+                albedo,
+                ..defaults.clone()
+            },
+            (),
+            &mat_storage,
+        ),
+        loading: loader.load_from_data(defaults.clone(), (), &mat_storage),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_budget_defers_textures_after_the_first() {
+        let budget = TextureUploadBudget::default().with_max_time(Duration::from_millis(4));
+        let mut uploads = FrameUploads::default();
+
+        // The first texture is uploaded even past the budget.
+        assert!(!uploads.defer(&budget, Duration::from_millis(10)));
+        uploads.uploaded(1 << 20);
+        assert!(!uploads.defer(&budget, Duration::from_millis(3)));
+        uploads.uploaded(1 << 20);
+        assert!(uploads.defer(&budget, Duration::from_millis(4)));
+        assert!(uploads.defer(&budget, Duration::from_millis(5)));
+        assert_eq!((uploads.textures, uploads.deferred), (2, 2));
+    }
+
+    #[test]
+    fn byte_budget_defers_textures_after_the_first() {
+        let budget = TextureUploadBudget::default().with_max_bytes(1000);
+        let mut uploads = FrameUploads::default();
+
+        // Time doesn't count without a time budget, and a texture bigger than the budget is
+        // uploaded when it's the first one.
+        assert!(!uploads.defer(&budget, Duration::from_secs(1)));
+        uploads.uploaded(600);
+        assert!(!uploads.defer(&budget, Duration::from_secs(1)));
+        uploads.uploaded(600);
+        assert!(uploads.defer(&budget, Duration::from_secs(0)));
+        assert_eq!(
+            (uploads.textures, uploads.bytes, uploads.deferred),
+            (2, 1200, 1)
+        );
+
+        let mut uploads = FrameUploads::default();
+        assert!(!uploads.defer(&budget, Duration::from_secs(0)));
+        uploads.uploaded(4000);
+        assert!(uploads.defer(&budget, Duration::from_secs(0)));
+
+        // Without limits, nothing is deferred.
+        let mut uploads = FrameUploads::default();
+        for _ in 0..3 {
+            assert!(!uploads.defer(&TextureUploadBudget::default(), Duration::from_secs(1)));
+            uploads.uploaded(1 << 30);
+        }
     }
 }
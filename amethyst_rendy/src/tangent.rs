@@ -0,0 +1,190 @@
+//! Tangents of meshes, orienting the normal maps of their materials.
+//!
+//! Normal mapped passes, like `DrawShadedNormalMapped` and `DrawPbr`, perturb the normals of
+//! meshes in the basis formed by their normal, tangent and bitangent. The tangent follows the
+//! direction of increasing U texture coordinate on the surface, and its W component gives the
+//! handedness of the basis, -1 where the texture is mirrored.
+
+use amethyst_core::math::Vector3;
+use rendy::mesh::{Normal, PosNormTangTex, PosNormTex, Position, Tangent, TexCoord};
+
+/// Vertex with a position, a normal, a tangent and texture coordinates, as drawn by the normal
+/// mapped passes.
+pub type VertexPosNormalTangentTex = PosNormTangTex;
+
+/// Tangents of the vertices of an indexed triangle list, one per vertex.
+///
+/// The tangent of a vertex averages the tangents of the triangles sharing it, made orthogonal
+/// to its normal. Vertices whose triangles have degenerate texture coordinates, e.g. all
+/// mapped to the same point, get an arbitrary tangent orthogonal to their normal instead.
+/// Indices past the last whole triangle are ignored.
+///
+/// # Panics
+///
+/// Panics when the slices of attributes have different lengths, or when an index is out of
+/// their bounds.
+pub fn generate_tangents(
+    positions: &[Position],
+    normals: &[Normal],
+    tex_coords: &[TexCoord],
+    indices: &[u32],
+) -> Vec<Tangent> {
+    assert_eq!(positions.len(), normals.len());
+    assert_eq!(positions.len(), tex_coords.len());
+
+    let mut tangents = vec![Vector3::zeros(); positions.len()];
+    let mut bitangents = vec![Vector3::zeros(); positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [
+            triangle[0] as usize,
+            triangle[1] as usize,
+            triangle[2] as usize,
+        ];
+        let position = |i: usize| Vector3::from(positions[i].0);
+        let (edge1, edge2) = (position(b) - position(a), position(c) - position(a));
+        let [u0, v0] = tex_coords[a].0;
+        let ([u1, v1], [u2, v2]) = (tex_coords[b].0, tex_coords[c].0);
+        let (du1, dv1, du2, dv2) = (u1 - u0, v1 - v0, u2 - u0, v2 - v0);
+
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() <= f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge1 * dv2 - edge2 * dv1) / det;
+        let bitangent = (edge2 * du1 - edge1 * du2) / det;
+        for &i in &[a, b, c] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    normals
+        .iter()
+        .zip(tangents)
+        .zip(bitangents)
+        .map(|((normal, tangent), bitangent)| {
+            let normal = Vector3::from(normal.0)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::z);
+            match (tangent - normal * normal.dot(&tangent)).try_normalize(f32::EPSILON) {
+                Some(tangent) => {
+                    // Textures are sampled from their top left corner, so the bitangent of the
+                    // shaders points towards decreasing V, like in the tangents of glTF meshes.
+                    let handedness = if normal.cross(&tangent).dot(&bitangent) > 0.0 {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    Tangent([tangent.x, tangent.y, tangent.z, handedness])
+                }
+                None => {
+                    let tangent = orthogonal(&normal);
+                    Tangent([tangent.x, tangent.y, tangent.z, 1.0])
+                }
+            }
+        })
+        .collect()
+}
+
+/// Vertices of an indexed triangle list with the tangents of [generate_tangents].
+pub fn with_tangents(vertices: &[PosNormTex], indices: &[u32]) -> Vec<VertexPosNormalTangentTex> {
+    let positions = vertices.iter().map(|v| v.position).collect::<Vec<_>>();
+    let normals = vertices.iter().map(|v| v.normal).collect::<Vec<_>>();
+    let tex_coords = vertices.iter().map(|v| v.tex_coord).collect::<Vec<_>>();
+    vertices
+        .iter()
+        .zip(generate_tangents(
+            &positions,
+            &normals,
+            &tex_coords,
+            indices,
+        ))
+        .map(|(v, tangent)| PosNormTangTex {
+            position: v.position,
+            normal: v.normal,
+            tangent,
+            tex_coord: v.tex_coord,
+        })
+        .collect()
+}
+
+/// Unit vector orthogonal to given unit normal, like the tangents of the generated shapes.
+fn orthogonal(normal: &Vector3<f32>) -> Vector3<f32> {
+    let tangent1 = normal.cross(&Vector3::x());
+    let tangent2 = normal.cross(&Vector3::y());
+    if tangent1.norm_squared() > tangent2.norm_squared() {
+        tangent1
+    } else {
+        tangent2
+    }
+    .cross(normal)
+    .normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Quad in the XY plane facing +Z, with given texture coordinates at its corners.
+    fn quad(tex_coords: [[f32; 2]; 4]) -> Vec<PosNormTex> {
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ];
+        positions
+            .iter()
+            .zip(&tex_coords)
+            .map(|(&position, &tex_coord)| PosNormTex {
+                position: position.into(),
+                normal: [0.0, 0.0, 1.0].into(),
+                tex_coord: tex_coord.into(),
+            })
+            .collect()
+    }
+
+    const INDICES: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+    #[test]
+    fn tangents_follow_the_texture_coordinates() {
+        let vertices = with_tangents(
+            &quad([[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]]),
+            &INDICES,
+        );
+        for v in &vertices {
+            assert_eq!(v.tangent.0, [1.0, 0.0, 0.0, 1.0]);
+        }
+
+        // Mirroring the texture flips the tangent and the handedness of the basis.
+        let vertices = with_tangents(
+            &quad([[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]]),
+            &INDICES,
+        );
+        for v in &vertices {
+            assert_eq!(v.tangent.0, [-1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn degenerate_texture_coordinates_get_orthogonal_tangents() {
+        let vertices = with_tangents(&quad([[0.5, 0.5]; 4]), &INDICES);
+        for v in &vertices {
+            let tangent = Vector3::new(v.tangent.0[0], v.tangent.0[1], v.tangent.0[2]);
+            assert!(tangent.iter().all(|c| c.is_finite()));
+            assert!((tangent.norm() - 1.0).abs() < 1.0e-6);
+            assert!(tangent.dot(&Vector3::z()).abs() < 1.0e-6);
+            assert_eq!(v.tangent.0[3], 1.0);
+        }
+
+        // Vertices of no triangle get an orthogonal tangent too.
+        let tangents = generate_tangents(
+            &[[0.0; 3].into()],
+            &[[0.0, 1.0, 0.0].into()],
+            &[[0.0; 2].into()],
+            &[],
+        );
+        let [x, y, z, _] = tangents[0].0;
+        assert!((Vector3::new(x, y, z).norm() - 1.0).abs() < 1.0e-6 && y.abs() < 1.0e-6);
+    }
+}
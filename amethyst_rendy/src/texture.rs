@@ -0,0 +1,318 @@
+//! Procedural test textures.
+//!
+//! Patterns for checking texture coordinates, filtering and mip selection without shipping
+//! image files. The `*_data` functions produce [TextureData] to load through the `Loader` like
+//! any other texture, while [checkerboard], [uv_gradient] and [mip_debug] build the texture
+//! directly on the factory.
+
+use crate::types::{Backend, Texture, TextureData};
+use palette::Srgba;
+use rendy::{
+    command::QueueId,
+    factory::{Factory, ImageState},
+    hal::{
+        self,
+        format::{Aspects, Format},
+        image::{Extent, Filter, Kind, Offset, SamplerInfo, SubresourceLayers, ViewKind, WrapMode},
+    },
+    texture::{
+        mip_levels_from_dims,
+        pixel::{Rgba8Srgb, Rgba8Unorm},
+        MipLevels, TextureBuilder,
+    },
+};
+use std::num::NonZeroU8;
+
+/// Colors of the [mip_debug] levels, starting from the full size level.
+pub const MIP_DEBUG_COLORS: [(u8, u8, u8); 16] = [
+    (255, 0, 0),
+    (0, 255, 0),
+    (0, 0, 255),
+    (255, 255, 0),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+    (255, 128, 0),
+    (128, 0, 255),
+    (0, 128, 64),
+    (128, 64, 0),
+    (255, 128, 192),
+    (64, 64, 64),
+    (128, 255, 128),
+    (0, 64, 128),
+    (0, 0, 0),
+];
+
+/// Sizes of the full mip chain of a `size` x `size` texture, down to 1x1.
+///
+/// Every level is half of the previous one rounded down, so sizes that aren't powers of two
+/// are handled the same way as by the GPU.
+pub fn mip_chain(size: u32) -> Vec<u32> {
+    (0..mip_levels_from_dims(size, size))
+        .map(|level| (size >> level).max(1))
+        .collect()
+}
+
+/// Pixels of a `size` x `size` checkerboard with `cells` x `cells` squares, starting with
+/// `color_a` in the top left corner.
+pub fn checkerboard_pixels(
+    size: u32,
+    cells: u32,
+    color_a: Srgba,
+    color_b: Srgba,
+) -> Vec<Rgba8Srgb> {
+    let cells = cells.max(1).min(size.max(1));
+    let (a, b) = (srgba8(color_a), srgba8(color_b));
+    let cell = |i: u32| i * cells / size;
+    (0..size)
+        .flat_map(|y| (0..size).map(move |x| (cell(x) + cell(y)) % 2 == 0))
+        .map(|even| if even { a } else { b })
+        .collect()
+}
+
+/// Pixels of a `size` x `size` gradient, with red set to the u and green to the v texture
+/// coordinate at the center of every texel.
+pub fn uv_gradient_pixels(size: u32) -> Vec<Rgba8Unorm> {
+    let channel = |i: u32| ((i as f32 + 0.5) / size as f32 * 255.0).round() as u8;
+    (0..size)
+        .flat_map(|y| {
+            (0..size).map(move |x| Rgba8Unorm {
+                repr: [channel(x), channel(y), 0, 255],
+            })
+        })
+        .collect()
+}
+
+/// Pixels of a `size` x `size` tiling value noise, smoothly interpolated between random values
+/// on a `cells` x `cells` grid. The noise is gray, with every channel set to it, and the same
+/// `seed` always gives the same noise.
+pub fn value_noise_pixels(size: u32, cells: u32, seed: u32) -> Vec<Rgba8Unorm> {
+    let cells = cells.max(1).min(size.max(1));
+    let lattice = |x: u32, y: u32| {
+        let mut h = (x % cells)
+            .wrapping_mul(0x8da6_b343)
+            .wrapping_add((y % cells).wrapping_mul(0xd816_3841))
+            .wrapping_add(seed.wrapping_mul(0xcb1a_b31f));
+        h ^= h >> 13;
+        h = h.wrapping_mul(0x5bd1_e995);
+        h ^= h >> 15;
+        (h & 0xffff) as f32 / 65535.0
+    };
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    (0..size)
+        .flat_map(|y| (0..size).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let fx = (x as f32 + 0.5) * cells as f32 / size as f32;
+            let fy = (y as f32 + 0.5) * cells as f32 / size as f32;
+            let (cx, cy) = (fx as u32, fy as u32);
+            let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+            let top = lattice(cx, cy) + (lattice(cx + 1, cy) - lattice(cx, cy)) * tx;
+            let bottom = lattice(cx, cy + 1) + (lattice(cx + 1, cy + 1) - lattice(cx, cy + 1)) * tx;
+            let value = ((top + (bottom - top) * ty) * 255.0).round() as u8;
+            Rgba8Unorm {
+                repr: [value, value, value, 255],
+            }
+        })
+        .collect()
+}
+
+/// Color of given [mip_debug] level.
+pub fn mip_debug_color(level: u32) -> Rgba8Srgb {
+    let (r, g, b) = MIP_DEBUG_COLORS[level as usize % MIP_DEBUG_COLORS.len()];
+    Rgba8Srgb {
+        repr: [r, g, b, 255],
+    }
+}
+
+/// Checkerboard texture data, see [checkerboard_pixels]. Sampled with nearest filtering so the
+/// cell edges stay sharp.
+pub fn checkerboard_data(size: u32, cells: u32, color_a: Srgba, color_b: Srgba) -> TextureData {
+    builder(size, Filter::Nearest)
+        .with_data(checkerboard_pixels(size, cells, color_a, color_b))
+        .into()
+}
+
+/// UV gradient texture data, see [uv_gradient_pixels]. The data is linear, so the sampled
+/// channels are the texture coordinates themselves.
+pub fn uv_gradient_data(size: u32) -> TextureData {
+    builder(size, Filter::Linear)
+        .with_data(uv_gradient_pixels(size))
+        .into()
+}
+
+/// Value noise texture data, see [value_noise_pixels]. The data is linear, so the sampled
+/// channels are the noise itself.
+pub fn value_noise_data(size: u32, cells: u32, seed: u32) -> TextureData {
+    builder(size, Filter::Linear)
+        .with_data(value_noise_pixels(size, cells, seed))
+        .into()
+}
+
+/// Build a checkerboard texture, see [checkerboard_pixels].
+pub fn checkerboard<B: Backend>(
+    factory: &mut Factory<B>,
+    queue: QueueId,
+    size: u32,
+    cells: u32,
+    color_a: Srgba,
+    color_b: Srgba,
+) -> Result<Texture, failure::Error> {
+    let data = checkerboard_data(size, cells, color_a, color_b);
+    let texture = data.0.build(shader_read(queue), factory)?;
+    Ok(B::wrap_texture(texture))
+}
+
+/// Build an UV gradient texture, see [uv_gradient_pixels].
+pub fn uv_gradient<B: Backend>(
+    factory: &mut Factory<B>,
+    queue: QueueId,
+    size: u32,
+) -> Result<Texture, failure::Error> {
+    let data = uv_gradient_data(size);
+    let texture = data.0.build(shader_read(queue), factory)?;
+    Ok(B::wrap_texture(texture))
+}
+
+/// Build a texture with the full mip chain of a `size` x `size` image, every level filled with
+/// its solid [mip_debug_color]. Sampling it shows which level the GPU selects.
+pub fn mip_debug<B: Backend>(
+    factory: &mut Factory<B>,
+    queue: QueueId,
+    size: u32,
+) -> Result<Texture, failure::Error> {
+    let chain = mip_chain(size);
+    let state = shader_read(queue);
+    let texture = builder(size, Filter::Nearest)
+        .with_mip_levels(MipLevels::RawLevels(
+            NonZeroU8::new(chain.len() as u8).expect("Mip chain is never empty"),
+        ))
+        .with_data(vec![mip_debug_color(0); (size * size) as usize])
+        .build(state, factory)?;
+
+    if texture.image().format() != Format::Rgba8Srgb {
+        failure::bail!(
+            "Mip debug texture requires Rgba8Srgb support, got {:?}",
+            texture.image().format()
+        );
+    }
+
+    for (level, &level_size) in chain.iter().enumerate().skip(1) {
+        let pixels = vec![mip_debug_color(level as u32); (level_size * level_size) as usize];
+        // The image was just created on this factory and isn't used by any submission yet.
+        unsafe {
+            factory.upload_image(
+                texture.image().clone(),
+                level_size,
+                level_size,
+                SubresourceLayers {
+                    aspects: Aspects::COLOR,
+                    level: level as u8,
+                    layers: 0..1,
+                },
+                Offset::ZERO,
+                Extent {
+                    width: level_size,
+                    height: level_size,
+                    depth: 1,
+                },
+                &pixels,
+                state,
+                state,
+            )?;
+        }
+    }
+    Ok(B::wrap_texture(texture))
+}
+
+fn srgba8(color: Srgba) -> Rgba8Srgb {
+    let color = color.into_format::<u8, u8>();
+    Rgba8Srgb {
+        repr: [color.red, color.green, color.blue, color.alpha],
+    }
+}
+
+fn builder(size: u32, filter: Filter) -> TextureBuilder<'static> {
+    TextureBuilder::new()
+        .with_kind(Kind::D2(size, size, 1, 1))
+        .with_view_kind(ViewKind::D2)
+        .with_data_width(size)
+        .with_data_height(size)
+        .with_sampler_info(SamplerInfo::new(filter, WrapMode::Tile))
+}
+
+fn shader_read(queue: QueueId) -> ImageState {
+    ImageState {
+        queue,
+        stage: hal::pso::PipelineStage::VERTEX_SHADER | hal::pso::PipelineStage::FRAGMENT_SHADER,
+        access: hal::image::Access::SHADER_READ,
+        layout: hal::image::Layout::ShaderReadOnlyOptimal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkerboard_cells() {
+        let white = Srgba::new(1.0, 1.0, 1.0, 1.0);
+        let black = Srgba::new(0.0, 0.0, 0.0, 1.0);
+        let pixels = checkerboard_pixels(8, 4, white, black);
+        let at = |x: usize, y: usize| pixels[y * 8 + x].repr;
+
+        assert_eq!(pixels.len(), 64);
+        assert_eq!(at(0, 0), [255, 255, 255, 255]);
+        assert_eq!(at(1, 1), [255, 255, 255, 255]);
+        assert_eq!(at(2, 0), [0, 0, 0, 255]);
+        assert_eq!(at(0, 2), [0, 0, 0, 255]);
+        assert_eq!(at(7, 7), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn uv_gradient_corners() {
+        let pixels = uv_gradient_pixels(4);
+        assert_eq!(pixels[0].repr, [32, 32, 0, 255]);
+        assert_eq!(pixels[3].repr, [223, 32, 0, 255]);
+        assert_eq!(pixels[12].repr, [32, 223, 0, 255]);
+        assert_eq!(pixels[15].repr, [223, 223, 0, 255]);
+    }
+
+    #[test]
+    fn value_noise_tiles_and_repeats() {
+        let values = |seed| {
+            value_noise_pixels(16, 4, seed)
+                .iter()
+                .map(|p| p.repr[0])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(7), values(7));
+        assert_ne!(values(7), values(8));
+
+        let values = values(7);
+        assert!(values.iter().min() < values.iter().max());
+        // Neighbours across the wrapping edge differ no more than neighbours inside.
+        let step = |a: u8, b: u8| (i32::from(a) - i32::from(b)).abs();
+        let inner = (0..16)
+            .flat_map(|y| (0..15).map(move |x| y * 16 + x))
+            .map(|i| step(values[i], values[i + 1]))
+            .max()
+            .unwrap();
+        let edge = (0..16)
+            .map(|y| step(values[y * 16 + 15], values[y * 16]))
+            .max()
+            .unwrap();
+        assert!(edge <= inner);
+    }
+
+    #[test]
+    fn mip_chain_of_non_power_of_two() {
+        assert_eq!(mip_chain(100), vec![100, 50, 25, 12, 6, 3, 1]);
+        assert_eq!(mip_chain(64).len(), 7);
+        assert_eq!(mip_chain(1), vec![1]);
+
+        let colors = (0..7).map(|l| mip_debug_color(l).repr).collect::<Vec<_>>();
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[..i].contains(color));
+        }
+    }
+}
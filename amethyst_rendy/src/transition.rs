@@ -0,0 +1,195 @@
+//! Transitions between the last frame of a scene and the frames following it, e.g. to
+//! cross-fade between two levels or the shots of a cutscene.
+//!
+//! `Transition::begin_transition` captures the next frame of the target of the
+//! `RenderTransitions` plugin, then composites it over the live frames of the following frames
+//! until the live frame is fully revealed. The capture is composited before tone mapping, so
+//! display passes like the UI aren't part of it. Transitions are aborted when the render graph
+//! is rebuilt, e.g. when the window is resized, as the capture is lost with the images of the
+//! graph.
+
+use crate::mtl::{DISSOLVE_EDGE_COLOR, DISSOLVE_EDGE_WIDTH};
+use amethyst_core::math::Vector2;
+
+/// How the captured frame gives way to the live frame during a [Transition].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionPattern {
+    /// The captured frame fades out linearly.
+    Fade,
+    /// The live frame is revealed behind an edge sweeping the target in given direction, in
+    /// texture coordinates going right and down.
+    Wipe(Vector2<f32>),
+    /// The captured frame dissolves in value noise, like the dissolve of materials.
+    Dissolve {
+        /// Size of the cells of the noise, in pixels.
+        cell_size: f32,
+        /// Width of the glowing edge, in noise values above the dissolved ones.
+        edge_width: f32,
+        /// Linear RGB color emitted by the edge.
+        edge_color: [f32; 3],
+    },
+}
+
+impl TransitionPattern {
+    /// Dissolve with cells of 16 pixels and the default edge of materials.
+    pub fn dissolve() -> Self {
+        TransitionPattern::Dissolve {
+            cell_size: 16.0,
+            edge_width: DISSOLVE_EDGE_WIDTH,
+            edge_color: DISSOLVE_EDGE_COLOR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TransitionState {
+    Idle,
+    Requested {
+        frames: u32,
+        pattern: TransitionPattern,
+    },
+    Running {
+        frame: u32,
+        frames: u32,
+        pattern: TransitionPattern,
+        size: (u32, u32),
+    },
+}
+
+/// Resource driving the transition of the `RenderTransitions` plugin, see
+/// [crate::transition].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    state: TransitionState,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self {
+            state: TransitionState::Idle,
+        }
+    }
+}
+
+impl Transition {
+    /// Capture the next rendered frame and composite it over the `frames` frames following it
+    /// with given pattern, replacing the transition in progress if any.
+    pub fn begin_transition(&mut self, frames: u32, pattern: TransitionPattern) {
+        self.state = TransitionState::Requested {
+            frames: frames.max(1),
+            pattern,
+        };
+    }
+
+    /// Stop the transition in progress, showing the live frame from the next frame.
+    pub fn abort(&mut self) {
+        self.state = TransitionState::Idle;
+    }
+
+    /// Whether a transition is requested or in progress.
+    pub fn is_active(&self) -> bool {
+        self.state != TransitionState::Idle
+    }
+
+    /// Progress of the transition in progress, from 0 showing the captured frame to 1 showing
+    /// the live frame.
+    pub fn progress(&self) -> Option<f32> {
+        match self.state {
+            TransitionState::Running { frame, frames, .. } => Some(frame as f32 / frames as f32),
+            _ => None,
+        }
+    }
+
+    /// Called when the capture pass prepares a frame of given size. Returns whether the frame
+    /// must be captured.
+    pub(crate) fn capture(&mut self, size: (u32, u32)) -> bool {
+        match std::mem::replace(&mut self.state, TransitionState::Idle) {
+            TransitionState::Requested { frames, pattern } => {
+                self.state = TransitionState::Running {
+                    frame: 0,
+                    frames,
+                    pattern,
+                    size,
+                };
+                true
+            }
+            state => {
+                self.state = state;
+                false
+            }
+        }
+    }
+
+    /// Called once per frame when the composite pass prepares a frame of given size, before
+    /// the capture pass. Returns the pattern and progress to composite the capture with, if
+    /// any.
+    ///
+    /// The transition is aborted when the size differs from the size of the capture, as the
+    /// capture was resized along with the target and lost its contents.
+    pub(crate) fn advance(&mut self, size: (u32, u32)) -> Option<(TransitionPattern, f32)> {
+        if let TransitionState::Running {
+            frame,
+            frames,
+            pattern,
+            size: captured,
+        } = &mut self.state
+        {
+            if *captured != size {
+                log::warn!(
+                    "Aborting transition, the target was resized from {:?} to {:?}.",
+                    captured,
+                    size
+                );
+            } else if *frame + 1 < *frames {
+                *frame += 1;
+                return Some((pattern.clone(), *frame as f32 / *frames as f32));
+            }
+            self.state = TransitionState::Idle;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions_capture_then_composite_the_following_frames() {
+        let mut transition = Transition::default();
+        transition.begin_transition(4, TransitionPattern::Fade);
+        assert!(transition.is_active());
+
+        // Nothing is captured yet during the frame the transition begins.
+        assert_eq!(transition.advance((64, 32)), None);
+        assert!(transition.capture((64, 32)));
+        assert_eq!(transition.progress(), Some(0.0));
+
+        let mut progress = Vec::new();
+        while let Some((pattern, p)) = transition.advance((64, 32)) {
+            assert_eq!(pattern, TransitionPattern::Fade);
+            assert!(!transition.capture((64, 32)));
+            progress.push(p);
+        }
+        assert_eq!(progress, vec![0.25, 0.5, 0.75]);
+        assert!(!transition.is_active());
+        assert!(!transition.capture((64, 32)));
+    }
+
+    #[test]
+    fn resizes_abort_transitions() {
+        let mut transition = Transition::default();
+        transition.begin_transition(10, TransitionPattern::dissolve());
+        assert!(transition.capture((64, 32)));
+        assert!(transition.advance((64, 32)).is_some());
+
+        assert_eq!(transition.advance((128, 64)), None);
+        assert!(!transition.is_active());
+        assert_eq!(transition.advance((64, 32)), None);
+
+        // Transitions requested before a resize capture the resized target.
+        transition.begin_transition(10, TransitionPattern::Wipe(Vector2::new(1.0, 0.0)));
+        assert!(transition.capture((128, 64)));
+        assert!(transition.advance((128, 64)).is_some());
+    }
+}
@@ -9,8 +9,11 @@ pub trait Backend: rendy::hal::Backend {
     fn unwrap_mesh(mesh: &Mesh) -> Option<&rendy::mesh::Mesh<Self>>;
     /// Unwrap a Backend to a rendy `Texture`
     fn unwrap_texture(texture: &Texture) -> Option<&rendy::texture::Texture<Self>>;
-    /// Wrap a rendy `Mesh` to its Backend generic.
+    /// Wrap a rendy `Mesh` to its Backend generic. The mesh is assumed to have no index buffer,
+    /// use `wrap_indexed_mesh` when it's known.
     fn wrap_mesh(mesh: rendy::mesh::Mesh<Self>) -> Mesh;
+    /// Wrap a rendy `Mesh` to its Backend generic, recording whether it has an index buffer.
+    fn wrap_indexed_mesh(mesh: rendy::mesh::Mesh<Self>, indexed: bool) -> Mesh;
     /// Wrap a rendy `Texture` to its Backend generic.
     fn wrap_texture(texture: rendy::texture::Texture<Self>) -> Texture;
 }
@@ -36,13 +39,13 @@ macro_rules! impl_backends {
             )*
         }
 
-        /// Mesh wrapper.
+        /// Mesh wrapper, along with whether the mesh has an index buffer.
         #[derive(Debug)]
         pub enum Mesh {
             $(
                 #[cfg(feature = $feature)]
                 #[doc = "Mesh Variant"]
-                $variant(rendy::mesh::Mesh<$backend>),
+                $variant(rendy::mesh::Mesh<$backend>, bool),
             )*
         }
 
@@ -56,13 +59,26 @@ macro_rules! impl_backends {
             )*
         }
 
+        impl Mesh {
+            /// Whether the mesh has an index buffer. Indexed draws of a mesh without one,
+            /// including indirect ones, are undefined behaviour.
+            pub fn is_indexed(&self) -> bool {
+                match self {
+                    $(
+                        #[cfg(feature = $feature)]
+                        Mesh::$variant(_, indexed) => *indexed,
+                    )*
+                }
+            }
+        }
+
         $(
             #[cfg(feature = $feature)]
             impl Backend for $backend {
                 #[inline]
                 #[allow(irrefutable_let_patterns)]
                 fn unwrap_mesh(mesh: &Mesh) -> Option<&rendy::mesh::Mesh<Self>> {
-                    if let Mesh::$variant(inner) = mesh {
+                    if let Mesh::$variant(inner, _) = mesh {
                         Some(inner)
                     } else {
                         None
@@ -79,7 +95,11 @@ macro_rules! impl_backends {
                 }
                 #[inline]
                 fn wrap_mesh(mesh: rendy::mesh::Mesh<Self>) -> Mesh {
-                    Mesh::$variant(mesh)
+                    Mesh::$variant(mesh, false)
+                }
+                #[inline]
+                fn wrap_indexed_mesh(mesh: rendy::mesh::Mesh<Self>, indexed: bool) -> Mesh {
+                    Mesh::$variant(mesh, indexed)
                 }
                 #[inline]
                 fn wrap_texture(texture: rendy::texture::Texture<Self>) -> Texture {
@@ -140,6 +160,14 @@ pub struct MeshData(
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextureData(pub rendy::texture::TextureBuilder<'static>);
 
+impl MeshData {
+    /// Whether the mesh will be built with an index buffer.
+    pub fn is_indexed(&self) -> bool {
+        // The builder doesn't expose its indices, but serializes them as an `Option`.
+        serde::Serialize::serialize(&self.0, IndicesProbe).unwrap_or(false)
+    }
+}
+
 impl From<rendy::mesh::MeshBuilder<'static>> for MeshData {
     fn from(builder: rendy::mesh::MeshBuilder<'static>) -> Self {
         Self(builder)
@@ -158,3 +186,181 @@ where
 {
     Ok(rendy::mesh::MeshBuilder::deserialize(deserializer)?.into_owned())
 }
+
+// Serializer answering whether the `indices` field of a `MeshBuilder` is set, without
+// serializing any of its data.
+struct IndicesProbe;
+
+struct IndicesField(Option<bool>);
+
+struct IsSome;
+
+#[derive(Debug)]
+struct NotAMeshBuilder;
+
+impl std::fmt::Display for NotAMeshBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("not a mesh builder")
+    }
+}
+
+impl std::error::Error for NotAMeshBuilder {}
+
+impl serde::ser::Error for NotAMeshBuilder {
+    fn custom<T: std::fmt::Display>(_: T) -> Self {
+        NotAMeshBuilder
+    }
+}
+
+macro_rules! reject {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, NotAMeshBuilder> {
+                Err(NotAMeshBuilder)
+            }
+        )*
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(
+            self,
+            _: &'static str,
+            _: &T,
+        ) -> Result<bool, NotAMeshBuilder> {
+            Err(NotAMeshBuilder)
+        }
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: &T,
+        ) -> Result<bool, NotAMeshBuilder> {
+            Err(NotAMeshBuilder)
+        }
+    };
+}
+
+macro_rules! reject_values {
+    () => {
+        reject! {
+            serialize_bool(bool) -> bool;
+            serialize_i8(i8) -> bool;
+            serialize_i16(i16) -> bool;
+            serialize_i32(i32) -> bool;
+            serialize_i64(i64) -> bool;
+            serialize_u8(u8) -> bool;
+            serialize_u16(u16) -> bool;
+            serialize_u32(u32) -> bool;
+            serialize_u64(u64) -> bool;
+            serialize_f32(f32) -> bool;
+            serialize_f64(f64) -> bool;
+            serialize_char(char) -> bool;
+            serialize_str(&str) -> bool;
+            serialize_bytes(&[u8]) -> bool;
+            serialize_unit() -> bool;
+            serialize_unit_struct(&'static str) -> bool;
+            serialize_unit_variant(&'static str, u32, &'static str) -> bool;
+            serialize_seq(Option<usize>) -> Self::SerializeSeq;
+            serialize_tuple(usize) -> Self::SerializeTuple;
+            serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+            serialize_tuple_variant(&'static str, u32, &'static str, usize)
+                -> Self::SerializeTupleVariant;
+            serialize_map(Option<usize>) -> Self::SerializeMap;
+            serialize_struct_variant(&'static str, u32, &'static str, usize)
+                -> Self::SerializeStructVariant;
+        }
+    };
+}
+
+impl serde::Serializer for IndicesProbe {
+    type Ok = bool;
+    type Error = NotAMeshBuilder;
+    type SerializeSeq = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeTuple = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeTupleStruct = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeTupleVariant = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeMap = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeStruct = IndicesField;
+    type SerializeStructVariant = serde::ser::Impossible<bool, NotAMeshBuilder>;
+
+    reject_values!();
+
+    fn serialize_none(self) -> Result<bool, NotAMeshBuilder> {
+        Err(NotAMeshBuilder)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<bool, NotAMeshBuilder> {
+        Err(NotAMeshBuilder)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<IndicesField, NotAMeshBuilder> {
+        Ok(IndicesField(None))
+    }
+}
+
+impl serde::ser::SerializeStruct for IndicesField {
+    type Ok = bool;
+    type Error = NotAMeshBuilder;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NotAMeshBuilder> {
+        if key == "indices" {
+            self.0 = Some(value.serialize(IsSome)?);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<bool, NotAMeshBuilder> {
+        self.0.ok_or(NotAMeshBuilder)
+    }
+}
+
+impl serde::Serializer for IsSome {
+    type Ok = bool;
+    type Error = NotAMeshBuilder;
+    type SerializeSeq = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeTuple = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeTupleStruct = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeTupleVariant = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeMap = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeStruct = serde::ser::Impossible<bool, NotAMeshBuilder>;
+    type SerializeStructVariant = serde::ser::Impossible<bool, NotAMeshBuilder>;
+
+    reject_values!();
+
+    fn serialize_none(self) -> Result<bool, NotAMeshBuilder> {
+        Ok(false)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<bool, NotAMeshBuilder> {
+        Ok(true)
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStruct, NotAMeshBuilder> {
+        Err(NotAMeshBuilder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendy::mesh::{MeshBuilder, Position};
+
+    #[test]
+    fn mesh_data_knows_if_it_is_indexed() {
+        let vertices = vec![Position([0.0, 0.0, 0.0]); 3];
+        let plain = MeshData(MeshBuilder::new().with_vertices(vertices.clone()));
+        let indexed = MeshData(
+            MeshBuilder::new()
+                .with_vertices(vertices)
+                .with_indices(vec![0u16, 1, 2]),
+        );
+        assert!(!plain.is_indexed());
+        assert!(indexed.is_indexed());
+    }
+}
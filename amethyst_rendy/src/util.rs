@@ -1,20 +1,26 @@
 //! Misc. rendy and rendering utility functions and types.
-use crate::types::{Backend, Texture};
+use crate::{
+    error::PassError,
+    types::{Backend, Texture},
+};
 use amethyst_core::num::PrimInt;
 use core::{
+    convert::TryInto,
     hash::Hash,
     iter::{DoubleEndedIterator, ExactSizeIterator, FusedIterator},
     ops::{Add, Range},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 use derivative::Derivative;
 use glsl_layout::*;
 use rendy::{
     factory::Factory,
     graph::render::PrepareResult,
-    hal::{self, buffer::Usage, format, pso},
+    hal::{self, buffer::Usage, device::Device, format, pso},
     memory::MemoryUsage,
     mesh::VertexFormat,
     resource::{BufferInfo, Escape},
+    shader::{Shader, SpirvShader},
 };
 use smallvec::SmallVec;
 
@@ -43,6 +49,8 @@ pub fn usize_range(range: Range<u64>) -> Range<usize> {
 /// their use. This function will either allocate a new buffer, resize the current buffer, or perform
 /// no action depending on the needs of the function call. This can be used for dynamic buffer
 /// allocation or single static buffer allocation.
+///
+/// Allocations are counted in `RenderStats::buffer_allocations`.
 pub fn ensure_buffer<B: Backend>(
     factory: &Factory<B>,
     buffer: &mut Option<Escape<rendy::resource::Buffer<B>>>,
@@ -63,12 +71,20 @@ pub fn ensure_buffer<B: Backend>(
             memory_usage,
         )?;
         *buffer = Some(new_buffer);
+        BUFFER_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
         Ok(true)
     } else {
         Ok(false)
     }
 }
 
+static BUFFER_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of buffers allocated by [ensure_buffer] since the last call.
+pub(crate) fn take_buffer_allocations() -> usize {
+    BUFFER_ALLOCATIONS.swap(0, Ordering::Relaxed)
+}
+
 /// Helper function for memory alignment.
 pub fn align_size<T: AsStd140>(align: u64, array_len: usize) -> u64
 where
@@ -78,6 +94,49 @@ where
     ((size + align - 1) / align) * align
 }
 
+/// Create the shader modules of the pass named `pass`, in order. When one of them fails, the
+/// modules created before it are destroyed.
+///
+/// # Safety
+///
+/// The returned modules must be destroyed with `Factory::destroy_shader_module`.
+pub unsafe fn shader_modules<B: Backend, const N: usize>(
+    factory: &Factory<B>,
+    pass: &'static str,
+    shaders: [&SpirvShader; N],
+) -> Result<[B::ShaderModule; N], PassError> {
+    match shader_module_vec(factory, pass, &shaders)?.try_into() {
+        Ok(modules) => Ok(modules),
+        Err(_) => unreachable!("Created one module per shader"),
+    }
+}
+
+/// Create the shader modules of the pass named `pass` like [shader_modules], for a number of
+/// shaders only known at runtime.
+///
+/// # Safety
+///
+/// The returned modules must be destroyed with `Factory::destroy_shader_module`.
+pub unsafe fn shader_module_vec<B: Backend>(
+    factory: &Factory<B>,
+    pass: &'static str,
+    shaders: &[&SpirvShader],
+) -> Result<Vec<B::ShaderModule>, PassError> {
+    let mut modules = Vec::with_capacity(shaders.len());
+    for shader in shaders {
+        match shader.module(factory) {
+            Ok(module) => modules.push(module),
+            Err(e) => {
+                for module in modules {
+                    factory.destroy_shader_module(module);
+                }
+                return Err(PassError::shader(pass, shader.stage(), e));
+            }
+        }
+    }
+    Ok(modules)
+}
+
 /// Helper function to create a `GraphicsShaderSet`
 pub fn simple_shader_set<'a, B: Backend>(
     vertex: &'a B::ShaderModule,
@@ -190,6 +249,18 @@ pub fn texture_desc<B: Backend>(
     })
 }
 
+/// Helper function to create a `CombinedImageSampler` from a supplied `Texture` and `Layout`,
+/// sampled with another sampler than its own.
+#[inline]
+pub fn texture_desc_with_sampler<'a, B: Backend>(
+    texture: &'a Texture,
+    layout: hal::image::Layout,
+    sampler: &'a B::Sampler,
+) -> Option<pso::Descriptor<'a, B>> {
+    B::unwrap_texture(texture)
+        .map(|inner| pso::Descriptor::CombinedImageSampler(inner.view().raw(), layout, sampler))
+}
+
 /// Combines an iterator of descriptor information in tuple form into a `DescriptorSetLayoutBinding`
 /// # Limitations
 /// * All descriptors are created as single count and immutable_samplers is false.
@@ -239,6 +310,11 @@ impl<I: Hash + Eq> LookupBuilder<I> {
             id_num
         }
     }
+
+    /// Return the supplied Id from the table, without inserting it.
+    pub fn get(&self, id: &I) -> Option<usize> {
+        self.forward.get(id).copied()
+    }
 }
 
 /// Convert any type slice to bytes slice.
@@ -0,0 +1,238 @@
+//! Binding of cameras to render targets.
+//!
+//! A [View] renders the scene seen by one camera into a region of a render target. Views are
+//! listed in the [Views] resource and drawn in ascending `order` by the `RenderViews` plugin,
+//! which expands every view into the passes of the standard forward pipeline. This covers the
+//! common split screen, mirror and minimap setups, while custom pipelines can ignore views and
+//! keep using the `ActiveCamera`.
+//!
+//! Every view is culled separately by the `VisibilitySortingSystem` into [ViewVisibility].
+
+use crate::{bundle::Target, error::RenderError, rendy::hal::pso::Rect, visibility::Visibility};
+use amethyst_core::ecs::prelude::{Component, DenseVecStorage, Entity};
+use fnv::FnvHashMap;
+
+/// Region of a render target, in fractions of its size with the origin at the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Left edge.
+    pub x: f32,
+    /// Top edge.
+    pub y: f32,
+    /// Width.
+    pub width: f32,
+    /// Height.
+    pub height: f32,
+}
+
+impl Viewport {
+    /// Viewport covering the whole target.
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    /// Create a viewport from fractions of the target size.
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Pixel rectangle of the viewport in a framebuffer of given size.
+    pub fn rect(&self, framebuffer_width: u32, framebuffer_height: u32) -> Rect {
+        let (fw, fh) = (framebuffer_width as f32, framebuffer_height as f32);
+        let x = (self.x * fw).round().max(0.0).min(fw);
+        let y = (self.y * fh).round().max(0.0).min(fh);
+        let right = ((self.x + self.width) * fw).round().max(x).min(fw);
+        let bottom = ((self.y + self.height) * fh).round().max(y).min(fh);
+        Rect {
+            x: x as i16,
+            y: y as i16,
+            w: (right - x) as i16,
+            h: (bottom - y) as i16,
+        }
+    }
+
+    /// Check if two viewports share any area.
+    pub fn overlaps(&self, other: &Viewport) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::FULL
+    }
+}
+
+/// What happens to the viewport of a view before it is drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ViewClear {
+    /// Keep the contents, e.g. to draw over another view.
+    #[default]
+    Load,
+    /// Clear the color to given linear RGBA and the depth to the far plane.
+    Clear([f32; 4]),
+}
+
+/// Layers an entity belongs to, as a bit mask. Views only draw entities sharing at least one
+/// layer with their `layers` mask. Entities without this component are on `RenderLayers::DEFAULT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLayers(pub u32);
+
+impl RenderLayers {
+    /// Layer of entities without `RenderLayers`.
+    pub const DEFAULT: RenderLayers = RenderLayers(1);
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers::DEFAULT
+    }
+}
+
+impl Component for RenderLayers {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// A camera rendering into a region of a render target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct View {
+    /// Entity with the `Camera` and `Transform` components to render with.
+    pub camera: Entity,
+    /// Target the view is rendered to.
+    pub target: Target,
+    /// Region of the target the view covers.
+    pub viewport: Viewport,
+    /// How the viewport is cleared before drawing.
+    pub clear: ViewClear,
+    /// Mask of the `RenderLayers` drawn by this view.
+    pub layers: u32,
+    /// Views are drawn in ascending order, views with equal order in the order they are listed.
+    pub order: i32,
+}
+
+impl View {
+    /// Render given camera into the whole target, drawing all layers over its contents.
+    pub fn new(camera: Entity, target: Target) -> Self {
+        Self {
+            camera,
+            target,
+            viewport: Viewport::FULL,
+            clear: ViewClear::Load,
+            layers: !0,
+            order: 0,
+        }
+    }
+
+    /// Render into given region of the target.
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Set how the viewport is cleared before drawing.
+    pub fn with_clear(mut self, clear: ViewClear) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    /// Only draw entities on given `RenderLayers` mask.
+    pub fn with_layers(mut self, layers: u32) -> Self {
+        self.layers = layers;
+        self
+    }
+
+    /// Set the order of the view.
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+/// Resource listing all views rendered by the `RenderViews` plugin.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Views(pub Vec<View>);
+
+impl Views {
+    /// Indices of the views in drawing order.
+    ///
+    /// Results in an error when two views write overlapping regions of the same target with
+    /// different clear settings, as whichever clears last would erase part of the other.
+    pub fn ordered(&self) -> Result<Vec<usize>, RenderError> {
+        let mut order = (0..self.0.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| self.0[i].order);
+
+        for (i, a) in self.0.iter().enumerate() {
+            for (j, b) in self.0.iter().enumerate().skip(i + 1) {
+                if a.target == b.target && a.clear != b.clear && a.viewport.overlaps(&b.viewport) {
+                    return Err(RenderError::OverlappingViews {
+                        first: i,
+                        second: j,
+                        target: a.target,
+                        clears: (a.clear, b.clear),
+                    });
+                }
+            }
+        }
+        Ok(order)
+    }
+}
+
+/// Visible entities of every view, keyed by its index in [Views].
+#[derive(Debug, Default)]
+pub struct ViewVisibility(pub FnvHashMap<usize, Visibility>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amethyst_core::ecs::{Builder, World, WorldExt};
+
+    #[test]
+    fn viewport_rect_and_overlap() {
+        let left = Viewport::new(0.0, 0.0, 0.5, 1.0);
+        let right = Viewport::new(0.5, 0.0, 0.5, 1.0);
+        assert_eq!(
+            right.rect(101, 50),
+            Rect {
+                x: 51,
+                y: 0,
+                w: 50,
+                h: 50
+            }
+        );
+        assert!(!left.overlaps(&right));
+        assert!(Viewport::FULL.overlaps(&right));
+    }
+
+    #[test]
+    fn views_are_ordered_and_validated() {
+        let mut world = World::new();
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+
+        let clear = ViewClear::Clear([0.0, 0.0, 0.0, 1.0]);
+        let mut views = Views(vec![
+            View::new(a, Target::Main).with_order(1),
+            View::new(b, Target::Main)
+                .with_viewport(Viewport::new(0.0, 0.0, 0.5, 1.0))
+                .with_clear(clear),
+            View::new(b, Target::Custom("minimap")).with_clear(clear),
+        ]);
+        // Full screen view loads over a half that clears.
+        assert!(views.ordered().is_err());
+
+        views.0[1].viewport = Viewport::new(0.5, 0.0, 0.5, 1.0);
+        views.0[0].viewport = Viewport::new(0.0, 0.0, 0.5, 1.0);
+        assert_eq!(views.ordered().unwrap(), vec![1, 2, 0]);
+    }
+}
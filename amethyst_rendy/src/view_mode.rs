@@ -0,0 +1,165 @@
+//! Debug views of the scene.
+//!
+//! The [ViewModes] resource selects how the 3D passes draw the scene, e.g. to inspect its
+//! wireframe or how many lights reach every surface. Every mode other than [ViewMode::Lit] is
+//! drawn by the passes of the `RenderViewModes` plugin, which stay in the render graph with
+//! their pipelines built, so switching modes at runtime is free. The 3D passes of the other
+//! plugins only draw in [ViewMode::Lit] and [ViewMode::WireframeOverlay], which draws the edges
+//! over the lit scene. Skyboxes, sprites, post-processing, debug lines and UI draw in every
+//! mode.
+
+use amethyst_core::ecs::{Read, SystemData, World};
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+
+/// View of the scene drawn by the 3D passes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Derivative)]
+#[derivative(Default)]
+pub enum ViewMode {
+    /// Scene lit by the 3D plugins, as in the game.
+    #[derivative(Default)]
+    Lit,
+    /// Albedo and tint of the materials, without lighting.
+    Unlit,
+    /// Edges of the triangles. Requires support for non-fill polygon modes.
+    Wireframe,
+    /// World space vertex normals, remapped to colors.
+    Normals,
+    /// Number of fragments drawn on every pixel, brighter when drawn more often, ignoring the
+    /// depth test.
+    Overdraw,
+    /// Number of lights reaching every surface, from blue for none to red for eight or more.
+    LightComplexity,
+    /// Distance to the camera, darker further away.
+    Depth,
+    /// Surfaces covered by the shadow map in green, darker in its shadows, and the others in
+    /// red. There is a single cascade, and the mode is only supported with `RenderShadows`.
+    ShadowCascades,
+    /// Lit scene with the edges of its visible triangles drawn over it. Requires support for
+    /// non-fill polygon modes.
+    WireframeOverlay,
+}
+
+impl ViewMode {
+    /// Every view mode, in order.
+    pub const ALL: [ViewMode; 9] = [
+        ViewMode::Lit,
+        ViewMode::Unlit,
+        ViewMode::Wireframe,
+        ViewMode::Normals,
+        ViewMode::Overdraw,
+        ViewMode::LightComplexity,
+        ViewMode::Depth,
+        ViewMode::ShadowCascades,
+        ViewMode::WireframeOverlay,
+    ];
+
+    /// Human readable name of the mode, e.g. to label statistics.
+    pub fn name(self) -> &'static str {
+        match self {
+            ViewMode::Lit => "Lit",
+            ViewMode::Unlit => "Unlit",
+            ViewMode::Wireframe => "Wireframe",
+            ViewMode::Normals => "Normals",
+            ViewMode::Overdraw => "Overdraw",
+            ViewMode::LightComplexity => "Light complexity",
+            ViewMode::Depth => "Depth",
+            ViewMode::ShadowCascades => "Shadow cascades",
+            ViewMode::WireframeOverlay => "Wireframe overlay",
+        }
+    }
+
+    /// Whether the passes drawing in the `pass` mode draw while the scene is shown in this mode.
+    pub fn draws(self, pass: ViewMode) -> bool {
+        self == pass || (self == ViewMode::WireframeOverlay && pass == ViewMode::Lit)
+    }
+
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Resource selecting the [ViewMode] of the scene at runtime, e.g. bound to function keys.
+///
+/// Selecting a mode no pass can draw falls back to [ViewMode::Lit].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewModes {
+    /// Selected view mode.
+    pub mode: ViewMode,
+    supported: u32,
+}
+
+impl Default for ViewModes {
+    fn default() -> Self {
+        Self {
+            mode: ViewMode::Lit,
+            supported: ViewMode::Lit.bit(),
+        }
+    }
+}
+
+impl ViewModes {
+    /// Whether any pass draws the scene in given mode.
+    pub fn supports(&self, mode: ViewMode) -> bool {
+        self.supported & mode.bit() != 0
+    }
+
+    /// View mode the scene is drawn in, the selected one when it is supported.
+    pub fn active(&self) -> ViewMode {
+        if self.supports(self.mode) {
+            self.mode
+        } else {
+            ViewMode::Lit
+        }
+    }
+
+    /// Select the next supported view mode, wrapping around to [ViewMode::Lit] after the last.
+    pub fn cycle(&mut self) {
+        let current = self.active() as usize;
+        self.mode = (1..ViewMode::ALL.len())
+            .map(|offset| ViewMode::ALL[(current + offset) % ViewMode::ALL.len()])
+            .find(|&mode| self.supports(mode))
+            .unwrap_or(ViewMode::Lit);
+    }
+
+    pub(crate) fn set_supported(&mut self, modes: impl IntoIterator<Item = ViewMode>) {
+        self.supported = modes
+            .into_iter()
+            .fold(ViewMode::Lit.bit(), |bits, mode| bits | mode.bit());
+    }
+}
+
+/// View mode the scene is drawn in, `ViewMode::Lit` without a `ViewModes` resource.
+pub(crate) fn active_view_mode(world: &World) -> ViewMode {
+    <Option<Read<'_, ViewModes>>>::fetch(world).map_or(ViewMode::Lit, |m| m.active())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_modes_fall_back_to_lit() {
+        let mut modes = ViewModes {
+            mode: ViewMode::Wireframe,
+            ..Default::default()
+        };
+        assert_eq!(modes.active(), ViewMode::Lit);
+
+        modes.set_supported(vec![ViewMode::Wireframe, ViewMode::Depth]);
+        assert_eq!(modes.active(), ViewMode::Wireframe);
+        modes.cycle();
+        assert_eq!(modes.mode, ViewMode::Depth);
+        modes.cycle();
+        assert_eq!(modes.mode, ViewMode::Lit);
+    }
+
+    #[test]
+    fn wireframe_overlay_draws_the_lit_scene() {
+        let overlay = ViewMode::WireframeOverlay;
+        assert!(overlay.draws(ViewMode::Lit) && overlay.draws(overlay));
+        assert!(!overlay.draws(ViewMode::Wireframe));
+        assert!(!ViewMode::Lit.draws(overlay));
+        assert!(!ViewMode::Wireframe.draws(ViewMode::Lit));
+    }
+}
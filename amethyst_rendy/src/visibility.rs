@@ -1,25 +1,33 @@
 //! Transparency, visibility sorting and camera centroid culling for 3D Meshes.
 use crate::{
-    camera::{ActiveCamera, Camera},
+    camera::{ActiveCamera, Camera, FrozenCamera},
+    impostor::Impostor,
+    stats::RenderStats,
     transparent::Transparent,
+    view::{RenderLayers, ViewVisibility, Views},
 };
 use amethyst_core::{
     ecs::{
         hibitset::BitSet,
         prelude::{
-            Component, DenseVecStorage, Entities, Entity, Join, Read, ReadStorage, System, Write,
+            Component, DenseVecStorage, Entities, Entity, Join, ParJoin, ParallelIterator, Read,
+            ReadStorage, System, Write,
         },
+        rayon::{self, iter::ParallelExtend},
     },
-    math::{convert, distance_squared, Matrix4, Point3, Vector4},
+    math::{distance_squared, Point3},
     Hidden, HiddenPropagate, Transform,
 };
 
+use fnv::FnvHashMap;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
+pub use crate::frustum::Frustum;
+
 /// Resource for controlling what entities should be rendered, and whether to draw them ordered or
 /// not, which is useful for transparent surfaces.
 #[derive(Default, Debug)]
@@ -28,19 +36,124 @@ pub struct Visibility {
     pub visible_unordered: BitSet,
     /// Visible entities that need to be drawn in the given order
     pub visible_ordered: Vec<Entity>,
+    /// Visible entities that are drawn as impostors instead of their mesh.
+    pub impostors: BitSet,
+    /// Entities close enough to the camera to be drawn into shadow maps.
+    /// Not frustum culled, as shadow casters outside of the view can still cast visible shadows.
+    pub shadow_casters: BitSet,
+    /// Opacity of ordered entities that are fading out near their `DrawDistance`.
+    /// Entities not present here are fully opaque.
+    pub fade: FnvHashMap<Entity, f32>,
+}
+
+impl Visibility {
+    /// Opacity factor of a visible entity, `1.0` unless it's fading out.
+    pub fn fade(&self, entity: Entity) -> f32 {
+        self.fade.get(&entity).copied().unwrap_or(1.0)
+    }
+}
+
+/// Fraction of the draw distance over which entities are faded out when
+/// `DrawDistanceSettings::fade` is enabled.
+pub const DRAW_DISTANCE_FADE_RANGE: f32 = 0.1;
+
+/// Limits the distance from the camera at which an entity is still drawn,
+/// even if it's inside of the view frustum.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawDistance {
+    /// Maximum distance between the camera and the entity's bounding sphere center.
+    pub max: f32,
+    /// Maximum distance at which the entity is drawn into shadow maps. Defaults to `max`.
+    #[serde(default)]
+    pub max_shadow: Option<f32>,
+}
+
+impl DrawDistance {
+    /// Create a new `DrawDistance` with the given limit.
+    pub fn new(max: f32) -> Self {
+        Self {
+            max,
+            max_shadow: None,
+        }
+    }
+
+    /// Use a separate, usually shorter, limit for shadow maps.
+    pub fn with_max_shadow(mut self, max_shadow: f32) -> Self {
+        self.max_shadow = Some(max_shadow);
+        self
+    }
+
+    /// Distance limit for shadow maps.
+    pub fn shadow_limit(&self) -> f32 {
+        self.max_shadow.unwrap_or(self.max)
+    }
+}
+
+impl Component for DrawDistance {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Resource controlling the `DrawDistance` limits of all entities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrawDistanceSettings {
+    /// Scale factor applied to all draw distances, e.g. from a graphics quality setting.
+    pub scale: f32,
+    /// Additional scale factor applied to shadow distances.
+    pub shadow_scale: f32,
+    /// Fade out transparent entities over the last `DRAW_DISTANCE_FADE_RANGE` of their draw
+    /// distance instead of popping out of view. Opaque entities are never faded.
+    pub fade: bool,
+}
+
+impl Default for DrawDistanceSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            shadow_scale: 1.0,
+            fade: false,
+        }
+    }
 }
 
 /// Determine what entities are visible to the camera, and which are not. Will also sort transparent
 /// entities back to front based on distance from camera.
 ///
+/// Entities are culled by the camera frustum and by their `DrawDistance`, both counted
+/// separately in `RenderStats`.
+///
+/// Every view in the `Views` resource is culled again with its own camera and layer mask into
+/// `ViewVisibility`. Only the culling of the active camera is counted in `RenderStats`.
+///
+/// Scenes with more entities than the parallel threshold are culled in chunks on the thread
+/// pool of the dispatcher, if it has more than one thread. Chunks are merged back in entity
+/// order, so the result is the same as when culling serially.
+///
 /// Note that this should run after `Transform` has been updated for the current frame, and
 /// before rendering occurs.
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct VisibilitySortingSystem {
-    centroids: Vec<Internals>,
+    culled: Culled,
+    chunks: Vec<Culled>,
     transparent: Vec<Internals>,
+    parallel_threshold: usize,
+}
+
+impl Default for VisibilitySortingSystem {
+    fn default() -> Self {
+        Self {
+            culled: Culled::default(),
+            chunks: Vec::new(),
+            transparent: Vec::new(),
+            parallel_threshold: PARALLEL_CULLING_THRESHOLD,
+        }
+    }
 }
 
+/// Default number of entities with a `Transform` above which `VisibilitySortingSystem` culls in
+/// parallel. Below it, splitting the work costs more than it saves.
+pub const PARALLEL_CULLING_THRESHOLD: usize = 4096;
+
 /// Defines a object's bounding sphere used by frustum culling.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BoundingSphere {
@@ -78,12 +191,41 @@ impl Component for BoundingSphere {
     type Storage = DenseVecStorage<Self>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 struct Internals {
     entity: Entity,
     transparent: bool,
     centroid: Point3<f32>,
     camera_distance: f32,
+    fade: f32,
+}
+
+/// Output of culling a chunk of entities, in entity order.
+#[derive(Debug, Default)]
+struct Culled {
+    visible: Vec<Internals>,
+    shadow_casters: Vec<u32>,
+    impostors: Vec<u32>,
+    frustum_culled: usize,
+    distance_culled: usize,
+}
+
+impl Culled {
+    fn clear(&mut self) {
+        self.visible.clear();
+        self.shadow_casters.clear();
+        self.impostors.clear();
+        self.frustum_culled = 0;
+        self.distance_culled = 0;
+    }
+
+    fn append(&mut self, other: &mut Culled) {
+        self.visible.append(&mut other.visible);
+        self.shadow_casters.append(&mut other.shadow_casters);
+        self.impostors.append(&mut other.impostors);
+        self.frustum_culled += other.frustum_culled;
+        self.distance_culled += other.distance_culled;
+    }
 }
 
 impl VisibilitySortingSystem {
@@ -91,6 +233,215 @@ impl VisibilitySortingSystem {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Cull in parallel when more than `threshold` entities have a `Transform`.
+    /// Use `usize::MAX` to always cull serially, or `0` to always cull in parallel.
+    pub fn with_parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = threshold;
+        self
+    }
+}
+
+/// Camera state shared by the culling of every entity, read from all threads.
+struct CullView<'s, 'a> {
+    origin: Point3<f32>,
+    camera_centroid: Point3<f32>,
+    frustum: Frustum,
+    layers: Option<u32>,
+    settings: &'s DrawDistanceSettings,
+    transparent: &'s ReadStorage<'a, Transparent>,
+}
+
+impl CullView<'_, '_> {
+    /// Cull a single entity into `out`: layer and distance tests, frustum test, then impostor
+    /// selection.
+    fn cull(
+        &self,
+        out: &mut Culled,
+        (entity, transform, sphere, draw_distance, impostor, entity_layers): (
+            Entity,
+            &Transform,
+            Option<&BoundingSphere>,
+            Option<&DrawDistance>,
+            Option<&Impostor>,
+            Option<&RenderLayers>,
+        ),
+    ) {
+        if let Some(mask) = self.layers {
+            if entity_layers.copied().unwrap_or_default().0 & mask == 0 {
+                return;
+            }
+        }
+
+        let settings = self.settings;
+        let pos = sphere.map_or(&self.origin, |s| &s.center);
+        let matrix = transform.global_matrix();
+        let centroid = matrix.transform_point(&pos);
+        let radius = sphere.map_or(1.0, |s| s.radius)
+            * matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]);
+        let camera_distance = distance_squared(&centroid, &self.camera_centroid);
+
+        let mut fade = 1.0;
+        if let Some(limit) = draw_distance {
+            let distance = camera_distance.sqrt();
+            if distance <= limit.shadow_limit() * settings.scale * settings.shadow_scale {
+                out.shadow_casters.push(entity.id());
+            }
+            let max = limit.max * settings.scale;
+            if distance > max {
+                out.distance_culled += 1;
+                return;
+            }
+            if settings.fade {
+                fade = ((max - distance) / (max * DRAW_DISTANCE_FADE_RANGE)).min(1.0);
+            }
+        } else {
+            out.shadow_casters.push(entity.id());
+        }
+
+        if !self.frustum.check_sphere(&centroid, radius) {
+            out.frustum_culled += 1;
+            return;
+        }
+        if impostor.is_some_and(|i| camera_distance > i.transition_distance.powi(2)) {
+            out.impostors.push(entity.id());
+            return;
+        }
+        out.visible.push(Internals {
+            entity,
+            transparent: self.transparent.contains(entity),
+            centroid,
+            camera_distance,
+            fade,
+        });
+    }
+}
+
+struct SortInput<'s, 'a> {
+    entities: &'s Entities<'a>,
+    hidden: &'s ReadStorage<'a, Hidden>,
+    hidden_prop: &'s ReadStorage<'a, HiddenPropagate>,
+    transparent: &'s ReadStorage<'a, Transparent>,
+    transform: &'s ReadStorage<'a, Transform>,
+    bound: &'s ReadStorage<'a, BoundingSphere>,
+    draw_distance: &'s ReadStorage<'a, DrawDistance>,
+    settings: &'s DrawDistanceSettings,
+    impostor: &'s ReadStorage<'a, Impostor>,
+    layers: &'s ReadStorage<'a, RenderLayers>,
+}
+
+impl VisibilitySortingSystem {
+    /// Fill `visibility` with the entities seen by given camera, skipping entities on none of
+    /// the `layers` if given. Returns the number of frustum and distance culled entities.
+    fn sort(
+        &mut self,
+        input: &SortInput<'_, '_>,
+        (camera, camera_transform): (&Camera, &Transform),
+        layers: Option<u32>,
+        visibility: &mut Visibility,
+    ) -> (usize, usize) {
+        let origin = Point3::origin();
+        let view = CullView {
+            origin,
+            camera_centroid: camera_transform.global_matrix().transform_point(&origin),
+            frustum: Frustum::from_camera(camera, camera_transform),
+            layers,
+            settings: input.settings,
+            transparent: input.transparent,
+        };
+
+        let join = || {
+            (
+                input.entities,
+                input.transform,
+                input.bound.maybe(),
+                input.draw_distance.maybe(),
+                input.impostor.maybe(),
+                input.layers.maybe(),
+                !input.hidden,
+                !input.hidden_prop,
+            )
+        };
+
+        self.culled.clear();
+        if rayon::current_num_threads() > 1 && input.transform.count() > self.parallel_threshold {
+            #[cfg(feature = "profiler")]
+            profile_scope!("parallel_cull");
+
+            // Rayon keeps the order of the folded chunks when collecting them, so appending
+            // them in turn gives the same order as a serial join.
+            self.chunks.clear();
+            self.chunks.par_extend(join().par_join().fold(
+                Culled::default,
+                |mut chunk, (entity, transform, sphere, draw_distance, impostor, layers, _, _)| {
+                    view.cull(
+                        &mut chunk,
+                        (entity, transform, sphere, draw_distance, impostor, layers),
+                    );
+                    chunk
+                },
+            ));
+            for chunk in &mut self.chunks {
+                self.culled.append(chunk);
+            }
+        } else {
+            for (entity, transform, sphere, draw_distance, impostor, layers, _, _) in join().join()
+            {
+                view.cull(
+                    &mut self.culled,
+                    (entity, transform, sphere, draw_distance, impostor, layers),
+                );
+            }
+        }
+
+        visibility.shadow_casters.clear();
+        visibility
+            .shadow_casters
+            .extend(self.culled.shadow_casters.iter().copied());
+        visibility.impostors.clear();
+        visibility
+            .impostors
+            .extend(self.culled.impostors.iter().copied());
+
+        self.transparent.clear();
+        self.transparent.extend(
+            self.culled
+                .visible
+                .iter()
+                .filter(|c| c.transparent)
+                .cloned(),
+        );
+
+        self.transparent.sort_by(|a, b| {
+            b.camera_distance
+                .partial_cmp(&a.camera_distance)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        visibility.visible_unordered.clear();
+        visibility.visible_unordered.extend(
+            self.culled
+                .visible
+                .iter()
+                .filter(|c| !c.transparent)
+                .map(|c| c.entity.id()),
+        );
+
+        visibility.visible_ordered.clear();
+        visibility
+            .visible_ordered
+            .extend(self.transparent.iter().map(|c| c.entity));
+
+        visibility.fade.clear();
+        visibility.fade.extend(
+            self.transparent
+                .iter()
+                .filter(|c| c.fade < 1.0)
+                .map(|c| (c.entity, c.fade)),
+        );
+
+        (self.culled.frustum_culled, self.culled.distance_culled)
+    }
 }
 
 impl<'a> System<'a> for VisibilitySortingSystem {
@@ -104,6 +455,14 @@ impl<'a> System<'a> for VisibilitySortingSystem {
         ReadStorage<'a, Transparent>,
         ReadStorage<'a, Transform>,
         ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, DrawDistance>,
+        Read<'a, DrawDistanceSettings>,
+        ReadStorage<'a, Impostor>,
+        Write<'a, RenderStats>,
+        Option<Read<'a, Views>>,
+        Write<'a, ViewVisibility>,
+        ReadStorage<'a, RenderLayers>,
+        Write<'a, FrozenCamera>,
     );
 
     fn run(
@@ -118,118 +477,349 @@ impl<'a> System<'a> for VisibilitySortingSystem {
             transparent,
             transform,
             bound,
+            draw_distance,
+            settings,
+            impostor,
+            mut stats,
+            views,
+            mut view_visibility,
+            layers,
+            mut frozen,
         ): Self::SystemData,
     ) {
         #[cfg(feature = "profiler")]
         profile_scope!("visibility_sorting_system");
 
-        let origin = Point3::origin();
         let defcam = Camera::standard_2d(1.0, 1.0);
         let identity = Transform::default();
+        let input = SortInput {
+            entities: &entities,
+            hidden: &hidden,
+            hidden_prop: &hidden_prop,
+            transparent: &transparent,
+            transform: &transform,
+            bound: &bound,
+            draw_distance: &draw_distance,
+            settings: &settings,
+            impostor: &impostor,
+            layers: &layers,
+        };
 
         let mut camera_join = (&camera, &transform).join();
-        let (camera, camera_transform) = active
+        let main_camera = active
             .entity
             .and_then(|a| camera_join.get(a, &entities))
             .or_else(|| camera_join.next())
             .unwrap_or((&defcam, &identity));
+        let main_camera = frozen.update(main_camera);
 
-        let camera_centroid = camera_transform.global_matrix().transform_point(&origin);
-        let frustum = Frustum::new(
-            convert::<_, Matrix4<f32>>(camera.matrix)
-                * camera_transform.global_matrix().try_inverse().unwrap(),
-        );
+        let (frustum_culled, distance_culled) =
+            self.sort(&input, main_camera, None, &mut visibility);
+        stats.frustum_culled = frustum_culled;
+        stats.distance_culled = distance_culled;
 
-        self.centroids.clear();
-        self.centroids.extend(
-            (
-                &*entities,
-                &transform,
-                bound.maybe(),
-                !&hidden,
-                !&hidden_prop,
-            )
+        let views = views.as_ref().map_or(&[][..], |v| &v.0[..]);
+        view_visibility.0.retain(|&index, _| index < views.len());
+        for (index, view) in views.iter().enumerate() {
+            let view_camera = (&camera, &transform)
                 .join()
-                .map(|(entity, transform, sphere, _, _)| {
-                    let pos = sphere.map_or(&origin, |s| &s.center);
-                    let matrix = transform.global_matrix();
-                    (
-                        entity,
-                        matrix.transform_point(&pos),
-                        sphere.map_or(1.0, |s| s.radius)
-                            * matrix[(0, 0)].max(matrix[(1, 1)]).max(matrix[(2, 2)]),
-                    )
-                })
-                .filter(|(_, centroid, radius)| frustum.check_sphere(centroid, *radius))
-                .map(|(entity, centroid, _)| Internals {
-                    entity,
-                    transparent: transparent.contains(entity),
-                    centroid,
-                    camera_distance: distance_squared(&centroid, &camera_centroid),
-                }),
-        );
-        self.transparent.clear();
-        self.transparent
-            .extend(self.centroids.iter().filter(|c| c.transparent).cloned());
+                .get(view.camera, &entities)
+                .unwrap_or(main_camera);
+            let view_visibility = view_visibility.0.entry(index).or_default();
+            self.sort(&input, view_camera, Some(view.layers), view_visibility);
+        }
+    }
+}
 
-        self.transparent.sort_by(|a, b| {
-            b.camera_distance
-                .partial_cmp(&a.camera_distance)
-                .unwrap_or(Ordering::Equal)
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{impostor::ImpostorLayout, texture::uv_gradient_data, types::Texture};
+    use amethyst_assets::{AssetStorage, Loader};
+    use amethyst_core::{
+        ecs::{Builder, DispatcherBuilder, World, WorldExt},
+        SystemBundle, TransformBundle,
+    };
+    use rayon::ThreadPoolBuilder;
+    use std::sync::Arc;
 
-        visibility.visible_unordered.clear();
-        visibility.visible_unordered.extend(
-            self.centroids
-                .iter()
-                .filter(|c| !c.transparent)
-                .map(|c| c.entity.id()),
-        );
+    fn cull(parallel_threshold: usize) -> (Visibility, usize, usize) {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder
+            .with(
+                VisibilitySortingSystem::new().with_parallel_threshold(parallel_threshold),
+                "visibility_system",
+                &["transform_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
 
-        visibility.visible_ordered.clear();
-        visibility
-            .visible_ordered
-            .extend(self.transparent.iter().map(|c| c.entity));
+        let mut camera = Transform::default();
+        camera.set_translation_xyz(0.0, 0.0, 20.0);
+        world
+            .create_entity()
+            .with(Camera::standard_3d(16.0, 9.0))
+            .with(camera)
+            .build();
+        for i in 0..4000 {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(
+                (i % 40) as f32 * 4.0 - 80.0,
+                (i / 40 % 10) as f32 - 5.0,
+                -((i / 400) as f32) * 4.0,
+            );
+            let mut builder = world.create_entity().with(transform);
+            if i % 3 == 0 {
+                builder = builder.with(Transparent);
+            }
+            if i % 5 == 0 {
+                builder = builder.with(DrawDistance::new(30.0).with_max_shadow(25.0));
+            }
+            builder.build();
+        }
+
+        dispatcher.dispatch(&world);
+
+        let visibility = world.remove::<Visibility>().unwrap();
+        let stats = world.read_resource::<RenderStats>();
+        (visibility, stats.frustum_culled, stats.distance_culled)
     }
-}
 
-/// Simple view Frustum implementation
-#[derive(Debug)]
-pub struct Frustum {
-    /// The planes of the frustum
-    pub planes: [Vector4<f32>; 6],
-}
-
-impl Frustum {
-    /// Create a new simple frustum from the provided matrix.
-    pub fn new(matrix: Matrix4<f32>) -> Self {
-        let planes = [
-            (matrix.row(3) + matrix.row(0)).transpose(),
-            (matrix.row(3) - matrix.row(0)).transpose(),
-            (matrix.row(3) - matrix.row(1)).transpose(),
-            (matrix.row(3) + matrix.row(1)).transpose(),
-            (matrix.row(3) + matrix.row(2)).transpose(),
-            (matrix.row(3) - matrix.row(2)).transpose(),
-        ];
-        Self {
-            planes: [
-                planes[0] * (1.0 / planes[0].xyz().magnitude()),
-                planes[1] * (1.0 / planes[1].xyz().magnitude()),
-                planes[2] * (1.0 / planes[2].xyz().magnitude()),
-                planes[3] * (1.0 / planes[3].xyz().magnitude()),
-                planes[4] * (1.0 / planes[4].xyz().magnitude()),
-                planes[5] * (1.0 / planes[5].xyz().magnitude()),
-            ],
+    #[test]
+    fn parallel_culling_matches_serial() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let (serial, serial_frustum, serial_distance) = pool.install(|| cull(usize::MAX));
+        let (parallel, parallel_frustum, parallel_distance) = pool.install(|| cull(0));
+
+        assert!(serial_frustum > 0 && serial_distance > 0);
+        assert!(!serial.visible_ordered.is_empty());
+        assert_eq!(serial_frustum, parallel_frustum);
+        assert_eq!(serial_distance, parallel_distance);
+        assert_eq!(serial.visible_ordered, parallel.visible_ordered);
+        assert_eq!(serial.visible_unordered, parallel.visible_unordered);
+        assert_eq!(serial.shadow_casters, parallel.shadow_casters);
+        assert_eq!(serial.impostors, parallel.impostors);
+        assert_eq!(serial.fade, parallel.fade);
+    }
+
+    #[test]
+    fn draw_distance_culls_fades_and_limits_shadows() {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder
+            .with(
+                VisibilitySortingSystem::new(),
+                "visibility_system",
+                &["transform_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+        world.write_resource::<DrawDistanceSettings>().fade = true;
+
+        let loader = Loader::new(".", Arc::new(ThreadPoolBuilder::new().build().unwrap()));
+        let texture =
+            loader.load_from_data(uv_gradient_data(4), (), &AssetStorage::<Texture>::new());
+        let layout = ImpostorLayout {
+            angles: 8,
+            center: [0.0; 3],
+            radius: 1.0,
+        };
+
+        // The camera looks down -Z from the origin. Like any entity without `DrawDistance`, it
+        // casts shadows.
+        let camera = world
+            .create_entity()
+            .with(Camera::standard_3d(16.0, 9.0))
+            .with(Transform::default())
+            .build();
+        let entity = |world: &mut World, z: f32, transparent: bool, limit: Option<DrawDistance>| {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(0.0, 0.0, z);
+            let mut builder = world.create_entity().with(transform);
+            if transparent {
+                builder = builder.with(Transparent);
+            }
+            if let Some(limit) = limit {
+                builder = builder.with(limit);
+            }
+            builder.build()
+        };
+        let limit = || Some(DrawDistance::new(100.0));
+        let inside = entity(
+            &mut world,
+            -50.0,
+            true,
+            Some(DrawDistance::new(100.0).with_max_shadow(40.0)),
+        );
+        // The fade band covers the last 10% of the draw distance.
+        let fading = entity(&mut world, -95.0, true, limit());
+        let fading_opaque = entity(&mut world, -95.0, false, limit());
+        let beyond = entity(&mut world, -105.0, true, limit());
+        let behind = entity(&mut world, 50.0, false, None);
+        let impostor = entity(&mut world, -50.0, false, limit());
+        world
+            .write_storage::<Impostor>()
+            .insert(impostor, Impostor::new(texture, layout, 30.0))
+            .unwrap();
+
+        dispatcher.dispatch(&world);
+        {
+            let visibility = world.read_resource::<Visibility>();
+            assert_eq!(visibility.visible_ordered, vec![fading, inside]);
+            assert!(visibility.visible_unordered.contains(fading_opaque.id()));
+            assert!(!visibility.visible_unordered.contains(impostor.id()));
+            assert_eq!(visibility.fade(inside), 1.0);
+            assert!((visibility.fade(fading) - 0.5).abs() < 1.0e-4);
+            // Opaque entities are never faded.
+            assert_eq!(visibility.fade.len(), 1);
+            assert_eq!(visibility.fade(fading_opaque), 1.0);
+
+            let impostors = (&visibility.impostors).join().collect::<Vec<_>>();
+            assert_eq!(impostors, vec![impostor.id()]);
+
+            // Shadow casters aren't frustum culled, but follow the shadow limit.
+            let casters = (&visibility.shadow_casters).join().collect::<Vec<_>>();
+            assert_eq!(
+                casters,
+                vec![
+                    camera.id(),
+                    fading.id(),
+                    fading_opaque.id(),
+                    behind.id(),
+                    impostor.id()
+                ]
+            );
+
+            let stats = world.read_resource::<RenderStats>();
+            assert_eq!((stats.frustum_culled, stats.distance_culled), (1, 1));
         }
+
+        // Scaled draw distances bring the entity beyond back, and move the others out of the
+        // fade band. Shadow limits are scaled by both factors, down to 60 units here.
+        world.write_resource::<DrawDistanceSettings>().scale = 2.0;
+        world.write_resource::<DrawDistanceSettings>().shadow_scale = 0.3;
+        dispatcher.dispatch(&world);
+        let visibility = world.read_resource::<Visibility>();
+        assert_eq!(visibility.visible_ordered, vec![beyond, fading, inside]);
+        assert!(visibility.fade.is_empty());
+        let casters = (&visibility.shadow_casters).join().collect::<Vec<_>>();
+        assert_eq!(casters, vec![camera.id(), behind.id(), impostor.id()]);
+        assert_eq!(world.read_resource::<RenderStats>().distance_culled, 0);
     }
 
-    /// Check if the given sphere is within the Frustum
-    pub fn check_sphere(&self, center: &Point3<f32>, radius: f32) -> bool {
-        for plane in &self.planes {
-            if plane.xyz().dot(&center.coords) + plane.w <= -radius {
-                return false;
+    #[test]
+    fn frozen_camera_keeps_culling_from_its_snapshot() {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder
+            .with(
+                VisibilitySortingSystem::new(),
+                "visibility_system",
+                &["transform_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, 20.0);
+        let camera = world
+            .create_entity()
+            .with(Camera::standard_3d(16.0, 9.0))
+            .with(transform)
+            .build();
+        let object = world.create_entity().with(Transform::default()).build();
+
+        world.write_resource::<FrozenCamera>().freeze = true;
+        dispatcher.dispatch(&world);
+        assert!(world
+            .read_resource::<Visibility>()
+            .visible_unordered
+            .contains(object.id()));
+
+        // Turn the live camera away, the frozen one still sees the object.
+        world
+            .write_storage::<Transform>()
+            .get_mut(camera)
+            .unwrap()
+            .set_rotation_y_axis(std::f32::consts::PI);
+        dispatcher.dispatch(&world);
+        assert!(world
+            .read_resource::<Visibility>()
+            .visible_unordered
+            .contains(object.id()));
+
+        world.write_resource::<FrozenCamera>().toggle();
+        dispatcher.dispatch(&world);
+        assert!(!world
+            .read_resource::<Visibility>()
+            .visible_unordered
+            .contains(object.id()));
+        assert!(world.read_resource::<FrozenCamera>().snapshot().is_none());
+    }
+
+    #[test]
+    fn transparent_entities_stay_sorted_back_to_front_around_the_camera() {
+        let mut world = World::new();
+        let mut builder = DispatcherBuilder::new();
+        TransformBundle::new()
+            .build(&mut world, &mut builder)
+            .unwrap();
+        let mut dispatcher = builder
+            .with(
+                VisibilitySortingSystem::new(),
+                "visibility_system",
+                &["transform_system"],
+            )
+            .build();
+        dispatcher.setup(&mut world);
+
+        let camera = world
+            .create_entity()
+            .with(Camera::standard_3d(16.0, 9.0))
+            .with(Transform::default())
+            .build();
+        let quad = |world: &mut World, z: f32| {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(0.0, 0.0, z);
+            world
+                .create_entity()
+                .with(transform)
+                .with(Transparent)
+                .build()
+        };
+        let front = quad(&mut world, 0.5);
+        let back = quad(&mut world, -0.5);
+        let opaque = world.create_entity().with(Transform::default()).build();
+
+        // Orbit the camera around the quads, looking at them.
+        for step in 0..8 {
+            let angle = step as f32 * std::f32::consts::FRAC_PI_4 + 0.1;
+            {
+                let mut transforms = world.write_storage::<Transform>();
+                let transform = transforms.get_mut(camera).unwrap();
+                transform.set_translation_xyz(angle.sin() * 10.0, 0.0, angle.cos() * 10.0);
+                transform.set_rotation_y_axis(angle);
             }
+            dispatcher.dispatch(&world);
+
+            let visibility = world.read_resource::<Visibility>();
+            let expected = if angle.cos() > 0.0 {
+                vec![back, front]
+            } else {
+                vec![front, back]
+            };
+            assert_eq!(visibility.visible_ordered, expected);
+            assert!(visibility.visible_unordered.contains(opaque.id()));
         }
-        true
     }
 }
@@ -48,6 +48,8 @@ use std::cmp::Ordering;
 use thread_profiler::profile_scope;
 
 /// A [RenderPlugin] for rendering UI elements.
+///
+/// UI is drawn on the display target of the selected target, after tonemapping.
 #[derive(Debug, Default)]
 pub struct RenderUi {
     target: Target,
@@ -81,7 +83,7 @@ impl<B: Backend> RenderPlugin<B> for RenderUi {
         _factory: &mut Factory<B>,
         _world: &World,
     ) -> Result<(), Error> {
-        plan.extend_target(self.target, |ctx| {
+        plan.extend_display_target(self.target, |ctx| {
             ctx.add(RenderOrder::Overlay, DrawUiDesc::new().builder())?;
             Ok(())
         });
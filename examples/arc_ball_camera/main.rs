@@ -1,23 +1,28 @@
 //! Demonstrates the arc ball camera
 
 use amethyst::{
-    assets::{PrefabLoader, PrefabLoaderSystemDesc, RonFormat},
+    assets::{AssetStorage, Loader, PrefabLoader, PrefabLoaderSystemDesc, RonFormat},
     controls::{ArcBallControlBundle, ArcBallControlTag},
     core::{
         shrev::{EventChannel, ReaderId},
         transform::{Transform, TransformBundle},
     },
     derive::SystemDesc,
-    ecs::prelude::{Join, Read, ReadStorage, System, SystemData, WorldExt, WriteStorage},
+    ecs::prelude::{
+        Join, Read, ReadExpect, ReadStorage, System, SystemData, WorldExt, WriteStorage,
+    },
     input::{
         is_key_down, InputBundle, InputEvent, ScrollDirection, StringBindings, VirtualKeyCode,
     },
     prelude::*,
     renderer::{
-        palette::Srgb,
+        formats::texture::cubemap_data,
+        palette::{Srgb, Srgba},
+        pass::SkyboxCubemap,
         plugins::{RenderShaded3D, RenderSkybox, RenderToWindow},
         rendy::mesh::{Normal, Position, TexCoord},
-        types::DefaultBackend,
+        texture::checkerboard_pixels,
+        types::{DefaultBackend, Texture},
         RenderingBundle,
     },
     utils::{application_root_dir, scene::BasicScenePrefab},
@@ -34,6 +39,36 @@ impl SimpleState for ExampleState {
             loader.load("prefab/arc_ball_camera.ron", RonFormat, ())
         });
         data.world.create_entity().with(prefab_handle).build();
+
+        // Checkered faces tinted by the axis they look along, staying fixed to the world while
+        // the camera orbits the teapot.
+        let tints = [
+            (1.0, 0.3, 0.3),
+            (0.5, 0.1, 0.1),
+            (0.3, 1.0, 0.3),
+            (0.1, 0.5, 0.1),
+            (0.3, 0.3, 1.0),
+            (0.1, 0.1, 0.5),
+        ];
+        let mut faces: [Vec<_>; 6] = Default::default();
+        for (face, &(r, g, b)) in faces.iter_mut().zip(&tints) {
+            *face = checkerboard_pixels(
+                256,
+                8,
+                Srgba::new(r, g, b, 1.0),
+                Srgba::new(r * 0.8, g * 0.8, b * 0.8, 1.0),
+            );
+        }
+        let cubemap = data.world.exec(
+            |(loader, textures): (ReadExpect<'_, Loader>, Read<'_, AssetStorage<Texture>>)| {
+                loader.load_from_data(
+                    cubemap_data(256, faces).expect("Faces are 256x256"),
+                    (),
+                    &textures,
+                )
+            },
+        );
+        data.world.insert(SkyboxCubemap(cubemap));
     }
 
     fn handle_event(
@@ -0,0 +1,157 @@
+//! Stands a figure of capsule occluders on a floor lit only by the ambient color, grounding
+//! it with soft contact shadows.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, WorldExt},
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        capsule::CapsuleOccluder,
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgba},
+        plugins::{RenderShaded3D, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        resources::AmbientColor,
+        shape::Shape,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+/// Limbs of the figure, as the position of their center, their radius and half their length.
+const LIMBS: [([f32; 3], f32, f32); 5] = [
+    // Legs
+    ([-0.12, 0.45, 0.0], 0.08, 0.35),
+    ([0.12, 0.45, 0.0], 0.08, 0.35),
+    // Torso
+    ([0.0, 1.2, 0.0], 0.18, 0.25),
+    // Arms
+    ([-0.32, 1.15, 0.0], 0.06, 0.3),
+    ([0.32, 1.15, 0.0], 0.06, 0.3),
+];
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let (floor, limb) = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            (
+                loader.load_from_data(
+                    Shape::Plane(None)
+                        .generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(Some((
+                            4.0, 4.0, 1.0,
+                        )))
+                        .into(),
+                    (),
+                ),
+                loader.load_from_data(
+                    Shape::Cylinder(32, None)
+                        .generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(None)
+                        .into(),
+                    (),
+                ),
+            )
+        });
+
+        let material = world.exec(
+            |(mtl_loader, tex_loader): (
+                AssetLoaderSystemData<'_, Material>,
+                AssetLoaderSystemData<'_, Texture>,
+            )| {
+                let albedo = tex_loader.load_from_data(
+                    load_from_linear_rgba(LinSrgba::new(0.8, 0.8, 0.8, 1.0)).into(),
+                    (),
+                );
+                mtl_loader.load_from_data(
+                    Material {
+                        albedo,
+                        ..mat_defaults
+                    },
+                    (),
+                )
+            },
+        );
+
+        // Both meshes are generated along the Z axis, stand them up along Y.
+        let mut transform = Transform::default();
+        transform.prepend_rotation_x_axis(-std::f32::consts::FRAC_PI_2);
+        world
+            .create_entity()
+            .with(transform)
+            .with(floor)
+            .with(material.clone())
+            .build();
+
+        for &(position, radius, half_length) in LIMBS.iter() {
+            let mut transform = Transform::default();
+            transform.set_translation(position.into());
+            world
+                .create_entity()
+                .with(transform.clone())
+                .with(CapsuleOccluder::new(radius, half_length))
+                .build();
+
+            transform.prepend_rotation_x_axis(-std::f32::consts::FRAC_PI_2);
+            transform.set_scale([radius, radius, half_length].into());
+            world
+                .create_entity()
+                .with(transform)
+                .with(limb.clone())
+                .with(material.clone())
+                .build();
+        }
+
+        // No lights, the figure only darkens the ambient lighting.
+        world.insert(AmbientColor(Srgba::new(0.8, 0.8, 0.8, 1.0)));
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 1.6, -3.0);
+        transform.prepend_rotation_y_axis(std::f32::consts::PI);
+        transform.prepend_rotation_x_axis(-0.3);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/capsule_occlusion/config/display.ron");
+    let assets_dir = app_root.join("examples/capsule_occlusion/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.05, 0.05, 0.08, 1.0]),
+                )
+                .with_plugin(RenderShaded3D::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
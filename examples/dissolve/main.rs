@@ -0,0 +1,138 @@
+//! Dissolves a cube with a noise texture and a glowing edge.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, WorldExt},
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        light::{Light, PointLight},
+        material_animation::{MaterialAnimator, MaterialCurve, MaterialParameter, MaterialTrack},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb},
+        plugins::{RenderShaded3D, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        shape::Shape,
+        texture::value_noise_data,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let mesh = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            loader.load_from_data(
+                Shape::Cube
+                    .generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(None)
+                    .into(),
+                (),
+            )
+        });
+
+        let material = world.exec(
+            |(mtl_loader, tex_loader): (
+                AssetLoaderSystemData<'_, Material>,
+                AssetLoaderSystemData<'_, Texture>,
+            )| {
+                mtl_loader.load_from_data(
+                    Material {
+                        albedo: tex_loader.load_from_data(
+                            load_from_linear_rgba(LinSrgba::new(0.2, 0.4, 0.8, 1.0)).into(),
+                            (),
+                        ),
+                        dissolve_noise: tex_loader.load_from_data(value_noise_data(128, 8, 1), ()),
+                        dissolve_edge_width: 0.06,
+                        ..mat_defaults.clone()
+                    },
+                    (),
+                )
+            },
+        );
+
+        // Dissolve over two seconds, stay hidden for half a second, then start over.
+        world.write_resource::<MaterialAnimator>().animate(
+            &material,
+            MaterialTrack::new(
+                MaterialParameter::DissolveAmount,
+                MaterialCurve::Keyframes {
+                    keys: vec![(0.0, [0.0; 4]), (2.0, [1.0; 4]), (2.5, [1.0; 4])],
+                    looping: true,
+                },
+            ),
+        );
+
+        let mut transform = Transform::default();
+        transform.set_rotation_euler(0.5, 0.7, 0.0);
+        world
+            .create_entity()
+            .with(transform)
+            .with(mesh)
+            .with(material)
+            .build();
+
+        let light: Light = PointLight {
+            intensity: 8.0,
+            color: Srgb::new(1.0, 1.0, 1.0),
+            ..PointLight::default()
+        }
+        .into();
+        let mut light_transform = Transform::default();
+        light_transform.set_translation_xyz(3.0, 3.0, -3.0);
+        world
+            .create_entity()
+            .with(light)
+            .with(light_transform)
+            .build();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, -5.0);
+        transform.prepend_rotation_y_axis(std::f32::consts::PI);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/dissolve/config/display.ron");
+    let assets_dir = app_root.join("examples/dissolve/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.05, 0.05, 0.08, 1.0]),
+                )
+                .with_plugin(RenderShaded3D::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Fountains of half a million particles simulated on the GPU, swirling around an attractor
+//! circling above them.
+use amethyst::{
+    core::{
+        ecs::{Builder, Read, System, WorldExt, Write},
+        math::Vector3,
+        timing::Time,
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        gpu_particles::{GpuParticleEmitter, GpuParticleForces, ParticleAttractor},
+        palette::Srgba,
+        plugins::{RenderGpuParticles, RenderToWindow},
+        types::DefaultBackend,
+        RenderingBundle,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+/// Particles alive at most, every fountain emitting a quarter of them.
+const CAPACITY: u32 = 500_000;
+const LIFETIME: f32 = 4.0;
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+
+        let fountains = [
+            (-2.0, -2.0, Srgba::new(1.0, 0.5, 0.1, 1.0)),
+            (2.0, -2.0, Srgba::new(0.2, 0.6, 1.0, 1.0)),
+            (-2.0, 2.0, Srgba::new(0.4, 1.0, 0.3, 1.0)),
+            (2.0, 2.0, Srgba::new(1.0, 0.3, 0.8, 1.0)),
+        ];
+        for &(x, z, color) in fountains.iter() {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(x, 0.0, z);
+            world
+                .create_entity()
+                .with(transform)
+                .with(GpuParticleEmitter {
+                    rate: CAPACITY as f32 / 4.0 / LIFETIME,
+                    lifetime: LIFETIME,
+                    velocity: Vector3::new(0.0, 6.0, 0.0),
+                    spread: 0.25,
+                    start_color: color,
+                    end_color: Srgba::new(color.red, color.green, color.blue, 0.0),
+                    start_size: 0.04,
+                    end_size: 0.01,
+                    ..Default::default()
+                })
+                .build();
+        }
+
+        world.insert(GpuParticleForces {
+            gravity: Vector3::new(0.0, -4.0, 0.0),
+            drag: 0.3,
+            attractors: vec![ParticleAttractor {
+                position: Vector3::new(0.0, 3.0, 0.0),
+                strength: 6.0,
+            }],
+        });
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 3.0, 10.0);
+        transform.prepend_rotation_x_axis(-0.2);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+/// Circles the attractor above the fountains.
+struct AttractorSystem;
+
+impl<'a> System<'a> for AttractorSystem {
+    type SystemData = (Read<'a, Time>, Write<'a, GpuParticleForces>);
+
+    fn run(&mut self, (time, mut forces): Self::SystemData) {
+        let t = time.absolute_time_seconds() as f32 * 0.5;
+        for attractor in &mut forces.attractors {
+            attractor.position = Vector3::new(t.cos() * 3.0, 3.0, t.sin() * 3.0);
+        }
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/gpu_particles/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with(AttractorSystem, "attractor_system", &[])
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.01, 0.01, 0.02, 1.0]),
+                )
+                .with_plugin(
+                    RenderGpuParticles::default()
+                        .with_capacity(CAPACITY)
+                        .with_additive(true),
+                ),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
@@ -0,0 +1,169 @@
+//! Draws a grid of ten thousand cubes in one instanced draw, or in one draw per cube with
+//! `--unique-materials`.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, Read, System, WorldExt},
+        timing::Time,
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb, Srgba},
+        plugins::{RenderShaded3D, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        resources::Tint,
+        shape::Shape,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::{
+        application_root_dir,
+        fps_counter::{FpsCounter, FpsCounterBundle},
+    },
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+/// Cubes along each side of the grid.
+const GRID: usize = 100;
+const SPACING: f32 = 1.5;
+
+struct Example {
+    unique_materials: bool,
+}
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let cube = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            loader.load_from_data(
+                Shape::Cube
+                    .generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(Some((0.5, 0.5, 0.5)))
+                    .into(),
+                (),
+            )
+        });
+
+        let unique_materials = self.unique_materials;
+        let materials = world.exec(
+            |(mtl_loader, tex_loader): (
+                AssetLoaderSystemData<'_, Material>,
+                AssetLoaderSystemData<'_, Texture>,
+            )| {
+                let albedo = tex_loader.load_from_data(
+                    load_from_linear_rgba(LinSrgba::new(1.0, 1.0, 1.0, 1.0)).into(),
+                    (),
+                );
+                let count = if unique_materials { GRID * GRID } else { 1 };
+                (0..count)
+                    .map(|_| {
+                        mtl_loader.load_from_data(
+                            Material {
+                                albedo: albedo.clone(),
+                                ..mat_defaults.clone()
+                            },
+                            (),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            },
+        );
+
+        let half = (GRID - 1) as f32 * SPACING / 2.0;
+        for x in 0..GRID {
+            for z in 0..GRID {
+                let mut transform = Transform::default();
+                transform.set_translation_xyz(
+                    x as f32 * SPACING - half,
+                    0.0,
+                    z as f32 * SPACING - half,
+                );
+                let tint = Srgba::new(x as f32 / GRID as f32, 0.5, z as f32 / GRID as f32, 1.0);
+                world
+                    .create_entity()
+                    .with(transform)
+                    .with(cube.clone())
+                    .with(materials[(x * GRID + z) % materials.len()].clone())
+                    .with(Tint(tint))
+                    .build();
+            }
+        }
+
+        world
+            .create_entity()
+            .with(Light::Directional(DirectionalLight {
+                color: Srgb::new(1.0, 1.0, 1.0),
+                intensity: 1.0,
+                direction: [-0.3, -1.0, -0.5].into(),
+                ..Default::default()
+            }))
+            .build();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 60.0, 110.0);
+        transform.prepend_rotation_x_axis(-0.5);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+/// Logs the frame rate every two seconds.
+#[derive(Default)]
+struct FpsLogSystem {
+    next: f64,
+}
+
+impl<'a> System<'a> for FpsLogSystem {
+    type SystemData = (Read<'a, Time>, Read<'a, FpsCounter>);
+
+    fn run(&mut self, (time, fps): Self::SystemData) {
+        if time.absolute_real_time_seconds() >= self.next {
+            self.next = time.absolute_real_time_seconds() + 2.0;
+            log::info!("{:.1} FPS", fps.sampled_fps());
+        }
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let unique_materials = std::env::args().any(|arg| arg == "--unique-materials");
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/instancing/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(FpsCounterBundle::default())?
+        .with(FpsLogSystem::default(), "fps_log_system", &[])
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                )
+                .with_plugin(RenderShaded3D::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example { unique_materials }, game_data)?;
+    game.run();
+    Ok(())
+}
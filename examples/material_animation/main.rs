@@ -0,0 +1,180 @@
+//! Animates a force field material with a `MaterialAnimator`.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, WorldExt},
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        light::{Light, PointLight},
+        material_animation::{MaterialAnimator, MaterialCurve, MaterialParameter, MaterialTrack},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb, Srgba},
+        plugins::{RenderPbr3D, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        shape::Shape,
+        texture::checkerboard_data,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let mesh = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            loader.load_from_data(
+                Shape::Sphere(32, 32)
+                    .generate::<(Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>)>(None)
+                    .into(),
+                (),
+            )
+        });
+
+        let (core, field) = world.exec(
+            |(mtl_loader, tex_loader): (
+                AssetLoaderSystemData<'_, Material>,
+                AssetLoaderSystemData<'_, Texture>,
+            )| {
+                let albedo = |r, g, b| {
+                    tex_loader.load_from_data(
+                        load_from_linear_rgba(LinSrgba::new(r, g, b, 1.0)).into(),
+                        (),
+                    )
+                };
+                let core = mtl_loader.load_from_data(
+                    Material {
+                        albedo: albedo(0.8, 0.3, 0.1),
+                        ..mat_defaults.clone()
+                    },
+                    (),
+                );
+                let field = mtl_loader.load_from_data(
+                    Material {
+                        albedo: albedo(0.0, 0.05, 0.1),
+                        emission: tex_loader.load_from_data(
+                            checkerboard_data(
+                                64,
+                                8,
+                                Srgba::new(0.6, 0.9, 1.0, 1.0),
+                                Srgba::new(0.0, 0.0, 0.0, 1.0),
+                            ),
+                            (),
+                        ),
+                        metallic_roughness: tex_loader.load_from_data(
+                            load_from_linear_rgba(LinSrgba::new(0.0, 0.1, 1.0, 0.0)).into(),
+                            (),
+                        ),
+                        ..mat_defaults.clone()
+                    },
+                    (),
+                );
+                (core, field)
+            },
+        );
+
+        {
+            let mut animator = world.write_resource::<MaterialAnimator>();
+            animator.animate(
+                &field,
+                MaterialTrack::new(
+                    MaterialParameter::UvOffset,
+                    MaterialCurve::Scroll {
+                        start: [0.0; 4],
+                        velocity: [0.1, 0.25, 0.0, 0.0],
+                    },
+                ),
+            );
+            animator.animate(
+                &field,
+                MaterialTrack::new(
+                    MaterialParameter::EmissionFactor,
+                    MaterialCurve::Pulse {
+                        base: [0.2, 0.5, 1.0, 0.0],
+                        amplitude: [0.1, 0.3, 0.6, 0.0],
+                        frequency: 0.5,
+                        phase: 0.0,
+                    },
+                ),
+            );
+        }
+
+        let mut core_transform = Transform::default();
+        core_transform.set_scale([0.6; 3].into());
+        world
+            .create_entity()
+            .with(core_transform)
+            .with(mesh.clone())
+            .with(core)
+            .build();
+        world
+            .create_entity()
+            .with(Transform::default())
+            .with(mesh)
+            .with(field)
+            .build();
+
+        let light: Light = PointLight {
+            intensity: 6.0,
+            color: Srgb::new(1.0, 1.0, 1.0),
+            ..PointLight::default()
+        }
+        .into();
+        let mut light_transform = Transform::default();
+        light_transform.set_translation_xyz(4.0, 4.0, -4.0);
+        world
+            .create_entity()
+            .with(light)
+            .with(light_transform)
+            .build();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, -4.0);
+        transform.prepend_rotation_y_axis(std::f32::consts::PI);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/material_animation/config/display.ron");
+    let assets_dir = app_root.join("examples/material/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.02, 0.02, 0.05, 1.0]),
+                )
+                .with_plugin(RenderPbr3D::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
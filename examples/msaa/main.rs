@@ -0,0 +1,83 @@
+//! Displays a shaded sphere with 4x MSAA, or without it when started with `--no-msaa`.
+//! Press W to toggle its wireframe overlay.
+
+use amethyst::{
+    assets::{PrefabLoader, PrefabLoaderSystemDesc, RonFormat},
+    core::transform::TransformBundle,
+    ecs::prelude::WorldExt,
+    input::{is_close_requested, is_key_down},
+    prelude::*,
+    renderer::{
+        plugins::{RenderShaded3D, RenderToWindow, RenderViewModes},
+        rendy::mesh::{Normal, Position, TexCoord},
+        types::DefaultBackend,
+        view_mode::{ViewMode, ViewModes},
+        RenderingBundle,
+    },
+    utils::{application_root_dir, scene::BasicScenePrefab},
+    winit::VirtualKeyCode,
+};
+
+type MyPrefabData = BasicScenePrefab<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>;
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let handle = data.world.exec(|loader: PrefabLoader<'_, MyPrefabData>| {
+            loader.load("prefab/sphere.ron", RonFormat, ())
+        });
+        data.world.create_entity().with(handle).build();
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::W) {
+                let mut modes = data.world.write_resource::<ViewModes>();
+                modes.mode = if modes.mode == ViewMode::WireframeOverlay {
+                    ViewMode::Lit
+                } else {
+                    ViewMode::WireframeOverlay
+                };
+            }
+        }
+        Trans::None
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+
+    let display_config_path = app_root.join("examples/msaa/config/display.ron");
+    let assets_dir = app_root.join("examples/sphere/assets");
+
+    let mut window =
+        RenderToWindow::from_config_path(display_config_path)?.with_clear([0.34, 0.36, 0.52, 1.0]);
+    if std::env::args().any(|arg| arg == "--no-msaa") {
+        log::info!("Rendering without MSAA.");
+    } else {
+        window = window.with_msaa();
+    }
+
+    let game_data = GameDataBuilder::default()
+        .with_system_desc(PrefabLoaderSystemDesc::<MyPrefabData>::default(), "", &[])
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(window)
+                .with_plugin(RenderShaded3D::default())
+                .with_plugin(RenderViewModes::default().with_wireframe_width(2.0)),
+        )?;
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
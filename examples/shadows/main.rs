@@ -0,0 +1,203 @@
+//! Casts the shadow of a spinning cube onto a ground plane with a shadow map.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, Component, DenseVecStorage, Join, Read, ReadStorage, System, WriteStorage},
+        timing::Time,
+        Transform, TransformBundle,
+    },
+    input::{get_key, is_close_requested, is_key_down},
+    prelude::*,
+    renderer::{
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb},
+        plugins::{RenderShaded3D, RenderShadows, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        shadow::{ShadowFilter, ShadowSettings},
+        shape::Shape,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    winit::{ElementState, VirtualKeyCode},
+};
+
+/// Spins the entity around the Y axis.
+struct Spin(f32);
+
+impl Component for Spin {
+    type Storage = DenseVecStorage<Self>;
+}
+
+struct SpinSystem;
+
+impl<'a> System<'a> for SpinSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        ReadStorage<'a, Spin>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (time, spins, mut transforms): Self::SystemData) {
+        for (spin, transform) in (&spins, &mut transforms).join() {
+            transform.prepend_rotation_y_axis(spin.0 * time.delta_seconds());
+        }
+    }
+}
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let (cube, ground) = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            let cube = |scale| {
+                loader.load_from_data(
+                    Shape::Cube
+                        .generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(Some(scale))
+                        .into(),
+                    (),
+                )
+            };
+            (cube((0.75, 0.75, 0.75)), cube((8.0, 0.1, 8.0)))
+        });
+
+        let material = |world: &mut World, color: LinSrgba| {
+            world.exec(
+                |(mtl_loader, tex_loader): (
+                    AssetLoaderSystemData<'_, Material>,
+                    AssetLoaderSystemData<'_, Texture>,
+                )| {
+                    let albedo = tex_loader.load_from_data(load_from_linear_rgba(color).into(), ());
+                    mtl_loader.load_from_data(
+                        Material {
+                            albedo,
+                            ..mat_defaults.clone()
+                        },
+                        (),
+                    )
+                },
+            )
+        };
+        let cube_material = material(world, LinSrgba::new(0.8, 0.2, 0.1, 1.0));
+        let ground_material = material(world, LinSrgba::new(0.7, 0.7, 0.7, 1.0));
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 2.0, 0.0);
+        transform.prepend_rotation_x_axis(0.6);
+        world
+            .create_entity()
+            .with(transform)
+            .with(cube)
+            .with(cube_material)
+            .with(Spin(0.8))
+            .build();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, -0.1, 0.0);
+        world
+            .create_entity()
+            .with(transform)
+            .with(ground)
+            .with(ground_material)
+            .build();
+
+        world
+            .create_entity()
+            .with(Light::Directional(DirectionalLight {
+                color: Srgb::new(1.0, 1.0, 1.0),
+                intensity: 1.0,
+                direction: [-0.4, -1.0, -0.3].into(),
+                ..Default::default()
+            }))
+            .build();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 7.0, 12.0);
+        transform.prepend_rotation_x_axis(-0.5);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+            let mut settings = data.world.write_resource::<ShadowSettings>();
+            match get_key(&event) {
+                Some((VirtualKeyCode::Space, ElementState::Pressed)) => {
+                    settings.filter = match settings.filter {
+                        ShadowFilter::Hard => ShadowFilter::Pcf3x3,
+                        ShadowFilter::Pcf3x3 => ShadowFilter::Pcf5x5,
+                        ShadowFilter::Pcf5x5 => ShadowFilter::Hard,
+                    };
+                    log::info!("Shadow filter: {:?}", settings.filter);
+                }
+                Some((VirtualKeyCode::B, ElementState::Pressed)) => {
+                    let bias = ShadowSettings::default();
+                    if settings.depth_bias > 0.0 {
+                        settings.depth_bias = 0.0;
+                        settings.slope_bias = 0.0;
+                    } else {
+                        settings.depth_bias = bias.depth_bias;
+                        settings.slope_bias = bias.slope_bias;
+                    }
+                    log::info!("Shadow depth bias: {}", settings.depth_bias);
+                }
+                _ => {}
+            }
+        }
+        Trans::None
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/shadows/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with(SpinSystem, "spin_system", &[])
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.34, 0.36, 0.52, 1.0]),
+                )
+                .with_plugin(RenderShadows::default().with_settings(ShadowSettings {
+                    extent: 10.0,
+                    depth: 40.0,
+                    ..Default::default()
+                }))
+                .with_plugin(RenderShaded3D::default().with_shadows()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
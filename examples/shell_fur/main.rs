@@ -0,0 +1,129 @@
+//! Draws a furry sphere with shells.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, WorldExt},
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb, Srgba},
+        plugins::{RenderShaded3D, RenderShells, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        shape::Shape,
+        shells::Shells,
+        texture::value_noise_data,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let mesh = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            loader.load_from_data(
+                Shape::Sphere(32, 32)
+                    .generate::<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>(None)
+                    .into(),
+                (),
+            )
+        });
+
+        let (material, density) = world.exec(
+            |(mtl_loader, tex_loader): (
+                AssetLoaderSystemData<'_, Material>,
+                AssetLoaderSystemData<'_, Texture>,
+            )| {
+                // The skin under the fur, as dark as the roots of the strands.
+                let material = mtl_loader.load_from_data(
+                    Material {
+                        albedo: tex_loader.load_from_data(
+                            load_from_linear_rgba(LinSrgba::new(0.1, 0.05, 0.02, 1.0)).into(),
+                            (),
+                        ),
+                        ..mat_defaults.clone()
+                    },
+                    (),
+                );
+                let density = tex_loader.load_from_data(value_noise_data(256, 128, 5), ());
+                (material, density)
+            },
+        );
+
+        let shells = Shells::new(0.15, density)
+            .with_comb([0.0, -0.6, 0.0])
+            .with_colors(
+                Srgba::new(0.35, 0.2, 0.1, 1.0),
+                Srgba::new(0.95, 0.75, 0.5, 1.0),
+            );
+
+        world
+            .create_entity()
+            .with(Transform::default())
+            .with(mesh)
+            .with(material)
+            .with(shells)
+            .build();
+
+        let light: Light = DirectionalLight {
+            color: Srgb::new(1.0, 1.0, 1.0),
+            direction: [-0.5, -1.0, 0.5].into(),
+            intensity: 1.0,
+            ..DirectionalLight::default()
+        }
+        .into();
+        world.create_entity().with(light).build();
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, -4.0);
+        transform.prepend_rotation_y_axis(std::f32::consts::PI);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/shell_fur/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.3, 0.35, 0.4, 1.0]),
+                )
+                .with_plugin(RenderShaded3D::default())
+                .with_plugin(RenderShells::default()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Renders a crowd of skinned glTF characters, each playing its animation at its own speed.
+
+use amethyst::{
+    animation::{
+        get_animation_set, AnimationBundle, AnimationCommand, AnimationControlSet, AnimationSet,
+        EndControl, VertexSkinningBundle,
+    },
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, Entity, ReadStorage, WorldExt, WriteStorage},
+        Time, Transform, TransformBundle,
+    },
+    prelude::*,
+    renderer::{
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        palette::Srgb,
+        plugins::{RenderPbr3D, RenderToWindow},
+        types::DefaultBackend,
+        RenderingBundle,
+    },
+    utils::{
+        application_root_dir,
+        fps_counter::{FpsCounter, FpsCounterBundle},
+    },
+    window::ScreenDimensions,
+};
+use amethyst_gltf::{GltfSceneAsset, GltfSceneFormat, GltfSceneLoaderSystemDesc};
+
+/// Characters along the x axis.
+const COLUMNS: usize = 25;
+/// Characters along the z axis.
+const ROWS: usize = 20;
+/// Distance between two characters in world units.
+const SPACING: f32 = 3.0;
+/// Seconds between two frame rate reports.
+const REPORT_INTERVAL: f32 = 2.0;
+
+#[derive(Default)]
+struct Crowd {
+    /// Characters whose animation isn't started yet, because their scene is still loading.
+    pending: Vec<Entity>,
+    since_report: f32,
+}
+
+impl SimpleState for Crowd {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let world = data.world;
+
+        let scene = world.exec(|loader: AssetLoaderSystemData<'_, GltfSceneAsset>| {
+            loader.load("mesh/puffy.gltf", GltfSceneFormat::default(), ())
+        });
+
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let mut transform = Transform::default();
+                transform.set_translation_xyz(
+                    (column as f32 - COLUMNS as f32 / 2.0) * SPACING,
+                    0.0,
+                    -(row as f32) * SPACING,
+                );
+                let entity = world
+                    .create_entity()
+                    .with(transform)
+                    .with(scene.clone())
+                    .build();
+                self.pending.push(entity);
+            }
+        }
+
+        let light: Light = DirectionalLight {
+            color: Srgb::new(1.0, 1.0, 1.0),
+            direction: [-0.3, -1.0, -0.5].into(),
+            intensity: 2.0,
+            ..DirectionalLight::default()
+        }
+        .into();
+        world.create_entity().with(light).build();
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 25.0, 25.0);
+        transform.prepend_rotation_x_axis(-0.6);
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        if !self.pending.is_empty() {
+            let pending = &mut self.pending;
+            data.world.exec(
+                |(sets, mut controls): (
+                    ReadStorage<'_, AnimationSet<usize, Transform>>,
+                    WriteStorage<'_, AnimationControlSet<usize, Transform>>,
+                )| {
+                    pending.retain(|&entity| {
+                        let animation = match sets.get(entity).and_then(|s| s.animations.get(&0)) {
+                            Some(animation) => animation,
+                            None => return true,
+                        };
+                        // Different speeds make the characters drift out of step, so every
+                        // instance draws a different pose.
+                        let rate = 0.6 + (entity.id() % 9) as f32 * 0.1;
+                        if let Some(set) = get_animation_set(&mut controls, entity) {
+                            set.add_animation(
+                                0,
+                                animation,
+                                EndControl::Loop(None),
+                                rate,
+                                AnimationCommand::Start,
+                            );
+                        }
+                        false
+                    });
+                },
+            );
+        }
+
+        let world = &data.world;
+        self.since_report += world.read_resource::<Time>().delta_seconds();
+        if self.since_report >= REPORT_INTERVAL {
+            self.since_report = 0.0;
+            log::info!(
+                "{} characters: {:.1} fps",
+                ROWS * COLUMNS,
+                world.read_resource::<FpsCounter>().sampled_fps()
+            );
+        }
+        Trans::None
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/skinned_crowd/config/display.ron");
+    let assets_dir = app_root.join("examples/gltf/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_system_desc(GltfSceneLoaderSystemDesc::default(), "gltf_loader", &[])
+        .with_bundle(
+            AnimationBundle::<usize, Transform>::new("animation_control", "sampler_interpolation")
+                .with_dep(&["gltf_loader"]),
+        )?
+        .with_bundle(
+            TransformBundle::new().with_dep(&["animation_control", "sampler_interpolation"]),
+        )?
+        .with_bundle(VertexSkinningBundle::new().with_dep(&[
+            "transform_system",
+            "animation_control",
+            "sampler_interpolation",
+        ]))?
+        .with_bundle(FpsCounterBundle)?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.3, 0.3, 0.35, 1.0]),
+                )
+                .with_plugin(RenderPbr3D::default().with_skinning()),
+        )?;
+
+    let mut game = Application::new(assets_dir, Crowd::default(), game_data)?;
+    game.run();
+    Ok(())
+}
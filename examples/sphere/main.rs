@@ -1,17 +1,20 @@
-//! Displays a shaded sphere to the user.
+//! Displays a shaded sphere to the user. Press W to toggle its wireframe overlay.
 
 use amethyst::{
     assets::{PrefabLoader, PrefabLoaderSystemDesc, RonFormat},
     core::transform::TransformBundle,
     ecs::prelude::WorldExt,
+    input::{is_close_requested, is_key_down},
     prelude::*,
     renderer::{
-        plugins::{RenderShaded3D, RenderToWindow},
+        plugins::{RenderShaded3D, RenderToWindow, RenderViewModes},
         rendy::mesh::{Normal, Position, TexCoord},
         types::DefaultBackend,
+        view_mode::{ViewMode, ViewModes},
         RenderingBundle,
     },
     utils::{application_root_dir, scene::BasicScenePrefab},
+    winit::VirtualKeyCode,
 };
 
 type MyPrefabData = BasicScenePrefab<(Vec<Position>, Vec<Normal>, Vec<TexCoord>)>;
@@ -25,6 +28,27 @@ impl SimpleState for Example {
         });
         data.world.create_entity().with(handle).build();
     }
+
+    fn handle_event(
+        &mut self,
+        data: StateData<'_, GameData<'_, '_>>,
+        event: StateEvent,
+    ) -> SimpleTrans {
+        if let StateEvent::Window(event) = &event {
+            if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+                return Trans::Quit;
+            }
+            if is_key_down(&event, VirtualKeyCode::W) {
+                let mut modes = data.world.write_resource::<ViewModes>();
+                modes.mode = if modes.mode == ViewMode::WireframeOverlay {
+                    ViewMode::Lit
+                } else {
+                    ViewMode::WireframeOverlay
+                };
+            }
+        }
+        Trans::None
+    }
 }
 
 fn main() -> amethyst::Result<()> {
@@ -44,7 +68,8 @@ fn main() -> amethyst::Result<()> {
                     RenderToWindow::from_config_path(display_config_path)?
                         .with_clear([0.34, 0.36, 0.52, 1.0]),
                 )
-                .with_plugin(RenderShaded3D::default()),
+                .with_plugin(RenderShaded3D::default())
+                .with_plugin(RenderViewModes::default().with_wireframe_width(2.0)),
         )?;
     let mut game = Application::new(assets_dir, Example, game_data)?;
     game.run();
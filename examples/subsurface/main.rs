@@ -0,0 +1,140 @@
+//! Lights two skin colored spheres from the side, the right one scattering its light under
+//! the surface.
+use amethyst::{
+    assets::AssetLoaderSystemData,
+    core::{
+        ecs::{Builder, WorldExt},
+        Transform, TransformBundle,
+    },
+    renderer::{
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb, Srgba},
+        pass::SubsurfaceProfile,
+        plugins::{RenderPbr3D, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        resources::AmbientColor,
+        shape::Shape,
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+    Application, GameData, GameDataBuilder, SimpleState, StateData,
+};
+
+/// Radius of the spheres, about the size of a head for a meter per world unit.
+const RADIUS: f32 = 0.1;
+
+struct Example;
+
+impl SimpleState for Example {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+
+        let mesh = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            loader.load_from_data(
+                Shape::Sphere(64, 64)
+                    .generate::<(Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>)>(Some((
+                        RADIUS, RADIUS, RADIUS,
+                    )))
+                    .into(),
+                (),
+            )
+        });
+
+        let (plastic, skin) = world.exec(
+            |(mtl_loader, tex_loader): (
+                AssetLoaderSystemData<'_, Material>,
+                AssetLoaderSystemData<'_, Texture>,
+            )| {
+                let albedo = tex_loader.load_from_data(
+                    load_from_linear_rgba(LinSrgba::new(0.8, 0.45, 0.35, 1.0)).into(),
+                    (),
+                );
+                let roughness = tex_loader.load_from_data(
+                    load_from_linear_rgba(LinSrgba::new(0.0, 0.5, 0.0, 0.0)).into(),
+                    (),
+                );
+                let plastic = Material {
+                    albedo,
+                    metallic_roughness: roughness,
+                    ..mat_defaults.clone()
+                };
+                let skin = Material {
+                    subsurface: 1.0,
+                    ..plastic.clone()
+                };
+                (
+                    mtl_loader.load_from_data(plastic, ()),
+                    mtl_loader.load_from_data(skin, ()),
+                )
+            },
+        );
+
+        for (x, material) in [(-1.2 * RADIUS, plastic), (1.2 * RADIUS, skin)] {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(x, 0.0, 0.0);
+            world
+                .create_entity()
+                .with(transform)
+                .with(mesh.clone())
+                .with(material)
+                .build();
+        }
+
+        // Grazing light from the side, the scattering softens and reddens the terminator.
+        let light: Light = DirectionalLight {
+            color: Srgb::new(1.0, 0.95, 0.9),
+            direction: [-1.0, -0.2, 0.3].into(),
+            intensity: 4.0,
+            ..DirectionalLight::default()
+        }
+        .into();
+        world.create_entity().with(light).build();
+        world.insert(AmbientColor(Srgba::new(0.02, 0.02, 0.03, 1.0)));
+
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 0.0, -0.6);
+        transform.prepend_rotation_y_axis(std::f32::consts::PI);
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/subsurface/config/display.ron");
+    let assets_dir = app_root.join("examples/subsurface/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.05, 0.05, 0.08, 1.0]),
+                )
+                .with_plugin(RenderPbr3D::default().with_subsurface(SubsurfaceProfile::skin())),
+        )?;
+
+    let mut game = Application::new(assets_dir, Example, game_data)?;
+    game.run();
+    Ok(())
+}
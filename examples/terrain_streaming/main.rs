@@ -0,0 +1,310 @@
+//! Streams procedurally generated terrain chunks around a camera flying over them.
+
+use amethyst::{
+    assets::{AssetLoaderSystemData, AssetStorage, Handle},
+    core::{
+        ecs::{Builder, Entity, WorldExt},
+        ArcThreadPool, Time, Transform, TransformBundle,
+    },
+    prelude::*,
+    renderer::{
+        async_factory::{AsyncFactory, Pending},
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        load_queue::{LoadQueue, LoadResult, LoadToken},
+        mtl::{DetailBlend, Material, MaterialDefaults},
+        palette::{Srgb, Srgba},
+        plugins::{RenderShaded3D, RenderToWindow},
+        rendy::mesh::{MeshBuilder, Normal, Position, Tangent, TexCoord},
+        texture::{checkerboard_data, value_noise_data},
+        types::{DefaultBackend, MeshData},
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Size of a single chunk in world units.
+const CHUNK_SIZE: f32 = 16.0;
+/// Number of quads along a chunk edge.
+const CHUNK_RESOLUTION: usize = 32;
+/// Chunks further away from the camera chunk than this are unloaded.
+const VIEW_RADIUS: i32 = 6;
+/// Camera speed in world units per second.
+const SPEED: f32 = 12.0;
+
+type ChunkCoord = (i32, i32);
+
+enum Chunk {
+    Generating(LoadToken),
+    Uploading(Pending<Mesh>),
+    Loaded(Entity),
+}
+
+fn height(x: f32, z: f32) -> f32 {
+    (x * 0.05).sin() * 3.0 + (z * 0.07).cos() * 2.0 + (x * 0.21 + z * 0.13).sin() * 0.5
+}
+
+fn chunk_mesh((cx, cz): ChunkCoord) -> MeshData {
+    let verts = CHUNK_RESOLUTION + 1;
+    let step = CHUNK_SIZE / CHUNK_RESOLUTION as f32;
+    let (origin_x, origin_z) = (cx as f32 * CHUNK_SIZE, cz as f32 * CHUNK_SIZE);
+
+    let mut positions = Vec::with_capacity(verts * verts);
+    let mut normals = Vec::with_capacity(verts * verts);
+    let mut tangents = Vec::with_capacity(verts * verts);
+    let mut tex_coords = Vec::with_capacity(verts * verts);
+    for j in 0..verts {
+        for i in 0..verts {
+            let (x, z) = (origin_x + i as f32 * step, origin_z + j as f32 * step);
+            let dx = height(x + step, z) - height(x - step, z);
+            let dz = height(x, z + step) - height(x, z - step);
+            let normal = [-dx, 2.0 * step, -dz];
+            let len =
+                (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+
+            positions.push(Position([x - origin_x, height(x, z), z - origin_z]));
+            normals.push(Normal([normal[0] / len, normal[1] / len, normal[2] / len]));
+            tangents.push(Tangent([1.0, 0.0, 0.0, 1.0]));
+            tex_coords.push(TexCoord([
+                i as f32 / CHUNK_RESOLUTION as f32,
+                j as f32 / CHUNK_RESOLUTION as f32,
+            ]));
+        }
+    }
+
+    let mut indices = Vec::with_capacity(CHUNK_RESOLUTION * CHUNK_RESOLUTION * 6);
+    for j in 0..CHUNK_RESOLUTION {
+        for i in 0..CHUNK_RESOLUTION {
+            let a = (j * verts + i) as u32;
+            let b = a + 1;
+            let c = a + verts as u32;
+            let d = c + 1;
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+
+    MeshBuilder::new()
+        .with_vertices(positions)
+        .with_vertices(normals)
+        .with_vertices(tangents)
+        .with_vertices(tex_coords)
+        .with_indices(indices)
+        .into()
+}
+
+fn chunk_priority((cx, cz): ChunkCoord, (camera_x, camera_z): ChunkCoord) -> i32 {
+    let (dx, dz) = (cx - camera_x, cz - camera_z);
+    -(dx * dx + dz * dz)
+}
+
+#[derive(Default)]
+struct TerrainStreaming {
+    queue: Option<LoadQueue>,
+    chunks: HashMap<ChunkCoord, Chunk>,
+    generated: Arc<Mutex<Vec<(ChunkCoord, Pending<Mesh>)>>>,
+    camera: Option<Entity>,
+    material: Option<Handle<Material>>,
+}
+
+impl TerrainStreaming {
+    fn camera_chunk(&self, world: &World) -> ChunkCoord {
+        let transforms = world.read_storage::<Transform>();
+        let translation = transforms.get(self.camera.unwrap()).unwrap().translation();
+        (
+            (translation.x / CHUNK_SIZE).floor() as i32,
+            (translation.z / CHUNK_SIZE).floor() as i32,
+        )
+    }
+
+    fn request_chunk(&mut self, coord: ChunkCoord, priority: i32) {
+        let generated = self.generated.clone();
+        let token = self.queue.as_ref().unwrap().submit(
+            priority,
+            move || Ok(chunk_mesh(coord).into()),
+            move |result| match result {
+                LoadResult::Mesh(pending) => generated.lock().unwrap().push((coord, pending)),
+                LoadResult::Failed(err) => log::error!("Failed to generate chunk: {}", err),
+                _ => unreachable!(),
+            },
+        );
+        self.chunks.insert(coord, Chunk::Generating(token));
+    }
+}
+
+impl SimpleState for TerrainStreaming {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+
+        let factory = AsyncFactory::clone(&world.read_resource());
+        let pool = ArcThreadPool::clone(&world.read_resource());
+        self.queue = Some(LoadQueue::new(factory, pool, 4));
+
+        let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+        let albedo = world.exec(|loader: AssetLoaderSystemData<'_, Texture>| {
+            loader.load_from_data(
+                checkerboard_data(
+                    64,
+                    8,
+                    Srgba::new(0.3, 0.6, 0.2, 1.0),
+                    Srgba::new(0.25, 0.5, 0.15, 1.0),
+                ),
+                (),
+            )
+        });
+        let detail = world.exec(|loader: AssetLoaderSystemData<'_, Texture>| {
+            loader.load_from_data(value_noise_data(128, 32, 7), ())
+        });
+        self.material = Some(world.exec(|loader: AssetLoaderSystemData<'_, Material>| {
+            loader.load_from_data(
+                Material {
+                    albedo,
+                    // Steep slopes have stretched texture coordinates, project along the axes.
+                    triplanar_scale: 0.25,
+                    // Breaks up the flat checkers up close.
+                    detail,
+                    detail_scale: 16.0,
+                    detail_blend: DetailBlend::Overlay,
+                    detail_fade_distance: 24.0,
+                    ..mat_defaults
+                },
+                (),
+            )
+        }));
+
+        let light: Light = DirectionalLight {
+            color: Srgb::new(1.0, 0.95, 0.9),
+            direction: [-0.3, -1.0, -0.2].into(),
+            intensity: 1.0,
+            ..DirectionalLight::default()
+        }
+        .into();
+        world.create_entity().with(light).build();
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 12.0, 0.0);
+        transform.prepend_rotation_x_axis(-0.4);
+        self.camera = Some(
+            world
+                .create_entity()
+                .with(Camera::standard_3d(width, height))
+                .with(transform)
+                .build(),
+        );
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let StateData { world, .. } = data;
+
+        {
+            let delta = world.read_resource::<Time>().delta_seconds();
+            let mut transforms = world.write_storage::<Transform>();
+            let transform = transforms.get_mut(self.camera.unwrap()).unwrap();
+            transform.append_translation_xyz(0.0, 0.0, -SPEED * delta);
+        }
+
+        let camera = self.camera_chunk(world);
+        let in_view = |(cx, cz): ChunkCoord| {
+            (cx - camera.0).abs() <= VIEW_RADIUS && (cz - camera.1).abs() <= VIEW_RADIUS
+        };
+
+        // Cancel or unload everything that left the view.
+        let mut removed = Vec::new();
+        for (&coord, chunk) in &self.chunks {
+            if !in_view(coord) {
+                match chunk {
+                    Chunk::Generating(token) => token.cancel(),
+                    Chunk::Uploading(_) => {}
+                    Chunk::Loaded(entity) => world.delete_entity(*entity).unwrap(),
+                }
+                removed.push(coord);
+            }
+        }
+        for coord in removed {
+            self.chunks.remove(&coord);
+        }
+
+        // Request new chunks and reprioritize the ones still waiting.
+        for cz in camera.1 - VIEW_RADIUS..=camera.1 + VIEW_RADIUS {
+            for cx in camera.0 - VIEW_RADIUS..=camera.0 + VIEW_RADIUS {
+                let coord = (cx, cz);
+                let priority = chunk_priority(coord, camera);
+                match self.chunks.get(&coord) {
+                    None => self.request_chunk(coord, priority),
+                    Some(Chunk::Generating(token)) => token.set_priority(priority),
+                    _ => {}
+                }
+            }
+        }
+
+        // Pick up generated chunks. Chunks that left the view in the meantime are simply
+        // dropped, which releases their meshes.
+        for (coord, pending) in self.generated.lock().unwrap().drain(..) {
+            if let Some(chunk @ Chunk::Generating(_)) = self.chunks.get_mut(&coord) {
+                *chunk = Chunk::Uploading(pending);
+            }
+        }
+
+        // Spawn entities for chunks whose meshes are ready.
+        let ready: Vec<_> = {
+            let storage = world.read_resource::<AssetStorage<Mesh>>();
+            self.chunks
+                .iter()
+                .filter_map(|(&coord, chunk)| match chunk {
+                    Chunk::Uploading(pending) if pending.is_loaded(&storage) => {
+                        Some((coord, pending.handle().unwrap()))
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        for (coord, mesh) in ready {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(
+                coord.0 as f32 * CHUNK_SIZE,
+                0.0,
+                coord.1 as f32 * CHUNK_SIZE,
+            );
+            let entity = world
+                .create_entity()
+                .with(mesh)
+                .with(self.material.clone().unwrap())
+                .with(transform)
+                .build();
+            self.chunks.insert(coord, Chunk::Loaded(entity));
+        }
+
+        Trans::None
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/terrain_streaming/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.55, 0.7, 0.9, 1.0]),
+                )
+                .with_plugin(RenderShaded3D::default().with_triplanar()),
+        )?;
+
+    let mut game = Application::new(assets_dir, TerrainStreaming::default(), game_data)?;
+    game.run();
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Toggles rain over a small street scene with the scene surface layer.
+use amethyst::{
+    assets::{AssetLoaderSystemData, Handle},
+    core::{
+        ecs::{Builder, WorldExt},
+        Time, Transform, TransformBundle,
+    },
+    prelude::*,
+    renderer::{
+        camera::Camera,
+        light::{DirectionalLight, Light},
+        mtl::{Material, MaterialDefaults},
+        palette::{LinSrgba, Srgb, Srgba},
+        plugins::{RenderPbr3D, RenderToWindow},
+        rendy::{
+            mesh::{Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        resources::{AmbientColor, SurfaceLayer},
+        shape::Shape,
+        texture::{checkerboard_data, value_noise_data},
+        types::DefaultBackend,
+        Mesh, RenderingBundle, Texture,
+    },
+    utils::application_root_dir,
+    window::ScreenDimensions,
+};
+
+/// Seconds between the rain starting and stopping.
+const RAIN_PERIOD: f64 = 6.0;
+/// Seconds for the street to get fully wet or dry.
+const SOAK_TIME: f32 = 2.0;
+
+struct WetStreet;
+
+fn load_material(
+    world: &mut World,
+    albedo: Handle<Texture>,
+    layer_mask: Option<Handle<Texture>>,
+) -> Handle<Material> {
+    let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
+    let metallic_roughness = world.exec(|loader: AssetLoaderSystemData<'_, Texture>| {
+        // Dry surfaces are rough.
+        loader.load_from_data(
+            load_from_linear_rgba(LinSrgba::new(0.0, 0.9, 0.0, 0.0)).into(),
+            (),
+        )
+    });
+    world.exec(|loader: AssetLoaderSystemData<'_, Material>| {
+        loader.load_from_data(
+            Material {
+                albedo,
+                metallic_roughness,
+                layer_mask: layer_mask.unwrap_or_else(|| mat_defaults.layer_mask.clone()),
+                layer_scene_factor: 1.0,
+                ..mat_defaults
+            },
+            (),
+        )
+    })
+}
+
+fn spawn(world: &mut World, mesh: Handle<Mesh>, material: Handle<Material>, transform: Transform) {
+    world
+        .create_entity()
+        .with(mesh)
+        .with(material)
+        .with(transform)
+        .build();
+}
+
+impl SimpleState for WetStreet {
+    fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+        let StateData { world, .. } = data;
+
+        let (plane, cube) = world.exec(|loader: AssetLoaderSystemData<'_, Mesh>| {
+            let generate = |shape: Shape| {
+                loader.load_from_data(
+                    shape
+                        .generate::<(Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>)>(None)
+                        .into(),
+                    (),
+                )
+            };
+            (generate(Shape::Plane(None)), generate(Shape::Cube))
+        });
+        let (road, wall, puddles) = world.exec(|loader: AssetLoaderSystemData<'_, Texture>| {
+            (
+                loader.load_from_data(
+                    checkerboard_data(
+                        64,
+                        16,
+                        Srgba::new(0.3, 0.3, 0.32, 1.0),
+                        Srgba::new(0.26, 0.26, 0.28, 1.0),
+                    ),
+                    (),
+                ),
+                loader.load_from_data(
+                    checkerboard_data(
+                        64,
+                        8,
+                        Srgba::new(0.6, 0.35, 0.3, 1.0),
+                        Srgba::new(0.5, 0.3, 0.25, 1.0),
+                    ),
+                    (),
+                ),
+                loader.load_from_data(value_noise_data(64, 6, 11), ()),
+            )
+        });
+        let road = load_material(world, road, Some(puddles));
+        let wall = load_material(world, wall, None);
+
+        let mut transform = Transform::default();
+        transform.set_rotation_x_axis(-std::f32::consts::FRAC_PI_2);
+        transform.set_scale([6.0, 20.0, 1.0].into());
+        spawn(world, plane, road, transform);
+        for &(x, z, height) in &[
+            (-8.0, -4.0, 5.0),
+            (-8.0, -14.0, 3.0),
+            (8.0, -6.0, 4.0),
+            (8.0, -16.0, 6.0),
+        ] {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(x, height, z);
+            transform.set_scale([2.0, height, 4.5].into());
+            spawn(world, cube.clone(), wall.clone(), transform);
+        }
+
+        let light: Light = DirectionalLight {
+            color: Srgb::new(0.9, 0.9, 1.0),
+            direction: [0.3, -1.0, -0.6].into(),
+            intensity: 1.5,
+            ..DirectionalLight::default()
+        }
+        .into();
+        world.create_entity().with(light).build();
+        world.insert(AmbientColor(Srgba::new(0.1, 0.1, 0.12, 1.0)));
+        world.insert(SurfaceLayer(0.0));
+
+        let (width, height) = {
+            let dim = world.read_resource::<ScreenDimensions>();
+            (dim.width(), dim.height())
+        };
+        let mut transform = Transform::default();
+        transform.set_translation_xyz(0.0, 3.0, 10.0);
+        transform.prepend_rotation_x_axis(-0.2);
+        world
+            .create_entity()
+            .with(Camera::standard_3d(width, height))
+            .with(transform)
+            .build();
+    }
+
+    fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        let (raining, delta) = {
+            let time = data.world.read_resource::<Time>();
+            let raining = (time.absolute_time_seconds() / RAIN_PERIOD) as u64 % 2 == 1;
+            (raining, time.delta_seconds())
+        };
+        let mut layer = data.world.write_resource::<SurfaceLayer>();
+        let step = delta / SOAK_TIME;
+        layer.0 = if raining {
+            (layer.0 + step).min(1.0)
+        } else {
+            (layer.0 - step).max(0.0)
+        };
+        Trans::None
+    }
+}
+
+fn main() -> amethyst::Result<()> {
+    amethyst::start_logger(Default::default());
+
+    let app_root = application_root_dir()?;
+    let display_config_path = app_root.join("examples/wet_street/config/display.ron");
+    let assets_dir = app_root.join("examples/assets/");
+
+    let game_data = GameDataBuilder::default()
+        .with_bundle(TransformBundle::new())?
+        .with_bundle(
+            RenderingBundle::<DefaultBackend>::new()
+                .with_plugin(
+                    RenderToWindow::from_config_path(display_config_path)?
+                        .with_clear([0.4, 0.45, 0.5, 1.0]),
+                )
+                .with_plugin(RenderPbr3D::default().with_surface_layer()),
+        )?;
+
+    let mut game = Application::new(assets_dir, WetStreet, game_data)?;
+    game.run();
+    Ok(())
+}